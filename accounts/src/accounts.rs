@@ -74,6 +74,65 @@ impl<'env> Accounts<'env> {
         Ok(receipts)
     }
 
+    /// Applies `transactions` on top of the current state without persisting anything. Returns
+    /// the states of `addresses` afterwards and the receipts, which include the pruned accounts.
+    ///
+    /// This only reads from the database and keeps the modified accounts in memory, so it
+    /// doesn't block writers.
+    pub fn simulate(&self, transactions: &[Transaction], block_height: u32, addresses: &[Address]) -> Result<(Vec<Account>, Receipts), AccountError> {
+        let txn = ReadTransaction::new(self.env);
+        let mut overlay: HashMap<Address, Account> = HashMap::new();
+        let get = |overlay: &HashMap<Address, Account>, address: &Address| overlay.get(address).cloned()
+            .unwrap_or_else(|| self.get(address, Some(&txn)));
+
+        let mut receipts = Vec::new();
+        for (index, transaction) in transactions.iter().enumerate() {
+            let mut account = get(&overlay, &transaction.sender);
+            if account.account_type() != transaction.sender_type {
+                return Err(AccountError::TypeMismatch { expected: account.account_type(), got: transaction.sender_type });
+            }
+            if let Some(data) = account.commit_outgoing_transaction(transaction, block_height)? {
+                receipts.push(Receipt::Transaction { index: index as u16, sender: true, data });
+            }
+            overlay.insert(transaction.sender.clone(), account);
+        }
+
+        for (index, transaction) in transactions.iter().enumerate() {
+            let mut account = get(&overlay, &transaction.recipient);
+            if !transaction.flags.contains(TransactionFlags::CONTRACT_CREATION) && account.account_type() != transaction.recipient_type {
+                return Err(AccountError::TypeMismatch { expected: account.account_type(), got: transaction.recipient_type });
+            }
+            if let Some(data) = account.commit_incoming_transaction(transaction, block_height)? {
+                receipts.push(Receipt::Transaction { index: index as u16, sender: false, data });
+            }
+            overlay.insert(transaction.recipient.clone(), account);
+        }
+
+        for transaction in transactions {
+            if transaction.flags.contains(TransactionFlags::CONTRACT_CREATION) {
+                let recipient_account = get(&overlay, &transaction.recipient);
+                let contract = Account::new_contract(transaction.recipient_type, recipient_account.balance(), transaction, block_height)?;
+                overlay.insert(transaction.recipient.clone(), contract);
+            }
+        }
+
+        for transaction in transactions {
+            let sender_account = get(&overlay, &transaction.sender);
+            if sender_account.is_to_be_pruned() {
+                receipts.push(Receipt::PrunedAccount(PrunedAccount {
+                    address: transaction.sender.clone(),
+                    account: sender_account,
+                }));
+                overlay.insert(transaction.sender.clone(), Account::INITIAL);
+            }
+        }
+
+        let accounts = addresses.iter()
+            .map(|address| get(&overlay, address))
+            .collect();
+        Ok((accounts, Receipts::from(receipts)))
+    }
+
     pub fn commit(&self, txn: &mut WriteTransaction, transactions: &[Transaction], inherents: &[Inherent], block_height: u32) -> Result<Receipts, AccountError> {
         let receipts = self.commit_nonfinal(txn, transactions, inherents, block_height)?;
         self.tree.finalize_batch(txn);
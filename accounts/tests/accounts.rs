@@ -220,6 +220,52 @@ fn it_checks_for_sufficient_funds() {
     assert_eq!(hash2, accounts.hash(None));
 }
 
+#[test]
+fn it_can_simulate_transactions_without_changing_the_state() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let accounts = Accounts::new(&env);
+    let address_sender = Address::from([1u8; Address::SIZE]);
+    let address_recipient = Address::from([2u8; Address::SIZE]);
+
+    let body = BlockBody {
+        miner: address_sender.clone(),
+        extra_data: Vec::new(),
+        transactions: Vec::new(),
+        receipts: Receipts::default()
+    };
+    {
+        let mut txn = WriteTransaction::new(&env);
+        assert!(accounts.commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).is_ok());
+        txn.commit();
+    }
+    let hash = accounts.hash(None);
+
+    let tx = Transaction::new_basic(
+        address_sender.clone(),
+        address_recipient.clone(),
+        Coin::try_from(10).unwrap(),
+        Coin::ZERO,
+        1,
+        NetworkId::Main
+    );
+    let addresses = vec![address_sender.clone(), address_recipient.clone()];
+
+    let (simulated, receipts) = accounts.simulate(&[tx.clone(), tx.clone()], 2, &addresses).unwrap();
+    assert_eq!(simulated[0].balance(), policy::block_reward_at(1) - Coin::try_from(20).unwrap());
+    assert_eq!(simulated[1].balance(), Coin::try_from(20).unwrap());
+    assert!(receipts.receipts.is_empty());
+
+    // Nothing was persisted.
+    assert_eq!(accounts.get(&address_sender, None).balance(), policy::block_reward_at(1));
+    assert_eq!(accounts.get(&address_recipient, None).balance(), Coin::ZERO);
+    assert_eq!(hash, accounts.hash(None));
+
+    // Insufficient funds are detected across the simulated transactions.
+    let mut tx2 = tx.clone();
+    tx2.value = policy::block_reward_at(1);
+    assert!(accounts.simulate(&[tx, tx2], 2, &addresses).is_err());
+}
+
 #[test]
 fn it_prevents_spending_of_funds_received_in_the_same_block() {
 
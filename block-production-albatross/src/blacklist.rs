@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use parking_lot::RwLock;
+
+use hash::{Blake2bHash, Hash};
+use keys::Address;
+use transaction::Transaction;
+
+/// Transactions that a block producer doesn't include in its blocks, either because they are
+/// sent from or to a blacklisted address, or by their hash.
+///
+/// Unlike a mempool filter, this doesn't affect which transactions the node accepts and relays.
+/// The blacklist can be changed while the producer is running.
+#[derive(Debug, Default)]
+pub struct Blacklist {
+    addresses: RwLock<HashSet<Address>>,
+    transactions: RwLock<HashSet<Blake2bHash>>,
+}
+
+impl Blacklist {
+    pub fn new<A, T>(addresses: A, transactions: T) -> Self
+        where A: IntoIterator<Item=Address>, T: IntoIterator<Item=Blake2bHash> {
+        Blacklist {
+            addresses: RwLock::new(addresses.into_iter().collect()),
+            transactions: RwLock::new(transactions.into_iter().collect()),
+        }
+    }
+
+    /// Returns `false` if the address was already blacklisted.
+    pub fn add_address(&self, address: Address) -> bool {
+        self.addresses.write().insert(address)
+    }
+
+    /// Returns `false` if the address wasn't blacklisted.
+    pub fn remove_address(&self, address: &Address) -> bool {
+        self.addresses.write().remove(address)
+    }
+
+    /// Returns `false` if the transaction was already blacklisted.
+    pub fn add_transaction(&self, hash: Blake2bHash) -> bool {
+        self.transactions.write().insert(hash)
+    }
+
+    /// Returns `false` if the transaction wasn't blacklisted.
+    pub fn remove_transaction(&self, hash: &Blake2bHash) -> bool {
+        self.transactions.write().remove(hash)
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.addresses.read().iter().cloned().collect()
+    }
+
+    pub fn transactions(&self) -> Vec<Blake2bHash> {
+        self.transactions.read().iter().cloned().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.read().is_empty() && self.transactions.read().is_empty()
+    }
+
+    /// Whether `transaction` must not be included in a block.
+    pub fn contains(&self, transaction: &Transaction) -> bool {
+        {
+            let addresses = self.addresses.read();
+            if addresses.contains(&transaction.sender) || addresses.contains(&transaction.recipient) {
+                return true;
+            }
+        }
+        let transactions = self.transactions.read();
+        !transactions.is_empty() && transactions.contains(&transaction.hash::<Blake2bHash>())
+    }
+}
@@ -1,6 +1,7 @@
 #[macro_use]
 extern crate log;
 
+extern crate nimiq_account as account;
 extern crate nimiq_block_albatross as block;
 extern crate nimiq_blockchain_albatross as blockchain;
 extern crate nimiq_blockchain_base as blockchain_base;
@@ -12,96 +13,363 @@ extern crate nimiq_keys as keys;
 extern crate nimiq_mempool as mempool;
 extern crate nimiq_network_primitives as network_primitives;
 extern crate nimiq_primitives as primitives;
+extern crate nimiq_transaction as transaction;
+extern crate nimiq_utils as utils;
 
+pub mod blacklist;
+mod proposal_cache;
+pub mod selector;
+pub mod stats;
+pub mod template;
+pub mod validator_keys;
+
+use std::cmp;
 use std::sync::Arc;
+use std::time::Instant;
+
+use parking_lot::Mutex;
 
+use account::Inherent;
 use beserial::Serialize;
 use block::{Block, MacroBlock, MacroExtrinsics, MacroHeader, MicroBlock, MicroExtrinsics, MicroHeader, PbftProposal, ViewChangeProof, ViewChanges};
 use block::ForkProof;
 use block::MicroJustification;
-use blockchain::blockchain::Blockchain;
+use blockchain::blockchain::{Blockchain, PushError};
 use blockchain_base::AbstractBlockchain;
 use bls::bls12_381::{CompressedSignature, KeyPair};
 use collections::compressed_list::CompressedList;
 use database::WriteTransaction;
-use hash::{Blake2bHash, Hash};
+use hash::{Blake2bHash, Hash, SerializeContent};
 use mempool::Mempool;
+use primitives::coin::Coin;
 use primitives::policy;
+use transaction::Transaction;
+
+use crate::blacklist::Blacklist;
+use crate::proposal_cache::{CachedProposal, ProposalCache};
+use crate::selector::{fill_block, fill_block_after, MempoolOrder, TransactionSelector};
+use crate::stats::ProducerStats;
+use crate::template::BlockTemplate;
 
 pub struct BlockProducer<'env> {
     pub blockchain: Arc<Blockchain<'env>>,
     pub mempool: Option<Arc<Mempool<'env, Blockchain<'env>>>>,
     pub validator_key: KeyPair,
+    /// Selects the transactions of micro blocks from the mempool. Shared, such that it can be
+    /// configured outside of the block producer.
+    pub transaction_selector: Arc<dyn TransactionSelector>,
+    /// Transactions that are left out of micro blocks, even if they are selected
+    pub blacklist: Arc<Blacklist>,
+    /// Bytes of each micro block that are reserved for transactions submitted locally (see
+    /// `Mempool::push_local_transaction`). They are selected before the transaction selector fills
+    /// the rest of the block.
+    pub local_transactions_size: usize,
+    /// If set, produced blocks are checked like `Blockchain::push` would before they are returned
+    /// by `verified_micro_block` and `verified_macro_block_proposal`.
+    pub verify_blocks: bool,
+    /// Statistics about the last micro block that was produced
+    last_stats: Mutex<Option<ProducerStats>>,
+    /// Epoch finalization of the last macro block proposal
+    proposal_cache: Mutex<Option<ProposalCache>>,
 }
 
 impl<'env> BlockProducer<'env> {
     pub fn new(blockchain: Arc<Blockchain<'env>>, mempool: Arc<Mempool<'env, Blockchain<'env>>>, validator_key: KeyPair) -> Self {
-        BlockProducer { blockchain, mempool: Some(mempool), validator_key }
+        Self::with_transaction_selector(blockchain, mempool, validator_key, MempoolOrder)
+    }
+
+    pub fn with_transaction_selector<S: TransactionSelector + 'static>(blockchain: Arc<Blockchain<'env>>, mempool: Arc<Mempool<'env, Blockchain<'env>>>, validator_key: KeyPair, transaction_selector: S) -> Self {
+        BlockProducer { blockchain, mempool: Some(mempool), validator_key, transaction_selector: Arc::new(transaction_selector), blacklist: Arc::new(Blacklist::default()), local_transactions_size: 0, verify_blocks: false, last_stats: Mutex::new(None), proposal_cache: Mutex::new(None) }
     }
 
     pub fn new_without_mempool(blockchain: Arc<Blockchain<'env>>, validator_key: KeyPair) -> Self {
-        BlockProducer { blockchain, mempool: None, validator_key }
+        BlockProducer { blockchain, mempool: None, validator_key, transaction_selector: Arc::new(MempoolOrder), blacklist: Arc::new(Blacklist::default()), local_transactions_size: 0, verify_blocks: false, last_stats: Mutex::new(None), proposal_cache: Mutex::new(None) }
     }
 
+    /// Produces a macro block proposal on top of the current head.
+    ///
+    /// The epoch finalization is cached, such that a re-proposal after a view change only needs
+    /// to apply the slashes of the view changes again. If those are the same as for the last
+    /// proposal, only the header fields that changed are updated.
     pub fn next_macro_block_proposal(&self, timestamp: u64, view_number: u32, view_change_proof: Option<ViewChangeProof>) -> (PbftProposal, MacroExtrinsics) {
+        self.macro_block_proposal(None, timestamp, view_number, view_change_proof)
+    }
+
+    /// Same as `next_macro_block_proposal`, but with the `seed` signed by an external signer (see
+    /// `next_seed_payload`). The proposal is signed with `SignedPbftProposal::from_signature`
+    /// from a signature over its `signing_payload`.
+    pub fn unsigned_macro_block_proposal(&self, seed: CompressedSignature, timestamp: u64, view_number: u32, view_change_proof: Option<ViewChangeProof>) -> (PbftProposal, MacroExtrinsics) {
+        self.macro_block_proposal(Some(seed), timestamp, view_number, view_change_proof)
+    }
+
+    fn macro_block_proposal(&self, seed: Option<CompressedSignature>, timestamp: u64, view_number: u32, view_change_proof: Option<ViewChangeProof>) -> (PbftProposal, MacroExtrinsics) {
         //  Lock blockchain/mempool while constructing the block.
         let _lock = self.blockchain.lock();
 
-        let seed = self.validator_key.sign(self.blockchain.head().seed()).compress();
-        let mut txn = self.blockchain.write_transaction();
+        let block_number = self.blockchain.height() + 1;
+        let parent_hash = self.blockchain.head_hash();
+        let seed = seed.unwrap_or_else(|| self.next_seed());
 
-        let mut header = self.next_macro_header(&mut txn, timestamp, view_number, seed);
-        let extrinsics = self.next_macro_extrinsics(&mut txn, &seed);
-        header.extrinsics_root = extrinsics.hash();
+        let mut cache = self.proposal_cache.lock();
+        if cache.as_ref().map(|cache| cache.parent_hash != parent_hash || cache.seed != seed).unwrap_or(true) {
+            *cache = Some(self.new_proposal_cache(parent_hash.clone(), seed, block_number));
+        }
+        let cache = cache.as_mut().unwrap();
 
-        txn.abort();
+        let mut header = MacroHeader {
+            version: self.blockchain.upgrades.block_version(block_number).expect("No block version active"),
+            validators: CompressedList::empty(),
+            block_number,
+            view_number,
+            parent_macro_hash: self.blockchain.macro_head_hash(),
+            seed: cache.seed.clone(),
+            parent_hash,
+            state_root: Blake2bHash::default(),
+            extrinsics_root: Blake2bHash::default(),
+            timestamp: u64::max(timestamp, self.blockchain.head().timestamp() + 1),
+            transactions_root: cache.transactions_root.clone(),
+            extension: Vec::new(),
+        };
+
+        // Add slashes for view changes.
+        let view_changes = ViewChanges::new(block_number, self.blockchain.view_number(), view_number);
+
+        let is_cached = cache.last_proposal.as_ref()
+            .map(|proposal| proposal.view_changes == view_changes)
+            .unwrap_or(false);
+        if !is_cached {
+            cache.last_proposal = Some(self.next_cached_proposal(&header, &cache.reward_inherents, view_changes));
+        }
+
+        let proposal = cache.last_proposal.as_ref().unwrap();
+        header.state_root = proposal.state_root.clone();
+        header.validators = proposal.validators.clone();
+        header.extrinsics_root = proposal.extrinsics.hash();
 
         (PbftProposal {
             header,
             view_change: view_change_proof,
-        }, extrinsics)
+        }, proposal.extrinsics.clone())
+    }
+
+    /// Same as `next_macro_block_proposal`, but if `verify_blocks` is set, the proposal is checked
+    /// against the blockchain first, such that the validator doesn't propose a block that the
+    /// other validators would reject.
+    pub fn verified_macro_block_proposal(&self, timestamp: u64, view_number: u32, view_change_proof: Option<ViewChangeProof>) -> Result<(PbftProposal, MacroExtrinsics), PushError> {
+        let (proposal, extrinsics) = self.next_macro_block_proposal(timestamp, view_number, view_change_proof);
+        if self.verify_blocks {
+            self.blockchain.verify_successor(&Block::Macro(MacroBlock {
+                header: proposal.header.clone(),
+                justification: None,
+                extrinsics: Some(extrinsics.clone()),
+            }))?;
+        }
+        Ok((proposal, extrinsics))
+    }
+
+    /// Same as `next_micro_block`, but if `verify_blocks` is set, the block is checked against
+    /// the blockchain first, such that the validator doesn't relay a block that its peers would
+    /// reject.
+    pub fn verified_micro_block(&self, fork_proofs: Vec<ForkProof>, timestamp: u64, view_number: u32, extra_data: Vec<u8>, view_change_proof: Option<ViewChangeProof>) -> Result<MicroBlock, PushError> {
+        let template = self.new_block_template(fork_proofs, timestamp, view_number, extra_data, view_change_proof);
+        self.verified_block_template(template, timestamp)
+    }
+
+    /// Same as `finish_block_template`, but if `verify_blocks` is set, the block is checked
+    /// against the blockchain first.
+    pub fn verified_block_template(&self, template: BlockTemplate, timestamp: u64) -> Result<MicroBlock, PushError> {
+        let block = self.finish_block_template(template, timestamp);
+        if self.verify_blocks {
+            self.blockchain.verify_successor(&Block::Micro(block.clone()))?;
+        }
+        Ok(block)
     }
 
     pub fn next_micro_block(&self, fork_proofs: Vec<ForkProof>, timestamp: u64, view_number: u32, extra_data: Vec<u8>, view_change_proof: Option<ViewChangeProof>) -> MicroBlock {
+        let template = self.new_block_template(fork_proofs, timestamp, view_number, extra_data, view_change_proof);
+        self.finish_block_template(template, timestamp)
+    }
+
+    /// Prepares the next micro block on top of the current head, which can be kept up to date
+    /// with `refresh_block_template` and turned into a block with `finish_block_template`.
+    pub fn new_block_template(&self, fork_proofs: Vec<ForkProof>, timestamp: u64, view_number: u32, extra_data: Vec<u8>, view_change_proof: Option<ViewChangeProof>) -> BlockTemplate {
+        self.block_template(None, fork_proofs, timestamp, view_number, extra_data, view_change_proof)
+    }
+
+    /// Same as `new_block_template`, but with the `seed` signed by an external signer (see
+    /// `next_seed_payload`). The block is finished with `BlockTemplate::into_block` from a
+    /// signature over its `signing_payload`.
+    pub fn new_unsigned_block_template(&self, seed: CompressedSignature, fork_proofs: Vec<ForkProof>, timestamp: u64, view_number: u32, extra_data: Vec<u8>, view_change_proof: Option<ViewChangeProof>) -> BlockTemplate {
+        self.block_template(Some(seed), fork_proofs, timestamp, view_number, extra_data, view_change_proof)
+    }
+
+    /// The bytes to sign for the seed of the next block, i.e. the seed of the head. The seed is a
+    /// BLS signature over their Blake2b hash. If the head changes, the seed must be signed again.
+    pub fn next_seed_payload(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        self.blockchain.head().seed().serialize_content(&mut payload).expect("Failed to serialize seed");
+        payload
+    }
+
+    fn block_template(&self, seed: Option<CompressedSignature>, fork_proofs: Vec<ForkProof>, timestamp: u64, view_number: u32, extra_data: Vec<u8>, view_change_proof: Option<ViewChangeProof>) -> BlockTemplate {
         // Lock blockchain/mempool while constructing the block.
         let _lock = self.blockchain.lock();
 
-        let view_changes = ViewChanges::new(self.blockchain.block_number() + 1, self.blockchain.next_view_number(), view_number);
-        let extrinsics = self.next_micro_extrinsics(fork_proofs, extra_data, &view_changes);
-        let header = self.next_micro_header(timestamp, view_number, &extrinsics, &view_changes);
+        let block_number = self.blockchain.block_number() + 1;
+        let view_changes = ViewChanges::new(block_number, self.blockchain.next_view_number(), view_number);
+        let inherents = self.blockchain.create_slash_inherents(&fork_proofs, &view_changes, None);
+
+        let parent_hash = self.blockchain.head_hash();
+        let head = self.blockchain.head();
+        let header = MicroHeader {
+            version: self.blockchain.upgrades.block_version(block_number).expect("No block version active"),
+            block_number,
+            view_number,
+            parent_hash,
+            extrinsics_root: Blake2bHash::default(),
+            state_root: Blake2bHash::default(),
+            seed: seed.unwrap_or_else(|| self.validator_key.sign(head.seed()).compress()),
+            timestamp: u64::max(timestamp, head.timestamp() + 1),
+        };
+        drop(head);
+
+        let mut template = BlockTemplate {
+            header,
+            extrinsics: MicroExtrinsics {
+                fork_proofs,
+                extra_data,
+                transactions: Vec::new(),
+            },
+            inherents,
+            view_change_proof,
+            stats: ProducerStats::default(),
+        };
+        self.fill_block_template(&mut template);
+        template
+    }
+
+    /// Reselects the transactions of `template` from the mempool and recomputes its roots.
+    /// Returns `false`, if the head changed since the template was created. The template is stale
+    /// then and must be replaced by a new one.
+    pub fn refresh_block_template(&self, template: &mut BlockTemplate) -> bool {
+        // Lock blockchain/mempool while constructing the block.
+        let _lock = self.blockchain.lock();
+
+        if template.header.parent_hash != self.blockchain.head_hash() {
+            return false;
+        }
+
+        self.fill_block_template(template);
+        true
+    }
+
+    /// Signs the block of `template`. Its timestamp is moved forward to `timestamp`, if that is
+    /// later than the one it was created with.
+    pub fn finish_block_template(&self, mut template: BlockTemplate, timestamp: u64) -> MicroBlock {
+        template.set_timestamp(timestamp);
+        let signature = self.validator_key.sign(&template.header).compress();
+
+        let mut stats = template.stats.clone();
+        let block = template.into_block(signature);
+        stats.size = block.serialized_size();
+        *self.last_stats.lock() = Some(stats);
+
+        block
+    }
+
+    /// Returns statistics about the last micro block that was produced.
+    pub fn last_stats(&self) -> Option<ProducerStats> {
+        self.last_stats.lock().clone()
+    }
+
+    /// Produces a micro block on top of the block `parent_hash`, which doesn't need to be the head
+    /// of the main chain, but must be part of the current epoch. Since the mempool only tracks the
+    /// main chain, the block doesn't contain any transactions.
+    ///
+    /// Returns `None` if the parent is unknown, can't be reached from the current epoch or is
+    /// followed by a macro block.
+    pub fn next_micro_block_at(&self, parent_hash: &Blake2bHash, fork_proofs: Vec<ForkProof>, timestamp: u64, view_number: u32, extra_data: Vec<u8>, view_change_proof: Option<ViewChangeProof>) -> Option<MicroBlock> {
+        // Lock blockchain/mempool while constructing the block.
+        let _lock = self.blockchain.lock();
+
+        let mut txn = self.blockchain.write_transaction();
+        let parent = match self.blockchain.revert_to(&mut txn, parent_hash) {
+            Some(parent) => parent,
+            None => {
+                txn.abort();
+                return None;
+            },
+        };
+
+        let block_number = parent.block_number() + 1;
+        if policy::is_macro_block_at(block_number) {
+            txn.abort();
+            return None;
+        }
+
+        let view_changes = ViewChanges::new(block_number, parent.next_view_number(), view_number);
+        let extrinsics = MicroExtrinsics {
+            fork_proofs,
+            extra_data,
+            transactions: Vec::new(),
+        };
+
+        let inherents = self.blockchain.create_slash_inherents(&extrinsics.fork_proofs, &view_changes, Some(&txn));
+        let state = self.blockchain.state();
+        state.accounts().commit(&mut txn, &[], &inherents, block_number)
+            .expect("Failed to compute accounts hash during block production");
+        let state_root = state.accounts().hash(Some(&txn));
+        txn.abort();
+
+        let header = MicroHeader {
+            version: self.blockchain.upgrades.block_version(block_number).expect("No block version active"),
+            block_number,
+            view_number,
+            parent_hash: parent_hash.clone(),
+            extrinsics_root: extrinsics.hash(),
+            state_root,
+            seed: self.validator_key.sign(parent.seed()).compress(),
+            timestamp: u64::max(timestamp, parent.timestamp() + 1),
+        };
         let signature = self.validator_key.sign(&header).compress();
 
-        MicroBlock {
+        Some(MicroBlock {
             header,
             extrinsics: Some(extrinsics),
             justification: MicroJustification {
                 signature,
                 view_change_proof,
             },
-        }
+        })
     }
 
-    pub fn next_macro_extrinsics(&self, txn: &mut WriteTransaction, seed: &CompressedSignature) -> MacroExtrinsics {
+    pub fn next_macro_extrinsics(&self, txn: &mut WriteTransaction, seed: &CompressedSignature, version: u16) -> MacroExtrinsics {
         // Determine slashed set without txn, so that it is not garbage collected yet.
         let prev_epoch = policy::epoch_at(self.blockchain.height() + 1) - 1;
         let slashed_set = self.blockchain.state()
             .reward_registry()
             .slashed_set(prev_epoch, None);
-        MacroExtrinsics::from(self.blockchain.next_slots(seed, Some(txn)), slashed_set)
+        MacroExtrinsics::from(version, self.blockchain.next_slots(seed, Some(txn)), slashed_set)
     }
 
-    fn next_micro_extrinsics(&self, fork_proofs: Vec<ForkProof>, extra_data: Vec<u8>, view_changes: &Option<ViewChanges>) -> MicroExtrinsics {
+    fn fill_block_template(&self, template: &mut BlockTemplate) {
+        let block_number = template.header.block_number;
         let max_size = MicroBlock::MAX_SIZE
             - MicroHeader::SIZE
-            - MicroExtrinsics::get_metadata_size(fork_proofs.len(), extra_data.len());
+            - MicroExtrinsics::get_metadata_size(template.extrinsics.fork_proofs.len(), template.extrinsics.extra_data.len());
+
+        let start = Instant::now();
         let mut transactions = self.mempool.as_ref()
-            .map(|mempool| mempool.get_transactions_for_block(max_size))
+            .map(|mempool| self.select_transactions(mempool, max_size))
             .unwrap_or_else(Vec::new);
+        if !self.blacklist.is_empty() {
+            transactions.retain(|transaction| !self.blacklist.contains(transaction));
+        }
+        let selection_time = start.elapsed();
 
-        let inherents = self.blockchain.create_slash_inherents(&fork_proofs, view_changes, None);
-
+        let start = Instant::now();
         self.blockchain.state().accounts()
-            .collect_receipts(&transactions, &inherents, self.blockchain.height() + 1)
+            .collect_receipts(&transactions, &template.inherents, block_number)
             .expect("Failed to collect receipts during block production");
 
         let mut size = transactions.iter().fold(0, |size, tx| size + tx.serialized_size());
@@ -110,96 +378,100 @@ impl<'env> BlockProducer<'env> {
                 size -= transactions.pop().serialized_size();
             }
             self.blockchain.state().accounts()
-                .collect_receipts(&transactions, &inherents, self.blockchain.height() + 1)
+                .collect_receipts(&transactions, &template.inherents, block_number)
                 .expect("Failed to collect pruned accounts during block production");
         }
+        let receipts_time = start.elapsed();
 
         transactions.sort_unstable_by(|a, b| a.cmp_block_order(b));
+        template.extrinsics.transactions = transactions;
 
-        MicroExtrinsics {
-            fork_proofs,
-            extra_data,
-            transactions,
-        }
+        // Rewards are distributed with delay.
+        let start = Instant::now();
+        template.header.state_root = self.blockchain.state().accounts()
+            .hash_with(&template.extrinsics.transactions, &template.inherents, block_number)
+            .expect("Failed to compute accounts hash during block production");
+        template.header.extrinsics_root = template.extrinsics.hash();
+
+        template.stats = ProducerStats {
+            selection_time,
+            receipts_time,
+            state_root_time: start.elapsed(),
+            num_transactions: template.extrinsics.transactions.len(),
+            size: 0,
+            fees: template.extrinsics.transactions.iter().fold(Coin::ZERO, |fees, tx| fees + tx.fee),
+        };
     }
 
-    pub fn next_macro_header(&self, txn: &mut WriteTransaction, timestamp: u64, view_number: u32, seed: CompressedSignature) -> MacroHeader {
-        let block_number = self.blockchain.height() + 1;
-        let timestamp = u64::max(timestamp, self.blockchain.head().timestamp() + 1);
+    /// Takes locally submitted transactions first, up to `local_transactions_size` bytes, and
+    /// fills the rest of the block with the transaction selector.
+    fn select_transactions(&self, mempool: &Mempool<'env, Blockchain<'env>>, max_size: usize) -> Vec<Transaction> {
+        if self.local_transactions_size == 0 {
+            return self.transaction_selector.select_transactions(mempool, max_size);
+        }
 
-        let parent_hash = self.blockchain.head_hash();
-        let parent_macro_hash = self.blockchain.macro_head_hash();
+        let local = mempool.get_local_transactions().into_iter()
+            .filter(|tx| !self.blacklist.contains(tx))
+            .map(|tx| Transaction::clone(&tx));
+        let local = fill_block(local, cmp::min(self.local_transactions_size, max_size));
+        if local.is_empty() {
+            return self.transaction_selector.select_transactions(mempool, max_size);
+        }
 
-        let mut header = MacroHeader {
-            version: Block::VERSION,
-            validators: CompressedList::empty(),
-            block_number,
-            view_number,
-            parent_macro_hash,
-            seed,
+        // The selector might pick some of the local transactions again. So it selects for the
+        // whole block, and the rest of the block is filled from its selection without them.
+        let selected = self.transaction_selector.select_transactions(mempool, max_size);
+        fill_block_after(local, selected, max_size)
+    }
+
+    /// Our seed for the next block. Must be called with the blockchain lock held.
+    fn next_seed(&self) -> CompressedSignature {
+        self.validator_key.sign(self.blockchain.head().seed()).compress()
+    }
+
+    fn new_proposal_cache(&self, parent_hash: Blake2bHash, seed: CompressedSignature, block_number: u32) -> ProposalCache {
+        ProposalCache {
             parent_hash,
-            state_root: Blake2bHash::default(),
-            extrinsics_root: Blake2bHash::default(),
-            timestamp,
-            transactions_root: Blake2bHash::default(),
-        };
+            seed,
+            reward_inherents: self.blockchain.finalize_last_epoch(&self.blockchain.state()),
+            transactions_root: self.blockchain.get_transactions_root(policy::epoch_at(block_number), None)
+                .expect("Failed to compute transactions root, micro blocks missing"),
+            last_proposal: None,
+        }
+    }
+
+    /// Computes the parts of a proposal for `header` that depend on the slashes for its view
+    /// changes.
+    fn next_cached_proposal(&self, header: &MacroHeader, reward_inherents: &[Inherent], view_changes: Option<ViewChanges>) -> CachedProposal {
+        let mut txn = self.blockchain.write_transaction();
 
         let state = self.blockchain.state();
-        state.reward_registry().commit_block(txn, &Block::Macro(MacroBlock {
+        state.reward_registry().commit_block(&mut txn, &Block::Macro(MacroBlock {
             header: header.clone(),
             justification: None,
             extrinsics: None,
         }), state.current_slots().expect("Current slots missing while rebranching"), self.blockchain.view_number())
             .expect("Failed to commit dummy block to reward registry");
 
-        let mut inherents = self.blockchain.finalize_last_epoch(&self.blockchain.state());
-
         // Add slashes for view changes.
-        let view_changes = ViewChanges::new(header.block_number, self.blockchain.view_number(), header.view_number);
-        inherents.append(&mut self.blockchain.create_slash_inherents(&[], &view_changes, Some(txn)));
+        let mut inherents = reward_inherents.to_vec();
+        inherents.append(&mut self.blockchain.create_slash_inherents(&[], &view_changes, Some(&txn)));
 
         // Rewards are distributed with delay.
-        state.accounts().commit(txn, &[], &inherents, block_number)
+        state.accounts().commit(&mut txn, &[], &inherents, header.block_number)
             .expect("Failed to compute accounts hash during block production");
+        let state_root = state.accounts().hash(Some(&txn));
+        drop(state);
 
-        let state_root = state.accounts().hash(Some(txn));
-
-        let transactions_root = self.blockchain.get_transactions_root(policy::epoch_at(block_number), Some(txn))
-            .expect("Failed to compute transactions root, micro blocks missing");
-
-        let validators = self.blockchain.next_validators(&seed, Some(txn));
-
-        header.validators = validators.into();
-        header.state_root = state_root;
-        header.transactions_root = transactions_root;
-
-        header
-    }
-
-    fn next_micro_header(&self, timestamp: u64, view_number: u32, extrinsics: &MicroExtrinsics, view_changes: &Option<ViewChanges>) -> MicroHeader {
-        let block_number = self.blockchain.height() + 1;
-        let timestamp = u64::max(timestamp, self.blockchain.head().timestamp() + 1);
-
-        let parent_hash = self.blockchain.head_hash();
-        let extrinsics_root = extrinsics.hash();
-
-        let inherents = self.blockchain.create_slash_inherents(&extrinsics.fork_proofs, view_changes, None);
-        // Rewards are distributed with delay.
-        let state_root = self.blockchain.state().accounts()
-            .hash_with(&extrinsics.transactions, &inherents, block_number)
-            .expect("Failed to compute accounts hash during block production");
-
-        let seed = self.validator_key.sign(self.blockchain.head().seed()).compress();
+        let validators = self.blockchain.next_validators(&header.seed, Some(&txn)).into();
+        let extrinsics = self.next_macro_extrinsics(&mut txn, &header.seed, header.version);
+        txn.abort();
 
-        MicroHeader {
-            version: Block::VERSION,
-            block_number,
-            view_number,
-            parent_hash,
-            extrinsics_root,
+        CachedProposal {
+            view_changes,
             state_root,
-            seed,
-            timestamp,
+            validators,
+            extrinsics,
         }
     }
 }
@@ -0,0 +1,26 @@
+use account::Inherent;
+use block::{MacroExtrinsics, ViewChanges};
+use bls::bls12_381::CompressedSignature;
+use bls::bls12_381::lazy::LazyPublicKey;
+use collections::compressed_list::CompressedList;
+use hash::Blake2bHash;
+
+/// The parts of the macro block proposals on top of a block that don't depend on the view
+/// number, so that re-proposals after view changes don't have to finalize the epoch again.
+pub(crate) struct ProposalCache {
+    /// The block the proposals build on
+    pub(crate) parent_hash: Blake2bHash,
+    pub(crate) seed: CompressedSignature,
+    /// Reward inherents finalizing the last epoch
+    pub(crate) reward_inherents: Vec<Inherent>,
+    pub(crate) transactions_root: Blake2bHash,
+    pub(crate) last_proposal: Option<CachedProposal>,
+}
+
+/// The parts of the last proposal that follow from the slashes of its view changes.
+pub(crate) struct CachedProposal {
+    pub(crate) view_changes: Option<ViewChanges>,
+    pub(crate) state_root: Blake2bHash,
+    pub(crate) validators: CompressedList<LazyPublicKey>,
+    pub(crate) extrinsics: MacroExtrinsics,
+}
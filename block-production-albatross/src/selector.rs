@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+
+use beserial::Serialize;
+use blockchain::blockchain::Blockchain;
+use hash::{Blake2bHash, Hash};
+use mempool::Mempool;
+use transaction::{Transaction, TransactionFlags};
+
+/// Selects the transactions of a micro block from the mempool.
+///
+/// The selected transactions must be taken from the mempool and must not exceed `max_size` bytes
+/// in total (serialized). Their order doesn't matter, since the block producer sorts them into
+/// block order.
+pub trait TransactionSelector: Send + Sync {
+    fn select_transactions<'env>(&self, mempool: &Mempool<'env, Blockchain<'env>>, max_size: usize) -> Vec<Transaction>;
+}
+
+/// The default selector, which takes transactions in the order of the mempool (i.e. by fee per
+/// byte) until the block is full.
+#[derive(Clone, Debug, Default)]
+pub struct MempoolOrder;
+
+impl TransactionSelector for MempoolOrder {
+    fn select_transactions<'env>(&self, mempool: &Mempool<'env, Blockchain<'env>>, max_size: usize) -> Vec<Transaction> {
+        mempool.get_transactions_for_block(max_size)
+    }
+}
+
+/// Takes transactions from the mempool in its order, skipping those for which `filter` returns
+/// `false`, until the block is full. This is a building block for selectors that only restrict
+/// which transactions go into a block, e.g. to exclude contract creations.
+pub struct FilteredMempoolOrder<F: Fn(&Transaction) -> bool + Send + Sync> {
+    filter: F,
+}
+
+impl<F: Fn(&Transaction) -> bool + Send + Sync> FilteredMempoolOrder<F> {
+    pub fn new(filter: F) -> Self {
+        FilteredMempoolOrder { filter }
+    }
+}
+
+impl FilteredMempoolOrder<fn(&Transaction) -> bool> {
+    /// Leaves out transactions that create contracts
+    pub fn without_contract_creations() -> Self {
+        Self::new(|tx| !tx.flags.contains(TransactionFlags::CONTRACT_CREATION))
+    }
+}
+
+impl<F: Fn(&Transaction) -> bool + Send + Sync> TransactionSelector for FilteredMempoolOrder<F> {
+    fn select_transactions<'env>(&self, mempool: &Mempool<'env, Blockchain<'env>>, max_size: usize) -> Vec<Transaction> {
+        mempool.get_filtered_transactions_for_block(max_size, &self.filter)
+    }
+}
+
+/// Takes transactions from `candidates` in order, skipping those that don't fit anymore, until
+/// no transaction fits into `max_size` bytes anymore.
+pub fn fill_block<I: IntoIterator<Item=Transaction>>(candidates: I, max_size: usize) -> Vec<Transaction> {
+    let mut txs = Vec::new();
+    let mut size = 0;
+
+    for tx in candidates {
+        let tx_size = tx.serialized_size();
+        if size + tx_size <= max_size {
+            txs.push(tx);
+            size += tx_size;
+        }
+        else if max_size - size < Transaction::MIN_SIZE {
+            // Break if we can't fit the smallest possible transaction anymore.
+            break;
+        }
+    }
+    txs
+}
+
+/// Takes all of `first`, which must fit into `max_size` bytes, and fills the rest of the block
+/// from `candidates` like `fill_block`. Candidates that are in `first` already are skipped
+/// before filling, so they don't take up space twice.
+pub fn fill_block_after<I: IntoIterator<Item=Transaction>>(first: Vec<Transaction>, candidates: I, max_size: usize) -> Vec<Transaction> {
+    let size = first.iter().fold(0, |size, tx| size + tx.serialized_size());
+    let hashes: HashSet<Blake2bHash> = first.iter().map(|tx| tx.hash()).collect();
+    let candidates = candidates.into_iter()
+        .filter(|tx| !hashes.contains(&tx.hash::<Blake2bHash>()));
+
+    let mut txs = first;
+    txs.extend(fill_block(candidates, max_size - size));
+    txs
+}
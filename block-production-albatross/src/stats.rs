@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+use primitives::coin::Coin;
+
+/// Statistics about the production of a micro block, e.g. to find out why blocks are small or
+/// slow to produce.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProducerStats {
+    /// Time spent selecting the transactions from the mempool
+    pub selection_time: Duration,
+    /// Time spent applying the selected transactions to the accounts to collect their receipts
+    pub receipts_time: Duration,
+    /// Time spent computing the state root
+    pub state_root_time: Duration,
+    pub num_transactions: usize,
+    /// Serialized size of the block. Only known once the block is signed.
+    pub size: usize,
+    /// Sum of the fees of all transactions in the block
+    pub fees: Coin,
+}
@@ -0,0 +1,76 @@
+use account::Inherent;
+use block::{MicroBlock, MicroExtrinsics, MicroHeader, MicroJustification, ViewChangeProof};
+use bls::bls12_381::CompressedSignature;
+use hash::{Blake2bHash, SerializeContent};
+use transaction::Transaction;
+
+use crate::stats::ProducerStats;
+
+/// A micro block that is being prepared for a slot, see `BlockProducer::new_block_template`.
+///
+/// Everything that only depends on the parent block (seed, view changes, slash inherents) is
+/// computed once. Refreshing the template only reselects the transactions and recomputes the
+/// roots, so that a validator can keep it up to date with the mempool until it proposes the block.
+pub struct BlockTemplate {
+    /// The header of the block. The state and extrinsics roots always match the current
+    /// extrinsics.
+    pub(crate) header: MicroHeader,
+    pub(crate) extrinsics: MicroExtrinsics,
+    /// Slash inherents for the fork proofs and view changes of the block
+    pub(crate) inherents: Vec<Inherent>,
+    pub(crate) view_change_proof: Option<ViewChangeProof>,
+    pub(crate) stats: ProducerStats,
+}
+
+impl BlockTemplate {
+    /// The block the template builds on. The template is stale once this isn't the head of the
+    /// main chain anymore.
+    pub fn parent_hash(&self) -> &Blake2bHash {
+        &self.header.parent_hash
+    }
+
+    pub fn block_number(&self) -> u32 {
+        self.header.block_number
+    }
+
+    pub fn view_number(&self) -> u32 {
+        self.header.view_number
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.extrinsics.transactions
+    }
+
+    /// Statistics about the last time the transactions were selected. The size of the block is
+    /// only known once it is finished.
+    pub fn stats(&self) -> &ProducerStats {
+        &self.stats
+    }
+
+    /// Moves the timestamp of the block forward to `timestamp`, if that is later than the current
+    /// one. This changes the signing payload.
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.header.timestamp = u64::max(self.header.timestamp, timestamp);
+    }
+
+    /// The bytes the block producer signs, i.e. the header of the block. The signature is a BLS
+    /// signature over their Blake2b hash.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        self.header.serialize_content(&mut payload).expect("Failed to serialize header");
+        payload
+    }
+
+    /// Turns the template into a block with a signature over the `signing_payload`, e.g. by an
+    /// external signer. The signature is not verified.
+    pub fn into_block(self, signature: CompressedSignature) -> MicroBlock {
+        MicroBlock {
+            header: self.header,
+            extrinsics: Some(self.extrinsics),
+            justification: MicroJustification {
+                signature,
+                view_change_proof: self.view_change_proof,
+            },
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use failure::Fail;
+use parking_lot::RwLock;
+
+use bls::bls12_381::{CompressedPublicKey, KeyPair};
+use collections::grouped_list::Group;
+use primitives::validators::Validators;
+use utils::key_store::{Error as KeyStoreError, KeyStore};
+
+#[derive(Debug, Fail)]
+pub enum ValidatorKeysError {
+    #[fail(display = "A key rotation is already in progress")]
+    RotationInProgress,
+    #[fail(display = "Failed to store validator key: {}", _0)]
+    KeyStore(#[cause] KeyStoreError),
+}
+
+impl From<KeyStoreError> for ValidatorKeysError {
+    fn from(e: KeyStoreError) -> Self {
+        ValidatorKeysError::KeyStore(e)
+    }
+}
+
+/// The BLS keys of a validator.
+///
+/// Usually this is a single key. A key rotation adds a new key while the old one is still
+/// elected, so during the transition the validator holds both: it signs with whichever key was
+/// elected for the current epoch, and the old key is dropped once it is neither elected nor
+/// staked anymore.
+///
+/// The keys can be rotated while the validator is running. If key stores are set, the keys are
+/// written to them, such that a restart during the transition loads both keys again.
+pub struct ValidatorKeys {
+    /// The newest key, followed by the key it replaces while a rotation is in progress.
+    keys: RwLock<Vec<KeyPair>>,
+    /// Where the newest and the previous key are stored.
+    key_stores: Option<(KeyStore, KeyStore)>,
+}
+
+impl ValidatorKeys {
+    pub fn new(key: KeyPair, previous_key: Option<KeyPair>) -> Self {
+        let mut keys = vec![key];
+        // A crash during `rotate` can leave the current key in both key stores.
+        if let Some(previous_key) = previous_key {
+            if previous_key.public.compress() != keys[0].public.compress() {
+                keys.push(previous_key);
+            }
+        }
+        ValidatorKeys {
+            keys: RwLock::new(keys),
+            key_stores: None,
+        }
+    }
+
+    /// Writes rotated keys to `key_store` and the key they replace to `previous_key_store`.
+    pub fn with_key_stores(mut self, key_store: KeyStore, previous_key_store: KeyStore) -> Self {
+        self.key_stores = Some((key_store, previous_key_store));
+        self
+    }
+
+    /// The newest key, i.e. the key a pending rotation switches to.
+    pub fn current(&self) -> KeyPair {
+        self.keys.read()[0].clone()
+    }
+
+    /// The key that a pending rotation replaces.
+    pub fn previous(&self) -> Option<KeyPair> {
+        self.keys.read().get(1).cloned()
+    }
+
+    /// All keys, the newest first.
+    pub fn key_pairs(&self) -> Vec<KeyPair> {
+        self.keys.read().clone()
+    }
+
+    pub fn public_keys(&self) -> Vec<CompressedPublicKey> {
+        self.keys.read().iter()
+            .map(|key_pair| key_pair.public.compress())
+            .collect()
+    }
+
+    /// Returns the key that was elected in `validators`, together with its index in `validators`
+    /// (the `pk_idx`) and its number of slots.
+    pub fn elected(&self, validators: &Validators) -> Option<(KeyPair, u16, u16)> {
+        self.keys.read().iter().find_map(|key_pair| {
+            let compressed = key_pair.public.compress();
+            validators.groups().iter().enumerate()
+                .find(|(_, Group(_, public_key))| public_key.compressed() == &compressed)
+                .map(|(pk_idx, Group(num_slots, _))| (key_pair.clone(), pk_idx as u16, *num_slots))
+        })
+    }
+
+    /// Starts a rotation to `new_key`. The current key is kept until `complete_rotation` drops
+    /// it. The rotation itself is a transaction to the staking contract that must be sent
+    /// separately.
+    pub fn rotate(&self, new_key: KeyPair) -> Result<(), ValidatorKeysError> {
+        let mut keys = self.keys.write();
+        if keys.len() > 1 {
+            return Err(ValidatorKeysError::RotationInProgress);
+        }
+
+        if let Some((ref key_store, ref previous_key_store)) = self.key_stores {
+            // Store the current key first, such that it isn't lost if we crash in between.
+            previous_key_store.save_key(&keys[0])?;
+            key_store.save_key(&new_key)?;
+        }
+
+        keys.insert(0, new_key);
+        Ok(())
+    }
+
+    /// Drops the key a rotation replaces, if it isn't `in_use` anymore, i.e. if it is neither
+    /// elected nor staked. Returns its public key if it was dropped.
+    pub fn complete_rotation<F>(&self, in_use: F) -> Option<CompressedPublicKey>
+        where F: Fn(&CompressedPublicKey) -> bool {
+        let mut keys = self.keys.write();
+        let previous_key = keys.get(1)?.public.compress();
+        if in_use(&previous_key) {
+            return None;
+        }
+
+        if let Some((_, ref previous_key_store)) = self.key_stores {
+            if let Err(e) = previous_key_store.remove_key() {
+                warn!("Failed to remove previous validator key: {}", e);
+            }
+        }
+
+        keys.truncate(1);
+        Some(previous_key)
+    }
+}
@@ -1,20 +1,31 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use beserial::Deserialize;
-use nimiq_block_albatross::{Block, BlockError, ForkProof, MacroBlock, MacroExtrinsics, PbftCommitMessage, PbftPrepareMessage, PbftProofBuilder, PbftProposal, SignedPbftCommitMessage, SignedPbftPrepareMessage, SignedViewChange, ViewChange, ViewChangeProof, ViewChangeProofBuilder};
-use nimiq_block_albatross::signed::SignedMessage;
+use beserial::{Deserialize, Serialize};
+use rand::thread_rng;
+use tempdir::TempDir;
+
+use nimiq_block_albatross::{Block, BlockError, ForkProof, MacroBlock, MacroExtrinsics, PbftCommitMessage, PbftPrepareMessage, PbftProofBuilder, PbftProposal, SignedPbftCommitMessage, SignedPbftPrepareMessage, SignedPbftProposal, SignedViewChange, ViewChange, ViewChangeProof, ViewChangeProofBuilder};
+use nimiq_block_albatross::signed::{Message, SignedMessage};
 use nimiq_block_production_albatross::BlockProducer;
+use nimiq_block_production_albatross::blacklist::Blacklist;
+use nimiq_block_production_albatross::selector::{fill_block_after, TransactionSelector};
+use nimiq_block_production_albatross::validator_keys::{ValidatorKeys, ValidatorKeysError};
 use nimiq_blockchain_albatross::blockchain::{Blockchain, PushError, PushResult};
 use nimiq_blockchain_base::AbstractBlockchain;
 use nimiq_bls::{KeyPair, SecretKey};
 use nimiq_bls::bls12_381::lazy::LazyPublicKey;
 use nimiq_collections::grouped_list::{Group, GroupedList};
 use nimiq_database::volatile::VolatileEnvironment;
-use nimiq_hash::{Blake2bHash, Hash};
+use nimiq_hash::{Blake2bHash, Blake2bHasher, Hash, Hasher};
+use nimiq_keys::Address;
 use nimiq_mempool::{Mempool, MempoolConfig};
 use nimiq_network_primitives::{networks::NetworkId};
+use nimiq_primitives::coin::Coin;
 use nimiq_primitives::policy;
 use nimiq_primitives::validators::Validators;
+use nimiq_transaction::Transaction;
+use nimiq_utils::key_store::KeyStore;
 
 /// Secret key of validator. Tests run with `network-primitives/src/genesis/unit-albatross.toml`
 const SECRET_KEY: &'static str = "49ea68eb6b8afdf4ca4d4c0a0b295c76ca85225293693bc30e755476492b707f";
@@ -131,4 +142,379 @@ fn it_can_produce_macro_blocks() {
     assert_eq!(blockchain.push_block(Block::Macro(block), true), Ok(PushResult::Extended));
 }
 
+#[test]
+fn it_can_repropose_macro_blocks_after_view_changes() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let producer = BlockProducer::new(Arc::clone(&blockchain), mempool, keypair.clone());
+
+    fill_micro_blocks(&producer, &blockchain);
+    let block_number = blockchain.block_number() + 1;
+
+    let (proposal, extrinsics) = producer.next_macro_block_proposal(1565720000000u64, 0u32, None);
+    assert_eq!(producer.next_macro_block_proposal(1565720000000u64, 0u32, None), (proposal, extrinsics));
+
+    // The re-proposal must match a proposal that is computed from scratch.
+    let (proposal, extrinsics) = producer.next_macro_block_proposal(1565720010000u64, 1u32, Some(sign_view_change(block_number, 1)));
+    let fresh_producer = BlockProducer::new_without_mempool(Arc::clone(&blockchain), keypair);
+    let (fresh_proposal, fresh_extrinsics) = fresh_producer.next_macro_block_proposal(1565720010000u64, 1u32, Some(sign_view_change(block_number, 1)));
+    assert_eq!(proposal.header, fresh_proposal.header);
+    assert_eq!(extrinsics, fresh_extrinsics);
+
+    let block = sign_macro_block(proposal, Some(extrinsics));
+    assert_eq!(blockchain.push_block(Block::Macro(block), true), Ok(PushResult::Extended));
+}
+
+#[test]
+fn it_accepts_view_changes_that_skip_views() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let producer = BlockProducer::new(Arc::clone(&blockchain), mempool, keypair);
+
+    // #1.3: Skip views 1 and 2 with a single view change
+    let view_change = sign_view_change(1, 3);
+    let block = producer.next_micro_block(vec![], 1565713920000, 3, vec![0x41], Some(view_change));
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+    assert_eq!(blockchain.block_number(), 1);
+    assert_eq!(blockchain.next_view_number(), 3);
+}
+
+#[test]
+fn it_can_produce_micro_blocks_on_forks() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let producer = BlockProducer::new(Arc::clone(&blockchain), mempool, keypair);
+    let genesis_hash = blockchain.head_hash();
+
+    // Main chain: #1.0, #2.0
+    for i in 1..3 {
+        let block = producer.next_micro_block(vec![], 1565713920000 + i * 2000, 0, vec![0x41], None);
+        assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+    }
+    let main_head = blockchain.head_hash();
+    let main_first = blockchain.get_block_at(1, false).unwrap().hash();
+
+    // Fork: #1.0', #2.0'
+    let mut fork_head = genesis_hash;
+    for i in 1..3 {
+        let block = producer.next_micro_block_at(&fork_head, vec![], 1565713920000 + i * 2000, 0, vec![0x42], None).unwrap();
+        assert_eq!(block.header.block_number, i as u32);
+        fork_head = block.header.hash::<Blake2bHash>();
+        assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Forked));
+    }
+    assert_eq!(blockchain.head_hash(), main_head);
+
+    // #3.0' makes the fork the main chain
+    let block = producer.next_micro_block_at(&fork_head, vec![], 1565713926000, 0, vec![0x42], None).unwrap();
+    let fork_head = block.header.hash::<Blake2bHash>();
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Rebranched));
+    assert_eq!(blockchain.block_number(), 3);
+
+    // Producing on the old main chain still works
+    let block = producer.next_micro_block_at(&main_head, vec![], 1565713926000, 0, vec![0x41], None).unwrap();
+    assert_eq!(block.header.block_number, 3);
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Forked));
+
+    // #2.1 on #1.0 slashes the producer of #2.0 and wins by its view number
+    let block = producer.next_micro_block_at(&main_first, vec![], 1565713924000, 1, vec![0x43], Some(sign_view_change(2, 1))).unwrap();
+    let slashed_head = block.header.hash::<Blake2bHash>();
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Rebranched));
+    assert_eq!(blockchain.block_number(), 2);
+    assert_eq!(blockchain.next_view_number(), 1);
+
+    // #3.2 with another view change. Its slashes depend on the ones of #2.1, so the block is only
+    // valid if those were applied to the reward registry.
+    let block = producer.next_micro_block_at(&slashed_head, vec![], 1565713928000, 2, vec![0x43], Some(sign_view_change(3, 2))).unwrap();
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+    assert_eq!(blockchain.block_number(), 3);
+    assert_eq!(blockchain.next_view_number(), 2);
+
+    // #4.3' back on the chain without the slashes of #2.1 and #3.2, which requires reverting them
+    // in the registry
+    let block = producer.next_micro_block_at(&fork_head, vec![], 1565713930000, 3, vec![0x42], Some(sign_view_change(4, 3))).unwrap();
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Rebranched));
+    assert_eq!(blockchain.block_number(), 4);
+    assert_eq!(blockchain.next_view_number(), 3);
+
+    // Unknown parent
+    assert!(producer.next_micro_block_at(&Blake2bHash::default(), vec![], 1565713932000, 0, vec![], None).is_none());
+}
+
+/// Selects no transactions, but remembers the block size it was asked for
+#[derive(Default)]
+struct EmptySelector {
+    max_size: Arc<AtomicUsize>,
+}
+
+impl TransactionSelector for EmptySelector {
+    fn select_transactions<'env>(&self, _mempool: &Mempool<'env, Blockchain<'env>>, max_size: usize) -> Vec<Transaction> {
+        self.max_size.store(max_size, Ordering::SeqCst);
+        Vec::new()
+    }
+}
+
+#[test]
+fn it_uses_the_transaction_selector() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+
+    let selector = EmptySelector::default();
+    let max_size = Arc::clone(&selector.max_size);
+    let producer = BlockProducer::with_transaction_selector(Arc::clone(&blockchain), mempool, keypair, selector);
+
+    let block = producer.next_micro_block(vec![], 1565713920000, 0, vec![0x41], None);
+    assert!(max_size.load(Ordering::SeqCst) > 0);
+    assert!(max_size.load(Ordering::SeqCst) < nimiq_block_albatross::MicroBlock::MAX_SIZE);
+    assert!(block.extrinsics.as_ref().unwrap().transactions.is_empty());
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+}
+
+#[test]
+fn it_can_refresh_block_templates() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let producer = BlockProducer::new(Arc::clone(&blockchain), mempool, keypair);
+
+    let mut template = producer.new_block_template(vec![], 1565713920000, 0, vec![0x41], None);
+    assert_eq!(template.block_number(), 1);
+    assert_eq!(template.parent_hash(), &blockchain.head_hash());
+    assert!(producer.refresh_block_template(&mut template));
+
+    // Finishing the template moves the timestamp forward
+    let block = producer.finish_block_template(template, 1565713921000);
+    assert_eq!(block.header.timestamp, 1565713921000);
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+
+    // The template is stale once the head changes
+    let mut template = producer.new_block_template(vec![], 1565713922000, 0, vec![0x41], None);
+    let block = producer.next_micro_block(vec![], 1565713922000, 0, vec![0x42], None);
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+    assert!(!producer.refresh_block_template(&mut template));
+}
+
+#[test]
+fn it_verifies_block_templates() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let mut producer = BlockProducer::new(Arc::clone(&blockchain), mempool, keypair);
+    producer.verify_blocks = true;
+
+    let template = producer.new_block_template(vec![], 1565713920000, 0, vec![0x41], None);
+    let block = producer.verified_block_template(template, 1565713921000).unwrap();
+    assert_eq!(block.header.timestamp, 1565713921000);
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+
+    // A stale template fails the verification
+    let template = producer.new_block_template(vec![], 1565713922000, 0, vec![0x41], None);
+    let block = producer.next_micro_block(vec![], 1565713922000, 0, vec![0x42], None);
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+    assert!(producer.verified_block_template(template, 1565713923000).is_err());
+}
+
+#[test]
+fn it_records_producer_stats() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let producer = BlockProducer::new(Arc::clone(&blockchain), mempool, keypair);
+    assert_eq!(producer.last_stats(), None);
+
+    let block = producer.next_micro_block(vec![], 1565713920000, 0, vec![0x41], None);
+    let stats = producer.last_stats().unwrap();
+    assert_eq!(stats.num_transactions, 0);
+    assert_eq!(stats.fees, Coin::ZERO);
+    assert_eq!(stats.size, block.serialized_size());
+}
+
+#[test]
+fn it_can_verify_produced_blocks() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let mut producer = BlockProducer::new(Arc::clone(&blockchain), mempool, keypair.clone());
+    producer.verify_blocks = true;
+
+    let block = producer.verified_micro_block(vec![], 1565713920000, 0, vec![0x41], None).unwrap();
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+
+    // A correctly signed block with a wrong state root is rejected without touching the chain.
+    let mut block = producer.next_micro_block(vec![], 1565713922000, 0, vec![0x41], None);
+    block.header.state_root = Blake2bHash::default();
+    block.justification.signature = keypair.sign(&block.header).compress();
+    assert_eq!(blockchain.verify_successor(&Block::Micro(block)), Err(PushError::InvalidBlock(BlockError::AccountsHashMismatch)));
+    assert_eq!(blockchain.block_number(), 1);
+
+    // A block with an unknown parent is rejected instead of panicking.
+    let mut block = producer.next_micro_block(vec![], 1565713922000, 0, vec![0x41], None);
+    block.header.parent_hash = Blake2bHash::default();
+    block.justification.signature = keypair.sign(&block.header).compress();
+    assert_eq!(blockchain.verify_successor(&Block::Micro(block)), Err(PushError::Orphan));
+
+    fill_micro_blocks(&producer, &blockchain);
+
+    let (proposal, extrinsics) = producer.verified_macro_block_proposal(1565720000000u64, 0u32, None).unwrap();
+    let block = sign_macro_block(proposal, Some(extrinsics));
+    assert_eq!(blockchain.push(Block::Macro(block)), Ok(PushResult::Extended));
+}
+
+#[test]
+fn it_can_produce_unsigned_blocks() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let producer = BlockProducer::new(Arc::clone(&blockchain), mempool, keypair.clone());
+
+    // The external signer only sees the payloads
+    let sign = |payload: &[u8]| keypair.sign_hash(Blake2bHasher::default().digest(payload));
+
+    let seed = sign(&producer.next_seed_payload()).compress();
+    let template = producer.new_unsigned_block_template(seed, vec![], 1565713920000, 0, vec![0x41], None);
+    let signature = sign(&template.signing_payload()).compress();
+    let block = template.into_block(signature);
+    assert_eq!(block, producer.next_micro_block(vec![], 1565713920000, 0, vec![0x41], None));
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+
+    fill_micro_blocks(&producer, &blockchain);
+
+    let seed = sign(&producer.next_seed_payload()).compress();
+    let (proposal, extrinsics) = producer.unsigned_macro_block_proposal(seed, 1565720000000u64, 0u32, None);
+    assert_eq!(proposal, producer.next_macro_block_proposal(1565720000000u64, 0u32, None).0);
+    let signed_proposal = SignedPbftProposal::from_signature(proposal.clone(), sign(&proposal.signing_payload()), 0);
+    assert!(signed_proposal.verify(&keypair.public));
+
+    let block = sign_macro_block(proposal, Some(extrinsics));
+    assert_eq!(blockchain.push(Block::Macro(block)), Ok(PushResult::Extended));
+}
+
+#[test]
+fn it_matches_blacklisted_transactions() {
+    let sender = Address::from([1u8; Address::SIZE]);
+    let recipient = Address::from([2u8; Address::SIZE]);
+    let transaction = Transaction::new_basic(sender.clone(), recipient.clone(), Coin::from_u64_unchecked(1000), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    let other = Transaction::new_basic(recipient.clone(), Address::from([3u8; Address::SIZE]), Coin::from_u64_unchecked(1000), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+
+    let blacklist = Blacklist::default();
+    assert!(!blacklist.contains(&transaction));
+
+    assert!(blacklist.add_address(recipient.clone()));
+    assert!(!blacklist.add_address(recipient.clone()));
+    assert!(blacklist.contains(&transaction));
+    assert!(blacklist.contains(&other));
+
+    assert!(blacklist.remove_address(&recipient));
+    assert!(blacklist.add_transaction(transaction.hash()));
+    assert!(blacklist.contains(&transaction));
+    assert!(!blacklist.contains(&other));
+}
+
+#[test]
+fn it_fills_blocks_after_local_transactions() {
+    let transactions: Vec<Transaction> = (1..=4u64)
+        .map(|value| Transaction::new_basic(Address::from([1u8; Address::SIZE]), Address::from([2u8; Address::SIZE]), Coin::from_u64_unchecked(value), Coin::ZERO, 1, NetworkId::UnitAlbatross))
+        .collect();
+    let tx_size = transactions[0].serialized_size();
+    let local = vec![transactions[0].clone(), transactions[1].clone()];
+
+    // The selection contains a local transaction again, which must not take up the space of the
+    // other transactions.
+    let selected = vec![transactions[0].clone(), transactions[2].clone(), transactions[3].clone()];
+    let block = fill_block_after(local.clone(), selected, 4 * tx_size);
+    assert_eq!(block, transactions);
+
+    let selected = vec![transactions[0].clone(), transactions[2].clone(), transactions[3].clone()];
+    let block = fill_block_after(local, selected, 3 * tx_size);
+    assert_eq!(block, transactions[..3].to_vec());
+}
+
+#[test]
+fn it_signs_with_the_elected_validator_key() {
+    let key = KeyPair::generate(&mut thread_rng());
+    let new_key = KeyPair::generate(&mut thread_rng());
+    let other = KeyPair::generate(&mut thread_rng());
+    let keys = ValidatorKeys::new(key.clone(), None);
+
+    let validators: Validators = GroupedList(vec![
+        Group(3, LazyPublicKey::from(other.public)),
+        Group(5, LazyPublicKey::from(key.public)),
+    ]);
+    assert!(keys.rotate(new_key.clone()).is_ok());
+    assert_eq!(keys.public_keys(), vec![new_key.public.compress(), key.public.compress()]);
+
+    // The previous key signs while it is still elected
+    let (elected, pk_idx, num_slots) = keys.elected(&validators).unwrap();
+    assert_eq!(elected.public.compress(), key.public.compress());
+    assert_eq!((pk_idx, num_slots), (1, 5));
+
+    // The new key signs once it is elected
+    let validators: Validators = GroupedList(vec![
+        Group(3, LazyPublicKey::from(other.public)),
+        Group(5, LazyPublicKey::from(new_key.public)),
+    ]);
+    let (elected, pk_idx, num_slots) = keys.elected(&validators).unwrap();
+    assert_eq!(elected.public.compress(), new_key.public.compress());
+    assert_eq!((pk_idx, num_slots), (1, 5));
+
+    let validators: Validators = GroupedList(vec![Group(3, LazyPublicKey::from(other.public))]);
+    assert!(keys.elected(&validators).is_none());
+}
+
+#[test]
+fn it_persists_validator_key_rotations() {
+    let dir = TempDir::new("validator-keys").unwrap();
+    let key_file = dir.path().join("validator_key.dat").to_str().unwrap().to_string();
+    let previous_key_file = format!("{}.previous", key_file);
+
+    let key = KeyPair::generate(&mut thread_rng());
+    let new_key = KeyPair::generate(&mut thread_rng());
+    KeyStore::new(key_file.clone()).save_key(&key).unwrap();
+    let keys = ValidatorKeys::new(key.clone(), None)
+        .with_key_stores(KeyStore::new(key_file.clone()), KeyStore::new(previous_key_file.clone()));
+
+    // Both keys are stored, such that a restart loads them again
+    assert!(keys.rotate(new_key.clone()).is_ok());
+    assert_eq!(KeyStore::new(key_file.clone()).load_key().ok(), Some(new_key.clone()));
+    assert_eq!(KeyStore::new(previous_key_file.clone()).load_key().ok(), Some(key.clone()));
+
+    // Only one rotation at a time
+    match keys.rotate(KeyPair::generate(&mut thread_rng())) {
+        Err(ValidatorKeysError::RotationInProgress) => {},
+        _ => panic!("Second rotation must be rejected"),
+    }
+    assert_eq!(keys.current().public.compress(), new_key.public.compress());
+
+    // The previous key is kept while it is in use
+    let previous_key = key.public.compress();
+    assert_eq!(keys.complete_rotation(|public_key| public_key == &previous_key), None);
+    assert!(keys.previous().is_some());
+    assert!(dir.path().join("validator_key.dat.previous").exists());
+
+    assert_eq!(keys.complete_rotation(|_| false), Some(previous_key));
+    assert!(keys.previous().is_none());
+    assert!(!dir.path().join("validator_key.dat.previous").exists());
+    assert_eq!(keys.complete_rotation(|_| false), None);
+}
+
+#[test]
+fn it_ignores_a_previous_validator_key_equal_to_the_current_one() {
+    let key = KeyPair::generate(&mut thread_rng());
+    let keys = ValidatorKeys::new(key.clone(), Some(key.clone()));
+    assert_eq!(keys.public_keys(), vec![key.public.compress()]);
+    assert!(keys.previous().is_none());
+}
+
 // TODO Test transactions
@@ -0,0 +1,75 @@
+use failure::Fail;
+
+use block::Block;
+use database::ReadTransaction;
+use hash::{Blake2bHash, Hash};
+use macro_verifier::attestation::{Attestation, JustifiedMacroHeader};
+use primitives::policy;
+use utils::merkle::MerkleProof;
+
+use crate::blockchain::Blockchain;
+
+#[derive(Debug, Fail)]
+pub enum AttestationError {
+    #[fail(display = "Block not found")]
+    BlockNotFound,
+    #[fail(display = "Transaction not found in block")]
+    TransactionNotFound,
+    #[fail(display = "The transaction's epoch isn't finalized yet")]
+    EpochNotFinalized,
+    #[fail(display = "The trusted block must be a macro block not after the transaction's epoch")]
+    InvalidTrustedBlock,
+    #[fail(display = "Macro block without justification")]
+    MissingJustification,
+}
+
+impl<'env> Blockchain<'env> {
+    /// Builds an attestation for the transaction `tx_hash` in block `block_number`, that can be
+    /// verified by anyone trusting the macro block `trusted_macro_block`.
+    pub fn transaction_attestation(&self, tx_hash: &Blake2bHash, block_number: u32, trusted_macro_block: u32) -> Result<Attestation, AttestationError> {
+        // The genesis block doesn't contain transactions.
+        if block_number == 0 {
+            return Err(AttestationError::TransactionNotFound);
+        }
+        let epoch = policy::epoch_at(block_number);
+        let macro_block_number = policy::macro_block_of(epoch);
+        if macro_block_number > self.block_number() {
+            return Err(AttestationError::EpochNotFinalized);
+        }
+        if !policy::is_macro_block_at(trusted_macro_block) || trusted_macro_block > macro_block_number {
+            return Err(AttestationError::InvalidTrustedBlock);
+        }
+
+        let txn = ReadTransaction::new(self.env);
+
+        let transaction = self.chain_store.get_block_at(block_number, true, Some(&txn))
+            .ok_or(AttestationError::BlockNotFound)?
+            .transactions()
+            .and_then(|transactions| transactions.iter().find(|tx| &tx.hash::<Blake2bHash>() == tx_hash).cloned())
+            .ok_or(AttestationError::TransactionNotFound)?;
+
+        let hashes = self.get_epoch_transaction_hashes(epoch, Some(&txn))
+            .ok_or(AttestationError::BlockNotFound)?;
+        let transactions_proof = MerkleProof::new(hashes, vec![tx_hash.clone()]);
+
+        let mut macro_chain = Vec::new();
+        let mut next_macro_block = trusted_macro_block;
+        while next_macro_block < macro_block_number {
+            next_macro_block = policy::macro_block_after(next_macro_block);
+            match self.chain_store.get_block_at(next_macro_block, true, Some(&txn)) {
+                Some(Block::Macro(macro_block)) => macro_chain.push(JustifiedMacroHeader {
+                    justification: macro_block.justification.ok_or(AttestationError::MissingJustification)?,
+                    header: macro_block.header,
+                }),
+                _ => return Err(AttestationError::BlockNotFound),
+            }
+        }
+
+        Ok(Attestation {
+            transaction,
+            block_number,
+            macro_chain,
+            transactions_proof,
+        })
+    }
+}
@@ -7,12 +7,13 @@ use std::sync::Arc;
 
 use parking_lot::{MappedMutexGuard, MappedRwLockReadGuard, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockUpgradableReadGuard};
 
-use account::{Account, Inherent, InherentType};
+use account::{Account, Inherent, InherentType, PrunedAccount, Receipt};
 use account::inherent::AccountInherentInteraction;
 use accounts::Accounts;
 use beserial::Serialize;
-use block::{Block, BlockError, BlockHeader, BlockType, ForkProof, MacroBlock, MacroExtrinsics, MicroBlock, ViewChange, ViewChangeProof, ViewChanges};
+use block::{Block, BlockError, BlockHeader, BlockType, ForkProof, MacroBlock, MacroExtrinsics, MacroHeader, MacroHeaderDiff, MicroBlock, ViewChange, ViewChangeProof, ViewChanges};
 use blockchain_base::{AbstractBlockchain, BlockchainError, Direction};
+use blockchain_base::upgrades::Upgrades;
 #[cfg(feature = "metrics")]
 use blockchain_base::chain_metrics::BlockchainMetrics;
 use bls::bls12_381::{CompressedSignature, PublicKey};
@@ -28,16 +29,19 @@ use network_primitives::time::NetworkTime;
 use primitives::coin::Coin;
 use primitives::networks::NetworkId;
 use primitives::policy;
-use primitives::validators::{IndexedSlot, Slots, Validator, Validators};
+use primitives::validators::{IndexedSlot, Slots, Validator, Validators, ValidatorsDiff};
 use transaction::{Transaction as BlockchainTransaction, TransactionReceipt, TransactionsProof};
 use tree_primitives::accounts_proof::AccountsProof;
 use tree_primitives::accounts_tree_chunk::AccountsTreeChunk;
 use utils::merkle;
-use utils::observer::{Listener, ListenerHandle, Notifier};
+use utils::observer::{Listener, Notifier, Subscription};
 
 use crate::chain_info::ChainInfo;
+use crate::chain_stats::ChainStatsCache;
 use crate::chain_store::ChainStore;
-use crate::reward_registry::{EpochStateError, SlashedSlots, SlashRegistry};
+use crate::history_shards::{HistoryShardError, HistoryShards};
+use crate::htlc_settlements::HtlcSettlement;
+use crate::reward_registry::{EpochRewards, EpochStateError, SlashedSlots, SlashRegistry};
 use crate::transaction_cache::TransactionCache;
 
 pub type PushResult = blockchain_base::PushResult;
@@ -88,11 +92,14 @@ impl<T> From<Option<T>> for OptionalCheck<T> {
 pub struct Blockchain<'env> {
     pub(crate) env: &'env Environment,
     pub network_id: NetworkId,
+    /// The protocol upgrades activated on the network.
+    pub upgrades: Upgrades,
     // TODO network_time: Arc<NetworkTime>,
     pub notifier: RwLock<Notifier<'env, BlockchainEvent>>,
     pub(crate) chain_store: Arc<ChainStore<'env>>,
     pub(crate) state: RwLock<BlockchainState<'env>>,
     pub push_lock: Mutex<()>, // TODO: Not very nice to have this public
+    pub(crate) stats_cache: ChainStatsCache,
 
     #[cfg(feature = "metrics")]
     metrics: BlockchainMetrics,
@@ -151,14 +158,37 @@ impl<'env> BlockchainState<'env> {
 
 impl<'env> Blockchain<'env> {
     pub fn new(env: &'env Environment, network_id: NetworkId) -> Result<Self, BlockchainError> {
-        let chain_store = Arc::new(ChainStore::new(env));
+        Self::with_chain_store(env, network_id, ChainStore::new(env))
+    }
+
+    /// Like `new`, but the block bodies of finalized epochs can be moved into per-epoch
+    /// environments, see `archive_epoch`.
+    pub fn with_history(env: &'env Environment, network_id: NetworkId, history: HistoryShards) -> Result<Self, BlockchainError> {
+        Self::with_chain_store(env, network_id, ChainStore::with_history(env, history))
+    }
+
+    fn with_chain_store(env: &'env Environment, network_id: NetworkId, chain_store: ChainStore<'env>) -> Result<Self, BlockchainError> {
+        let chain_store = Arc::new(chain_store);
+        chain_store.migrations().run(env)
+            .map_err(|e| BlockchainError::MigrationFailed(e.to_string()))?;
         Ok(match chain_store.get_head(None) {
-            Some(head_hash) => Blockchain::load(env, network_id, chain_store, head_hash)?,
+            Some(_) => {
+                // A read-only environment is recovered by the node writing it.
+                if !env.is_read_only() {
+                    let state_root = Accounts::new(env).hash(None);
+                    if let Some(head_hash) = chain_store.recover_head(&state_root) {
+                        warn!("Recovered head {} after an unclean shutdown", head_hash);
+                    }
+                }
+                Blockchain::load(env, network_id, chain_store)?
+            },
+            // A read-only environment must have been initialized by the node writing it.
+            None if env.is_read_only() => return Err(BlockchainError::FailedLoadingMainChain),
             None => Blockchain::init(env, network_id, chain_store)?
         })
     }
 
-    fn load(env: &'env Environment, network_id: NetworkId, chain_store: Arc<ChainStore<'env>>, head_hash: Blake2bHash) -> Result<Self, BlockchainError> {
+    fn load(env: &'env Environment, network_id: NetworkId, chain_store: Arc<ChainStore<'env>>) -> Result<Self, BlockchainError> {
         // Check that the correct genesis block is stored.
         let network_info = NetworkInfo::from_network_id(network_id);
         let genesis_info = chain_store.get_chain_info(network_info.genesis_hash(), false, None);
@@ -166,20 +196,51 @@ impl<'env> Blockchain<'env> {
             return Err(BlockchainError::InvalidGenesisBlock)
         }
 
+        let state = Self::load_state(env, &chain_store)?;
+        state.reward_registry.migrations().run(env)
+            .map_err(|e| BlockchainError::MigrationFailed(e.to_string()))?;
+
+        Ok(Blockchain {
+            env,
+            network_id,
+            upgrades: Upgrades::new(network_id),
+            //network_time,
+            notifier: RwLock::new(Notifier::new()),
+            chain_store,
+            state: RwLock::new(state),
+            push_lock: Mutex::new(()),
+            stats_cache: ChainStatsCache::new(),
+
+            #[cfg(feature = "metrics")]
+            metrics: BlockchainMetrics::default()
+        })
+    }
+
+    /// Loads the state of the main chain up to the stored head.
+    fn load_state(env: &'env Environment, chain_store: &Arc<ChainStore<'env>>) -> Result<BlockchainState<'env>, BlockchainError> {
+        // Databases can't be opened while the read transaction below holds the creation lock.
+        let accounts = Accounts::new(env);
+        let slash_registry = SlashRegistry::new(env, Arc::clone(chain_store));
+
+        // Read everything in one transaction, so the state is consistent even if another process
+        // writes to the environment.
+        let txn = ReadTransaction::new(env);
+        let head_hash = chain_store.get_head(Some(&txn))
+            .ok_or(BlockchainError::FailedLoadingMainChain)?;
+
         // Load main chain from store.
         let main_chain = chain_store
-            .get_chain_info(&head_hash, true, None)
+            .get_chain_info(&head_hash, true, Some(&txn))
             .ok_or(BlockchainError::FailedLoadingMainChain)?;
 
         // Check that chain/accounts state is consistent.
-        let accounts = Accounts::new(env);
-        if main_chain.head.state_root() != &accounts.hash(None) {
+        if main_chain.head.state_root() != &accounts.hash(Some(&txn)) {
             return Err(BlockchainError::InconsistentState);
         }
 
         // Load macro chain from store.
         let macro_chain_info = chain_store
-            .get_chain_info_at(policy::last_macro_block(main_chain.head.block_number()), true, None)
+            .get_chain_info_at(policy::last_macro_block(main_chain.head.block_number()), true, Some(&txn))
             .ok_or(BlockchainError::FailedLoadingMainChain)?;
         let macro_head = match macro_chain_info.head {
             Block::Macro(macro_head) => macro_head,
@@ -189,53 +250,95 @@ impl<'env> Blockchain<'env> {
 
         // Initialize TransactionCache.
         let mut transaction_cache = TransactionCache::new();
-        let blocks = chain_store.get_blocks_backward(&head_hash, transaction_cache.missing_blocks() - 1, true, None);
+        let blocks = chain_store.get_blocks_backward(&head_hash, transaction_cache.missing_blocks() - 1, true, Some(&txn));
         for block in blocks.iter().rev() {
             transaction_cache.push_block(block);
         }
         transaction_cache.push_block(&main_chain.head);
         assert_eq!(transaction_cache.missing_blocks(), policy::TRANSACTION_VALIDITY_WINDOW_ALBATROSS.saturating_sub(main_chain.head.block_number() + 1));
 
-        // Initialize SlashRegistry.
-        let slash_registry = SlashRegistry::new(env, Arc::clone(&chain_store));
-
         // Current slots and validators
         let (current_slots, current_validators) = Self::slots_and_validators_from_block(&macro_head);
 
         // Get last slots and validators
-        let prev_block = chain_store.get_block(&macro_head.header.parent_macro_hash, true, None);
+        let prev_block = chain_store.get_block(&macro_head.header.parent_macro_hash, true, Some(&txn));
         let (last_slots, last_validators) = match prev_block {
             Some(Block::Macro(prev_macro_block)) => Self::slots_and_validators_from_block(&prev_macro_block),
             None => (Slots::new(vec![], Coin::ZERO), GroupedList::empty()),
             _ => return Err(BlockchainError::InconsistentState),
         };
 
-        Ok(Blockchain {
-            env,
-            network_id,
-            //network_time,
-            notifier: RwLock::new(Notifier::new()),
-            chain_store,
-            state: RwLock::new(BlockchainState {
-                accounts,
-                transaction_cache,
-                reward_registry: slash_registry,
-                main_chain,
-                head_hash,
-                macro_head,
-                macro_head_hash,
-                current_slots: Some(current_slots),
-                current_validators: Some(current_validators),
-                last_slots: Some(last_slots),
-                last_validators: Some(last_validators),
-            }),
-            push_lock: Mutex::new(()),
-
-            #[cfg(feature = "metrics")]
-            metrics: BlockchainMetrics::default()
+        Ok(BlockchainState {
+            accounts,
+            transaction_cache,
+            reward_registry: slash_registry,
+            main_chain,
+            head_hash,
+            macro_head,
+            macro_head_hash,
+            current_slots: Some(current_slots),
+            current_validators: Some(current_validators),
+            last_slots: Some(last_slots),
+            last_validators: Some(last_validators),
         })
     }
 
+    /// Reloads the main chain if another process moved the stored head, which is how a blockchain
+    /// on a read-only environment follows the node writing it. Returns whether the head changed.
+    ///
+    /// If the main chain was only extended, the new blocks are applied to the current state.
+    /// Otherwise the whole state is loaded again. Listeners are not notified.
+    pub fn reload(&self) -> Result<bool, BlockchainError> {
+        let _push_lock = self.push_lock.lock();
+        let txn = ReadTransaction::new(self.env);
+        let head_hash = self.chain_store.get_head(Some(&txn))
+            .ok_or(BlockchainError::FailedLoadingMainChain)?;
+        let current_hash = self.head_hash();
+        if head_hash == current_hash {
+            return Ok(false);
+        }
+
+        match self.main_chain_since(&current_hash, &head_hash, &txn) {
+            Some(chain_infos) => {
+                let mut state = self.state.write();
+                for (block_hash, chain_info) in chain_infos {
+                    Self::advance_head(&mut state, block_hash, chain_info);
+                }
+            },
+            None => {
+                txn.close();
+                let state = Self::load_state(self.env, &self.chain_store)?;
+                *self.state.write() = state;
+            },
+        }
+        Ok(true)
+    }
+
+    /// Returns the main chain blocks after `from` up to `to`. Returns `None` if `from` is not on
+    /// the main chain anymore, or if `to` is further ahead than loading the whole state takes.
+    fn main_chain_since(&self, from: &Blake2bHash, to: &Blake2bHash, txn: &Transaction) -> Option<Vec<(Blake2bHash, ChainInfo)>> {
+        let from_info = self.chain_store.get_chain_info(from, false, Some(txn))?;
+        if !from_info.on_main_chain {
+            return None;
+        }
+
+        let mut chain_infos = Vec::new();
+        let mut successor = from_info.main_chain_successor;
+        while let Some(block_hash) = successor {
+            if chain_infos.len() >= policy::TRANSACTION_VALIDITY_WINDOW_ALBATROSS as usize {
+                return None;
+            }
+            let chain_info = self.chain_store.get_chain_info(&block_hash, true, Some(txn))?;
+            successor = chain_info.main_chain_successor.clone();
+            let is_head = &block_hash == to;
+            chain_infos.push((block_hash, chain_info));
+            if is_head {
+                return Some(chain_infos);
+            }
+        }
+        None
+    }
+
     fn init(env: &'env Environment, network_id: NetworkId, chain_store: Arc<ChainStore<'env>>) -> Result<Self, BlockchainError> {
         // Initialize chain & accounts with genesis block.
         let network_info = NetworkInfo::from_network_id(network_id);
@@ -262,6 +365,8 @@ impl<'env> Blockchain<'env> {
 
         // Initialize SlashRegistry.
         let slash_registry = SlashRegistry::new(env, Arc::clone(&chain_store));
+        slash_registry.migrations().run(env)
+            .map_err(|e| BlockchainError::MigrationFailed(e.to_string()))?;
 
         // current slots and validators
         let (current_slots, current_validators) = Self::slots_and_validators_from_block(&genesis_macro_block);
@@ -270,6 +375,7 @@ impl<'env> Blockchain<'env> {
         Ok(Blockchain {
             env,
             network_id,
+            upgrades: Upgrades::new(network_id),
             //network_time,
             notifier: RwLock::new(Notifier::new()),
             chain_store,
@@ -287,6 +393,7 @@ impl<'env> Blockchain<'env> {
                 last_validators: Some(last_validators),
             }),
             push_lock: Mutex::new(()),
+            stats_cache: ChainStatsCache::new(),
 
             #[cfg(feature = "metrics")]
             metrics: BlockchainMetrics::default()
@@ -403,14 +510,57 @@ impl<'env> Blockchain<'env> {
 
         // Check if we already know this block.
         let hash: Blake2bHash = block.hash();
-        if self.chain_store.get_chain_info(&hash, false, Some(&read_txn)).is_some() {
+        if self.chain_store.contains_chain_info(&hash, Some(&read_txn)) {
             return Ok(PushResult::Known);
         }
 
+        let slot = self.verify_block(&block, create_macro_extrinsics, true, &read_txn)?;
+
+        let prev_info = self.chain_store.get_chain_info(&block.parent_hash(), false, Some(&read_txn)).unwrap();
+        let chain_info = ChainInfo::new(block, Some(slot));
+
+        // Drop read transaction before calling other functions.
+        drop(read_txn);
+
+        if *chain_info.head.parent_hash() == self.head_hash() {
+            return self.extend(chain_info.head.hash(), chain_info, prev_info, create_macro_extrinsics);
+        }
+
+        let is_better_chain = Ordering::Equal
+            .then_with(|| chain_info.head.view_number().cmp(&self.next_view_number()))
+            .then_with(|| chain_info.head.block_number().cmp(&self.block_number()))
+            .eq(&Ordering::Greater);
+        if is_better_chain {
+            return self.rebranch(chain_info.head.hash(), chain_info);
+        }
+
+        // Otherwise, we are creating/extending a fork. Store ChainInfo.
+        debug!("Creating/extending fork with block {}, block number #{}, view number {}", chain_info.head.hash(), chain_info.head.block_number(), chain_info.head.view_number());
+        let mut txn = WriteTransaction::new(self.env);
+        self.chain_store.put_chain_info(&mut txn, &chain_info.head.hash(), &chain_info, true);
+        txn.commit();
+
+        Ok(PushResult::Forked)
+    }
+
+    /// Checks everything about `block` that doesn't require applying it: intrinsic invariants,
+    /// version, header, justification and fork proofs. Returns the slot of the block producer.
+    fn verify_block(&self, block: &Block, create_macro_extrinsics: bool, check_justification: bool, read_txn: &ReadTransaction) -> Result<IndexedSlot, PushError> {
         // Check (sort of) intrinsic block invariants.
-        if let Err(e) = block.verify(self.network_id) {
-            warn!("Rejecting block - verification failed ({:?})", e);
-            return Err(PushError::InvalidBlock(e));
+        match block.verify(self.network_id) {
+            Ok(()) => {},
+            // Proposals are only justified once the validators signed them.
+            Err(BlockError::NoJustification) if !check_justification => {},
+            Err(e) => {
+                warn!("Rejecting block - verification failed ({:?})", e);
+                return Err(PushError::InvalidBlock(e));
+            },
+        }
+
+        // Check that the block has the version activated at its height.
+        if Some(block.version()) != self.upgrades.block_version(block.block_number()) {
+            warn!("Rejecting block - version {} not active at block {}", block.version(), block.block_number());
+            return Err(PushError::InvalidBlock(BlockError::InvalidVersion));
         }
 
         let view_change_proof = match block {
@@ -419,13 +569,13 @@ impl<'env> Blockchain<'env> {
         };
 
         // Public keys are checked when staking, so this should never fail.
-        let slot: IndexedSlot = self.get_block_producer_at(block.block_number(), block.view_number(), Some(&read_txn))
+        let slot: IndexedSlot = self.get_block_producer_at(block.block_number(), block.view_number(), Some(read_txn))
             .ok_or(PushError::InvalidSuccessor)?;
 
         {
             let intended_slot_owner = slot.slot.public_key.uncompress_unchecked();
             // This will also check that the type at this block number is correct.
-            if let Err(e) = self.verify_block_header(&block.header(), view_change_proof, &intended_slot_owner, Some(&read_txn)) {
+            if let Err(e) = self.verify_block_header(&block.header(), view_change_proof, &intended_slot_owner, Some(read_txn)) {
                 warn!("Rejecting block - Bad header / justification");
                 return Err(e);
             }
@@ -450,7 +600,7 @@ impl<'env> Blockchain<'env> {
 
             // Validate slash inherents
             for fork_proof in &micro_block.extrinsics.as_ref().unwrap().fork_proofs {
-                match self.get_block_producer_at(fork_proof.header1.block_number, fork_proof.header1.view_number, Some(&read_txn)) {
+                match self.get_block_producer_at(fork_proof.header1.block_number, fork_proof.header1.view_number, Some(read_txn)) {
                     None => {
                         warn!("Rejecting block - Bad fork proof: Unknown block owner");
                         return Err(PushError::InvalidSuccessor)
@@ -468,12 +618,13 @@ impl<'env> Blockchain<'env> {
         if let Block::Macro(ref macro_block) = block {
             // Check Macro Justification
             match macro_block.justification {
+                None if !check_justification => {},
                 None => {
                     warn!("Rejecting block - macro block without justification");
                     return Err(PushError::InvalidBlock(BlockError::NoJustification));
                 },
                 Some(ref justification) => {
-                    if justification.verify(macro_block.hash(),&self.current_validators(), policy::TWO_THIRD_SLOTS).is_err() {
+                    if macro_verifier::verify_justification(&macro_block.header, justification, &self.current_validators()).is_err() {
                         warn!("Rejecting block - macro block with bad justification");
                         return Err(PushError::InvalidBlock(BlockError::NoJustification));
                     }
@@ -495,68 +646,109 @@ impl<'env> Blockchain<'env> {
             }
         }
 
-        let prev_info = self.chain_store.get_chain_info(&block.parent_hash(), false, Some(&read_txn)).unwrap();
-        let chain_info = ChainInfo::new(block, Some(slot));
+        Ok(slot)
+    }
 
-        // Drop read transaction before calling other functions.
-        drop(read_txn);
+    fn extend(&self, block_hash: Blake2bHash, mut chain_info: ChainInfo, mut prev_info: ChainInfo, create_macro_extrinsics: bool) -> Result<PushResult, PushError> {
+        let mut txn = WriteTransaction::new(self.env);
+        let state = self.state.upgradable_read();
 
-        if *chain_info.head.parent_hash() == self.head_hash() {
-            return self.extend(chain_info.head.hash(), chain_info, prev_info, create_macro_extrinsics);
-        }
+        let computed_extrinsics = match self.apply_block(&state, &mut txn, &chain_info.head, prev_info.head.next_view_number()) {
+            Ok(computed_extrinsics) => computed_extrinsics,
+            Err(e) => {
+                txn.abort();
+                return Err(e);
+            },
+        };
 
-        let is_better_chain = Ordering::Equal
-            .then_with(|| chain_info.head.view_number().cmp(&self.next_view_number()))
-            .then_with(|| chain_info.head.block_number().cmp(&self.block_number()))
-            .eq(&Ordering::Greater);
-        if is_better_chain {
-            return self.rebranch(chain_info.head.hash(), chain_info);
+        // Set macro extrinsics if the option is given.
+        if let Block::Macro(ref mut macro_block) = &mut chain_info.head {
+            if create_macro_extrinsics && macro_block.extrinsics.is_none() {
+                macro_block.extrinsics = computed_extrinsics;
+            }
         }
 
-        // Otherwise, we are creating/extending a fork. Store ChainInfo.
-        debug!("Creating/extending fork with block {}, block number #{}, view number {}", chain_info.head.hash(), chain_info.head.block_number(), chain_info.head.view_number());
-        let mut txn = WriteTransaction::new(self.env);
-        self.chain_store.put_chain_info(&mut txn, &chain_info.head.hash(), &chain_info, true);
+        chain_info.on_main_chain = true;
+        prev_info.main_chain_successor = Some(chain_info.head.hash());
+
+        self.chain_store.put_chain_info(&mut txn, &block_hash, &chain_info, true);
+        self.chain_store.put_chain_info(&mut txn, &chain_info.head.parent_hash(), &prev_info, false);
+        self.chain_store.set_head(&mut txn, &block_hash);
+
+        // Acquire write lock & commit changes.
+        let mut state = RwLockUpgradableReadGuard::upgrade(state);
+        let block_type = chain_info.head.ty();
+        Self::advance_head(&mut state, block_hash.clone(), chain_info);
         txn.commit();
 
-        Ok(PushResult::Forked)
+        // Give up lock before notifying.
+        drop(state);
+
+        if block_type == BlockType::Macro {
+            self.notifier.read().notify(BlockchainEvent::Finalized(block_hash));
+        }
+        else {
+            self.notifier.read().notify(BlockchainEvent::Extended(block_hash));
+        }
+
+        Ok(PushResult::Extended)
     }
 
-    fn extend(&self, block_hash: Blake2bHash, mut chain_info: ChainInfo, mut prev_info: ChainInfo, create_macro_extrinsics: bool) -> Result<PushResult, PushError> {
-        let mut txn = WriteTransaction::new(self.env);
-        let state = self.state.upgradable_read();
+    /// Makes `chain_info`, a successor of the current head, the head of the main chain.
+    fn advance_head(state: &mut BlockchainState<'env>, block_hash: Blake2bHash, chain_info: ChainInfo) {
+        state.transaction_cache.push_block(&chain_info.head);
+
+        if let Block::Macro(ref macro_block) = chain_info.head {
+            state.macro_head = macro_block.clone();
+            state.macro_head_hash = block_hash.clone();
+            info!("Epoch {} state digest: {}", policy::epoch_at(macro_block.header.block_number), macro_block.header.epoch_state_digest());
 
+            let slots = state.current_slots.take().unwrap();
+            let validators = state.current_validators.take().unwrap();
+            state.last_slots.replace(slots);
+            state.last_validators.replace(validators);
+
+            let (slot, validators) = Self::slots_and_validators_from_block(&macro_block);
+            state.current_slots.replace(slot);
+            state.current_validators.replace(validators);
+        }
+
+        state.main_chain = chain_info;
+        state.head_hash = block_hash;
+    }
+
+    /// Applies `block` on top of the head in `txn` and checks the result against the block.
+    /// Returns the computed extrinsics for macro blocks. The caller must abort `txn` on errors.
+    fn apply_block(&self, state: &BlockchainState, txn: &mut WriteTransaction, block: &Block, prev_view_number: u32) -> Result<Option<MacroExtrinsics>, PushError> {
         // Check transactions against TransactionCache to prevent replay.
         // XXX This is technically unnecessary for macro blocks, but it doesn't hurt either.
-        if state.transaction_cache.contains_any(&chain_info.head) {
+        if state.transaction_cache.contains_any(block) {
             warn!("Rejecting block - transaction already included");
-            txn.abort();
             return Err(PushError::DuplicateTransaction);
         }
 
         // Get the slashed set used to finalize the previous epoch before garbage collecting it below.
         let mut slashed_set: Option<BitSet> = None;
-        if chain_info.head.ty() == BlockType::Macro {
-            slashed_set = Some(state.reward_registry.slashed_set(policy::epoch_at(chain_info.head.block_number()) - 1, Some(&txn)));
+        if block.ty() == BlockType::Macro {
+            slashed_set = Some(state.reward_registry.slashed_set(policy::epoch_at(block.block_number()) - 1, Some(txn)));
         }
 
-        if let Err(e) = state.reward_registry.commit_block(&mut txn, &chain_info.head, state.current_slots.as_ref().expect("Current slots missing while rebranching"), prev_info.head.next_view_number()) {
+        if let Err(e) = state.reward_registry.commit_block(txn, block, state.current_slots.as_ref().expect("Current slots missing while rebranching"), prev_view_number) {
             warn!("Rejecting block - slash commit failed: {:?}", e);
             return Err(PushError::InvalidSuccessor);
         }
 
         // Commit block to AccountsTree.
-        if let Err(e) = self.commit_accounts(&state, &mut txn, &chain_info.head) {
+        if let Err(e) = self.commit_accounts(state, txn, block) {
             warn!("Rejecting block - commit failed: {:?}", e);
-            txn.abort();
             #[cfg(feature = "metrics")]
                 self.metrics.note_invalid_block();
             return Err(e);
         }
 
         // Only now can we check macro extrinsics.
-        if let Block::Macro(ref mut macro_block) = &mut chain_info.head {
-            let slots = self.next_slots(&macro_block.header.seed, Some(&txn));
+        if let Block::Macro(ref macro_block) = block {
+            let slots = self.next_slots(&macro_block.header.seed, Some(txn));
             let computed_validators: Validators = slots.clone().into();
             let computed_validators: CompressedList<LazyPublicKey> = computed_validators.into();
             if computed_validators != macro_block.header.validators {
@@ -565,61 +757,49 @@ impl<'env> Blockchain<'env> {
             }
 
             let slashed_set = slashed_set.unwrap();
-            let computed_extrinsics: MacroExtrinsics = MacroExtrinsics::from(slots, slashed_set);
+            let computed_extrinsics: MacroExtrinsics = MacroExtrinsics::from(macro_block.header.version, slots, slashed_set);
             let computed_extrinsics_hash: Blake2bHash = computed_extrinsics.hash();
             if computed_extrinsics_hash != macro_block.header.extrinsics_root {
                 warn!("Rejecting block - Extrinsics hash doesn't match real extrinsics hash");
                 return Err(PushError::InvalidBlock(BlockError::ExtrinsicsHashMismatch));
             }
 
-            // Set macro extrinsics if the option is given.
-            if create_macro_extrinsics && macro_block.extrinsics.is_none() {
-                macro_block.extrinsics.replace(computed_extrinsics);
-            }
+            return Ok(Some(computed_extrinsics));
         }
 
-        chain_info.on_main_chain = true;
-        prev_info.main_chain_successor = Some(chain_info.head.hash());
-
-        self.chain_store.put_chain_info(&mut txn, &block_hash, &chain_info, true);
-        self.chain_store.put_chain_info(&mut txn, &chain_info.head.parent_hash(), &prev_info, false);
-        self.chain_store.set_head(&mut txn, &block_hash);
-
-        // Acquire write lock & commit changes.
-        let mut state = RwLockUpgradableReadGuard::upgrade(state);
-        state.transaction_cache.push_block(&chain_info.head);
+        Ok(None)
+    }
 
-        if let Block::Macro(ref macro_block) = chain_info.head {
-            state.macro_head = macro_block.clone();
-            state.macro_head_hash = block_hash.clone();
+    /// Runs `block` through the same checks as `push` without changing the chain, e.g. so that
+    /// validators can check the blocks they produce before relaying them. The block must extend
+    /// the current head.
+    ///
+    /// Macro blocks are checked as proposals, i.e. they don't need to be justified yet, but must
+    /// contain their extrinsics.
+    pub fn verify_successor(&self, block: &Block) -> Result<(), PushError> {
+        // Only one push operation at a time.
+        let _push_lock = self.push_lock.lock();
 
-            let slots = state.current_slots.take().unwrap();
-            let validators = state.current_validators.take().unwrap();
-            state.last_slots.replace(slots);
-            state.last_validators.replace(validators);
+        let read_txn = ReadTransaction::new(self.env);
+        let prev_info = self.chain_store.get_chain_info(&block.parent_hash(), false, Some(&read_txn))
+            .ok_or_else(|| {
+                warn!("Rejecting block - unknown predecessor");
+                PushError::Orphan
+            })?;
+        self.verify_block(block, false, false, &read_txn)?;
+        drop(read_txn);
 
-            let (slot, validators) = Self::slots_and_validators_from_block(&macro_block);
-            state.current_slots.replace(slot);
-            state.current_validators.replace(validators);
+        if *block.parent_hash() != self.head_hash() {
+            warn!("Rejecting block - not a successor of the head");
+            return Err(PushError::InvalidSuccessor);
         }
 
-        let block_type = chain_info.head.ty();
-
-        state.main_chain = chain_info;
-        state.head_hash = block_hash.clone();
-        txn.commit();
-
-        // Give up lock before notifying.
-        drop(state);
-
-        if block_type == BlockType::Macro {
-            self.notifier.read().notify(BlockchainEvent::Finalized(block_hash));
-        }
-        else {
-            self.notifier.read().notify(BlockchainEvent::Extended(block_hash));
-        }
+        let mut txn = WriteTransaction::new(self.env);
+        let state = self.state.upgradable_read();
+        let result = self.apply_block(&state, &mut txn, block, prev_info.head.next_view_number());
+        txn.abort();
 
-        Ok(PushResult::Extended)
+        result.map(|_| ())
     }
 
     fn rebranch(&self, block_hash: Blake2bHash, chain_info: ChainInfo) -> Result<PushResult, PushError> {
@@ -796,7 +976,10 @@ impl<'env> Blockchain<'env> {
 
         match block {
             Block::Macro(ref macro_block) => {
-                let mut inherents = self.finalize_last_epoch(state);
+                let (mut inherents, rewards) = self.last_epoch_rewards(state);
+                if let Some(ref rewards) = rewards {
+                    state.reward_registry.commit_epoch_rewards(txn, rewards);
+                }
 
                 // Add slashes for view changes.
                 let view_changes = ViewChanges::new(macro_block.header.block_number, self.view_number(), macro_block.header.view_number);
@@ -823,6 +1006,7 @@ impl<'env> Blockchain<'env> {
                 // Store receipts.
                 let receipts = receipts.unwrap();
                 self.chain_store.put_receipts(txn, micro_block.header.block_number, &receipts);
+                self.chain_store.put_htlc_settlements(txn, &block.hash(), micro_block);
             }
         }
 
@@ -850,10 +1034,84 @@ impl<'env> Blockchain<'env> {
         if let Err(e) = accounts.revert(txn, &extrinsics.transactions, &inherents, micro_block.header.block_number, &receipts) {
             panic!("Failed to revert - {}", e);
         }
+        self.chain_store.remove_htlc_settlements(txn, micro_block);
 
         Ok(())
     }
 
+    /// Changes the AccountsTree and the reward registry in `txn` to the state after the block
+    /// `block_hash`, which must be known and part of the current epoch, but doesn't need to be on
+    /// the main chain. Returns the block, or `None` if it can't be reached from the current head
+    /// without crossing a macro block.
+    ///
+    /// This doesn't change the main chain, the caller is expected to abort `txn` afterwards.
+    pub fn revert_to(&self, txn: &mut WriteTransaction, block_hash: &Blake2bHash) -> Option<Block> {
+        let state = self.state.read();
+
+        // Walk up the fork chain until we find a block that is part of the main chain.
+        let target = self.chain_store.get_chain_info(block_hash, true, Some(txn))?;
+        let mut fork_chain: Vec<ChainInfo> = vec![];
+        let mut ancestor = (block_hash.clone(), target.clone());
+        while !ancestor.1.on_main_chain {
+            // Macro blocks are final
+            if ancestor.1.head.ty() != BlockType::Micro {
+                return None;
+            }
+
+            let prev_hash = ancestor.1.head.parent_hash().clone();
+            let prev_info = self.chain_store.get_chain_info(&prev_hash, true, Some(txn))?;
+            fork_chain.push(ancestor.1);
+            ancestor = (prev_hash, prev_info);
+        }
+
+        // Revert the main chain to the common ancestor.
+        let mut current = (state.head_hash.clone(), state.main_chain.clone());
+        while current.0 != ancestor.0 {
+            let micro_block = match current.1.head {
+                Block::Macro(_) => return None,
+                Block::Micro(ref micro_block) => micro_block,
+            };
+
+            let prev_hash = micro_block.header.parent_hash.clone();
+            let prev_info = self.chain_store.get_chain_info(&prev_hash, true, Some(txn))
+                .expect("Corrupted store: Failed to find main chain predecessor while reverting");
+
+            self.revert_accounts(&state.accounts, txn, &micro_block, prev_info.head.view_number())
+                .expect("Failed to revert main chain");
+
+            // The slash inherents of the following blocks depend on the slashes recorded so far.
+            let slots = state.current_slots.as_ref().expect("Current slots missing while reverting");
+            state.reward_registry.revert_block(txn, &current.1.head, slots, prev_info.head.view_number())
+                .expect("Failed to revert reward registry");
+
+            current = (prev_hash, prev_info);
+        }
+
+        // Apply the fork blocks on top of it.
+        let mut prev_view_number = ancestor.1.head.next_view_number();
+        for fork_info in fork_chain.iter().rev() {
+            if let Block::Micro(ref micro_block) = fork_info.head {
+                let slots = state.current_slots.as_ref().expect("Current slots missing while reverting");
+                if let Err(e) = state.reward_registry.commit_block(txn, &fork_info.head, slots, prev_view_number) {
+                    warn!("Failed to apply fork block to reward registry while reverting - {:?}", e);
+                    return None;
+                }
+
+                let extrinsics = micro_block.extrinsics.as_ref().unwrap();
+                let view_changes = ViewChanges::new(micro_block.header.block_number, prev_view_number, micro_block.header.view_number);
+                let inherents = self.create_slash_inherents(&extrinsics.fork_proofs, &view_changes, Some(txn));
+
+                if let Err(e) = state.accounts.commit(txn, &extrinsics.transactions, &inherents, micro_block.header.block_number) {
+                    warn!("Failed to apply fork block while reverting - {:?}", e);
+                    return None;
+                }
+                prev_view_number = fork_info.head.next_view_number();
+            }
+        }
+
+        Some(target.head)
+    }
+
     /// Pushes a macro block without requiring the micro blocks of the previous epoch.
     pub fn push_isolated_macro_block(&self, block: Block, transactions: &[BlockchainTransaction]) -> Result<PushResult, PushError> {
         // TODO: Deduplicate code as much as possible...
@@ -871,7 +1129,7 @@ impl<'env> Blockchain<'env> {
 
         // Check if we already know this block.
         let hash: Blake2bHash = block.hash();
-        if self.chain_store.get_chain_info(&hash, false, Some(&read_txn)).is_some() {
+        if self.chain_store.contains_chain_info(&hash, Some(&read_txn)) {
             return Ok(PushResult::Known);
         }
 
@@ -887,6 +1145,12 @@ impl<'env> Blockchain<'env> {
             return Err(PushError::InvalidBlock(e));
         }
 
+        // Check that the block has the version activated at its height.
+        if Some(block.version()) != self.upgrades.block_version(block.block_number()) {
+            warn!("Rejecting block - version {} not active at block {}", block.version(), block.block_number());
+            return Err(PushError::InvalidBlock(BlockError::InvalidVersion));
+        }
+
         // Check if the block's immediate predecessor is part of the chain.
         let prev_info = self.chain_store.get_chain_info(&macro_block.header.parent_macro_hash, false, Some(&read_txn))
             .ok_or_else(|| {
@@ -915,7 +1179,7 @@ impl<'env> Blockchain<'env> {
                 return Err(PushError::InvalidBlock(BlockError::NoJustification));
             },
             Some(ref justification) => {
-                if let Err(_) = justification.verify(macro_block.hash(),&self.current_validators(), policy::TWO_THIRD_SLOTS) {
+                if let Err(_) = macro_verifier::verify_justification(&macro_block.header, justification, &self.current_validators()) {
                     warn!("Rejecting block - macro block with bad justification");
                     return Err(PushError::InvalidBlock(BlockError::NoJustification));
                 }
@@ -963,7 +1227,11 @@ impl<'env> Blockchain<'env> {
         // Apply transactions and inherents to AccountsTree.
         let slots = state.last_slots.as_ref().expect("Slots for last epoch are missing");
         let mut inherents = self.inherents_from_slashed_set(&slashed_set, slots, Some(&txn));
-        inherents.append(&mut self.finalize_last_epoch(&state));
+        let (mut reward_inherents, rewards) = self.last_epoch_rewards(&state);
+        inherents.append(&mut reward_inherents);
+        if let Some(ref rewards) = rewards {
+            state.reward_registry.commit_epoch_rewards(&mut txn, rewards);
+        }
 
         // Commit epoch to AccountsTree.
         let receipts = state.accounts.commit(&mut txn, transactions, &inherents, chain_info.head.block_number());
@@ -987,7 +1255,7 @@ impl<'env> Blockchain<'env> {
                 return Err(PushError::InvalidBlock(BlockError::InvalidValidators));
             }
 
-            let computed_extrinsics = MacroExtrinsics::from(slots, slashed_set);
+            let computed_extrinsics = MacroExtrinsics::from(macro_block.header.version, slots, slashed_set);
             let computed_extrinsics_hash: Blake2bHash = computed_extrinsics.hash();
             if computed_extrinsics_hash != macro_block.header.extrinsics_root {
                 warn!("Rejecting block - Extrinsics hash doesn't match real extrinsics hash");
@@ -1011,6 +1279,7 @@ impl<'env> Blockchain<'env> {
         if let Block::Macro(ref macro_block) = chain_info.head {
             state.macro_head = macro_block.clone();
             state.macro_head_hash = block_hash.clone();
+            info!("Epoch {} state digest: {}", policy::epoch_at(macro_block.header.block_number), macro_block.header.epoch_state_digest());
 
             let slots = state.current_slots.take().unwrap();
             let validators = state.current_validators.take().unwrap();
@@ -1028,11 +1297,12 @@ impl<'env> Blockchain<'env> {
         state.head_hash = block_hash.clone();
         txn.commit();
 
-        // Give up lock before notifying.
+        // Give up lock before notifying. Like in `extend`, the push lock is held while notifying,
+        // such that listeners like the mempool are synchronized with pushes.
         drop(state);
-        drop(push_lock);
 
         self.notifier.read().notify(BlockchainEvent::Finalized(block_hash));
+        drop(push_lock);
 
         Ok(PushResult::Extended)
     }
@@ -1062,7 +1332,68 @@ impl<'env> Blockchain<'env> {
         self.chain_store.get_blocks(start_block_hash, count, include_body, direction, None)
     }
 
+    /// Looks up the bodies of archived epochs in `history` from now on, like a blockchain created
+    /// with `with_history`. This has to be done before any epoch is archived.
+    pub fn set_history(&self, history: HistoryShards) {
+        self.chain_store.set_history(history);
+    }
+
+    /// Moves the block bodies of the finalized `epoch` into its history shard. Blocks are still
+    /// found by all lookups afterwards. Returns the number of moved blocks.
+    pub fn archive_epoch(&self, epoch: u32) -> Result<usize, HistoryShardError> {
+        self.chain_store.archive_epoch(epoch, self.block_number())
+    }
+
+    /// Deletes the history shard of `epoch`. The bodies of its blocks are gone afterwards.
+    pub fn delete_epoch(&self, epoch: u32) -> Result<bool, HistoryShardError> {
+        self.chain_store.delete_epoch(epoch)
+    }
+
+    /// Returns the state digest of a finalized epoch, see `MacroHeader::epoch_state_digest`.
+    pub fn epoch_state_digest(&self, epoch: u32) -> Option<Blake2bHash> {
+        match self.get_block_at(policy::macro_block_of(epoch), false)? {
+            Block::Macro(macro_block) => Some(macro_block.header.epoch_state_digest()),
+            Block::Micro(_) => None,
+        }
+    }
+
+    /// Returns the changes from the validators elected at the end of `epoch - 1` to those
+    /// elected at the end of `epoch`.
+    pub fn get_validators_diff(&self, epoch: u32) -> Option<ValidatorsDiff> {
+        let prev_validators = self.get_elected_validators(epoch.checked_sub(1)?)?;
+        let validators = self.get_elected_validators(epoch)?;
+        Some(ValidatorsDiff::between(&prev_validators, &validators))
+    }
+
+    /// Returns the header of the macro block of `epoch` with its validators encoded as the diff
+    /// to those of the previous macro block, see `MacroHeaderDiff`.
+    pub fn get_macro_header_diff(&self, epoch: u32) -> Option<MacroHeaderDiff> {
+        let prev_validators = self.get_elected_validators(epoch.checked_sub(1)?)?;
+        let header = self.get_macro_header(epoch)?;
+        Some(MacroHeaderDiff::new(&header, &prev_validators))
+    }
+
+    /// Returns the validators elected by the macro block of `epoch`, i.e. those of the next epoch.
+    pub fn get_elected_validators(&self, epoch: u32) -> Option<Validators> {
+        let header = self.get_macro_header(epoch)?;
+        Some(header.validators.into_iter().cloned().collect())
+    }
+
+    fn get_macro_header(&self, epoch: u32) -> Option<MacroHeader> {
+        match self.get_block_at(policy::macro_block_of(epoch), false)? {
+            Block::Macro(macro_block) => Some(macro_block.header),
+            Block::Micro(_) => None,
+        }
+    }
+
     pub fn get_transactions_root(&self, epoch: u32, txn_option: Option<&Transaction>) -> Option<Blake2bHash> {
+        let hashes = self.get_epoch_transaction_hashes(epoch, txn_option)?;
+        Some(merkle::compute_root_from_hashes::<Blake2bHash>(&hashes))
+    }
+
+    /// Returns the hashes of all transactions in the micro blocks of `epoch`, in the order they
+    /// are committed to by the epoch's transactions root.
+    pub fn get_epoch_transaction_hashes(&self, epoch: u32, txn_option: Option<&Transaction>) -> Option<Vec<Blake2bHash>> {
         let mut hashes = Vec::new();
 
         let first_block = policy::first_block_of(epoch);
@@ -1080,7 +1411,7 @@ impl<'env> Blockchain<'env> {
             hashes.extend(block.transactions().unwrap().iter().map(|tx| tx.hash()));
         }
 
-        Some(merkle::compute_root_from_hashes::<Blake2bHash>(&hashes))
+        Some(hashes)
     }
 
     pub fn height(&self) -> u32 {
@@ -1304,6 +1635,12 @@ impl<'env> Blockchain<'env> {
     }
 
     pub fn finalize_last_epoch(&self, state: &BlockchainState) -> Vec<Inherent> {
+        self.last_epoch_rewards(state).0
+    }
+
+    /// Computes the reward inherents for the last epoch together with a record of how its reward
+    /// pot is distributed. There is no record for epoch 0.
+    pub fn last_epoch_rewards(&self, state: &BlockchainState) -> (Vec<Inherent>, Option<EpochRewards>) {
         let mut inherents = Vec::new();
 
         // It might be that we don't have any micro blocks, thus we need to look at the next macro block.
@@ -1311,7 +1648,7 @@ impl<'env> Blockchain<'env> {
 
         // Special case for first epoch: Epoch 0 is finalized by definition.
         if epoch == 0 {
-            return vec![];
+            return (vec![], None);
         }
 
         /*
@@ -1335,6 +1672,8 @@ impl<'env> Blockchain<'env> {
         let initial_reward = reward_pot / num_eligible;
         let mut remainder = reward_pot % num_eligible;
 
+        let mut rewards = EpochRewards::new(epoch, reward_pot, num_eligible as u16, slashed_set.len() as u16, initial_reward);
+
         for slot in reward_eligible.iter() {
             let reward = initial_reward;
 
@@ -1349,6 +1688,7 @@ impl<'env> Blockchain<'env> {
             let account = state.accounts.get(&inherent.target, None);
             if account.check_inherent(&inherent).is_err() {
                 remainder += reward;
+                rewards.add_rejected(&inherent.target, reward);
             } else {
                 inherents.push(inherent);
             }
@@ -1358,6 +1698,7 @@ impl<'env> Blockchain<'env> {
         let accepting_slots = inherents.len() as u64;
         let reward = remainder / accepting_slots;
         let remainder = u64::from(remainder % accepting_slots);
+        rewards.redistributed_reward = reward;
 
         for (i, inherent) in inherents.iter_mut().enumerate() {
             let mut additional_reward = reward;
@@ -1367,9 +1708,36 @@ impl<'env> Blockchain<'env> {
             }
 
             inherent.value += additional_reward;
+            rewards.add_reward(&inherent.target, inherent.value);
         }
 
-        inherents
+        (inherents, Some(rewards))
+    }
+
+    /// Returns the accounts pruned by the main chain block at `block_number`. They are only known
+    /// for the micro blocks of the current epoch.
+    pub fn get_pruned_accounts(&self, block_number: u32) -> Option<Vec<PrunedAccount>> {
+        if block_number > self.block_number() {
+            return None;
+        }
+        let receipts = self.chain_store.get_receipts(block_number, None)?;
+        Some(receipts.receipts.into_iter()
+            .filter_map(|receipt| match receipt {
+                Receipt::PrunedAccount(pruned) => Some(pruned),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Returns the settlements of the HTLC at `address` in main chain blocks, in chain order.
+    /// Settlements of blocks that are reverted are removed again.
+    pub fn get_htlc_settlements(&self, address: &Address) -> Vec<HtlcSettlement> {
+        self.chain_store.get_htlc_settlements(address, None)
+    }
+
+    /// Returns how the reward pot of `epoch` was distributed, if this node processed the payout.
+    pub fn epoch_rewards(&self, epoch: u32) -> Option<EpochRewards> {
+        self.state.read().reward_registry.epoch_rewards(epoch, None)
     }
 
     pub fn get_macro_block_locators(&self, max_count: usize) -> Vec<Blake2bHash> {
@@ -1549,8 +1917,8 @@ impl<'env> AbstractBlockchain<'env> for Blockchain<'env> {
         unimplemented!()
     }
 
-    fn register_listener<T: Listener<BlockchainEvent> + 'env>(&self, listener: T) -> ListenerHandle {
-        self.notifier.write().register(listener)
+    fn subscribe_listener<T: Listener<BlockchainEvent> + 'env>(&self, listener: T) -> Subscription<'env, BlockchainEvent> {
+        self.notifier.write().subscribe(listener)
     }
 
     fn lock(&self) -> MutexGuard<()> {
@@ -0,0 +1,195 @@
+use std::cmp;
+use std::collections::HashMap;
+
+use parking_lot::RwLock;
+
+use block::Block;
+use blockchain_base::Direction;
+use database::ReadTransaction;
+use primitives::coin::Coin;
+use primitives::policy;
+
+use crate::blockchain::Blockchain;
+
+/// Statistics about the blocks of a single epoch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpochStats {
+    pub epoch: u32,
+    /// Number of blocks of this epoch on the main chain.
+    pub block_count: u32,
+    /// Timestamp of the macro block preceding this epoch.
+    pub start_timestamp: u64,
+    /// Timestamp of the latest block of this epoch.
+    pub end_timestamp: u64,
+    pub view_changes: u32,
+    pub transaction_count: u64,
+    pub fees: Coin,
+    /// Whether the epoch has been finalized by its macro block.
+    pub complete: bool,
+}
+
+impl EpochStats {
+    /// Duration covered by this epoch in milliseconds.
+    pub fn duration(&self) -> u64 {
+        self.end_timestamp.saturating_sub(self.start_timestamp)
+    }
+
+    /// Average block time in milliseconds.
+    pub fn average_block_time(&self) -> Option<f64> {
+        if self.block_count == 0 {
+            return None;
+        }
+        Some(self.duration() as f64 / f64::from(self.block_count))
+    }
+
+    pub fn transactions_per_second(&self) -> Option<f64> {
+        if self.duration() == 0 {
+            return None;
+        }
+        Some(self.transaction_count as f64 * 1000f64 / self.duration() as f64)
+    }
+}
+
+/// Statistics accumulated over a range of epochs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChainStats {
+    pub epochs: Vec<EpochStats>,
+}
+
+impl ChainStats {
+    pub fn block_count(&self) -> u32 {
+        self.epochs.iter().map(|stats| stats.block_count).sum()
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.epochs.iter().map(EpochStats::duration).sum()
+    }
+
+    pub fn view_changes(&self) -> u32 {
+        self.epochs.iter().map(|stats| stats.view_changes).sum()
+    }
+
+    pub fn transaction_count(&self) -> u64 {
+        self.epochs.iter().map(|stats| stats.transaction_count).sum()
+    }
+
+    pub fn fees(&self) -> Coin {
+        self.epochs.iter().fold(Coin::ZERO, |sum, stats| sum + stats.fees)
+    }
+
+    pub fn average_block_time(&self) -> Option<f64> {
+        let block_count = self.block_count();
+        if block_count == 0 {
+            return None;
+        }
+        Some(self.duration() as f64 / f64::from(block_count))
+    }
+
+    pub fn transactions_per_second(&self) -> Option<f64> {
+        let duration = self.duration();
+        if duration == 0 {
+            return None;
+        }
+        Some(self.transaction_count() as f64 * 1000f64 / duration as f64)
+    }
+}
+
+/// Caches statistics of finalized epochs. Finalized epochs can't be reverted, so they never
+/// need to be invalidated.
+#[derive(Default)]
+pub struct ChainStatsCache {
+    epochs: RwLock<HashMap<u32, EpochStats>>,
+}
+
+impl ChainStatsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get(&self, epoch: u32) -> Option<EpochStats> {
+        self.epochs.read().get(&epoch).cloned()
+    }
+
+    fn put(&self, stats: EpochStats) {
+        debug_assert!(stats.complete);
+        self.epochs.write().insert(stats.epoch, stats);
+    }
+
+    /// Whether the statistics of `epoch` are cached.
+    pub fn contains(&self, epoch: u32) -> bool {
+        self.epochs.read().contains_key(&epoch)
+    }
+
+    /// Drops the statistics of the epochs before `epoch`. They are computed again if requested.
+    pub fn prune(&self, epoch: u32) {
+        self.epochs.write().retain(|&cached_epoch, _| cached_epoch >= epoch);
+    }
+}
+
+impl<'env> Blockchain<'env> {
+    /// The cache of the statistics of finalized epochs.
+    pub fn stats_cache(&self) -> &ChainStatsCache {
+        &self.stats_cache
+    }
+
+    /// Returns the statistics for the given epoch, or `None` if the epoch hasn't started yet.
+    pub fn epoch_stats(&self, epoch: u32) -> Option<EpochStats> {
+        // The genesis block is the only block of epoch 0.
+        if epoch == 0 || epoch > policy::epoch_at(self.block_number()) {
+            return None;
+        }
+
+        if let Some(stats) = self.stats_cache.get(epoch) {
+            return Some(stats);
+        }
+
+        let txn = ReadTransaction::new(self.env);
+        let start_block = self.chain_store.get_block_at(policy::macro_block_of(epoch - 1), false, Some(&txn))?;
+        let blocks = self.chain_store.get_blocks(&start_block.hash(), policy::EPOCH_LENGTH, true, Direction::Forward, Some(&txn));
+        let stats = Self::compute_epoch_stats(epoch, &start_block, &blocks);
+
+        if stats.complete {
+            self.stats_cache.put(stats.clone());
+        }
+
+        Some(stats)
+    }
+
+    /// Returns the statistics for all epochs in `from_epoch..=to_epoch` that have already started.
+    pub fn chain_stats(&self, from_epoch: u32, to_epoch: u32) -> ChainStats {
+        let to_epoch = cmp::min(to_epoch, policy::epoch_at(self.block_number()));
+        ChainStats {
+            epochs: (cmp::max(from_epoch, 1)..=to_epoch)
+                .filter_map(|epoch| self.epoch_stats(epoch))
+                .collect(),
+        }
+    }
+
+    fn compute_epoch_stats(epoch: u32, start_block: &Block, blocks: &[Block]) -> EpochStats {
+        let mut stats = EpochStats {
+            epoch,
+            block_count: blocks.len() as u32,
+            start_timestamp: start_block.timestamp(),
+            end_timestamp: start_block.timestamp(),
+            view_changes: 0,
+            transaction_count: 0,
+            fees: Coin::ZERO,
+            complete: false,
+        };
+
+        for block in blocks {
+            stats.end_timestamp = block.timestamp();
+            // The view number is reset at the start of each epoch and only increases afterwards.
+            stats.view_changes = block.view_number();
+            if let Some(transactions) = block.transactions() {
+                stats.transaction_count += transactions.len() as u64;
+                stats.fees = transactions.iter().fold(stats.fees, |sum, tx| sum + tx.fee);
+            }
+            if let Block::Macro(_) = block {
+                stats.complete = true;
+            }
+        }
+
+        stats
+    }
+}
@@ -1,39 +1,118 @@
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
 use account::Receipts;
-use block::Block;
+use block::{Block, MicroBlock};
 use blockchain_base::Direction;
-use database::{Database, DatabaseFlags, Environment, ReadTransaction, Transaction, WriteTransaction};
+use database::{AtomicWrite, Database, DatabaseFlags, Environment, ReadTransaction, Transaction, TypedDatabase, WriteAheadLog, WriteTransaction};
 use database::cursor::ReadCursor;
-use database::cursor::WriteCursor;
-use hash::Blake2bHash;
+use database::migrations::Migrations;
+use database::verify::{decode, decode_fixed, IssueKind, Verifier};
+use hash::{Blake2bHash, Hash};
+use keys::Address;
 use primitives::policy;
 
 use crate::chain_info::ChainInfo;
+use crate::history_shards::{HistoryShardError, HistoryShards};
+use crate::htlc_settlements::{HtlcSettlement, HtlcSettlementKey};
 
 #[derive(Debug)]
 pub struct ChainStore<'env> {
     env: &'env Environment,
     chain_db: Database<'env>,
-    block_db: Database<'env>,
-    height_idx: Database<'env>,
-    receipt_db: Database<'env>,
+    block_db: TypedDatabase<'env, Blake2bHash, Block>,
+    height_idx: TypedDatabase<'env, u32, Blake2bHash>,
+    receipt_db: TypedDatabase<'env, u32, Receipts>,
+    /// Settlements of HTLCs in main chain blocks, by HTLC address.
+    htlc_settlement_idx: TypedDatabase<'env, HtlcSettlementKey, HtlcSettlement>,
+    history: RwLock<Option<Arc<HistoryShards>>>,
 }
 
 impl<'env> ChainStore<'env> {
-    const CHAIN_DB_NAME: &'static str = "ChainData";
+    pub(crate) const CHAIN_DB_NAME: &'static str = "ChainData";
     const BLOCK_DB_NAME: &'static str = "Block";
     const HEIGHT_IDX_NAME: &'static str = "HeightIdx";
     const RECEIPT_DB_NAME: &'static str = "Receipts";
+    const HTLC_SETTLEMENT_IDX_NAME: &'static str = "HtlcSettlementIdx";
 
     const HEAD_KEY: &'static str = "head";
+    /// Maximum number of heights below the highest one that `recover_head` searches
+    const MAX_HEAD_RECOVERY_DEPTH: u32 = 1024;
 
     pub fn new(env: &'env Environment) -> Self {
         let chain_db = env.open_database(Self::CHAIN_DB_NAME.to_string());
-        let block_db = env.open_database(Self::BLOCK_DB_NAME.to_string());
-        let height_idx = env.open_database_with_flags(Self::HEIGHT_IDX_NAME.to_string(),
-                                                      DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_FIXED_SIZE_VALUES);
-        let receipt_db = env.open_database_with_flags(Self::RECEIPT_DB_NAME.to_string(),
-                                                      DatabaseFlags::UINT_KEYS);
-        ChainStore { env, chain_db, block_db, height_idx, receipt_db }
+        let block_db = TypedDatabase::open(env, Self::BLOCK_DB_NAME.to_string());
+        let height_idx = TypedDatabase::open_with_flags(env, Self::HEIGHT_IDX_NAME.to_string(),
+                                                        DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_FIXED_SIZE_VALUES);
+        let receipt_db = TypedDatabase::open_with_flags(env, Self::RECEIPT_DB_NAME.to_string(),
+                                                        DatabaseFlags::UINT_KEYS);
+        let htlc_settlement_idx = TypedDatabase::open(env, Self::HTLC_SETTLEMENT_IDX_NAME.to_string());
+        ChainStore { env, chain_db, block_db, height_idx, receipt_db, htlc_settlement_idx, history: RwLock::new(None) }
+    }
+
+    /// Like `new`, but the bodies of archived epochs are looked up in `history`.
+    pub fn with_history(env: &'env Environment, history: HistoryShards) -> Self {
+        let chain_store = Self::new(env);
+        chain_store.set_history(history);
+        chain_store
+    }
+
+    /// Looks up the bodies of archived epochs in `history` from now on. This has to be done
+    /// before any epoch is archived, otherwise its blocks have no bodies.
+    ///
+    /// Archiving that was interrupted by a crash is completed first.
+    pub fn set_history(&self, history: HistoryShards) {
+        self.replay_archive_logs(&history);
+        *self.history.write() = Some(Arc::new(history));
+    }
+
+    fn replay_archive_logs(&self, history: &HistoryShards) {
+        for epoch in history.epochs() {
+            if !history.log_path(epoch).exists() {
+                continue;
+            }
+            let shard = match history.get(epoch) {
+                Some(shard) => shard,
+                None => continue,
+            };
+            if let Err(e) = WriteAheadLog::open(history.log_path(epoch), vec![shard.env(), self.env]) {
+                warn!("{}", HistoryShardError::Log(epoch, e));
+            }
+        }
+    }
+
+    pub fn history(&self) -> Option<Arc<HistoryShards>> {
+        self.history.read().clone()
+    }
+
+    /// Migrations of the chain store's databases. Migrations for layout changes are registered
+    /// here.
+    pub fn migrations(&self) -> Migrations {
+        let mut migrations = Migrations::new(Self::CHAIN_DB_NAME);
+        // Version 1 indexes the HTLC settlements of the existing main chain.
+        migrations.with_migration(1, move |txn| {
+            self.index_main_chain_htlc_settlements(txn);
+            Ok(())
+        });
+        migrations
+    }
+
+    fn index_main_chain_htlc_settlements(&self, txn: &mut WriteTransaction) {
+        let head = match self.get_head(Some(txn)) {
+            Some(head) => head,
+            None => return,
+        };
+        let head_height = match self.get_chain_info(&head, false, Some(txn)) {
+            Some(chain_info) => chain_info.head.block_number(),
+            None => return,
+        };
+        for block_number in 1..=head_height {
+            let block = self.get_block_at(block_number, true, Some(txn));
+            if let Some(Block::Micro(ref micro_block)) = block {
+                self.put_htlc_settlements(txn, &micro_block.header.hash(), micro_block);
+            }
+        }
     }
 
     pub fn get_head(&self, txn_option: Option<&Transaction>) -> Option<Blake2bHash> {
@@ -47,6 +126,87 @@ impl<'env> ChainStore<'env> {
         txn.put(&self.chain_db, ChainStore::HEAD_KEY, hash);
     }
 
+    /// Repairs the head pointer after a crash, such that it points to a block on the main chain
+    /// whose state root is `state_root`, the root of the stored accounts tree. Commits are atomic,
+    /// so this is only needed if a backend lost writes, e.g. because the head pointer was
+    /// persisted without the block it points to. The main chain is then searched from the top.
+    /// Returns the new head if the head pointer was changed.
+    pub fn recover_head(&self, state_root: &Blake2bHash) -> Option<Blake2bHash> {
+        // The head is consistent unless writes were lost, so a write transaction is only taken
+        // for the repair.
+        let read_txn = ReadTransaction::new(self.env);
+        let head: Option<Blake2bHash> = read_txn.get(&self.chain_db, ChainStore::HEAD_KEY);
+        if head.as_ref().map_or(false, |hash| self.is_main_chain_state(&read_txn, hash, state_root)) {
+            return None;
+        }
+
+        // If nothing is found, loading the blockchain reports the inconsistency.
+        let recovered_head = self.find_main_chain_block(&read_txn, state_root)?;
+        drop(read_txn);
+
+        let mut txn = WriteTransaction::new(self.env);
+        txn.put(&self.chain_db, ChainStore::HEAD_KEY, &recovered_head);
+        txn.commit();
+        Some(recovered_head)
+    }
+
+    /// Searches the height index from the top for a main chain block whose state root is
+    /// `state_root`. Lost writes only affect the most recent commits, so only the
+    /// `MAX_HEAD_RECOVERY_DEPTH` highest heights are searched.
+    fn find_main_chain_block(&self, txn: &Transaction, state_root: &Blake2bHash) -> Option<Blake2bHash> {
+        let max_height = self.max_indexed_height(txn)?;
+        let min_height = max_height.saturating_sub(Self::MAX_HEAD_RECOVERY_DEPTH);
+
+        let mut cursor = txn.cursor(self.height_idx.database());
+        for height in (min_height..=max_height).rev() {
+            let mut block_hash = cursor.seek_key::<u32, Blake2bHash>(&height);
+            while let Some(hash) = block_hash {
+                if self.is_main_chain_state(txn, &hash, state_root) {
+                    return Some(hash);
+                }
+                block_hash = cursor.next_duplicate::<u32, Blake2bHash>().map(|(_, hash)| hash);
+            }
+        }
+        None
+    }
+
+    /// The highest height in the height index. Every height below it is indexed as well, so it's
+    /// found by a galloping search. Heights are stored in native byte order, so the last key isn't
+    /// necessarily the highest.
+    fn max_indexed_height(&self, txn: &Transaction) -> Option<u32> {
+        if !self.height_idx.contains(txn, &0) {
+            return None;
+        }
+
+        // Find a height that isn't indexed, then bisect between it and the highest indexed one.
+        let mut indexed = 0u32;
+        let mut not_indexed = 1u32;
+        while self.height_idx.contains(txn, &not_indexed) {
+            if not_indexed == u32::max_value() {
+                return Some(not_indexed);
+            }
+            indexed = not_indexed;
+            not_indexed = not_indexed.saturating_mul(2);
+        }
+        while not_indexed - indexed > 1 {
+            let height = indexed + (not_indexed - indexed) / 2;
+            if self.height_idx.contains(txn, &height) {
+                indexed = height;
+            } else {
+                not_indexed = height;
+            }
+        }
+        Some(indexed)
+    }
+
+    /// Whether `hash` is on the main chain and its state root is `state_root`. Chain infos that
+    /// are missing or can't be decoded, e.g. because they were torn, don't match.
+    fn is_main_chain_state(&self, txn: &Transaction, hash: &Blake2bHash, state_root: &Blake2bHash) -> bool {
+        txn.get_cow(&self.chain_db, hash)
+            .and_then(|bytes| decode::<ChainInfo>(&bytes).ok())
+            .map_or(false, |chain_info| chain_info.on_main_chain && chain_info.head.state_root() == state_root)
+    }
+
     pub fn get_chain_info(&self, hash: &Blake2bHash, include_body: bool, txn_option: Option<&Transaction>) -> Option<ChainInfo> {
         let read_txn: ReadTransaction;
         let txn = match txn_option {
@@ -63,7 +223,7 @@ impl<'env> ChainStore<'env> {
         };
 
         if include_body {
-            if let Some(block) = txn.get(&self.block_db, hash) {
+            if let Some(block) = self.get_block_body(txn, hash, chain_info.head.block_number()) {
                 chain_info.head = block;
             } else {
                 warn!("Block body requested but not present");
@@ -73,24 +233,41 @@ impl<'env> ChainStore<'env> {
         Some(chain_info)
     }
 
+    /// Whether the chain info of `hash` is stored. The chain info isn't deserialized.
+    pub fn contains_chain_info(&self, hash: &Blake2bHash, txn_option: Option<&Transaction>) -> bool {
+        match txn_option {
+            Some(txn) => txn.get_cow(&self.chain_db, hash).is_some(),
+            None => ReadTransaction::new(self.env).get_cow(&self.chain_db, hash).is_some(),
+        }
+    }
+
+    /// Looks up the body of a block in the main environment and then in the shard of its epoch.
+    fn get_block_body(&self, txn: &Transaction, hash: &Blake2bHash, block_number: u32) -> Option<Block> {
+        self.block_db.get(txn, hash).or_else(|| {
+            self.history()?
+                .get(policy::epoch_at(block_number))?
+                .get_block(hash)
+        })
+    }
+
     pub fn put_chain_info(&self, txn: &mut WriteTransaction, hash: &Blake2bHash, chain_info: &ChainInfo, include_body: bool) {
         // Store chain data. Block body will not be persisted.
         txn.put_reserve(&self.chain_db, hash, chain_info);
 
         // Store body if requested.
         if include_body {
-            txn.put_reserve(&self.block_db, hash, &chain_info.head);
+            self.block_db.put_reserve(txn, hash, &chain_info.head);
         }
 
         // Add to height index.
         let height = chain_info.head.block_number();
-        txn.put(&self.height_idx, &height, hash);
+        self.height_idx.put(txn, &height, hash);
     }
 
     pub fn remove_chain_info(&self, txn: &mut WriteTransaction, hash: &Blake2bHash, height: u32) {
         txn.remove(&self.chain_db, hash);
-        txn.remove(&self.block_db, hash);
-        txn.remove_item(&self.height_idx, &height, hash);
+        self.block_db.remove(txn, hash);
+        self.height_idx.remove_item(txn, &height, hash);
     }
 
     pub fn get_chain_info_at(&self, block_height: u32, include_body: bool, txn_option: Option<&Transaction>) -> Option<ChainInfo> {
@@ -104,7 +281,7 @@ impl<'env> ChainStore<'env> {
         };
 
         // Seek to the first block at the given height.
-        let mut cursor = txn.cursor(&self.height_idx);
+        let mut cursor = txn.cursor(self.height_idx.database());
         let mut block_hash = match cursor.seek_key::<u32, Blake2bHash>(&block_height) {
             Some(hash) => hash,
             None => return None
@@ -127,7 +304,7 @@ impl<'env> ChainStore<'env> {
         }
 
         if include_body {
-            if let Some(block) = txn.get(&self.block_db, &block_hash) {
+            if let Some(block) = self.get_block_body(txn, &block_hash, block_height) {
                 chain_info.head = block;
             } else {
                 warn!("Block body requested but not present");
@@ -148,7 +325,12 @@ impl<'env> ChainStore<'env> {
         };
 
         if include_body {
-            txn.get(&self.block_db, hash)
+            self.block_db.get(txn, hash).or_else(|| {
+                // Archived blocks are only found through their block number.
+                self.history.read().as_ref()?;
+                let chain_info: ChainInfo = txn.get(&self.chain_db, hash)?;
+                self.get_block_body(txn, hash, chain_info.head.block_number())
+            })
         } else {
             txn.get(&self.chain_db, hash).map(|chain_info: ChainInfo| chain_info.head)
         }
@@ -302,7 +484,7 @@ impl<'env> ChainStore<'env> {
     }
 
     pub fn put_receipts(&self, txn: &mut WriteTransaction, block_height: u32, receipts: &Receipts) {
-        txn.put_reserve(&self.receipt_db, &block_height, receipts);
+        self.receipt_db.put_reserve(txn, &block_height, receipts);
     }
 
     pub fn get_receipts(&self, block_height: u32, txn_option: Option<&Transaction>) -> Option<Receipts> {
@@ -315,16 +497,191 @@ impl<'env> ChainStore<'env> {
             }
         };
 
-        txn.get(&self.receipt_db, &block_height)
+        self.receipt_db.get(txn, &block_height)
     }
 
     pub fn clear_receipts(&self, txn: &mut WriteTransaction) {
-        let mut cursor = txn.write_cursor(&self.receipt_db);
-        let mut pos: Option<(u32, Receipts)> = cursor.first();
+        self.receipt_db.clear(txn);
+    }
+
+    /// Indexes the HTLC settlements of a block that is added to the main chain.
+    pub fn put_htlc_settlements(&self, txn: &mut WriteTransaction, block_hash: &Blake2bHash, block: &MicroBlock) {
+        for settlement in HtlcSettlement::from_block(block_hash, block) {
+            self.htlc_settlement_idx.put(txn, &settlement.key(), &settlement);
+        }
+    }
+
+    /// Removes the HTLC settlements of a block that is reverted from the main chain.
+    pub fn remove_htlc_settlements(&self, txn: &mut WriteTransaction, block: &MicroBlock) {
+        for settlement in HtlcSettlement::from_block(&block.header.hash(), block) {
+            self.htlc_settlement_idx.remove(txn, &settlement.key());
+        }
+    }
+
+    /// The settlements of the HTLC at `address` in main chain blocks, in chain order.
+    pub fn get_htlc_settlements(&self, address: &Address, txn_option: Option<&Transaction>) -> Vec<HtlcSettlement> {
+        let read_txn: ReadTransaction;
+        let txn = match txn_option {
+            Some(txn) => txn,
+            None => {
+                read_txn = ReadTransaction::new(self.env);
+                &read_txn
+            }
+        };
+
+        txn.scan_prefix(self.htlc_settlement_idx.database(), address)
+            .map(|(_, settlement): (HtlcSettlementKey, HtlcSettlement)| settlement)
+            .collect()
+    }
+
+    /// Moves the block bodies of `epoch` into its history shard. The epoch must be finalized,
+    /// i.e. `head_height` must be at or after its macro block, since archived blocks can't be
+    /// reverted anymore. Returns the number of moved blocks.
+    pub fn archive_epoch(&self, epoch: u32, head_height: u32) -> Result<usize, HistoryShardError> {
+        let history = self.history().ok_or(HistoryShardError::NotConfigured)?;
+        if head_height < policy::macro_block_of(epoch) {
+            return Err(HistoryShardError::EpochNotFinalized(epoch));
+        }
+        let shard = history.get_or_create(epoch)?;
+        let shard_block_db = shard.block_db();
+
+        // The bodies are moved atomically, so that they are never missing from both environments
+        // or left in both after a crash. The environments are always given in this order.
+        let wal = WriteAheadLog::open(history.log_path(epoch), vec![shard.env(), self.env])
+            .map_err(|e| HistoryShardError::Log(epoch, e))?;
+        let mut write = wal.begin();
+        let moved = self.stage_epoch_bodies(epoch, shard.env(), &shard_block_db, &mut write);
+        if moved == 0 {
+            return Ok(0);
+        }
+        wal.commit(write).map_err(|e| HistoryShardError::Log(epoch, e))?;
+
+        Ok(moved)
+    }
+
+    /// Stages moving all block bodies of `epoch` found in the main environment into the shard
+    /// environment `shard_env` and returns their number.
+    fn stage_epoch_bodies<'db, 'a>(&'db self, epoch: u32, shard_env: &'a Environment, shard_block_db: &'db Database<'a>, write: &mut AtomicWrite<'db, 'a>) -> usize where 'env: 'a {
+        let read_txn = ReadTransaction::new(self.env);
+        let mut moved = 0;
+
+        let mut cursor = read_txn.cursor(self.height_idx.database());
+        for height in policy::first_block_of(epoch)..=policy::macro_block_of(epoch) {
+            let mut hash_opt = cursor.seek_key::<u32, Blake2bHash>(&height);
+            while let Some(hash) = hash_opt {
+                // The serialized blocks are copied as they are.
+                if let Some(block) = self.block_db.get_cow(&read_txn, &hash) {
+                    write.batch(shard_env).put(shard_block_db, &hash, block.as_ref());
+                    write.batch(self.env).remove(self.block_db.database(), &hash);
+                    moved += 1;
+                }
+                hash_opt = cursor.next_duplicate::<u32, Blake2bHash>().map(|(_, hash)| hash);
+            }
+        }
+
+        moved
+    }
+
+    /// Checks the entries of all databases of the chain store.
+    pub fn verify(&self, verifier: &mut Verifier<'_, 'env>) {
+        let txn = verifier.txn();
+        let chain_info_exists = |hash: &Blake2bHash| txn.get::<Blake2bHash, ChainInfo>(&self.chain_db, hash).is_some();
+
+        verifier.check_entries(&self.chain_db, |key, value| {
+            if key == ChainStore::HEAD_KEY.as_bytes() {
+                let head_hash: Blake2bHash = decode_fixed(value, Blake2bHash::SIZE)
+                    .map_err(|e| (IssueKind::InvalidValue, e))?;
+                if !chain_info_exists(&head_hash) {
+                    return Err((IssueKind::Inconsistent, format!("Head {} not found", head_hash)));
+                }
+                return Ok(());
+            }
+            let hash: Blake2bHash = decode_fixed(key, Blake2bHash::SIZE).map_err(|e| (IssueKind::InvalidKey, e))?;
+            let chain_info: ChainInfo = decode(value).map_err(|e| (IssueKind::InvalidValue, e))?;
+            if chain_info.head.hash() != hash {
+                return Err((IssueKind::Inconsistent, "Chain info is stored under another block's hash".to_string()));
+            }
+            Ok(())
+        });
+
+        verifier.check_entries(self.block_db.database(), |key, value| {
+            let hash: Blake2bHash = decode_fixed(key, Blake2bHash::SIZE).map_err(|e| (IssueKind::InvalidKey, e))?;
+            let block: Block = decode(value).map_err(|e| (IssueKind::InvalidValue, e))?;
+            if !chain_info_exists(&hash) {
+                return Err((IssueKind::Orphaned, "Block without chain info".to_string()));
+            }
+            if block.hash() != hash {
+                return Err((IssueKind::Inconsistent, "Block is stored under another block's hash".to_string()));
+            }
+            Ok(())
+        });
+
+        verifier.check_entries(self.height_idx.database(), |key, value| {
+            let height: u32 = decode_fixed(key, 4).map_err(|e| (IssueKind::InvalidKey, e))?;
+            let hash: Blake2bHash = decode_fixed(value, Blake2bHash::SIZE).map_err(|e| (IssueKind::InvalidValue, e))?;
+            match txn.get::<Blake2bHash, ChainInfo>(&self.chain_db, &hash) {
+                None => Err((IssueKind::Orphaned, format!("Indexed block {} not found", hash))),
+                Some(ref chain_info) if chain_info.head.block_number() != height =>
+                    Err((IssueKind::Orphaned, format!("Block {} is indexed at the wrong height", hash))),
+                Some(_) => Ok(()),
+            }
+        });
+
+        verifier.check_entries(self.receipt_db.database(), |key, value| {
+            decode_fixed::<u32>(key, 4).map_err(|e| (IssueKind::InvalidKey, e))?;
+            decode::<Receipts>(value).map_err(|e| (IssueKind::InvalidValue, e))?;
+            Ok(())
+        });
+
+        verifier.check_entries(self.htlc_settlement_idx.database(), |key, value| {
+            let key: HtlcSettlementKey = decode(key).map_err(|e| (IssueKind::InvalidKey, e))?;
+            let settlement: HtlcSettlement = decode(value).map_err(|e| (IssueKind::InvalidValue, e))?;
+            if settlement.key() != key {
+                return Err((IssueKind::Inconsistent, "Settlement is stored under another key".to_string()));
+            }
+            match txn.get::<Blake2bHash, ChainInfo>(&self.chain_db, &settlement.block_hash) {
+                Some(ref chain_info) if chain_info.on_main_chain => Ok(()),
+                Some(_) => Err((IssueKind::Orphaned, format!("Settlement in block {} off the main chain", settlement.block_hash))),
+                None => Err((IssueKind::Orphaned, format!("Settlement in unknown block {}", settlement.block_hash))),
+            }
+        });
+
+        self.verify_macro_blocks(verifier);
+    }
+
+    /// Checks that each macro block of the main chain up to the head is stored with its body.
+    fn verify_macro_blocks(&self, verifier: &mut Verifier<'_, 'env>) {
+        let txn = verifier.txn();
+        let head = match self.get_head(Some(txn)).and_then(|hash| self.get_chain_info(&hash, false, Some(txn))) {
+            Some(head) => head,
+            None => return,
+        };
 
-        while let Some(_) = pos {
-            cursor.remove();
-            pos = cursor.next();
+        let mut block_number = policy::macro_block_after(0);
+        while block_number <= head.head.block_number() {
+            let message = match self.get_chain_info_at(block_number, true, Some(txn)) {
+                Some(ChainInfo { head: Block::Macro(ref block), .. }) if block.extrinsics.is_none() => {
+                    Some(format!("Body of macro block #{} not found", block_number))
+                },
+                Some(ChainInfo { head: Block::Macro(_), .. }) => None,
+                Some(_) => Some(format!("Block #{} on the main chain isn't a macro block", block_number)),
+                None => Some(format!("Macro block #{} on the main chain not found", block_number)),
+            };
+            if let Some(message) = message {
+                verifier.report(ChainStore::HEIGHT_IDX_NAME, &block_number.to_ne_bytes(), IssueKind::Inconsistent, message);
+            }
+            block_number = policy::macro_block_after(block_number);
         }
     }
+
+    /// The databases checked by `verify` that only index the chain data. Their orphaned entries
+    /// can be removed, while the chain infos, blocks and receipts are only reported.
+    pub fn derived_databases(&self) -> Vec<&Database<'env>> {
+        vec![self.height_idx.database(), self.htlc_settlement_idx.database()]
+    }
+
+    /// Deletes the history shard of `epoch`, together with the block bodies in it.
+    pub fn delete_epoch(&self, epoch: u32) -> Result<bool, HistoryShardError> {
+        self.history().ok_or(HistoryShardError::NotConfigured)?.delete(epoch)
+    }
 }
@@ -0,0 +1,140 @@
+use std::cmp;
+use std::sync::{Arc, Weak};
+
+use parking_lot::{Mutex, RwLock};
+
+use block::Block;
+use hash::Blake2bHash;
+use keys::Address;
+use primitives::coin::Coin;
+use primitives::policy;
+use transaction::Transaction as BlockchainTransaction;
+use utils::observer::{Notifier, Subscription, weak_listener};
+
+use crate::blockchain::{Blockchain, BlockchainEvent};
+
+/// When a block is considered confirmed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfirmationPolicy {
+    /// The block is buried under at least this many blocks, or finalized by a macro block.
+    /// A rebranch deeper than the given depth can still revert such a block.
+    Depth(u32),
+    /// The block is finalized by a macro block. Finalized blocks can never be reverted.
+    Finalized,
+}
+
+impl ConfirmationPolicy {
+    /// Returns the block number up to which blocks are confirmed, given the current head.
+    pub fn confirmed_block_number(&self, head_number: u32) -> u32 {
+        let finalized_number = policy::last_macro_block(head_number);
+        match self {
+            ConfirmationPolicy::Depth(depth) => cmp::max(head_number.saturating_sub(*depth), finalized_number),
+            ConfirmationPolicy::Finalized => finalized_number,
+        }
+    }
+}
+
+/// A main chain block that reached the configured confirmation policy.
+#[derive(Clone, Debug)]
+pub struct ConfirmedBlock {
+    pub hash: Blake2bHash,
+    pub block: Block,
+    pub finalized: bool,
+}
+
+impl ConfirmedBlock {
+    /// Returns the transactions in this block that pay at least `min_value` to `recipient`,
+    /// together with their index in the block.
+    pub fn transactions_to<'a>(&'a self, recipient: &'a Address, min_value: Coin) -> impl Iterator<Item=(usize, &'a BlockchainTransaction)> + 'a {
+        self.block.transactions()
+            .into_iter()
+            .flat_map(|transactions| transactions.iter().enumerate())
+            .filter(move |(_, tx)| &tx.recipient == recipient && tx.value >= min_value)
+    }
+}
+
+/// Emits main chain blocks exactly once, in order, as soon as they satisfy a `ConfirmationPolicy`.
+///
+/// Blocks are looked up on the main chain only once they are confirmed, so rebranches above the
+/// confirmation depth never reach the listeners and no rollback handling is required by them.
+pub struct ConfirmationTracker<'env> {
+    blockchain: Arc<Blockchain<'env>>,
+    policy: ConfirmationPolicy,
+    /// Block number of the last block that was emitted.
+    last_confirmed: Mutex<u32>,
+    subscription: Mutex<Option<Subscription<'env, BlockchainEvent>>>,
+    pub notifier: RwLock<Notifier<'env, ConfirmedBlock>>,
+}
+
+impl<'env> ConfirmationTracker<'env> {
+    /// Creates a new tracker that emits all blocks that aren't confirmed yet.
+    pub fn from_head(blockchain: Arc<Blockchain<'env>>, policy: ConfirmationPolicy) -> Arc<Self> {
+        let start_block_number = policy.confirmed_block_number(blockchain.block_number());
+        Self::new(blockchain, policy, start_block_number)
+    }
+
+    /// Creates a new tracker that emits all blocks after `start_block_number`.
+    pub fn new(blockchain: Arc<Blockchain<'env>>, policy: ConfirmationPolicy, start_block_number: u32) -> Arc<Self> {
+        let this = Arc::new(ConfirmationTracker {
+            blockchain,
+            policy,
+            last_confirmed: Mutex::new(start_block_number),
+            subscription: Mutex::new(None),
+            notifier: RwLock::new(Notifier::new()),
+        });
+
+        let weak: Weak<Self> = Arc::downgrade(&this);
+        let subscription = this.blockchain.notifier.write().subscribe(weak_listener(weak,
+            |this, event: &BlockchainEvent| this.on_blockchain_event(event)));
+        *this.subscription.lock() = Some(subscription);
+
+        this
+    }
+
+    pub fn policy(&self) -> ConfirmationPolicy {
+        self.policy
+    }
+
+    /// Block number of the last block that was emitted.
+    pub fn last_confirmed(&self) -> u32 {
+        *self.last_confirmed.lock()
+    }
+
+    fn on_blockchain_event(&self, event: &BlockchainEvent) {
+        if let BlockchainEvent::Rebranched(reverted_blocks, _) = event {
+            let last_confirmed = self.last_confirmed();
+            if reverted_blocks.iter().any(|(_, block)| block.block_number() <= last_confirmed) {
+                warn!("Rebranch reverted blocks that were already confirmed at {:?}", self.policy);
+            }
+        }
+
+        self.process();
+    }
+
+    /// Emits all blocks that became confirmed since the last call.
+    pub fn process(&self) {
+        let mut last_confirmed = self.last_confirmed.lock();
+        let confirmed_number = self.policy.confirmed_block_number(self.blockchain.block_number());
+        let finalized_number = policy::last_macro_block(self.blockchain.block_number());
+
+        while *last_confirmed < confirmed_number {
+            let block_number = *last_confirmed + 1;
+            let block = match self.blockchain.get_block_at(block_number, true) {
+                Some(block) => block,
+                None => {
+                    // This can happen if the chain is being rebranched concurrently. We'll catch up
+                    // with the next blockchain event.
+                    debug!("Confirmed block #{} not found on main chain", block_number);
+                    break;
+                }
+            };
+
+            self.notifier.read().notify(ConfirmedBlock {
+                hash: block.hash(),
+                block,
+                finalized: block_number <= finalized_number,
+            });
+            *last_confirmed = block_number;
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use failure::Fail;
+use parking_lot::RwLock;
+
+use block::Block;
+use database::{Database, Environment, ReadTransaction};
+use database::lmdb::LmdbEnvironmentBuilder;
+use hash::Blake2bHash;
+
+#[derive(Debug, Fail)]
+pub enum HistoryShardError {
+    #[fail(display = "The chain store has no history shards")]
+    NotConfigured,
+    #[fail(display = "Epoch {} isn't finalized yet", _0)]
+    EpochNotFinalized(u32),
+    #[fail(display = "Failed to open the shard of epoch {}: {}", _0, _1)]
+    Open(u32, String),
+    #[fail(display = "Failed to delete the shard of epoch {}: {}", _0, _1)]
+    Delete(u32, #[cause] io::Error),
+    #[fail(display = "Failed to write the archive log of epoch {}: {}", _0, _1)]
+    Log(u32, #[cause] io::Error),
+}
+
+/// The LMDB environment holding the block bodies of one epoch.
+pub struct EpochShard {
+    env: Environment,
+}
+
+impl EpochShard {
+    const BLOCK_DB_NAME: &'static str = "Block";
+
+    fn open(path: &str, size: usize) -> Result<Self, String> {
+        let env = LmdbEnvironmentBuilder::new(path)
+            .with_size(size)
+            .with_no_meta_sync(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+        // Create the database right away, so that lookups never find it missing.
+        env.open_database(Self::BLOCK_DB_NAME.to_string());
+        Ok(EpochShard { env })
+    }
+
+    pub fn env(&self) -> &Environment {
+        &self.env
+    }
+
+    /// Opens the database of the block bodies. The handle borrows the environment, so it is
+    /// opened for each use instead of being kept in the shard. Like all databases, it can't be
+    /// opened while a transaction of the shard is open.
+    pub fn block_db(&self) -> Database {
+        self.env.open_database(Self::BLOCK_DB_NAME.to_string())
+    }
+
+    pub fn get_block(&self, hash: &Blake2bHash) -> Option<Block> {
+        let block_db = self.block_db();
+        ReadTransaction::new(&self.env).get(&block_db, hash)
+    }
+}
+
+/// Per-epoch LMDB environments in subdirectories of `dir`, holding the block bodies of
+/// finalized epochs, while the chain infos stay in the main environment.
+///
+/// Each shard can be moved to other storage (e.g. by mounting or symlinking its directory)
+/// or deleted independently. Shards are opened when they are first needed, so a deleted shard
+/// simply has no blocks.
+pub struct HistoryShards {
+    dir: PathBuf,
+    size: usize,
+    shards: RwLock<BTreeMap<u32, Arc<EpochShard>>>,
+}
+
+impl HistoryShards {
+    pub const DEFAULT_SIZE: usize = 1024 * 1024 * 1024;
+
+    pub fn new<P: Into<PathBuf>>(dir: P, size: usize) -> Self {
+        HistoryShards {
+            dir: dir.into(),
+            size,
+            shards: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// The directory of the shard of `epoch`.
+    pub fn path(&self, epoch: u32) -> PathBuf {
+        self.dir.join(format!("epoch-{}", epoch))
+    }
+
+    /// The write-ahead log used while the bodies of `epoch` are moved into its shard. It is
+    /// kept in the shard's directory, so it is deleted together with the shard.
+    pub fn log_path(&self, epoch: u32) -> PathBuf {
+        self.path(epoch).join("archive.log")
+    }
+
+    /// Returns the shard of `epoch`, if it exists.
+    pub fn get(&self, epoch: u32) -> Option<Arc<EpochShard>> {
+        if let Some(shard) = self.shards.read().get(&epoch) {
+            return Some(Arc::clone(shard));
+        }
+        if !self.path(epoch).exists() {
+            return None;
+        }
+        match self.open(epoch) {
+            Ok(shard) => Some(shard),
+            Err(e) => {
+                warn!("{}", e);
+                None
+            },
+        }
+    }
+
+    /// Returns the shard of `epoch`, creating it if necessary.
+    pub fn get_or_create(&self, epoch: u32) -> Result<Arc<EpochShard>, HistoryShardError> {
+        if let Some(shard) = self.shards.read().get(&epoch) {
+            return Ok(Arc::clone(shard));
+        }
+        self.open(epoch)
+    }
+
+    fn open(&self, epoch: u32) -> Result<Arc<EpochShard>, HistoryShardError> {
+        let mut shards = self.shards.write();
+        // Another thread might have opened the shard in the meantime.
+        if let Some(shard) = shards.get(&epoch) {
+            return Ok(Arc::clone(shard));
+        }
+        let path = self.path(epoch);
+        let shard = EpochShard::open(&path.to_string_lossy(), self.size)
+            .map_err(|e| HistoryShardError::Open(epoch, e))?;
+        let shard = Arc::new(shard);
+        shards.insert(epoch, Arc::clone(&shard));
+        Ok(shard)
+    }
+
+    /// Closes the shard of `epoch` and deletes its directory. Returns false if it didn't exist.
+    pub fn delete(&self, epoch: u32) -> Result<bool, HistoryShardError> {
+        self.shards.write().remove(&epoch);
+        let path = self.path(epoch);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_dir_all(path).map_err(|e| HistoryShardError::Delete(epoch, e))?;
+        Ok(true)
+    }
+
+    /// Epochs with a shard on disk, in ascending order.
+    pub fn epochs(&self) -> Vec<u32> {
+        let mut epochs: Vec<u32> = fs::read_dir(&self.dir).into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                if !name.starts_with("epoch-") {
+                    return None;
+                }
+                name["epoch-".len()..].parse().ok()
+            })
+            .collect();
+        epochs.sort();
+        epochs
+    }
+}
+
+impl std::fmt::Debug for HistoryShards {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("HistoryShards")
+            .field("dir", &self.dir)
+            .field("size", &self.size)
+            .finish()
+    }
+}
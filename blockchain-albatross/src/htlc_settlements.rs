@@ -0,0 +1,86 @@
+use std::borrow::Cow;
+use std::io;
+
+use account::AccountType;
+use beserial::{Deserialize, Serialize};
+use block::MicroBlock;
+use database::{AsDatabaseBytes, FromDatabaseValue};
+use hash::Blake2bHash;
+use keys::Address;
+use transaction::Transaction;
+
+/// A transaction from an HTLC in a main chain block, i.e. a redeem, an early resolve or a timeout
+/// resolve. The proof of a redeem reveals the pre-image of the HTLC's hash root.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HtlcSettlement {
+    pub block_hash: Blake2bHash,
+    pub block_number: u32,
+    /// Index of the transaction in its block.
+    pub index: u16,
+    pub transaction: Transaction,
+}
+
+impl HtlcSettlement {
+    /// The settlements of HTLCs in `block`, in block order.
+    pub fn from_block(block_hash: &Blake2bHash, block: &MicroBlock) -> Vec<HtlcSettlement> {
+        let transactions = match block.extrinsics {
+            Some(ref extrinsics) => &extrinsics.transactions,
+            None => return Vec::new(),
+        };
+        transactions.iter()
+            .enumerate()
+            .filter(|(_, transaction)| transaction.sender_type == AccountType::HTLC)
+            .map(|(index, transaction)| HtlcSettlement {
+                block_hash: block_hash.clone(),
+                block_number: block.header.block_number,
+                index: index as u16,
+                transaction: transaction.clone(),
+            })
+            .collect()
+    }
+
+    pub(crate) fn key(&self) -> HtlcSettlementKey {
+        HtlcSettlementKey {
+            address: self.transaction.sender.clone(),
+            block_number: self.block_number,
+            index: self.index,
+        }
+    }
+}
+
+impl AsDatabaseBytes for HtlcSettlement {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        let v = Serialize::serialize_to_vec(&self);
+        Cow::Owned(v)
+    }
+}
+
+impl FromDatabaseValue for HtlcSettlement {
+    fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
+        let mut cursor = io::Cursor::new(bytes);
+        Ok(Deserialize::deserialize(&mut cursor)?)
+    }
+}
+
+/// Key of the settlement index. Serialized big-endian, so the settlements of an HTLC are sorted
+/// by block number and index and can be found by a prefix scan over its address.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct HtlcSettlementKey {
+    address: Address,
+    block_number: u32,
+    index: u16,
+}
+
+impl AsDatabaseBytes for HtlcSettlementKey {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        let v = Serialize::serialize_to_vec(&self);
+        Cow::Owned(v)
+    }
+}
+
+impl FromDatabaseValue for HtlcSettlementKey {
+    fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
+        let mut cursor = io::Cursor::new(bytes);
+        Ok(Deserialize::deserialize(&mut cursor)?)
+    }
+}
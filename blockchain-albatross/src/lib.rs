@@ -12,16 +12,26 @@ extern crate nimiq_collections as collections;
 extern crate nimiq_database as database;
 extern crate nimiq_hash as hash;
 extern crate nimiq_keys as keys;
+extern crate nimiq_macro_verifier as macro_verifier;
 extern crate nimiq_network_primitives as network_primitives;
 extern crate nimiq_primitives as primitives;
 extern crate nimiq_transaction as transaction;
 extern crate nimiq_tree_primitives as tree_primitives;
 extern crate nimiq_utils as utils;
 
+pub mod attestation;
 pub mod blockchain;
 pub mod chain_info;
+pub mod chain_stats;
 pub mod chain_store;
+pub mod confirmations;
+pub mod history_shards;
+pub mod htlc_settlements;
+pub mod pruned_accounts;
 pub mod reward_registry;
+pub mod slot_schedule;
 pub mod transaction_cache;
+pub mod verify;
+pub mod watch_registry;
 
 pub use blockchain::Blockchain;
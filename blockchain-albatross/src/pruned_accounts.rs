@@ -0,0 +1,80 @@
+use std::sync::{Arc, Weak};
+
+use parking_lot::{Mutex, RwLock};
+
+use account::PrunedAccount;
+use block::Block;
+use hash::Blake2bHash;
+use utils::observer::{Notifier, Subscription, weak_listener};
+
+use crate::blockchain::{Blockchain, BlockchainEvent};
+
+/// Accounts that were pruned by a main chain block, because their balance dropped to zero.
+#[derive(Clone, Debug)]
+pub struct PrunedAccounts {
+    pub block_hash: Blake2bHash,
+    pub block_number: u32,
+    /// The state of the accounts right before they were pruned. Contracts can be recreated from
+    /// it, see `PrunedAccount::restoration`.
+    pub accounts: Vec<PrunedAccount>,
+}
+
+/// Emits the accounts pruned by each block that is added to the main chain.
+///
+/// Blocks that are adopted by a rebranch are emitted again, listeners need to handle that the
+/// pruning of reverted blocks didn't happen.
+pub struct PrunedAccountsTracker<'env> {
+    blockchain: Arc<Blockchain<'env>>,
+    subscription: Mutex<Option<Subscription<'env, BlockchainEvent>>>,
+    pub notifier: RwLock<Notifier<'env, PrunedAccounts>>,
+}
+
+impl<'env> PrunedAccountsTracker<'env> {
+    pub fn new(blockchain: Arc<Blockchain<'env>>) -> Arc<Self> {
+        let this = Arc::new(PrunedAccountsTracker {
+            blockchain,
+            subscription: Mutex::new(None),
+            notifier: RwLock::new(Notifier::new()),
+        });
+
+        let weak: Weak<Self> = Arc::downgrade(&this);
+        let subscription = this.blockchain.notifier.write().subscribe(weak_listener(weak,
+            |this, event: &BlockchainEvent| this.on_blockchain_event(event)));
+        *this.subscription.lock() = Some(subscription);
+
+        this
+    }
+
+    fn on_blockchain_event(&self, event: &BlockchainEvent) {
+        match event {
+            BlockchainEvent::Extended(hash) => {
+                if let Some(block) = self.blockchain.get_block(hash, false, false) {
+                    self.on_block(hash, &block);
+                }
+            },
+            BlockchainEvent::Rebranched(_, adopted_blocks) => {
+                for (hash, block) in adopted_blocks {
+                    self.on_block(hash, block);
+                }
+            },
+            // Macro blocks don't contain transactions.
+            BlockchainEvent::Finalized(_) => {},
+        }
+    }
+
+    fn on_block(&self, hash: &Blake2bHash, block: &Block) {
+        if let Block::Micro(_) = block {
+            let accounts = match self.blockchain.get_pruned_accounts(block.block_number()) {
+                Some(accounts) => accounts,
+                None => return,
+            };
+            if !accounts.is_empty() {
+                self.notifier.read().notify(PrunedAccounts {
+                    block_hash: hash.clone(),
+                    block_number: block.block_number(),
+                    accounts,
+                });
+            }
+        }
+    }
+}
@@ -10,8 +10,10 @@ use beserial::{Deserialize, Serialize};
 use block::{Block, MacroBlock, MicroBlock};
 use collections::bitset::BitSet;
 use database::{AsDatabaseBytes, Database, DatabaseFlags, Environment, FromDatabaseValue,
-               ReadTransaction, Transaction, WriteTransaction};
-use database::cursor::{ReadCursor, WriteCursor};
+               ReadTransaction, Transaction, TypedDatabase, WriteTransaction};
+use database::cursor::ReadCursor;
+use database::migrations::Migrations;
+use database::verify::{decode, decode_fixed, IssueKind, Verifier};
 use hash::{Blake2bHasher, Hasher};
 use primitives::coin::Coin;
 use primitives::policy;
@@ -19,17 +21,21 @@ use primitives::validators::{IndexedSlot, Slots};
 use transaction::Transaction as BlockchainTransaction;
 
 use crate::chain_store::ChainStore;
+use crate::reward_registry::reward_log::RewardLog;
 use crate::reward_registry::reward_pot::RewardPot;
+pub use crate::reward_registry::reward_log::{EpochRewards, RecipientReward};
 pub use crate::reward_registry::slashed_slots::SlashedSlots;
 
+mod reward_log;
 mod reward_pot;
 mod slashed_slots;
 
 pub struct SlashRegistry<'env> {
     env: &'env Environment,
     chain_store: Arc<ChainStore<'env>>,
-    slash_registry_db: Database<'env>,
+    slash_registry_db: TypedDatabase<'env, u32, BlockDescriptor>,
     reward_pot: RewardPot<'env>,
+    reward_log: RewardLog<'env>,
 }
 
 // TODO Better error messages
@@ -65,16 +71,41 @@ impl<'env> SlashRegistry<'env> {
     const SLASH_REGISTRY_DB_NAME: &'static str = "SlashRegistry";
 
     pub fn new(env: &'env Environment, chain_store: Arc<ChainStore<'env>>) -> Self {
-        let slash_registry_db = env.open_database_with_flags(SlashRegistry::SLASH_REGISTRY_DB_NAME.to_string(), DatabaseFlags::UINT_KEYS);
+        let slash_registry_db = TypedDatabase::open_with_flags(env, SlashRegistry::SLASH_REGISTRY_DB_NAME.to_string(), DatabaseFlags::UINT_KEYS);
 
         Self {
             env,
             chain_store,
             slash_registry_db,
             reward_pot: RewardPot::new(env),
+            reward_log: RewardLog::new(env),
         }
     }
 
+    /// Migrations of the slash registry's database. Migrations for layout changes are registered
+    /// here.
+    pub fn migrations(&self) -> Migrations {
+        Migrations::new(Self::SLASH_REGISTRY_DB_NAME)
+    }
+
+    /// Checks the entries of the slash registry. Entries after `head_height` are orphaned.
+    pub fn verify(&self, verifier: &mut Verifier<'_, 'env>, head_height: u32) {
+        verifier.check_entries(self.slash_registry_db.database(), |key, value| {
+            let block_number: u32 = decode_fixed(key, 4).map_err(|e| (IssueKind::InvalidKey, e))?;
+            decode::<BlockDescriptor>(value).map_err(|e| (IssueKind::InvalidValue, e))?;
+            if block_number > head_height {
+                return Err((IssueKind::Orphaned, format!("Block #{} is after the head", block_number)));
+            }
+            Ok(())
+        });
+    }
+
+    /// The databases checked by `verify` that are derived from the blocks, see
+    /// `ChainStore::derived_databases`.
+    pub fn derived_databases(&self) -> Vec<&Database<'env>> {
+        vec![self.slash_registry_db.database()]
+    }
+
     #[inline]
     pub fn current_reward_pot(&self) -> Coin {
         self.reward_pot.current_reward_pot()
@@ -85,6 +116,24 @@ impl<'env> SlashRegistry<'env> {
         self.reward_pot.previous_reward_pot()
     }
 
+    /// Records how the reward pot of an epoch was distributed.
+    #[inline]
+    pub fn commit_epoch_rewards(&self, txn: &mut WriteTransaction, rewards: &EpochRewards) {
+        self.reward_log.put(rewards, txn);
+    }
+
+    /// Returns how the reward pot of `epoch` was distributed, if this node processed the payout.
+    #[inline]
+    pub fn epoch_rewards(&self, epoch: u32, txn_option: Option<&Transaction>) -> Option<EpochRewards> {
+        self.reward_log.get(epoch, txn_option)
+    }
+
+    /// Removes the reward distributions of the epochs before `epoch`. They are only kept for
+    /// reference and aren't needed to process blocks.
+    pub fn prune_epoch_rewards(&self, txn: &mut WriteTransaction, epoch: u32) {
+        self.reward_log.remove_before(epoch, txn);
+    }
+
     /// Register slashes of block
     ///  * `block` - Block to commit
     ///  * `seed`- Seed of previous block
@@ -113,7 +162,7 @@ impl<'env> SlashRegistry<'env> {
         let descriptor = BlockDescriptor { epoch_state: BitSet::new(), prev_epoch_state: slashed_slots.clone() };
 
         // Put descriptor into database.
-        txn.put(&self.slash_registry_db, &block_number, &descriptor);
+        self.slash_registry_db.put(txn, &block_number, &descriptor);
         self.gc(txn, policy::epoch_at(block_number));
 
         Ok(())
@@ -123,7 +172,7 @@ impl<'env> SlashRegistry<'env> {
         let block_epoch = policy::epoch_at(block_number);
 
         // Lookup slash state.
-        let mut cursor = txn.cursor(&self.slash_registry_db);
+        let mut cursor = txn.cursor(self.slash_registry_db.database());
         // Move cursor to first entry with a block number >= ours (or end of the database).
         let _: Option<(u32, BlockDescriptor)> = cursor.seek_range_key(&block_number);
         // Then move cursor back by one.
@@ -173,7 +222,7 @@ impl<'env> SlashRegistry<'env> {
         let descriptor = BlockDescriptor { epoch_state, prev_epoch_state };
 
         // Put descriptor into database.
-        txn.put(&self.slash_registry_db, &block.header.block_number, &descriptor);
+        self.slash_registry_db.put(txn, &block.header.block_number, &descriptor);
 
         Ok(())
     }
@@ -208,7 +257,7 @@ impl<'env> SlashRegistry<'env> {
         }
 
         // Lookup slash state.
-        let mut cursor = txn.cursor(&self.slash_registry_db);
+        let mut cursor = txn.cursor(self.slash_registry_db.database());
         // Move cursor to first entry with a block number >= ours (or end of the database).
         let _: Option<(u32, BlockDescriptor)> = cursor.seek_range_key(&block.header.block_number);
         // Then move cursor back by one.
@@ -259,7 +308,7 @@ impl<'env> SlashRegistry<'env> {
         let descriptor = BlockDescriptor { epoch_state, prev_epoch_state };
 
         // Put descriptor into database.
-        txn.put(&self.slash_registry_db, &block.header.block_number, &descriptor);
+        self.slash_registry_db.put(txn, &block.header.block_number, &descriptor);
 
         Ok(())
     }
@@ -272,16 +321,7 @@ impl<'env> SlashRegistry<'env> {
             return;
         }
 
-        let mut cursor = txn.write_cursor(&self.slash_registry_db);
-        let mut pos: Option<(u32, BlockDescriptor)> = cursor.first();
-
-        while let Some((block_number, _)) = pos {
-            if block_number >= cutoff {
-                return;
-            }
-            cursor.remove();
-            pos = cursor.next();
-        }
+        txn.remove_range::<u32>(self.slash_registry_db.database(), 0..cutoff);
     }
 
     #[inline]
@@ -295,7 +335,7 @@ impl<'env> SlashRegistry<'env> {
     }
 
     fn revert_micro_block(&self, txn: &mut WriteTransaction, block: &MicroBlock) -> Result<(), SlashPushError> {
-        txn.remove(&self.slash_registry_db, &block.header.block_number);
+        self.slash_registry_db.remove(txn, &block.header.block_number);
         Ok(())
     }
 
@@ -364,7 +404,7 @@ impl<'env> SlashRegistry<'env> {
         };
 
         // Lookup slash state.
-        let mut cursor = txn.cursor(&self.slash_registry_db);
+        let mut cursor = txn.cursor(self.slash_registry_db.database());
         // Move cursor to first entry with a block number >= ours (or end of the database).
         let _: Option<(u32, BlockDescriptor)> = cursor.seek_range_key(&block_number);
         // Then move cursor back by one.
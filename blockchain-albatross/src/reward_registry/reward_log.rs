@@ -0,0 +1,135 @@
+use std::borrow::Cow;
+use std::io;
+
+use beserial::{Deserialize, Serialize};
+use database::{AsDatabaseBytes, DatabaseFlags, Environment, FromDatabaseValue, ReadTransaction,
+               Transaction, TypedDatabase, WriteTransaction};
+use keys::Address;
+use primitives::coin::Coin;
+
+/// Rewards paid to a single address at the end of an epoch.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipientReward {
+    pub address: Address,
+    /// Number of reward eligible slots paying to this address.
+    pub num_slots: u16,
+    pub value: Coin,
+}
+
+/// How the reward pot of an epoch was distributed. The rewards are paid by inherents of the macro
+/// block ending the following epoch.
+///
+/// Each reward eligible slot gets `slot_reward`. Rewards of slots whose recipient rejects the
+/// inherent are split evenly over the accepting slots, each of which gets `redistributed_reward`
+/// on top. The Luna left over from both divisions go to the first accepting slots, one each.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochRewards {
+    pub epoch: u32,
+    pub reward_pot: Coin,
+    pub num_eligible_slots: u16,
+    pub num_slashed_slots: u16,
+    pub slot_reward: Coin,
+    pub redistributed_reward: Coin,
+    /// Recipients in the order of their first slot.
+    #[beserial(len_type(u16))]
+    pub rewards: Vec<RecipientReward>,
+    /// Recipients that rejected the reward inherent, with the rewards they would have received.
+    #[beserial(len_type(u16))]
+    pub rejected: Vec<RecipientReward>,
+}
+
+impl EpochRewards {
+    pub fn new(epoch: u32, reward_pot: Coin, num_eligible_slots: u16, num_slashed_slots: u16, slot_reward: Coin) -> Self {
+        EpochRewards {
+            epoch,
+            reward_pot,
+            num_eligible_slots,
+            num_slashed_slots,
+            slot_reward,
+            redistributed_reward: Coin::ZERO,
+            rewards: Vec::new(),
+            rejected: Vec::new(),
+        }
+    }
+
+    /// Adds the reward of one slot paying to `address`.
+    pub fn add_reward(&mut self, address: &Address, value: Coin) {
+        Self::add(&mut self.rewards, address, value);
+    }
+
+    /// Adds the reward of one slot paying to `address`, which rejected it.
+    pub fn add_rejected(&mut self, address: &Address, value: Coin) {
+        Self::add(&mut self.rejected, address, value);
+    }
+
+    /// The total value paid out, which should equal the reward pot.
+    pub fn total_value(&self) -> Coin {
+        self.rewards.iter().fold(Coin::ZERO, |sum, reward| sum + reward.value)
+    }
+
+    pub fn reward_of(&self, address: &Address) -> Option<&RecipientReward> {
+        self.rewards.iter().find(|reward| &reward.address == address)
+    }
+
+    fn add(recipients: &mut Vec<RecipientReward>, address: &Address, value: Coin) {
+        match recipients.iter_mut().find(|reward| &reward.address == address) {
+            Some(reward) => {
+                reward.num_slots += 1;
+                reward.value += value;
+            },
+            None => recipients.push(RecipientReward {
+                address: address.clone(),
+                num_slots: 1,
+                value,
+            }),
+        }
+    }
+}
+
+impl AsDatabaseBytes for EpochRewards {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        let v = Serialize::serialize_to_vec(&self);
+        Cow::Owned(v)
+    }
+}
+
+impl FromDatabaseValue for EpochRewards {
+    fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
+        let mut cursor = io::Cursor::new(bytes);
+        Ok(Deserialize::deserialize(&mut cursor)?)
+    }
+}
+
+/// Keeps the `EpochRewards` of all finalized epochs, so they can be audited later.
+pub struct RewardLog<'env> {
+    env: &'env Environment,
+    reward_log: TypedDatabase<'env, u32, EpochRewards>,
+}
+
+impl<'env> RewardLog<'env> {
+    const REWARD_LOG_DB_NAME: &'static str = "RewardLog";
+
+    pub fn new(env: &'env Environment) -> Self {
+        let reward_log = TypedDatabase::open_with_flags(env, RewardLog::REWARD_LOG_DB_NAME.to_string(), DatabaseFlags::UINT_KEYS);
+
+        Self {
+            env,
+            reward_log,
+        }
+    }
+
+    pub(super) fn put(&self, rewards: &EpochRewards, txn: &mut WriteTransaction) {
+        self.reward_log.put(txn, &rewards.epoch, rewards);
+    }
+
+    pub(super) fn get(&self, epoch: u32, txn_option: Option<&Transaction>) -> Option<EpochRewards> {
+        match txn_option {
+            Some(txn) => self.reward_log.get(txn, &epoch),
+            None => self.reward_log.get(&ReadTransaction::new(self.env), &epoch),
+        }
+    }
+
+    pub(super) fn remove_before(&self, epoch: u32, txn: &mut WriteTransaction) {
+        txn.remove_range::<u32>(self.reward_log.database(), 0..epoch);
+    }
+}
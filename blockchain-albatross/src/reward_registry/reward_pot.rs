@@ -1,6 +1,6 @@
 use block::{MacroBlock, MicroBlock};
 use collections::bitset::BitSet;
-use database::{Database, Environment, ReadTransaction, WriteTransaction};
+use database::{Environment, ReadTransaction, TypedDatabase, WriteTransaction};
 use primitives::coin::Coin;
 use primitives::policy;
 use primitives::validators::Slots;
@@ -8,7 +8,7 @@ use transaction::Transaction as BlockchainTransaction;
 
 pub struct RewardPot<'env> {
     env: &'env Environment,
-    reward_pot: Database<'env>,
+    reward_pot: TypedDatabase<'env, str, u64>,
 }
 
 impl<'env> RewardPot<'env> {
@@ -17,7 +17,7 @@ impl<'env> RewardPot<'env> {
     const PREVIOUS_EPOCH_KEY: &'static str = "prev";
 
     pub fn new(env: &'env Environment) -> Self {
-        let reward_pot = env.open_database(RewardPot::REWARD_POT_DB_NAME.to_string());
+        let reward_pot = TypedDatabase::open(env, RewardPot::REWARD_POT_DB_NAME.to_string());
 
         Self {
             env,
@@ -30,10 +30,10 @@ impl<'env> RewardPot<'env> {
         let mut current_reward = RewardPot::reward_for_macro_block(block, slots, prev_view_number);
 
         // Add to current reward pot of epoch.
-        current_reward += Coin::from_u64_unchecked(txn.get(&self.reward_pot, Self::CURRENT_EPOCH_KEY).unwrap_or(0));
+        current_reward += Coin::from_u64_unchecked(self.reward_pot.get(txn, Self::CURRENT_EPOCH_KEY).unwrap_or(0));
 
-        txn.put(&self.reward_pot, Self::CURRENT_EPOCH_KEY, &0u64);
-        txn.put(&self.reward_pot, Self::PREVIOUS_EPOCH_KEY, &u64::from(current_reward));
+        self.reward_pot.put(txn, Self::CURRENT_EPOCH_KEY, &0u64);
+        self.reward_pot.put(txn, Self::PREVIOUS_EPOCH_KEY, &u64::from(current_reward));
     }
 
     pub(super) fn commit_epoch(&self, block_number: u32, transactions: &[BlockchainTransaction], slashed_set: &BitSet, slots: &Slots, txn: &mut WriteTransaction) {
@@ -57,8 +57,8 @@ impl<'env> RewardPot<'env> {
             .checked_mul(slashed_set.len() as u64)
             .unwrap_or_else(|| panic!("Slash fine overflowed"));
 
-        txn.put(&self.reward_pot, Self::CURRENT_EPOCH_KEY, &0u64);
-        txn.put(&self.reward_pot, Self::PREVIOUS_EPOCH_KEY, &u64::from(reward));
+        self.reward_pot.put(txn, Self::CURRENT_EPOCH_KEY, &0u64);
+        self.reward_pot.put(txn, Self::PREVIOUS_EPOCH_KEY, &u64::from(reward));
     }
 
     pub(super) fn commit_micro_block(&self, block: &MicroBlock, slots: &Slots, prev_view_number: u32, txn: &mut WriteTransaction) {
@@ -66,18 +66,18 @@ impl<'env> RewardPot<'env> {
         let mut reward = RewardPot::reward_for_micro_block(block, slots, prev_view_number);
 
         // Add to current reward pot of epoch.
-        reward += Coin::from_u64_unchecked(txn.get(&self.reward_pot, Self::CURRENT_EPOCH_KEY).unwrap_or(0));
-        txn.put(&self.reward_pot, Self::CURRENT_EPOCH_KEY, &u64::from(reward));
+        reward += Coin::from_u64_unchecked(self.reward_pot.get(txn, Self::CURRENT_EPOCH_KEY).unwrap_or(0));
+        self.reward_pot.put(txn, Self::CURRENT_EPOCH_KEY, &u64::from(reward));
     }
 
     pub(super) fn revert_micro_block(&self, block: &MicroBlock, slots: &Slots, prev_view_number: u32, txn: &mut WriteTransaction) {
         // The total reward of a block is composed of the block reward, transaction fees and slashes.
-        let mut reward = Coin::from_u64_unchecked(txn.get(&self.reward_pot, Self::CURRENT_EPOCH_KEY).unwrap_or(0));
+        let mut reward = Coin::from_u64_unchecked(self.reward_pot.get(txn, Self::CURRENT_EPOCH_KEY).unwrap_or(0));
 
         // Add to current reward pot of epoch.
         reward -= RewardPot::reward_for_micro_block(block, slots, prev_view_number);
 
-        txn.put(&self.reward_pot, Self::CURRENT_EPOCH_KEY, &u64::from(reward));
+        self.reward_pot.put(txn, Self::CURRENT_EPOCH_KEY, &u64::from(reward));
     }
 
     fn reward_for_micro_block(block: &MicroBlock, slots: &Slots, prev_view_number: u32) -> Coin {
@@ -120,11 +120,11 @@ impl<'env> RewardPot<'env> {
 
     pub fn current_reward_pot(&self) -> Coin {
         let txn = ReadTransaction::new(self.env);
-        Coin::from_u64_unchecked(txn.get(&self.reward_pot, Self::CURRENT_EPOCH_KEY).unwrap_or(0))
+        Coin::from_u64_unchecked(self.reward_pot.get(&txn, Self::CURRENT_EPOCH_KEY).unwrap_or(0))
     }
 
     pub fn previous_reward_pot(&self) -> Coin {
         let txn = ReadTransaction::new(self.env);
-        Coin::from_u64_unchecked(txn.get(&self.reward_pot, Self::PREVIOUS_EPOCH_KEY).unwrap_or(0))
+        Coin::from_u64_unchecked(self.reward_pot.get(&txn, Self::PREVIOUS_EPOCH_KEY).unwrap_or(0))
     }
 }
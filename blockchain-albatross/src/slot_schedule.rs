@@ -0,0 +1,110 @@
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use bls::bls12_381::CompressedPublicKey;
+use primitives::policy;
+use primitives::validators::IndexedSlot;
+use utils::observer::{Subscription, weak_listener};
+
+use crate::blockchain::{Blockchain, BlockchainEvent};
+
+/// Tells heavy background work (e.g. backups) whether a validator is about to take part in a
+/// block, so it can pause its I/O in a window around the validator's own slots instead of making
+/// it miss them.
+///
+/// Only the producer of the next block is known in advance, since it depends on the seed of the
+/// head. So the schedule is busy while the validator produces the next micro block or signs the
+/// next macro block, and for `window` after that block was pushed. View changes aren't followed.
+pub struct SlotSchedule<'env> {
+    blockchain: Arc<Blockchain<'env>>,
+    /// The validator's keys, both keys during a key rotation.
+    public_keys: Vec<CompressedPublicKey>,
+    window: Duration,
+    state: Mutex<ScheduleState>,
+    subscription: Mutex<Option<Subscription<'env, BlockchainEvent>>>,
+}
+
+#[derive(Default)]
+struct ScheduleState {
+    /// Whether the validator takes part in the next block.
+    next_block: bool,
+    /// When the last block the validator took part in was pushed.
+    last_block: Option<Instant>,
+}
+
+impl<'env> SlotSchedule<'env> {
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(5);
+    /// How long background work waits for the schedule to become idle before it starts anyway.
+    /// A validator that takes part in every block (e.g. the only one) is never idle.
+    pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(60);
+    /// How often `wait_until_idle` checks the schedule.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    pub fn new(blockchain: Arc<Blockchain<'env>>, public_keys: Vec<CompressedPublicKey>, window: Duration) -> Arc<Self> {
+        let this = Arc::new(SlotSchedule {
+            blockchain,
+            public_keys,
+            window,
+            state: Mutex::new(ScheduleState::default()),
+            subscription: Mutex::new(None),
+        });
+
+        let weak: Weak<Self> = Arc::downgrade(&this);
+        let subscription = this.blockchain.notifier.write().subscribe(weak_listener(weak,
+            |this, _: &BlockchainEvent| this.update()));
+        *this.subscription.lock() = Some(subscription);
+        this.update();
+
+        this
+    }
+
+    /// Whether the validator is about to take part in a block or just did.
+    pub fn is_busy(&self) -> bool {
+        let state = self.state.lock();
+        state.next_block || state.last_block.map_or(false, |pushed| pushed.elapsed() < self.window)
+    }
+
+    /// Blocks the current thread while the schedule is busy, but for at most `max_wait`. Returns
+    /// whether the schedule is idle, i.e. `false` if it gave up waiting. Must only be called from
+    /// threads of background work, not from the runtime.
+    ///
+    /// This only gates the start of the work: once it returns, the work isn't paused again if
+    /// the validator's next slot comes up while it is still running.
+    pub fn wait_until_idle(&self, max_wait: Duration) -> bool {
+        let deadline = Instant::now() + max_wait;
+        while self.is_busy() {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            thread::sleep(Self::POLL_INTERVAL.min(deadline - now));
+        }
+        true
+    }
+
+    fn update(&self) {
+        let next_block = self.takes_part_in_next_block();
+        let mut state = self.state.lock();
+        // The head changed, so the block the validator took part in has been pushed.
+        if state.next_block {
+            state.last_block = Some(Instant::now());
+        }
+        state.next_block = next_block;
+    }
+
+    fn takes_part_in_next_block(&self) -> bool {
+        let block_number = self.blockchain.block_number() + 1;
+        if policy::is_macro_block_at(block_number) {
+            // All validators of the epoch sign its macro block.
+            return self.blockchain.current_validators().iter_groups()
+                .any(|group| self.public_keys.contains(group.1.compressed()));
+        }
+        match self.blockchain.get_block_producer_at(block_number, 0, None) {
+            Some(IndexedSlot { slot, .. }) => self.public_keys.contains(slot.public_key.compressed()),
+            None => false,
+        }
+    }
+}
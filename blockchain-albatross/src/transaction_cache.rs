@@ -2,24 +2,48 @@ use std::collections::{HashSet, VecDeque};
 
 use hash::{Blake2bHash, Hash};
 use block::Block;
+use keys::Address;
 use primitives::policy;
 
+/// A transaction tracked by the `TransactionCache`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedTransaction {
+    pub hash: Blake2bHash,
+    pub sender: Address,
+    pub validity_start_height: u32,
+    pub block_number: u32,
+}
+
+impl CachedTransaction {
+    /// First block height at which this transaction is no longer valid and thus can't be replayed.
+    pub fn expires_at(&self) -> u32 {
+        self.validity_start_height.saturating_add(policy::TRANSACTION_VALIDITY_WINDOW_ALBATROSS)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct BlockDescriptor {
     hash: Blake2bHash,
     prev_hash: Blake2bHash,
-    transaction_hashes: Vec<Blake2bHash>
+    transactions: Vec<CachedTransaction>,
 }
 
 impl<'a> From<&'a Block> for BlockDescriptor {
     fn from(block: &'a Block) -> Self {
-        let transactions = block.transactions();
-        let hashes = transactions.map(|txs| txs.iter().map(Hash::hash).collect()).unwrap_or_else(|| vec![]);
+        let block_number = block.block_number();
+        let transactions = block.transactions()
+            .map(|txs| txs.iter().map(|tx| CachedTransaction {
+                hash: tx.hash(),
+                sender: tx.sender.clone(),
+                validity_start_height: tx.validity_start_height,
+                block_number,
+            }).collect())
+            .unwrap_or_else(|| vec![]);
 
         BlockDescriptor {
             hash: block.hash(),
             prev_hash: block.parent_hash().clone(),
-            transaction_hashes: hashes,
+            transactions,
         }
     }
 }
@@ -63,12 +87,20 @@ impl TransactionCache {
         false
     }
 
+    /// Returns all transactions from `sender` that are still in the replay protection window,
+    /// ordered by block number.
+    pub fn transactions_from<'a>(&'a self, sender: &'a Address) -> impl Iterator<Item=&'a CachedTransaction> + 'a {
+        self.block_order.iter()
+            .flat_map(|descriptor| descriptor.transactions.iter())
+            .filter(move |tx| &tx.sender == sender)
+    }
+
     pub fn push_block(&mut self, block: &Block) {
         assert!(self.block_order.is_empty() || *block.parent_hash() == self.block_order.back().as_ref().unwrap().hash);
 
         let descriptor = BlockDescriptor::from(block);
-        for hash in &descriptor.transaction_hashes {
-            let is_new = self.transaction_hashes.insert(hash.clone());
+        for tx in &descriptor.transactions {
+            let is_new = self.transaction_hashes.insert(tx.hash.clone());
             assert!(is_new);
         }
         self.block_order.push_back(descriptor);
@@ -82,8 +114,8 @@ impl TransactionCache {
         let descriptor = self.block_order.pop_back();
         if let Some(descriptor) = descriptor {
             assert_eq!(descriptor.hash, block.hash());
-            for hash in &descriptor.transaction_hashes {
-                self.transaction_hashes.remove(hash);
+            for tx in &descriptor.transactions {
+                self.transaction_hashes.remove(&tx.hash);
             }
         }
     }
@@ -93,8 +125,8 @@ impl TransactionCache {
         assert!(self.missing_blocks() > 0);
 
         let descriptor = BlockDescriptor::from(block);
-        for hash in &descriptor.transaction_hashes {
-            let is_new = self.transaction_hashes.insert(hash.clone());
+        for tx in &descriptor.transactions {
+            let is_new = self.transaction_hashes.insert(tx.hash.clone());
             assert!(is_new);
         }
         self.block_order.push_front(descriptor);
@@ -103,8 +135,8 @@ impl TransactionCache {
     fn shift_block(&mut self) {
         let descriptor_opt = self.block_order.pop_front();
         if let Some(descriptor) = descriptor_opt {
-            for hash in &descriptor.transaction_hashes {
-                self.transaction_hashes.remove(hash);
+            for tx in &descriptor.transactions {
+                self.transaction_hashes.remove(&tx.hash);
             }
         }
     }
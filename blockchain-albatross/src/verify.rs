@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use accounts::Accounts;
+use database::{Environment, ReadTransaction};
+use database::verify::{self, IssueKind, Report, Verifier};
+
+use crate::chain_store::ChainStore;
+use crate::reward_registry::SlashRegistry;
+
+/// Checks the databases of the chain store, the accounts tree and the slash registry in `env`,
+/// e.g. after an unclean shutdown. The node must not be running.
+///
+/// The accounts tree only holds the state after the head, so its root is only compared to the
+/// head's state root. Macro blocks on the main chain are checked for being present with bodies.
+pub fn verify_databases(env: &Environment) -> Report {
+    let chain_store = Arc::new(ChainStore::new(env));
+    let slash_registry = SlashRegistry::new(env, Arc::clone(&chain_store));
+    let accounts = Accounts::new(env);
+
+    let txn = ReadTransaction::new(env);
+    let mut verifier = Verifier::new(&txn);
+    chain_store.verify(&mut verifier);
+
+    let head = chain_store.get_head(Some(&txn))
+        .and_then(|hash| chain_store.get_chain_info(&hash, false, Some(&txn)));
+    match head {
+        Some(head) => {
+            slash_registry.verify(&mut verifier, head.head.block_number());
+
+            let accounts_hash = accounts.hash(Some(&txn));
+            if &accounts_hash != head.head.state_root() {
+                verifier.report("accounts", &[], IssueKind::Inconsistent,
+                    format!("Accounts root {} doesn't match the state root {} of block #{}",
+                            accounts_hash, head.head.state_root(), head.head.block_number()));
+            }
+        },
+        None => verifier.report(ChainStore::CHAIN_DB_NAME, b"head", IssueKind::Inconsistent, "No head block".to_string()),
+    }
+
+    verifier.finish()
+}
+
+/// Removes the orphaned entries of the derived databases found by `verify_databases`. All other
+/// issues are left for the operator, since removing chain data can't be undone. Returns the
+/// number of removed entries.
+pub fn repair_databases(env: &Environment, report: &Report) -> usize {
+    let chain_store = Arc::new(ChainStore::new(env));
+    let slash_registry = SlashRegistry::new(env, Arc::clone(&chain_store));
+
+    let mut databases = chain_store.derived_databases();
+    databases.extend(slash_registry.derived_databases());
+    verify::prune(env, &databases, report)
+}
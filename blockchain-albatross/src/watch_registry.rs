@@ -0,0 +1,303 @@
+use std::borrow::Cow;
+use std::io;
+use std::ops::Range;
+use std::sync::{Arc, Weak};
+
+use parking_lot::{Mutex, RwLock};
+
+use beserial::{Deserialize, Serialize};
+use database::{AsDatabaseBytes, Database, Environment, FromDatabaseValue, ReadTransaction, WriteTransaction};
+use database::cursor::ReadCursor;
+use hash::{Blake2bHash, Hash};
+use keys::Address;
+use transaction::Transaction as BlockchainTransaction;
+use utils::observer::{Notifier, Subscription, weak_listener};
+
+use crate::blockchain::Blockchain;
+use crate::confirmations::{ConfirmationPolicy, ConfirmationTracker, ConfirmedBlock};
+
+/// A confirmed transaction sent from or to a watched address.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchedTransaction {
+    /// The watched address.
+    pub address: Address,
+    pub block_hash: Blake2bHash,
+    pub block_number: u32,
+    pub timestamp: u64,
+    /// Index of the transaction in its block.
+    pub index: u16,
+    pub transaction: BlockchainTransaction,
+}
+
+impl AsDatabaseBytes for WatchedTransaction {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        let v = Serialize::serialize_to_vec(&self);
+        Cow::Owned(v)
+    }
+}
+
+impl FromDatabaseValue for WatchedTransaction {
+    fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
+        let mut cursor = io::Cursor::new(bytes);
+        Ok(Deserialize::deserialize(&mut cursor)?)
+    }
+}
+
+/// Key of the activity database. Serialized big-endian, so the activity of an address is sorted
+/// by block number and index.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct ActivityKey {
+    address: Address,
+    block_number: u32,
+    index: u16,
+}
+
+impl AsDatabaseBytes for ActivityKey {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        let v = Serialize::serialize_to_vec(&self);
+        Cow::Owned(v)
+    }
+}
+
+impl FromDatabaseValue for ActivityKey {
+    fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
+        let mut cursor = io::Cursor::new(bytes);
+        Ok(Deserialize::deserialize(&mut cursor)?)
+    }
+}
+
+/// Indexes the transactions of watched addresses, so wallets can follow their activity without
+/// a full address history index.
+///
+/// Addresses are only indexed from the block at which they were registered on, and only once
+/// their blocks are finalized, so the index never has to be rolled back. Listeners of `notifier`
+/// are notified of each indexed transaction. After a restart, indexing resumes after the last
+/// indexed block.
+///
+/// The activity of unwatched addresses is only hidden when they are unwatched, and removed later
+/// by `purge_unwatched`.
+pub struct WatchRegistry<'env> {
+    env: &'env Environment,
+    /// Maps watched addresses to the block number from which on they are indexed.
+    address_db: Database<'env>,
+    activity_db: Database<'env>,
+    /// Maps unwatched addresses to the last block that was indexed while they were watched.
+    unwatched_db: Database<'env>,
+    /// Holds the number of the last indexed block.
+    state_db: Database<'env>,
+    tracker: Arc<ConfirmationTracker<'env>>,
+    pub notifier: RwLock<Notifier<'env, WatchedTransaction>>,
+    subscription: Mutex<Option<Subscription<'env, ConfirmedBlock>>>,
+}
+
+impl<'env> WatchRegistry<'env> {
+    const ADDRESS_DB_NAME: &'static str = "WatchedAddresses";
+    const ACTIVITY_DB_NAME: &'static str = "WatchedActivity";
+    const UNWATCHED_DB_NAME: &'static str = "UnwatchedAddresses";
+    const STATE_DB_NAME: &'static str = "WatchedState";
+    const LAST_INDEXED_KEY: &'static str = "lastIndexed";
+
+    pub fn new(env: &'env Environment, blockchain: Arc<Blockchain<'env>>) -> Arc<Self> {
+        let address_db = env.open_database(Self::ADDRESS_DB_NAME.to_string());
+        let activity_db = env.open_database(Self::ACTIVITY_DB_NAME.to_string());
+        let unwatched_db = env.open_database(Self::UNWATCHED_DB_NAME.to_string());
+        let state_db = env.open_database(Self::STATE_DB_NAME.to_string());
+
+        // Resume after the last indexed block, so that no finalized block is missed.
+        let last_indexed: Option<u32> = ReadTransaction::new(env).get(&state_db, Self::LAST_INDEXED_KEY);
+        let tracker = match last_indexed {
+            Some(last_indexed) => ConfirmationTracker::new(blockchain, ConfirmationPolicy::Finalized, last_indexed),
+            None => ConfirmationTracker::from_head(blockchain, ConfirmationPolicy::Finalized),
+        };
+
+        let this = Arc::new(WatchRegistry {
+            env,
+            address_db,
+            activity_db,
+            unwatched_db,
+            state_db,
+            tracker,
+            notifier: RwLock::new(Notifier::new()),
+            subscription: Mutex::new(None),
+        });
+
+        let weak: Weak<Self> = Arc::downgrade(&this);
+        let subscription = this.tracker.notifier.write().subscribe(weak_listener(weak,
+            |this, block: &ConfirmedBlock| this.index_block(block)));
+        *this.subscription.lock() = Some(subscription);
+        // Catch up with the blocks that were finalized while the registry wasn't running.
+        this.tracker.process();
+
+        this
+    }
+
+    /// Starts watching `address` and returns the block number from which on its transactions are
+    /// indexed. Watching an address again doesn't change that block number.
+    pub fn watch(&self, address: &Address) -> u32 {
+        if let Some(since) = self.watched_since(address) {
+            return since;
+        }
+
+        let since = self.tracker.last_confirmed() + 1;
+        let mut txn = WriteTransaction::new(self.env);
+        txn.put(&self.address_db, address, &since);
+        txn.commit();
+        since
+    }
+
+    /// Stops watching `address`. Its indexed activity is no longer returned and is removed by the
+    /// next `purge_unwatched`. Returns whether the address was watched.
+    pub fn unwatch(&self, address: &Address) -> bool {
+        if self.watched_since(address).is_none() {
+            return false;
+        }
+
+        let mut txn = WriteTransaction::new(self.env);
+        let last_indexed: u32 = txn.get(&self.state_db, Self::LAST_INDEXED_KEY).unwrap_or(0);
+        txn.remove(&self.address_db, address);
+        txn.put(&self.unwatched_db, address, &last_indexed);
+        txn.commit();
+        true
+    }
+
+    /// The keys of the indexed transactions of `address` in blocks before `block_number`.
+    fn activity_range(address: &Address, block_number: u32) -> Range<ActivityKey> {
+        ActivityKey { address: address.clone(), block_number: 0, index: 0 }
+            ..ActivityKey { address: address.clone(), block_number, index: 0 }
+    }
+
+    /// The block number from which on `address` is indexed, if it is watched.
+    pub fn watched_since(&self, address: &Address) -> Option<u32> {
+        ReadTransaction::new(self.env).get(&self.address_db, address)
+    }
+
+    /// All watched addresses.
+    pub fn watched_addresses(&self) -> Vec<Address> {
+        let txn = ReadTransaction::new(self.env);
+        let mut cursor = txn.cursor(&self.address_db);
+        let mut addresses = Vec::new();
+        let mut entry: Option<(Address, u32)> = cursor.first();
+        while let Some((address, _)) = entry {
+            addresses.push(address);
+            entry = cursor.next();
+        }
+        addresses
+    }
+
+    /// The indexed transactions of `address` in blocks from `since` on, in chain order. Activity
+    /// from before the address was last watched isn't returned.
+    pub fn activity(&self, address: &Address, since: u32) -> Vec<WatchedTransaction> {
+        let watched_since = match self.watched_since(address) {
+            Some(watched_since) => watched_since,
+            None => return Vec::new(),
+        };
+
+        let txn = ReadTransaction::new(self.env);
+        let mut cursor = txn.cursor(&self.activity_db);
+        let start = ActivityKey { address: address.clone(), block_number: since.max(watched_since), index: 0 };
+
+        let mut transactions = Vec::new();
+        let mut entry: Option<(ActivityKey, WatchedTransaction)> = cursor.seek_range_key(&start);
+        while let Some((key, transaction)) = entry {
+            if &key.address != address {
+                break;
+            }
+            transactions.push(transaction);
+            entry = cursor.next();
+        }
+        transactions
+    }
+
+    /// Removes the indexed transactions of blocks before `block_number`. The addresses stay
+    /// watched.
+    pub fn prune(&self, txn: &mut WriteTransaction, block_number: u32) {
+        let mut watched = Vec::new();
+        {
+            let mut cursor = txn.cursor(&self.address_db);
+            let mut entry: Option<(Address, u32)> = cursor.first();
+            while let Some((address, _)) = entry {
+                watched.push(address);
+                entry = cursor.next();
+            }
+        }
+        for address in watched.iter() {
+            txn.remove_range(&self.activity_db, Self::activity_range(address, block_number));
+        }
+    }
+
+    /// Removes the indexed transactions of the addresses that were unwatched. Returns the number
+    /// of these addresses.
+    ///
+    /// If an address was watched again in the meantime, only its activity from before it was
+    /// unwatched is removed.
+    pub fn purge_unwatched(&self, txn: &mut WriteTransaction) -> usize {
+        let mut unwatched = Vec::new();
+        {
+            let mut cursor = txn.cursor(&self.unwatched_db);
+            let mut entry: Option<(Address, u32)> = cursor.first();
+            while let Some(address) = entry {
+                unwatched.push(address);
+                entry = cursor.next();
+            }
+        }
+        for (address, last_indexed) in unwatched.iter() {
+            txn.remove_range(&self.activity_db, Self::activity_range(address, last_indexed.saturating_add(1)));
+            txn.remove(&self.unwatched_db, address);
+        }
+        unwatched.len()
+    }
+
+    /// Block number of the last block that was indexed.
+    pub fn last_indexed(&self) -> u32 {
+        self.tracker.last_confirmed()
+    }
+
+    /// Indexes the transactions of `block` from or to watched addresses.
+    pub fn index_block(&self, block: &ConfirmedBlock) {
+        let block_number = block.block.block_number();
+        let transactions = block.block.transactions().map(Vec::as_slice).unwrap_or(&[]);
+
+        let mut watched = Vec::new();
+        {
+            let txn = ReadTransaction::new(self.env);
+            for (index, transaction) in transactions.iter().enumerate() {
+                let mut addresses = vec![&transaction.sender];
+                if transaction.recipient != transaction.sender {
+                    addresses.push(&transaction.recipient);
+                }
+
+                for address in addresses {
+                    let since: Option<u32> = txn.get(&self.address_db, address);
+                    if since.map(|since| block_number >= since).unwrap_or(false) {
+                        watched.push(WatchedTransaction {
+                            address: address.clone(),
+                            block_hash: block.hash.clone(),
+                            block_number,
+                            timestamp: block.block.timestamp(),
+                            index: index as u16,
+                            transaction: transaction.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut txn = WriteTransaction::new(self.env);
+        txn.put(&self.state_db, Self::LAST_INDEXED_KEY, &block_number);
+        for transaction in watched.iter() {
+            let key = ActivityKey {
+                address: transaction.address.clone(),
+                block_number,
+                index: transaction.index,
+            };
+            txn.put(&self.activity_db, &key, transaction);
+        }
+        txn.commit();
+
+        for transaction in watched {
+            debug!("Indexed transaction {} of watched address {}", transaction.transaction.hash::<Blake2bHash>().to_hex(),
+                   transaction.address.to_user_friendly_address());
+            self.notifier.read().notify(transaction);
+        }
+    }
+}
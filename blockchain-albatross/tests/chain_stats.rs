@@ -0,0 +1,59 @@
+use nimiq_block_albatross::Block;
+use nimiq_blockchain_albatross::blockchain::PushResult;
+use nimiq_database::volatile::VolatileEnvironment;
+use nimiq_primitives::coin::Coin;
+
+use crate::common::{block_producer, blockchain, produce_epoch};
+
+mod common;
+
+#[test]
+fn it_computes_stats_of_current_epoch() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    // No stats for the genesis epoch.
+    assert!(blockchain.epoch_stats(0).is_none());
+
+    let first_timestamp = 1565713920000;
+    for i in 1..=10u64 {
+        let block = producer.next_micro_block(vec![], first_timestamp + i * 2000, 0, vec![0x42], None);
+        assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+    }
+
+    let stats = blockchain.epoch_stats(1).unwrap();
+    assert_eq!(stats.epoch, 1);
+    assert_eq!(stats.block_count, 10);
+    assert_eq!(stats.end_timestamp, first_timestamp + 10 * 2000);
+    assert_eq!(stats.view_changes, 0);
+    assert_eq!(stats.transaction_count, 0);
+    assert_eq!(stats.fees, Coin::ZERO);
+    assert!(!stats.complete);
+
+    // Epochs that haven't started yet are skipped.
+    assert!(blockchain.epoch_stats(2).is_none());
+    let chain_stats = blockchain.chain_stats(0, 5);
+    assert_eq!(chain_stats.epochs.len(), 1);
+    assert_eq!(chain_stats.block_count(), 10);
+}
+
+#[test]
+fn it_prunes_cached_stats() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    produce_epoch(&producer, &blockchain);
+    let stats = blockchain.epoch_stats(1).unwrap();
+    assert!(stats.complete);
+    assert!(blockchain.stats_cache().contains(1));
+
+    blockchain.stats_cache().prune(1);
+    assert!(blockchain.stats_cache().contains(1));
+
+    blockchain.stats_cache().prune(2);
+    assert!(!blockchain.stats_cache().contains(1));
+    // Pruned stats are computed again.
+    assert_eq!(blockchain.epoch_stats(1), Some(stats));
+}
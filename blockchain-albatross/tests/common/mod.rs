@@ -0,0 +1,70 @@
+//! Fixtures shared by the blockchain tests. Each test crate only uses some of them.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use beserial::Deserialize;
+use nimiq_bls::{KeyPair, SecretKey};
+use nimiq_block_production_albatross::BlockProducer;
+use nimiq_block_albatross::{Block, MacroBlock, PbftProposal, PbftProofBuilder, PbftPrepareMessage, PbftCommitMessage, SignedPbftPrepareMessage, SignedPbftCommitMessage};
+use nimiq_block_albatross::signed::SignedMessage;
+use nimiq_blockchain_albatross::blockchain::{Blockchain, PushResult};
+use nimiq_blockchain_base::AbstractBlockchain;
+use nimiq_database::Environment;
+use nimiq_hash::{Blake2bHash, Hash};
+use nimiq_network_primitives::networks::NetworkId;
+use nimiq_primitives::policy;
+
+/// Secret key of validator. Tests run with `network-primitives/src/genesis/unit-albatross.toml`
+pub const SECRET_KEY: &'static str = "49ea68eb6b8afdf4ca4d4c0a0b295c76ca85225293693bc30e755476492b707f";
+
+pub fn validator_key() -> KeyPair {
+    KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap())
+}
+
+pub fn blockchain(env: &Environment) -> Arc<Blockchain> {
+    Arc::new(Blockchain::new(env, NetworkId::UnitAlbatross).unwrap())
+}
+
+/// A producer signing with the validator key.
+pub fn block_producer<'env>(blockchain: &Arc<Blockchain<'env>>) -> BlockProducer<'env> {
+    BlockProducer::new_without_mempool(Arc::clone(blockchain), validator_key())
+}
+
+/// Signs the proposal with all slots of the validator.
+pub fn sign_macro_block(proposal: PbftProposal) -> MacroBlock {
+    let keypair = validator_key();
+    let block_hash = proposal.header.hash::<Blake2bHash>();
+
+    let prepare = SignedPbftPrepareMessage::from_message(
+        PbftPrepareMessage { block_hash: block_hash.clone() },
+        &keypair.secret,
+        0);
+    let commit = SignedPbftCommitMessage::from_message(
+        PbftCommitMessage { block_hash: block_hash.clone() },
+        &keypair.secret,
+        0);
+
+    let mut pbft_proof = PbftProofBuilder::new();
+    pbft_proof.add_prepare_signature(&keypair.public, policy::SLOTS, &prepare);
+    pbft_proof.add_commit_signature(&keypair.public, policy::SLOTS, &commit);
+
+    MacroBlock {
+        header: proposal.header,
+        justification: Some(pbft_proof.build()),
+        extrinsics: None,
+    }
+}
+
+/// Pushes micro blocks up to the next macro block and the macro block itself.
+pub fn produce_epoch(producer: &BlockProducer, blockchain: &Arc<Blockchain>) {
+    let macro_block_number = policy::macro_block_after(blockchain.head_height() + 1);
+    for i in (blockchain.head_height() + 1)..macro_block_number {
+        let block = producer.next_micro_block(vec![], 1565713920000 + i as u64 * 2000, 0, vec![0x42], None);
+        assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+    }
+
+    let (proposal, _) = producer.next_macro_block_proposal(1565713920000 + macro_block_number as u64 * 2000, 0u32, None);
+    let block = sign_macro_block(proposal);
+    assert_eq!(blockchain.push_block(Block::Macro(block), true), Ok(PushResult::Extended));
+}
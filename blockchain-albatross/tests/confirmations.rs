@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use nimiq_block_albatross::Block;
+use nimiq_blockchain_albatross::blockchain::PushResult;
+use nimiq_blockchain_albatross::confirmations::{ConfirmationPolicy, ConfirmationTracker, ConfirmedBlock};
+use nimiq_database::volatile::VolatileEnvironment;
+
+use crate::common::{block_producer, blockchain};
+
+mod common;
+
+#[test]
+fn it_emits_blocks_at_confirmation_depth() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    let tracker = ConfirmationTracker::new(Arc::clone(&blockchain), ConfirmationPolicy::Depth(3), 0);
+    let finalized_tracker = ConfirmationTracker::new(Arc::clone(&blockchain), ConfirmationPolicy::Finalized, 0);
+
+    let confirmed = Arc::new(Mutex::new(Vec::new()));
+    let confirmed1 = Arc::clone(&confirmed);
+    tracker.notifier.write().register(move |block: &ConfirmedBlock| {
+        assert!(!block.finalized);
+        confirmed1.lock().push(block.block.block_number());
+    });
+    finalized_tracker.notifier.write().register(|_: &ConfirmedBlock| {
+        panic!("No block is finalized yet");
+    });
+
+    for i in 1..=5u64 {
+        let block = producer.next_micro_block(vec![], 1565713920000 + i * 2000, 0, vec![0x42], None);
+        assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+    }
+
+    assert_eq!(*confirmed.lock(), vec![1, 2]);
+    assert_eq!(tracker.last_confirmed(), 2);
+    assert_eq!(finalized_tracker.last_confirmed(), 0);
+}
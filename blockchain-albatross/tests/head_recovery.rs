@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use nimiq_block_albatross::Block;
+use nimiq_blockchain_albatross::blockchain::{Blockchain, PushResult};
+use nimiq_blockchain_albatross::chain_store::ChainStore;
+use nimiq_database::WriteTransaction;
+use nimiq_database::volatile::VolatileEnvironment;
+use nimiq_hash::Blake2bHash;
+use nimiq_network_primitives::networks::NetworkId;
+
+use crate::common::{block_producer, blockchain};
+
+mod common;
+
+fn push_micro_blocks(blockchain: &Arc<Blockchain>, count: u64) -> Vec<Block> {
+    let producer = block_producer(blockchain);
+
+    let mut blocks = Vec::new();
+    for i in 1..=count {
+        let block = Block::Micro(producer.next_micro_block(vec![], 1565713920000 + i * 2000, 0, vec![0x42], None));
+        assert_eq!(blockchain.push(block.clone()), Ok(PushResult::Extended));
+        blocks.push(block);
+    }
+    blocks
+}
+
+#[test]
+fn it_keeps_a_head_that_matches_the_state() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    let blocks = push_micro_blocks(&blockchain, 3);
+    drop(blockchain);
+
+    let chain_store = ChainStore::new(&env);
+    assert_eq!(chain_store.recover_head(blocks[2].state_root()), None);
+    assert_eq!(chain_store.get_head(None), Some(blocks[2].hash()));
+}
+
+#[test]
+fn it_recovers_from_a_head_without_its_block() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    push_micro_blocks(&blockchain, 3);
+    let head_hash = blockchain.head_hash();
+    drop(blockchain);
+
+    // Lose the head block, as if only the head pointer had reached the disk.
+    let chain_store = ChainStore::new(&env);
+    let mut txn = WriteTransaction::new(&env);
+    chain_store.set_head(&mut txn, &Blake2bHash::from([1u8; Blake2bHash::SIZE]));
+    txn.commit();
+
+    let blockchain = Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap();
+    assert_eq!(blockchain.head_hash(), head_hash);
+    assert_eq!(chain_store.get_head(None), Some(head_hash));
+}
+
+#[test]
+fn it_recovers_from_a_torn_head() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    let blocks = push_micro_blocks(&blockchain, 3);
+    let state_root = blocks[2].state_root().clone();
+    drop(blockchain);
+
+    // Lose the head block, as if only the head pointer had reached the disk. Empty micro blocks
+    // don't change the accounts, so the previous block matches them.
+    let chain_store = ChainStore::new(&env);
+    let mut txn = WriteTransaction::new(&env);
+    chain_store.remove_chain_info(&mut txn, &blocks[2].hash(), 3);
+    txn.commit();
+
+    let blockchain = Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap();
+    assert_eq!(blockchain.head_hash(), blocks[1].hash());
+    assert_eq!(blockchain.block_number(), 2);
+
+    // Nothing is left to recover, and a state that no block has can't be recovered.
+    assert_eq!(chain_store.recover_head(&state_root), None);
+    assert_eq!(chain_store.recover_head(&Blake2bHash::from([1u8; Blake2bHash::SIZE])), None);
+    assert_eq!(chain_store.get_head(None), Some(blocks[1].hash()));
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use tempdir::TempDir;
+
+use nimiq_block_albatross::Block;
+use nimiq_blockchain_albatross::blockchain::Blockchain;
+use nimiq_blockchain_albatross::history_shards::{HistoryShardError, HistoryShards};
+use nimiq_database::volatile::VolatileEnvironment;
+use nimiq_network_primitives::networks::NetworkId;
+use nimiq_primitives::policy;
+
+use crate::common::{block_producer, produce_epoch};
+
+mod common;
+
+fn has_body(block: Option<Block>) -> bool {
+    match block {
+        Some(Block::Micro(block)) => block.extrinsics.is_some(),
+        Some(Block::Macro(block)) => block.extrinsics.is_some(),
+        None => false,
+    }
+}
+
+#[test]
+fn it_looks_up_archived_blocks_in_their_shard() {
+    let dir = TempDir::new("history_shards").unwrap();
+    let env = VolatileEnvironment::new(10).unwrap();
+    let history = HistoryShards::new(dir.path(), 10 * 1024 * 1024);
+    let blockchain = Arc::new(Blockchain::with_history(&env, NetworkId::UnitAlbatross, history).unwrap());
+    let producer = block_producer(&blockchain);
+
+    match blockchain.archive_epoch(1) {
+        Err(HistoryShardError::EpochNotFinalized(1)) => {},
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    produce_epoch(&producer, &blockchain);
+    let block = blockchain.get_block_at(5, true).unwrap();
+    let hash = block.hash();
+
+    assert_eq!(blockchain.archive_epoch(1).unwrap(), policy::EPOCH_LENGTH as usize);
+    assert!(dir.path().join("epoch-1").exists());
+    // The log is removed once both environments have committed.
+    assert!(!dir.path().join("epoch-1/archive.log").exists());
+    assert_eq!(blockchain.get_block_at(5, true), Some(block.clone()));
+    assert_eq!(blockchain.get_block(&hash, false, true), Some(block));
+    assert!(has_body(blockchain.get_block_at(policy::macro_block_of(1), true)));
+
+    // Nothing is left to archive.
+    assert_eq!(blockchain.archive_epoch(1).unwrap(), 0);
+
+    assert_eq!(blockchain.delete_epoch(1).unwrap(), true);
+    assert!(!dir.path().join("epoch-1").exists());
+    assert!(!has_body(blockchain.get_block_at(5, true)));
+    assert!(blockchain.get_block_at(5, false).is_some());
+}
@@ -0,0 +1,78 @@
+use nimiq_block_production_albatross::BlockProducer;
+use nimiq_block_albatross::MicroBlock;
+use nimiq_blockchain_albatross::chain_store::ChainStore;
+use nimiq_blockchain_albatross::htlc_settlements::HtlcSettlement;
+use nimiq_database::WriteTransaction;
+use nimiq_database::volatile::VolatileEnvironment;
+use nimiq_hash::{Blake2bHash, Hash};
+use nimiq_keys::Address;
+use nimiq_network_primitives::networks::NetworkId;
+use nimiq_primitives::account::AccountType;
+use nimiq_primitives::coin::Coin;
+use nimiq_transaction::Transaction;
+
+use crate::common::{block_producer, blockchain};
+
+mod common;
+
+fn settlement(htlc: &Address, recipient: &Address) -> Transaction {
+    Transaction::new_extended(htlc.clone(), AccountType::HTLC, recipient.clone(), AccountType::Basic,
+                              Coin::from_u64_unchecked(100), Coin::ZERO, vec![], 1, NetworkId::UnitAlbatross)
+}
+
+fn micro_block(producer: &BlockProducer, timestamp: u64, transactions: Vec<Transaction>) -> MicroBlock {
+    let mut block = producer.next_micro_block(vec![], timestamp, 0, vec![0x42], None);
+    block.extrinsics.as_mut().unwrap().transactions = transactions;
+    block
+}
+
+#[test]
+fn it_indexes_settlements_by_htlc() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    let store_env = VolatileEnvironment::new(10).unwrap();
+    let chain_store = ChainStore::new(&store_env);
+
+    let htlc = Address::from([1u8; Address::SIZE]);
+    let other_htlc = Address::from([2u8; Address::SIZE]);
+    let recipient = Address::from([3u8; Address::SIZE]);
+    let basic = Transaction::new_basic(recipient.clone(), htlc.clone(), Coin::from_u64_unchecked(100), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+
+    let block1 = micro_block(&producer, 1565713920000, vec![basic, settlement(&htlc, &recipient), settlement(&other_htlc, &recipient)]);
+    let block2 = micro_block(&producer, 1565713922000, vec![settlement(&htlc, &htlc)]);
+    let hash1: Blake2bHash = block1.header.hash();
+    let hash2: Blake2bHash = block2.header.hash();
+
+    let mut txn = WriteTransaction::new(&store_env);
+    chain_store.put_htlc_settlements(&mut txn, &hash1, &block1);
+    chain_store.put_htlc_settlements(&mut txn, &hash2, &block2);
+    txn.commit();
+
+    // Only transactions sent by the HTLC are settlements, and they are returned in chain order.
+    let settlements = chain_store.get_htlc_settlements(&htlc, None);
+    assert_eq!(settlements.iter().map(|s| (s.block_hash.clone(), s.index)).collect::<Vec<_>>(),
+               vec![(hash1.clone(), 1), (hash2.clone(), 0)]);
+    assert_eq!(settlements[0], HtlcSettlement::from_block(&hash1, &block1)[0]);
+    assert_eq!(chain_store.get_htlc_settlements(&other_htlc, None).len(), 1);
+    assert!(chain_store.get_htlc_settlements(&recipient, None).is_empty());
+
+    // Reverting a block removes its settlements.
+    let mut txn = WriteTransaction::new(&store_env);
+    chain_store.remove_htlc_settlements(&mut txn, &block2);
+    txn.commit();
+    assert_eq!(chain_store.get_htlc_settlements(&htlc, None).len(), 1);
+    assert_eq!(chain_store.get_htlc_settlements(&other_htlc, None).len(), 1);
+}
+
+#[test]
+fn it_has_no_settlements_for_blocks_without_htlcs() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    let block = producer.next_micro_block(vec![], 1565713920000, 0, vec![0x42], None);
+    assert!(HtlcSettlement::from_block(&block.header.hash(), &block).is_empty());
+    assert!(blockchain.get_htlc_settlements(&Address::from([1u8; Address::SIZE])).is_empty());
+}
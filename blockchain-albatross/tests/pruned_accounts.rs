@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use nimiq_block_albatross::Block;
+use nimiq_blockchain_albatross::blockchain::PushResult;
+use nimiq_blockchain_albatross::pruned_accounts::{PrunedAccounts, PrunedAccountsTracker};
+use nimiq_database::volatile::VolatileEnvironment;
+
+use crate::common::{block_producer, blockchain};
+
+mod common;
+
+#[test]
+fn it_only_knows_pruned_accounts_of_main_chain_blocks() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    let tracker = PrunedAccountsTracker::new(Arc::clone(&blockchain));
+    tracker.notifier.write().register(|_: &PrunedAccounts| {
+        panic!("Blocks without transactions don't prune accounts");
+    });
+
+    for i in 1..=2u64 {
+        let block = producer.next_micro_block(vec![], 1565713920000 + i * 2000, 0, vec![0x42], None);
+        assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+    }
+
+    assert_eq!(blockchain.get_pruned_accounts(1), Some(vec![]));
+    assert_eq!(blockchain.get_pruned_accounts(2), Some(vec![]));
+    assert_eq!(blockchain.get_pruned_accounts(3), None);
+
+    // The tracker stops listening once it is dropped.
+    drop(tracker);
+    let block = producer.next_micro_block(vec![], 1565713920000 + 3 * 2000, 0, vec![0x42], None);
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+}
@@ -0,0 +1,39 @@
+use nimiq_block_albatross::Block;
+use nimiq_blockchain_albatross::blockchain::{Blockchain, PushResult};
+use nimiq_database::volatile::VolatileEnvironment;
+use nimiq_network_primitives::networks::NetworkId;
+
+use crate::common::{block_producer, blockchain};
+
+mod common;
+
+#[test]
+fn it_follows_the_head_written_by_another_instance() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let primary = blockchain(&env);
+    let replica = Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap();
+    let producer = block_producer(&primary);
+
+    assert_eq!(replica.reload(), Ok(false));
+    for i in 1..=3u64 {
+        let block = producer.next_micro_block(vec![], 1565713920000 + i * 2000, 0, vec![0x42], None);
+        assert_eq!(primary.push(Block::Micro(block)), Ok(PushResult::Extended));
+    }
+    assert_eq!(replica.block_number(), 0);
+
+    assert_eq!(replica.reload(), Ok(true));
+    assert_eq!(replica.block_number(), 3);
+    assert_eq!(replica.head_hash(), primary.head_hash());
+    assert_eq!(replica.macro_head_hash(), primary.macro_head_hash());
+    assert_eq!(replica.reload(), Ok(false));
+
+    // The blocks since the last reload are applied to the loaded state.
+    for i in 4..=5u64 {
+        let block = producer.next_micro_block(vec![], 1565713920000 + i * 2000, 0, vec![0x42], None);
+        assert_eq!(primary.push(Block::Micro(block)), Ok(PushResult::Extended));
+    }
+    assert_eq!(replica.reload(), Ok(true));
+    assert_eq!(replica.block_number(), 5);
+    assert_eq!(replica.head_hash(), primary.head_hash());
+    assert_eq!(replica.reload(), Ok(false));
+}
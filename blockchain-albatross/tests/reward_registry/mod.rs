@@ -1 +1,2 @@
+mod reward_log;
 mod slashed_slots;
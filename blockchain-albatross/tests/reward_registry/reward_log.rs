@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use beserial::{Deserialize, Serialize};
+use nimiq_blockchain_albatross::chain_store::ChainStore;
+use nimiq_blockchain_albatross::reward_registry::{EpochRewards, SlashRegistry};
+use nimiq_database::WriteTransaction;
+use nimiq_database::volatile::VolatileEnvironment;
+use nimiq_keys::Address;
+use nimiq_primitives::coin::Coin;
+
+fn epoch_rewards() -> EpochRewards {
+    let mut rewards = EpochRewards::new(3, Coin::from_u64_unchecked(1001), 4, 1, Coin::from_u64_unchecked(250));
+    rewards.add_rejected(&Address::from([7u8; 20]), Coin::from_u64_unchecked(250));
+    rewards.redistributed_reward = Coin::from_u64_unchecked(83);
+    rewards.add_reward(&Address::from([3u8; 20]), Coin::from_u64_unchecked(334));
+    rewards.add_reward(&Address::from([5u8; 20]), Coin::from_u64_unchecked(334));
+    rewards.add_reward(&Address::from([3u8; 20]), Coin::from_u64_unchecked(333));
+    rewards
+}
+
+#[test]
+fn it_groups_rewards_by_recipient() {
+    let rewards = epoch_rewards();
+
+    assert_eq!(rewards.rewards.len(), 2);
+    assert_eq!(rewards.total_value(), rewards.reward_pot);
+
+    let reward = rewards.reward_of(&Address::from([3u8; 20])).unwrap();
+    assert_eq!(reward.num_slots, 2);
+    assert_eq!(reward.value, Coin::from_u64_unchecked(667));
+    assert_eq!(rewards.rewards[1].address, Address::from([5u8; 20]));
+
+    assert_eq!(rewards.rejected.len(), 1);
+    assert!(rewards.reward_of(&Address::from([7u8; 20])).is_none());
+}
+
+#[test]
+fn it_can_serialize_epoch_rewards() {
+    let rewards = epoch_rewards();
+    let bytes = rewards.serialize_to_vec();
+    assert_eq!(EpochRewards::deserialize_from_vec(&bytes).unwrap(), rewards);
+}
+
+#[test]
+fn it_stores_epoch_rewards() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let chain_store = Arc::new(ChainStore::new(&env));
+    let registry = SlashRegistry::new(&env, chain_store);
+    assert!(registry.epoch_rewards(3, None).is_none());
+
+    let rewards = epoch_rewards();
+    let mut txn = WriteTransaction::new(&env);
+    registry.commit_epoch_rewards(&mut txn, &rewards);
+    txn.commit();
+
+    assert_eq!(registry.epoch_rewards(3, None), Some(rewards));
+    assert!(registry.epoch_rewards(4, None).is_none());
+}
+
+#[test]
+fn it_prunes_epoch_rewards() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let chain_store = Arc::new(ChainStore::new(&env));
+    let registry = SlashRegistry::new(&env, chain_store);
+
+    let rewards = epoch_rewards();
+    let mut txn = WriteTransaction::new(&env);
+    registry.commit_epoch_rewards(&mut txn, &rewards);
+    txn.commit();
+
+    let mut txn = WriteTransaction::new(&env);
+    registry.prune_epoch_rewards(&mut txn, 3);
+    txn.commit();
+    assert_eq!(registry.epoch_rewards(3, None), Some(rewards));
+
+    let mut txn = WriteTransaction::new(&env);
+    registry.prune_epoch_rewards(&mut txn, 4);
+    txn.commit();
+    assert!(registry.epoch_rewards(3, None).is_none());
+}
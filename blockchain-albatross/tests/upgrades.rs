@@ -0,0 +1,42 @@
+use nimiq_block_albatross::{Block, BlockError};
+use nimiq_blockchain_albatross::blockchain::{PushError, PushResult};
+use nimiq_blockchain_base::upgrades::{Feature, Upgrades};
+use nimiq_database::volatile::VolatileEnvironment;
+use nimiq_network_primitives::networks::NetworkId;
+
+use crate::common::{block_producer, blockchain};
+
+mod common;
+
+#[test]
+fn it_looks_up_activations() {
+    let upgrades = Upgrades::new(NetworkId::UnitAlbatross);
+    assert_eq!(upgrades.activation_height(Feature::AlbatrossV1), Some(0));
+    assert!(upgrades.is_active(Feature::AlbatrossV1, 0));
+    assert_eq!(upgrades.block_version(0), Some(1));
+
+    let upgrades = Upgrades::new(NetworkId::Main);
+    assert!(!upgrades.is_active(Feature::AlbatrossV1, 1000));
+    assert_eq!(upgrades.block_version(1000), None);
+
+    let upgrades = Upgrades::with_activations(vec![(Feature::AlbatrossV1, 100)]);
+    assert!(!upgrades.is_active(Feature::AlbatrossV1, 99));
+    assert!(upgrades.is_active(Feature::AlbatrossV1, 100));
+    assert_eq!(upgrades.block_version(99), None);
+    assert_eq!(upgrades.block_version(100), Some(1));
+}
+
+#[test]
+fn it_rejects_blocks_with_inactive_versions() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    let mut block = producer.next_micro_block(vec![], 1565713922000, 0, vec![0x42], None);
+    assert_eq!(block.header.version, 1);
+    block.header.version = 2;
+    assert_eq!(blockchain.push(Block::Micro(block)), Err(PushError::InvalidBlock(BlockError::InvalidVersion)));
+
+    let block = producer.next_micro_block(vec![], 1565713922000, 0, vec![0x42], None);
+    assert_eq!(blockchain.push(Block::Micro(block)), Ok(PushResult::Extended));
+}
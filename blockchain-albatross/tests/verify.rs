@@ -0,0 +1,55 @@
+use nimiq_blockchain_albatross::blockchain::Blockchain;
+use nimiq_blockchain_albatross::verify::{repair_databases, verify_databases};
+use nimiq_database::{DatabaseFlags, WriteTransaction};
+use nimiq_database::verify::IssueKind;
+use nimiq_database::volatile::VolatileEnvironment;
+use nimiq_hash::Blake2bHash;
+use nimiq_network_primitives::networks::NetworkId;
+
+#[test]
+fn it_finds_and_removes_orphaned_index_entries() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap();
+
+    let report = verify_databases(&env);
+    assert!(report.is_ok(), "Unexpected issues: {:?}", report.issues);
+    assert!(report.checked > 0);
+
+    // An index entry of a block that was never stored.
+    let height_idx = env.open_database_with_flags("HeightIdx".to_string(),
+                                                  DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_FIXED_SIZE_VALUES);
+    let mut txn = WriteTransaction::new(&env);
+    txn.put(&height_idx, &42u32, &Blake2bHash::from([1u8; 32]));
+    txn.commit();
+
+    let report = verify_databases(&env);
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].kind, IssueKind::Orphaned);
+    assert_eq!(report.issues[0].database, "HeightIdx");
+
+    assert_eq!(repair_databases(&env, &report), 1);
+    assert!(verify_databases(&env).is_ok());
+    assert_eq!(blockchain.block_number(), 0);
+}
+
+#[test]
+fn it_only_reports_issues_in_chain_data() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap();
+
+    // A block body without chain info, and a chain info that can't be decoded.
+    let block_db = env.open_database("Block".to_string());
+    let chain_db = env.open_database("ChainData".to_string());
+    let mut txn = WriteTransaction::new(&env);
+    txn.put_reserve(&block_db, &Blake2bHash::from([2u8; 32]), &blockchain.head().clone());
+    txn.put(&chain_db, &Blake2bHash::from([3u8; 32]), &[0u8, 1, 2][..]);
+    txn.commit();
+
+    let report = verify_databases(&env);
+    assert_eq!(report.issues.len(), 2);
+    assert!(report.issues.iter().any(|issue| issue.database == "Block" && issue.kind == IssueKind::Orphaned));
+    assert!(report.issues.iter().any(|issue| issue.database == "ChainData" && issue.kind == IssueKind::InvalidValue));
+
+    assert_eq!(repair_databases(&env, &report), 0);
+    assert_eq!(verify_databases(&env).issues.len(), 2);
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use nimiq_block_production_albatross::BlockProducer;
+use nimiq_block_albatross::Block;
+use nimiq_blockchain_albatross::confirmations::ConfirmedBlock;
+use nimiq_blockchain_albatross::watch_registry::{WatchRegistry, WatchedTransaction};
+use nimiq_database::WriteTransaction;
+use nimiq_database::volatile::VolatileEnvironment;
+use nimiq_keys::Address;
+use nimiq_network_primitives::networks::NetworkId;
+use nimiq_primitives::coin::Coin;
+use nimiq_transaction::Transaction;
+
+use crate::common::{block_producer, blockchain};
+
+mod common;
+
+fn confirmed_block(producer: &BlockProducer, transactions: Vec<Transaction>) -> ConfirmedBlock {
+    let mut block = Block::Micro(producer.next_micro_block(vec![], 1565713920000, 0, vec![0x42], None));
+    *block.transactions_mut().unwrap() = transactions;
+    ConfirmedBlock {
+        hash: block.hash(),
+        block,
+        finalized: true,
+    }
+}
+
+#[test]
+fn it_indexes_watched_addresses() {
+    let env = VolatileEnvironment::new(16).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    let registry = WatchRegistry::new(&env, Arc::clone(&blockchain));
+    let notified = Arc::new(Mutex::new(Vec::new()));
+    let notified1 = Arc::clone(&notified);
+    registry.notifier.write().register(move |tx: &WatchedTransaction| notified1.lock().push(tx.clone()));
+
+    let watched = Address::from([1u8; 20]);
+    let other = Address::from([2u8; 20]);
+    assert_eq!(registry.watch(&watched), 1);
+    assert_eq!(registry.watch(&watched), 1);
+    assert_eq!(registry.watched_addresses(), vec![watched.clone()]);
+
+    let incoming = Transaction::new_basic(other.clone(), watched.clone(), Coin::from_u64_unchecked(10), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    let unrelated = Transaction::new_basic(other.clone(), other.clone(), Coin::from_u64_unchecked(20), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    let outgoing = Transaction::new_basic(watched.clone(), other.clone(), Coin::from_u64_unchecked(30), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    registry.index_block(&confirmed_block(&producer, vec![incoming.clone(), unrelated, outgoing.clone()]));
+
+    let activity = registry.activity(&watched, 0);
+    assert_eq!(activity.len(), 2);
+    assert_eq!(activity[0].transaction, incoming);
+    assert_eq!(activity[0].index, 0);
+    assert_eq!(activity[1].transaction, outgoing);
+    assert_eq!(activity[1].index, 2);
+    assert_eq!(*notified.lock(), activity);
+    assert!(registry.activity(&watched, 2).is_empty());
+    assert!(registry.activity(&other, 0).is_empty());
+
+    assert!(registry.unwatch(&watched));
+    assert!(!registry.unwatch(&watched));
+    assert!(registry.activity(&watched, 0).is_empty());
+    assert!(registry.watched_addresses().is_empty());
+}
+
+#[test]
+fn it_resumes_after_the_last_indexed_block() {
+    let env = VolatileEnvironment::new(16).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    let registry = WatchRegistry::new(&env, Arc::clone(&blockchain));
+    assert_eq!(registry.last_indexed(), 0);
+    // Blocks without watched transactions are recorded as indexed too.
+    registry.index_block(&confirmed_block(&producer, vec![]));
+    drop(registry);
+
+    let registry = WatchRegistry::new(&env, Arc::clone(&blockchain));
+    assert_eq!(registry.last_indexed(), 1);
+    assert_eq!(registry.watch(&Address::from([1u8; 20])), 2);
+}
+
+#[test]
+fn it_prunes_activity_before_a_block() {
+    let env = VolatileEnvironment::new(16).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    let registry = WatchRegistry::new(&env, Arc::clone(&blockchain));
+    let watched = Address::from([1u8; 20]);
+    let other = Address::from([2u8; 20]);
+    registry.watch(&watched);
+    registry.watch(&other);
+
+    let transaction = Transaction::new_basic(other.clone(), watched.clone(), Coin::from_u64_unchecked(10), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    registry.index_block(&confirmed_block(&producer, vec![transaction]));
+
+    // The block is at height 1.
+    let mut txn = WriteTransaction::new(&env);
+    registry.prune(&mut txn, 1);
+    txn.commit();
+    assert_eq!(registry.activity(&watched, 0).len(), 1);
+    assert_eq!(registry.activity(&other, 0).len(), 1);
+
+    let mut txn = WriteTransaction::new(&env);
+    registry.prune(&mut txn, 2);
+    txn.commit();
+    assert!(registry.activity(&watched, 0).is_empty());
+    assert!(registry.activity(&other, 0).is_empty());
+    assert_eq!(registry.watched_addresses().len(), 2);
+}
+
+#[test]
+fn it_purges_the_activity_of_unwatched_addresses() {
+    let env = VolatileEnvironment::new(16).unwrap();
+    let blockchain = blockchain(&env);
+    let producer = block_producer(&blockchain);
+
+    let registry = WatchRegistry::new(&env, Arc::clone(&blockchain));
+    let unwatched = Address::from([1u8; 20]);
+    let watched = Address::from([2u8; 20]);
+    registry.watch(&unwatched);
+    registry.watch(&watched);
+
+    let transaction = Transaction::new_basic(watched.clone(), unwatched.clone(), Coin::from_u64_unchecked(10), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    registry.index_block(&confirmed_block(&producer, vec![transaction]));
+
+    assert!(registry.unwatch(&unwatched));
+    assert!(registry.activity(&unwatched, 0).is_empty());
+
+    let mut txn = WriteTransaction::new(&env);
+    assert_eq!(registry.purge_unwatched(&mut txn), 1);
+    txn.commit();
+
+    // Watching the address again doesn't bring back its purged activity.
+    registry.watch(&unwatched);
+    assert!(registry.activity(&unwatched, 0).is_empty());
+    assert_eq!(registry.activity(&watched, 0).len(), 1);
+
+    let mut txn = WriteTransaction::new(&env);
+    assert_eq!(registry.purge_unwatched(&mut txn), 0);
+    txn.commit();
+}
@@ -24,13 +24,15 @@ use hash::Blake2bHash;
 use keys::Address;
 use nimiq_network_primitives::time::NetworkTime;
 use primitives::networks::NetworkId;
+use primitives::policy;
 use transaction::{TransactionReceipt, TransactionsProof};
 use tree_primitives::accounts_proof::AccountsProof;
 use tree_primitives::accounts_tree_chunk::AccountsTreeChunk;
-use utils::observer::{Listener, ListenerHandle};
+use utils::observer::{Listener, Subscription};
 
 #[cfg(feature = "metrics")]
 pub mod chain_metrics;
+pub mod upgrades;
 
 pub trait AbstractBlockchain<'env>: Sized + Send + Sync {
     type Block: Block;
@@ -93,7 +95,8 @@ pub trait AbstractBlockchain<'env>: Sized + Send + Sync {
 
     /* Required by Mempool */
 
-    fn register_listener<T: Listener<BlockchainEvent<Self::Block>> + 'env>(&self, listener: T) -> ListenerHandle;
+    /// Notifies `listener` of blockchain events as long as the returned subscription is kept.
+    fn subscribe_listener<T: Listener<BlockchainEvent<Self::Block>> + 'env>(&self, listener: T) -> Subscription<'env, BlockchainEvent<Self::Block>>;
 
     fn lock(&self) -> MutexGuard<()>;
 
@@ -101,6 +104,11 @@ pub trait AbstractBlockchain<'env>: Sized + Send + Sync {
 
     fn contains_tx_in_validity_window(&self, tx_hash: &Blake2bHash) -> bool;
 
+    /// Number of blocks a transaction is valid on this chain.
+    fn transaction_validity_window(&self) -> u32 {
+        policy::transaction_validity_window(self.network_id())
+    }
+
 
     /* Required by AccountsChunkCache */
     // TODO Why do we need this? Remove if possible.
@@ -126,6 +134,8 @@ pub enum BlockchainError {
     InconsistentState,
     #[fail(display = "No network for: {:?}", _0)]
     NoNetwork(NetworkId),
+    #[fail(display = "{}", _0)]
+    MigrationFailed(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
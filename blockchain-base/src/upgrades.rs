@@ -0,0 +1,69 @@
+use primitives::networks::NetworkId;
+
+/// Changes to the consensus rules. They are shipped in a release before they are activated, so a
+/// hard fork can be coordinated by configuring its activation height.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// The initial Albatross rules, using block version 1.
+    AlbatrossV1,
+}
+
+impl Feature {
+    /// The block version blocks must use once the feature is active, if the feature introduces
+    /// a new version.
+    pub fn block_version(self) -> Option<u16> {
+        match self {
+            Feature::AlbatrossV1 => Some(1),
+        }
+    }
+}
+
+/// The block numbers at which features are activated on each network. Features that aren't
+/// listed for a network are never active on it.
+const ACTIVATIONS: &[(NetworkId, Feature, u32)] = &[
+    (NetworkId::TestAlbatross, Feature::AlbatrossV1, 0),
+    (NetworkId::DevAlbatross, Feature::AlbatrossV1, 0),
+    (NetworkId::UnitAlbatross, Feature::AlbatrossV1, 0),
+];
+
+/// The features activated on a network and their activation heights.
+#[derive(Clone, Debug)]
+pub struct Upgrades {
+    activations: Vec<(Feature, u32)>,
+}
+
+impl Upgrades {
+    pub fn new(network_id: NetworkId) -> Self {
+        Self::with_activations(ACTIVATIONS.iter()
+            .filter(|(network, _, _)| *network == network_id)
+            .map(|(_, feature, block_number)| (*feature, *block_number))
+            .collect())
+    }
+
+    pub fn with_activations(activations: Vec<(Feature, u32)>) -> Self {
+        Upgrades { activations }
+    }
+
+    /// The block number from which on `feature` is active.
+    pub fn activation_height(&self, feature: Feature) -> Option<u32> {
+        self.activations.iter()
+            .find(|(f, _)| *f == feature)
+            .map(|(_, block_number)| *block_number)
+    }
+
+    pub fn is_active(&self, feature: Feature, block_number: u32) -> bool {
+        self.activation_height(feature)
+            .map(|activation_height| block_number >= activation_height)
+            .unwrap_or(false)
+    }
+
+    /// The version a block at `block_number` must have, i.e. the version of the most recently
+    /// activated feature that introduces one.
+    pub fn block_version(&self, block_number: u32) -> Option<u16> {
+        self.activations.iter()
+            .filter(|(_, activation_height)| block_number >= *activation_height)
+            .filter_map(|(feature, activation_height)| feature.block_version().map(|version| (*activation_height, version)))
+            .max()
+            .map(|(_, version)| version)
+    }
+}
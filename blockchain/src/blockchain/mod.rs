@@ -21,7 +21,7 @@ use primitives::policy;
 use transaction::{TransactionReceipt, TransactionsProof};
 use tree_primitives::accounts_proof::AccountsProof;
 use tree_primitives::accounts_tree_chunk::AccountsTreeChunk;
-use utils::observer::{Listener, ListenerHandle, Notifier};
+use utils::observer::{Listener, Notifier, Subscription};
 
 use crate::chain_info::ChainInfo;
 use crate::chain_store::ChainStore;
@@ -787,8 +787,8 @@ impl<'env> AbstractBlockchain<'env> for Blockchain<'env> {
         self.get_transaction_receipts_by_address(address, sender_limit, recipient_limit)
     }
 
-    fn register_listener<T: Listener<BlockchainEvent> + 'env>(&self, listener: T) -> ListenerHandle {
-        self.notifier.write().register(listener)
+    fn subscribe_listener<T: Listener<BlockchainEvent> + 'env>(&self, listener: T) -> Subscription<'env, BlockchainEvent> {
+        self.notifier.write().subscribe(listener)
     }
 
     fn lock(&self) -> MutexGuard<()> {
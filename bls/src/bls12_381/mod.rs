@@ -5,8 +5,6 @@ use group::{CurveAffine, CurveProjective, EncodedPoint, GroupDecodingError};
 use pairing::bls12_381::{Bls12, G1Compressed, G2Compressed};
 use pairing::Engine;
 
-#[cfg(feature = "beserial")]
-use beserial::Serialize;
 use hash::Hash;
 
 use super::{
@@ -32,17 +30,18 @@ impl SecretKey {
     pub const SIZE: usize = 32;
 }
 
-#[cfg(feature = "beserial")]
+// Never print the secret key, only the public key.
 impl fmt::Debug for KeyPair {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        f.write_str(&::hex::encode(self.serialize_to_vec()))
+        f.debug_struct("KeyPair")
+            .field("public", &self.public)
+            .finish()
     }
 }
 
-#[cfg(feature = "beserial")]
 impl fmt::Debug for SecretKey {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        f.write_str(&::hex::encode(self.serialize_to_vec()))
+        f.write_str("SecretKey")
     }
 }
 
@@ -3,13 +3,17 @@
 extern crate nimiq_hash as hash;
 extern crate hex;
 
-use ff::Field;
+use core::ptr;
+use core::sync::atomic::{self, Ordering};
+
+use ff::{Field, PrimeField};
 use group::{CurveAffine, CurveProjective};
 use hashbrown::HashSet;
 use pairing::Engine;
 use rand::{Rng, SeedableRng};
 use rand04_compat::RngExt;
 use rand_chacha::ChaChaRng;
+use subtle::ConstantTimeEq;
 
 use hash::{Hash, Blake2bHash};
 
@@ -20,6 +24,10 @@ pub mod serialization;
 /// Hash used for signatures
 pub type SigHash = Blake2bHash;
 
+/// The curve arithmetic is portable Rust without CPU-specific code paths. Only hashing messages
+/// benefits from the optimized Blake2b implementations.
+pub const IMPLEMENTATION: &str = "portable";
+
 /// Map hash to point in G1
 pub(crate) fn hash_to_g1<E: Engine>(h: SigHash) -> E::G1 {
     ChaChaRng::from_seed(h.into()).gen04()
@@ -37,7 +45,8 @@ impl<E: Engine> PartialEq for Signature<E> {
     }
 }
 
-#[derive(Clone, Copy)]
+/// The secret scalar is overwritten with zero when dropped, so the key isn't `Copy`.
+#[derive(Clone)]
 pub struct SecretKey<E: Engine> {
     pub(crate) x: E::Fr,
 }
@@ -45,7 +54,18 @@ pub struct SecretKey<E: Engine> {
 impl<E: Engine> Eq for SecretKey<E>{}
 impl<E: Engine> PartialEq for SecretKey<E> {
     fn eq(&self, other: &Self) -> bool {
-        self.x.eq(&other.x)
+        // Compare in constant time.
+        let lhs = self.x.into_repr();
+        let rhs = other.x.into_repr();
+        bool::from(lhs.as_ref().ct_eq(rhs.as_ref()))
+    }
+}
+
+impl<E: Engine> Drop for SecretKey<E> {
+    fn drop(&mut self) {
+        // A volatile write can't be optimized away, even though the key isn't read afterwards.
+        unsafe { ptr::write_volatile(&mut self.x, E::Fr::zero()); }
+        atomic::compiler_fence(Ordering::SeqCst);
     }
 }
 
@@ -291,6 +311,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compare_secret_keys() {
+        let mut rng = XorShiftRng::from_seed([0x44, 0x6d, 0x4f, 0xbc, 0x6c, 0x27, 0x2f, 0xd6, 0xd0, 0xaf, 0x63, 0xb9, 0x3d, 0x86, 0x55, 0x54]);
+
+        let secret = SecretKey::<Bls12>::generate(&mut rng);
+        let other = SecretKey::<Bls12>::generate(&mut rng);
+        assert!(secret == secret.clone());
+        assert!(secret != other);
+    }
+
     #[test]
     fn aggregate_signatures() {
         let mut rng = XorShiftRng::from_seed([0x44, 0x6d, 0x4f, 0xbc, 0x6c, 0x27, 0x2f, 0xd6, 0xd0, 0xaf, 0x63, 0xb9, 0x3d, 0x86, 0x55, 0x54]);
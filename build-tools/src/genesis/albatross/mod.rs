@@ -203,7 +203,7 @@ impl GenesisBuilder {
         let (slot_allocation, validators) = self.select_validators(&pre_genesis_seed, &staking_contract)?;
 
         // extrinsics
-        let extrinsics = MacroExtrinsics::from(slot_allocation, BitSet::new());
+        let extrinsics = MacroExtrinsics::from(Block::VERSION, slot_allocation, BitSet::new());
         let extrinsics_root = extrinsics.hash::<Blake2bHash>();
         debug!("Extrinsics root: {}", &extrinsics_root);
 
@@ -227,7 +227,7 @@ impl GenesisBuilder {
 
         // the header
         let header = MacroHeader {
-            version: 1,
+            version: Block::VERSION,
             validators: validators.iter().collect(),
             block_number: 0,
             view_number: 0,
@@ -239,6 +239,7 @@ impl GenesisBuilder {
             transactions_root: [0u8; 32].into(),
             timestamp: u64::try_from(timestamp.timestamp_millis())
                 .map_err(|_| GenesisBuilderError::InvalidTimestamp(timestamp))?,
+            extension: Vec::new(),
         };
 
         // genesis hash
@@ -5,7 +5,7 @@ use log::LevelFilter;
 use clap::{Arg, App, Values};
 use failure::Fail;
 
-use crate::settings::{Network, NodeType};
+use lib::config::{Network, NodeType};
 
 
 #[derive(Debug, Fail)]
@@ -30,6 +30,11 @@ pub(crate) struct Options {
     pub passive: bool,
     pub consensus_type: Option<NodeType>,
     pub network: Option<Network>,
+    pub check_config: bool,
+    pub epoch_digests: Option<String>,
+    pub verify_database: bool,
+    pub repair_database: bool,
+    pub compact_database: bool,
 }
 
 
@@ -84,6 +89,27 @@ impl Options {
                 .value_name("NAME")
                 .help("Configure the network to connect to, one of main (default), test or dev.")
                 .possible_values(&["main", "test", "dev"]))
+            .arg(Arg::with_name("check_config")
+                .long("check-config")
+                .help("Validate the configuration and print the effective configuration without starting the node.")
+                .takes_value(false))
+            .arg(Arg::with_name("epoch_digests")
+                .long("epoch-digests")
+                .value_name("FILE")
+                .help("Cross-check the state digest of each epoch against the digests listed in FILE (Albatross only).")
+                .takes_value(true))
+            .arg(Arg::with_name("verify_database")
+                .long("verify-database")
+                .help("Check the blockchain database for inconsistencies without starting the node (Albatross only).")
+                .takes_value(false))
+            .arg(Arg::with_name("repair_database")
+                .long("repair-database")
+                .help("Like --verify-database, but also remove invalid and orphaned entries.")
+                .takes_value(false))
+            .arg(Arg::with_name("compact_database")
+                .long("compact-database")
+                .help("Compact the LMDB database to reclaim disk space before starting the node.")
+                .takes_value(false))
     }
 
     /// Parses a command line option from a string into `T` and returns `error`, when parsing fails.
@@ -129,6 +155,11 @@ impl Options {
             passive: matches.is_present("passive"),
             consensus_type: Self::parse_option::<NodeType>(matches.value_of("consensus_type"), ParseError::ConsensusType)?,
             network: Self::parse_option::<Network>(matches.value_of("network"), ParseError::Network)?,
+            check_config: matches.is_present("check_config"),
+            epoch_digests: matches.value_of("epoch_digests").map(String::from),
+            verify_database: matches.is_present("verify_database"),
+            repair_database: matches.is_present("repair_database"),
+            compact_database: matches.is_present("compact_database"),
         })
     }
 }
@@ -6,12 +6,11 @@ extern crate lazy_static;
 extern crate log;
 #[cfg(feature = "deadlock-detection")]
 extern crate parking_lot;
-#[macro_use]
-extern crate serde_derive;
 #[cfg(feature = "human-panic")]
 #[macro_use]
 extern crate human_panic;
 
+extern crate nimiq_blockchain_albatross as blockchain_albatross;
 extern crate nimiq_database as database;
 extern crate nimiq_lib as lib;
 extern crate nimiq_mempool as mempool;
@@ -26,91 +25,108 @@ extern crate nimiq_keys as keys;
 extern crate nimiq_utils as utils;
 extern crate nimiq_consensus as consensus;
 extern crate nimiq_bls as bls;
+extern crate nimiq_hash as hash;
 
 
 mod deadlock;
 mod logging;
-mod settings;
 mod cmdline;
 mod static_env;
-mod serialization;
 mod files;
+mod sandbox;
 
 
+use std::convert::TryFrom;
 use std::io;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::collections::HashSet;
 use std::iter::FromIterator;
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
 
-use failure::{Error, Fail};
+use failure::Error;
 use fern::log_file;
 use futures::{Future, future};
 use log::Level;
 use rand::rngs::OsRng;
 use parking_lot::RwLock;
+use hex::FromHex;
+use url::Url;
 
-use database::lmdb::{LmdbEnvironment, open};
+#[cfg(feature = "rpc-server")]
+use blockchain_albatross::slot_schedule::SlotSchedule;
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::history_shards::HistoryShards;
+use blockchain_albatross::verify::{repair_databases, verify_databases};
+use database::lmdb::{LmdbEnvironment, LmdbEnvironmentBuilder};
+#[cfg(feature = "rocksdb")]
+use database::rocks::RocksEnvironment;
+#[cfg(feature = "mdbx")]
+use database::mdbx::MdbxEnvironment;
+#[cfg(feature = "sled")]
+use database::sled::SledEnvironment;
 use mempool::MempoolConfig;
 use network_primitives::protocol::Protocol;
 use network_primitives::address::NetAddress;
-use network::network_config::Seed;
-use utils::key_store::KeyStore;
+use network_primitives::heartbeat::ValidatorLiveness;
+use network::network_config::{NodeRole, Seed};
+use utils::key_store::{Error as KeyStoreError, KeyStore};
+use keys::{Address, PrivateKey, PublicKey, SecretBytes};
+use hash::Blake2bHash;
 use primitives::networks::NetworkId;
 use consensus::{Consensus, ConsensusProtocol, AlbatrossConsensusProtocol, NimiqConsensusProtocol};
 use bls::bls12_381::KeyPair;
 use network_primitives::services::ServiceFlags;
 #[cfg(feature = "metrics-server")]
-use metrics_server::{metrics_server, AlbatrossChainMetrics, NimiqChainMetrics, AbstractChainMetrics};
+use metrics_server::{metrics_server, MetricsServerConfig, AlbatrossChainMetrics, NimiqChainMetrics, AbstractChainMetrics};
 #[cfg(feature = "rpc-server")]
 use rpc_server::{
     rpc_server,
-    Credentials, JsonRpcConfig, Handler as RpcHandler,
+    Credentials, JsonRpcConfig, Handler as RpcHandler, TlsConfig,
     handlers::blockchain_nimiq::BlockchainNimiqHandler,
     handlers::blockchain_albatross::BlockchainAlbatrossHandler,
     handlers::block_production_nimiq::BlockProductionNimiqHandler,
     handlers::block_production_albatross::BlockProductionAlbatrossHandler,
     handlers::consensus::ConsensusHandler,
+    handlers::admin::AdminHandler,
+    handlers::database::DatabaseHandler,
     handlers::mempool::MempoolHandler,
     handlers::mempool_albatross::MempoolAlbatrossHandler,
-    handlers::network::NetworkHandler,
+    handlers::network::{NetworkHandler, NodeFeatures},
+    handlers::test::TestHandler,
     handlers::wallet::{WalletHandler, UnlockedWalletManager},
+    handlers::watch::WatchHandler,
 };
 
 use lib::block_producer::{BlockProducer, DummyBlockProducer};
-use lib::block_producer::albatross::{ValidatorConfig, AlbatrossBlockProducer};
+use lib::block_producer::albatross::{Blacklist, ExtraDataProvider, FilteredMempoolOrder, MempoolOrder, ValidatorConfig, ValidatorKeys, AlbatrossBlockProducer};
+use lib::rewards::RewardSweepConfig;
 use lib::error::ClientError;
 use lib::client::{Client, ClientBuilder, ClientInitializeFuture};
+use lib::config as s;
+use lib::config::{ClientConfig, ConfigError, RpcServerSettings};
+use lib::config::serialization::SeedError;
+use lib::replica::{Replica, ReplicaConfig};
+use lib::updater::{Updater, UpdaterConfig};
+use lib::webhooks::{WebhookConfig, WebhookDispatcher, WebhookEvent};
+use lib::epoch_digests::EpochDigests;
+use lib::gc::GarbageCollector;
+use lib::header_diffs::MacroHeaderDiffRelay;
+use lib::history::HistoryArchiver;
 
 use crate::cmdline::Options;
 use crate::logging::{DEFAULT_LEVEL, NimiqDispatch};
 use crate::logging::force_log_error_cause_chain;
-use crate::settings as s;
-use crate::settings::{Settings, RpcServerSettings};
 use crate::static_env::ENV;
-use crate::serialization::SeedError;
 use crate::files::LazyFileLocations;
+use crate::sandbox::Sandbox;
 
 type OtherFuture = Box<dyn Future<Item=(), Error=()> + Send + Sync + 'static>;
 
-#[derive(Debug, Fail)]
-pub enum ConfigError {
-    #[fail(display = "Please configure a TLS identity file and the password in the `[tls]` section.")]
-    NoTlsIdentityFile,
-    #[fail(display = "Please configure a hostname in the `[network]` section.")]
-    NoHostname,
-    #[fail(display = "Invalid IP address.")]
-    InvalidIpAddress,
-    #[fail(display = "Username or password missing for RPC server.")]
-    MissingRpcCredentials,
-    #[fail(display = "The public key for a seed node is missing. Seed nodes without public_key are currently not implemented.")]
-    MissingPublicKey,
-    #[fail(display = "Config file not found")]
-    MissingConfigFile
-}
-
 fn main() {
     #[cfg(feature = "deadlock-detection")]
     deadlock::deadlock_detection();
@@ -138,59 +154,96 @@ fn find_config_file(cmdline: &Options, files: &mut LazyFileLocations) -> Result<
 
 fn run_albatross_node(
     client_builder: ClientBuilder,
-    settings: Settings,
-    block_producer_config: <DummyBlockProducer as BlockProducer<AlbatrossConsensusProtocol>>::Config
+    settings: ClientConfig,
+    block_producer_config: <DummyBlockProducer as BlockProducer<AlbatrossConsensusProtocol>>::Config,
+    epoch_digests: Option<EpochDigests>,
+    sandbox: Option<Sandbox>
 ) -> Result<!, Error> {
     let client: ClientInitializeFuture<AlbatrossConsensusProtocol, DummyBlockProducer> =
         client_builder.build_client(block_producer_config)?;
     let consensus = client.consensus();
 
+    // Live as long as the client runs.
+    let _epoch_digests = epoch_digests.map(|epoch_digests| epoch_digests.watch(&consensus.blockchain));
+    let _header_diff_relay = MacroHeaderDiffRelay::start(&consensus);
+    let _history_archiver = start_history_archiver(&settings, &consensus.blockchain);
+    let gc = start_gc(&settings, &consensus);
+
     // Additional futures we want to run.
-    let mut other_futures = build_other_futures::<AlbatrossConfiguration>(&settings, &consensus)?;
+    let mut other_futures = build_other_futures::<AlbatrossConfiguration>(&settings, &consensus, None)?;
+    other_futures.extend(build_webhook_dispatcher(&settings, &consensus)?);
 
     // start RPC server if enabled
     #[cfg(feature = "rpc-server")] {
-        if let Some((future, handler)) = build_rpc_server(settings.rpc_server)? {
-            let unlocked_wallets = add_generic_rpc_modules(&handler, &consensus);
+        if let Some((future, handler)) = build_rpc_server(settings.rpc_server.clone())? {
+            let unlocked_wallets = add_generic_rpc_modules(&handler, &consensus, &settings, false, None);
 
             let blockchain_handler = BlockchainAlbatrossHandler::new(Arc::clone(&consensus.blockchain));
             let mempool_handler = MempoolAlbatrossHandler::new(
                 Arc::clone(&consensus.mempool),
                 Some(unlocked_wallets),
             );
+            let watch_handler = WatchHandler::new(consensus.env, Arc::clone(&consensus.blockchain));
+            gc.register_watch_registry(watch_handler.registry());
 
             handler.add_module(blockchain_handler);
             handler.add_module(mempool_handler);
+            handler.add_module(watch_handler);
 
             other_futures.push(future);
         }
     }
 
-    run_client(client, other_futures)
+    run_client(client, other_futures, &settings.threads, sandbox)
 }
 
 fn run_albatross_validator_node(
     client_builder: ClientBuilder,
-    settings: Settings,
-    block_producer_config: <AlbatrossBlockProducer as BlockProducer<AlbatrossConsensusProtocol>>::Config
+    settings: ClientConfig,
+    block_producer_config: <AlbatrossBlockProducer as BlockProducer<AlbatrossConsensusProtocol>>::Config,
+    epoch_digests: Option<EpochDigests>,
+    sandbox: Option<Sandbox>
 ) -> Result<!, Error> {
-    let client: ClientInitializeFuture<AlbatrossConsensusProtocol, AlbatrossBlockProducer> =
+    let mut client: ClientInitializeFuture<AlbatrossConsensusProtocol, AlbatrossBlockProducer> =
         client_builder.build_client(block_producer_config.clone())?;
     let consensus = client.consensus();
 
+    // Lives as long as the client runs.
+    let _epoch_digests = epoch_digests.map(|epoch_digests| epoch_digests.watch(&consensus.blockchain));
+
+    let minimal = settings.validator.as_ref().map_or(false, s::ValidatorSettings::is_minimal);
+    if minimal {
+        info!("Running minimal validator: no transaction relay, transaction index or watch RPC methods");
+    }
+
+    if block_producer_config.manual_block_production && (!cfg!(feature = "rpc-server") || settings.rpc_server.is_none()) {
+        warn!("Manual block production without RPC server, this validator won't produce any blocks");
+    }
+
+    let liveness = Arc::clone(&block_producer_config.liveness);
+
     // Additional futures we want to run.
-    let mut other_futures = build_other_futures::<AlbatrossValidatorConfiguration>(&settings, &consensus)?;
+    let mut other_futures = build_other_futures::<AlbatrossValidatorConfiguration>(&settings, &consensus, Some(Arc::clone(&liveness)))?;
+    if !minimal {
+        other_futures.extend(build_webhook_dispatcher(&settings, &consensus)?);
+    }
+    // Minimal validators don't serve light clients. Lives as long as the client runs.
+    let _header_diff_relay = if minimal { None } else { Some(MacroHeaderDiffRelay::start(&consensus)) };
+    let _history_archiver = start_history_archiver(&settings, &consensus.blockchain);
+    let gc = start_gc(&settings, &consensus);
+    client.block_producer_config_mut().gc = Some(Arc::clone(&gc));
 
     // start RPC server if enabled
     #[cfg(feature = "rpc-server")] {
-        if let Some((future, mut handler)) = build_rpc_server(settings.rpc_server)? {
-            let unlocked_wallets = add_generic_rpc_modules(&mut handler, &consensus);
+        if let Some((future, mut handler)) = build_rpc_server(settings.rpc_server.clone())? {
+            let validator_keys = Arc::clone(&block_producer_config.validator_keys);
 
-            let public_key = block_producer_config.validator_key.public.compress();
-            let proof_of_knowledge = block_producer_config.validator_key.sign(&public_key).compress();
+            // Backups wait for a window without the validator's own slots.
+            let slot_schedule = SlotSchedule::new(Arc::clone(&consensus.blockchain), validator_keys.public_keys(), SlotSchedule::DEFAULT_WINDOW);
+            let unlocked_wallets = add_generic_rpc_modules(&mut handler, &consensus, &settings, true, Some(slot_schedule));
 
             let blockchain_handler = BlockchainAlbatrossHandler::new(Arc::clone(&consensus.blockchain));
-            let block_production_handler = BlockProductionAlbatrossHandler::new(public_key, proof_of_knowledge);
+            let block_production_handler = BlockProductionAlbatrossHandler::new(Arc::clone(&validator_keys), liveness, Arc::clone(&block_producer_config.blacklist));
             let mempool_handler = MempoolAlbatrossHandler::new(
                 Arc::clone(&consensus.mempool),
                 Some(unlocked_wallets),
@@ -200,29 +253,52 @@ fn run_albatross_validator_node(
             handler.add_module(block_production_handler);
             handler.add_module(mempool_handler);
 
+            // The watch methods index the transactions of watched addresses, which a minimal
+            // validator doesn't.
+            if !minimal {
+                let watch_handler = WatchHandler::new(consensus.env, Arc::clone(&consensus.blockchain));
+                gc.register_watch_registry(watch_handler.registry());
+                handler.add_module(watch_handler);
+            }
+
+            // Lets application tests control the chain, which must never be possible on a public
+            // network. The validator doesn't produce blocks on its own then, so the blocks don't
+            // compete.
+            if block_producer_config.manual_block_production && consensus.blockchain.network_id.is_dev() {
+                info!("Manual block production, enabling test RPC methods");
+                let test_handler = TestHandler::new(
+                    Arc::clone(&consensus.blockchain),
+                    Arc::clone(&consensus.mempool),
+                    validator_keys.current(),
+                    Arc::clone(&consensus.network.network_time),
+                );
+                handler.add_module(test_handler);
+            }
+
             other_futures.push(future);
         }
     }
 
-    run_client(client, other_futures)
+    run_client(client, other_futures, &settings.threads, sandbox)
 }
 
 fn run_nimiq_node(
     client_builder: ClientBuilder,
-    settings: Settings,
-    block_producer_config: <DummyBlockProducer as BlockProducer<NimiqConsensusProtocol>>::Config
+    settings: ClientConfig,
+    block_producer_config: <DummyBlockProducer as BlockProducer<NimiqConsensusProtocol>>::Config,
+    sandbox: Option<Sandbox>
 ) -> Result<!, Error> {
     let client: ClientInitializeFuture<NimiqConsensusProtocol, DummyBlockProducer> =
         client_builder.build_client(block_producer_config)?;
     let consensus = client.consensus();
 
     // Additional futures we want to run.
-    let mut other_futures = build_other_futures::<NimiqConfiguration>(&settings, &consensus)?;
+    let mut other_futures = build_other_futures::<NimiqConfiguration>(&settings, &consensus, None)?;
 
     // start RPC server if enabled
     #[cfg(feature = "rpc-server")] {
-        if let Some((future, mut handler)) = build_rpc_server(settings.rpc_server)? {
-            let unlocked_wallets = add_generic_rpc_modules(&mut handler, &consensus);
+        if let Some((future, mut handler)) = build_rpc_server(settings.rpc_server.clone())? {
+            let unlocked_wallets = add_generic_rpc_modules(&mut handler, &consensus, &settings, false, None);
 
             let blockchain_handler = BlockchainNimiqHandler::new(Arc::clone(&consensus.blockchain));
             let block_production_handler = BlockProductionNimiqHandler::new(
@@ -242,26 +318,88 @@ fn run_nimiq_node(
         }
     }
 
-    run_client(client, other_futures)
+    run_client(client, other_futures, &settings.threads, sandbox)
+}
+
+fn run_replica(settings: ClientConfig, network_id: NetworkId, sandbox: Option<Sandbox>) -> Result<!, Error> {
+    let replica_settings = settings.replica.clone().unwrap_or_default();
+    let config = ReplicaConfig {
+        refresh_interval: replica_settings.refresh_interval
+            .map(Duration::from_secs)
+            .unwrap_or(ReplicaConfig::DEFAULT_REFRESH_INTERVAL),
+        max_staleness: replica_settings.max_staleness
+            .map(Duration::from_secs)
+            .unwrap_or(ReplicaConfig::DEFAULT_MAX_STALENESS),
+    };
+    let replica = Arc::new(Replica::new(ENV.get(), network_id, config)?);
+    // The node moves the blocks of old epochs, the replica only reads them.
+    if let Some(ref history_dir) = settings.database.history_dir {
+        let size = settings.database.history_size.unwrap_or(HistoryShards::DEFAULT_SIZE);
+        replica.blockchain.set_history(HistoryShards::new(history_dir, size));
+    }
+    info!("Running as read replica of the node database at {}, head is #{}",
+          settings.database.path.as_ref().unwrap(), replica.blockchain.block_number());
+
+    let mut futures: Vec<OtherFuture> = vec![Box::new(Replica::run(Arc::clone(&replica)))];
+
+    // start RPC server if enabled
+    #[cfg(feature = "rpc-server")] {
+        if let Some((future, handler)) = build_rpc_server(settings.rpc_server)? {
+            let blockchain_handler = BlockchainAlbatrossHandler::new(Arc::clone(&replica.blockchain));
+            let guard_replica = Arc::clone(&replica);
+            handler.add_guarded_module(blockchain_handler,
+                move || guard_replica.check_staleness().map_err(|e| e.to_string()));
+
+            futures.push(future);
+        }
+    }
+    #[cfg(not(feature = "rpc-server"))] {
+        warn!("Client was built without RPC server, the read replica has nothing to do");
+    }
+
+    // Everything that needs to be set up outside of the sandbox is initialized at this point.
+    if let Some(sandbox) = sandbox {
+        sandbox.apply()?;
+    }
+
+    run_runtime(&settings.threads, future::join_all(futures).map(|_| ()))
 }
 
-fn run_client<P, BP>(client: ClientInitializeFuture<P, BP>, other_futures: Vec<OtherFuture>) -> Result<!, Error>
+fn run_client<P, BP>(client: ClientInitializeFuture<P, BP>, other_futures: Vec<OtherFuture>, threads: &s::ThreadSettings, sandbox: Option<Sandbox>) -> Result<!, Error>
     where P: ConsensusProtocol + 'static,
           BP: BlockProducer<P> + 'static
 {
+    // Everything that needs to be set up outside of the sandbox is initialized at this point.
+    if let Some(sandbox) = sandbox {
+        sandbox.apply()?;
+    }
+
     // Run client and other futures
-    tokio::run(
+    run_runtime(threads,
         client
             .and_then(|c| c.connect()) // Run Nimiq client
             .and_then( move |c| future::join_all(other_futures)
                 .map_err(|_| ClientError::OtherFailed)
                 .and_then(|_| c)) // Run other futures (e.g. RPC server)
             .map_err(|e| error!("Client initialization failed: {}", e))
-    );
+    )
+}
+
+/// Runs `future` on a runtime with the number of worker threads from the `[threads]` section.
+fn run_runtime<F>(threads: &s::ThreadSettings, future: F) -> Result<!, Error>
+    where F: Future<Item=(), Error=()> + Send + 'static
+{
+    let tokio_workers = threads.tokio_workers();
+    info!("Running with {} worker threads", tokio_workers);
+    let mut runtime = tokio::runtime::Builder::new()
+        .core_threads(tokio_workers)
+        .build()?;
+    runtime.spawn(future);
+    let _ = runtime.shutdown_on_idle().wait();
     panic!("Tokio exited")
 }
 
-fn build_other_futures<CC>(settings: &Settings, consensus: &Arc<Consensus<CC::Protocol>>) -> Result<Vec<OtherFuture>, Error>
+fn build_other_futures<CC>(settings: &ClientConfig, consensus: &Arc<Consensus<CC::Protocol>>, validator_liveness: Option<Arc<ValidatorLiveness>>) -> Result<Vec<OtherFuture>, Error>
     where CC: ClientConfiguration
 {
     let mut futures = Vec::<OtherFuture>::new();
@@ -280,22 +418,97 @@ fn build_other_futures<CC>(settings: &Settings, consensus: &Arc<Consensus<CC::Pr
                 .unwrap_or_else(|| NetAddress::from_str("127.0.0.1").unwrap())
                 .into_ip_address().unwrap();
             let port = metrics_settings.port.unwrap_or(s::DEFAULT_METRICS_PORT);
+            // The subnets were already validated when the config was loaded.
+            let allowip = metrics_settings.allowip.iter()
+                .map(|subnet| network_primitives::address::NetSubnet::from_str(subnet).unwrap())
+                .collect();
+            let config = MetricsServerConfig {
+                username: metrics_settings.username.clone(),
+                password: metrics_settings.password.clone(),
+                allowip,
+                tls: metrics_settings.tls.clone().map(|tls_settings| metrics_server::TlsConfig {
+                    certificate_file: tls_settings.certificate_file,
+                    private_key_file: tls_settings.private_key_file,
+                    client_ca_file: tls_settings.client_ca_file,
+                }),
+            };
             info!("Starting metrics server listening on port {}", port);
             futures.push(metrics_server::<CC::Protocol, CC::ChainMetrics>(
-                Arc::clone(&consensus), bind, port, metrics_settings.password.clone()
+                Arc::clone(&consensus), validator_liveness, bind, port, config
             )?);
         }
     }
     // If the metrics server is enabled, but the client is not compiled with it, inform the user
     #[cfg(not(feature = "metrics-server"))] {
+        let _ = validator_liveness;
         if settings.metrics_server.is_some() {
             warn!("Client was built without Metrics server.");
         }
     }
 
+    // Start update checker if enabled
+    if let Some(ref updater_settings) = settings.updater {
+        let config = UpdaterConfig {
+            manifest_url: Url::parse(&updater_settings.manifest_url)?,
+            public_key: PublicKey::from_hex(&updater_settings.public_key)?,
+            interval: updater_settings.interval
+                .map(Duration::from_secs)
+                .unwrap_or(UpdaterConfig::DEFAULT_INTERVAL),
+            download_dir: updater_settings.download_dir.clone().map(PathBuf::from),
+        };
+        info!("Checking for updates at {}", config.manifest_url);
+        futures.push(Box::new(Updater::new(config).run()));
+    }
+
     Ok(futures)
 }
 
+fn build_webhook_dispatcher(settings: &ClientConfig, consensus: &Arc<Consensus<AlbatrossConsensusProtocol>>) -> Result<Option<OtherFuture>, Error> {
+    if settings.webhook.is_empty() {
+        return Ok(None);
+    }
+
+    let mut webhooks = Vec::with_capacity(settings.webhook.len());
+    for webhook_settings in settings.webhook.iter() {
+        let webhook = WebhookConfig {
+            url: Url::parse(&webhook_settings.url)?,
+            events: webhook_settings.events.iter()
+                .map(|event| WebhookEvent::from_str(event))
+                .collect::<Result<_, _>>()?,
+            addresses: webhook_settings.addresses.iter()
+                .map(|address| Address::from_user_friendly_address(address))
+                .collect::<Result<_, _>>()?,
+            secret: webhook_settings.secret.clone(),
+        };
+        info!("Delivering blockchain events to webhook {}", webhook.url);
+        webhooks.push(webhook);
+    }
+
+    let dispatcher = WebhookDispatcher::new(consensus.env, Arc::clone(&consensus.blockchain), webhooks);
+    Ok(Some(Box::new(WebhookDispatcher::run(dispatcher))))
+}
+
+/// Moves the block bodies of old epochs to the configured history directory. Returns `None` if
+/// there is none.
+fn start_history_archiver(settings: &ClientConfig, blockchain: &Arc<Blockchain<'static>>) -> Option<HistoryArchiver> {
+    let history_dir = settings.database.history_dir.as_ref()?;
+    let size = settings.database.history_size.unwrap_or(HistoryShards::DEFAULT_SIZE);
+    blockchain.set_history(HistoryShards::new(history_dir, size));
+
+    let keep_epochs = settings.database.history_keep_epochs.unwrap_or(HistoryArchiver::MIN_KEEP_EPOCHS);
+    info!("Moving the blocks of old epochs to {}", history_dir);
+    Some(HistoryArchiver::start(blockchain, keep_epochs))
+}
+
+/// Starts the node's garbage collector with the stores of the blockchain and the network. The
+/// other stores are registered with it once they are created.
+fn start_gc(settings: &ClientConfig, consensus: &Arc<Consensus<AlbatrossConsensusProtocol>>) -> Arc<GarbageCollector> {
+    let gc = GarbageCollector::start(consensus, settings.gc.clone());
+    gc.register_blockchain();
+    gc.register_network(consensus);
+    gc
+}
+
 fn run() -> Result<!, Error> {
     // Parse command line arguments.
     let cmdline = Options::parse()?;
@@ -310,7 +523,25 @@ fn run() -> Result<!, Error> {
         eprintln!("If you haven't configured the Nimiq client yet, do this by copying the client.example.toml to client.toml in the path above and editing it appropriately.");
         return Err(ConfigError::MissingConfigFile.into());
     }
-    let settings = Settings::from_file(&config_file)?;
+    let mut settings = ClientConfig::from_file(&config_file)?;
+
+    // Command-line options override the config file.
+    if let Some(hostname) = cmdline.hostname.clone() {
+        settings.network.host = Some(hostname);
+    }
+    if let Some(port) = cmdline.port {
+        settings.network.port = Some(port);
+    }
+    if let Some(node_type) = cmdline.consensus_type {
+        settings.consensus.node_type = node_type;
+    }
+    if let Some(network) = cmdline.network {
+        settings.consensus.network = network;
+    }
+
+    if cmdline.check_config {
+        return check_config(settings, &config_file, &mut files);
+    }
 
     // Setup logging.
     let mut dispatch = fern::Dispatch::new()
@@ -334,33 +565,93 @@ fn run() -> Result<!, Error> {
     log_panics::init();
 
     info!("Loaded config file from: {}", config_file.display());
+    info!("Crypto backend: Blake2b {}, Argon2d {}, BLS {} (CPU features: {})",
+          hash::backend::blake2b_implementation(), hash::backend::argon2_implementation().name(),
+          bls::IMPLEMENTATION, hash::backend::cpu_features().join(", "));
     trace!("Command-line options: {:#?}", cmdline);
     trace!("Settings: {:#?}", settings);
 
-    // We only allow full nodes right now.
-    if settings.consensus.node_type != s::NodeType::Full {
-        error!("Only full consensus is implemented right now.");
-        unimplemented!();
-    }
+    settings.validate()?;
+    resolve_files(&mut settings, &mut files)?;
+    let sandbox = Sandbox::from_config(&settings, &config_file);
 
     // Get network ID.
-    let network_id = NetworkId::from(cmdline.network.unwrap_or(settings.consensus.network));
+    let network_id = NetworkId::from(settings.consensus.network);
+
+    // Load the expected epoch state digests before the sandbox prevents reading the file.
+    let epoch_digests = cmdline.epoch_digests.as_ref()
+        .map(|path| EpochDigests::load(path)).transpose()?;
+    if epoch_digests.is_some() && !network_id.is_albatross() {
+        warn!("Epoch state digests are only supported on Albatross networks");
+    }
 
     // Start database and obtain a 'static reference to it.
-    let default_database_settings = s::DatabaseSettings::default();
-    let env = LmdbEnvironment::new(&settings.database.path.clone()
-        .unwrap_or_else(|| files.database(network_id).expect("Failed to find database").to_str().unwrap().to_string()),
-            settings.database.size.unwrap_or_else(|| default_database_settings.size.unwrap()),
-            settings.database.max_dbs.unwrap_or_else(|| default_database_settings.max_dbs.unwrap()),
-            if settings.database.no_lmdb_sync.unwrap_or(false) { open::NOSYNC } else { open::NOMETASYNC })?;
+    let env = match settings.database.backend.unwrap_or_default() {
+        s::DatabaseBackend::Lmdb => {
+            let path = settings.database.path.as_ref().unwrap();
+            let mut builder = LmdbEnvironmentBuilder::new(path);
+            builder.with_size(settings.database.size.unwrap())
+                .with_max_dbs(settings.database.max_dbs.unwrap())
+                .with_no_sync(settings.database.no_lmdb_sync.unwrap_or(false))
+                .with_no_meta_sync(settings.database.no_lmdb_meta_sync.unwrap_or(true))
+                .with_write_map(settings.database.lmdb_write_map.unwrap_or(false))
+                .with_no_read_ahead(settings.database.no_lmdb_read_ahead.unwrap_or(false));
+            if let Some(max_readers) = settings.database.lmdb_max_readers {
+                builder.with_max_readers(max_readers);
+            }
+            match settings.database.passphrase() {
+                None if LmdbEnvironment::is_encrypted(path) => return Err(ConfigError::DatabasePassphraseMissing.into()),
+                ref passphrase if settings.replica.is_some() => builder.build_read_only(passphrase.as_ref().map(SecretBytes::as_bytes))?,
+                Some(ref passphrase) => builder.build_encrypted(passphrase.as_bytes())?,
+                None => builder.build()?,
+            }
+        },
+        #[cfg(feature = "rocksdb")]
+        s::DatabaseBackend::Rocksdb => RocksEnvironment::new(settings.database.path.as_ref().unwrap(),
+            settings.database.max_dbs.unwrap())?,
+        #[cfg(feature = "mdbx")]
+        s::DatabaseBackend::Mdbx => MdbxEnvironment::new(settings.database.path.as_ref().unwrap(),
+            settings.database.size.unwrap(),
+            settings.database.max_dbs.unwrap(),
+            settings.database.no_lmdb_sync.unwrap_or(false))?,
+        #[cfg(feature = "sled")]
+        s::DatabaseBackend::Sled => SledEnvironment::new(settings.database.path.as_ref().unwrap(),
+            settings.database.max_dbs.unwrap())?,
+        #[cfg(not(feature = "rocksdb"))]
+        s::DatabaseBackend::Rocksdb => return Err(ConfigError::DatabaseBackendUnavailable(s::DatabaseBackend::Rocksdb, "rocksdb").into()),
+        #[cfg(not(feature = "mdbx"))]
+        s::DatabaseBackend::Mdbx => return Err(ConfigError::DatabaseBackendUnavailable(s::DatabaseBackend::Mdbx, "mdbx").into()),
+        #[cfg(not(feature = "sled"))]
+        s::DatabaseBackend::Sled => return Err(ConfigError::DatabaseBackendUnavailable(s::DatabaseBackend::Sled, "sled").into()),
+    };
+    // Compacting reopens the environment, so it must happen before it becomes static.
+    let env = if cmdline.compact_database {
+        info!("Compacting database");
+        env.compact()?
+    } else {
+        env
+    };
     // Initialize the static environment variable
     ENV.initialize(env);
+    // Create the backup and history directories now, so that the sandbox can allow access to them.
+    if let Some(ref backup_dir) = settings.database.backup_dir {
+        fs::create_dir_all(backup_dir)?;
+    }
+    if let Some(ref history_dir) = settings.database.history_dir {
+        fs::create_dir_all(history_dir)?;
+    }
+
+    if cmdline.verify_database || cmdline.repair_database {
+        return verify_database(network_id, cmdline.repair_database);
+    }
+
+    // A read replica doesn't run a node, it only serves RPC queries from the node's database.
+    if settings.replica.is_some() {
+        return run_replica(settings, network_id, sandbox);
+    }
 
     // Open peer key store.
-    let peer_key_store = KeyStore::new(settings.peer_key_file.clone()
-        .unwrap_or_else(|| files.peer_key()
-            .expect("Failed to find peer key file")
-            .to_str().unwrap().into()));
+    let peer_key_store = KeyStore::new(settings.peer_key_file.clone().unwrap());
 
     // Start building the client with network ID and environment.
     let mut client_builder = ClientBuilder::new(Protocol::from(settings.network.protocol), ENV.get(), peer_key_store);
@@ -369,13 +660,10 @@ fn run() -> Result<!, Error> {
     client_builder.with_network_id(network_id);
 
     // Add hostname and port to builder.
-    if let Some(hostname) = cmdline.hostname.or_else(|| settings.network.host.clone()) {
-        client_builder.with_hostname(&hostname);
+    if let Some(ref hostname) = settings.network.host {
+        client_builder.with_hostname(hostname);
     }
-    else if settings.network.protocol == s::Protocol::Ws || settings.network.protocol == s::Protocol::Wss {
-        return Err(ConfigError::NoHostname.into());
-    }
-    if let Some(port) = cmdline.port.or(settings.network.port) {
+    if let Some(port) = settings.network.port {
         client_builder.with_port(port);
     }
 
@@ -389,82 +677,265 @@ fn run() -> Result<!, Error> {
         );
     }
 
-    // Add mempool settings to filter.
-    if let Some(mempool_settings) = settings.mempool.clone() {
-        client_builder.with_mempool_config(MempoolConfig::from(mempool_settings.clone()));
+    // Add mempool settings to filter. A minimal validator keeps a smaller mempool and doesn't
+    // relay transactions.
+    let mut mempool_config = settings.mempool.clone().map(MempoolConfig::from).unwrap_or_default();
+    if settings.validator.as_ref().map_or(false, s::ValidatorSettings::is_minimal) {
+        mempool_config.size_limit = s::MINIMAL_VALIDATOR_MEMPOOL_SIZE;
+        mempool_config.relay_transactions = false;
+    }
+    client_builder.with_mempool_config(mempool_config);
+
+    // Set memory budget, if present.
+    if let Some(memory_budget) = settings.memory_budget_bytes() {
+        client_builder.with_memory_budget(memory_budget);
+    }
+
+    // Throttle the initial sync, if configured.
+    if let Some(sync_rate_limit) = settings.consensus.sync_rate_limit {
+        client_builder.with_sync_rate_limit(sync_rate_limit);
     }
 
     // Add TLS configuration, if present.
     // NOTE: Currently we only need to set TLS settings for Wss.
     if settings.network.protocol == s::Protocol::Wss {
-        if let Some(tls_settings) = settings.network.tls.clone() {
+        if let Some(ref tls_settings) = settings.network.tls {
             client_builder.with_tls_identity(&tls_settings.identity_file, &tls_settings.identity_password);
         }
-        else {
-            return Err(ConfigError::NoTlsIdentityFile.into())
-        }
     }
 
     // Parse additional seed nodes and add them.
     let seeds = settings.network.seed_nodes.iter()
-        .map(|s| s::Seed::try_from(s.clone()))
+        .map(|s| Seed::try_from(s.clone()))
         .collect::<Result<Vec<Seed>, SeedError>>()?;
-    if seeds.iter().any(|s| match s {
-        Seed::Peer(uri) => uri.public_key().is_none(),
-        _ => false
-    }) {
-        return Err(ConfigError::MissingPublicKey.into());
-    }
     client_builder.with_seeds(seeds);
 
     client_builder.with_instant_inbound(settings.network.instant_inbound.unwrap_or(false));
 
+    // Set node role and peer count targets.
+    if let Some(role) = settings.network.role {
+        client_builder.with_node_role(NodeRole::from(role));
+    }
+    for (role, targets) in settings.network.peer_targets.to_targets() {
+        client_builder.with_peer_count_targets(role, targets);
+    }
+
     // Setup client future to initialize and connect.
     if network_id.is_albatross() {
         warn!("!!!!");
         warn!("!!!! Albatross node running");
         warn!("!!!!");
 
+        // Peers only get macro header diffs if they announce that they understand them. Minimal
+        // validators don't send them.
+        if !settings.validator.as_ref().map_or(false, s::ValidatorSettings::is_minimal) {
+            client_builder.with_service_flags(ServiceFlags::MACRO_HEADER_DIFFS);
+        }
+
         match &settings.validator {
             Some(validator_settings) => {
-                let validator_key = {
-                    // Load validator key from key store, or create a new one, if key store doesn't exist
-                    let key_store_file = validator_settings.key_file.clone()
-                        .map(|s| Ok(PathBuf::from(s)))
-                        .unwrap_or_else(|| files.validator_key())?;
-                    let key_store = KeyStore::new(key_store_file.to_str().unwrap().to_string());
-                    if !key_store_file.exists() {
-                        info!("Generating validator key");
-                        let key_pair = KeyPair::generate(&mut OsRng::new()?);
-                        if let Err(ref err) = key_store.save_key(&key_pair) {
-                            warn!("Failed to save key: {}", err);
-                        }
-                        key_pair
-                    }
-                    else {
-                        key_store.load_key()?
+                // Load validator key from key store, or create a new one, if key store doesn't exist
+                let key_store_file = PathBuf::from(validator_settings.key_file.clone().unwrap());
+                let key_store = KeyStore::new(key_store_file.to_str().unwrap().to_string());
+                let validator_key = if !key_store_file.exists() {
+                    info!("Generating validator key");
+                    let key_pair = KeyPair::generate(&mut OsRng::new()?);
+                    if let Err(ref err) = key_store.save_key(&key_pair) {
+                        warn!("Failed to save key: {}", err);
                     }
+                    key_pair
+                }
+                else {
+                    key_store.load_key()?
                 };
 
+                // The previous key only exists while a key rotation is in progress.
+                let previous_key_file = validator_settings.previous_key_file.clone().unwrap();
+                let previous_key_store = KeyStore::new(previous_key_file.clone());
+                let previous_validator_key = if PathBuf::from(&previous_key_file).exists() {
+                    Some(previous_key_store.load_key()?)
+                }
+                else {
+                    None
+                };
+                let validator_keys = ValidatorKeys::new(validator_key, previous_validator_key)
+                    .with_key_stores(key_store, previous_key_store);
+
+                // Load the key of the reward address, if rewards are swept. It must exist already,
+                // since the reward address has to be registered when staking.
+                let reward_sweep = match validator_settings.reward_sweep {
+                    Some(ref sweep_settings) => Some(RewardSweepConfig {
+                        key_pair: KeyStore::new(sweep_settings.key_file.clone()).load_key::<keys::KeyPair>()?,
+                        address: Address::from_user_friendly_address(&sweep_settings.address)?,
+                        interval: sweep_settings.interval.unwrap_or(s::DEFAULT_REWARD_SWEEP_INTERVAL),
+                        fee: sweep_settings.fee,
+                    }),
+                    None => None,
+                };
+                let sweep_address = reward_sweep.as_ref().map(|sweep| Address::from(&sweep.key_pair.public));
+                let reward_address = validator_settings.reward_address.as_ref()
+                    .map(|address| Address::from_user_friendly_address(address)).transpose()?;
+                if let (Some(reward_address), Some(sweep_address)) = (&reward_address, &sweep_address) {
+                    if reward_address != sweep_address {
+                        return Err(ConfigError::RewardSweepKeyMismatch(sweep_address.to_user_friendly_address(),
+                            reward_address.to_user_friendly_address()).into());
+                    }
+                }
+
                 client_builder.with_service_flags(ServiceFlags::VALIDATOR);
+                client_builder.with_node_role(NodeRole::Validator);
 
                 let validator_config = ValidatorConfig {
-                    validator_key,
+                    validator_keys: Arc::new(validator_keys),
+                    reward_address: reward_address.or(sweep_address),
+                    reward_sweep,
+                    liveness: Arc::new(ValidatorLiveness::new()),
+                    verify_blocks: validator_settings.verify_blocks,
+                    extra_data: validator_settings.extra_data.clone()
+                        .map(|extra_data| ExtraDataProvider::Static(extra_data.into_bytes()))
+                        .unwrap_or_default(),
+                    blacklist: Arc::new(Blacklist::new(
+                        validator_settings.blacklist.addresses.iter()
+                            .map(|address| Address::from_user_friendly_address(address))
+                            .collect::<Result<Vec<Address>, _>>()?,
+                        validator_settings.blacklist.transactions.iter()
+                            .map(|hash| Blake2bHash::from_str(hash))
+                            .collect::<Result<Vec<Blake2bHash>, _>>()?,
+                    )),
+                    local_transactions_size: validator_settings.local_transactions_size,
+                    transaction_selector: if validator_settings.exclude_contract_creations {
+                        Arc::new(FilteredMempoolOrder::without_contract_creations())
+                    }
+                    else {
+                        Arc::new(MempoolOrder)
+                    },
+                    verification_threads: settings.threads.handel_verification(),
+                    blockchain_event_threads: settings.threads.blockchain_events(),
+                    // Set once the collector is started with the consensus.
+                    gc: None,
+                    manual_block_production: validator_settings.manual_block_production,
                 };
-                run_albatross_validator_node(client_builder, settings, validator_config)
+                run_albatross_validator_node(client_builder, settings, validator_config, epoch_digests, sandbox)
             },
             None => {
                 info!("No validator");
                 info!("Ignoring validator config");
-                run_albatross_node(client_builder, settings, ())
+                run_albatross_node(client_builder, settings, (), epoch_digests, sandbox)
             }
         }
     }
     else {
-        run_nimiq_node(client_builder, settings, ())?;
+        run_nimiq_node(client_builder, settings, (), sandbox)?;
     }
 }
 
+/// Fills in default file locations and database settings for everything that isn't configured, so
+/// `settings` becomes the effective configuration.
+fn resolve_files(settings: &mut ClientConfig, files: &mut LazyFileLocations) -> Result<(), Error> {
+    let network_id = NetworkId::from(settings.consensus.network);
+    let default_database_settings = s::DatabaseSettings::default();
+
+    if settings.database.path.is_none() {
+        settings.database.path = Some(files.database(network_id)?.to_str().unwrap().to_string());
+    }
+    settings.database.size = settings.database.size.or(default_database_settings.size);
+    settings.database.max_dbs = settings.database.max_dbs.or(default_database_settings.max_dbs);
+
+    if settings.peer_key_file.is_none() {
+        settings.peer_key_file = Some(files.peer_key()?.to_str().unwrap().to_string());
+    }
+    if let Some(ref mut validator_settings) = settings.validator {
+        if validator_settings.key_file.is_none() {
+            validator_settings.key_file = Some(files.validator_key()?.to_str().unwrap().to_string());
+        }
+        if validator_settings.previous_key_file.is_none() {
+            let key_file = validator_settings.key_file.as_ref().unwrap();
+            validator_settings.previous_key_file = Some(format!("{}.previous", key_file));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the blockchain database and prints the issues found. With `repair`, invalid and orphaned
+/// entries are removed. Exits with an error if issues remain.
+fn verify_database(network_id: NetworkId, repair: bool) -> Result<!, Error> {
+    if !network_id.is_albatross() {
+        eprintln!("Database verification is only supported on Albatross networks.");
+        process::exit(1);
+    }
+
+    let report = verify_databases(ENV.get());
+    for issue in report.issues.iter() {
+        eprintln!("{}", issue);
+    }
+    eprintln!("Checked {} entries, found {} issues.", report.checked, report.issues.len());
+
+    let mut remaining = report.issues.len();
+    if repair && report.prunable().next().is_some() {
+        if ENV.get().is_read_only() {
+            eprintln!("Can't repair a read-only database.");
+            process::exit(1);
+        }
+        let pruned = repair_databases(ENV.get(), &report);
+        eprintln!("Removed {} entries.", pruned);
+        remaining -= pruned;
+    }
+
+    process::exit(if remaining == 0 { 0 } else { 1 });
+}
+
+/// Validates the configuration and the key files it refers to and prints the effective
+/// configuration. Exits without starting the node.
+fn check_config(mut settings: ClientConfig, config_file: &Path, files: &mut LazyFileLocations) -> Result<!, Error> {
+    let mut problems: Vec<String> = match settings.validate() {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors.0.iter().map(|e| e.to_string()).collect(),
+    };
+
+    resolve_files(&mut settings, files)?;
+    if let Err(e) = check_key_file::<PrivateKey>(settings.peer_key_file.as_ref().unwrap()) {
+        problems.push(format!("Can't load peer key: {}", e));
+    }
+    if let Some(key_file) = settings.validator.as_ref().and_then(|v| v.key_file.as_ref()) {
+        if let Err(e) = check_key_file::<KeyPair>(key_file) {
+            problems.push(format!("Can't load validator key: {}", e));
+        }
+    }
+    // The previous key file only exists while a key rotation is in progress.
+    if let Some(key_file) = settings.validator.as_ref().and_then(|v| v.previous_key_file.as_ref()) {
+        if let Err(e) = check_key_file::<KeyPair>(key_file) {
+            problems.push(format!("Can't load previous validator key: {}", e));
+        }
+    }
+    if let Some(sweep_settings) = settings.validator.as_ref().and_then(|v| v.reward_sweep.as_ref()) {
+        if let Err(e) = KeyStore::new(sweep_settings.key_file.clone()).load_key::<keys::KeyPair>() {
+            problems.push(format!("Can't load reward sweep key: {}", e));
+        }
+    }
+
+    println!("# Effective configuration loaded from {}", config_file.display());
+    print!("{}", settings.to_toml()?);
+
+    if problems.is_empty() {
+        eprintln!("Configuration is valid.");
+        process::exit(0);
+    }
+    for problem in problems {
+        eprintln!("Error: {}", problem);
+    }
+    process::exit(1);
+}
+
+/// Checks that a key file can be loaded. Key files that don't exist yet are generated on start.
+fn check_key_file<K: beserial::Serialize + beserial::Deserialize>(path: &str) -> Result<(), KeyStoreError> {
+    if !Path::new(path).exists() {
+        return Ok(());
+    }
+    KeyStore::new(path.to_string()).load_key::<K>()?;
+    Ok(())
+}
+
 #[cfg(feature = "rpc-server")]
 fn build_rpc_server(rpc_settings: Option<RpcServerSettings>) -> Result<Option<(OtherFuture, Arc<RpcHandler>)>, Error> {
     let rpc_settings = if let Some(s) = rpc_settings {
@@ -482,7 +953,7 @@ fn build_rpc_server(rpc_settings: Option<RpcServerSettings>) -> Result<Option<(O
     info!("Starting RPC server listening on {}, port {}", bind, port);
 
     let credentials = match (&rpc_settings.username, &rpc_settings.password) {
-        (Some(username), Some(password)) => Some(Credentials::new(&username, &password)),
+        (Some(username), Some(password)) => Some(Credentials::new(&username, password.clone())),
         (None, None) => None,
         _ => return Err(ConfigError::MissingRpcCredentials.into())
     };
@@ -499,26 +970,42 @@ fn build_rpc_server(rpc_settings: Option<RpcServerSettings>) -> Result<Option<(O
         credentials,
         methods: HashSet::from_iter(rpc_settings.methods),
         allowip: (), // TODO
-        corsdomain: rpc_settings.corsdomain
+        corsdomain: rpc_settings.corsdomain,
+        rate_limit: rpc_settings.rate_limit,
     };
 
+    let tls = rpc_settings.tls.map(|tls_settings| TlsConfig {
+        certificate_file: tls_settings.certificate_file,
+        private_key_file: tls_settings.private_key_file,
+        client_ca_file: tls_settings.client_ca_file,
+    });
+
     let rpc_handler = Arc::new(RpcHandler::new(config));
-    let future = Box::new(rpc_server(bind, port, Arc::clone(&rpc_handler))?);
+    let future = Box::new(rpc_server(bind, port, Arc::clone(&rpc_handler), tls)?);
     Ok(Some((future, rpc_handler)))
 }
 
+/// `validator` tells whether this node runs a validator, which only Albatross validator nodes do.
 #[cfg(feature = "rpc-server")]
-fn add_generic_rpc_modules<CP>(handler: &Arc<RpcHandler>, consensus: &Arc<Consensus<CP>>) -> Arc<RwLock<UnlockedWalletManager>>
-    where CP: ConsensusProtocol
+fn add_generic_rpc_modules<CP>(handler: &Arc<RpcHandler>, consensus: &Arc<Consensus<CP>>, settings: &ClientConfig, validator: bool, slot_schedule: Option<Arc<SlotSchedule<'static>>>) -> Arc<RwLock<UnlockedWalletManager>>
+    where CP: ConsensusProtocol + 'static
 {
     let consensus_handler = ConsensusHandler::new(Arc::clone(&consensus));
     let wallet_handler = WalletHandler::new(consensus.env);
-    let network_handler = NetworkHandler::new(&consensus);
+    let network_handler = NetworkHandler::new(&consensus, NodeFeatures {
+        version: lib::client::VERSION.clone(),
+        validator,
+        metrics: settings.metrics_server.is_some(),
+    });
     let unlocked_wallets = Arc::clone(&wallet_handler.unlocked_wallets);
 
     handler.add_module(consensus_handler);
     handler.add_module(network_handler);
     handler.add_module(wallet_handler);
+    handler.add_module(AdminHandler::<CP>::new(Arc::clone(&consensus.mempool)));
+    if let Some(ref backup_dir) = settings.database.backup_dir {
+        handler.add_module(DatabaseHandler::new(consensus.env, PathBuf::from(backup_dir), slot_schedule));
+    }
 
     unlocked_wallets
 }
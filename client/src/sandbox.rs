@@ -0,0 +1,254 @@
+use std::path::{Path, PathBuf};
+
+use failure::Fail;
+
+use lib::config::ClientConfig;
+
+/// System files the node reads after initialization, e.g. for name resolution and TLS.
+const SYSTEM_READ_PATHS: &[&str] = &[
+    "/etc/hosts",
+    "/etc/resolv.conf",
+    "/etc/nsswitch.conf",
+    "/etc/gai.conf",
+    "/etc/ssl",
+    "/etc/pki",
+    "/usr/lib/ssl",
+    "/usr/share/ca-certificates",
+    "/dev/urandom",
+];
+
+#[derive(Debug, Fail)]
+pub enum SandboxError {
+    #[fail(display = "Sandboxing is not supported on this platform")]
+    Unsupported,
+    #[cfg(target_os = "linux")]
+    #[fail(display = "Failed to restrict filesystem access: {}", _0)]
+    Landlock(#[cause] landlock::RulesetError),
+    #[cfg(target_os = "linux")]
+    #[fail(display = "Failed to build syscall filter: {}", _0)]
+    SeccompFilter(#[cause] seccompiler::BackendError),
+    #[cfg(target_os = "linux")]
+    #[fail(display = "Failed to install syscall filter: {}", _0)]
+    Seccomp(#[cause] seccompiler::Error),
+}
+
+#[cfg(target_os = "linux")]
+impl From<landlock::RulesetError> for SandboxError {
+    fn from(e: landlock::RulesetError) -> Self {
+        SandboxError::Landlock(e)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<seccompiler::BackendError> for SandboxError {
+    fn from(e: seccompiler::BackendError) -> Self {
+        SandboxError::SeccompFilter(e)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<seccompiler::Error> for SandboxError {
+    fn from(e: seccompiler::Error) -> Self {
+        SandboxError::Seccomp(e)
+    }
+}
+
+/// The directory containing `file`.
+fn parent_dir(file: &str) -> PathBuf {
+    match Path::new(file).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Restrictions that are applied to the node process once it is initialized, so that a bug in
+/// the network-facing code can't be used to access the rest of the system.
+#[derive(Debug)]
+pub struct Sandbox {
+    filesystem: bool,
+    syscalls: bool,
+    read_write_paths: Vec<PathBuf>,
+    read_paths: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// Returns the sandbox configured in `settings`, if any. Must be called after the file
+    /// locations in `settings` have been resolved.
+    pub fn from_config(settings: &ClientConfig, config_file: &Path) -> Option<Self> {
+        let sandbox_settings = settings.sandbox.as_ref()?;
+
+        let mut read_write_paths = Vec::new();
+        read_write_paths.extend(settings.database.path.iter().map(PathBuf::from));
+        read_write_paths.extend(settings.database.backup_dir.iter().map(PathBuf::from));
+        read_write_paths.extend(settings.database.history_dir.iter().map(PathBuf::from));
+        if let Some(ref updater_settings) = settings.updater {
+            read_write_paths.extend(updater_settings.download_dir.iter().map(PathBuf::from));
+        }
+        if let Some(ref validator_settings) = settings.validator {
+            // Key rotations rewrite the key files and create and remove the previous key file,
+            // which needs access to the directories containing them.
+            let key_files = validator_settings.key_file.iter().chain(validator_settings.previous_key_file.iter());
+            for dir in key_files.map(|key_file| parent_dir(key_file)) {
+                if !read_write_paths.contains(&dir) {
+                    read_write_paths.push(dir);
+                }
+            }
+        }
+
+        let mut read_paths = vec![config_file.to_path_buf()];
+        read_paths.extend(settings.peer_key_file.iter().map(PathBuf::from));
+        if let Some(ref validator_settings) = settings.validator {
+            read_paths.extend(validator_settings.reward_sweep.iter().map(|sweep| PathBuf::from(&sweep.key_file)));
+        }
+        if let Some(ref tls_settings) = settings.network.tls {
+            read_paths.push(PathBuf::from(&tls_settings.identity_file));
+        }
+        read_paths.extend(SYSTEM_READ_PATHS.iter().map(PathBuf::from));
+        read_paths.extend(sandbox_settings.read_paths.iter().map(PathBuf::from));
+
+        Some(Sandbox {
+            filesystem: sandbox_settings.filesystem.unwrap_or(true),
+            syscalls: sandbox_settings.syscalls.unwrap_or(true),
+            read_write_paths,
+            read_paths,
+        })
+    }
+
+    /// Applies the sandbox to all threads of the process. This can't be undone.
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self) -> Result<(), SandboxError> {
+        if self.filesystem {
+            self.restrict_filesystem()?;
+        }
+        if self.syscalls {
+            self.restrict_syscalls()?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self) -> Result<(), SandboxError> {
+        Err(SandboxError::Unsupported)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn restrict_filesystem(&self) -> Result<(), SandboxError> {
+        use landlock::{ABI, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus, path_beneath_rules};
+
+        // Rules can only be added for paths that exist.
+        let existing = |paths: &[PathBuf]| paths.iter()
+            .filter(|path| path.exists())
+            .cloned()
+            .collect::<Vec<PathBuf>>();
+
+        let abi = ABI::V1;
+        let status = Ruleset::new()
+            .handle_access(AccessFs::from_all(abi))?
+            .create()?
+            .add_rules(path_beneath_rules(existing(&self.read_write_paths), AccessFs::from_all(abi)))?
+            .add_rules(path_beneath_rules(existing(&self.read_paths), AccessFs::from_read(abi)))?
+            .restrict_self()?;
+
+        match status.ruleset {
+            RulesetStatus::FullyEnforced => info!("Filesystem access restricted to: {:?}", self.read_write_paths),
+            RulesetStatus::PartiallyEnforced => warn!("Filesystem access is only partially restricted by this kernel"),
+            RulesetStatus::NotEnforced => warn!("Landlock is not supported by this kernel, filesystem access is not restricted"),
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn restrict_syscalls(&self) -> Result<(), SandboxError> {
+        use std::collections::BTreeMap;
+        use std::convert::TryInto;
+
+        use seccompiler::{BpfProgram, SeccompAction, SeccompFilter, SeccompRule, TargetArch};
+
+        /// Syscalls the node never needs. Everything else is allowed, so that dependencies
+        /// keep working.
+        const DENIED_SYSCALLS: &[i64] = &[
+            libc::SYS_execve, libc::SYS_execveat,
+            libc::SYS_ptrace, libc::SYS_process_vm_readv, libc::SYS_process_vm_writev,
+            libc::SYS_mount, libc::SYS_umount2, libc::SYS_pivot_root, libc::SYS_chroot,
+            libc::SYS_unshare, libc::SYS_setns,
+            libc::SYS_setuid, libc::SYS_setgid, libc::SYS_setreuid, libc::SYS_setregid,
+            libc::SYS_setresuid, libc::SYS_setresgid,
+            libc::SYS_init_module, libc::SYS_finit_module, libc::SYS_delete_module,
+            libc::SYS_kexec_load, libc::SYS_reboot, libc::SYS_swapon, libc::SYS_swapoff,
+            libc::SYS_bpf, libc::SYS_perf_event_open, libc::SYS_userfaultfd, libc::SYS_personality,
+            libc::SYS_add_key, libc::SYS_request_key, libc::SYS_keyctl,
+        ];
+
+        let arch = match std::env::consts::ARCH {
+            "x86_64" => TargetArch::x86_64,
+            "aarch64" => TargetArch::aarch64,
+            _ => return Err(SandboxError::Unsupported),
+        };
+
+        // An empty rule list matches the syscall regardless of its arguments.
+        let rules = DENIED_SYSCALLS.iter()
+            .map(|&syscall| (syscall, Vec::<SeccompRule>::new()))
+            .collect::<BTreeMap<i64, Vec<SeccompRule>>>();
+        let filter = SeccompFilter::new(rules, SeccompAction::Allow, SeccompAction::Errno(libc::EPERM as u32), arch)?;
+        let program: BpfProgram = filter.try_into()?;
+        seccompiler::apply_filter_all_threads(&program)?;
+
+        info!("Denied {} syscalls", DENIED_SYSCALLS.len());
+        Ok(())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::thread;
+
+    use rand::thread_rng;
+    use tempdir::TempDir;
+
+    use bls::bls12_381::KeyPair;
+    use lib::block_producer::albatross::ValidatorKeys;
+    use lib::config::{SandboxSettings, ValidatorSettings};
+    use utils::key_store::KeyStore;
+
+    use super::*;
+
+    #[test]
+    fn it_allows_validator_key_rotations() {
+        let dir = TempDir::new("sandbox").unwrap();
+        let key_file = dir.path().join("validator_key.dat").to_str().unwrap().to_string();
+        let previous_key_file = format!("{}.previous", key_file);
+        let config_file = dir.path().join("client.toml");
+
+        let mut settings = ClientConfig::default();
+        settings.validator = Some(ValidatorSettings {
+            key_file: Some(key_file.clone()),
+            previous_key_file: Some(previous_key_file.clone()),
+            ..Default::default()
+        });
+        settings.sandbox = Some(SandboxSettings {
+            filesystem: Some(true),
+            syscalls: Some(false),
+            read_paths: Vec::new(),
+        });
+        let sandbox = Sandbox::from_config(&settings, &config_file).unwrap();
+
+        let key = KeyPair::generate(&mut thread_rng());
+        let new_key = KeyPair::generate(&mut thread_rng());
+        KeyStore::new(key_file.clone()).save_key(&key).unwrap();
+
+        // Landlock only restricts the thread that applies it, so the rest of the tests aren't
+        // affected.
+        thread::spawn(move || {
+            sandbox.restrict_filesystem().unwrap();
+
+            let keys = ValidatorKeys::new(key.clone(), None)
+                .with_key_stores(KeyStore::new(key_file.clone()), KeyStore::new(previous_key_file.clone()));
+            keys.rotate(new_key.clone()).unwrap();
+            assert_eq!(KeyStore::new(previous_key_file.clone()).load_key().ok(), Some(key.clone()));
+            assert_eq!(KeyStore::new(key_file.clone()).load_key().ok(), Some(new_key.clone()));
+
+            assert_eq!(keys.complete_rotation(|_| false), Some(key.public.compress()));
+            assert!(!Path::new(&previous_key_file).exists());
+        }).join().unwrap();
+    }
+}
@@ -11,7 +11,7 @@ use futures::Poll;
 use futures::prelude::*;
 use futures::task;
 use futures::task::Task;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 
 use beserial::Serialize;
 use blockchain_base::{AbstractBlockchain, BlockchainEvent};
@@ -19,6 +19,7 @@ use database::Environment;
 use database::ReadTransaction;
 use hash::Blake2bHash;
 use utils::mutable_once::MutableOnce;
+use utils::observer::{Subscription, weak_listener};
 
 pub type SerializedChunk = Vec<u8>;
 
@@ -29,7 +30,8 @@ pub struct AccountsChunkCache<B: AbstractBlockchain<'static> + 'static> {
     chunks_by_prefix_by_block: RwLock<HashMap<Blake2bHash, HashMap<String, SerializedChunk>>>,
     tasks_by_block: RwLock<HashMap<Blake2bHash, Vec<Task>>>,
     block_history_order: RwLock<VecDeque<Blake2bHash>>,
-    weak_self: MutableOnce<Weak<Self>>
+    weak_self: MutableOnce<Weak<Self>>,
+    subscription: Mutex<Option<Subscription<'static, BlockchainEvent<B::Block>>>>,
 }
 
 impl<B: AbstractBlockchain<'static> + 'static> AccountsChunkCache<B> {
@@ -46,12 +48,14 @@ impl<B: AbstractBlockchain<'static> + 'static> AccountsChunkCache<B> {
             tasks_by_block: RwLock::new(HashMap::with_capacity(Self::MAX_BLOCKS_BACKLOG + 1)),
             block_history_order: RwLock::new(VecDeque::with_capacity(Self::MAX_BLOCKS_BACKLOG + 1)),
             weak_self: MutableOnce::new(Weak::new()),
+            subscription: Mutex::new(None),
         };
         let cache_arc = Arc::new(cache);
         unsafe { cache_arc.weak_self.replace(Arc::downgrade(&cache_arc)) };
 
-        let cache_arc2 = Arc::clone(&cache_arc);
-        cache_arc.blockchain.register_listener(move |event: &BlockchainEvent<B::Block>| cache_arc2.on_blockchain_event(event));
+        let subscription = cache_arc.blockchain.subscribe_listener(weak_listener(Arc::downgrade(&cache_arc),
+            |cache, event: &BlockchainEvent<B::Block>| cache.on_blockchain_event(event)));
+        *cache_arc.subscription.lock() = Some(subscription);
 
         cache_arc
     }
@@ -66,6 +70,21 @@ impl<B: AbstractBlockchain<'static> + 'static> AccountsChunkCache<B> {
         GetChunkFuture::new(hash.clone(), prefix.to_string(), self.weak_self.upgrade().unwrap())
     }
 
+    /// Size of all cached chunks in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.chunks_by_prefix_by_block.read().values()
+            .flat_map(|chunks_by_prefix| chunks_by_prefix.values())
+            .map(Vec::len)
+            .sum()
+    }
+
+    /// Drops all cached chunks. Chunks that are being computed right now are dropped as well,
+    /// pending requests for them resolve to `None`.
+    pub fn clear(&self) {
+        self.chunks_by_prefix_by_block.write().clear();
+        self.block_history_order.write().clear();
+    }
+
     /// Trigger computation of chunks asynchronously after blockchain events.
     fn on_blockchain_event(&self, event: &BlockchainEvent<B::Block>) {
         if !self.computing_enabled.load(Ordering::Acquire) {
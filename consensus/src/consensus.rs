@@ -5,19 +5,21 @@ use std::collections::HashMap;
 use std::sync::{Arc, Weak};
 use std::time::Duration;
 
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 
 use blockchain_base::{AbstractBlockchain, BlockchainEvent};
 use database::Environment;
-use mempool::{Mempool, MempoolEvent, MempoolConfig};
+use hash::{Blake2bHash, Hash};
+use mempool::{BroadcastStrategy, Mempool, MempoolEvent, MempoolConfig};
 use network::{Network, NetworkConfig, NetworkEvent, Peer};
 use network_primitives::networks::NetworkId;
 use network_primitives::time::NetworkTime;
 use transaction::Transaction;
+use utils::memory::{MemoryAccountant, MemoryCategory, MemoryPressure};
 use utils::mutable_once::MutableOnce;
-use utils::observer::Notifier;
+use utils::observer::{Notifier, Subscription};
 use utils::timers::Timers;
 
 use crate::accounts_chunk_cache::AccountsChunkCache;
@@ -25,12 +27,19 @@ use crate::consensus_agent::{ConsensusAgent, ConsensusAgentEvent};
 use crate::error::Error;
 use crate::inventory::InventoryManager;
 use crate::protocol::ConsensusProtocol;
+use crate::sync_throttle::SyncThrottle;
+use crate::tx_relay::TxRelayMonitor;
 
 pub struct Consensus<P: ConsensusProtocol + 'static> {
     pub blockchain: Arc<P::Blockchain>,
     pub mempool: Arc<Mempool<'static, P::Blockchain>>,
     pub network: Arc<Network<P::Blockchain>>,
     pub env: &'static Environment,
+    pub tx_relay: Arc<TxRelayMonitor>,
+    pub memory: Arc<MemoryAccountant>,
+    pub sync_throttle: Arc<SyncThrottle>,
+    relay_transactions: bool,
+    broadcast_strategy: BroadcastStrategy,
 
     inv_mgr: Arc<RwLock<InventoryManager<P::Blockchain, P::MessageAdapter>>>,
     timers: Timers<ConsensusTimer>,
@@ -40,6 +49,9 @@ pub struct Consensus<P: ConsensusProtocol + 'static> {
 
     self_weak: MutableOnce<Weak<Consensus<P>>>,
     pub notifier: RwLock<Notifier<'static, ConsensusEvent>>,
+    network_subscription: Mutex<Option<Subscription<'static, NetworkEvent>>>,
+    mempool_subscription: Mutex<Option<Subscription<'static, MempoolEvent>>>,
+    blockchain_subscription: Mutex<Option<Subscription<'static, BlockchainEvent<<P::Blockchain as AbstractBlockchain<'static>>::Block>>>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -54,6 +66,8 @@ pub enum ConsensusEvent {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum ConsensusTimer {
     Sync,
+    MemoryCheck,
+    MempoolSweep,
 }
 
 type ConsensusAgentMap<P> = HashMap<Arc<Peer>, Arc<ConsensusAgent<<P as ConsensusProtocol>::Blockchain, <P as ConsensusProtocol>::MessageAdapter>>>;
@@ -61,19 +75,31 @@ type ConsensusAgentMap<P> = HashMap<Arc<Peer>, Arc<ConsensusAgent<<P as Consensu
 struct ConsensusState<P: ConsensusProtocol + 'static> {
     established: bool,
     agents: ConsensusAgentMap<P>,
+    agent_subscriptions: HashMap<Arc<Peer>, Subscription<'static, ConsensusAgentEvent>>,
 
     sync_peer: Option<Arc<Peer>>,
+    memory_pressure: MemoryPressure,
 }
 
 impl<P: ConsensusProtocol + 'static> Consensus<P> {
     const MIN_FULL_NODES: usize = 0;
     const SYNC_THROTTLE: Duration = Duration::from_millis(1500);
-
-    pub fn new(env: &'static Environment, network_id: NetworkId, network_config: NetworkConfig, mempool_config: MempoolConfig) -> Result<Arc<Self>, Error> {
+    const MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+    /// Number of peers that are disconnected per memory check while the memory pressure is critical.
+    const MEMORY_SHED_PEERS: u32 = 2;
+    const MEMPOOL_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Creates the consensus. If `memory_budget` is set, load is shed when the memory used by
+    /// the mempool, peer send queues and caches gets close to it. If `sync_rate_limit` is set,
+    /// at most that many blocks per second are applied until consensus is established.
+    pub fn new(env: &'static Environment, network_id: NetworkId, network_config: NetworkConfig, mempool_config: MempoolConfig, memory_budget: Option<usize>, sync_rate_limit: Option<usize>) -> Result<Arc<Self>, Error> {
         let network_time = Arc::new(NetworkTime::new());
         let blockchain = Arc::new(<P::Blockchain as AbstractBlockchain<'static>>::new(env, network_id, Arc::clone(&network_time))?);
+        let relay_transactions = mempool_config.relay_transactions;
+        let broadcast_strategy = mempool_config.broadcast_strategy;
         let mempool = Mempool::new(blockchain.clone(), mempool_config);
         let network = Network::new(blockchain.clone(), network_config, network_time, network_id)?;
+        network.connections.persist_bans(env);
         let accounts_chunk_cache = AccountsChunkCache::new(env, Arc::clone(&blockchain));
 
         let this = Arc::new(Consensus {
@@ -81,6 +107,11 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
             mempool,
             network,
             env,
+            tx_relay: Arc::new(TxRelayMonitor::new()),
+            memory: Arc::new(MemoryAccountant::new(memory_budget)),
+            sync_throttle: Arc::new(SyncThrottle::new(sync_rate_limit)),
+            relay_transactions,
+            broadcast_strategy,
 
             inv_mgr: InventoryManager::new(),
             timers: Timers::new(),
@@ -89,12 +120,17 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
             state: RwLock::new(ConsensusState {
                 established: false,
                 agents: HashMap::new(),
+                agent_subscriptions: HashMap::new(),
 
                 sync_peer: None,
+                memory_pressure: MemoryPressure::Normal,
             }),
 
             self_weak: MutableOnce::new(Weak::new()),
             notifier: RwLock::new(Notifier::new()),
+            network_subscription: Mutex::new(None),
+            mempool_subscription: Mutex::new(None),
+            blockchain_subscription: Mutex::new(None),
         });
         Consensus::init_listeners(&this);
         Ok(this)
@@ -104,7 +140,7 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
         unsafe { this.self_weak.replace(Arc::downgrade(this)) };
 
         let weak = Arc::downgrade(this);
-        this.network.notifier.write().register(move |e: &NetworkEvent| {
+        let subscription = this.network.notifier.write().subscribe(move |e: &NetworkEvent| {
             let this = upgrade_weak!(weak);
             match e {
                 NetworkEvent::PeerJoined(peer) => this.on_peer_joined(Arc::clone(peer)),
@@ -112,10 +148,11 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
                 _ => {}
             }
         });
+        *this.network_subscription.lock() = Some(subscription);
 
         // Relay new (verified) transactions to peers.
         let weak = Arc::downgrade(this);
-        this.mempool.notifier.write().register(move |e: &MempoolEvent| {
+        let subscription = this.mempool.notifier.write().subscribe(move |e: &MempoolEvent| {
             let this = upgrade_weak!(weak);
             match e {
                 MempoolEvent::TransactionAdded(_, transaction) => this.on_transaction_added(transaction),
@@ -123,15 +160,39 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
                 MempoolEvent::TransactionRestored(transaction) => this.on_transaction_added(transaction),
                 MempoolEvent::TransactionEvicted(transaction) => this.on_transaction_removed(transaction),
                 MempoolEvent::TransactionMined(transaction) => this.on_transaction_removed(transaction),
+                // The replacement is relayed when it's added.
+                MempoolEvent::TransactionReplaced(transaction, _) => this.on_transaction_removed(transaction),
             }
         });
+        *this.mempool_subscription.lock() = Some(subscription);
 
         // Notify peers when our blockchain head changes.
         let weak = Arc::downgrade(this);
-        this.blockchain.register_listener(move |e: &BlockchainEvent<<P::Blockchain as AbstractBlockchain<'static>>::Block>| {
+        let subscription = this.blockchain.subscribe_listener(move |e: &BlockchainEvent<<P::Blockchain as AbstractBlockchain<'static>>::Block>| {
             let this = upgrade_weak!(weak);
             this.on_blockchain_event(e);
         });
+        *this.blockchain_subscription.lock() = Some(subscription);
+
+        // Periodically account memory usage and shed load if we are over budget.
+        if this.memory.budget().is_some() {
+            let weak = Arc::downgrade(this);
+            this.timers.set_interval(ConsensusTimer::MemoryCheck, move || {
+                let this = upgrade_weak!(weak);
+                this.check_memory();
+            }, Self::MEMORY_CHECK_INTERVAL);
+        }
+
+        // Periodically evict expired transactions, which would otherwise stay in the mempool
+        // until the next block is applied.
+        let weak = Arc::downgrade(this);
+        this.timers.set_interval(ConsensusTimer::MempoolSweep, move || {
+            let this = upgrade_weak!(weak);
+            let expired = this.mempool.evict_expired();
+            if expired > 0 {
+                debug!("Evicted {} expired transactions from the mempool", expired);
+            }
+        }, Self::MEMPOOL_SWEEP_INTERVAL);
     }
 
     fn on_peer_joined(&self, peer: Arc<Peer>) {
@@ -140,12 +201,14 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
             self.blockchain.clone(),
             self.mempool.clone(),
             self.inv_mgr.clone(),
+            self.tx_relay.clone(),
+            self.sync_throttle.clone(),
             self.accounts_chunk_cache.clone(),
             peer.clone());
 
         let weak = self.self_weak.clone();
         let peer_arc_moved = peer.clone();
-        agent.notifier.write().register(move |e: &ConsensusAgentEvent| {
+        let subscription = agent.notifier.write().subscribe(move |e: &ConsensusAgentEvent| {
             let this = upgrade_weak!(weak);
             match e {
                 ConsensusAgentEvent::Synced => this.on_peer_synced(peer_arc_moved.clone()),
@@ -160,7 +223,9 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
             this.sync_blockchain();
         }, Self::SYNC_THROTTLE);
 
-        self.state.write().agents.insert(peer, agent);
+        let mut state = self.state.write();
+        state.agent_subscriptions.insert(Arc::clone(&peer), subscription);
+        state.agents.insert(peer, agent);
     }
 
     fn on_peer_left(&self, peer: Arc<Peer>) {
@@ -169,6 +234,7 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
             let mut state = self.state.write();
 
             state.agents.remove(&peer);
+            state.agent_subscriptions.remove(&peer);
 
             // Reset syncPeer if it left during the sync.
             if state.sync_peer.as_ref().map_or(false, |sync_peer| sync_peer == &peer) {
@@ -221,7 +287,11 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
         if !state.established {
             let height = self.blockchain.head_height();
             if height % 100 == 0 {
-                info!("Now at block #{}", height);
+                match self.sync_throttle.progress(height).and_then(|progress| progress.eta.map(|eta| (progress, eta))) {
+                    Some((progress, eta)) => info!("Now at block #{} of #{} ({:.1} blocks/s, ~{}s remaining)",
+                                                   height, progress.highest_block, progress.blocks_per_second, eta.as_secs()),
+                    None => info!("Now at block #{}", height),
+                }
             }
             return;
         } else {
@@ -233,9 +303,15 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
                 agent.relay_block(block);
             }
         }
+
+        self.rebroadcast_stale_transactions(&state);
     }
 
     fn on_transaction_added(&self, transaction: &Arc<Transaction>) {
+        if !self.relay_transactions {
+            return;
+        }
+
         let state = self.state.read();
 
         // Don't relay transactions if we are not synced yet.
@@ -243,8 +319,58 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
             return;
         }
 
-        for agent in state.agents.values() {
-            agent.relay_transaction(transaction.as_ref());
+        // Only our own transactions are subject to the broadcast strategy.
+        let strategy = match self.broadcast_strategy {
+            BroadcastStrategy::Flood => BroadcastStrategy::Flood,
+            strategy => if self.mempool.is_local(&transaction.hash::<Blake2bHash>()) { strategy } else { BroadcastStrategy::Flood },
+        };
+
+        Self::broadcast_transaction(&state.agents, transaction, strategy, false);
+    }
+
+    /// Relays the local transactions that weren't mined in time again, see
+    /// `Mempool::stale_local_transactions`.
+    fn rebroadcast_stale_transactions(&self, state: &ConsensusState<P>) {
+        if !self.relay_transactions {
+            return;
+        }
+
+        let transactions = self.mempool.stale_local_transactions(self.blockchain.head_height());
+        if !transactions.is_empty() {
+            debug!("Rebroadcasting {} local transactions that weren't mined yet", transactions.len());
+        }
+        for transaction in transactions {
+            Self::broadcast_transaction(&state.agents, &transaction, self.broadcast_strategy, true);
+        }
+    }
+
+    fn broadcast_transaction(agents: &ConsensusAgentMap<P>, transaction: &Transaction, strategy: BroadcastStrategy, rebroadcast: bool) {
+        let agents: Vec<_> = match strategy {
+            BroadcastStrategy::Flood => agents.values().collect(),
+            BroadcastStrategy::Random(count) => {
+                let agents: Vec<_> = agents.values().collect();
+                agents.choose_multiple(&mut thread_rng(), count).cloned().collect()
+            },
+            BroadcastStrategy::ValidatorsFirst => {
+                let validators: Vec<_> = agents.values()
+                    .filter(|agent| agent.peer.peer_address().services.is_validator())
+                    .collect();
+                if validators.is_empty() {
+                    debug!("No validator connected, relaying local transaction to all peers");
+                    agents.values().collect()
+                } else {
+                    validators
+                }
+            },
+            BroadcastStrategy::Private => Vec::new(),
+        };
+
+        for agent in agents {
+            if rebroadcast {
+                agent.rebroadcast_transaction(transaction);
+            } else {
+                agent.relay_transaction(transaction);
+            }
         }
     }
 
@@ -255,6 +381,47 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
         }
     }
 
+    fn check_memory(&self) {
+        self.memory.set(MemoryCategory::Mempool, self.mempool.size_bytes());
+        self.memory.set(MemoryCategory::PeerQueues, self.network.queued_bytes());
+        self.memory.set(MemoryCategory::Caches, self.accounts_chunk_cache.size_bytes());
+
+        let pressure = self.memory.pressure();
+        let previous_pressure = {
+            let mut state = self.state.write();
+            let previous_pressure = state.memory_pressure;
+            state.memory_pressure = pressure;
+            previous_pressure
+        };
+        if pressure != previous_pressure {
+            info!("Memory pressure changed to {:?}: {} of {} bytes used", pressure, self.memory.total(), self.memory.budget().unwrap_or(0));
+        }
+
+        if pressure >= MemoryPressure::High {
+            // The accounts chunk cache can be recomputed, so drop it first.
+            let mut excess = self.memory.excess();
+            let cache_size = self.memory.usage(MemoryCategory::Caches);
+            if cache_size > 0 {
+                self.accounts_chunk_cache.clear();
+                excess = excess.saturating_sub(cache_size);
+            }
+
+            // Then evict the transactions with the lowest fees.
+            if excess > 0 {
+                let freed = self.mempool.evict_bytes(excess);
+                debug!("Evicted {} bytes of transactions from the mempool", freed);
+            }
+        }
+
+        if pressure == MemoryPressure::Critical {
+            // Sync is paused in `sync_blockchain`. Peer queues can only be freed by disconnecting.
+            self.network.shed_peers(Self::MEMORY_SHED_PEERS);
+        } else if previous_pressure == MemoryPressure::Critical {
+            info!("Resuming sync");
+            self.sync_blockchain();
+        }
+    }
+
     fn sync_blockchain(&self) {
         let mut state = self.state.write();
 
@@ -263,6 +430,12 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
             return;
         }
 
+        // Don't start syncing with another peer until memory is freed again.
+        if state.memory_pressure == MemoryPressure::Critical {
+            debug!("Sync paused due to memory pressure");
+            return;
+        }
+
         let mut num_synced_full_nodes: usize = 0;
         let candidates: Vec<&Arc<ConsensusAgent<P::Blockchain, P::MessageAdapter>>> = state.agents.values()
             .filter(|&agent| {
@@ -292,6 +465,7 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
 
             // Notify listeners when we start syncing and have not established consensus yet.
             if !established {
+                self.sync_throttle.start(self.blockchain.head_height());
                 self.notifier.read().notify(ConsensusEvent::Syncing);
             }
 
@@ -308,6 +482,8 @@ impl<P: ConsensusProtocol + 'static> Consensus<P> {
                     state.established = true;
                     drop(state);
 
+                    self.sync_throttle.stop();
+
                     // Report consensus-established.
                     self.notifier.read().notify(ConsensusEvent::Established);
 
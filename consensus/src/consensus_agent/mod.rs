@@ -10,7 +10,7 @@ use beserial::Serialize;
 use block_base::Block;
 use blockchain_base::{AbstractBlockchain, PushError, PushResult};
 use hash::Blake2bHash;
-use mempool::{Mempool, ReturnCode};
+use mempool::{FeeFeedback, Mempool, ReturnCode};
 use network::connection::close_type::CloseType;
 use network::Peer;
 use network_messages::{
@@ -21,15 +21,18 @@ use network_messages::{
     RejectMessageCode,
     GetBlockProofMessage,
 };
+use network_primitives::version;
 use network_primitives::subscription::Subscription;
 use transaction::Transaction;
 use utils::mutable_once::MutableOnce;
 use utils::observer::{Notifier, weak_listener, weak_passthru_listener};
-use utils::rate_limit::RateLimit;
+use utils::rate_limit::RateLimiter;
 use utils::timers::Timers;
 
 use crate::inventory::{InventoryAgent, InventoryEvent, InventoryManager};
 use crate::accounts_chunk_cache::AccountsChunkCache;
+use crate::sync_throttle::SyncThrottle;
+use crate::tx_relay::TxRelayMonitor;
 
 pub mod requests;
 pub mod sync;
@@ -63,19 +66,19 @@ pub struct ConsensusAgentState {
     failed_syncs: u32,
 
     /// Rate limit for GetChainProof messages.
-    chain_proof_limit: RateLimit,
+    chain_proof_limit: Arc<RateLimiter>,
 
     /// Rate limit for GetBlockProof messages.
-    block_proof_limit: RateLimit,
+    block_proof_limit: Arc<RateLimiter>,
 
     /// Rate limit for GetTransactionReceipts messages.
-    transaction_receipts_limit: RateLimit,
+    transaction_receipts_limit: Arc<RateLimiter>,
 
     /// Rate limit for GetTransactionsProof messages.
-    transactions_proof_limit: RateLimit,
+    transactions_proof_limit: Arc<RateLimiter>,
 
     /// Rate limit for AccountsProof messages.
-    accounts_proof_limit: RateLimit,
+    accounts_proof_limit: Arc<RateLimiter>,
 }
 
 #[derive(Ord, PartialOrd, PartialEq, Eq, Hash, Clone, Copy, Debug)]
@@ -119,10 +122,10 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
     /// Maximum time to wait before triggering the initial mempool request.
     const MEMPOOL_DELAY_MAX: u64 = 20 * 1000; // in ms
 
-    pub fn new(blockchain: Arc<B>, mempool: Arc<Mempool<'static, B>>, inv_mgr: Arc<RwLock<InventoryManager<B, MA>>>, accounts_chunk_cache: Arc<AccountsChunkCache<B>>, peer: Arc<Peer>) -> Arc<Self> {
+    pub fn new(blockchain: Arc<B>, mempool: Arc<Mempool<'static, B>>, inv_mgr: Arc<RwLock<InventoryManager<B, MA>>>, tx_relay: Arc<TxRelayMonitor>, sync_throttle: Arc<SyncThrottle>, accounts_chunk_cache: Arc<AccountsChunkCache<B>>, peer: Arc<Peer>) -> Arc<Self> {
         let sync_target = peer.head_hash.clone();
         let peer_arc = peer;
-        let inv_agent = InventoryAgent::new(blockchain.clone(), mempool.clone(), inv_mgr,peer_arc.clone());
+        let inv_agent = InventoryAgent::new(blockchain.clone(), mempool.clone(), inv_mgr, tx_relay, sync_throttle, peer_arc.clone());
         let this = Arc::new(ConsensusAgent {
             blockchain,
             accounts_chunk_cache,
@@ -139,11 +142,11 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
                 num_blocks_forking: 0,
                 failed_syncs: 0,
 
-                chain_proof_limit: RateLimit::new_per_minute(Self::CHAIN_PROOF_RATE_LIMIT),
-                block_proof_limit: RateLimit::new_per_minute(Self::BLOCK_PROOF_RATE_LIMIT),
-                transaction_receipts_limit: RateLimit::new_per_minute(Self::TRANSACTION_RECEIPTS_RATE_LIMIT),
-                transactions_proof_limit: RateLimit::new_per_minute(Self::TRANSACTIONS_PROOF_RATE_LIMIT),
-                accounts_proof_limit: RateLimit::new_per_minute(Self::ACCOUNTS_PROOF_RATE_LIMIT),
+                chain_proof_limit: RateLimiter::new_per_minute("consensus_peer_chain_proofs", Self::CHAIN_PROOF_RATE_LIMIT),
+                block_proof_limit: RateLimiter::new_per_minute("consensus_peer_block_proofs", Self::BLOCK_PROOF_RATE_LIMIT),
+                transaction_receipts_limit: RateLimiter::new_per_minute("consensus_peer_transaction_receipts", Self::TRANSACTION_RECEIPTS_RATE_LIMIT),
+                transactions_proof_limit: RateLimiter::new_per_minute("consensus_peer_transactions_proofs", Self::TRANSACTIONS_PROOF_RATE_LIMIT),
+                accounts_proof_limit: RateLimiter::new_per_minute("consensus_peer_accounts_proofs", Self::ACCOUNTS_PROOF_RATE_LIMIT),
             }),
 
             notifier: RwLock::new(Notifier::new()),
@@ -197,6 +200,10 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
         self.inv_agent.relay_transaction(transaction)
     }
 
+    pub fn rebroadcast_transaction(&self, transaction: &Transaction) -> bool {
+        self.inv_agent.rebroadcast_transaction(transaction)
+    }
+
     pub fn remove_transaction(&self, transaction: &Transaction) {
         self.inv_agent.remove_transaction(transaction);
     }
@@ -250,12 +257,17 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
         // Subscribe to all announcements from the peer.
         self.inv_agent.subscribe(Subscription::Any);
 
-        // Request the peer's mempool.
+        // Request the peer's mempool. Peers that support digests only announce the transactions
+        // we are missing.
         let weak = self.self_weak.clone();
         self.timers.set_delay(ConsensusAgentTimer::Mempool, move || {
             let agent = upgrade_weak!(weak);
             agent.timers.clear_delay(&ConsensusAgentTimer::Mempool);
-            agent.inv_agent.mempool();
+            if agent.peer.version >= version::MEMPOOL_DIGEST {
+                agent.inv_agent.mempool_digest();
+            } else {
+                agent.inv_agent.mempool();
+            }
         }, Duration::from_millis(rand::thread_rng()
             .gen_range(Self::MEMPOOL_DELAY_MIN, Self::MEMPOOL_DELAY_MAX)));
 
@@ -323,7 +335,7 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
             InventoryEvent::NoNewObjectsAnnounced => self.on_no_new_objects_announced(),
             InventoryEvent::AllObjectsReceived => self.on_all_objects_received(),
             InventoryEvent::BlockProcessed(hash, result) => self.on_block_processed(hash, result),
-            InventoryEvent::TransactionProcessed(hash, result) => self.on_tx_processed(hash, result),
+            InventoryEvent::TransactionProcessed(hash, result, fee_feedback) => self.on_tx_processed(hash, result, fee_feedback.as_ref()),
             InventoryEvent::GetBlocksTimeout => self.on_get_blocks_timeout(),
             _ => {}
         }
@@ -376,23 +388,36 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
         }
     }
 
-    fn on_tx_processed(&self, hash: &Blake2bHash, result: &ReturnCode) {
-        match result {
-            ReturnCode::Accepted => {
+    fn on_tx_processed(&self, hash: &Blake2bHash, result: &ReturnCode, fee_feedback: Option<&FeeFeedback>) {
+        match (result, fee_feedback) {
+            (ReturnCode::Accepted, _) => {
                 debug!("Accepted tx {} from {}", hash, self.peer.peer_address());
             },
-            ReturnCode::Known => {
+            (ReturnCode::Known, _) => {
                 debug!("Known tx {} from {}", hash, self.peer.peer_address());
             },
-            ReturnCode::FeeTooLow => {
+            (ReturnCode::Orphan, _) => {
+                debug!("Orphan tx {} from {}", hash, self.peer.peer_address());
+            },
+            (ReturnCode::FeeTooLow, _) | (ReturnCode::Filtered, Some(_)) => {
+                // Tell the peer which fee we would accept: The extra data contains the transaction
+                // hash followed by the minimum fee for this transaction.
+                let mut extra_data = hash.serialize_to_vec();
+                let reason = match fee_feedback {
+                    Some(feedback) => {
+                        extra_data.extend(u64::from(feedback.min_fee).serialize_to_vec());
+                        format!("{:?}, min fee per byte {:.2}", feedback.reason, feedback.min_fee_per_byte())
+                    },
+                    None => String::from("Insufficient fee"),
+                };
                 self.peer.channel.send_or_close(RejectMessage::new(
                     MessageType::Tx,
                     RejectMessageCode::InsufficientFee,
-                    String::from("Sender has too many free transactions)"),
-                    Some(hash.serialize_to_vec())
+                    reason,
+                    Some(extra_data)
                 ));
             },
-            ReturnCode::Invalid => {
+            (ReturnCode::Invalid, _) => {
                 self.peer.channel.send_or_close(RejectMessage::new(
                     MessageType::Tx,
                     RejectMessageCode::Invalid,
@@ -400,9 +425,17 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
                     Some(hash.serialize_to_vec())
                 ));
             },
-            ReturnCode::Filtered => {
+            (ReturnCode::Filtered, None) => {
                 debug!("Filtered tx {} from {}", hash, self.peer.peer_address());
             },
+            (ReturnCode::Expiring, _) => {
+                self.peer.channel.send_or_close(RejectMessage::new(
+                    MessageType::Tx,
+                    RejectMessageCode::Obsolete,
+                    String::from("Transaction would expire before inclusion"),
+                    Some(hash.serialize_to_vec())
+                ));
+            },
         }
     }
 
@@ -10,7 +10,7 @@ use block_base::{Block, BlockHeader, BlockError};
 use blockchain_base::{AbstractBlockchain, Direction, PushError, PushResult};
 use collections::{LimitHashSet, UniqueLinkedList};
 use hash::{Blake2bHash, Hash};
-use mempool::{Mempool, ReturnCode};
+use mempool::{FeeFeedback, Mempool, ReturnCode};
 use network::connection::close_type::CloseType;
 use network::Peer;
 use network_messages::{
@@ -19,6 +19,7 @@ use network_messages::{
     GetBlocksMessage,
     InvVector,
     InvVectorType,
+    MempoolDigestMessage,
     Message,
     TxMessage,
 };
@@ -33,9 +34,12 @@ use utils::{
 };
 use utils::throttled_queue::ThrottledQueue;
 use collections::queue::Queue;
-use utils::rate_limit::RateLimit;
+use utils::rate_limit::RateLimiter;
 use beserial::Serialize;
 
+use crate::sync_throttle::SyncThrottle;
+use crate::tx_relay::{TxRejection, TxRelayMonitor, TxSpamScore};
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum InventoryManagerTimer {
     Request(InvVector)
@@ -147,7 +151,9 @@ pub enum InventoryEvent<BE: BlockError> {
     NoNewObjectsAnnounced,
     AllObjectsReceived,
     BlockProcessed(Blake2bHash, Result<PushResult, PushError<BE>>),
-    TransactionProcessed(Blake2bHash, ReturnCode),
+    /// A transaction was pushed into the mempool. If it was rejected because of its fee, the
+    /// `FeeFeedback` describes the fee that would have been accepted.
+    TransactionProcessed(Blake2bHash, ReturnCode, Option<FeeFeedback>),
     GetBlocksTimeout,
 }
 
@@ -156,6 +162,7 @@ enum InventoryAgentTimer {
     GetDataThrottle,
     GetData,
     GetBlocks,
+    SyncThrottle,
     TxInvVectors,
     FreeTxInvVectors,
 }
@@ -219,7 +226,13 @@ struct InventoryAgentState {
     objects_that_flew: HashSet<InvVector>,
 
     /// The rate limit for getblocks messages.
-    get_blocks_limit: RateLimit,
+    get_blocks_limit: Arc<RateLimiter>,
+
+    /// The number of transactions from the peer we validate per minute.
+    tx_relay_budget: Arc<RateLimiter>,
+
+    /// Decaying score of the rejected transactions the peer relayed to us.
+    tx_spam_score: TxSpamScore,
 
     /// A Subscription object specifying which objects should be announced to the peer.
     remote_subscription: Subscription,
@@ -227,6 +240,9 @@ struct InventoryAgentState {
     local_subscription: Subscription,
 
     last_subscription_change: Instant,
+
+    /// Whether we have sent a digest of our mempool to the peer.
+    mempool_digest_sent: bool,
 }
 
 pub struct InventoryAgent<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 'static> {
@@ -234,6 +250,8 @@ pub struct InventoryAgent<B: AbstractBlockchain<'static> + 'static, MA: MessageA
     mempool: Arc<Mempool<'static, B>>,
     peer: Arc<Peer>,
     inv_mgr: Arc<RwLock<InventoryManager<B, MA>>>,
+    tx_relay: Arc<TxRelayMonitor>,
+    sync_throttle: Arc<SyncThrottle>,
     state: RwLock<InventoryAgentState>,
     pub notifier: RwLock<Notifier<'static, InventoryEvent<<B::Block as Block>::Error>>>,
     self_weak: MutableOnce<Weak<InventoryAgent<B, MA>>>,
@@ -264,6 +282,7 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
     const TRANSACTION_THROTTLE: Duration = Duration::from_millis(1000);
     const REQUEST_TRANSACTIONS_WAITING_MAX: usize = 5000;
     const GET_BLOCKS_RATE_LIMIT: usize = 30; // per minute
+    const TRANSACTION_RELAY_BUDGET: usize = 1000; // per minute
     /// Time {ms} to wait between sending full inv vectors of transactions during Mempool request
     const MEMPOOL_THROTTLE: Duration = Duration::from_millis(1000); // 1 second
     const MEMPOOL_ENTRIES_MAX: usize = 10_000;
@@ -272,12 +291,14 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
 
     const SUBSCRIPTION_CHANGE_GRACE_PERIOD: Duration = Duration::from_secs(2);
 
-    pub fn new(blockchain: Arc<B>, mempool: Arc<Mempool<'static, B>>, inv_mgr: Arc<RwLock<InventoryManager<B, MA>>>, peer: Arc<Peer>) -> Arc<Self> {
+    pub fn new(blockchain: Arc<B>, mempool: Arc<Mempool<'static, B>>, inv_mgr: Arc<RwLock<InventoryManager<B, MA>>>, tx_relay: Arc<TxRelayMonitor>, sync_throttle: Arc<SyncThrottle>, peer: Arc<Peer>) -> Arc<Self> {
         let this = Arc::new(InventoryAgent {
             blockchain,
             mempool,
             peer,
             inv_mgr,
+            tx_relay,
+            sync_throttle,
             state: RwLock::new(InventoryAgentState {
                 bypass_mgr: false,
                 known_objects: LimitHashSet::new(Self::KNOWN_OBJECTS_COUNT_MAX),
@@ -306,7 +327,10 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
 
                 objects_that_flew: HashSet::new(),
 
-                get_blocks_limit: RateLimit::new_per_minute(Self::GET_BLOCKS_RATE_LIMIT),
+                get_blocks_limit: RateLimiter::new_per_minute("consensus_peer_get_blocks", Self::GET_BLOCKS_RATE_LIMIT),
+
+                tx_relay_budget: RateLimiter::new_per_minute("consensus_peer_tx_relay", Self::TRANSACTION_RELAY_BUDGET),
+                tx_spam_score: TxSpamScore::new(),
 
                 // Initially, we don't announce anything to the peer until it tells us otherwise.
                 remote_subscription: Subscription::None,
@@ -314,6 +338,8 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
                 local_subscription: Subscription::None,
 
                 last_subscription_change: Instant::now(),
+
+                mempool_digest_sent: false,
             }),
             notifier: RwLock::new(Notifier::new()),
             self_weak: MutableOnce::new(Weak::new()),
@@ -357,6 +383,9 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
         msg_notifier.mempool.write().register(weak_passthru_listener(
             Arc::downgrade(this),
             |this, _ | this.on_mempool()));
+        msg_notifier.mempool_digest.write().register(weak_passthru_listener(
+            Arc::downgrade(this),
+            |this, msg: MempoolDigestMessage| this.on_mempool_digest(msg)));
 
         msg_notifier.subscribe.write().register(weak_passthru_listener(
             Arc::downgrade(this),
@@ -398,6 +427,16 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
         self.peer.channel.send_or_close(Message::Mempool);
     }
 
+    /// Sends a digest of our mempool to the peer. The peer announces the transactions we are
+    /// missing and answers with its own digest, so we can announce the ones it is missing.
+    pub fn mempool_digest(&self) {
+        let transactions = self.mempool.get_transactions(Self::MEMPOOL_ENTRIES_MAX, 0f64);
+        let hashes: Vec<Blake2bHash> = transactions.iter().map(|tx| tx.hash()).collect();
+
+        self.state.write().mempool_digest_sent = true;
+        self.peer.channel.send_or_close(MempoolDigestMessage::new(hashes.iter()));
+    }
+
     pub fn subscribe(&self, subscription: Subscription) {
         let mut state = self.state.write();
         state.local_subscription = subscription.clone();
@@ -502,6 +541,7 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
 
         let hash = block.hash();
         trace!("[BLOCK] #{} ({} txs) from {}", block.height(), block.transactions().map(|txs| txs.len()).unwrap_or(0), self.peer.peer_address());
+        self.sync_throttle.note_height(block.height());
 
         // Check if we have requested this block.
         let vector = InvVector::from_block_hash(hash);
@@ -559,11 +599,27 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
         // Check whether we subscribed for this transaction.
         let state = self.state.read();
         if state.local_subscription.matches_transaction(&msg.transaction) {
+            // Don't spend any time on validating transactions if the peer exceeded its budget.
+            if !state.tx_relay_budget.note_single() {
+                drop(state);
+                debug!("Transaction relay budget of {} exceeded - discarding transaction", self.peer.peer_address());
+                self.on_tx_rejected(TxRejection::OverBudget);
+                return;
+            }
+
             // Give up read lock before pushing transaction.
             drop(state);
 
-            let result = self.mempool.push_transaction(msg.transaction);
-            self.notifier.read().notify(InventoryEvent::TransactionProcessed(vector.hash.clone(), result));
+            let transaction = msg.transaction;
+            let result = self.mempool.push_transaction(transaction.clone());
+            let fee_feedback = match result {
+                ReturnCode::FeeTooLow | ReturnCode::Filtered => self.mempool.fee_feedback(&transaction),
+                _ => None,
+            };
+            if let Some(rejection) = TxRejection::from_return_code(&result) {
+                self.on_tx_rejected(rejection);
+            }
+            self.notifier.read().notify(InventoryEvent::TransactionProcessed(vector.hash.clone(), result, fee_feedback));
         } else if state.last_subscription_change.elapsed() > Self::SUBSCRIPTION_CHANGE_GRACE_PERIOD {
             // Give up read lock.
             drop(state);
@@ -573,9 +629,50 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
         }
     }
 
+    fn on_tx_rejected(&self, rejection: TxRejection) {
+        self.tx_relay.record(rejection);
+
+        let score = self.state.write().tx_spam_score.add(rejection);
+        if score >= TxRelayMonitor::DISCONNECT_SCORE && !self.peer.channel.closed() {
+            let close_type = self.tx_relay.punish(self.peer.peer_address().peer_id());
+            warn!("Too many rejected transactions from {} (score {:.1}) - closing the channel", self.peer.peer_address(), score);
+            self.peer.channel.close(close_type);
+        }
+    }
+
     fn on_mempool(&self) {
         trace!("[MEMPOOL] from {}", self.peer.peer_address());
 
+        let transactions = match self.mempool_for_peer() {
+            Some(transactions) => transactions,
+            None => return,
+        };
+        self.send_mempool_inv_vectors(transactions);
+    }
+
+    fn on_mempool_digest(&self, msg: MempoolDigestMessage) {
+        trace!("[MEMPOOLDIGEST] {} short ids from {}", msg.short_ids.len(), self.peer.peer_address());
+
+        // Answer with our own digest, so the peer can announce what we are missing.
+        if !self.state.read().mempool_digest_sent {
+            self.mempool_digest();
+        }
+
+        let transactions = match self.mempool_for_peer() {
+            Some(transactions) => transactions,
+            None => return,
+        };
+
+        // Only announce the transactions that are not in the peer's digest.
+        let short_ids: HashSet<u64> = msg.short_ids.into_iter().collect();
+        let missing: Vec<Arc<Transaction>> = transactions.into_iter()
+            .filter(|tx| !short_ids.contains(&MempoolDigestMessage::short_id(msg.salt, &tx.hash())))
+            .collect();
+        self.send_mempool_inv_vectors(missing);
+    }
+
+    /// Returns the transactions in our mempool that match the peer's subscription.
+    fn mempool_for_peer(&self) -> Option<Vec<Arc<Transaction>>> {
         let state = self.state.read();
         // Query mempool for transactions
         let mut transactions = match &state.remote_subscription {
@@ -589,9 +686,14 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
            Subscription::Any => {
                 self.mempool.get_transactions(Self::MEMPOOL_ENTRIES_MAX, 0f64)
            },
-           Subscription::None => return,
+           Subscription::None => return None,
         };
+        // Local transactions are only announced as the broadcast strategy allows.
+        self.mempool.retain_announceable(&mut transactions);
+        Some(transactions)
+    }
 
+    fn send_mempool_inv_vectors(&self, mut transactions: Vec<Arc<Transaction>>) {
         // Send an InvVector for each transaction in the mempool.
         // Split into multiple Inv messages if the mempool is large.
         while !transactions.is_empty() {
@@ -678,9 +780,24 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
         }
 
         // Request queued objects from the peer. Only request up to VECTORS_MAX_COUNT objects at a time.
-        let num_blocks = state.blocks_to_request.len().min(Self::REQUEST_VECTORS_MAX);
+        let mut num_blocks = state.blocks_to_request.len().min(Self::REQUEST_VECTORS_MAX);
         let num_txs = Self::REQUEST_VECTORS_MAX - num_blocks; // `dequeue_multi` takes care of the above comparison
 
+        // Limit the number of blocks we apply per second while syncing.
+        if state.bypass_mgr && num_blocks > 0 {
+            num_blocks = self.sync_throttle.reserve(num_blocks);
+            if num_blocks == 0 && !state.txs_to_request.check_available() {
+                let weak = self.self_weak.clone();
+                self.timers.reset_delay(InventoryAgentTimer::SyncThrottle, move || {
+                    let this = upgrade_weak!(weak);
+                    this.timers.clear_delay(&InventoryAgentTimer::SyncThrottle);
+                    let mut state = this.state.write();
+                    this.request_vectors(&mut *state);
+                }, self.sync_throttle.time_until_next_window());
+                return;
+            }
+        }
+
         let mut vectors = Vec::new();
         for vector in state.blocks_to_request.dequeue_multi(num_blocks) {
             state.objects_in_flight.insert(vector.clone());
@@ -757,7 +874,7 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
 
     fn on_get_blocks(&self, msg: GetBlocksMessage) {
         {
-            let mut state = self.state.write();
+            let state = self.state.read();
             if !state.get_blocks_limit.note_single() {
                 warn!("Rejecting GetBlocks message - rate limit exceeded");
                 return;
@@ -949,6 +1066,14 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
         true
     }
 
+    /// Relays the transaction even if the peer is assumed to know it already, e.g. because it
+    /// wasn't mined and our first announcement might have been lost.
+    pub fn rebroadcast_transaction(&self, transaction: &Transaction) -> bool {
+        let vector = InvVector::from_tx_hash(transaction.hash());
+        self.state.write().known_objects.remove(&vector);
+        self.relay_transaction(transaction)
+    }
+
     pub fn remove_transaction(&self, transaction: &Transaction) {
         let vector = InvVector::from_tx_hash(transaction.hash());
         let mut state = self.state.write();
@@ -1006,6 +1131,8 @@ impl<B: AbstractBlockchain<'static> + 'static, MA: MessageAdapter<B::Block> + 's
     }
 
     pub fn is_busy(&self) -> bool {
-        !self.state.read().objects_in_flight.is_empty() || self.timers.delay_exists(&InventoryAgentTimer::GetBlocks)
+        !self.state.read().objects_in_flight.is_empty()
+            || self.timers.delay_exists(&InventoryAgentTimer::GetBlocks)
+            || self.timers.delay_exists(&InventoryAgentTimer::SyncThrottle)
     }
 }
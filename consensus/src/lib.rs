@@ -21,6 +21,8 @@ pub mod consensus;
 pub mod consensus_agent;
 pub mod inventory;
 pub mod error;
+pub mod sync_throttle;
+pub mod tx_relay;
 mod accounts_chunk_cache;
 mod protocol;
 
@@ -0,0 +1,142 @@
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// Progress of the initial sync.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncProgress {
+    /// Height of our head block when the sync started.
+    pub starting_block: u32,
+    pub current_block: u32,
+    /// Highest block height we have seen from our peers.
+    pub highest_block: u32,
+    /// Average number of blocks applied per second since the sync started.
+    pub blocks_per_second: f64,
+    /// Estimated time until we reach `highest_block`, if we are making progress.
+    pub eta: Option<Duration>,
+}
+
+struct SyncThrottleState {
+    active: bool,
+    starting_block: u32,
+    started_at: Instant,
+    highest_block: u32,
+    window_start: Instant,
+    window_blocks: usize,
+}
+
+/// Limits the number of blocks requested from the sync peer per second while we haven't
+/// established consensus, and tracks the progress of this initial sync.
+///
+/// Every requested block is pushed to the blockchain in its own database transaction, so this
+/// limits the blocks applied and the database commits per second alike.
+pub struct SyncThrottle {
+    blocks_per_second: Option<usize>,
+    state: Mutex<SyncThrottleState>,
+}
+
+impl SyncThrottle {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    pub fn new(blocks_per_second: Option<usize>) -> Self {
+        let now = Instant::now();
+        SyncThrottle {
+            blocks_per_second,
+            state: Mutex::new(SyncThrottleState {
+                active: false,
+                starting_block: 0,
+                started_at: now,
+                highest_block: 0,
+                window_start: now,
+                window_blocks: 0,
+            }),
+        }
+    }
+
+    pub fn blocks_per_second(&self) -> Option<usize> {
+        self.blocks_per_second
+    }
+
+    /// Starts tracking the initial sync at `head_height`. Does nothing if it is already tracked.
+    pub fn start(&self, head_height: u32) {
+        let mut state = self.state.lock();
+        if state.active {
+            return;
+        }
+        state.active = true;
+        state.starting_block = head_height;
+        state.started_at = Instant::now();
+        state.highest_block = head_height;
+    }
+
+    /// Ends the initial sync. Requests aren't limited anymore afterwards.
+    pub fn stop(&self) {
+        self.state.lock().active = false;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state.lock().active
+    }
+
+    /// Notes a block height a peer announced or sent to us.
+    pub fn note_height(&self, height: u32) {
+        let mut state = self.state.lock();
+        if height > state.highest_block {
+            state.highest_block = height;
+        }
+    }
+
+    /// Returns how many of `wanted` blocks may be requested now and counts them against the limit.
+    pub fn reserve(&self, wanted: usize) -> usize {
+        let blocks_per_second = match self.blocks_per_second {
+            Some(blocks_per_second) => blocks_per_second,
+            None => return wanted,
+        };
+
+        let mut state = self.state.lock();
+        if !state.active {
+            return wanted;
+        }
+        if state.window_start.elapsed() >= Self::WINDOW {
+            state.window_start = Instant::now();
+            state.window_blocks = 0;
+        }
+        let allowed = wanted.min(blocks_per_second.saturating_sub(state.window_blocks));
+        state.window_blocks += allowed;
+        allowed
+    }
+
+    /// Time until more blocks may be requested.
+    pub fn time_until_next_window(&self) -> Duration {
+        Self::WINDOW.checked_sub(self.state.lock().window_start.elapsed()).unwrap_or_default()
+    }
+
+    /// Returns the progress of the initial sync, or `None` if we aren't syncing.
+    pub fn progress(&self, current_block: u32) -> Option<SyncProgress> {
+        let state = self.state.lock();
+        if !state.active {
+            return None;
+        }
+
+        let elapsed = state.started_at.elapsed();
+        let elapsed_secs = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1000.0;
+        let synced = current_block.saturating_sub(state.starting_block);
+        let blocks_per_second = if elapsed_secs > 0.0 { f64::from(synced) / elapsed_secs } else { 0.0 };
+
+        let highest_block = state.highest_block.max(current_block);
+        let eta = if blocks_per_second > 0.0 {
+            let remaining = f64::from(highest_block - current_block);
+            Some(Duration::from_secs((remaining / blocks_per_second).ceil() as u64))
+        } else {
+            None
+        };
+
+        Some(SyncProgress {
+            starting_block: state.starting_block,
+            current_block,
+            highest_block,
+            blocks_per_second,
+            eta,
+        })
+    }
+}
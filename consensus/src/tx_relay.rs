@@ -0,0 +1,166 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use collections::LimitHashSet;
+use mempool::ReturnCode;
+use network::connection::close_type::CloseType;
+use network_primitives::address::PeerId;
+
+/// Reasons for which a transaction relayed by a peer was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxRejection {
+    /// The transaction was invalid.
+    Invalid,
+    /// We already knew the transaction.
+    Duplicate,
+    /// The fee of the transaction was too low, it didn't pass our filter rules or it would expire
+    /// before the transactions paying more are included.
+    BelowFee,
+    /// The peer exceeded its transaction relay budget, so the transaction wasn't validated.
+    OverBudget,
+}
+
+impl TxRejection {
+    pub fn from_return_code(code: &ReturnCode) -> Option<Self> {
+        match code {
+            ReturnCode::Accepted | ReturnCode::Orphan => None,
+            ReturnCode::Known => Some(TxRejection::Duplicate),
+            ReturnCode::Invalid => Some(TxRejection::Invalid),
+            ReturnCode::FeeTooLow | ReturnCode::Filtered | ReturnCode::Expiring => Some(TxRejection::BelowFee),
+        }
+    }
+
+    /// The amount this rejection adds to the spam score of a peer.
+    pub fn weight(self) -> f64 {
+        match self {
+            TxRejection::Invalid => 10.0,
+            TxRejection::Duplicate => 1.0,
+            TxRejection::BelowFee => 2.0,
+            TxRejection::OverBudget => 1.0,
+        }
+    }
+}
+
+/// Score of the transactions a peer relayed to us that were rejected. The score halves every
+/// `HALF_LIFE`, so honest peers that occasionally relay bad transactions are never punished.
+pub struct TxSpamScore {
+    score: f64,
+    last_update: Instant,
+}
+
+impl TxSpamScore {
+    pub const HALF_LIFE: Duration = Duration::from_secs(60);
+
+    pub fn new() -> Self {
+        TxSpamScore {
+            score: 0.0,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Adds a rejection and returns the new score.
+    pub fn add(&mut self, rejection: TxRejection) -> f64 {
+        self.decay();
+        self.score += rejection.weight();
+        self.score
+    }
+
+    pub fn score(&mut self) -> f64 {
+        self.decay();
+        self.score
+    }
+
+    fn decay(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update);
+        let half_lives = (elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1000.0) / Self::HALF_LIFE.as_secs() as f64;
+        self.score *= 0.5f64.powf(half_lives);
+        self.last_update = now;
+    }
+}
+
+impl Default for TxSpamScore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rejection statistics and repeat offenders, shared by the inventory agents of all peers.
+pub struct TxRelayMonitor {
+    invalid: AtomicUsize,
+    duplicate: AtomicUsize,
+    below_fee: AtomicUsize,
+    over_budget: AtomicUsize,
+    disconnected_peers: AtomicUsize,
+    banned_peers: AtomicUsize,
+    /// Peers that have been disconnected for spamming before.
+    offenders: Mutex<LimitHashSet<PeerId>>,
+}
+
+impl TxRelayMonitor {
+    /// Spam score at which a peer is disconnected. A peer is banned if it reaches this score
+    /// again after reconnecting.
+    pub const DISCONNECT_SCORE: f64 = 100.0;
+    const OFFENDERS_MAX: usize = 1000;
+
+    pub fn new() -> Self {
+        TxRelayMonitor {
+            invalid: AtomicUsize::new(0),
+            duplicate: AtomicUsize::new(0),
+            below_fee: AtomicUsize::new(0),
+            over_budget: AtomicUsize::new(0),
+            disconnected_peers: AtomicUsize::new(0),
+            banned_peers: AtomicUsize::new(0),
+            offenders: Mutex::new(LimitHashSet::new(Self::OFFENDERS_MAX)),
+        }
+    }
+
+    pub fn record(&self, rejection: TxRejection) {
+        self.counter(rejection).fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of relayed transactions that were rejected for the given reason.
+    pub fn rejections(&self, rejection: TxRejection) -> usize {
+        self.counter(rejection).load(Ordering::Relaxed)
+    }
+
+    /// Called when a peer reached `DISCONNECT_SCORE`. Returns the close type with which the
+    /// connection should be closed: Peers are disconnected on their first offense and banned on
+    /// the next one.
+    pub fn punish(&self, peer_id: &PeerId) -> CloseType {
+        let mut offenders = self.offenders.lock();
+        if offenders.contains(peer_id) {
+            self.banned_peers.fetch_add(1, Ordering::Relaxed);
+            CloseType::TransactionFlooding
+        } else {
+            offenders.insert(peer_id.clone());
+            self.disconnected_peers.fetch_add(1, Ordering::Relaxed);
+            CloseType::TooManyRejectedTransactions
+        }
+    }
+
+    pub fn disconnected_peers(&self) -> usize {
+        self.disconnected_peers.load(Ordering::Relaxed)
+    }
+
+    pub fn banned_peers(&self) -> usize {
+        self.banned_peers.load(Ordering::Relaxed)
+    }
+
+    fn counter(&self, rejection: TxRejection) -> &AtomicUsize {
+        match rejection {
+            TxRejection::Invalid => &self.invalid,
+            TxRejection::Duplicate => &self.duplicate,
+            TxRejection::BelowFee => &self.below_fee,
+            TxRejection::OverBudget => &self.over_budget,
+        }
+    }
+}
+
+impl Default for TxRelayMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,71 @@
+extern crate nimiq_consensus as consensus;
+extern crate nimiq_network as network;
+extern crate nimiq_network_primitives as network_primitives;
+
+use consensus::sync_throttle::SyncThrottle;
+use consensus::tx_relay::{TxRejection, TxRelayMonitor, TxSpamScore};
+use network::connection::close_type::CloseType;
+use network_primitives::address::PeerId;
+
+#[test]
+fn it_accumulates_spam_score() {
+    let mut score = TxSpamScore::new();
+    score.add(TxRejection::Duplicate);
+    let current = score.add(TxRejection::Invalid);
+    // Almost no time passed, so the score barely decayed.
+    assert!(current > 10.9 && current <= 11.0);
+}
+
+#[test]
+fn it_bans_repeat_offenders() {
+    let monitor = TxRelayMonitor::new();
+    let peer_id = PeerId::from([1u8; PeerId::SIZE]);
+
+    monitor.record(TxRejection::Invalid);
+    monitor.record(TxRejection::Invalid);
+    monitor.record(TxRejection::BelowFee);
+    assert_eq!(monitor.rejections(TxRejection::Invalid), 2);
+    assert_eq!(monitor.rejections(TxRejection::BelowFee), 1);
+    assert_eq!(monitor.rejections(TxRejection::OverBudget), 0);
+
+    assert_eq!(monitor.punish(&peer_id), CloseType::TooManyRejectedTransactions);
+    assert!(!CloseType::TooManyRejectedTransactions.is_banning_type());
+    assert_eq!(monitor.punish(&peer_id), CloseType::TransactionFlooding);
+    assert!(CloseType::TransactionFlooding.is_banning_type());
+    assert_eq!(monitor.disconnected_peers(), 1);
+    assert_eq!(monitor.banned_peers(), 1);
+}
+
+#[test]
+fn it_limits_blocks_while_syncing() {
+    let throttle = SyncThrottle::new(Some(100));
+    // Not limited before the sync started.
+    assert_eq!(throttle.reserve(500), 500);
+
+    throttle.start(10);
+    assert_eq!(throttle.reserve(60), 60);
+    assert_eq!(throttle.reserve(60), 40);
+    assert_eq!(throttle.reserve(60), 0);
+
+    throttle.stop();
+    assert_eq!(throttle.reserve(60), 60);
+    assert_eq!(SyncThrottle::new(None).reserve(500), 500);
+}
+
+#[test]
+fn it_reports_sync_progress() {
+    let throttle = SyncThrottle::new(None);
+    assert!(throttle.progress(0).is_none());
+
+    throttle.start(10);
+    throttle.note_height(500);
+    throttle.note_height(200);
+    let progress = throttle.progress(100).unwrap();
+    assert_eq!(progress.starting_block, 10);
+    assert_eq!(progress.current_block, 100);
+    assert_eq!(progress.highest_block, 500);
+
+    // Starting again while syncing keeps the original starting block.
+    throttle.start(100);
+    assert_eq!(throttle.progress(100).unwrap().starting_block, 10);
+}
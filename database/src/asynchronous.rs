@@ -0,0 +1,236 @@
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use futures::{Async, Future, Poll};
+use futures::sync::oneshot;
+use futures_cpupool::{Builder, CpuPool};
+
+use super::*;
+
+type ReadJob = Box<dyn FnOnce(&ReadTransaction) + Send>;
+
+enum WriteJob {
+    Run(Box<dyn FnOnce(&mut WriteTransaction) + Send>),
+    Commit(oneshot::Sender<()>),
+}
+
+/// Threads running database transactions, so they don't block the event loop.
+///
+/// A transaction is bound to the thread it was created on, so each open `AsyncReadTransaction`
+/// and `AsyncWriteTransaction` occupies one thread of the pool until it is dropped or committed.
+/// Transactions opened while all threads are busy only start once a thread becomes available.
+/// To bound how long a thread is occupied, a transaction is closed once it didn't get an
+/// operation for `idle_timeout`. A write transaction that is closed like this is aborted.
+#[derive(Clone)]
+pub struct BlockingPool {
+    pool: CpuPool,
+    idle_timeout: Duration,
+}
+
+impl BlockingPool {
+    pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(num_threads: usize) -> Self {
+        Self::with_idle_timeout(num_threads, Self::DEFAULT_IDLE_TIMEOUT)
+    }
+
+    pub fn with_idle_timeout(num_threads: usize, idle_timeout: Duration) -> Self {
+        BlockingPool {
+            pool: Builder::new()
+                .pool_size(num_threads)
+                .name_prefix("database-")
+                .create(),
+            idle_timeout,
+        }
+    }
+
+    fn spawn<F>(&self, f: F) where F: FnOnce() + Send + 'static {
+        self.pool.spawn_fn(move || {
+            f();
+            Ok::<(), ()>(())
+        }).forget();
+    }
+}
+
+/// The result of an operation on an async transaction. Fails if the transaction was closed
+/// because an earlier operation panicked.
+pub struct TransactionFuture<T> {
+    receiver: oneshot::Receiver<T>,
+}
+
+impl<T> Future for TransactionFuture<T> {
+    type Item = T;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<T, io::Error> {
+        match self.receiver.poll() {
+            Ok(Async::Ready(value)) => Ok(Async::Ready(value)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(_) => Err(io::Error::new(io::ErrorKind::Other, "Database transaction was closed")),
+        }
+    }
+}
+
+/// A read transaction running on a `BlockingPool`. All operations see the same snapshot of the
+/// database and are run in order.
+pub struct AsyncReadTransaction {
+    sender: mpsc::Sender<ReadJob>,
+}
+
+impl AsyncReadTransaction {
+    pub fn new(env: &'static Environment, pool: &BlockingPool) -> Self {
+        let (sender, receiver) = mpsc::channel::<ReadJob>();
+        let idle_timeout = pool.idle_timeout;
+        pool.spawn(move || {
+            let txn = ReadTransaction::new(env);
+            loop {
+                match receiver.recv_timeout(idle_timeout) {
+                    Ok(job) => job(&txn),
+                    Err(RecvTimeoutError::Timeout) => {
+                        debug!("Closing read transaction that was idle for {:?}", idle_timeout);
+                        return;
+                    },
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        });
+        AsyncReadTransaction { sender }
+    }
+
+    /// Runs `f` with the transaction, e.g. to scan a database with a cursor.
+    pub fn run<F, T>(&self, f: F) -> TransactionFuture<T>
+        where F: FnOnce(&ReadTransaction) -> T + Send + 'static, T: Send + 'static {
+        let (sender, receiver) = oneshot::channel();
+        // If the transaction was closed, the job is dropped and the future fails.
+        let _ = self.sender.send(Box::new(move |txn: &ReadTransaction| {
+            let _ = sender.send(f(txn));
+        }));
+        TransactionFuture { receiver }
+    }
+}
+
+/// A write transaction running on a `BlockingPool`. Operations are run in order. The transaction
+/// is aborted if it is dropped before `commit` is called, or if it is idle for too long.
+pub struct AsyncWriteTransaction {
+    sender: mpsc::Sender<WriteJob>,
+}
+
+impl AsyncWriteTransaction {
+    pub fn new(env: &'static Environment, pool: &BlockingPool) -> Self {
+        let (sender, receiver) = mpsc::channel::<WriteJob>();
+        let idle_timeout = pool.idle_timeout;
+        pool.spawn(move || {
+            let mut txn = WriteTransaction::new(env);
+            loop {
+                match receiver.recv_timeout(idle_timeout) {
+                    Ok(WriteJob::Run(f)) => f(&mut txn),
+                    Ok(WriteJob::Commit(done)) => {
+                        txn.commit();
+                        let _ = done.send(());
+                        return;
+                    },
+                    Err(RecvTimeoutError::Timeout) => {
+                        warn!("Aborting write transaction that was idle for {:?}", idle_timeout);
+                        break;
+                    },
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            txn.abort();
+        });
+        AsyncWriteTransaction { sender }
+    }
+
+    /// Runs `f` with the transaction.
+    pub fn run<F, T>(&self, f: F) -> TransactionFuture<T>
+        where F: FnOnce(&mut WriteTransaction) -> T + Send + 'static, T: Send + 'static {
+        let (sender, receiver) = oneshot::channel();
+        // If the transaction was closed, the job is dropped and the future fails.
+        let _ = self.sender.send(WriteJob::Run(Box::new(move |txn: &mut WriteTransaction| {
+            let _ = sender.send(f(txn));
+        })));
+        TransactionFuture { receiver }
+    }
+
+    /// Commits the transaction after all operations are done.
+    pub fn commit(self) -> TransactionFuture<()> {
+        let (sender, receiver) = oneshot::channel();
+        let _ = self.sender.send(WriteJob::Commit(sender));
+        TransactionFuture { receiver }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::volatile::VolatileEnvironment;
+
+    fn env() -> &'static Environment {
+        Box::leak(Box::new(VolatileEnvironment::new(1).unwrap()))
+    }
+
+    #[test]
+    fn it_commits_write_transactions() {
+        let env = env();
+        let db = Box::leak(Box::new(env.open_database("test".to_string())));
+        let pool = BlockingPool::new(2);
+
+        let txn = AsyncWriteTransaction::new(env, &pool);
+        let put = txn.run(move |txn| txn.put_reserve(db, "key", "value"));
+        let get = txn.run(move |txn| txn.get::<str, String>(db, "key"));
+        put.wait().unwrap();
+        assert_eq!(get.wait().unwrap(), Some("value".to_string()));
+        txn.commit().wait().unwrap();
+
+        let txn = AsyncReadTransaction::new(env, &pool);
+        let value = txn.run(move |txn| txn.get::<str, String>(db, "key"));
+        assert_eq!(value.wait().unwrap(), Some("value".to_string()));
+    }
+
+    #[test]
+    fn it_aborts_dropped_write_transactions() {
+        let env = env();
+        let db = Box::leak(Box::new(env.open_database("test".to_string())));
+        let pool = BlockingPool::new(1);
+
+        let txn = AsyncWriteTransaction::new(env, &pool);
+        txn.run(move |txn| txn.put_reserve(db, "key", "value")).wait().unwrap();
+        drop(txn);
+
+        // The pool has a single thread, so this only runs after the write transaction is closed.
+        let txn = AsyncReadTransaction::new(env, &pool);
+        let value = txn.run(move |txn| txn.get::<str, String>(db, "key"));
+        assert_eq!(value.wait().unwrap(), None);
+    }
+
+    #[test]
+    fn it_aborts_idle_write_transactions() {
+        let env = env();
+        let db = Box::leak(Box::new(env.open_database("test".to_string())));
+        let pool = BlockingPool::with_idle_timeout(1, Duration::from_millis(50));
+
+        let txn = AsyncWriteTransaction::new(env, &pool);
+        txn.run(move |txn| txn.put_reserve(db, "key", "value")).wait().unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert!(txn.run(|_| ()).wait().is_err());
+        assert!(txn.commit().wait().is_err());
+
+        let txn = AsyncReadTransaction::new(env, &pool);
+        let value = txn.run(move |txn| txn.get::<str, String>(db, "key"));
+        assert_eq!(value.wait().unwrap(), None);
+    }
+
+    #[test]
+    fn it_fails_after_a_panic() {
+        let env = env();
+        let pool = BlockingPool::new(1);
+
+        let txn = AsyncReadTransaction::new(env, &pool);
+        assert!(txn.run(|_| panic!("test")).wait().is_err());
+        assert!(txn.run(|_| ()).wait().is_err());
+    }
+}
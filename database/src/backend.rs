@@ -0,0 +1,150 @@
+//! The interface between `Environment` and the storage backends.
+//!
+//! Backends only deal with raw bytes. `Environment`, `Database`, the transactions and the
+//! cursors in the crate root wrap them and do the conversion from and to typed keys and values,
+//! so that a backend only has to implement the traits of this module.
+
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::TryInto;
+use std::fmt;
+use std::io;
+
+use crate::{Database, DatabaseFlags, DatabaseStats, EnvironmentStats};
+
+/// A storage backend, i.e. the environment of one of the backend modules.
+pub(crate) trait Backend: fmt::Debug + Send + Sync {
+    /// Name of the backend, as in the `backend` setting of the database configuration.
+    fn name(&self) -> &'static str;
+
+    /// Opens (and creates) the database `name` and returns the handle the transactions need to
+    /// access it. The transactions get the handle back through `Database::handle`.
+    fn open_database(&self, name: &str, flags: DatabaseFlags) -> Box<dyn Any + Send + Sync>;
+
+    fn stats(&self) -> EnvironmentStats;
+
+    fn database_stats(&self, db: &Database) -> DatabaseStats;
+
+    /// Flushes all committed writes to disk, see `Environment::sync`.
+    fn sync(&self) -> io::Result<()>;
+
+    fn read_transaction<'env>(&'env self) -> Box<dyn BackendTransaction + 'env>;
+
+    fn write_transaction<'env>(&'env self) -> Box<dyn BackendWriteTransaction + 'env>;
+
+    fn drop_database(self: Box<Self>) -> io::Result<()>;
+
+    /// Rewrites the backend without its free space, see `Environment::compact`. Backends that
+    /// can't do this return themselves.
+    fn compact(self: Box<Self>) -> io::Result<Box<dyn Backend>>;
+
+    /// Writes a copy of the running backend to the directory at `path`.
+    fn backup_to(&self, _path: &str) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, format!("The {} backend can't be backed up while running", self.name())))
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+}
+
+pub(crate) trait BackendTransaction: fmt::Debug {
+    /// Returns the value of `key`, or its first value in databases with duplicate keys.
+    fn get(&self, db: &Database, key: &[u8]) -> Option<Cow<[u8]>>;
+
+    fn cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendCursor + 'txn>;
+}
+
+pub(crate) trait BackendWriteTransaction: BackendTransaction {
+    fn put(&mut self, db: &Database, key: &[u8], value: &[u8]);
+
+    /// Puts a value of `size` bytes, which `write` copies into the reserved space.
+    fn put_reserve(&mut self, db: &Database, key: &[u8], size: usize, write: &mut dyn FnMut(&mut [u8]));
+
+    /// Puts a key/value pair at the end of the database.
+    /// The key must be greater than all keys already in the database.
+    fn put_append(&mut self, db: &Database, key: &[u8], value: &[u8]);
+
+    /// Returns the greatest key in the database, if any.
+    fn last_key(&self, db: &Database) -> Option<Vec<u8>>;
+
+    /// Removes the key with all of its values.
+    fn remove(&mut self, db: &Database, key: &[u8]);
+
+    /// Removes one value of a key. Databases without duplicate keys ignore the value and remove
+    /// the key, like LMDB does.
+    fn remove_item(&mut self, db: &Database, key: &[u8], value: &[u8]);
+
+    fn clear_database(&mut self, db: &Database);
+
+    /// Removes all keys from `start` (inclusive) to `end` (exclusive) in the order of the
+    /// database, see `compare_keys`.
+    fn remove_range(&mut self, db: &Database, start: &[u8], end: &[u8]);
+
+    fn commit(self: Box<Self>);
+
+    fn write_cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendWriteCursor + 'txn>;
+}
+
+/// A cursor over the raw entries of a database. The typed `ReadCursor` methods map to these,
+/// `seek_range_key` and `seek_range` both map to `seek_range`.
+pub(crate) trait BackendCursor {
+    fn first(&mut self) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn first_duplicate(&mut self) -> Option<Vec<u8>>;
+
+    fn last(&mut self) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn last_duplicate(&mut self) -> Option<Vec<u8>>;
+
+    fn seek_key_value(&mut self, key: &[u8], value: &[u8]) -> bool;
+
+    fn seek_key_nearest_value(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>>;
+
+    fn get_current(&mut self) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn next_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn next_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn prev(&mut self) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn prev_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn prev_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn seek_key(&mut self, key: &[u8]) -> Option<Vec<u8>>;
+
+    fn seek_key_both(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn seek_range(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    fn count_duplicates(&mut self) -> usize;
+}
+
+pub(crate) trait BackendWriteCursor: BackendCursor {
+    fn put_current(&mut self, value: &[u8]);
+
+    fn append(&mut self, key: &[u8], value: &[u8]);
+
+    fn delete_current(&mut self);
+}
+
+/// Compares two keys in the order of a database with `flags`, i.e. integer keys by their value
+/// and all others bytewise.
+pub(crate) fn compare_keys(flags: DatabaseFlags, a: &[u8], b: &[u8]) -> Ordering {
+    if flags.contains(DatabaseFlags::UINT_KEYS) && a.len() == 4 && b.len() == 4 {
+        let a = u32::from_ne_bytes(a.try_into().unwrap());
+        let b = u32::from_ne_bytes(b.try_into().unwrap());
+        return a.cmp(&b);
+    }
+    if flags.contains(DatabaseFlags::U64_KEYS) && a.len() == 8 && b.len() == 8 {
+        let a = u64::from_ne_bytes(a.try_into().unwrap());
+        let b = u64::from_ne_bytes(b.try_into().unwrap());
+        return a.cmp(&b);
+    }
+    a.cmp(b)
+}
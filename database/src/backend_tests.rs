@@ -0,0 +1,467 @@
+//! Tests that every storage backend runs, so that all of them behave like LMDB.
+//!
+//! `backend_tests!` instantiates the tests for one backend, each with a fresh environment in its
+//! own temporary directory. Backend specific behaviour is tested in the backend's module.
+
+use tempdir::TempDir;
+
+use crate::cursor::{ReadCursor, WriteCursor};
+
+use super::*;
+
+fn it_can_save_basic_objects(env: &Environment) {
+    let db = env.open_database("test".to_string());
+
+    // Read non-existent value.
+    {
+        let tx = ReadTransaction::new(env);
+        assert!(tx.get::<str, String>(&db, "test").is_none());
+    }
+
+    // Read non-existent value.
+    let mut tx = WriteTransaction::new(env);
+    assert!(tx.get::<str, String>(&db, "test").is_none());
+
+    // Write and read value.
+    tx.put_reserve(&db, "test", "one");
+    assert_eq!(tx.get::<str, String>(&db, "test"), Some("one".to_string()));
+    // Overwrite and read value.
+    tx.put_reserve(&db, "test", "two");
+    assert_eq!(tx.get::<str, String>(&db, "test"), Some("two".to_string()));
+    tx.commit();
+
+    // Read value.
+    let tx = ReadTransaction::new(env);
+    assert_eq!(tx.get::<str, String>(&db, "test"), Some("two".to_string()));
+    tx.close();
+
+    // Remove value.
+    let mut tx = WriteTransaction::new(env);
+    tx.remove(&db, "test");
+    assert!(tx.get::<str, String>(&db, "test").is_none());
+    tx.commit();
+
+    // Check removal.
+    {
+        let tx = ReadTransaction::new(env);
+        assert!(tx.get::<str, String>(&db, "test").is_none());
+    }
+
+    // Write and abort.
+    let mut tx = WriteTransaction::new(env);
+    tx.put_reserve(&db, "test", "one");
+    tx.abort();
+
+    // Check aborted transaction.
+    let tx = ReadTransaction::new(env);
+    assert!(tx.get::<str, String>(&db, "test").is_none());
+}
+
+fn isolation_test(env: &Environment) {
+    let db = env.open_database("test".to_string());
+
+    // Read non-existent value.
+    let tx = ReadTransaction::new(env);
+    assert!(tx.get::<str, String>(&db, "test").is_none());
+
+    // WriteTransaction.
+    let mut txw = WriteTransaction::new(env);
+    assert!(txw.get::<str, String>(&db, "test").is_none());
+    txw.put_reserve(&db, "test", "one");
+    assert_eq!(txw.get::<str, String>(&db, "test"), Some("one".to_string()));
+
+    // ReadTransaction should still have the old state.
+    assert!(tx.get::<str, String>(&db, "test").is_none());
+
+    // Commit WriteTransaction.
+    txw.commit();
+
+    // ReadTransaction should still have the old state.
+    assert!(tx.get::<str, String>(&db, "test").is_none());
+
+    // Have a new ReadTransaction read the new state.
+    let tx2 = ReadTransaction::new(env);
+    assert_eq!(tx2.get::<str, String>(&db, "test"), Some("one".to_string()));
+}
+
+fn databases_are_separate(env: &Environment) {
+    let db1 = env.open_database("test1".to_string());
+    let db2 = env.open_database("test2".to_string());
+
+    let mut txw = WriteTransaction::new(env);
+    txw.put::<str, u32>(&db1, "a", &1);
+    txw.put::<str, u32>(&db2, "b", &2);
+    txw.commit();
+
+    let tx = ReadTransaction::new(env);
+    assert!(tx.get::<str, u32>(&db1, "b").is_none());
+    let mut cursor = tx.cursor(&db2);
+    assert_eq!(cursor.first::<String, u32>(), Some(("b".to_string(), 2)));
+    assert!(cursor.next::<String, u32>().is_none());
+}
+
+fn duplicates_test(env: &Environment) {
+    let db = env.open_database_with_flags("test".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_UINT_VALUES);
+
+    // Write one value.
+    let mut txw = WriteTransaction::new(env);
+    assert!(txw.get::<str, u32>(&db, "test").is_none());
+    txw.put::<str, u32>(&db, "test", &125);
+    assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
+    txw.commit();
+
+    // Have a new ReadTransaction read the new state.
+    {
+        let tx = ReadTransaction::new(env);
+        assert_eq!(tx.get::<str, u32>(&db, "test"), Some(125));
+    }
+
+    // Write a second smaller value.
+    let mut txw = WriteTransaction::new(env);
+    assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
+    txw.put::<str, u32>(&db, "test", &12);
+    assert_eq!(txw.get::<str, u32>(&db, "test"), Some(12));
+    txw.commit();
+
+    // Have a new ReadTransaction read the smaller value.
+    {
+        let tx = ReadTransaction::new(env);
+        assert_eq!(tx.get::<str, u32>(&db, "test"), Some(12));
+    }
+
+    // Remove smaller value and write larger value.
+    let mut txw = WriteTransaction::new(env);
+    assert_eq!(txw.get::<str, u32>(&db, "test"), Some(12));
+    txw.remove_item::<str, u32>(&db, "test", &12);
+    txw.put::<str, u32>(&db, "test", &5783);
+    assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
+    txw.commit();
+
+    // Have a new ReadTransaction read the smallest value.
+    {
+        let tx = ReadTransaction::new(env);
+        assert_eq!(tx.get::<str, u32>(&db, "test"), Some(125));
+    }
+
+    // Remove everything.
+    let mut txw = WriteTransaction::new(env);
+    assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
+    txw.remove::<str>(&db, "test");
+    assert!(txw.get::<str, u32>(&db, "test").is_none());
+    txw.commit();
+
+    // Have a new ReadTransaction read the new state.
+    {
+        let tx = ReadTransaction::new(env);
+        assert!(tx.get::<str, u32>(&db, "test").is_none());
+    }
+}
+
+fn cursor_test(env: &Environment) {
+    let db = env.open_database_with_flags("test".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_UINT_VALUES);
+
+    let test1: String = "test1".to_string();
+    let test2: String = "test2".to_string();
+
+    // Write some values.
+    let mut txw = WriteTransaction::new(env);
+    assert!(txw.get::<str, u32>(&db, "test").is_none());
+    txw.put::<str, u32>(&db, "test1", &125);
+    txw.put::<str, u32>(&db, "test1", &12);
+    txw.put::<str, u32>(&db, "test1", &5783);
+    txw.put::<str, u32>(&db, "test2", &5783);
+    txw.commit();
+
+    // Have a new ReadTransaction read the new state.
+    let tx = ReadTransaction::new(env);
+    let mut cursor = tx.cursor(&db);
+    assert_eq!(cursor.first::<String, u32>(), Some((test1.clone(), 12)));
+    assert_eq!(cursor.last::<String, u32>(), Some((test2.clone(), 5783)));
+    assert_eq!(cursor.prev::<String, u32>(), Some((test1.clone(), 5783)));
+    assert_eq!(cursor.first_duplicate::<u32>(), Some(12));
+    assert_eq!(cursor.next_duplicate::<String, u32>(), Some((test1.clone(), 125)));
+    assert_eq!(cursor.prev_duplicate::<String, u32>(), Some((test1.clone(), 12)));
+    assert_eq!(cursor.next_no_duplicate::<String, u32>(), Some((test2.clone(), 5783)));
+    assert!(cursor.seek_key::<str, u32>("test").is_none());
+    assert_eq!(cursor.seek_key::<str, u32>("test1"), Some(12));
+    assert_eq!(cursor.count_duplicates(), 3);
+    assert_eq!(cursor.last_duplicate::<u32>(), Some(5783));
+    assert!(!cursor.seek_key_value::<str, u32>("test1", &15));
+    assert!(cursor.seek_key_value::<str, u32>("test1", &125));
+    assert_eq!(cursor.get_current::<String, u32>(), Some((test1.clone(), 125)));
+    assert_eq!(cursor.seek_key_nearest_value::<str, u32>("test1", &126), Some(5783));
+    assert_eq!(cursor.get_current::<String, u32>(), Some((test1.clone(), 5783)));
+    assert!(cursor.prev_no_duplicate::<String, u32>().is_none());
+    assert_eq!(cursor.next::<String, u32>(), Some((test2.clone(), 5783)));
+
+    let entries: Vec<(String, u32)> = cursor.iter().collect();
+    assert_eq!(entries, vec![(test1.clone(), 12), (test1.clone(), 125), (test1.clone(), 5783), (test2.clone(), 5783)]);
+    let entries: Vec<(String, u32)> = cursor.iter_from("test2").collect();
+    assert_eq!(entries, vec![(test2.clone(), 5783)]);
+    tx.close();
+
+    // Remove the values of one key.
+    let mut txw = WriteTransaction::new(env);
+    txw.remove::<str>(&db, "test1");
+    assert!(txw.get::<str, u32>(&db, "test1").is_none());
+    assert_eq!(txw.cursor(&db).first::<String, u32>(), Some((test2.clone(), 5783)));
+    txw.commit();
+}
+
+fn scan_test(env: &Environment) {
+    let db = env.open_database("test".to_string());
+    let uint_db = env.open_database_with_flags("uint".to_string(), DatabaseFlags::UINT_KEYS);
+
+    let mut txw = WriteTransaction::new(env);
+    txw.put::<str, u32>(&db, "a", &1);
+    txw.put::<str, u32>(&db, "ab1", &2);
+    txw.put::<str, u32>(&db, "ab2", &3);
+    txw.put::<str, u32>(&db, "ac", &4);
+    for i in 1u32..6 {
+        txw.put::<u32, u32>(&uint_db, &i, &(i * 10));
+    }
+    txw.commit();
+
+    let tx = ReadTransaction::new(env);
+    let entries: Vec<(String, u32)> = tx.scan_prefix(&db, "ab").collect();
+    assert_eq!(entries, vec![("ab1".to_string(), 2), ("ab2".to_string(), 3)]);
+    assert_eq!(tx.scan_prefix::<str, String, u32>(&db, "b").count(), 0);
+
+    let entries: Vec<(u32, u32)> = tx.scan_range(&uint_db, 2..4).collect();
+    assert_eq!(entries, vec![(2, 20), (3, 30)]);
+    assert_eq!(tx.scan_range::<u32, u32>(&uint_db, 6..10).count(), 0);
+}
+
+fn remove_range_test(env: &Environment) {
+    let uint_db = env.open_database_with_flags("uint".to_string(), DatabaseFlags::UINT_KEYS);
+    let dup_db = env.open_database_with_flags("dup".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::UINT_KEYS | DatabaseFlags::DUP_UINT_VALUES);
+
+    let mut txw = WriteTransaction::new(env);
+    for i in 1u32..6 {
+        txw.put::<u32, u32>(&uint_db, &i, &(i * 10));
+        txw.put::<u32, u32>(&dup_db, &i, &1);
+        txw.put::<u32, u32>(&dup_db, &i, &2);
+    }
+    // Keys whose bytes sort differently than their values.
+    txw.put::<u32, u32>(&uint_db, &256, &2560);
+    txw.commit();
+
+    let mut txw = WriteTransaction::new(env);
+    txw.remove_range::<u32>(&uint_db, 2..4);
+    txw.remove_range::<u32>(&dup_db, 0..3);
+    // Empty ranges don't remove anything.
+    txw.remove_range::<u32>(&uint_db, 5..5);
+    txw.commit();
+
+    let tx = ReadTransaction::new(env);
+    let entries: Vec<(u32, u32)> = tx.cursor(&uint_db).iter().collect();
+    assert_eq!(entries, vec![(1, 10), (4, 40), (5, 50), (256, 2560)]);
+    let entries: Vec<(u32, u32)> = tx.cursor(&dup_db).iter().collect();
+    assert_eq!(entries, vec![(3, 1), (3, 2), (4, 1), (4, 2), (5, 1), (5, 2)]);
+    tx.close();
+
+    let mut txw = WriteTransaction::new(env);
+    txw.clear_database(&dup_db);
+    txw.commit();
+
+    let tx = ReadTransaction::new(env);
+    assert_eq!(tx.cursor(&dup_db).iter::<u32, u32>().count(), 0);
+    assert_eq!(tx.cursor(&uint_db).iter::<u32, u32>().count(), 4);
+}
+
+fn uncommitted_changes_test(env: &Environment) {
+    let uint_db = env.open_database_with_flags("uint".to_string(), DatabaseFlags::UINT_KEYS);
+    let dup_db = env.open_database_with_flags("dup".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::UINT_KEYS | DatabaseFlags::DUP_UINT_VALUES);
+
+    let mut txw = WriteTransaction::new(env);
+    for i in 1u32..7 {
+        txw.put::<u32, u32>(&uint_db, &i, &i);
+        txw.put::<u32, u32>(&dup_db, &1, &i);
+    }
+    txw.commit();
+
+    // Scans merge the changes of the transaction with the committed entries.
+    let mut txw = WriteTransaction::new(env);
+    for i in (2u32..7).step_by(2) {
+        txw.put::<u32, u32>(&uint_db, &i, &(i * 10));
+    }
+    txw.remove::<u32>(&uint_db, &3);
+    txw.put::<u32, u32>(&uint_db, &7, &7);
+    let entries: Vec<(u32, u32)> = txw.cursor(&uint_db).iter().collect();
+    assert_eq!(entries, vec![(1, 1), (2, 20), (4, 40), (5, 5), (6, 60), (7, 7)]);
+    let mut cursor = txw.cursor(&uint_db);
+    assert_eq!(cursor.last::<u32, u32>(), Some((7, 7)));
+    assert_eq!(cursor.prev::<u32, u32>(), Some((6, 60)));
+    assert_eq!(cursor.prev::<u32, u32>(), Some((5, 5)));
+    assert_eq!(cursor.prev::<u32, u32>(), Some((4, 40)));
+    assert_eq!(cursor.prev::<u32, u32>(), Some((2, 20)));
+
+    txw.remove_item::<u32, u32>(&dup_db, &1, &2);
+    txw.put::<u32, u32>(&dup_db, &1, &7);
+    let mut cursor = txw.cursor(&dup_db);
+    assert_eq!(cursor.seek_key::<u32, u32>(&1), Some(1));
+    assert_eq!(cursor.count_duplicates(), 6);
+
+    txw.remove_range::<u32>(&uint_db, 2..6);
+    let entries: Vec<(u32, u32)> = txw.cursor(&uint_db).iter().collect();
+    assert_eq!(entries, vec![(1, 1), (6, 60), (7, 7)]);
+    txw.commit();
+
+    let tx = ReadTransaction::new(env);
+    let entries: Vec<(u32, u32)> = tx.cursor(&uint_db).iter().collect();
+    assert_eq!(entries, vec![(1, 1), (6, 60), (7, 7)]);
+}
+
+fn write_cursor_test(env: &Environment) {
+    let uint_db = env.open_database_with_flags("uint".to_string(), DatabaseFlags::UINT_KEYS);
+    let dup_db = env.open_database_with_flags("dup".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::UINT_KEYS | DatabaseFlags::DUP_UINT_VALUES);
+
+    let mut txw = WriteTransaction::new(env);
+    for i in 1u32..4 {
+        txw.put::<u32, u32>(&uint_db, &i, &(i * 10));
+        txw.put::<u32, u32>(&dup_db, &i, &1);
+        txw.put::<u32, u32>(&dup_db, &i, &2);
+    }
+    {
+        let mut cursor = txw.write_cursor(&uint_db);
+        assert_eq!(cursor.first::<u32, u32>(), Some((1, 10)));
+        cursor.put_current::<u32>(&11);
+        assert_eq!(cursor.get_current::<u32, u32>(), Some((1, 11)));
+        assert_eq!(cursor.next::<u32, u32>(), Some((2, 20)));
+        cursor.delete_current();
+        assert_eq!(cursor.next::<u32, u32>(), Some((3, 30)));
+        cursor.append::<u32, u32>(&4, &40);
+        assert_eq!(cursor.get_current::<u32, u32>(), Some((4, 40)));
+
+        let mut cursor = txw.write_cursor(&dup_db);
+        assert!(cursor.seek_key_value::<u32, u32>(&2, &1));
+        cursor.put_current::<u32>(&3);
+        assert_eq!(cursor.get_current::<u32, u32>(), Some((2, 3)));
+    }
+    txw.commit();
+
+    let tx = ReadTransaction::new(env);
+    let entries: Vec<(u32, u32)> = tx.cursor(&uint_db).iter().collect();
+    assert_eq!(entries, vec![(1, 11), (3, 30), (4, 40)]);
+    let entries: Vec<(u32, u32)> = tx.cursor(&dup_db).iter().collect();
+    assert_eq!(entries, vec![(1, 1), (1, 2), (2, 2), (2, 3), (3, 1), (3, 2)]);
+}
+
+fn write_batch_test(env: &Environment) {
+    let db = env.open_database("test".to_string());
+    let dup_db = env.open_database_with_flags("dup".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_UINT_VALUES);
+
+    let mut txw = WriteTransaction::new(env);
+    txw.put_reserve(&db, "b", "existing");
+    txw.put_reserve(&db, "d", "removed");
+    txw.commit();
+
+    let mut batch = WriteBatch::new();
+    // Appended, since they are past the last key.
+    batch.put_reserve(&db, "f", "six");
+    batch.put_reserve(&db, "e", "five");
+    // Overwrites and inserts before the last key.
+    batch.put_reserve(&db, "b", "two");
+    batch.put_reserve(&db, "a", "one");
+    batch.remove(&db, "d");
+    // Operations on the same key are applied in order.
+    batch.put_reserve(&db, "g", "seven");
+    batch.remove(&db, "g");
+    batch.put::<str, u32>(&dup_db, "x", &2);
+    batch.put::<str, u32>(&dup_db, "x", &1);
+    batch.remove_item::<str, u32>(&dup_db, "x", &2);
+    assert_eq!(batch.len(), 10);
+    batch.commit(env);
+
+    let tx = ReadTransaction::new(env);
+    let mut cursor = tx.cursor(&db);
+    let entries: Vec<(String, String)> = cursor.iter().collect();
+    assert_eq!(entries, vec![
+        ("a".to_string(), "one".to_string()),
+        ("b".to_string(), "two".to_string()),
+        ("e".to_string(), "five".to_string()),
+        ("f".to_string(), "six".to_string()),
+    ]);
+
+    let mut cursor = tx.cursor(&dup_db);
+    assert_eq!(cursor.seek_key::<str, u32>("x"), Some(1));
+    assert_eq!(cursor.count_duplicates(), 1);
+}
+
+macro_rules! backend_tests {
+    ($backend: ident, $open: expr) => {
+        mod $backend {
+            use super::*;
+
+            /// Runs `test` with a new environment, which is opened by `$open` in a temporary
+            /// directory.
+            fn run(test: fn(&Environment)) {
+                let dir = TempDir::new(concat!("backend-tests-", stringify!($backend))).unwrap();
+                let open: fn(&str) -> Environment = $open;
+                let env = open(dir.path().to_str().unwrap());
+                test(&env);
+                env.drop_database().unwrap();
+            }
+
+            #[test]
+            fn it_can_save_basic_objects() {
+                run(super::it_can_save_basic_objects);
+            }
+
+            #[test]
+            fn isolation_test() {
+                run(super::isolation_test);
+            }
+
+            #[test]
+            fn databases_are_separate() {
+                run(super::databases_are_separate);
+            }
+
+            #[test]
+            fn duplicates_test() {
+                run(super::duplicates_test);
+            }
+
+            #[test]
+            fn cursor_test() {
+                run(super::cursor_test);
+            }
+
+            #[test]
+            fn scan_test() {
+                run(super::scan_test);
+            }
+
+            #[test]
+            fn remove_range_test() {
+                run(super::remove_range_test);
+            }
+
+            #[test]
+            fn uncommitted_changes_test() {
+                run(super::uncommitted_changes_test);
+            }
+
+            #[test]
+            fn write_cursor_test() {
+                run(super::write_cursor_test);
+            }
+
+            #[test]
+            fn write_batch_test() {
+                run(super::write_batch_test);
+            }
+        }
+    };
+}
+
+#[cfg(feature = "lmdb")]
+backend_tests!(lmdb, |path| crate::lmdb::LmdbEnvironmentBuilder::new(path).with_max_dbs(4).build().unwrap());
+backend_tests!(volatile, |_| crate::volatile::VolatileEnvironment::new(4).unwrap());
+#[cfg(feature = "rocksdb")]
+backend_tests!(rocks, |path| crate::rocks::RocksEnvironment::new(path, 4).unwrap());
+#[cfg(feature = "mdbx")]
+backend_tests!(mdbx, |path| crate::mdbx::MdbxEnvironment::new(path, 0, 4, false).unwrap());
+#[cfg(feature = "sled")]
+backend_tests!(sled, |path| crate::sled::SledEnvironment::new(path, 4).unwrap());
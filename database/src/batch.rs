@@ -0,0 +1,184 @@
+use std::io::{Read, Write};
+
+use beserial::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::*;
+
+#[derive(Debug)]
+enum BatchOp {
+    Put(Vec<u8>),
+    Remove,
+    RemoveItem(Vec<u8>),
+}
+
+/// Collects puts and removes in memory, so that they can be applied in a single write transaction.
+///
+/// Operations are applied sorted by key. For databases without duplicate or integer keys,
+/// puts past the last key already in the database are appended, which avoids the B-tree
+/// traversal for each key. Operations on the same key are applied in the order they were added.
+#[derive(Debug, Default)]
+pub struct WriteBatch<'db, 'env> {
+    databases: Vec<(&'db Database<'env>, Vec<(Vec<u8>, BatchOp)>)>,
+    len: usize,
+}
+
+impl<'db, 'env> WriteBatch<'db, 'env> {
+    pub fn new() -> Self {
+        WriteBatch {
+            databases: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn put<K, V>(&mut self, db: &'db Database<'env>, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
+        let value = AsDatabaseBytes::as_database_bytes(value).into_owned();
+        self.push(db, key, BatchOp::Put(value));
+    }
+
+    /// Serialises the value into the batch, analogous to `WriteTransaction::put_reserve`.
+    pub fn put_reserve<K, V>(&mut self, db: &'db Database<'env>, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: IntoDatabaseValue + ?Sized {
+        let mut bytes = vec![0u8; IntoDatabaseValue::database_byte_size(value)];
+        IntoDatabaseValue::copy_into_database(value, &mut bytes);
+        self.push(db, key, BatchOp::Put(bytes));
+    }
+
+    pub fn remove<K>(&mut self, db: &'db Database<'env>, key: &K) where K: AsDatabaseBytes + ?Sized {
+        self.push(db, key, BatchOp::Remove);
+    }
+
+    pub fn remove_item<K, V>(&mut self, db: &'db Database<'env>, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
+        let value = AsDatabaseBytes::as_database_bytes(value).into_owned();
+        self.push(db, key, BatchOp::RemoveItem(value));
+    }
+
+    /// Number of operations in the batch.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Applies the batch in a new write transaction and commits it.
+    pub fn commit(self, env: &'env Environment) {
+        let mut txn = WriteTransaction::new(env);
+        self.apply(&mut txn);
+        txn.commit();
+    }
+
+    /// Applies the batch in the given write transaction.
+    pub fn apply(self, txn: &mut WriteTransaction) {
+        for (db, mut ops) in self.databases {
+            // The sort is stable, so operations on the same key keep their order.
+            ops.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            let flags = db.flags();
+            let can_append = !flags.contains(DatabaseFlags::DUPLICATE_KEYS) && !flags.has_uint_keys();
+            let mut last_key = if can_append { txn.last_key(db) } else { None };
+
+            for (key, op) in ops {
+                match op {
+                    BatchOp::Put(value) => {
+                        if can_append && last_key.as_ref().map_or(true, |last_key| key > *last_key) {
+                            txn.put_append(db, &key, &value);
+                            last_key = Some(key);
+                        } else {
+                            txn.put(db, key.as_slice(), value.as_slice());
+                        }
+                    },
+                    BatchOp::Remove => txn.remove(db, key.as_slice()),
+                    BatchOp::RemoveItem(value) => txn.remove_item(db, key.as_slice(), value.as_slice()),
+                }
+            }
+        }
+    }
+
+    /// Writes the batch to `writer`, so that it can be replayed with `replay` after a crash.
+    pub(crate) fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.databases.len() as u32)?;
+        for (db, ops) in self.databases.iter() {
+            write_bytes(writer, db.name().as_bytes())?;
+            writer.write_u32::<BigEndian>(db.flags().bits())?;
+            writer.write_u32::<BigEndian>(ops.len() as u32)?;
+            for (key, op) in ops.iter() {
+                write_bytes(writer, key)?;
+                match op {
+                    BatchOp::Put(value) => {
+                        writer.write_u8(0)?;
+                        write_bytes(writer, value)?;
+                    },
+                    BatchOp::Remove => writer.write_u8(1)?,
+                    BatchOp::RemoveItem(value) => {
+                        writer.write_u8(2)?;
+                        write_bytes(writer, value)?;
+                    },
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a batch written by `write_to` and commits it to `env`. The databases are opened by
+    /// name, so this works in a new process.
+    pub(crate) fn replay<R: Read>(reader: &mut R, env: &Environment) -> io::Result<()> {
+        let mut databases = Vec::new();
+        let mut all_ops = Vec::new();
+        for _ in 0..reader.read_u32::<BigEndian>()? {
+            let name = String::from_utf8(read_bytes(reader)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let flags = DatabaseFlags::from_bits(reader.read_u32::<BigEndian>()?)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid database flags"))?;
+            let mut ops = Vec::new();
+            for _ in 0..reader.read_u32::<BigEndian>()? {
+                let key = read_bytes(reader)?;
+                let op = match reader.read_u8()? {
+                    0 => BatchOp::Put(read_bytes(reader)?),
+                    1 => BatchOp::Remove,
+                    2 => BatchOp::RemoveItem(read_bytes(reader)?),
+                    _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid batch operation")),
+                };
+                ops.push((key, op));
+            }
+            databases.push((name, flags));
+            all_ops.push(ops);
+        }
+
+        // Open all databases before the write transaction is created.
+        let databases: Vec<Database> = databases.into_iter()
+            .map(|(name, flags)| env.open_database_with_flags(name, flags))
+            .collect();
+        let mut batch = WriteBatch::new();
+        for (db, ops) in databases.iter().zip(all_ops) {
+            for (key, op) in ops {
+                batch.push(db, key.as_slice(), op);
+            }
+        }
+        batch.commit(env);
+        Ok(())
+    }
+
+    fn push<K>(&mut self, db: &'db Database<'env>, key: &K, op: BatchOp) where K: AsDatabaseBytes + ?Sized {
+        let key = AsDatabaseBytes::as_database_bytes(key).into_owned();
+        let index = match self.databases.iter().position(|(other, _)| std::ptr::eq(*other, db)) {
+            Some(index) => index,
+            None => {
+                self.databases.push((db, Vec::new()));
+                self.databases.len() - 1
+            },
+        };
+        self.databases[index].1.push((key, op));
+        self.len += 1;
+    }
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_u32::<BigEndian>(bytes.len() as u32)?;
+    writer.write_all(bytes)
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0u8; reader.read_u32::<BigEndian>()? as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
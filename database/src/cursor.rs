@@ -1,40 +1,6 @@
-use crate::{AsDatabaseBytes, FromDatabaseValue};
-
-pub(crate) trait RawReadCursor {
-    fn first<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
-
-    fn first_duplicate<V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(V)> where V: FromDatabaseValue;
-
-    fn last<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
-
-    fn last_duplicate<V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(V)> where V: FromDatabaseValue;
-
-    fn seek_key_value<K, V>(&mut self, key: &K, value: &V) -> bool where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized;
-
-    fn seek_key_nearest_value<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor, key: &K, value: &V) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + FromDatabaseValue;
-
-    fn get_current<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
-
-    fn next<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
-
-    fn next_duplicate<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
-
-    fn next_no_duplicate<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
+use std::ops::Range;
 
-    fn prev<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
-
-    fn prev_duplicate<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
-
-    fn prev_no_duplicate<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
-
-    fn seek_key<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue;
-
-    fn seek_key_both<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue;
-
-    fn seek_range_key<K, V>(&mut self, accessor: &lmdb_zero::ConstAccessor, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue;
-
-    fn count_duplicates(&mut self) -> usize;
-}
+use crate::{AsDatabaseBytes, FromDatabaseValue};
 
 pub trait ReadCursor {
     fn first<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue;
@@ -69,98 +35,132 @@ pub trait ReadCursor {
 
     fn seek_range_key<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue;
 
+    /// Like `seek_range_key`, but `key` doesn't need to be of the same type as the keys in the
+    /// database, e.g. it can be a prefix of them.
+    fn seek_range<Q, K, V>(&mut self, key: &Q) -> Option<(K, V)> where Q: AsDatabaseBytes + ?Sized, K: FromDatabaseValue, V: FromDatabaseValue;
+
     fn count_duplicates(&mut self) -> usize;
+
+    /// Returns an iterator over all `(K, V)` pairs, starting at the first entry.
+    fn iter<K, V>(&mut self) -> CursorIter<Self, K, V> where Self: Sized, K: FromDatabaseValue, V: FromDatabaseValue {
+        let first = self.first();
+        CursorIter::new(self, first)
+    }
+
+    /// Returns an iterator over all `(K, V)` pairs, starting at the first key greater than or
+    /// equal to `key`.
+    fn iter_from<Q, K, V>(&mut self, key: &Q) -> CursorIter<Self, K, V> where Self: Sized, Q: AsDatabaseBytes + ?Sized, K: FromDatabaseValue, V: FromDatabaseValue {
+        let first = self.seek_range(key);
+        CursorIter::new(self, first)
+    }
 }
 
-macro_rules! impl_read_cursor_from_raw {
-    ($t: ty, $raw: ident, $txn: ident) => {
-        impl<'txn, 'db> ReadCursor for $t {
-            fn first<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.first(&access)
-            }
-
-            fn first_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.first_duplicate(&access)
-            }
-
-            fn last<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.last(&access)
-            }
-
-            fn last_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.last_duplicate(&access)
-            }
-
-            fn seek_key_value<K, V>(&mut self, key: &K, value: &V) -> bool where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-                self.$raw.seek_key_value(key, value)
-            }
-
-            fn seek_key_nearest_value<K, V>(&mut self, key: &K, value: &V) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.seek_key_nearest_value(&access, key, value)
-            }
-
-            fn get_current<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.get_current(&access)
-            }
-
-            fn next<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.next(&access)
-            }
-
-            fn next_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.next_duplicate(&access)
-            }
-
-            fn next_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.next_no_duplicate(&access)
-            }
-
-            fn prev<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.prev(&access)
-            }
-
-            fn prev_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.prev_duplicate(&access)
-            }
-
-            fn prev_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.prev_no_duplicate(&access)
-            }
-
-            fn seek_key<K, V>(&mut self, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.seek_key(&access, key)
-            }
-
-            fn seek_key_both<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.seek_key_both(&access, key)
-            }
-
-            fn seek_range_key<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-                let access = self.$txn.access();
-                self.$raw.seek_range_key(&access, key)
-            }
-
-            fn count_duplicates(&mut self) -> usize {
-                self.$raw.count_duplicates()
-            }
+/// Iterator over the `(K, V)` pairs of a cursor, see `ReadCursor::iter` and `ReadCursor::iter_from`.
+/// Duplicate values are yielded as separate pairs.
+pub struct CursorIter<'c, C: ReadCursor, K, V> {
+    cursor: &'c mut C,
+    first: Option<Option<(K, V)>>,
+    done: bool,
+}
+
+impl<'c, C: ReadCursor, K, V> CursorIter<'c, C, K, V> {
+    fn new(cursor: &'c mut C, first: Option<(K, V)>) -> Self {
+        CursorIter {
+            cursor,
+            first: Some(first),
+            done: false,
+        }
+    }
+}
+
+impl<'c, C: ReadCursor, K: FromDatabaseValue, V: FromDatabaseValue> Iterator for CursorIter<'c, C, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        if self.done {
+            return None;
+        }
+
+        let item = match self.first.take() {
+            Some(first) => first,
+            None => self.cursor.next(),
+        };
+        self.done = item.is_none();
+        item
+    }
+}
+
+/// Where a `Scan` stops.
+enum ScanEnd<K> {
+    /// At the first key that doesn't start with these bytes.
+    Prefix(Vec<u8>),
+    /// At the first key greater than or equal to this one.
+    Before(K),
+}
+
+/// Iterator over the `(K, V)` pairs of a prefix or range scan, see `Transaction::scan_prefix` and
+/// `Transaction::scan_range`. Unlike `CursorIter`, it owns its cursor.
+pub struct Scan<C: ReadCursor, K, V> {
+    cursor: C,
+    first: Option<Option<(K, V)>>,
+    end: ScanEnd<K>,
+    done: bool,
+}
+
+impl<C: ReadCursor, K: FromDatabaseValue, V: FromDatabaseValue> Scan<C, K, V> {
+    pub(crate) fn prefix<P>(mut cursor: C, prefix: &P) -> Self where P: AsDatabaseBytes + ?Sized {
+        let first = cursor.seek_range(prefix);
+        Scan {
+            cursor,
+            first: Some(first),
+            end: ScanEnd::Prefix(prefix.as_database_bytes().into_owned()),
+            done: false,
         }
-    };
+    }
+
+    pub(crate) fn range(mut cursor: C, range: Range<K>) -> Self where K: AsDatabaseBytes {
+        let first = cursor.seek_range(&range.start);
+        Scan {
+            cursor,
+            first: Some(first),
+            end: ScanEnd::Before(range.end),
+            done: false,
+        }
+    }
 }
 
+impl<C: ReadCursor, K: AsDatabaseBytes + FromDatabaseValue + PartialOrd, V: FromDatabaseValue> Iterator for Scan<C, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        if self.done {
+            return None;
+        }
+
+        let item = match self.first.take() {
+            Some(first) => first,
+            None => self.cursor.next(),
+        };
+        let item = item.filter(|(key, _)| match self.end {
+            ScanEnd::Prefix(ref prefix) => key.as_database_bytes().starts_with(prefix),
+            ScanEnd::Before(ref end) => key < end,
+        });
+        self.done = item.is_none();
+        item
+    }
+}
+
+/// A cursor of a write transaction, which can also modify the entry it is positioned at.
+/// Modifying consecutive entries through a cursor avoids looking up each key again.
 pub trait WriteCursor: ReadCursor {
-    fn remove(&mut self);
+    /// Replaces the value of the current entry. The cursor stays at the modified entry.
+    /// Panics if the cursor isn't positioned at an entry.
+    fn put_current<V>(&mut self, value: &V) where V: AsDatabaseBytes + ?Sized;
+
+    /// Puts a key/value pair at the end of the database and moves the cursor to it.
+    /// The key must be greater than all keys already in the database.
+    fn append<K, V>(&mut self, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized;
+
+    /// Removes the current entry. Afterwards, `next` continues with the following entry.
+    fn delete_current(&mut self);
 }
@@ -0,0 +1,143 @@
+use std::cmp;
+use std::collections::BTreeMap;
+
+use super::DatabaseFlags;
+
+/// Backends without named databases store all databases in one ordered key space. Each
+/// database has its own key prefix, which is its id in big-endian. Id 0 is reserved for the
+/// entries mapping database names to ids.
+pub(crate) const META_PREFIX: [u8; 4] = [0; 4];
+
+/// Terminates an escaped key in databases with duplicate keys, see `escape_key`.
+const KEY_TERMINATOR: [u8; 2] = [0x00, 0x00];
+/// Sorts after `KEY_TERMINATOR` and before all escaped keys that are longer.
+const KEY_GROUP_END: [u8; 2] = [0x00, 0x01];
+
+/// Changes to the committed state that are kept in memory. `None` marks an absent entry.
+pub(crate) type Overlay = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// Encodes the entries of a database such that the bytewise order of the raw keys matches the
+/// order LMDB would use:
+///  * Integer keys and values are stored in big-endian.
+///  * In databases with duplicate keys, each value is appended to its escaped key and the raw
+///    value is empty.
+pub(crate) trait PrefixedKeys {
+    /// The id of the database, which is the prefix of all its raw keys.
+    fn id(&self) -> u32;
+
+    fn key_flags(&self) -> DatabaseFlags;
+
+    fn has_duplicates(&self) -> bool {
+        self.key_flags().contains(DatabaseFlags::DUPLICATE_KEYS)
+    }
+
+    /// The smallest raw key of this database.
+    fn start(&self) -> Vec<u8> {
+        self.id().to_be_bytes().to_vec()
+    }
+
+    /// The smallest raw key past this database.
+    fn end(&self) -> Vec<u8> {
+        (self.id() + 1).to_be_bytes().to_vec()
+    }
+
+    /// Encodes a key without the terminator, so that it can be used to seek to keys it is a
+    /// prefix of.
+    fn encode_prefix(&self, key: &[u8]) -> Vec<u8> {
+        let key = to_uint_order(key, self.key_flags().has_uint_keys());
+        let mut bytes = self.start();
+        if self.has_duplicates() {
+            escape_key(&key, &mut bytes);
+        } else {
+            bytes.extend_from_slice(&key);
+        }
+        bytes
+    }
+
+    /// Encodes a key. In databases with duplicate keys, this is the smallest raw key of all its
+    /// values.
+    fn encode_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut bytes = self.encode_prefix(key);
+        if self.has_duplicates() {
+            bytes.extend_from_slice(&KEY_TERMINATOR);
+        }
+        bytes
+    }
+
+    /// Encodes a key/value pair into the raw key and value.
+    fn encode(&self, key: &[u8], value: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut bytes = self.encode_key(key);
+        if self.has_duplicates() {
+            bytes.extend_from_slice(&to_uint_order(value, self.key_flags().contains(DatabaseFlags::DUP_UINT_VALUES)));
+            (bytes, Vec::new())
+        } else {
+            (bytes, value.to_vec())
+        }
+    }
+
+    /// The smallest raw key past all values of `key`.
+    fn encode_key_end(&self, key: &[u8]) -> Vec<u8> {
+        let mut bytes = self.encode_prefix(key);
+        if self.has_duplicates() {
+            bytes.extend_from_slice(&KEY_GROUP_END);
+        } else {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    fn decode(&self, raw_key: &[u8], raw_value: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let flags = self.key_flags();
+        let bytes = &raw_key[META_PREFIX.len()..];
+        if self.has_duplicates() {
+            let (key, value) = unescape_key(bytes);
+            (to_uint_order(&key, flags.has_uint_keys()),
+             to_uint_order(value, flags.contains(DatabaseFlags::DUP_UINT_VALUES)))
+        } else {
+            (to_uint_order(bytes, flags.has_uint_keys()), raw_value.to_vec())
+        }
+    }
+
+    fn decode_key(&self, raw_key: &[u8]) -> Vec<u8> {
+        self.decode(raw_key, &[]).0
+    }
+}
+
+/// Escapes all zero bytes in `key`, so that a terminator can be appended without changing the
+/// order of the keys.
+fn escape_key(key: &[u8], bytes: &mut Vec<u8>) {
+    for &byte in key {
+        bytes.push(byte);
+        if byte == 0 {
+            bytes.push(0xff);
+        }
+    }
+}
+
+/// Returns the unescaped key and the bytes after its terminator.
+fn unescape_key(bytes: &[u8]) -> (Vec<u8>, &[u8]) {
+    let mut key = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == 0 {
+            if bytes.get(i + 1) != Some(&0xff) {
+                return (key, &bytes[cmp::min(i + KEY_TERMINATOR.len(), bytes.len())..]);
+            }
+            key.push(0);
+            i += 2;
+        } else {
+            key.push(bytes[i]);
+            i += 1;
+        }
+    }
+    (key, &[])
+}
+
+/// Converts native-endian integers to big-endian and back, so that they sort bytewise.
+fn to_uint_order(bytes: &[u8], is_uint: bool) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    if is_uint && cfg!(target_endian = "little") {
+        bytes.reverse();
+    }
+    bytes
+}
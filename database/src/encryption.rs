@@ -0,0 +1,195 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chacha20poly1305::XChaCha20Poly1305;
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use clear_on_drop::clear::Clear;
+#[cfg(feature = "lmdb")]
+use lmdb_zero;
+use nimiq_hash::argon2kdf::{Argon2Error, compute_argon2_kdf};
+use nimiq_hash::hmac::compute_hmac_sha512;
+use rand::{Rng, thread_rng};
+
+/// Encrypts the values of a database with XChaCha20-Poly1305. The nonce is stored in front of the
+/// ciphertext. The name of the database and the key of the entry are authenticated with the
+/// value, so a value can't be moved to another entry unnoticed.
+///
+/// The key is derived from a passphrase with Argon2d and never stored. The salt file in the
+/// database directory only holds the salt and an encrypted marker to recognize wrong passphrases.
+pub struct Cipher {
+    aead: XChaCha20Poly1305,
+    /// Key for the nonces of `encrypt_deterministic`.
+    nonce_key: [u8; Cipher::KEY_SIZE],
+}
+
+impl Cipher {
+    pub const SALT_FILE_NAME: &'static str = "encryption";
+    pub const KEY_SIZE: usize = 32;
+    const NONCE_SIZE: usize = 24;
+    const SALT_SIZE: usize = 32;
+    const KDF_ITERATIONS: u32 = 256;
+    const MARKER: &'static [u8] = b"nimiq-database";
+    const NONCE_KEY_CONTEXT: &'static [u8] = b"nimiq-database-nonce";
+
+    pub fn new(key: &[u8; Cipher::KEY_SIZE]) -> Self {
+        let mut nonce_key = [0u8; Cipher::KEY_SIZE];
+        nonce_key.copy_from_slice(&compute_hmac_sha512(key, Cipher::NONCE_KEY_CONTEXT).as_bytes()[..Cipher::KEY_SIZE]);
+        Cipher {
+            aead: XChaCha20Poly1305::new(GenericArray::clone_from_slice(key)),
+            nonce_key,
+        }
+    }
+
+    /// Derives the key of the database at `path` from `passphrase`. Creates the salt file if the
+    /// database is new.
+    pub fn open(path: &str, passphrase: &[u8]) -> Result<Self, EncryptionError> {
+        let salt_file = Path::new(path).join(Cipher::SALT_FILE_NAME);
+        if !salt_file.exists() {
+            if Path::new(path).join("data.mdb").exists() {
+                return Err(EncryptionError::Unencrypted);
+            }
+            return Cipher::create(&salt_file, passphrase);
+        }
+
+        let bytes = fs::read(&salt_file).map_err(EncryptionError::IoError)?;
+        if bytes.len() < Cipher::SALT_SIZE {
+            return Err(EncryptionError::IoError(io::Error::new(io::ErrorKind::InvalidData, "Salt file is too short")));
+        }
+        let (salt, marker) = bytes.split_at(Cipher::SALT_SIZE);
+        let cipher = Cipher::derive(passphrase, salt)?;
+        match cipher.decrypt(&[], marker) {
+            Some(ref plaintext) if plaintext.as_slice() == Cipher::MARKER => Ok(cipher),
+            _ => Err(EncryptionError::WrongPassphrase),
+        }
+    }
+
+    /// Whether the database at `path` was created with encryption.
+    pub fn is_encrypted(path: &str) -> bool {
+        Path::new(path).join(Cipher::SALT_FILE_NAME).exists()
+    }
+
+    /// The data authenticated with the value of `key` in the database `db_name`. Both lengths are
+    /// included, so that different entries never have the same associated data.
+    pub fn associated_data(db_name: &str, key: &[u8]) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(2 + db_name.len() + 4 + key.len());
+        aad.extend_from_slice(&(db_name.len() as u16).to_be_bytes());
+        aad.extend_from_slice(db_name.as_bytes());
+        aad.extend_from_slice(&(key.len() as u32).to_be_bytes());
+        aad.extend_from_slice(key);
+        aad
+    }
+
+    /// Encrypts `plaintext` with a random nonce.
+    pub fn encrypt(&self, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; Cipher::NONCE_SIZE];
+        thread_rng().fill(&mut nonce);
+        self.encrypt_with_nonce(nonce, aad, plaintext)
+    }
+
+    /// Encrypts `plaintext` with a nonce derived from it and `aad`, so that equal values of an
+    /// entry have equal ciphertexts. Databases with duplicate keys need this to find values.
+    pub fn encrypt_deterministic(&self, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut input = Vec::with_capacity(aad.len() + plaintext.len());
+        input.extend_from_slice(aad);
+        input.extend_from_slice(plaintext);
+        let mut nonce = [0u8; Cipher::NONCE_SIZE];
+        nonce.copy_from_slice(&compute_hmac_sha512(&self.nonce_key, &input).as_bytes()[..Cipher::NONCE_SIZE]);
+        self.encrypt_with_nonce(nonce, aad, plaintext)
+    }
+
+    fn encrypt_with_nonce(&self, nonce: [u8; Cipher::NONCE_SIZE], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let ciphertext = self.aead.encrypt(GenericArray::from_slice(&nonce), Payload { msg: plaintext, aad })
+            .expect("Failed to encrypt value");
+
+        let mut bytes = Vec::with_capacity(Cipher::NONCE_SIZE + ciphertext.len());
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&ciphertext);
+        bytes
+    }
+
+    /// Returns `None` if `bytes` weren't encrypted with this key and `aad` or were tampered with.
+    pub fn decrypt(&self, aad: &[u8], bytes: &[u8]) -> Option<Vec<u8>> {
+        if bytes.len() < Cipher::NONCE_SIZE {
+            return None;
+        }
+        let (nonce, ciphertext) = bytes.split_at(Cipher::NONCE_SIZE);
+        self.aead.decrypt(GenericArray::from_slice(nonce), Payload { msg: ciphertext, aad }).ok()
+    }
+
+    fn create(salt_file: &Path, passphrase: &[u8]) -> Result<Self, EncryptionError> {
+        let mut salt = [0u8; Cipher::SALT_SIZE];
+        thread_rng().fill(&mut salt);
+        let cipher = Cipher::derive(passphrase, &salt)?;
+
+        let mut bytes = salt.to_vec();
+        bytes.extend(cipher.encrypt(&[], Cipher::MARKER));
+        if let Some(dir) = salt_file.parent() {
+            fs::create_dir_all(dir).map_err(EncryptionError::IoError)?;
+        }
+        fs::write(salt_file, bytes).map_err(EncryptionError::IoError)?;
+        Ok(cipher)
+    }
+
+    fn derive(passphrase: &[u8], salt: &[u8]) -> Result<Self, EncryptionError> {
+        let mut derived = compute_argon2_kdf(passphrase, salt, Cipher::KDF_ITERATIONS, Cipher::KEY_SIZE)
+            .map_err(EncryptionError::KdfError)?;
+        let mut key = [0u8; Cipher::KEY_SIZE];
+        key.copy_from_slice(&derived);
+        let cipher = Cipher::new(&key);
+        derived.as_mut_slice().clear();
+        key[..].clear();
+        Ok(cipher)
+    }
+}
+
+impl Drop for Cipher {
+    fn drop(&mut self) {
+        self.nonce_key[..].clear();
+    }
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cipher {{ .. }}")
+    }
+}
+
+#[derive(Debug)]
+pub enum EncryptionError {
+    IoError(io::Error),
+    #[cfg(feature = "lmdb")]
+    LmdbError(lmdb_zero::Error),
+    KdfError(Argon2Error),
+    /// The passphrase doesn't match the salt file.
+    WrongPassphrase,
+    /// The database already exists and isn't encrypted.
+    Unencrypted,
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncryptionError::IoError(e) => e.fmt(f),
+            #[cfg(feature = "lmdb")]
+            EncryptionError::LmdbError(e) => e.fmt(f),
+            EncryptionError::KdfError(e) => write!(f, "Key derivation failed: {:?}", e),
+            EncryptionError::WrongPassphrase => write!(f, "Wrong database passphrase"),
+            EncryptionError::Unencrypted => write!(f, "The existing database is not encrypted"),
+        }
+    }
+}
+
+impl Error for EncryptionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EncryptionError::IoError(e) => Some(e),
+            #[cfg(feature = "lmdb")]
+            EncryptionError::LmdbError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
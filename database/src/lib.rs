@@ -2,21 +2,55 @@
 extern crate bitflags;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "metrics")]
+#[macro_use]
+extern crate lazy_static;
 
+use std::any::Any;
 use std::borrow::Cow;
+use std::fmt;
 use std::io;
-use std::ops::Deref;
-
-use lmdb_zero;
-
-use crate::cursor::{ReadCursor, WriteCursor as WriteCursorTrait};
-pub use crate::traits::{AsDatabaseBytes, FromDatabaseValue, IntoDatabaseValue};
+use std::marker::PhantomData;
+use std::ops::{Deref, Range};
+
+use crate::backend::{Backend, BackendCursor, BackendTransaction, BackendWriteCursor, BackendWriteTransaction};
+use crate::cursor::{ReadCursor, Scan, WriteCursor as WriteCursorTrait};
+pub use crate::asynchronous::{AsyncReadTransaction, AsyncWriteTransaction, BlockingPool, TransactionFuture};
+pub use crate::batch::WriteBatch;
+pub use crate::encryption::{Cipher, EncryptionError};
+pub use crate::stats::{DatabaseStats, EnvironmentStats};
+pub use crate::ttl::TtlDatabase;
+pub use crate::typed::TypedDatabase;
+pub use crate::traits::{AsDatabaseBytes, FromDatabaseBytes, FromDatabaseValue, IntoDatabaseValue};
+pub use crate::wal::{AtomicWrite, WriteAheadLog};
 
-#[macro_use]
 pub mod cursor;
+pub mod asynchronous;
+pub mod batch;
+pub mod encryption;
+pub mod migrations;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod stats;
+pub mod ttl;
+pub mod typed;
+pub mod verify;
+pub mod wal;
+#[cfg(feature = "lmdb")]
 pub mod lmdb;
 pub mod volatile;
+#[cfg(feature = "rocksdb")]
+pub mod rocks;
+#[cfg(feature = "mdbx")]
+pub mod mdbx;
+#[cfg(feature = "sled")]
+pub mod sled;
+mod backend;
+mod encoding;
+mod prefixed;
 pub mod traits;
+#[cfg(test)]
+mod backend_tests;
 
 bitflags! {
     #[derive(Default)]
@@ -31,109 +65,222 @@ bitflags! {
         const UINT_KEYS             = 0b0000_0100;
         /// This option specifies that duplicate data items are binary integers, similar to `UINT_KEYS` keys.
         const DUP_UINT_VALUES       = 0b0000_1000;
+        /// Keys are `u64` in native byte order and will be sorted as such. Like `UINT_KEYS`, this
+        /// uses LMDB's integer keys, which are `size_t`, so it needs a 64-bit target.
+        /// Must not be combined with `UINT_KEYS`.
+        const U64_KEYS              = 0b0001_0000;
     }
 }
 
+impl DatabaseFlags {
+    /// Whether the keys are native-endian integers of either size.
+    pub(crate) fn has_uint_keys(self) -> bool {
+        self.intersects(DatabaseFlags::UINT_KEYS | DatabaseFlags::U64_KEYS)
+    }
+}
+
+/// An environment of one of the storage backends, which contains the databases.
 #[derive(Debug)]
-pub enum Environment {
-    Volatile(volatile::VolatileEnvironment),
-    Persistent(lmdb::LmdbEnvironment),
+pub struct Environment {
+    backend: Box<dyn Backend>,
 }
 
 impl Environment {
+    pub(crate) fn from_backend<B: Backend + 'static>(backend: B) -> Self {
+        Environment { backend: Box::new(backend) }
+    }
+
     pub fn open_database(&self, name: String) -> Database {
-        match *self {
-            Environment::Volatile(ref env) => { Database::Volatile(env.open_database(name, Default::default())) }
-            Environment::Persistent(ref env) => { Database::Persistent(env.open_database(name, Default::default())) }
-        }
+        self.open_database_with_flags(name, Default::default())
     }
 
     pub fn open_database_with_flags(&self, name: String, flags: DatabaseFlags) -> Database {
-        match *self {
-            Environment::Volatile(ref env) => { Database::Volatile(env.open_database(name, flags)) }
-            Environment::Persistent(ref env) => { Database::Persistent(env.open_database(name, flags)) }
-        }
+        assert!(!flags.contains(DatabaseFlags::UINT_KEYS | DatabaseFlags::U64_KEYS), "Database {} can't have both u32 and u64 keys", name);
+        assert!(!flags.contains(DatabaseFlags::U64_KEYS) || cfg!(target_pointer_width = "64"), "Database {} needs a 64-bit target for u64 keys", name);
+        #[cfg(feature = "metrics")]
+        metrics::DATABASE_METRICS.register_database(&name);
+        let handle = self.backend.open_database(&name, flags);
+        Database { env: &*self.backend, name, flags, handle }
+    }
+
+    /// Returns page usage and map size of the environment.
+    pub fn stats(&self) -> EnvironmentStats {
+        self.backend.stats()
+    }
+
+    /// Whether the environment was opened read-only, see `LmdbEnvironmentBuilder::build_read_only`. Write
+    /// transactions on it panic.
+    pub fn is_read_only(&self) -> bool {
+        self.backend.is_read_only()
+    }
+
+    /// Name of the storage backend, as in the `backend` setting of the database configuration.
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
     }
 
     pub fn close(self) {}
 
     pub fn drop_database(self) -> io::Result<()> {
-        match self {
-            Environment::Volatile(env) => { env.drop_database() }
-            Environment::Persistent(env) => { env.drop_database() }
-        }
+        self.backend.drop_database()
+    }
+
+    /// Flushes all committed writes to disk, regardless of the sync settings of the environment.
+    /// Once it returns, the writes before it are durable, so it can be used as a barrier between
+    /// commits that must reach the disk in order.
+    pub fn sync(&self) -> io::Result<()> {
+        self.backend.sync()
+    }
+
+    /// Writes a copy of a running environment to the directory at `path`. Only LMDB
+    /// environments support this.
+    pub fn backup_to(&self, path: &str) -> io::Result<()> {
+        self.backend.backup_to(path)
+    }
+
+    /// Rewrites the environment without its free pages to reclaim disk space, see
+    /// `LmdbEnvironment::compact`. Only LMDB environments support this, others are returned as is.
+    pub fn compact(self) -> io::Result<Self> {
+        Ok(Environment { backend: self.backend.compact()? })
     }
 }
 
-#[derive(Debug)]
-pub enum Database<'env> {
-    Volatile(volatile::VolatileDatabase<'env>),
-    Persistent(lmdb::LmdbDatabase<'env>),
+pub struct Database<'env> {
+    env: &'env dyn Backend,
+    name: String,
+    flags: DatabaseFlags,
+    /// The backend's handle of the database, see `Backend::open_database`.
+    handle: Box<dyn Any + Send + Sync>,
 }
 
 impl<'env> Database<'env> {
-    fn volatile(&self) -> Option<&volatile::VolatileDatabase> {
-        if let Database::Volatile(ref db) = self {
-            return Some(db);
-        }
-        None
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
-    fn persistent(&self) -> Option<&lmdb::LmdbDatabase> {
-        match self {
-            Database::Persistent(ref db) => Some(db),
-            Database::Volatile(ref db) => Some(db.as_lmdb()),
-        }
+    /// Returns entry count and page usage of the database.
+    /// This opens a read transaction, so changes of uncommitted write transactions are not included.
+    pub fn stats(&self) -> DatabaseStats {
+        self.env.database_stats(self)
+    }
+
+    pub(crate) fn flags(&self) -> DatabaseFlags {
+        self.flags
+    }
+
+    /// Returns the handle the backend returned when it opened the database.
+    pub(crate) fn handle<T: 'static>(&self) -> &T {
+        self.handle.downcast_ref().expect("Database belongs to another backend")
+    }
+}
+
+impl<'env> fmt::Debug for Database<'env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Database {{ backend: {}, name: {}, flags: {:?} }}", self.env.name(), self.name, self.flags)
     }
 }
 
 #[derive(Debug)]
-pub enum Transaction<'env> {
-    VolatileRead(volatile::VolatileReadTransaction<'env>),
-    VolatileWrite(volatile::VolatileWriteTransaction<'env>),
-    PersistentRead(lmdb::LmdbReadTransaction<'env>),
-    PersistentWrite(lmdb::LmdbWriteTransaction<'env>),
+enum BackendTransactionKind<'env> {
+    Read(Box<dyn BackendTransaction + 'env>),
+    Write(Box<dyn BackendWriteTransaction + 'env>),
+}
+
+#[derive(Debug)]
+pub struct Transaction<'env> {
+    txn: BackendTransactionKind<'env>,
 }
 
 impl<'env> Transaction<'env> {
+    fn backend_mut(&mut self) -> &mut (dyn BackendWriteTransaction + 'env) {
+        match self.txn {
+            BackendTransactionKind::Write(ref mut txn) => &mut **txn,
+            BackendTransactionKind::Read(_) => unreachable!(),
+        }
+    }
+
     pub fn get<K, V>(&self, db: &Database, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        match *self {
-            Transaction::VolatileRead(ref txn) => { txn.get(db.volatile().unwrap(), key) }
-            Transaction::VolatileWrite(ref txn) => { txn.get(db.volatile().unwrap(), key) }
-            Transaction::PersistentRead(ref txn) => { txn.get(db.persistent().unwrap(), key) }
-            Transaction::PersistentWrite(ref txn) => { txn.get(db.persistent().unwrap(), key) }
+        let bytes = self.get_cow(db, key)?;
+        Some(FromDatabaseValue::copy_from_database(&bytes).unwrap())
+    }
+
+    /// Returns the raw bytes of the value of `key`. Read transactions of the LMDB and MDBX
+    /// backends borrow unencrypted values from the memory map instead of copying them.
+    pub fn get_cow<'txn, K>(&'txn self, db: &Database, key: &K) -> Option<Cow<'txn, [u8]>> where K: AsDatabaseBytes + ?Sized {
+        #[cfg(feature = "metrics")]
+        metrics::DATABASE_METRICS.note_read(db);
+        let key = AsDatabaseBytes::as_database_bytes(key);
+        match self.txn {
+            BackendTransactionKind::Read(ref txn) => txn.get(db, key.as_ref()),
+            BackendTransactionKind::Write(ref txn) => txn.get(db, key.as_ref()),
         }
     }
 
-    pub fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> Cursor<'txn, 'db> {
-        match *self {
-            Transaction::VolatileRead(ref txn) => { Cursor::VolatileCursor(txn.cursor(db)) }
-            Transaction::VolatileWrite(ref txn) => { Cursor::VolatileCursor(txn.cursor(db)) }
-            Transaction::PersistentRead(ref txn) => { Cursor::PersistentCursor(txn.cursor(db)) }
-            Transaction::PersistentWrite(ref txn) => { Cursor::PersistentCursor(txn.cursor(db)) }
+    /// Like `get`, but borrows the value from the bytes of `get_cow` if they are borrowed.
+    pub fn get_ref<'txn, K, V>(&'txn self, db: &Database, key: &K) -> Option<Cow<'txn, V>> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseBytes + ToOwned + ?Sized {
+        match self.get_cow(db, key)? {
+            Cow::Borrowed(bytes) => Some(Cow::Borrowed(V::from_database_bytes(bytes).unwrap())),
+            Cow::Owned(bytes) => Some(Cow::Owned(V::from_database_bytes(&bytes).unwrap().to_owned())),
         }
     }
+
+    pub fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> Cursor<'txn, 'db> where 'db: 'txn {
+        #[cfg(feature = "metrics")]
+        metrics::DATABASE_METRICS.note_read(db);
+        let raw = match self.txn {
+            BackendTransactionKind::Read(ref txn) => txn.cursor(db),
+            BackendTransactionKind::Write(ref txn) => txn.cursor(db),
+        };
+        Cursor { raw, _db: PhantomData }
+    }
+
+    /// Iterates over all entries whose key starts with `prefix`. The database must be ordered
+    /// by the bytes of its keys, i.e. must not use `UINT_KEYS` or `U64_KEYS`.
+    pub fn scan_prefix<'txn, 'db, P, K, V>(&'txn self, db: &'db Database<'env>, prefix: &P) -> Scan<Cursor<'txn, 'db>, K, V>
+        where 'db: 'txn, P: AsDatabaseBytes + ?Sized, K: FromDatabaseValue, V: FromDatabaseValue {
+        Scan::prefix(self.cursor(db), prefix)
+    }
+
+    /// Iterates over all entries whose key is in `range`. The ordering of `K` must match the
+    /// ordering of the keys in the database.
+    pub fn scan_range<'txn, 'db, K, V>(&'txn self, db: &'db Database<'env>, range: Range<K>) -> Scan<Cursor<'txn, 'db>, K, V>
+        where 'db: 'txn, K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
+        Scan::range(self.cursor(db), range)
+    }
 }
 
 #[derive(Debug)]
-pub struct ReadTransaction<'env>(Transaction<'env>);
+pub struct ReadTransaction<'env> {
+    txn: Transaction<'env>,
+    #[cfg(feature = "metrics")]
+    metrics: metrics::TransactionMetrics,
+}
 
 impl<'env> ReadTransaction<'env> {
     pub fn new(env: &'env Environment) -> Self {
-        match *env {
-            Environment::Volatile(ref env) => { ReadTransaction(Transaction::VolatileRead(volatile::VolatileReadTransaction::new(env))) }
-            Environment::Persistent(ref env) => { ReadTransaction(Transaction::PersistentRead(lmdb::LmdbReadTransaction::new(env))) }
+        ReadTransaction {
+            txn: Transaction { txn: BackendTransactionKind::Read(env.backend.read_transaction()) },
+            #[cfg(feature = "metrics")]
+            metrics: metrics::TransactionMetrics::new(metrics::TransactionKind::Read),
         }
     }
 
     pub fn get<K, V>(&self, db: &Database, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        self.0.get(db, key)
+        self.txn.get(db, key)
+    }
+
+    pub fn get_cow<'txn, K>(&'txn self, db: &Database, key: &K) -> Option<Cow<'txn, [u8]>> where K: AsDatabaseBytes + ?Sized {
+        self.txn.get_cow(db, key)
+    }
+
+    pub fn get_ref<'txn, K, V>(&'txn self, db: &Database, key: &K) -> Option<Cow<'txn, V>> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseBytes + ToOwned + ?Sized {
+        self.txn.get_ref(db, key)
     }
 
     pub fn close(self) {}
 
-    pub fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> Cursor<'txn, 'db> {
-        self.0.cursor(db)
+    pub fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> Cursor<'txn, 'db> where 'db: 'txn {
+        self.txn.cursor(db)
     }
 }
 
@@ -141,34 +288,47 @@ impl<'env> Deref for ReadTransaction<'env> {
     type Target = Transaction<'env>;
 
     fn deref(&self) -> &Transaction<'env> {
-        &self.0
+        &self.txn
     }
 }
 
 #[derive(Debug)]
-pub struct WriteTransaction<'env>(Transaction<'env>);
+pub struct WriteTransaction<'env> {
+    txn: Transaction<'env>,
+    #[cfg(feature = "metrics")]
+    metrics: metrics::TransactionMetrics,
+}
 
 impl<'env> WriteTransaction<'env> {
     pub fn new(env: &'env Environment) -> Self {
-        match *env {
-            Environment::Volatile(ref env) => { WriteTransaction(Transaction::VolatileWrite(volatile::VolatileWriteTransaction::new(env))) }
-            Environment::Persistent(ref env) => { WriteTransaction(Transaction::PersistentWrite(lmdb::LmdbWriteTransaction::new(env))) }
+        WriteTransaction {
+            txn: Transaction { txn: BackendTransactionKind::Write(env.backend.write_transaction()) },
+            #[cfg(feature = "metrics")]
+            metrics: metrics::TransactionMetrics::new(metrics::TransactionKind::Write),
         }
     }
 
     pub fn get<K, V>(&self, db: &Database, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        self.0.get(db, key)
+        self.txn.get(db, key)
+    }
+
+    pub fn get_cow<'txn, K>(&'txn self, db: &Database, key: &K) -> Option<Cow<'txn, [u8]>> where K: AsDatabaseBytes + ?Sized {
+        self.txn.get_cow(db, key)
+    }
+
+    pub fn get_ref<'txn, K, V>(&'txn self, db: &Database, key: &K) -> Option<Cow<'txn, V>> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseBytes + ToOwned + ?Sized {
+        self.txn.get_ref(db, key)
     }
 
     /// Puts a key/value pair into the database by copying it into a reserved space in the database.
     /// This works best for values that need to be serialised into the reserved space.
     /// This method will panic when called on a database with duplicate keys!
     pub fn put_reserve<K, V>(&mut self, db: &Database, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: IntoDatabaseValue + ?Sized {
-        match self.0 {
-            Transaction::VolatileWrite(ref mut txn) => { txn.put_reserve(db.volatile().unwrap(), key, value) }
-            Transaction::PersistentWrite(ref mut txn) => { txn.put_reserve(db.persistent().unwrap(), key, value) }
-            _ => { unreachable!(); }
-        }
+        let key = AsDatabaseBytes::as_database_bytes(key);
+        let size = IntoDatabaseValue::database_byte_size(value);
+        #[cfg(feature = "metrics")]
+        self.metrics.note_write(db, key.len() + size);
+        self.txn.backend_mut().put_reserve(db, key.as_ref(), size, &mut |bytes| IntoDatabaseValue::copy_into_database(value, bytes));
     }
 
     /// Puts a key/value pair into the database by passing a reference to a byte slice.
@@ -176,48 +336,84 @@ impl<'env> WriteTransaction<'env> {
     /// and the existing value can be immediately written into the database.
     /// This also works with duplicate key databases.
     pub fn put<K, V>(&mut self, db: &Database, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        match self.0 {
-            Transaction::VolatileWrite(ref mut txn) => { txn.put(db.volatile().unwrap(), key, value) }
-            Transaction::PersistentWrite(ref mut txn) => { txn.put(db.persistent().unwrap(), key, value) }
-            _ => { unreachable!(); }
-        }
+        let key = AsDatabaseBytes::as_database_bytes(key);
+        let value = AsDatabaseBytes::as_database_bytes(value);
+        #[cfg(feature = "metrics")]
+        self.metrics.note_write(db, key.len() + value.len());
+        self.txn.backend_mut().put(db, key.as_ref(), value.as_ref());
     }
 
     pub fn remove<K>(&mut self, db: &Database, key: &K) where K: AsDatabaseBytes + ?Sized {
-        match self.0 {
-            Transaction::VolatileWrite(ref mut txn) => { txn.remove(db.volatile().unwrap(), key) }
-            Transaction::PersistentWrite(ref mut txn) => { txn.remove(db.persistent().unwrap(), key) }
-            _ => { unreachable!(); }
-        }
+        let key = AsDatabaseBytes::as_database_bytes(key);
+        #[cfg(feature = "metrics")]
+        self.metrics.note_write(db, key.len());
+        self.txn.backend_mut().remove(db, key.as_ref());
     }
 
     pub fn remove_item<K, V>(&mut self, db: &Database, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        match self.0 {
-            Transaction::VolatileWrite(ref mut txn) => { txn.remove_item(db.volatile().unwrap(), key, value) }
-            Transaction::PersistentWrite(ref mut txn) => { txn.remove_item(db.persistent().unwrap(), key, value) }
-            _ => { unreachable!(); }
+        let key = AsDatabaseBytes::as_database_bytes(key);
+        #[cfg(feature = "metrics")]
+        self.metrics.note_write(db, key.len());
+        self.txn.backend_mut().remove_item(db, key.as_ref(), AsDatabaseBytes::as_database_bytes(value).as_ref());
+    }
+
+    /// Removes all entries from the database.
+    pub fn clear_database(&mut self, db: &Database) {
+        #[cfg(feature = "metrics")]
+        self.metrics.note_write(db, 0);
+        self.txn.backend_mut().clear_database(db);
+    }
+
+    /// Removes all entries whose key is in `range`, including all values of duplicate keys.
+    /// The ordering of `K` must match the ordering of the keys in the database. Only the keys
+    /// are read, so this is much cheaper than removing the entries through a cursor.
+    pub fn remove_range<K>(&mut self, db: &Database, range: Range<K>) where K: AsDatabaseBytes {
+        #[cfg(feature = "metrics")]
+        self.metrics.note_write(db, 0);
+        let start = AsDatabaseBytes::as_database_bytes(&range.start);
+        let end = AsDatabaseBytes::as_database_bytes(&range.end);
+        self.txn.backend_mut().remove_range(db, start.as_ref(), end.as_ref());
+    }
+
+    /// Applies all operations of a `WriteBatch` in this transaction.
+    pub fn write_batch(&mut self, batch: WriteBatch) {
+        batch.apply(self)
+    }
+
+    fn put_append(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        #[cfg(feature = "metrics")]
+        self.metrics.note_write(db, key.len() + value.len());
+        self.txn.backend_mut().put_append(db, key, value);
+    }
+
+    fn last_key(&self, db: &Database) -> Option<Vec<u8>> {
+        match self.txn.txn {
+            BackendTransactionKind::Write(ref txn) => txn.last_key(db),
+            BackendTransactionKind::Read(_) => unreachable!(),
         }
     }
 
     pub fn commit(self) {
-        match self.0 {
-            Transaction::VolatileWrite(txn) => { txn.commit() }
-            Transaction::PersistentWrite(txn) => { txn.commit() }
-            _ => { unreachable!(); }
+        #[cfg(feature = "metrics")]
+        let metrics = self.metrics;
+        match self.txn.txn {
+            BackendTransactionKind::Write(txn) => txn.commit(),
+            BackendTransactionKind::Read(_) => unreachable!(),
         }
+        #[cfg(feature = "metrics")]
+        metrics.commit();
     }
 
     pub fn abort(self) {}
 
-    pub fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> Cursor<'txn, 'db> {
-        self.0.cursor(db)
+    pub fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> Cursor<'txn, 'db> where 'db: 'txn {
+        self.txn.cursor(db)
     }
 
-    pub fn write_cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> WriteCursor<'txn, 'db> {
-        match self.0 {
-            Transaction::VolatileWrite(ref txn) => { WriteCursor::VolatileCursor(txn.write_cursor(db)) }
-            Transaction::PersistentWrite(ref txn) => { WriteCursor::PersistentCursor(txn.write_cursor(db)) }
-            _ => unreachable!()
+    pub fn write_cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> WriteCursor<'txn, 'db> where 'db: 'txn {
+        match self.txn.txn {
+            BackendTransactionKind::Write(ref txn) => WriteCursor { raw: txn.write_cursor(db), _db: PhantomData },
+            BackendTransactionKind::Read(_) => unreachable!(),
         }
     }
 }
@@ -226,195 +422,120 @@ impl<'env> Deref for WriteTransaction<'env> {
     type Target = Transaction<'env>;
 
     fn deref(&self) -> &Transaction<'env> {
-        &self.0
+        &self.txn
     }
 }
 
-pub enum Cursor<'txn, 'db> {
-    VolatileCursor(volatile::VolatileCursor<'txn, 'db>),
-    PersistentCursor(lmdb::LmdbCursor<'txn, 'db>),
+pub struct Cursor<'txn, 'db> {
+    raw: Box<dyn BackendCursor + 'txn>,
+    _db: PhantomData<&'db ()>,
 }
 
-pub enum WriteCursor<'txn, 'db> {
-    VolatileCursor(volatile::VolatileWriteCursor<'txn, 'db>),
-    PersistentCursor(lmdb::LmdbWriteCursor<'txn, 'db>),
+pub struct WriteCursor<'txn, 'db> {
+    raw: Box<dyn BackendWriteCursor + 'txn>,
+    _db: PhantomData<&'db ()>,
 }
 
-macro_rules! gen_cursor_match {
-    ($self: ident, $f: ident, $t: ident) => {
-        match $self {
-            $t::PersistentCursor(ref mut cursor) => {
-                cursor.$f()
-            },
-            $t::VolatileCursor(ref mut cursor) => {
-                cursor.$f()
-            },
-        }
-    };
-    ($self: ident, $f: ident, $k: expr, $t: ident) => {
-        match $self {
-            $t::PersistentCursor(ref mut cursor) => {
-                cursor.$f($k)
-            },
-            $t::VolatileCursor(ref mut cursor) => {
-                cursor.$f($k)
-            },
-        }
-    };
-    ($self: ident, $f: ident, $k: expr, $v: expr, $t: ident) => {
-        match $self {
-            $t::PersistentCursor(ref mut cursor) => {
-                cursor.$f($k, $v)
-            },
-            $t::VolatileCursor(ref mut cursor) => {
-                cursor.$f($k, $v)
-            },
-        }
-    };
+fn decode<T: FromDatabaseValue>(bytes: Vec<u8>) -> T {
+    FromDatabaseValue::copy_from_database(&bytes).unwrap()
 }
 
-impl<'txn, 'db> ReadCursor for Cursor<'txn, 'db> {
-    fn first<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, first, Cursor)
-    }
-
-    fn first_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-        gen_cursor_match!(self, first_duplicate, Cursor)
-    }
-
-    fn last<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, last, Cursor)
-    }
-
-    fn last_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-        gen_cursor_match!(self, last_duplicate, Cursor)
-    }
-
-    fn seek_key_value<K, V>(&mut self, key: &K, value: &V) -> bool where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        gen_cursor_match!(self, seek_key_value, key, value, Cursor)
-    }
-
-    fn seek_key_nearest_value<K, V>(&mut self, key: &K, value: &V) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + FromDatabaseValue {
-        gen_cursor_match!(self, seek_key_nearest_value, key, value, Cursor)
-    }
-
-    fn get_current<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, get_current, Cursor)
-    }
-
-    fn next<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, next, Cursor)
-    }
-
-    fn next_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, next_duplicate, Cursor)
-    }
-
-    fn next_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, next_no_duplicate, Cursor)
-    }
-
-    fn prev<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, prev, Cursor)
-    }
-
-    fn prev_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, prev_duplicate, Cursor)
-    }
-
-    fn prev_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, prev_no_duplicate, Cursor)
-    }
-
-    fn seek_key<K, V>(&mut self, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        gen_cursor_match!(self, seek_key, key, Cursor)
-    }
-
-    fn seek_key_both<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, seek_key_both, key, Cursor)
-    }
-
-    fn seek_range_key<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, seek_range_key, key, Cursor)
-    }
-
-    fn count_duplicates(&mut self) -> usize {
-        gen_cursor_match!(self, count_duplicates, Cursor)
-    }
+fn decode_entry<K: FromDatabaseValue, V: FromDatabaseValue>(entry: Option<(Vec<u8>, Vec<u8>)>) -> Option<(K, V)> {
+    entry.map(|(key, value)| (decode(key), decode(value)))
 }
 
-impl<'txn, 'db> ReadCursor for WriteCursor<'txn, 'db> {
-    fn first<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, first, WriteCursor)
-    }
-
-    fn first_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-        gen_cursor_match!(self, first_duplicate, WriteCursor)
-    }
-
-    fn last<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, last, WriteCursor)
-    }
-
-    fn last_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-        gen_cursor_match!(self, last_duplicate, WriteCursor)
-    }
-
-    fn seek_key_value<K, V>(&mut self, key: &K, value: &V) -> bool where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        gen_cursor_match!(self, seek_key_value, key, value, WriteCursor)
-    }
-
-    fn seek_key_nearest_value<K, V>(&mut self, key: &K, value: &V) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + FromDatabaseValue {
-        gen_cursor_match!(self, seek_key_nearest_value, key, value, WriteCursor)
-    }
-
-    fn get_current<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, get_current, WriteCursor)
-    }
-
-    fn next<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, next, WriteCursor)
-    }
-
-    fn next_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, next_duplicate, WriteCursor)
-    }
-
-    fn next_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, next_no_duplicate, WriteCursor)
-    }
-
-    fn prev<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, prev, WriteCursor)
-    }
-
-    fn prev_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, prev_duplicate, WriteCursor)
-    }
-
-    fn prev_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, prev_no_duplicate, WriteCursor)
-    }
-
-    fn seek_key<K, V>(&mut self, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        gen_cursor_match!(self, seek_key, key, WriteCursor)
-    }
+/// Implements the typed `ReadCursor` on top of the raw cursor of the backend.
+macro_rules! impl_read_cursor {
+    ($t: ident) => {
+        impl<'txn, 'db> ReadCursor for $t<'txn, 'db> {
+            fn first<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.first())
+            }
+
+            fn first_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
+                self.raw.first_duplicate().map(decode)
+            }
+
+            fn last<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.last())
+            }
+
+            fn last_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
+                self.raw.last_duplicate().map(decode)
+            }
+
+            fn seek_key_value<K, V>(&mut self, key: &K, value: &V) -> bool where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
+                self.raw.seek_key_value(key.as_database_bytes().as_ref(), value.as_database_bytes().as_ref())
+            }
+
+            fn seek_key_nearest_value<K, V>(&mut self, key: &K, value: &V) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + FromDatabaseValue {
+                self.raw.seek_key_nearest_value(key.as_database_bytes().as_ref(), value.as_database_bytes().as_ref()).map(decode)
+            }
+
+            fn get_current<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.get_current())
+            }
+
+            fn next<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.next())
+            }
+
+            fn next_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.next_duplicate())
+            }
+
+            fn next_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.next_no_duplicate())
+            }
+
+            fn prev<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.prev())
+            }
+
+            fn prev_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.prev_duplicate())
+            }
+
+            fn prev_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.prev_no_duplicate())
+            }
+
+            fn seek_key<K, V>(&mut self, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
+                self.raw.seek_key(key.as_database_bytes().as_ref()).map(decode)
+            }
+
+            fn seek_key_both<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.seek_key_both(key.as_database_bytes().as_ref()))
+            }
+
+            fn seek_range_key<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.seek_range(key.as_database_bytes().as_ref()))
+            }
+
+            fn seek_range<Q, K, V>(&mut self, key: &Q) -> Option<(K, V)> where Q: AsDatabaseBytes + ?Sized, K: FromDatabaseValue, V: FromDatabaseValue {
+                decode_entry(self.raw.seek_range(key.as_database_bytes().as_ref()))
+            }
+
+            fn count_duplicates(&mut self) -> usize {
+                self.raw.count_duplicates()
+            }
+        }
+    };
+}
 
-    fn seek_key_both<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, seek_key_both, key, WriteCursor)
-    }
+impl_read_cursor!(Cursor);
+impl_read_cursor!(WriteCursor);
 
-    fn seek_range_key<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        gen_cursor_match!(self, seek_range_key, key, WriteCursor)
+impl<'txn, 'db> WriteCursorTrait for WriteCursor<'txn, 'db> {
+    fn put_current<V>(&mut self, value: &V) where V: AsDatabaseBytes + ?Sized {
+        self.raw.put_current(value.as_database_bytes().as_ref())
     }
 
-    fn count_duplicates(&mut self) -> usize {
-        gen_cursor_match!(self, count_duplicates, WriteCursor)
+    fn append<K, V>(&mut self, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
+        self.raw.append(key.as_database_bytes().as_ref(), value.as_database_bytes().as_ref())
     }
-}
 
-impl<'txn, 'db> WriteCursorTrait for WriteCursor<'txn, 'db> {
-    fn remove(&mut self) {
-        gen_cursor_match!(self, remove, WriteCursor)
+    fn delete_current(&mut self) {
+        self.raw.delete_current()
     }
 }
@@ -1,6 +1,10 @@
+use std::any::Any;
 use std::cmp;
+use std::cmp::Ordering;
 use std::fmt;
 use std::fs;
+use std::path::Path;
+use std::sync::Arc;
 
 use fs2;
 use lmdb_zero;
@@ -9,27 +13,158 @@ use lmdb_zero::traits::LmdbResultExt;
 use parking_lot;
 use rand::{Rng, thread_rng};
 
-use crate::cursor::{ReadCursor, WriteCursor as WriteCursorTrait, RawReadCursor};
+use crate::backend::{Backend, BackendCursor, BackendTransaction, BackendWriteCursor, BackendWriteTransaction, compare_keys};
+use crate::encryption::{Cipher, EncryptionError};
 
 use super::*;
 
 #[derive(Debug)]
 pub struct LmdbEnvironment {
-    env: lmdb_zero::Environment,
+    env: Arc<lmdb_zero::Environment>,
     creation_gate: parking_lot::RwLock<()>,
+    // Held by backups instead of the creation gate, so that a running backup only holds up
+    // resizes and not every new transaction.
+    backup_gate: parking_lot::RwLock<()>,
+    cipher: Option<Cipher>,
+    read_only: bool,
+    // Needed to reopen the environment after compacting it.
+    max_dbs: u32,
+    max_readers: Option<u32>,
+    flags: open::Flags,
+}
+
+/// Opens an `LmdbEnvironment` with LMDB's tuning options. Without any options, LMDB's defaults
+/// apply, i.e. each commit is synced to disk.
+#[derive(Clone, Debug)]
+pub struct LmdbEnvironmentBuilder {
+    path: String,
+    size: usize,
+    max_dbs: u32,
+    max_readers: Option<u32>,
+    flags: open::Flags,
+}
+
+impl LmdbEnvironmentBuilder {
+    pub fn new(path: &str) -> Self {
+        LmdbEnvironmentBuilder {
+            path: path.to_string(),
+            size: 0,
+            max_dbs: 1,
+            max_readers: None,
+            flags: open::Flags::empty(),
+        }
+    }
+
+    /// Minimum size of the memory map, which limits the size of the database.
+    pub fn with_size(&mut self, size: usize) -> &mut Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_max_dbs(&mut self, max_dbs: u32) -> &mut Self {
+        self.max_dbs = max_dbs;
+        self
+    }
+
+    /// Maximum number of concurrent read transactions. LMDB's default is 126.
+    pub fn with_max_readers(&mut self, max_readers: u32) -> &mut Self {
+        self.max_readers = Some(max_readers);
+        self
+    }
+
+    /// Don't sync to disk on commit (`MDB_NOSYNC`). A crash may undo the last commits or, with
+    /// `with_write_map`, corrupt the database.
+    pub fn with_no_sync(&mut self, no_sync: bool) -> &mut Self {
+        self.with_flag(open::NOSYNC, no_sync)
+    }
+
+    /// Don't sync the meta page on commit (`MDB_NOMETASYNC`). A crash may undo the last commit.
+    pub fn with_no_meta_sync(&mut self, no_meta_sync: bool) -> &mut Self {
+        self.with_flag(open::NOMETASYNC, no_meta_sync)
+    }
+
+    /// Write through a writable memory map (`MDB_WRITEMAP`). Faster, but stray writes to the map
+    /// can corrupt the database.
+    pub fn with_write_map(&mut self, write_map: bool) -> &mut Self {
+        self.with_flag(open::WRITEMAP, write_map)
+    }
+
+    /// Turn off read-ahead (`MDB_NORDAHEAD`), which helps random reads of databases larger than
+    /// the RAM.
+    pub fn with_no_read_ahead(&mut self, no_read_ahead: bool) -> &mut Self {
+        self.with_flag(open::NORDAHEAD, no_read_ahead)
+    }
+
+    /// Tie read transactions to their transaction object instead of their thread (`MDB_NOTLS`),
+    /// so a thread can hold several of them.
+    pub fn with_no_tls(&mut self, no_tls: bool) -> &mut Self {
+        self.with_flag(open::NOTLS, no_tls)
+    }
+
+    fn with_flag(&mut self, flag: open::Flags, enabled: bool) -> &mut Self {
+        if enabled {
+            self.flags.insert(flag);
+        } else {
+            self.flags.remove(flag);
+        }
+        self
+    }
+
+    pub fn build(&self) -> Result<Environment, lmdb_zero::Error> {
+        Ok(Environment::from_backend(self.open(None)?))
+    }
+
+    /// Like `build`, but encrypts the values with a key derived from `passphrase`, see `Cipher`.
+    /// Keys are stored unencrypted, since LMDB needs to compare them. The duplicates of a key
+    /// are sorted by their ciphertexts, so their order is arbitrary but fixed and
+    /// `seek_key_nearest_value` doesn't find the nearest value.
+    pub fn build_encrypted(&self, passphrase: &[u8]) -> Result<Environment, EncryptionError> {
+        let cipher = Cipher::open(&self.path, passphrase)?;
+        Ok(Environment::from_backend(self.open(Some(cipher)).map_err(EncryptionError::LmdbError)?))
+    }
+
+    /// Opens the existing environment read-only, e.g. to serve queries from the database of a
+    /// node running in another process. Read transactions always see the latest data the other
+    /// process committed. Values are decrypted with `passphrase` if the database is encrypted.
+    /// Write transactions panic.
+    ///
+    /// Of the sync and map options, only `with_no_read_ahead` and `with_no_tls` apply, since
+    /// the other process writes the database.
+    pub fn build_read_only(&self, passphrase: Option<&[u8]>) -> Result<Environment, EncryptionError> {
+        let cipher = match passphrase {
+            Some(passphrase) if Cipher::is_encrypted(&self.path) => Some(Cipher::open(&self.path, passphrase)?),
+            Some(_) => return Err(EncryptionError::Unencrypted),
+            None => None,
+        };
+        let flags = open::RDONLY | (self.flags & (open::NORDAHEAD | open::NOTLS));
+        Ok(Environment::from_backend(LmdbEnvironment::new_lmdb_environment(&self.path, 0, self.max_dbs, self.max_readers, flags, cipher)
+            .map_err(EncryptionError::LmdbError)?))
+    }
+
+    fn open(&self, cipher: Option<Cipher>) -> Result<LmdbEnvironment, lmdb_zero::Error> {
+        LmdbEnvironment::new_lmdb_environment(&self.path, self.size, self.max_dbs, self.max_readers, self.flags, cipher)
+    }
 }
 
 impl LmdbEnvironment {
-    #[allow(clippy::new_ret_no_self)]
-    pub fn new(path: &str, size: usize, max_dbs: u32, flags: open::Flags) -> Result<Environment, lmdb_zero::Error> {
-        Ok(Environment::Persistent(LmdbEnvironment::new_lmdb_environment(path, size, max_dbs, flags)?))
+    const DATA_FILE_NAME: &'static str = "data.mdb";
+
+    /// Whether the database at `path` was created by `LmdbEnvironmentBuilder::build_encrypted`.
+    pub fn is_encrypted(path: &str) -> bool {
+        Cipher::is_encrypted(path)
     }
 
-    pub(in super) fn new_lmdb_environment(path: &str, size: usize, max_dbs: u32, flags: open::Flags) -> Result<Self, lmdb_zero::Error> {
-        fs::create_dir_all(path).unwrap();
+    pub(in super) fn new_lmdb_environment(path: &str, size: usize, max_dbs: u32, max_readers: Option<u32>, flags: open::Flags, cipher: Option<Cipher>) -> Result<Self, lmdb_zero::Error> {
+        let read_only = flags.contains(open::RDONLY);
+        if !read_only {
+            fs::create_dir_all(path).unwrap();
+        }
 
         let mut env = lmdb_zero::EnvBuilder::new()?;
         env.set_maxdbs(max_dbs)?;
+        if let Some(max_readers) = max_readers {
+            env.set_maxreaders(max_readers)?;
+        }
         let env = unsafe {
             env.open(path, flags, 0o600)?
         };
@@ -43,8 +178,9 @@ impl LmdbEnvironment {
             info!("LMDB memory map size: {}", cur_mapsize);
         }
 
-        let lmdb = LmdbEnvironment { env, creation_gate: parking_lot::RwLock::new(()) };
-        if lmdb.need_resize(0) {
+        let lmdb = LmdbEnvironment { env: Arc::new(env), creation_gate: parking_lot::RwLock::new(()), backup_gate: parking_lot::RwLock::new(()), cipher, read_only, max_dbs, max_readers, flags };
+        // The map of a read-only environment grows with the writer's, see `LmdbReadTransaction::new`.
+        if !read_only && lmdb.need_resize(0) {
             info!("LMDB memory needs to be resized.");
             lmdb.do_resize(0);
         }
@@ -52,40 +188,58 @@ impl LmdbEnvironment {
         Ok(lmdb)
     }
 
-    pub(in super) fn open_database(&self, name: String, flags: DatabaseFlags) -> LmdbDatabase {
-        // This is an implicit transaction, so take the lock first.
-        let _guard = self.creation_gate.read();
-        let mut db_flags = lmdb_zero::db::CREATE;
-
-        // Translate flags.
-        if flags.contains(DatabaseFlags::DUPLICATE_KEYS) {
-            db_flags.insert(lmdb_zero::db::DUPSORT);
-
-            if flags.contains(DatabaseFlags::DUP_FIXED_SIZE_VALUES) {
-                db_flags.insert(lmdb_zero::db::DUPFIXED);
-            }
-
-            if flags.contains(DatabaseFlags::DUP_UINT_VALUES) {
-                db_flags.insert(lmdb_zero::db::INTEGERDUP);
-            }
+    /// Replaces the environment by a compacted copy, which returns the pages of the free list to
+    /// the file system. The environment is closed while the copy is swapped in, so this consumes
+    /// it and returns the reopened environment.
+    ///
+    /// The copy is written to `<path>.compact` and its data file is renamed over the original one,
+    /// so a crash leaves either the old or the compacted environment.
+    fn compact_environment(self) -> io::Result<Self> {
+        if self.read_only {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Can't compact a read-only database"));
         }
-        if flags.contains(DatabaseFlags::UINT_KEYS) {
-            db_flags.insert(lmdb_zero::db::INTEGERKEY);
+        if self.flags.contains(open::NOSUBDIR) {
+            return Err(io::Error::new(io::ErrorKind::Other, "Only databases in their own directory can be compacted"));
         }
 
-        LmdbDatabase { db: lmdb_zero::Database::open(&self.env, Some(&name), &lmdb_zero::DatabaseOptions::new(db_flags)).unwrap() }
-    }
-
-    pub(in super) fn drop_database(self) -> io::Result<()> {
-        fs::remove_dir_all(self.path().as_ref())
+        let path = self.path().into_owned();
+        let tmp_path = format!("{}.compact", path.trim_end_matches('/'));
+        if Path::new(&tmp_path).exists() {
+            // Left over from an interrupted compaction.
+            fs::remove_dir_all(&tmp_path)?;
+        }
+        fs::create_dir_all(&tmp_path)?;
+
+        let size = self.env.info().map_err(to_io_error)?.mapsize;
+        let data_path = Path::new(&path).join(Self::DATA_FILE_NAME);
+        let old_size = fs::metadata(&data_path)?.len();
+        self.env.copy(&tmp_path, lmdb_zero::copy::COMPACT).map_err(to_io_error)?;
+
+        // All databases borrow the `Environment`, so this is the last reference.
+        let LmdbEnvironment { env, cipher, max_dbs, max_readers, flags, .. } = self;
+        drop(env);
+        fs::rename(Path::new(&tmp_path).join(Self::DATA_FILE_NAME), &data_path)?;
+        fs::File::open(&path)?.sync_all()?;
+        fs::remove_dir_all(&tmp_path)?;
+
+        let new_size = fs::metadata(&data_path)?.len();
+        info!("Compacted LMDB database from {} to {} bytes", old_size, new_size);
+        LmdbEnvironment::new_lmdb_environment(&path, size, max_dbs, max_readers, flags, cipher).map_err(to_io_error)
     }
 
     fn path(&self) -> Cow<str> {
         self.env.path().unwrap().to_string_lossy()
     }
 
+    /// The cipher for the values of `db`, if they are encrypted.
+    fn cipher<'a>(&'a self, db: &'a LmdbDatabase) -> Option<DatabaseCipher<'a>> {
+        Some(DatabaseCipher { cipher: self.cipher.as_ref()?, db })
+    }
+
     pub fn do_resize(&self, increase_size: usize) {
-        // Lock creation of new transactions until resize is finished.
+        // Wait for running backups before locking creation of new transactions until resize is
+        // finished.
+        let _backup_guard = self.backup_gate.write();
         let _guard = self.creation_gate.write();
         let add_size: usize = cmp::max(1 << 30, increase_size);
 
@@ -149,38 +303,221 @@ impl LmdbEnvironment {
     }
 }
 
+impl Backend for LmdbEnvironment {
+    fn name(&self) -> &'static str {
+        "lmdb"
+    }
+
+    fn open_database(&self, name: &str, flags: DatabaseFlags) -> Box<dyn Any + Send + Sync> {
+        // This is an implicit transaction, so take the lock first.
+        let _guard = self.creation_gate.read();
+        // Read-only environments can only open databases that exist already.
+        let mut db_flags = if self.read_only { lmdb_zero::db::Flags::empty() } else { lmdb_zero::db::CREATE };
+
+        // Translate flags.
+        if flags.contains(DatabaseFlags::DUPLICATE_KEYS) {
+            db_flags.insert(lmdb_zero::db::DUPSORT);
+
+            if flags.contains(DatabaseFlags::DUP_FIXED_SIZE_VALUES) {
+                db_flags.insert(lmdb_zero::db::DUPFIXED);
+            }
+
+            // Encrypted values aren't integers anymore.
+            if flags.contains(DatabaseFlags::DUP_UINT_VALUES) && self.cipher.is_none() {
+                db_flags.insert(lmdb_zero::db::INTEGERDUP);
+            }
+        }
+        if flags.has_uint_keys() {
+            db_flags.insert(lmdb_zero::db::INTEGERKEY);
+        }
+
+        let db = lmdb_zero::Database::open(Arc::clone(&self.env), Some(name), &lmdb_zero::DatabaseOptions::new(db_flags))
+            .unwrap_or_else(|e| panic!("Failed to open database {}: {}", name, e));
+        Box::new(LmdbDatabase { db, name: name.to_string(), duplicate_keys: flags.contains(DatabaseFlags::DUPLICATE_KEYS) })
+    }
+
+    fn stats(&self) -> EnvironmentStats {
+        EnvironmentStats::new(self.env.stat().unwrap(), self.env.info().unwrap())
+    }
+
+    fn database_stats(&self, db: &Database) -> DatabaseStats {
+        // This is an implicit transaction, so take the lock first.
+        let _guard = self.creation_gate.read();
+        let txn = lmdb_zero::ReadTransaction::new(&*self.env).unwrap();
+        DatabaseStats::from(txn.db_stat(&db.handle::<LmdbDatabase>().db).unwrap())
+    }
+
+    /// Flushes the data and meta pages to disk, even if the environment was opened with
+    /// `NOSYNC` or `NOMETASYNC`. A read-only environment has nothing to flush.
+    fn sync(&self) -> io::Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        self.env.sync(true).map_err(to_io_error)
+    }
+
+    fn read_transaction<'env>(&'env self) -> Box<dyn BackendTransaction + 'env> {
+        Box::new(LmdbReadTransaction::new(self))
+    }
+
+    fn write_transaction<'env>(&'env self) -> Box<dyn BackendWriteTransaction + 'env> {
+        Box::new(LmdbWriteTransaction::new(self))
+    }
+
+    fn drop_database(self: Box<Self>) -> io::Result<()> {
+        fs::remove_dir_all(self.path().as_ref())
+    }
+
+    fn compact(self: Box<Self>) -> io::Result<Box<dyn Backend>> {
+        Ok(Box::new(self.compact_environment()?))
+    }
+
+    /// Writes a compacted copy of the environment to the directory at `path`, which must not
+    /// contain a database yet. This runs in its own read transaction, so writes can continue
+    /// while the copy is made.
+    /// The copy is an implicit read transaction. It only holds off resizes, other transactions
+    /// can be created while it runs.
+    fn backup_to(&self, path: &str) -> io::Result<()> {
+        fs::create_dir_all(path)?;
+        {
+            let _guard = self.backup_gate.read();
+            self.env.copy(path, lmdb_zero::copy::COMPACT).map_err(to_io_error)?;
+        }
+        if self.cipher.is_some() {
+            // The backup can only be decrypted with the salt in the salt file.
+            let salt_file = Path::new(self.path().as_ref()).join(Cipher::SALT_FILE_NAME);
+            fs::copy(salt_file, Path::new(path).join(Cipher::SALT_FILE_NAME))?;
+        }
+        info!("Backed up LMDB database to {}", path);
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+}
+
+fn to_io_error(e: lmdb_zero::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+/// The handle of an LMDB database. It keeps the environment alive, so that it doesn't borrow it.
 #[derive(Debug)]
-pub struct LmdbDatabase<'env> {
-    db: lmdb_zero::Database<'env>,
+struct LmdbDatabase {
+    db: lmdb_zero::Database<'static>,
+    /// Authenticated with every encrypted value, see `Cipher::associated_data`.
+    name: String,
+    duplicate_keys: bool,
+}
+
+/// The cipher of an environment, bound to one of its databases.
+#[derive(Clone, Copy)]
+struct DatabaseCipher<'a> {
+    cipher: &'a Cipher,
+    db: &'a LmdbDatabase,
+}
+
+impl<'a> DatabaseCipher<'a> {
+    /// Values of databases with duplicate keys are encrypted deterministically, so that they can
+    /// be found by their value.
+    fn encrypt(&self, key: &[u8], value: &[u8]) -> Vec<u8> {
+        let aad = Cipher::associated_data(&self.db.name, key);
+        if self.db.duplicate_keys {
+            self.cipher.encrypt_deterministic(&aad, value)
+        } else {
+            self.cipher.encrypt(&aad, value)
+        }
+    }
+
+    /// Values that fail to decrypt are logged and treated as missing.
+    fn decrypt(&self, key: &[u8], bytes: &[u8]) -> Option<Vec<u8>> {
+        let value = self.cipher.decrypt(&Cipher::associated_data(&self.db.name, key), bytes);
+        if value.is_none() {
+            error!("Failed to decrypt value of key {:?} in database {}", key, self.db.name);
+        }
+        value
+    }
+}
+
+/// Encrypts `value` if the values of the database are encrypted.
+fn encode_value<'a>(cipher: Option<DatabaseCipher>, key: &[u8], value: &'a [u8]) -> Cow<'a, [u8]> {
+    match cipher {
+        Some(cipher) => Cow::Owned(cipher.encrypt(key, value)),
+        None => Cow::Borrowed(value),
+    }
 }
 
-pub struct LmdbReadTransaction<'env> {
+/// Copies a value read from a database with the given cipher, decrypting it if necessary.
+fn decode_value(cipher: Option<DatabaseCipher>, key: &[u8], bytes: &[u8]) -> Option<Vec<u8>> {
+    match cipher {
+        Some(cipher) => cipher.decrypt(key, bytes),
+        None => Some(bytes.to_vec()),
+    }
+}
+
+fn decode_entry(cipher: Option<DatabaseCipher>, entry: Option<(&[u8], &[u8])>) -> Option<(Vec<u8>, Vec<u8>)> {
+    let (key, value) = entry?;
+    Some((key.to_vec(), decode_value(cipher, key, value)?))
+}
+
+/// Returns the bytes of a value read from a database with the given cipher. Unencrypted values
+/// are borrowed from the memory map.
+///
+/// The caller must make sure that the memory map isn't written to while the bytes are borrowed.
+unsafe fn borrow_value<'txn>(cipher: Option<DatabaseCipher>, key: &[u8], bytes: &[u8]) -> Option<Cow<'txn, [u8]>> {
+    match cipher {
+        Some(cipher) => cipher.decrypt(key, bytes).map(Cow::Owned),
+        None => Some(Cow::Borrowed(&*(bytes as *const [u8]))),
+    }
+}
+
+struct LmdbReadTransaction<'env> {
     txn: lmdb_zero::ReadTransaction<'env>,
+    env: &'env LmdbEnvironment,
     #[allow(dead_code)]
     guard: parking_lot::RwLockReadGuard<'env, ()>,
 }
 
 impl<'env> LmdbReadTransaction<'env> {
-    pub(in super) fn new(env: &'env LmdbEnvironment) -> Self {
+    fn new(env: &'env LmdbEnvironment) -> Self {
         // This is an implicit transaction, so take the lock first.
         let guard = env.creation_gate.read();
-        LmdbReadTransaction { txn: lmdb_zero::ReadTransaction::new(&env.env).unwrap(), guard }
+        match lmdb_zero::ReadTransaction::new(&*env.env) {
+            Ok(txn) => LmdbReadTransaction { txn, env, guard },
+            Err(lmdb_zero::Error::Code(lmdb_zero::error::MAP_RESIZED)) => {
+                // Another process grew the map, so adopt its size before trying again.
+                drop(guard);
+                {
+                    let _backup_guard = env.backup_gate.write();
+                    let _guard = env.creation_gate.write();
+                    unsafe { env.env.set_mapsize(0).unwrap() };
+                }
+                LmdbReadTransaction::new(env)
+            },
+            Err(e) => panic!("Failed to begin read transaction: {}", e),
+        }
     }
+}
 
-    pub(in super) fn get<K, V>(&self, db: &LmdbDatabase<'env>, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
+impl<'env> BackendTransaction for LmdbReadTransaction<'env> {
+    fn get(&self, db: &Database, key: &[u8]) -> Option<Cow<[u8]>> {
+        let db = db.handle::<LmdbDatabase>();
         let access = self.txn.access();
-        let result: Option<&[u8]> = access.get(&db.db, AsDatabaseBytes::as_database_bytes(key).as_ref()).to_opt().unwrap();
-        Some(FromDatabaseValue::copy_from_database(result?).unwrap())
+        let result: Option<&[u8]> = access.get(&db.db, key).to_opt().unwrap();
+        // The pages of a read transaction stay valid until it ends, even if they are written to
+        // by another transaction.
+        unsafe { borrow_value(self.env.cipher(db), key, result?) }
     }
 
-    pub(in super) fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> LmdbCursor<'txn, 'db> {
-        let cursor = self.txn.cursor(&db.persistent().unwrap().db).unwrap();
-        LmdbCursor {
-            raw: RawLmdbCursor {
-                cursor,
-            },
+    fn cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendCursor + 'txn> {
+        let db = db.handle::<LmdbDatabase>();
+        Box::new(LmdbCursor {
+            cursor: self.txn.cursor(&db.db).unwrap(),
             txn: &self.txn,
-        }
+            write_txn: None,
+            cipher: self.env.cipher(db),
+            duplicate_keys: db.duplicate_keys,
+        })
     }
 }
 
@@ -190,77 +527,147 @@ impl<'env> fmt::Debug for LmdbReadTransaction<'env> {
     }
 }
 
-pub struct LmdbWriteTransaction<'env> {
+struct LmdbWriteTransaction<'env> {
     txn: lmdb_zero::WriteTransaction<'env>,
+    env: &'env LmdbEnvironment,
     #[allow(dead_code)]
     guard: parking_lot::RwLockReadGuard<'env, ()>,
 }
 
 impl<'env> LmdbWriteTransaction<'env> {
-    pub(in super) fn new(env: &'env LmdbEnvironment) -> Self {
+    fn new(env: &'env LmdbEnvironment) -> Self {
+        assert!(!env.read_only, "Write transaction in read-only environment");
         // Check for enough space before every write transaction.
         if env.need_resize(0) {
             env.do_resize(0);
         }
         let guard = env.creation_gate.read();
-        LmdbWriteTransaction { txn: lmdb_zero::WriteTransaction::new(&env.env).unwrap(), guard }
+        LmdbWriteTransaction { txn: lmdb_zero::WriteTransaction::new(&*env.env).unwrap(), env, guard }
+    }
+
+    fn lmdb_cursor<'txn>(&'txn self, db: &'txn Database) -> LmdbCursor<'txn> {
+        let lmdb_db = db.handle::<LmdbDatabase>();
+        LmdbCursor {
+            cursor: self.txn.cursor(&lmdb_db.db).unwrap(),
+            txn: &self.txn,
+            write_txn: Some(&self.txn),
+            cipher: self.env.cipher(lmdb_db),
+            duplicate_keys: lmdb_db.duplicate_keys,
+        }
     }
+}
 
-    pub(in super) fn get<K, V>(&self, db: &LmdbDatabase<'env>, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
+impl<'env> BackendTransaction for LmdbWriteTransaction<'env> {
+    /// The value is copied, since a write cursor of this transaction could overwrite it.
+    fn get(&self, db: &Database, key: &[u8]) -> Option<Cow<[u8]>> {
+        let db = db.handle::<LmdbDatabase>();
         let access = self.txn.access();
-        let result: Option<&[u8]> = access.get(&db.db, AsDatabaseBytes::as_database_bytes(key).as_ref()).to_opt().unwrap();
-        Some(FromDatabaseValue::copy_from_database(result?).unwrap())
+        let result: Option<&[u8]> = access.get(&db.db, key).to_opt().unwrap();
+        decode_value(self.env.cipher(db), key, result?).map(Cow::Owned)
     }
 
-    pub(in super) fn put_reserve<K, V>(&mut self, db: &LmdbDatabase, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: IntoDatabaseValue + ?Sized {
-        let key = AsDatabaseBytes::as_database_bytes(key);
-        let value_size = IntoDatabaseValue::database_byte_size(value);
+    fn cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendCursor + 'txn> {
+        Box::new(self.lmdb_cursor(db))
+    }
+}
+
+impl<'env> BackendWriteTransaction for LmdbWriteTransaction<'env> {
+    fn put(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        let db = db.handle::<LmdbDatabase>();
+        let value = encode_value(self.env.cipher(db), key, value);
+        let mut access = self.txn.access();
+        access.put(&db.db, key, value.as_ref(), lmdb_zero::put::Flags::empty()).unwrap();
+    }
+
+    fn put_reserve(&mut self, db: &Database, key: &[u8], size: usize, write: &mut dyn FnMut(&mut [u8])) {
+        let db = db.handle::<LmdbDatabase>();
+        if let Some(cipher) = self.env.cipher(db) {
+            // The value needs to be serialized before it can be encrypted.
+            let mut bytes = vec![0u8; size];
+            write(&mut bytes);
+            let mut access = self.txn.access();
+            access.put(&db.db, key, cipher.encrypt(key, &bytes).as_slice(), lmdb_zero::put::Flags::empty()).unwrap();
+            return;
+        }
         unsafe {
             let mut access = self.txn.access();
-            let mut bytes: &mut [u8] = access.put_reserve_unsized(&db.db, key.as_ref(), value_size, lmdb_zero::put::Flags::empty()).unwrap();
-            IntoDatabaseValue::copy_into_database(value, &mut bytes);
+            let bytes: &mut [u8] = access.put_reserve_unsized(&db.db, key, size, lmdb_zero::put::Flags::empty()).unwrap();
+            write(bytes);
         }
     }
 
-    pub(in super) fn put<K, V>(&mut self, db: &LmdbDatabase, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        let key = AsDatabaseBytes::as_database_bytes(key);
-        let value = AsDatabaseBytes::as_database_bytes(value);
+    fn put_append(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        let db = db.handle::<LmdbDatabase>();
+        let value = encode_value(self.env.cipher(db), key, value);
         let mut access = self.txn.access();
-        access.put(&db.db, key.as_ref(), value.as_ref(), lmdb_zero::put::Flags::empty()).unwrap();
+        access.put(&db.db, key, value.as_ref(), lmdb_zero::put::APPEND).unwrap();
     }
 
-    pub(in super) fn remove<K>(&mut self, db: &LmdbDatabase, key: &K) where K: AsDatabaseBytes + ?Sized {
+    fn last_key(&self, db: &Database) -> Option<Vec<u8>> {
+        let db = db.handle::<LmdbDatabase>();
+        let access = self.txn.access();
+        let mut cursor = self.txn.cursor(&db.db).unwrap();
+        let result: Option<(&[u8], &[u8])> = cursor.last(&access).to_opt().unwrap();
+        result.map(|(key, _)| key.to_vec())
+    }
+
+    fn remove(&mut self, db: &Database, key: &[u8]) {
+        let db = db.handle::<LmdbDatabase>();
         let mut access = self.txn.access();
-        access.del_key(&db.db, AsDatabaseBytes::as_database_bytes(key).as_ref()).to_opt().unwrap();
+        access.del_key(&db.db, key).to_opt().unwrap();
     }
 
-    pub(in super) fn remove_item<K, V>(&mut self, db: &LmdbDatabase, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
+    fn remove_item(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        let db = db.handle::<LmdbDatabase>();
+        if !db.duplicate_keys {
+            // LMDB ignores the value, which can't be compared if it's encrypted anyway.
+            let mut access = self.txn.access();
+            access.del_key(&db.db, key).to_opt().unwrap();
+            return;
+        }
+        let value = encode_value(self.env.cipher(db), key, value);
         let mut access = self.txn.access();
-        access.del_item(&db.db, AsDatabaseBytes::as_database_bytes(key).as_ref(), AsDatabaseBytes::as_database_bytes(value).as_ref()).to_opt().unwrap();
+        access.del_item(&db.db, key, value.as_ref()).to_opt().unwrap();
     }
 
-    pub(in super) fn commit(self) {
-        self.txn.commit().unwrap();
+    fn clear_database(&mut self, db: &Database) {
+        let db = db.handle::<LmdbDatabase>();
+        let mut access = self.txn.access();
+        access.clear_db(&db.db).unwrap();
     }
 
-    pub(in super) fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> LmdbCursor<'txn, 'db> {
-        let cursor = self.txn.cursor(&db.persistent().unwrap().db).unwrap();
-        LmdbCursor {
-            raw: RawLmdbCursor {
-                cursor,
-            },
-            txn: &self.txn,
+    fn remove_range(&mut self, db: &Database, start: &[u8], end: &[u8]) {
+        let key_flags = db.flags();
+        let flags = if key_flags.contains(DatabaseFlags::DUPLICATE_KEYS) {
+            lmdb_zero::del::NODUPDATA
+        } else {
+            lmdb_zero::del::Flags::empty()
+        };
+        let db = db.handle::<LmdbDatabase>();
+        let mut cursor = self.txn.cursor(&db.db).unwrap();
+        let mut access = self.txn.access();
+        loop {
+            // Deleting moves the cursor, so seek to the first remaining key each time.
+            let in_range = {
+                let result: Option<(&[u8], &[u8])> = cursor.seek_range_k(&access, start).to_opt().unwrap();
+                match result {
+                    Some((key, _)) => compare_keys(key_flags, key, end) == Ordering::Less,
+                    None => false,
+                }
+            };
+            if !in_range {
+                break;
+            }
+            cursor.del(&mut access, flags).unwrap();
         }
     }
 
-    pub(in super) fn write_cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> LmdbWriteCursor<'txn, 'db> {
-        let cursor = self.txn.cursor(&db.persistent().unwrap().db).unwrap();
-        LmdbWriteCursor {
-            raw: RawLmdbCursor {
-                cursor,
-            },
-            txn: &self.txn,
-        }
+    fn commit(self: Box<Self>) {
+        self.txn.commit().unwrap();
+    }
+
+    fn write_cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendWriteCursor + 'txn> {
+        Box::new(self.lmdb_cursor(db))
     }
 }
 
@@ -270,107 +677,122 @@ impl<'env> fmt::Debug for LmdbWriteTransaction<'env> {
     }
 }
 
-pub struct RawLmdbCursor<'txn, 'db> {
-    cursor: lmdb_zero::Cursor<'txn, 'db>,
+/// A cursor of a read or a write transaction. LMDB needs an accessor of the transaction for
+/// every operation, so the cursor keeps a reference to it. Only cursors of write transactions can
+/// modify entries.
+struct LmdbCursor<'txn> {
+    cursor: lmdb_zero::Cursor<'txn, 'txn>,
+    txn: &'txn lmdb_zero::ConstTransaction<'txn>,
+    write_txn: Option<&'txn lmdb_zero::WriteTransaction<'txn>>,
+    cipher: Option<DatabaseCipher<'txn>>,
+    duplicate_keys: bool,
 }
 
-impl<'txn, 'db> RawReadCursor for RawLmdbCursor<'txn, 'db> {
-    fn first<K, V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        let result: Option<(&[u8], &[u8])> = self.cursor.first(&access).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+impl<'txn> LmdbCursor<'txn> {
+    fn write_txn(&self) -> &'txn lmdb_zero::WriteTransaction<'txn> {
+        self.write_txn.expect("Cursor of a read transaction")
     }
+}
+
+macro_rules! read_entry {
+    ($self: ident, $op: ident $(, $arg: expr)*) => {{
+        let access = $self.txn.access();
+        let result: Option<(&[u8], &[u8])> = $self.cursor.$op(&access $(, $arg)*).to_opt().unwrap();
+        decode_entry($self.cipher, result)
+    }};
+}
 
-    fn first_duplicate<V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(V)> where V: FromDatabaseValue {
-        let result: Option<&[u8]> = self.cursor.first_dup(&access).to_opt().unwrap();
-        Some(FromDatabaseValue::copy_from_database(result?).unwrap())
+/// Reads the value of `$key`, or of the key the cursor is positioned at afterwards.
+macro_rules! read_value {
+    ($self: ident, $op: ident, $key: expr $(, $arg: expr)*) => {{
+        let access = $self.txn.access();
+        let result: Option<&[u8]> = $self.cursor.$op(&access $(, $arg)*).to_opt().unwrap();
+        decode_value($self.cipher, $key, result?)
+    }};
+    ($self: ident, $op: ident) => {{
+        let access = $self.txn.access();
+        let result: Option<&[u8]> = $self.cursor.$op(&access).to_opt().unwrap();
+        let value = result?;
+        // The key is only needed to decrypt the value.
+        match $self.cipher {
+            Some(cipher) => {
+                let current: Option<(&[u8], &[u8])> = $self.cursor.get_current(&access).to_opt().unwrap();
+                cipher.decrypt(current?.0, value)
+            },
+            None => Some(value.to_vec()),
+        }
+    }};
+}
+
+impl<'txn> BackendCursor for LmdbCursor<'txn> {
+    fn first(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, first)
     }
 
-    fn last<K, V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        let result: Option<(&[u8], &[u8])> = self.cursor.last(&access).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn first_duplicate(&mut self) -> Option<Vec<u8>> {
+        read_value!(self, first_dup)
     }
 
-    fn last_duplicate<V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(V)> where V: FromDatabaseValue {
-        let result: Option<&[u8]> = self.cursor.last_dup(&access).to_opt().unwrap();
-        Some(FromDatabaseValue::copy_from_database(result?).unwrap())
+    fn last(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, last)
     }
 
-    fn seek_key_value<K, V>(&mut self, key: &K, value: &V) -> bool where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        let key = AsDatabaseBytes::as_database_bytes(key);
-        let value = AsDatabaseBytes::as_database_bytes(value);
-        let result = self.cursor.seek_kv(key.as_ref(), value.as_ref());
-        result.is_ok()
+    fn last_duplicate(&mut self) -> Option<Vec<u8>> {
+        read_value!(self, last_dup)
     }
 
-    fn seek_key_nearest_value<K, V>(&mut self, access: &lmdb_zero::ConstAccessor, key: &K, value: &V) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + FromDatabaseValue {
-        let key = AsDatabaseBytes::as_database_bytes(key);
-        let value = AsDatabaseBytes::as_database_bytes(value);
-        let result: Option<&[u8]> = self.cursor.seek_k_nearest_v(&access, key.as_ref(), value.as_ref()).to_opt().unwrap();
-        Some(FromDatabaseValue::copy_from_database(result?).unwrap())
+    fn seek_key_value(&mut self, key: &[u8], value: &[u8]) -> bool {
+        if self.cipher.is_some() && !self.duplicate_keys {
+            // Only values of databases with duplicate keys are encrypted deterministically.
+            return self.seek_key(key).map_or(false, |current| current.as_slice() == value);
+        }
+        let value = encode_value(self.cipher, key, value);
+        self.cursor.seek_kv(key, value.as_ref()).is_ok()
     }
 
-    fn get_current<K, V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        let result: Option<(&[u8], &[u8])> = self.cursor.get_current(&access).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn seek_key_nearest_value(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        let value = encode_value(self.cipher, key, value);
+        read_value!(self, seek_k_nearest_v, key, key, value.as_ref())
     }
 
-    fn next<K, V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        let result: Option<(&[u8], &[u8])> = self.cursor.next(&access).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn get_current(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, get_current)
     }
 
-    fn next_duplicate<K, V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        let result: Option<(&[u8], &[u8])> = self.cursor.next_dup(&access).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn next(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, next)
     }
 
-    fn next_no_duplicate<K, V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        let result: Option<(&[u8], &[u8])> = self.cursor.next_nodup(&access).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn next_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, next_dup)
     }
 
-    fn prev<K, V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        let result: Option<(&[u8], &[u8])> = self.cursor.prev(&access).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn next_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, next_nodup)
     }
 
-    fn prev_duplicate<K, V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        let result: Option<(&[u8], &[u8])> = self.cursor.prev_dup(&access).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn prev(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, prev)
     }
 
-    fn prev_no_duplicate<K, V>(&mut self, access: &lmdb_zero::ConstAccessor) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        let result: Option<(&[u8], &[u8])> = self.cursor.prev_nodup(&access).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn prev_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, prev_dup)
     }
 
-    fn seek_key<K, V>(&mut self, access: &lmdb_zero::ConstAccessor, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        let key = AsDatabaseBytes::as_database_bytes(key);
-        let result: Option<&[u8]> = self.cursor.seek_k(&access, key.as_ref()).to_opt().unwrap();
-        Some(FromDatabaseValue::copy_from_database(result?).unwrap())
+    fn prev_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, prev_nodup)
     }
 
-    fn seek_key_both<K, V>(&mut self, access: &lmdb_zero::ConstAccessor, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        let key = AsDatabaseBytes::as_database_bytes(key);
-        let result: Option<(&[u8], &[u8])> = self.cursor.seek_k_both(&access, key.as_ref()).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn seek_key(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        read_value!(self, seek_k, key, key)
     }
 
-    fn seek_range_key<K, V>(&mut self, access: &lmdb_zero::ConstAccessor, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        let key = AsDatabaseBytes::as_database_bytes(key);
-        let result: Option<(&[u8], &[u8])> = self.cursor.seek_range_k(&access, key.as_ref()).to_opt().unwrap();
-        let (key, value) = result?;
-        Some((FromDatabaseValue::copy_from_database(key).unwrap(), FromDatabaseValue::copy_from_database(value).unwrap()))
+    fn seek_key_both(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, seek_k_both, key)
+    }
+
+    fn seek_range(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        read_entry!(self, seek_range_k, key)
     }
 
     fn count_duplicates(&mut self) -> usize {
@@ -378,224 +800,240 @@ impl<'txn, 'db> RawReadCursor for RawLmdbCursor<'txn, 'db> {
     }
 }
 
-pub struct LmdbCursor<'txn, 'db> {
-    raw: RawLmdbCursor<'txn, 'db>,
-    txn: &'txn lmdb_zero::ConstTransaction<'txn>,
-}
-
-impl_read_cursor_from_raw!(LmdbCursor<'txn, 'db>, raw, txn);
-
-pub struct LmdbWriteCursor<'txn, 'db> {
-    raw: RawLmdbCursor<'txn, 'db>,
-    txn: &'txn lmdb_zero::WriteTransaction<'txn>,
-}
+impl<'txn> BackendWriteCursor for LmdbCursor<'txn> {
+    fn put_current(&mut self, value: &[u8]) {
+        let mut access = self.write_txn().access();
+        let key = {
+            let current: Option<(&[u8], &[u8])> = self.cursor.get_current(&access).to_opt().unwrap();
+            current.expect("Cursor is not positioned at an entry").0.to_vec()
+        };
+        let value = encode_value(self.cipher, &key, value);
+        if self.duplicate_keys {
+            // LMDB can only overwrite duplicates that sort to the same position, so the entry is
+            // replaced instead.
+            self.cursor.del(&mut access, lmdb_zero::del::Flags::empty()).unwrap();
+            self.cursor.put(&mut access, key.as_slice(), value.as_ref(), lmdb_zero::put::Flags::empty()).unwrap();
+        } else {
+            self.cursor.overwrite(&mut access, key.as_slice(), value.as_ref(), lmdb_zero::put::Flags::empty()).unwrap();
+        }
+    }
 
-impl_read_cursor_from_raw!(LmdbWriteCursor<'txn, 'db>, raw, txn);
+    fn append(&mut self, key: &[u8], value: &[u8]) {
+        let value = encode_value(self.cipher, key, value);
+        let mut access = self.write_txn().access();
+        self.cursor.put(&mut access, key, value.as_ref(), lmdb_zero::put::APPEND).unwrap();
+    }
 
-impl<'txn, 'db> WriteCursorTrait for LmdbWriteCursor<'txn, 'db> {
-    fn remove(&mut self) {
-        let mut access = self.txn.access();
-        self.raw.cursor.del(&mut access, lmdb_zero::del::Flags::empty()).unwrap();
+    fn delete_current(&mut self) {
+        let mut access = self.write_txn().access();
+        self.cursor.del(&mut access, lmdb_zero::del::Flags::empty()).unwrap();
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::cursor::ReadCursor;
+    use crate::verify::RawBytes;
+
     use super::*;
 
     #[test]
-    fn it_can_save_basic_objects() {
-        let env = LmdbEnvironment::new("./test", 0, 1, open::Flags::empty()).unwrap();
+    fn it_borrows_values_in_read_transactions() {
+        let env = LmdbEnvironmentBuilder::new("./test_cow").build().unwrap();
         {
             let db = env.open_database("test".to_string());
 
-            // Read non-existent value.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert!(tx.get::<str, String>(&db, "test").is_none());
-            }
-
-            // Read non-existent value.
             let mut tx = WriteTransaction::new(&env);
-            assert!(tx.get::<str, String>(&db, "test").is_none());
-
-            // Write and read value.
-            tx.put_reserve(&db, "test", "one");
-            assert_eq!(tx.get::<str, String>(&db, "test"), Some("one".to_string()));
-            // Overwrite and read value.
-            tx.put_reserve(&db, "test", "two");
-            assert_eq!(tx.get::<str, String>(&db, "test"), Some("two".to_string()));
+            tx.put(&db, "test", "one");
+            match tx.get_cow(&db, "test") {
+                Some(Cow::Owned(ref bytes)) => assert_eq!(bytes.as_slice(), b"one"),
+                value => panic!("Unexpected value: {:?}", value),
+            }
             tx.commit();
 
-            // Read value.
             let tx = ReadTransaction::new(&env);
-            assert_eq!(tx.get::<str, String>(&db, "test"), Some("two".to_string()));
-            tx.close();
-
-            // Remove value.
-            let mut tx = WriteTransaction::new(&env);
-            tx.remove(&db, "test");
-            assert!(tx.get::<str, String>(&db, "test").is_none());
-            tx.commit();
-
-            // Check removal.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert!(tx.get::<str, String>(&db, "test").is_none());
+            match tx.get_cow(&db, "test") {
+                Some(Cow::Borrowed(bytes)) => assert_eq!(bytes, b"one"),
+                value => panic!("Unexpected value: {:?}", value),
             }
+            assert_eq!(tx.get_ref::<str, str>(&db, "test"), Some(Cow::Borrowed("one")));
+            assert!(tx.get_cow(&db, "none").is_none());
+        }
+
+        env.drop_database().unwrap();
+    }
 
-            // Write and abort.
+    #[test]
+    fn it_opens_environments_with_the_builder() {
+        let env = LmdbEnvironmentBuilder::new("./test_builder")
+            .with_max_dbs(2)
+            .with_max_readers(8)
+            .with_no_sync(true)
+            .with_write_map(true)
+            .with_no_read_ahead(true)
+            .build()
+            .unwrap();
+        {
+            let db = env.open_database("test".to_string());
             let mut tx = WriteTransaction::new(&env);
-            tx.put_reserve(&db, "test", "one");
-            tx.abort();
+            tx.put(&db, "test", "one");
+            tx.commit();
 
-            // Check aborted transaction.
             let tx = ReadTransaction::new(&env);
-            assert!(tx.get::<str, String>(&db, "test").is_none());
+            assert_eq!(tx.get::<str, String>(&db, "test"), Some("one".to_string()));
         }
 
         env.drop_database().unwrap();
     }
 
     #[test]
-    fn isolation_test() {
-        let env = LmdbEnvironment::new("./test2", 0, 1, open::NOTLS).unwrap();
+    fn it_compacts_the_environment() {
+        let env = LmdbEnvironmentBuilder::new("./test_compact").build().unwrap();
         {
             let db = env.open_database("test".to_string());
+            let mut tx = WriteTransaction::new(&env);
+            for i in 0..1000u32 {
+                tx.put(&db, &i, &[0u8; 512][..]);
+            }
+            tx.commit();
+            let mut tx = WriteTransaction::new(&env);
+            for i in 1..1000u32 {
+                tx.remove(&db, &i);
+            }
+            tx.commit();
+        }
+        let size = fs::metadata("./test_compact/data.mdb").unwrap().len();
 
-            // Read non-existent value.
+        let env = env.compact().unwrap();
+        assert!(fs::metadata("./test_compact/data.mdb").unwrap().len() < size);
+        assert!(!Path::new("./test_compact.compact").exists());
+        {
+            let db = env.open_database("test".to_string());
             let tx = ReadTransaction::new(&env);
-            assert!(tx.get::<str, String>(&db, "test").is_none());
-
-            // WriteTransaction.
-            let mut txw = WriteTransaction::new(&env);
-            assert!(txw.get::<str, String>(&db, "test").is_none());
-            txw.put_reserve(&db, "test", "one");
-            assert_eq!(txw.get::<str, String>(&db, "test"), Some("one".to_string()));
-
-            // ReadTransaction should still have the old state.
-            assert!(tx.get::<str, String>(&db, "test").is_none());
-
-            // Commit WriteTransaction.
-            txw.commit();
-
-            // ReadTransaction should still have the old state.
-            assert!(tx.get::<str, String>(&db, "test").is_none());
-
-            // Have a new ReadTransaction read the new state.
-            let tx2 = ReadTransaction::new(&env);
-            assert_eq!(tx2.get::<str, String>(&db, "test"), Some("one".to_string()));
+            assert_eq!(tx.get::<u32, RawBytes>(&db, &0), Some(RawBytes(vec![0u8; 512])));
+            assert!(tx.get::<u32, RawBytes>(&db, &1).is_none());
         }
 
         env.drop_database().unwrap();
     }
 
     #[test]
-    fn duplicates_test() {
-        let env = LmdbEnvironment::new("./test3", 0, 1, open::NOTLS).unwrap();
+    fn backup_test() {
+        let env = LmdbEnvironmentBuilder::new("./test-backup").with_no_tls(true).build().unwrap();
         {
-            let db = env.open_database_with_flags("test".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_UINT_VALUES);
+            let db = env.open_database("test".to_string());
 
-            // Write one value.
             let mut txw = WriteTransaction::new(&env);
-            assert!(txw.get::<str, u32>(&db, "test").is_none());
-            txw.put::<str, u32>(&db, "test", &125);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
+            txw.put_reserve(&db, "test", "one");
             txw.commit();
 
-            // Have a new ReadTransaction read the new state.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert_eq!(tx.get::<str, u32>(&db, "test"), Some(125));
-            }
+            env.backup_to("./test-backup-copy").unwrap();
+
+            // Failures are returned.
+            assert!(env.backup_to("./test-backup/data.mdb/copy").is_err());
 
-            // Write a second smaller value.
+            // Later changes don't affect the backup.
             let mut txw = WriteTransaction::new(&env);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
-            txw.put::<str, u32>(&db, "test", &12);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(12));
+            txw.put_reserve(&db, "test", "two");
             txw.commit();
+        }
+        env.drop_database().unwrap();
 
-            // Have a new ReadTransaction read the smaller value.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert_eq!(tx.get::<str, u32>(&db, "test"), Some(12));
-            }
+        let backup = LmdbEnvironmentBuilder::new("./test-backup-copy").with_no_tls(true).build().unwrap();
+        {
+            let db = backup.open_database("test".to_string());
+            let tx = ReadTransaction::new(&backup);
+            assert_eq!(tx.get::<str, String>(&db, "test"), Some("one".to_string()));
+        }
+        backup.drop_database().unwrap();
+    }
+
+    #[test]
+    fn encryption_test() {
+        {
+            let env = LmdbEnvironmentBuilder::new("./test-encrypted").with_max_dbs(2).with_no_tls(true).build_encrypted(b"passphrase").unwrap();
+            let db = env.open_database("test".to_string());
+            let dup_db = env.open_database_with_flags("dup".to_string(), DatabaseFlags::DUPLICATE_KEYS);
 
-            // Remove smaller value and write larger value.
             let mut txw = WriteTransaction::new(&env);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(12));
-            txw.remove_item::<str, u32>(&db, "test", &12);
-            txw.put::<str, u32>(&db, "test", &5783);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
+            txw.put_reserve(&db, "test", "one");
+            txw.put(&db, "test2", "two");
+            txw.put(&dup_db, "test", &1u32);
+            txw.put(&dup_db, "test", &2u32);
+            assert_eq!(txw.get::<str, String>(&db, "test"), Some("one".to_string()));
             txw.commit();
 
-            // Have a new ReadTransaction read the smallest value.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert_eq!(tx.get::<str, u32>(&db, "test"), Some(125));
-            }
+            let tx = ReadTransaction::new(&env);
+            let mut cursor = tx.cursor(&db);
+            assert_eq!(cursor.first::<String, String>(), Some(("test".to_string(), "one".to_string())));
+            assert_eq!(cursor.next::<String, String>(), Some(("test2".to_string(), "two".to_string())));
+            let mut cursor = tx.cursor(&dup_db);
+            assert!(cursor.seek_key_value::<str, u32>("test", &2));
+            assert!(!cursor.seek_key_value::<str, u32>("test", &3));
+            assert_eq!(cursor.count_duplicates(), 2);
+            drop(cursor);
+            drop(tx);
 
-            // Remove everything.
             let mut txw = WriteTransaction::new(&env);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
-            txw.remove::<str>(&db, "test");
-            assert!(txw.get::<str, u32>(&db, "test").is_none());
+            txw.remove_item(&dup_db, "test", &1u32);
             txw.commit();
+            let tx = ReadTransaction::new(&env);
+            let mut cursor = tx.cursor(&dup_db);
+            assert_eq!(cursor.first::<String, u32>(), Some(("test".to_string(), 2)));
+            assert!(cursor.next::<String, u32>().is_none());
+        }
 
-            // Have a new ReadTransaction read the new state.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert!(tx.get::<str, u32>(&db, "test").is_none());
-            }
+        // Values copied to another key don't decrypt.
+        let raw_env = LmdbEnvironmentBuilder::new("./test-encrypted").with_max_dbs(2).with_no_tls(true).build().unwrap();
+        {
+            let raw_db = raw_env.open_database("test".to_string());
+            let mut txw = WriteTransaction::new(&raw_env);
+            let ciphertext = txw.get::<str, RawBytes>(&raw_db, "test").unwrap();
+            txw.put(&raw_db, "test3", &ciphertext);
+            txw.commit();
+        }
+        raw_env.close();
+
+        assert!(LmdbEnvironment::is_encrypted("./test-encrypted"));
+        match LmdbEnvironmentBuilder::new("./test-encrypted").with_max_dbs(2).with_no_tls(true).build_encrypted(b"wrong") {
+            Err(EncryptionError::WrongPassphrase) => {},
+            _ => panic!("Expected wrong passphrase"),
         }
 
+        let env = LmdbEnvironmentBuilder::new("./test-encrypted").with_max_dbs(2).with_no_tls(true).build_encrypted(b"passphrase").unwrap();
+        {
+            let db = env.open_database("test".to_string());
+            let tx = ReadTransaction::new(&env);
+            assert_eq!(tx.get::<str, String>(&db, "test"), Some("one".to_string()));
+            assert!(tx.get::<str, String>(&db, "test3").is_none());
+        }
         env.drop_database().unwrap();
     }
 
     #[test]
-    fn cursor_test() {
-        let env = LmdbEnvironment::new("./test4", 0, 1, open::NOTLS).unwrap();
+    fn read_only_test() {
         {
-            let db = env.open_database_with_flags("test".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_UINT_VALUES);
-
-            let test1: String = "test1".to_string();
-            let test2: String = "test2".to_string();
-
-            // Write some values.
+            let env = LmdbEnvironmentBuilder::new("./test-read-only").with_no_tls(true).build().unwrap();
+            let db = env.open_database("test".to_string());
             let mut txw = WriteTransaction::new(&env);
-            assert!(txw.get::<str, u32>(&db, "test").is_none());
-            txw.put::<str, u32>(&db, "test1", &125);
-            txw.put::<str, u32>(&db, "test1", &12);
-            txw.put::<str, u32>(&db, "test1", &5783);
-            txw.put::<str, u32>(&db, "test2", &5783);
+            txw.put_reserve(&db, "test", "one");
             txw.commit();
+            assert!(!env.is_read_only());
+        }
 
-            // Have a new ReadTransaction read the new state.
+        let env = LmdbEnvironmentBuilder::new("./test-read-only").with_max_readers(8).build_read_only(None).unwrap();
+        assert!(env.is_read_only());
+        {
+            let db = env.open_database("test".to_string());
             let tx = ReadTransaction::new(&env);
-            let mut cursor = tx.cursor(&db);
-            assert_eq!(cursor.first::<String, u32>(), Some((test1.clone(), 12)));
-            assert_eq!(cursor.last::<String, u32>(), Some((test2.clone(), 5783)));
-            assert_eq!(cursor.prev::<String, u32>(), Some((test1.clone(), 5783)));
-            assert_eq!(cursor.first_duplicate::<u32>(), Some(12));
-            assert_eq!(cursor.next_duplicate::<String, u32>(), Some((test1.clone(), 125)));
-            assert_eq!(cursor.prev_duplicate::<String, u32>(), Some((test1.clone(), 12)));
-            assert_eq!(cursor.next_no_duplicate::<String, u32>(), Some((test2.clone(), 5783)));
-            assert!(cursor.seek_key::<str, u32>("test").is_none());
-            assert_eq!(cursor.seek_key::<str, u32>("test1"), Some(12));
-            assert_eq!(cursor.count_duplicates(), 3);
-            assert_eq!(cursor.last_duplicate::<u32>(), Some(5783));
-//            assert_eq!(cursor.seek_key_both::<String, u32>(&test1), Some((test1.clone(), 12)));
-            assert!(!cursor.seek_key_value::<str, u32>("test1", &15));
-            assert!(cursor.seek_key_value::<str, u32>("test1", &125));
-            assert_eq!(cursor.get_current::<String, u32>(), Some((test1.clone(), 125)));
-            assert_eq!(cursor.seek_key_nearest_value::<str, u32>("test1", &126), Some(5783));
-            assert_eq!(cursor.get_current::<String, u32>(), Some((test1.clone(), 5783)));
-            assert!(cursor.prev_no_duplicate::<String, u32>().is_none());
-            assert_eq!(cursor.next::<String, u32>(), Some((test2.clone(), 5783)));
-//            assert_eq!(cursor.seek_range_key::<String, u32>("test"), Some((test1.clone(), 12)));
+            assert_eq!(tx.get::<str, String>(&db, "test"), Some("one".to_string()));
         }
+        env.close();
 
-        env.drop_database().unwrap();
+        match LmdbEnvironmentBuilder::new("./test-read-only").with_max_readers(8).build_read_only(Some(&b"passphrase"[..])) {
+            Err(EncryptionError::Unencrypted) => {},
+            _ => panic!("Expected unencrypted database"),
+        }
+        fs::remove_dir_all("./test-read-only").unwrap();
     }
 }
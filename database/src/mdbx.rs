@@ -0,0 +1,436 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use libmdbx;
+use libmdbx::{EnvironmentFlags, Geometry, Mode, NoWriteMap, SyncMode, WriteFlags, RO, RW};
+
+use crate::backend::{Backend, BackendCursor, BackendTransaction, BackendWriteCursor, BackendWriteTransaction, compare_keys};
+
+use super::*;
+
+/// A libmdbx backed environment.
+///
+/// MDBX is a fork of LMDB with the same data model, so databases behave exactly like the ones of
+/// `LmdbEnvironment`. Unlike LMDB, MDBX grows (and shrinks) the database file on its own, so
+/// there is no fixed map size that can run full.
+pub struct MdbxEnvironment {
+    env: libmdbx::Environment<NoWriteMap>,
+    path: String,
+}
+
+impl MdbxEnvironment {
+    /// The database file grows in steps of this size.
+    const GROWTH_STEP: usize = 64 * 1024 * 1024;
+    /// Upper bound of the database size. The address space must be reserved up front.
+    #[cfg(target_pointer_width = "64")]
+    const MAX_SIZE: usize = 1 << 40;
+    #[cfg(not(target_pointer_width = "64"))]
+    const MAX_SIZE: usize = 1 << 30;
+
+    /// Opens the environment at `path`. The database file is at least `size` bytes large and
+    /// grows as needed. If `no_sync` is set, commits aren't flushed to disk.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(path: &str, size: usize, max_dbs: u32, no_sync: bool) -> Result<Environment, libmdbx::Error> {
+        Ok(Environment::from_backend(MdbxEnvironment::new_mdbx_environment(path, size, max_dbs, no_sync)?))
+    }
+
+    pub(in super) fn new_mdbx_environment(path: &str, size: usize, max_dbs: u32, no_sync: bool) -> Result<Self, libmdbx::Error> {
+        fs::create_dir_all(path).unwrap();
+
+        let sync_mode = if no_sync { SyncMode::SafeNoSync } else { SyncMode::NoMetaSync };
+        let mut builder = libmdbx::Environment::<NoWriteMap>::new();
+        builder.set_max_dbs(max_dbs as usize);
+        builder.set_geometry(Geometry {
+            size: Some(size.min(Self::MAX_SIZE)..Self::MAX_SIZE),
+            growth_step: Some(Self::GROWTH_STEP as isize),
+            shrink_threshold: None,
+            page_size: None,
+        });
+        builder.set_flags(EnvironmentFlags {
+            mode: Mode::ReadWrite { sync_mode },
+            ..Default::default()
+        });
+        let env = builder.open(Path::new(path))?;
+        info!("Opened MDBX database at {}", path);
+
+        Ok(MdbxEnvironment { env, path: path.to_string() })
+    }
+}
+
+impl Backend for MdbxEnvironment {
+    fn name(&self) -> &'static str {
+        "mdbx"
+    }
+
+    fn open_database(&self, name: &str, flags: DatabaseFlags) -> Box<dyn Any + Send + Sync> {
+        let mut db_flags = libmdbx::DatabaseFlags::empty();
+
+        // Translate flags.
+        if flags.contains(DatabaseFlags::DUPLICATE_KEYS) {
+            db_flags.insert(libmdbx::DatabaseFlags::DUP_SORT);
+
+            if flags.contains(DatabaseFlags::DUP_FIXED_SIZE_VALUES) {
+                db_flags.insert(libmdbx::DatabaseFlags::DUP_FIXED);
+            }
+
+            if flags.contains(DatabaseFlags::DUP_UINT_VALUES) {
+                db_flags.insert(libmdbx::DatabaseFlags::INTEGER_DUP);
+            }
+        }
+        if flags.has_uint_keys() {
+            db_flags.insert(libmdbx::DatabaseFlags::INTEGER_KEY);
+        }
+
+        // Create the database if it doesn't exist yet.
+        let txn = self.env.begin_rw_txn().unwrap();
+        txn.create_db(Some(name), db_flags).unwrap();
+        txn.commit().unwrap();
+
+        Box::new(MdbxDatabase { name: name.to_string() })
+    }
+
+    fn stats(&self) -> EnvironmentStats {
+        let stat = self.env.stat().unwrap();
+        let info = self.env.info().unwrap();
+        let page_size = stat.page_size();
+        let total_pages = info.map_size() / page_size as usize;
+        let used_pages = info.last_pgno() + 1;
+        EnvironmentStats {
+            map_size: info.map_size(),
+            page_size,
+            used_pages,
+            free_pages: total_pages.saturating_sub(used_pages),
+            // The main database only contains the names of the other databases.
+            databases: stat.entries(),
+            last_transaction_id: info.last_txnid(),
+            readers: info.num_readers() as u32,
+            max_readers: info.max_readers() as u32,
+        }
+    }
+
+    fn database_stats(&self, db: &Database) -> DatabaseStats {
+        let txn = self.env.begin_ro_txn().unwrap();
+        let db = txn.open_db(Some(&db.handle::<MdbxDatabase>().name)).unwrap();
+        let stat = txn.db_stat(&db).unwrap();
+        DatabaseStats {
+            page_size: stat.page_size(),
+            depth: stat.depth(),
+            branch_pages: stat.branch_pages(),
+            leaf_pages: stat.leaf_pages(),
+            overflow_pages: stat.overflow_pages(),
+            entries: stat.entries(),
+        }
+    }
+
+    /// Flushes the commits to disk, even in `SafeNoSync` mode.
+    fn sync(&self) -> io::Result<()> {
+        self.env.sync(true)
+            .map(|_| ())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
+    fn read_transaction<'env>(&'env self) -> Box<dyn BackendTransaction + 'env> {
+        Box::new(MdbxReadTransaction { txn: self.env.begin_ro_txn().unwrap() })
+    }
+
+    fn write_transaction<'env>(&'env self) -> Box<dyn BackendWriteTransaction + 'env> {
+        Box::new(MdbxWriteTransaction { txn: self.env.begin_rw_txn().unwrap() })
+    }
+
+    fn drop_database(self: Box<Self>) -> io::Result<()> {
+        let path = self.path.clone();
+        drop(self);
+        fs::remove_dir_all(path)
+    }
+
+    /// MDBX shrinks the database file on its own.
+    fn compact(self: Box<Self>) -> io::Result<Box<dyn Backend>> {
+        Ok(self)
+    }
+}
+
+impl fmt::Debug for MdbxEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MdbxEnvironment {{ path: {:?} }}", self.path)
+    }
+}
+
+/// Database handles in libmdbx are bound to a transaction, so the database is looked up by its
+/// name in every transaction. This is cheap for databases that have been opened before.
+#[derive(Debug)]
+struct MdbxDatabase {
+    name: String,
+}
+
+fn owned(value: Option<Cow<[u8]>>) -> Option<Vec<u8>> {
+    value.map(Cow::into_owned)
+}
+
+fn owned_pair(pair: Option<(Cow<[u8]>, Cow<[u8]>)>) -> Option<(Vec<u8>, Vec<u8>)> {
+    pair.map(|(key, value)| (key.into_owned(), value.into_owned()))
+}
+
+struct MdbxReadTransaction<'env> {
+    txn: libmdbx::Transaction<'env, RO, NoWriteMap>,
+}
+
+impl<'env> BackendTransaction for MdbxReadTransaction<'env> {
+    fn get(&self, db: &Database, key: &[u8]) -> Option<Cow<[u8]>> {
+        let db = self.txn.open_db(Some(&db.handle::<MdbxDatabase>().name)).unwrap();
+        self.txn.get(&db, key).unwrap()
+    }
+
+    fn cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendCursor + 'txn> {
+        let db = self.txn.open_db(Some(&db.handle::<MdbxDatabase>().name)).unwrap();
+        Box::new(MdbxCursor { raw: RawMdbxCursor::Read(self.txn.cursor(&db).unwrap()) })
+    }
+}
+
+impl<'env> fmt::Debug for MdbxReadTransaction<'env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MdbxReadTransaction {{ id: {} }}", self.txn.id())
+    }
+}
+
+struct MdbxWriteTransaction<'env> {
+    txn: libmdbx::Transaction<'env, RW, NoWriteMap>,
+}
+
+impl<'env> MdbxWriteTransaction<'env> {
+    fn open_db(&self, db: &Database) -> libmdbx::Database {
+        self.txn.open_db(Some(&db.handle::<MdbxDatabase>().name)).unwrap()
+    }
+}
+
+impl<'env> BackendTransaction for MdbxWriteTransaction<'env> {
+    /// The value is copied, since a write cursor of this transaction could overwrite it.
+    fn get(&self, db: &Database, key: &[u8]) -> Option<Cow<[u8]>> {
+        let db = self.open_db(db);
+        let result: Option<Cow<[u8]>> = self.txn.get(&db, key).unwrap();
+        Some(Cow::Owned(result?.into_owned()))
+    }
+
+    fn cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendCursor + 'txn> {
+        let db = self.open_db(db);
+        Box::new(MdbxCursor { raw: RawMdbxCursor::Write(self.txn.cursor(&db).unwrap()) })
+    }
+}
+
+impl<'env> BackendWriteTransaction for MdbxWriteTransaction<'env> {
+    fn put(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        let db = self.open_db(db);
+        self.txn.put(&db, key, value, WriteFlags::empty()).unwrap();
+    }
+
+    fn put_reserve(&mut self, db: &Database, key: &[u8], size: usize, write: &mut dyn FnMut(&mut [u8])) {
+        let db = self.open_db(db);
+        write(self.txn.reserve(&db, key, size, WriteFlags::empty()).unwrap());
+    }
+
+    fn put_append(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        let db = self.open_db(db);
+        self.txn.put(&db, key, value, WriteFlags::APPEND).unwrap();
+    }
+
+    fn last_key(&self, db: &Database) -> Option<Vec<u8>> {
+        let db = self.open_db(db);
+        let mut cursor = self.txn.cursor(&db).unwrap();
+        let result: Option<(Cow<[u8]>, Cow<[u8]>)> = cursor.last().unwrap();
+        result.map(|(key, _)| key.into_owned())
+    }
+
+    fn remove(&mut self, db: &Database, key: &[u8]) {
+        let db = self.open_db(db);
+        self.txn.del(&db, key, None).unwrap();
+    }
+
+    fn remove_item(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        // Like LMDB, databases without duplicate keys ignore the value.
+        let value = if db.flags().contains(DatabaseFlags::DUPLICATE_KEYS) { Some(value) } else { None };
+        let db = self.open_db(db);
+        self.txn.del(&db, key, value).unwrap();
+    }
+
+    fn clear_database(&mut self, db: &Database) {
+        let db = self.open_db(db);
+        self.txn.clear_db(&db).unwrap();
+    }
+
+    fn remove_range(&mut self, db: &Database, start: &[u8], end: &[u8]) {
+        let key_flags = db.flags();
+        let flags = if key_flags.contains(DatabaseFlags::DUPLICATE_KEYS) {
+            WriteFlags::NO_DUP_DATA
+        } else {
+            WriteFlags::empty()
+        };
+        let db = self.open_db(db);
+        let mut cursor = self.txn.cursor(&db).unwrap();
+        loop {
+            // Deleting moves the cursor, so seek to the first remaining key each time.
+            let result: Option<(Cow<[u8]>, Cow<[u8]>)> = cursor.set_range(start).unwrap();
+            match result {
+                Some((ref key, _)) if compare_keys(key_flags, key, end) == Ordering::Less => {},
+                _ => break,
+            }
+            cursor.del(flags).unwrap();
+        }
+    }
+
+    fn commit(self: Box<Self>) {
+        self.txn.commit().unwrap();
+    }
+
+    fn write_cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendWriteCursor + 'txn> {
+        let db = self.open_db(db);
+        Box::new(MdbxCursor { raw: RawMdbxCursor::Write(self.txn.cursor(&db).unwrap()) })
+    }
+}
+
+impl<'env> fmt::Debug for MdbxWriteTransaction<'env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MdbxWriteTransaction {{ id: {} }}", self.txn.id())
+    }
+}
+
+enum RawMdbxCursor<'txn> {
+    Read(libmdbx::Cursor<'txn, RO>),
+    Write(libmdbx::Cursor<'txn, RW>),
+}
+
+/// Cursors of read and write transactions have different types in libmdbx, so this cursor is
+/// used for both. Only cursors of write transactions can modify entries.
+struct MdbxCursor<'txn> {
+    raw: RawMdbxCursor<'txn>,
+}
+
+macro_rules! raw_cursor {
+    ($self: ident, $cursor: ident => $e: expr) => {
+        match $self.raw {
+            RawMdbxCursor::Read(ref mut $cursor) => $e,
+            RawMdbxCursor::Write(ref mut $cursor) => $e,
+        }
+    };
+}
+
+impl<'txn> BackendCursor for MdbxCursor<'txn> {
+    fn first(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.first().unwrap()))
+    }
+
+    fn first_duplicate(&mut self) -> Option<Vec<u8>> {
+        raw_cursor!(self, cursor => owned(cursor.first_dup().unwrap()))
+    }
+
+    fn last(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.last().unwrap()))
+    }
+
+    fn last_duplicate(&mut self) -> Option<Vec<u8>> {
+        raw_cursor!(self, cursor => owned(cursor.last_dup().unwrap()))
+    }
+
+    fn seek_key_value(&mut self, key: &[u8], value: &[u8]) -> bool {
+        raw_cursor!(self, cursor => {
+            let result: Option<Cow<[u8]>> = cursor.get_both(key, value).unwrap();
+            result.is_some()
+        })
+    }
+
+    fn seek_key_nearest_value(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        raw_cursor!(self, cursor => owned(cursor.get_both_range(key, value).unwrap()))
+    }
+
+    fn get_current(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.get_current().unwrap()))
+    }
+
+    fn next(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.next().unwrap()))
+    }
+
+    fn next_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.next_dup().unwrap()))
+    }
+
+    fn next_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.next_nodup().unwrap()))
+    }
+
+    fn prev(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.prev().unwrap()))
+    }
+
+    fn prev_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.prev_dup().unwrap()))
+    }
+
+    fn prev_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.prev_nodup().unwrap()))
+    }
+
+    fn seek_key(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        raw_cursor!(self, cursor => owned(cursor.set(key).unwrap()))
+    }
+
+    fn seek_key_both(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.set_key(key).unwrap()))
+    }
+
+    fn seek_range(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        raw_cursor!(self, cursor => owned_pair(cursor.set_range(key).unwrap()))
+    }
+
+    fn count_duplicates(&mut self) -> usize {
+        // libmdbx doesn't expose `mdbx_cursor_count`, so count the values of the current key and
+        // restore the position afterwards.
+        raw_cursor!(self, cursor => {
+            let current: Option<(Cow<[u8]>, Cow<[u8]>)> = cursor.get_current().unwrap();
+            let (key, value) = match current {
+                Some((key, value)) => (key.into_owned(), value.into_owned()),
+                None => return 0,
+            };
+
+            let mut count = 0;
+            let mut result: Option<Cow<[u8]>> = cursor.first_dup().unwrap();
+            while result.is_some() {
+                count += 1;
+                let next: Option<(Cow<[u8]>, Cow<[u8]>)> = cursor.next_dup().unwrap();
+                result = next.map(|(_, value)| value);
+            }
+
+            let _: Option<Cow<[u8]>> = cursor.get_both(&key, &value).unwrap();
+            count
+        })
+    }
+}
+
+impl<'txn> MdbxCursor<'txn> {
+    fn write_cursor(&mut self) -> &mut libmdbx::Cursor<'txn, RW> {
+        match self.raw {
+            RawMdbxCursor::Write(ref mut cursor) => cursor,
+            RawMdbxCursor::Read(_) => unreachable!(),
+        }
+    }
+}
+
+impl<'txn> BackendWriteCursor for MdbxCursor<'txn> {
+    fn put_current(&mut self, value: &[u8]) {
+        let cursor = self.write_cursor();
+        let current: Option<(Cow<[u8]>, Cow<[u8]>)> = cursor.get_current().unwrap();
+        let (key, _) = current.expect("Cursor is not positioned at an entry");
+        let key = key.into_owned();
+        cursor.put(&key, value, WriteFlags::CURRENT).unwrap();
+    }
+
+    fn append(&mut self, key: &[u8], value: &[u8]) {
+        self.write_cursor().put(key, value, WriteFlags::APPEND).unwrap();
+    }
+
+    fn delete_current(&mut self) {
+        self.write_cursor().del(WriteFlags::empty()).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! Metrics of the database operations of all environments in the process, collected in
+//! `DATABASE_METRICS`. Only compiled with the `metrics` feature.
+//!
+//! Reads and writes are counted per database name, so databases with the same name in different
+//! environments share their counters.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use parking_lot::RwLock;
+
+use crate::Database;
+
+lazy_static! {
+    pub static ref DATABASE_METRICS: DatabaseMetrics = DatabaseMetrics::new();
+}
+
+/// Bucket bounds of the transaction durations in microseconds.
+const DURATION_BOUNDS: &[usize] = &[100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000, 5_000_000];
+/// Bucket bounds of the commit sizes in bytes.
+const SIZE_BOUNDS: &[usize] = &[256, 1_024, 4_096, 16_384, 65_536, 262_144, 1_048_576, 4_194_304, 16_777_216];
+
+/// Counts observations in buckets with fixed upper bounds, like a Prometheus histogram.
+pub struct Histogram {
+    bounds: &'static [usize],
+    /// Non-cumulative counts of the buckets. The last bucket counts observations above all bounds.
+    buckets: Vec<AtomicUsize>,
+    sum: AtomicUsize,
+    count: AtomicUsize,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [usize]) -> Self {
+        Histogram {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicUsize::new(0)).collect(),
+            sum: AtomicUsize::new(0),
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn observe(&self, value: usize) {
+        let bucket = self.bounds.iter().position(|&bound| value <= bound).unwrap_or(self.bounds.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Release);
+        self.sum.fetch_add(value, Ordering::Release);
+        self.count.fetch_add(1, Ordering::Release);
+    }
+
+    /// Upper bounds of the buckets and the number of observations less than or equal to them.
+    pub fn cumulative_buckets(&self) -> Vec<(usize, usize)> {
+        let mut total = 0;
+        self.bounds.iter().zip(self.buckets.iter())
+            .map(|(&bound, bucket)| {
+                total += bucket.load(Ordering::Acquire);
+                (bound, total)
+            })
+            .collect()
+    }
+
+    pub fn sum(&self) -> usize {
+        self.sum.load(Ordering::Acquire)
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Default)]
+struct DatabaseCounters {
+    reads: AtomicUsize,
+    writes: AtomicUsize,
+}
+
+/// Number of reads and writes of a database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DatabaseCounts {
+    pub reads: usize,
+    pub writes: usize,
+}
+
+pub struct DatabaseMetrics {
+    /// Durations of read transactions in microseconds.
+    pub read_transaction_duration: Histogram,
+    /// Durations of write transactions in microseconds, including the commit.
+    pub write_transaction_duration: Histogram,
+    /// Bytes of keys and values written by committed transactions.
+    pub commit_size: Histogram,
+    abort_count: AtomicUsize,
+    databases: RwLock<HashMap<String, DatabaseCounters>>,
+}
+
+impl DatabaseMetrics {
+    fn new() -> Self {
+        DatabaseMetrics {
+            read_transaction_duration: Histogram::new(DURATION_BOUNDS),
+            write_transaction_duration: Histogram::new(DURATION_BOUNDS),
+            commit_size: Histogram::new(SIZE_BOUNDS),
+            abort_count: AtomicUsize::new(0),
+            databases: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Number of write transactions that were dropped without being committed.
+    #[inline]
+    pub fn abort_count(&self) -> usize {
+        self.abort_count.load(Ordering::Acquire)
+    }
+
+    /// Reads and writes per database name, sorted by name.
+    pub fn database_counts(&self) -> Vec<(String, DatabaseCounts)> {
+        let mut counts: Vec<(String, DatabaseCounts)> = self.databases.read().iter()
+            .map(|(name, counters)| (name.clone(), DatabaseCounts {
+                reads: counters.reads.load(Ordering::Acquire),
+                writes: counters.writes.load(Ordering::Acquire),
+            }))
+            .collect();
+        counts.sort_by(|a, b| a.0.cmp(&b.0));
+        counts
+    }
+
+    pub(crate) fn register_database(&self, name: &str) {
+        if !self.databases.read().contains_key(name) {
+            self.databases.write().entry(name.to_string()).or_default();
+        }
+    }
+
+    #[inline]
+    pub(crate) fn note_read(&self, db: &Database) {
+        self.with_counters(db, |counters| { counters.reads.fetch_add(1, Ordering::Release); });
+    }
+
+    #[inline]
+    pub(crate) fn note_write(&self, db: &Database) {
+        self.with_counters(db, |counters| { counters.writes.fetch_add(1, Ordering::Release); });
+    }
+
+    fn with_counters<F: Fn(&DatabaseCounters)>(&self, db: &Database, f: F) {
+        if let Some(counters) = self.databases.read().get(db.name()) {
+            f(counters);
+            return;
+        }
+        f(self.databases.write().entry(db.name().to_string()).or_default());
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TransactionKind {
+    Read,
+    Write,
+}
+
+/// Measures a transaction. Its duration is recorded when it is dropped.
+#[derive(Debug)]
+pub(crate) struct TransactionMetrics {
+    kind: TransactionKind,
+    start: Instant,
+    written: usize,
+    committed: bool,
+}
+
+impl TransactionMetrics {
+    pub(crate) fn new(kind: TransactionKind) -> Self {
+        TransactionMetrics {
+            kind,
+            start: Instant::now(),
+            written: 0,
+            committed: false,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn note_write(&mut self, db: &Database, bytes: usize) {
+        self.written += bytes;
+        DATABASE_METRICS.note_write(db);
+    }
+
+    pub(crate) fn commit(mut self) {
+        self.committed = true;
+        DATABASE_METRICS.commit_size.observe(self.written);
+    }
+}
+
+impl Drop for TransactionMetrics {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let micros = elapsed.as_secs() as usize * 1_000_000 + elapsed.subsec_micros() as usize;
+        match self.kind {
+            TransactionKind::Read => DATABASE_METRICS.read_transaction_duration.observe(micros),
+            TransactionKind::Write => {
+                DATABASE_METRICS.write_transaction_duration.observe(micros);
+                if !self.committed {
+                    DATABASE_METRICS.abort_count.fetch_add(1, Ordering::Release);
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ReadTransaction, WriteTransaction};
+    use crate::volatile::VolatileEnvironment;
+
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let histogram = Histogram::new(&[10, 100]);
+        for value in &[5, 10, 50, 1000] {
+            histogram.observe(*value);
+        }
+        assert_eq!(histogram.cumulative_buckets(), vec![(10, 2), (100, 3)]);
+        assert_eq!(histogram.sum(), 1065);
+        assert_eq!(histogram.count(), 4);
+    }
+
+    #[test]
+    fn it_counts_operations_and_aborts() {
+        let env = VolatileEnvironment::new(1).unwrap();
+        let db = env.open_database("metrics_test".to_string());
+        let commits = DATABASE_METRICS.commit_size.count();
+        let aborts = DATABASE_METRICS.abort_count();
+
+        let mut txn = WriteTransaction::new(&env);
+        txn.put(&db, "key", "value");
+        txn.commit();
+        let txn = ReadTransaction::new(&env);
+        assert_eq!(txn.get::<str, String>(&db, "key"), Some("value".to_string()));
+        let mut txn = WriteTransaction::new(&env);
+        txn.remove(&db, "key");
+        txn.abort();
+
+        let counts = DATABASE_METRICS.database_counts().into_iter()
+            .find(|(name, _)| name == "metrics_test")
+            .map(|(_, counts)| counts);
+        assert_eq!(counts, Some(DatabaseCounts { reads: 1, writes: 2 }));
+        assert!(DATABASE_METRICS.commit_size.count() > commits);
+        assert!(DATABASE_METRICS.abort_count() > aborts);
+    }
+}
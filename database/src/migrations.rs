@@ -0,0 +1,200 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use super::*;
+
+type Migration<'m> = Box<dyn Fn(&mut WriteTransaction) -> io::Result<()> + 'm>;
+
+/// Schema versions of named databases and the migrations between them.
+///
+/// The version of each database is stored in the `SchemaVersions` database under its name. A
+/// database without a stored version has version 0, which is also the version of all databases
+/// created before migrations existed. Migrations must therefore handle empty databases.
+pub struct Migrations<'m> {
+    name: String,
+    migrations: Vec<Migration<'m>>,
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The database was written by a newer version of the software.
+    UnsupportedVersion { name: String, version: u32, latest_version: u32 },
+    /// A migration failed. No changes were committed.
+    Failed { name: String, version: u32, error: io::Error },
+    /// The database is read-only and wasn't migrated by the node writing it yet.
+    Pending { name: String, version: u32, latest_version: u32 },
+}
+
+impl<'m> Migrations<'m> {
+    pub const SCHEMA_VERSIONS_DB_NAME: &'static str = "SchemaVersions";
+
+    pub fn new(name: &str) -> Self {
+        Migrations {
+            name: name.to_string(),
+            migrations: Vec::new(),
+        }
+    }
+
+    /// Registers the migration to `version` from the version before. Migrations must be registered
+    /// in order, starting at version 1.
+    pub fn with_migration<F>(&mut self, version: u32, migration: F) -> &mut Self
+        where F: Fn(&mut WriteTransaction) -> io::Result<()> + 'm {
+        assert_eq!(version, self.latest_version() + 1, "Migrations of {} must be registered in order", self.name);
+        self.migrations.push(Box::new(migration));
+        self
+    }
+
+    /// The version the migrations upgrade the database to.
+    pub fn latest_version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    /// Returns the stored schema version of the database `name`.
+    pub fn version(env: &Environment, name: &str) -> u32 {
+        let db = env.open_database(Self::SCHEMA_VERSIONS_DB_NAME.to_string());
+        ReadTransaction::new(env).get(&db, name).unwrap_or(0)
+    }
+
+    /// Runs all migrations the database hasn't seen yet in a single write transaction and records
+    /// the latest version. Returns the version the database had before.
+    ///
+    /// All databases the migrations use must be opened before, since databases can't be opened
+    /// while a write transaction is running.
+    ///
+    /// Read-only environments are only checked to be at the latest version.
+    pub fn run(&self, env: &Environment) -> Result<u32, MigrationError> {
+        if env.is_read_only() {
+            return self.check(env);
+        }
+
+        let db = env.open_database(Self::SCHEMA_VERSIONS_DB_NAME.to_string());
+        let mut txn = WriteTransaction::new(env);
+        let version: u32 = txn.get(&db, self.name.as_str()).unwrap_or(0);
+        let latest_version = self.latest_version();
+
+        if version > latest_version {
+            return Err(MigrationError::UnsupportedVersion { name: self.name.clone(), version, latest_version });
+        }
+
+        for (i, migration) in self.migrations.iter().enumerate().skip(version as usize) {
+            let to_version = i as u32 + 1;
+            info!("Migrating database {} to version {}", self.name, to_version);
+            migration(&mut txn)
+                .map_err(|error| MigrationError::Failed { name: self.name.clone(), version: to_version, error })?;
+        }
+
+        if version != latest_version || txn.get::<str, u32>(&db, self.name.as_str()).is_none() {
+            txn.put(&db, self.name.as_str(), &latest_version);
+            txn.commit();
+        }
+        Ok(version)
+    }
+
+    fn check(&self, env: &Environment) -> Result<u32, MigrationError> {
+        let version = Self::version(env, &self.name);
+        let latest_version = self.latest_version();
+        if version > latest_version {
+            return Err(MigrationError::UnsupportedVersion { name: self.name.clone(), version, latest_version });
+        }
+        if version < latest_version {
+            return Err(MigrationError::Pending { name: self.name.clone(), version, latest_version });
+        }
+        Ok(version)
+    }
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationError::UnsupportedVersion { name, version, latest_version } =>
+                write!(f, "Database {} has schema version {}, but only versions up to {} are supported", name, version, latest_version),
+            MigrationError::Failed { name, version, error } =>
+                write!(f, "Migrating database {} to version {} failed: {}", name, version, error),
+            MigrationError::Pending { name, version, latest_version } =>
+                write!(f, "Read-only database {} has schema version {}, but version {} is required", name, version, latest_version),
+        }
+    }
+}
+
+impl Error for MigrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MigrationError::UnsupportedVersion { .. } | MigrationError::Pending { .. } => None,
+            MigrationError::Failed { error, .. } => Some(error),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::volatile::VolatileEnvironment;
+
+    #[test]
+    fn it_runs_new_migrations_once() {
+        let env = VolatileEnvironment::new(2).unwrap();
+        let db = env.open_database("test".to_string());
+        assert_eq!(Migrations::version(&env, "test"), 0);
+
+        let runs = Cell::new(0);
+        let mut migrations = Migrations::new("test");
+        migrations.with_migration(1, |txn| {
+            runs.set(runs.get() + 1);
+            txn.put_reserve(&db, "key", "one");
+            Ok(())
+        });
+        assert_eq!(migrations.run(&env).unwrap(), 0);
+        assert_eq!(migrations.run(&env).unwrap(), 1);
+        assert_eq!(runs.get(), 1);
+        assert_eq!(Migrations::version(&env, "test"), 1);
+
+        migrations.with_migration(2, |txn| {
+            let value: String = txn.get(&db, "key").unwrap();
+            txn.put_reserve(&db, "key", &format!("{} two", value)[..]);
+            Ok(())
+        });
+        assert_eq!(migrations.run(&env).unwrap(), 1);
+        assert_eq!(runs.get(), 1);
+        assert_eq!(Migrations::version(&env, "test"), 2);
+        assert_eq!(ReadTransaction::new(&env).get::<str, String>(&db, "key"), Some("one two".to_string()));
+    }
+
+    #[test]
+    fn it_rolls_back_failed_migrations() {
+        let env = VolatileEnvironment::new(2).unwrap();
+        let db = env.open_database("test".to_string());
+
+        let mut migrations = Migrations::new("test");
+        migrations
+            .with_migration(1, |txn| {
+                txn.put_reserve(&db, "key", "one");
+                Ok(())
+            })
+            .with_migration(2, |_| Err(io::Error::new(io::ErrorKind::Other, "failed")));
+        match migrations.run(&env) {
+            Err(MigrationError::Failed { version: 2, .. }) => {},
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        assert_eq!(Migrations::version(&env, "test"), 0);
+        assert!(ReadTransaction::new(&env).get::<str, String>(&db, "key").is_none());
+    }
+
+    #[test]
+    fn it_rejects_newer_versions() {
+        let env = VolatileEnvironment::new(1).unwrap();
+
+        let mut migrations = Migrations::new("test");
+        migrations.with_migration(1, |_| Ok(()));
+        migrations.run(&env).unwrap();
+
+        match Migrations::new("test").run(&env) {
+            Err(MigrationError::UnsupportedVersion { version: 1, latest_version: 0, .. }) => {},
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,577 @@
+//! Transactions and cursors of the backends that store all databases in one ordered key space,
+//! see `PrefixedKeys`. These backends only provide their committed entries as a `Snapshot`.
+//!
+//! Like in LMDB, there can only be one write transaction at a time. Its changes are kept in an
+//! overlay on top of the snapshot it started with and handed to the backend on commit.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::iter::Peekable;
+use std::ops::Bound;
+
+use crate::backend::{BackendCursor, BackendTransaction, BackendWriteCursor, BackendWriteTransaction};
+use crate::encoding::{Overlay, PrefixedKeys};
+use crate::{Database, DatabaseFlags};
+
+/// The committed entries a transaction reads, ordered by their raw keys.
+pub(crate) trait Snapshot {
+    fn get(&self, raw_key: &[u8]) -> Option<Vec<u8>>;
+
+    /// Returns the entries after `raw_key` (or from it, if `inclusive`) and before `end`, in
+    /// ascending order.
+    fn range_forward<'a>(&'a self, raw_key: &[u8], inclusive: bool, end: &[u8]) -> CommittedEntries<'a>;
+
+    /// Returns the entries before `raw_key` (or from it, if `inclusive`) and not before `start`,
+    /// in descending order.
+    fn range_backward<'a>(&'a self, raw_key: &[u8], inclusive: bool, start: &[u8]) -> CommittedEntries<'a>;
+}
+
+/// The raw keys and values of a range of committed entries.
+pub(crate) type CommittedEntries<'a> = Box<dyn Iterator<Item=(Vec<u8>, Vec<u8>)> + 'a>;
+
+/// Returns the first entry after `raw_key` (or at it, if `inclusive`) and before `end`, either
+/// from `overlay` or from `committed`, which yields the committed entries of that range.
+///
+/// Only the committed entries up to the returned one are consumed, so a scan continues with the
+/// same iterator instead of seeking again for every entry.
+pub(crate) fn seek_forward_with<I>(overlay: Option<&Overlay>, raw_key: &[u8], inclusive: bool, end: &[u8], committed: &mut Peekable<I>) -> Option<(Vec<u8>, Vec<u8>)>
+    where I: Iterator<Item=(Vec<u8>, Vec<u8>)> {
+    let start = if inclusive { Bound::Included(raw_key) } else { Bound::Excluded(raw_key) };
+    let mut changes = overlay.map(|overlay| overlay.range::<[u8], _>((start, Bound::Excluded(end))));
+    loop {
+        match changes.as_mut().and_then(Iterator::next) {
+            // Committed entries before the next change are unchanged.
+            Some((key, _)) if committed.peek().map_or(false, |(committed_key, _)| committed_key < key) => return committed.next(),
+            Some((key, value)) => {
+                // The change replaces the committed entry with the same key.
+                if committed.peek().map_or(false, |(committed_key, _)| committed_key == key) {
+                    committed.next();
+                }
+                if let Some(value) = value {
+                    return Some((key.clone(), value.clone()));
+                }
+            },
+            None => return committed.next(),
+        }
+    }
+}
+
+/// Like `seek_forward_with`, but returns the last entry before `raw_key` (or at it, if
+/// `inclusive`) and not before `start`. `committed` yields the entries in descending order.
+pub(crate) fn seek_backward_with<I>(overlay: Option<&Overlay>, raw_key: &[u8], inclusive: bool, start: &[u8], committed: &mut Peekable<I>) -> Option<(Vec<u8>, Vec<u8>)>
+    where I: Iterator<Item=(Vec<u8>, Vec<u8>)> {
+    let end = if inclusive { Bound::Included(raw_key) } else { Bound::Excluded(raw_key) };
+    let mut changes = overlay.map(|overlay| overlay.range::<[u8], _>((Bound::Included(start), end)).rev());
+    loop {
+        match changes.as_mut().and_then(Iterator::next) {
+            Some((key, _)) if committed.peek().map_or(false, |(committed_key, _)| committed_key > key) => return committed.next(),
+            Some((key, value)) => {
+                if committed.peek().map_or(false, |(committed_key, _)| committed_key == key) {
+                    committed.next();
+                }
+                if let Some(value) = value {
+                    return Some((key.clone(), value.clone()));
+                }
+            },
+            None => return committed.next(),
+        }
+    }
+}
+
+/// The snapshot a transaction started with, together with the changes of a write transaction.
+#[derive(Clone, Copy)]
+struct View<'txn> {
+    snapshot: &'txn dyn Snapshot,
+    overlay: Option<&'txn RefCell<Overlay>>,
+}
+
+impl<'txn> View<'txn> {
+    fn get(&self, raw_key: &[u8]) -> Option<Vec<u8>> {
+        if let Some(overlay) = self.overlay {
+            if let Some(value) = overlay.borrow().get(raw_key) {
+                return value.clone();
+            }
+        }
+        self.snapshot.get(raw_key)
+    }
+
+    /// Returns the first entry after `raw_key` (or at it, if `inclusive`) and before `end`.
+    fn seek_forward(&self, raw_key: &[u8], inclusive: bool, end: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        if raw_key >= end {
+            return None;
+        }
+        let overlay = self.overlay.map(|overlay| overlay.borrow());
+        let mut committed = self.snapshot.range_forward(raw_key, inclusive, end).peekable();
+        seek_forward_with(overlay.as_ref().map(|overlay| &**overlay), raw_key, inclusive, end, &mut committed)
+    }
+
+    /// Returns the last entry before `raw_key` (or at it, if `inclusive`) and not before `start`.
+    fn seek_backward(&self, raw_key: &[u8], inclusive: bool, start: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        if raw_key < start {
+            return None;
+        }
+        let overlay = self.overlay.map(|overlay| overlay.borrow());
+        let mut committed = self.snapshot.range_backward(raw_key, inclusive, start).peekable();
+        seek_backward_with(overlay.as_ref().map(|overlay| &**overlay), raw_key, inclusive, start, &mut committed)
+    }
+
+    /// Returns the raw keys of all entries in `start..end`, in a single pass over the snapshot.
+    fn keys(&self, start: &[u8], end: &[u8]) -> Vec<Vec<u8>> {
+        let mut raw_keys = Vec::new();
+        if start >= end {
+            return raw_keys;
+        }
+        let overlay = self.overlay.map(|overlay| overlay.borrow());
+        let overlay = overlay.as_ref().map(|overlay| &**overlay);
+        let mut committed = self.snapshot.range_forward(start, true, end).peekable();
+        let mut next = seek_forward_with(overlay, start, true, end, &mut committed);
+        while let Some((raw_key, _)) = next {
+            next = seek_forward_with(overlay, &raw_key, false, end, &mut committed);
+            raw_keys.push(raw_key);
+        }
+        raw_keys
+    }
+}
+
+/// The handle of a database in a backend with prefixed keys.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PrefixedDatabase {
+    id: u32,
+    flags: DatabaseFlags,
+}
+
+impl PrefixedDatabase {
+    pub(crate) fn new(id: u32, flags: DatabaseFlags) -> Self {
+        PrefixedDatabase { id, flags }
+    }
+
+    /// Returns the value of `key`, or its first value in databases with duplicate keys.
+    fn get(&self, view: View, key: &[u8]) -> Option<Vec<u8>> {
+        if self.has_duplicates() {
+            let (raw_key, _) = view.seek_forward(&self.encode_key(key), true, &self.encode_key_end(key))?;
+            Some(self.decode(&raw_key, &[]).1)
+        } else {
+            view.get(&self.encode_key(key))
+        }
+    }
+}
+
+impl PrefixedKeys for PrefixedDatabase {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn key_flags(&self) -> DatabaseFlags {
+        self.flags
+    }
+}
+
+pub(crate) struct PrefixedReadTransaction<S> {
+    snapshot: S,
+}
+
+impl<S: Snapshot> PrefixedReadTransaction<S> {
+    pub(crate) fn new(snapshot: S) -> Self {
+        PrefixedReadTransaction { snapshot }
+    }
+
+    fn view(&self) -> View {
+        View { snapshot: &self.snapshot, overlay: None }
+    }
+}
+
+impl<S: Snapshot> BackendTransaction for PrefixedReadTransaction<S> {
+    fn get(&self, db: &Database, key: &[u8]) -> Option<Cow<[u8]>> {
+        db.handle::<PrefixedDatabase>().get(self.view(), key).map(Cow::Owned)
+    }
+
+    fn cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendCursor + 'txn> {
+        Box::new(PrefixedCursor::new(self.view(), *db.handle()))
+    }
+}
+
+impl<S> fmt::Debug for PrefixedReadTransaction<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PrefixedReadTransaction")
+    }
+}
+
+pub(crate) struct PrefixedWriteTransaction<'env, S> {
+    snapshot: S,
+    overlay: RefCell<Overlay>,
+    /// Hands the changes to the backend on commit. It holds the write lock of the backend, so
+    /// dropping it aborts the transaction.
+    apply: Box<dyn FnOnce(Overlay) + 'env>,
+}
+
+impl<'env, S: Snapshot> PrefixedWriteTransaction<'env, S> {
+    /// The snapshot must contain all commits, so the backend has to take its write lock first.
+    pub(crate) fn new<F>(snapshot: S, apply: F) -> Self where F: FnOnce(Overlay) + 'env {
+        PrefixedWriteTransaction {
+            snapshot,
+            overlay: RefCell::new(Overlay::new()),
+            apply: Box::new(apply),
+        }
+    }
+
+    /// Removes all entries with raw keys in `start..end`.
+    fn remove_raw_range(&mut self, start: &[u8], end: &[u8]) {
+        let raw_keys = self.view().keys(start, end);
+        let mut overlay = self.overlay.borrow_mut();
+        for raw_key in raw_keys {
+            overlay.insert(raw_key, None);
+        }
+    }
+
+    fn put_raw(&mut self, db: &PrefixedDatabase, key: &[u8], value: &[u8]) {
+        let (raw_key, raw_value) = db.encode(key, value);
+        self.overlay.borrow_mut().insert(raw_key, Some(raw_value));
+    }
+
+    fn view(&self) -> View {
+        View { snapshot: &self.snapshot, overlay: Some(&self.overlay) }
+    }
+}
+
+impl<'env, S: Snapshot> BackendTransaction for PrefixedWriteTransaction<'env, S> {
+    fn get(&self, db: &Database, key: &[u8]) -> Option<Cow<[u8]>> {
+        db.handle::<PrefixedDatabase>().get(self.view(), key).map(Cow::Owned)
+    }
+
+    fn cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendCursor + 'txn> {
+        Box::new(PrefixedCursor::new(self.view(), *db.handle()))
+    }
+}
+
+impl<'env, S: Snapshot> BackendWriteTransaction for PrefixedWriteTransaction<'env, S> {
+    fn put(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        self.put_raw(db.handle(), key, value);
+    }
+
+    fn put_reserve(&mut self, db: &Database, key: &[u8], size: usize, write: &mut dyn FnMut(&mut [u8])) {
+        let mut bytes = vec![0u8; size];
+        write(&mut bytes);
+        self.put_raw(db.handle(), key, &bytes);
+    }
+
+    /// The key space has no append hint, so this is a regular put.
+    fn put_append(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        self.put_raw(db.handle(), key, value);
+    }
+
+    fn last_key(&self, db: &Database) -> Option<Vec<u8>> {
+        let db: &PrefixedDatabase = db.handle();
+        let (raw_key, _) = self.view().seek_backward(&db.end(), false, &db.start())?;
+        Some(db.decode_key(&raw_key))
+    }
+
+    fn remove(&mut self, db: &Database, key: &[u8]) {
+        let db: &PrefixedDatabase = db.handle();
+        if !db.has_duplicates() {
+            self.overlay.borrow_mut().insert(db.encode_key(key), None);
+            return;
+        }
+
+        // Remove all values of the key.
+        self.remove_raw_range(&db.encode_key(key), &db.encode_key_end(key));
+    }
+
+    fn remove_item(&mut self, db: &Database, key: &[u8], value: &[u8]) {
+        let db: &PrefixedDatabase = db.handle();
+        // Like LMDB, databases without duplicate keys ignore the value.
+        if !db.has_duplicates() {
+            self.overlay.borrow_mut().insert(db.encode_key(key), None);
+            return;
+        }
+        let (raw_key, _) = db.encode(key, value);
+        self.overlay.borrow_mut().insert(raw_key, None);
+    }
+
+    fn clear_database(&mut self, db: &Database) {
+        let db: &PrefixedDatabase = db.handle();
+        self.remove_raw_range(&db.start(), &db.end());
+    }
+
+    fn remove_range(&mut self, db: &Database, start: &[u8], end: &[u8]) {
+        // The encoding preserves the order of the keys, so the range can be removed by raw keys.
+        let db: &PrefixedDatabase = db.handle();
+        self.remove_raw_range(&db.encode_key(start), &db.encode_key(end));
+    }
+
+    fn commit(self: Box<Self>) {
+        let PrefixedWriteTransaction { snapshot, overlay, apply } = *self;
+        // Release the snapshot first, since some backends have to copy the entries it still uses.
+        drop(snapshot);
+        apply(overlay.into_inner());
+    }
+
+    fn write_cursor<'txn>(&'txn self, db: &'txn Database) -> Box<dyn BackendWriteCursor + 'txn> {
+        Box::new(PrefixedWriteCursor {
+            cursor: PrefixedCursor::new(self.view(), *db.handle()),
+            overlay: &self.overlay,
+        })
+    }
+}
+
+impl<'env, S> fmt::Debug for PrefixedWriteTransaction<'env, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PrefixedWriteTransaction {{ changes: {} }}", self.overlay.borrow().len())
+    }
+}
+
+struct PrefixedCursor<'txn> {
+    view: View<'txn>,
+    db: PrefixedDatabase,
+    /// The raw key of the current entry.
+    position: Option<Vec<u8>>,
+}
+
+impl<'txn> PrefixedCursor<'txn> {
+    fn new(view: View<'txn>, db: PrefixedDatabase) -> Self {
+        PrefixedCursor { view, db, position: None }
+    }
+
+    /// Moves the cursor to `entry`, if there is one, and returns the decoded key and value.
+    fn move_to(&mut self, entry: Option<(Vec<u8>, Vec<u8>)>) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (raw_key, raw_value) = entry?;
+        let item = self.db.decode(&raw_key, &raw_value);
+        self.position = Some(raw_key);
+        Some(item)
+    }
+
+    /// Returns the range of raw keys holding the values of the current key.
+    fn current_key_range(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let key = self.db.decode_key(self.position.as_ref()?);
+        Some((self.db.encode_key(&key), self.db.encode_key_end(&key)))
+    }
+
+    fn seek_forward(&mut self, raw_key: &[u8], inclusive: bool, end: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        let entry = self.view.seek_forward(raw_key, inclusive, end);
+        self.move_to(entry)
+    }
+
+    fn seek_backward(&mut self, raw_key: &[u8], inclusive: bool, start: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        let entry = self.view.seek_backward(raw_key, inclusive, start);
+        self.move_to(entry)
+    }
+}
+
+impl<'txn> BackendCursor for PrefixedCursor<'txn> {
+    fn first(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (start, end) = (self.db.start(), self.db.end());
+        self.seek_forward(&start, true, &end)
+    }
+
+    fn first_duplicate(&mut self) -> Option<Vec<u8>> {
+        let (start, end) = self.current_key_range()?;
+        self.seek_forward(&start, true, &end).map(|(_, value)| value)
+    }
+
+    fn last(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (start, end) = (self.db.start(), self.db.end());
+        self.seek_backward(&end, false, &start)
+    }
+
+    fn last_duplicate(&mut self) -> Option<Vec<u8>> {
+        let (start, end) = self.current_key_range()?;
+        self.seek_backward(&end, false, &start).map(|(_, value)| value)
+    }
+
+    fn seek_key_value(&mut self, key: &[u8], value: &[u8]) -> bool {
+        let (raw_key, raw_value) = self.db.encode(key, value);
+        if self.view.get(&raw_key) == Some(raw_value) {
+            self.position = Some(raw_key);
+            return true;
+        }
+        false
+    }
+
+    fn seek_key_nearest_value(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        let (raw_key, _) = self.db.encode(key, value);
+        let end = self.db.encode_key_end(key);
+        self.seek_forward(&raw_key, true, &end).map(|(_, value)| value)
+    }
+
+    fn get_current(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let raw_key = self.position.as_ref()?;
+        let raw_value = self.view.get(raw_key)?;
+        Some(self.db.decode(raw_key, &raw_value))
+    }
+
+    fn next(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let raw_key = match self.position.clone() {
+            Some(raw_key) => raw_key,
+            None => return self.first(),
+        };
+        let end = self.db.end();
+        self.seek_forward(&raw_key, false, &end)
+    }
+
+    fn next_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let raw_key = self.position.clone()?;
+        let (_, end) = self.current_key_range()?;
+        self.seek_forward(&raw_key, false, &end)
+    }
+
+    fn next_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (_, key_end) = match self.current_key_range() {
+            Some(range) => range,
+            None => return self.first(),
+        };
+        let end = self.db.end();
+        self.seek_forward(&key_end, true, &end)
+    }
+
+    fn prev(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let raw_key = match self.position.clone() {
+            Some(raw_key) => raw_key,
+            None => return self.last(),
+        };
+        let start = self.db.start();
+        self.seek_backward(&raw_key, false, &start)
+    }
+
+    fn prev_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let raw_key = self.position.clone()?;
+        let (start, _) = self.current_key_range()?;
+        self.seek_backward(&raw_key, false, &start)
+    }
+
+    fn prev_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (key_start, _) = match self.current_key_range() {
+            Some(range) => range,
+            None => return self.last(),
+        };
+        let start = self.db.start();
+        self.seek_backward(&key_start, false, &start)
+    }
+
+    fn seek_key(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let (start, end) = (self.db.encode_key(key), self.db.encode_key_end(key));
+        self.seek_forward(&start, true, &end).map(|(_, value)| value)
+    }
+
+    fn seek_key_both(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (start, end) = (self.db.encode_key(key), self.db.encode_key_end(key));
+        self.seek_forward(&start, true, &end)
+    }
+
+    fn seek_range(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (start, end) = (self.db.encode_prefix(key), self.db.end());
+        self.seek_forward(&start, true, &end)
+    }
+
+    fn count_duplicates(&mut self) -> usize {
+        let (start, end) = match self.current_key_range() {
+            Some(range) => range,
+            None => return 0,
+        };
+        self.view.keys(&start, &end).len()
+    }
+}
+
+struct PrefixedWriteCursor<'txn> {
+    cursor: PrefixedCursor<'txn>,
+    overlay: &'txn RefCell<Overlay>,
+}
+
+impl<'txn> BackendCursor for PrefixedWriteCursor<'txn> {
+    fn first(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.first()
+    }
+
+    fn first_duplicate(&mut self) -> Option<Vec<u8>> {
+        self.cursor.first_duplicate()
+    }
+
+    fn last(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.last()
+    }
+
+    fn last_duplicate(&mut self) -> Option<Vec<u8>> {
+        self.cursor.last_duplicate()
+    }
+
+    fn seek_key_value(&mut self, key: &[u8], value: &[u8]) -> bool {
+        self.cursor.seek_key_value(key, value)
+    }
+
+    fn seek_key_nearest_value(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        self.cursor.seek_key_nearest_value(key, value)
+    }
+
+    fn get_current(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.get_current()
+    }
+
+    fn next(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.next()
+    }
+
+    fn next_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.next_duplicate()
+    }
+
+    fn next_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.next_no_duplicate()
+    }
+
+    fn prev(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.prev()
+    }
+
+    fn prev_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.prev_duplicate()
+    }
+
+    fn prev_no_duplicate(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.prev_no_duplicate()
+    }
+
+    fn seek_key(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        self.cursor.seek_key(key)
+    }
+
+    fn seek_key_both(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.seek_key_both(key)
+    }
+
+    fn seek_range(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.cursor.seek_range(key)
+    }
+
+    fn count_duplicates(&mut self) -> usize {
+        self.cursor.count_duplicates()
+    }
+}
+
+impl<'txn> BackendWriteCursor for PrefixedWriteCursor<'txn> {
+    /// In databases with duplicate keys, the value is part of the raw key, so the entry is
+    /// replaced by a new one.
+    fn put_current(&mut self, value: &[u8]) {
+        let raw_key = self.cursor.position.take().expect("Cursor is not positioned at an entry");
+        let key = self.cursor.db.decode_key(&raw_key);
+        let (new_raw_key, raw_value) = self.cursor.db.encode(&key, value);
+
+        let mut overlay = self.overlay.borrow_mut();
+        if new_raw_key != raw_key {
+            overlay.insert(raw_key, None);
+        }
+        overlay.insert(new_raw_key.clone(), Some(raw_value));
+        self.cursor.position = Some(new_raw_key);
+    }
+
+    fn append(&mut self, key: &[u8], value: &[u8]) {
+        let (raw_key, raw_value) = self.cursor.db.encode(key, value);
+        self.overlay.borrow_mut().insert(raw_key.clone(), Some(raw_value));
+        self.cursor.position = Some(raw_key);
+    }
+
+    /// The cursor keeps its position, so `next` continues with the following entry.
+    fn delete_current(&mut self) {
+        if let Some(ref raw_key) = self.cursor.position {
+            self.overlay.borrow_mut().insert(raw_key.clone(), None);
+        }
+    }
+}
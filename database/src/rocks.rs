@@ -0,0 +1,234 @@
+use std::any::Any;
+use std::fmt;
+use std::fs;
+use std::iter;
+
+use parking_lot;
+use rocksdb;
+
+use crate::backend::{Backend, BackendTransaction, BackendWriteTransaction};
+use crate::encoding::{META_PREFIX, Overlay, PrefixedKeys};
+use crate::prefixed::{CommittedEntries, PrefixedDatabase, PrefixedReadTransaction, PrefixedWriteTransaction, Snapshot};
+
+use super::*;
+
+/// A RocksDB backed environment.
+///
+/// RocksDB doesn't support duplicate or integer keys, so entries are encoded as described in
+/// `PrefixedKeys`. All databases share the default column family.
+///
+/// The changes of a write transaction are written atomically on commit.
+pub struct RocksEnvironment {
+    db: rocksdb::DB,
+    path: String,
+    max_dbs: u32,
+    /// Serialises the creation of databases.
+    creation_lock: parking_lot::Mutex<()>,
+    write_lock: parking_lot::Mutex<()>,
+}
+
+impl RocksEnvironment {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(path: &str, max_dbs: u32) -> Result<Environment, rocksdb::Error> {
+        Ok(Environment::from_backend(RocksEnvironment::new_rocks_environment(path, max_dbs)?))
+    }
+
+    pub(in super) fn new_rocks_environment(path: &str, max_dbs: u32) -> Result<Self, rocksdb::Error> {
+        fs::create_dir_all(path).unwrap();
+
+        let mut options = rocksdb::Options::default();
+        options.create_if_missing(true);
+        let db = rocksdb::DB::open(&options, path)?;
+        info!("Opened RocksDB database at {}", path);
+
+        Ok(RocksEnvironment {
+            db,
+            path: path.to_string(),
+            max_dbs,
+            creation_lock: parking_lot::Mutex::new(()),
+            write_lock: parking_lot::Mutex::new(()),
+        })
+    }
+
+    fn num_databases(&self) -> u32 {
+        let mut iter = self.db.raw_iterator();
+        iter.seek(&META_PREFIX);
+        let mut count = 0;
+        while iter.valid() && iter.key().map_or(false, |key| key.starts_with(&META_PREFIX)) {
+            count += 1;
+            iter.next();
+        }
+        count
+    }
+
+    /// Writes the changes of a write transaction atomically.
+    fn apply(&self, changes: Overlay) {
+        let mut batch = rocksdb::WriteBatch::default();
+        for (raw_key, value) in changes {
+            match value {
+                Some(value) => batch.put(raw_key, value),
+                None => batch.delete(raw_key),
+            }
+        }
+        self.db.write(batch).unwrap();
+    }
+}
+
+impl Backend for RocksEnvironment {
+    fn name(&self) -> &'static str {
+        "rocksdb"
+    }
+
+    fn open_database(&self, name: &str, flags: DatabaseFlags) -> Box<dyn Any + Send + Sync> {
+        let _guard = self.creation_lock.lock();
+        let meta_key = [&META_PREFIX[..], name.as_bytes()].concat();
+
+        let id = match self.db.get(&meta_key).unwrap() {
+            Some(id) => {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&id);
+                u32::from_be_bytes(bytes)
+            },
+            None => {
+                // Ids are assigned in order, so the next id is one more than the number of databases.
+                let id = self.num_databases() + 1;
+                assert!(id <= self.max_dbs, "Maximum number of databases reached");
+                self.db.put(&meta_key, &id.to_be_bytes()).unwrap();
+                id
+            },
+        };
+
+        Box::new(PrefixedDatabase::new(id, flags))
+    }
+
+    /// RocksDB has neither pages nor a memory map. Sizes are reported in bytes, i.e. with a page
+    /// size of one, and the map size and free space are zero.
+    fn stats(&self) -> EnvironmentStats {
+        let property = |name: &str| self.db.property_int_value(name).unwrap().unwrap_or(0) as usize;
+        EnvironmentStats {
+            map_size: 0,
+            page_size: 1,
+            used_pages: property("rocksdb.total-sst-files-size") + property("rocksdb.cur-size-all-mem-tables"),
+            free_pages: 0,
+            databases: self.num_databases() as usize,
+            last_transaction_id: 0,
+            readers: property("rocksdb.num-snapshots") as u32,
+            max_readers: 0,
+        }
+    }
+
+    /// Counts the entries of the database, so this takes time linear in its size.
+    /// Like the environment stats, sizes are reported in bytes.
+    fn database_stats(&self, db: &Database) -> DatabaseStats {
+        let db: &PrefixedDatabase = db.handle();
+        let snapshot = self.db.snapshot();
+        let mut iter = snapshot.raw_iterator();
+        iter.seek(&db.start());
+
+        let end = db.end();
+        let mut entries = 0;
+        let mut size = 0;
+        while iter.valid() {
+            let (key, value) = match (iter.key(), iter.value()) {
+                (Some(key), Some(value)) if key < end.as_slice() => (key, value),
+                _ => break,
+            };
+            entries += 1;
+            size += key.len() + value.len();
+            iter.next();
+        }
+
+        DatabaseStats {
+            page_size: 1,
+            depth: 0,
+            branch_pages: 0,
+            leaf_pages: size,
+            overflow_pages: 0,
+            entries,
+        }
+    }
+
+    /// Flushes the memtables to SST files, such that the commits don't depend on the WAL.
+    fn sync(&self) -> io::Result<()> {
+        self.db.flush().map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
+    fn read_transaction<'env>(&'env self) -> Box<dyn BackendTransaction + 'env> {
+        Box::new(PrefixedReadTransaction::new(self.db.snapshot()))
+    }
+
+    fn write_transaction<'env>(&'env self) -> Box<dyn BackendWriteTransaction + 'env> {
+        // Take the snapshot after the lock, so that it contains all committed changes.
+        let guard = self.write_lock.lock();
+        Box::new(PrefixedWriteTransaction::new(self.db.snapshot(), move |changes| {
+            self.apply(changes);
+            drop(guard);
+        }))
+    }
+
+    fn drop_database(self: Box<Self>) -> io::Result<()> {
+        let path = self.path.clone();
+        drop(self);
+        fs::remove_dir_all(path)
+    }
+
+    /// RocksDB reclaims space in its own compactions.
+    fn compact(self: Box<Self>) -> io::Result<Box<dyn Backend>> {
+        Ok(self)
+    }
+}
+
+impl<'env> Snapshot for rocksdb::Snapshot<'env> {
+    fn get(&self, raw_key: &[u8]) -> Option<Vec<u8>> {
+        rocksdb::Snapshot::get(self, raw_key).unwrap().map(|value| value.to_vec())
+    }
+
+    /// Walks a single raw iterator, which stays positioned between the entries it yields.
+    fn range_forward<'a>(&'a self, raw_key: &[u8], inclusive: bool, end: &[u8]) -> CommittedEntries<'a> {
+        let mut iter = self.raw_iterator();
+        iter.seek(raw_key);
+        if !inclusive && iter.valid() && iter.key() == Some(raw_key) {
+            iter.next();
+        }
+        let end = end.to_vec();
+        Box::new(iter::from_fn(move || {
+            if !iter.valid() {
+                return None;
+            }
+            let key = iter.key().unwrap().to_vec();
+            if key >= end {
+                return None;
+            }
+            let entry = (key, iter.value().unwrap().to_vec());
+            iter.next();
+            Some(entry)
+        }))
+    }
+
+    fn range_backward<'a>(&'a self, raw_key: &[u8], inclusive: bool, start: &[u8]) -> CommittedEntries<'a> {
+        let mut iter = self.raw_iterator();
+        iter.seek_for_prev(raw_key);
+        if !inclusive && iter.valid() && iter.key() == Some(raw_key) {
+            iter.prev();
+        }
+        let start = start.to_vec();
+        Box::new(iter::from_fn(move || {
+            if !iter.valid() {
+                return None;
+            }
+            let key = iter.key().unwrap().to_vec();
+            if key < start {
+                return None;
+            }
+            let entry = (key, iter.value().unwrap().to_vec());
+            iter.prev();
+            Some(entry)
+        }))
+    }
+}
+
+impl fmt::Debug for RocksEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RocksEnvironment {{ path: {:?} }}", self.path)
+    }
+}
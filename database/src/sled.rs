@@ -0,0 +1,327 @@
+use std::any::Any;
+use std::fmt;
+use std::fs;
+use std::iter::{self, Peekable};
+use std::ops::Bound;
+use std::sync::{Arc, Weak};
+
+use parking_lot::{Mutex, RwLock};
+
+use crate::backend::{Backend, BackendTransaction, BackendWriteTransaction};
+use crate::encoding::{META_PREFIX, Overlay, PrefixedKeys};
+use crate::prefixed::{CommittedEntries, PrefixedDatabase, PrefixedReadTransaction, PrefixedWriteTransaction, seek_backward_with, seek_forward_with, Snapshot};
+
+use super::*;
+
+/// A sled backed environment. sled is written in Rust only, so this backend can be used where
+/// building LMDB's C library is a problem. LMDB is only linked if the `lmdb` feature is enabled
+/// as well, which it is by default, so such builds need `--no-default-features --features sled`.
+///
+/// All databases share sled's default tree and are encoded as described in `PrefixedKeys`.
+///
+/// The changes of a write transaction are written atomically on commit. sled has no snapshots,
+/// so read transactions keep the previous values of all entries that are committed while they
+/// are open instead.
+pub struct SledEnvironment {
+    db: ::sled::Db,
+    /// `None` if the environment is volatile, in which case sled removes it when it is dropped.
+    path: Option<String>,
+    max_dbs: u32,
+    /// Serialises the creation of databases.
+    creation_lock: Mutex<()>,
+    write_lock: Mutex<()>,
+    /// Held exclusively while a commit is applied, so that read transactions never see the new
+    /// state without the previous values they have to keep.
+    commit_lock: RwLock<()>,
+    /// The previous values kept by the open read transactions.
+    readers: Mutex<Vec<Weak<Mutex<Overlay>>>>,
+}
+
+impl SledEnvironment {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(path: &str, max_dbs: u32) -> Result<Environment, ::sled::Error> {
+        Ok(Environment::from_backend(SledEnvironment::new_sled_environment(Some(path), max_dbs)?))
+    }
+
+    /// Opens an environment that is removed when it is dropped, like a `VolatileEnvironment`.
+    pub fn new_volatile(max_dbs: u32) -> Result<Environment, ::sled::Error> {
+        Ok(Environment::from_backend(SledEnvironment::new_sled_environment(None, max_dbs)?))
+    }
+
+    pub(in super) fn new_sled_environment(path: Option<&str>, max_dbs: u32) -> Result<Self, ::sled::Error> {
+        let mut config = ::sled::Config::new();
+        match path {
+            Some(path) => {
+                fs::create_dir_all(path).unwrap();
+                config = config.path(path);
+            },
+            None => config = config.temporary(true),
+        }
+        let db = config.open()?;
+        match path {
+            Some(path) => info!("Opened sled database at {}", path),
+            None => debug!("Opened volatile sled database"),
+        }
+
+        Ok(SledEnvironment {
+            db,
+            path: path.map(str::to_string),
+            max_dbs,
+            creation_lock: Mutex::new(()),
+            write_lock: Mutex::new(()),
+            commit_lock: RwLock::new(()),
+            readers: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn num_databases(&self) -> u32 {
+        self.db.scan_prefix(&META_PREFIX).count() as u32
+    }
+
+    fn register_reader(&self) -> Arc<Mutex<Overlay>> {
+        let previous = Arc::new(Mutex::new(Overlay::new()));
+        // Register under the commit lock, so that the reader either sees a commit or keeps the
+        // values it replaced.
+        let _guard = self.commit_lock.read();
+        self.readers.lock().push(Arc::downgrade(&previous));
+        previous
+    }
+
+    /// Applies `changes` and keeps the values they replace for all open read transactions.
+    fn apply(&self, changes: Overlay) {
+        let mut batch = ::sled::Batch::default();
+        for (raw_key, value) in &changes {
+            match value {
+                Some(value) => batch.insert(raw_key.as_slice(), value.as_slice()),
+                None => batch.remove(raw_key.as_slice()),
+            }
+        }
+
+        let _guard = self.commit_lock.write();
+        let mut readers = self.readers.lock();
+        readers.retain(|reader| reader.upgrade().is_some());
+        if !readers.is_empty() {
+            let replaced: Vec<(Vec<u8>, Option<Vec<u8>>)> = changes.into_iter()
+                .map(|(raw_key, _)| {
+                    let value = self.db.get(&raw_key).unwrap().map(|value| value.to_vec());
+                    (raw_key, value)
+                })
+                .collect();
+            for reader in readers.iter().filter_map(Weak::upgrade) {
+                let mut previous = reader.lock();
+                for (raw_key, value) in &replaced {
+                    // Only the value at the start of the read transaction counts.
+                    previous.entry(raw_key.clone()).or_insert_with(|| value.clone());
+                }
+            }
+        }
+        self.db.apply_batch(batch).unwrap();
+    }
+}
+
+impl Backend for SledEnvironment {
+    fn name(&self) -> &'static str {
+        "sled"
+    }
+
+    fn open_database(&self, name: &str, flags: DatabaseFlags) -> Box<dyn Any + Send + Sync> {
+        let _guard = self.creation_lock.lock();
+        let meta_key = [&META_PREFIX[..], name.as_bytes()].concat();
+
+        let id = match self.db.get(&meta_key).unwrap() {
+            Some(id) => {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&id);
+                u32::from_be_bytes(bytes)
+            },
+            None => {
+                // Ids are assigned in order, so the next id is one more than the number of databases.
+                let id = self.num_databases() + 1;
+                assert!(id <= self.max_dbs, "Maximum number of databases reached");
+                self.db.insert(meta_key, &id.to_be_bytes()[..]).unwrap();
+                self.db.flush().unwrap();
+                id
+            },
+        };
+
+        Box::new(PrefixedDatabase::new(id, flags))
+    }
+
+    /// sled has neither pages nor a memory map. Sizes are reported in bytes, i.e. with a page
+    /// size of one, and the map size and free space are zero.
+    fn stats(&self) -> EnvironmentStats {
+        EnvironmentStats {
+            map_size: 0,
+            page_size: 1,
+            used_pages: self.db.size_on_disk().unwrap() as usize,
+            free_pages: 0,
+            databases: self.num_databases() as usize,
+            last_transaction_id: 0,
+            readers: self.readers.lock().iter().filter(|reader| reader.upgrade().is_some()).count() as u32,
+            max_readers: 0,
+        }
+    }
+
+    /// Counts the entries of the database, so this takes time linear in its size.
+    /// Like the environment stats, sizes are reported in bytes.
+    fn database_stats(&self, db: &Database) -> DatabaseStats {
+        let db: &PrefixedDatabase = db.handle();
+        let mut entries = 0;
+        let mut size = 0;
+        for entry in self.db.range(db.start()..db.end()) {
+            let (key, value) = entry.unwrap();
+            entries += 1;
+            size += key.len() + value.len();
+        }
+
+        DatabaseStats {
+            page_size: 1,
+            depth: 0,
+            branch_pages: 0,
+            leaf_pages: size,
+            overflow_pages: 0,
+            entries,
+        }
+    }
+
+    /// Flushes the dirty pages and the log to disk.
+    fn sync(&self) -> io::Result<()> {
+        self.db.flush()
+            .map(|_| ())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
+    fn read_transaction<'env>(&'env self) -> Box<dyn BackendTransaction + 'env> {
+        Box::new(PrefixedReadTransaction::new(SledSnapshot { env: self, previous: Some(self.register_reader()) }))
+    }
+
+    fn write_transaction<'env>(&'env self) -> Box<dyn BackendWriteTransaction + 'env> {
+        let guard = self.write_lock.lock();
+        // Only the write transaction itself commits, so the committed state can't change.
+        Box::new(PrefixedWriteTransaction::new(SledSnapshot { env: self, previous: None }, move |changes| {
+            self.apply(changes);
+            if self.path.is_some() {
+                self.db.flush().unwrap();
+            }
+            drop(guard);
+        }))
+    }
+
+    fn drop_database(self: Box<Self>) -> io::Result<()> {
+        let path = self.path.clone();
+        drop(self);
+        match path {
+            Some(path) => fs::remove_dir_all(path),
+            None => Ok(()),
+        }
+    }
+
+    /// sled reclaims space in its own garbage collection.
+    fn compact(self: Box<Self>) -> io::Result<Box<dyn Backend>> {
+        Ok(self)
+    }
+}
+
+/// The committed state as seen by a transaction.
+struct SledSnapshot<'env> {
+    env: &'env SledEnvironment,
+    /// The values that were replaced since a read transaction started.
+    previous: Option<Arc<Mutex<Overlay>>>,
+}
+
+impl<'env> SledSnapshot<'env> {
+    /// Returns the committed `entries` as of the start of a read transaction. The values that
+    /// commits replaced since then are merged in with `seek`, step by step, so the database is
+    /// still read in a single pass.
+    fn committed<'a, I, F>(&'a self, entries: I, raw_key: &[u8], inclusive: bool, bound: &[u8], seek: F) -> CommittedEntries<'a>
+        where I: Iterator<Item=::sled::Result<(::sled::IVec, ::sled::IVec)>> + 'a,
+              F: Fn(Option<&Overlay>, &[u8], bool, &[u8], &mut Peekable<CommittedEntries<'a>>) -> Option<(Vec<u8>, Vec<u8>)> + 'a {
+        let entries: CommittedEntries<'a> = Box::new(entries.map(|entry| {
+            let (key, value) = entry.unwrap();
+            (key.to_vec(), value.to_vec())
+        }));
+        let previous = match self.previous {
+            Some(ref previous) => previous,
+            None => return entries,
+        };
+
+        let mut entries = entries.peekable();
+        let (mut position, mut inclusive, bound) = (raw_key.to_vec(), inclusive, bound.to_vec());
+        Box::new(iter::from_fn(move || {
+            let _guard = self.env.commit_lock.read();
+            let previous = previous.lock();
+            let entry = seek(Some(&*previous), &position, inclusive, &bound, &mut entries)?;
+            position = entry.0.clone();
+            inclusive = false;
+            Some(entry)
+        }))
+    }
+}
+
+impl<'env> Snapshot for SledSnapshot<'env> {
+    fn get(&self, raw_key: &[u8]) -> Option<Vec<u8>> {
+        if let Some(ref previous) = self.previous {
+            let _guard = self.env.commit_lock.read();
+            if let Some(value) = previous.lock().get(raw_key) {
+                return value.clone();
+            }
+        }
+        self.env.db.get(raw_key).unwrap().map(|value| value.to_vec())
+    }
+
+    fn range_forward<'a>(&'a self, raw_key: &[u8], inclusive: bool, end: &[u8]) -> CommittedEntries<'a> {
+        let start = if inclusive { Bound::Included(raw_key) } else { Bound::Excluded(raw_key) };
+        let entries = self.env.db.range::<&[u8], _>((start, Bound::Excluded(end)));
+        self.committed(entries, raw_key, inclusive, end, seek_forward_with)
+    }
+
+    fn range_backward<'a>(&'a self, raw_key: &[u8], inclusive: bool, start: &[u8]) -> CommittedEntries<'a> {
+        let end = if inclusive { Bound::Included(raw_key) } else { Bound::Excluded(raw_key) };
+        let entries = self.env.db.range::<&[u8], _>((Bound::Included(start), end)).rev();
+        self.committed(entries, raw_key, inclusive, start, seek_backward_with)
+    }
+}
+
+impl fmt::Debug for SledEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SledEnvironment {{ path: {:?} }}", self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cursor::ReadCursor;
+
+    use super::*;
+
+    #[test]
+    fn read_transactions_keep_their_state() {
+        let env = SledEnvironment::new_volatile(1).unwrap();
+        {
+            let db = env.open_database_with_flags("test".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_UINT_VALUES);
+
+            let mut txw = WriteTransaction::new(&env);
+            txw.put::<str, u32>(&db, "test1", &1);
+            txw.put::<str, u32>(&db, "test1", &2);
+            txw.commit();
+
+            let tx = ReadTransaction::new(&env);
+            let mut txw = WriteTransaction::new(&env);
+            txw.remove_item::<str, u32>(&db, "test1", &1);
+            txw.put::<str, u32>(&db, "test2", &3);
+            txw.commit();
+
+            let mut cursor = tx.cursor(&db);
+            assert_eq!(cursor.first::<String, u32>(), Some(("test1".to_string(), 1)));
+            assert_eq!(cursor.next::<String, u32>(), Some(("test1".to_string(), 2)));
+            assert!(cursor.next::<String, u32>().is_none());
+
+            let tx2 = ReadTransaction::new(&env);
+            let mut cursor = tx2.cursor(&db);
+            assert_eq!(cursor.first::<String, u32>(), Some(("test1".to_string(), 2)));
+            assert_eq!(cursor.next::<String, u32>(), Some(("test2".to_string(), 3)));
+        }
+
+        env.drop_database().unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(feature = "lmdb")]
+use lmdb_zero;
+
+/// Statistics of a single database, as reported by `mdb_stat`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DatabaseStats {
+    /// Size of a page in bytes.
+    pub page_size: u32,
+    /// Depth of the B-tree.
+    pub depth: u32,
+    pub branch_pages: usize,
+    pub leaf_pages: usize,
+    pub overflow_pages: usize,
+    /// Number of key/value pairs, including duplicates.
+    pub entries: usize,
+}
+
+impl DatabaseStats {
+    /// Total number of pages used by the database.
+    pub fn pages(&self) -> usize {
+        self.branch_pages + self.leaf_pages + self.overflow_pages
+    }
+
+    /// Number of bytes used by the database.
+    pub fn size(&self) -> usize {
+        self.pages() * self.page_size as usize
+    }
+}
+
+#[cfg(feature = "lmdb")]
+impl From<lmdb_zero::Stat> for DatabaseStats {
+    fn from(stat: lmdb_zero::Stat) -> Self {
+        DatabaseStats {
+            page_size: stat.psize,
+            depth: stat.depth,
+            branch_pages: stat.branch_pages,
+            leaf_pages: stat.leaf_pages,
+            overflow_pages: stat.overflow_pages,
+            entries: stat.entries,
+        }
+    }
+}
+
+/// Statistics of an environment, combining `mdb_env_stat` and `mdb_env_info`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnvironmentStats {
+    /// Size of the memory map in bytes. This is the maximum size the environment can grow to
+    /// before it is resized.
+    pub map_size: usize,
+    /// Size of a page in bytes.
+    pub page_size: u32,
+    /// Number of pages in use, i.e. the last used page number plus one.
+    pub used_pages: usize,
+    /// Number of pages in the memory map that have not been used yet.
+    pub free_pages: usize,
+    /// Number of named databases in the environment.
+    pub databases: usize,
+    pub last_transaction_id: usize,
+    pub readers: u32,
+    pub max_readers: u32,
+}
+
+impl EnvironmentStats {
+    #[cfg(feature = "lmdb")]
+    pub(crate) fn new(stat: lmdb_zero::Stat, info: lmdb_zero::EnvInfo) -> Self {
+        let total_pages = info.mapsize / stat.psize as usize;
+        let used_pages = info.last_pgno + 1;
+        EnvironmentStats {
+            map_size: info.mapsize,
+            page_size: stat.psize,
+            used_pages,
+            free_pages: total_pages.saturating_sub(used_pages),
+            // The main database only contains the names of the other databases.
+            databases: stat.entries,
+            last_transaction_id: info.last_txnid,
+            readers: info.numreaders,
+            max_readers: info.maxreaders,
+        }
+    }
+
+    /// Number of bytes in use.
+    pub fn used_size(&self) -> usize {
+        self.used_pages * self.page_size as usize
+    }
+
+    /// Number of bytes left in the memory map.
+    pub fn free_size(&self) -> usize {
+        self.free_pages * self.page_size as usize
+    }
+}
@@ -3,7 +3,7 @@ use std::io;
 
 use nimiq_hash::Blake2bHash;
 
-use crate::{AsDatabaseBytes, FromDatabaseValue};
+use crate::{AsDatabaseBytes, FromDatabaseBytes, FromDatabaseValue};
 
 impl AsDatabaseBytes for Blake2bHash {
     fn as_database_bytes(&self) -> Cow<[u8]> {
@@ -15,4 +15,14 @@ impl FromDatabaseValue for Blake2bHash {
     fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
         Ok(bytes.into())
     }
-}
\ No newline at end of file
+}
+
+impl FromDatabaseBytes for Blake2bHash {
+    fn from_database_bytes(bytes: &[u8]) -> io::Result<&Self> {
+        if bytes.len() != Blake2bHash::SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid hash length"));
+        }
+        // `Blake2bHash` is a `repr(C)` wrapper of a byte array, so it has the same layout.
+        Ok(unsafe { &*(bytes.as_ptr() as *const Blake2bHash) })
+    }
+}
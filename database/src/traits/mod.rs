@@ -1,8 +1,9 @@
 use std::borrow::Cow;
+use std::convert::TryInto;
 use std::ffi::CStr;
 use std::io;
-
-use lmdb_zero::traits::AsLmdbBytes;
+use std::mem;
+use std::slice;
 
 #[cfg(feature = "hash")]
 mod hash;
@@ -35,6 +36,11 @@ pub trait AsDatabaseBytes {
     fn as_database_bytes(&self) -> Cow<[u8]>;
 }
 
+/// Values that can be borrowed from the bytes of a database entry without copying them.
+pub trait FromDatabaseBytes {
+    fn from_database_bytes(bytes: &[u8]) -> io::Result<&Self>;
+}
+
 // Trait implementations
 impl IntoDatabaseValue for [u8] {
     fn database_byte_size(&self) -> usize {
@@ -56,6 +62,18 @@ impl IntoDatabaseValue for str {
     }
 }
 
+impl FromDatabaseBytes for [u8] {
+    fn from_database_bytes(bytes: &[u8]) -> io::Result<&Self> {
+        Ok(bytes)
+    }
+}
+
+impl FromDatabaseBytes for str {
+    fn from_database_bytes(bytes: &[u8]) -> io::Result<&Self> {
+        std::str::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
 impl FromDatabaseValue for String {
     fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
         Ok(String::from_utf8(bytes.to_vec()).unwrap())
@@ -64,61 +82,61 @@ impl FromDatabaseValue for String {
 
 impl FromDatabaseValue for u32 {
     fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
-        let lmdb_result: Result<&lmdb_zero::Unaligned<u32>, String> = lmdb_zero::traits::FromLmdbBytes::from_lmdb_bytes(bytes);
-        Ok(lmdb_result.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?.get())
+        let bytes = bytes.try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Expected 4 bytes for a u32"))?;
+        Ok(u32::from_ne_bytes(bytes))
     }
 }
 
 impl FromDatabaseValue for u64 {
     fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
-        let lmdb_result: Result<&lmdb_zero::Unaligned<u64>, String> = lmdb_zero::traits::FromLmdbBytes::from_lmdb_bytes(bytes);
-        Ok(lmdb_result.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?.get())
+        let bytes = bytes.try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Expected 8 bytes for a u64"))?;
+        Ok(u64::from_ne_bytes(bytes))
     }
 }
 
-// Conflicting implementation:
-//impl<T> FromDatabaseValue for T
-//    where T: lmdb_zero::traits::FromLmdbBytes + ?Sized {
-//    fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
-//        Ok(lmdb_zero::traits::FromLmdbBytes::from_lmdb_bytes(bytes).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?.to_owned())
-//    }
-//}
-
-//impl<T> AsDatabaseBytes for T
-//    where T: lmdb_zero::traits::AsLmdbBytes + ?Sized {
-//    fn as_database_bytes(&self) -> Cow<[u8]> {
-//        return Cow::Borrowed(self.as_lmdb_bytes());
-//    }
-//}
-
-macro_rules! as_lmdb_bytes {
+/// Uses the in-memory representation of plain numbers and chars, i.e. native byte order, which
+/// is what the `UINT_KEYS` and `U64_KEYS` databases compare.
+macro_rules! as_native_bytes {
     ($t: ty) => {
 impl AsDatabaseBytes for $t {
     fn as_database_bytes(&self) -> Cow<[u8]> {
-        return Cow::Borrowed(self.as_lmdb_bytes());
+        // The types have no padding and every byte of them is initialized.
+        let bytes = unsafe { slice::from_raw_parts(self as *const $t as *const u8, mem::size_of_val(self)) };
+        return Cow::Borrowed(bytes);
     }
 }
     };
 }
 
-as_lmdb_bytes!(u16);
-as_lmdb_bytes!(i16);
-as_lmdb_bytes!(u32);
-as_lmdb_bytes!(i32);
-as_lmdb_bytes!(u64);
-as_lmdb_bytes!(i64);
-as_lmdb_bytes!(f32);
-as_lmdb_bytes!(f64);
-as_lmdb_bytes!(str);
-as_lmdb_bytes!(CStr);
-as_lmdb_bytes!(char);
-
-as_lmdb_bytes!([u16]);
-as_lmdb_bytes!([i16]);
-as_lmdb_bytes!([u32]);
-as_lmdb_bytes!([i32]);
-as_lmdb_bytes!([u64]);
-as_lmdb_bytes!([i64]);
-as_lmdb_bytes!([f32]);
-as_lmdb_bytes!([f64]);
-as_lmdb_bytes!([char]);
+as_native_bytes!(u16);
+as_native_bytes!(i16);
+as_native_bytes!(u32);
+as_native_bytes!(i32);
+as_native_bytes!(u64);
+as_native_bytes!(i64);
+as_native_bytes!(f32);
+as_native_bytes!(f64);
+as_native_bytes!(char);
+
+as_native_bytes!([u8]);
+as_native_bytes!([u16]);
+as_native_bytes!([i16]);
+as_native_bytes!([u32]);
+as_native_bytes!([i32]);
+as_native_bytes!([u64]);
+as_native_bytes!([i64]);
+as_native_bytes!([f32]);
+as_native_bytes!([f64]);
+as_native_bytes!([char]);
+
+impl AsDatabaseBytes for str {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        return Cow::Borrowed(self.as_bytes());
+    }
+}
+
+impl AsDatabaseBytes for CStr {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        return Cow::Borrowed(self.to_bytes_with_nul());
+    }
+}
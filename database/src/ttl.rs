@@ -0,0 +1,207 @@
+//! Databases whose entries expire after a time-to-live.
+
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{AsDatabaseBytes, Database, DatabaseFlags, Environment, FromDatabaseValue, Transaction, WriteTransaction};
+use crate::cursor::ReadCursor;
+use crate::verify::RawBytes;
+
+/// Milliseconds since the Unix epoch.
+fn now() -> u64 {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis())
+}
+
+/// A database whose values are stored together with an expiry timestamp. Expired values are
+/// never returned, and `purge_expired` removes them, e.g. from a maintenance timer.
+///
+/// Each value is prefixed with its expiry timestamp (milliseconds since the Unix epoch, big
+/// endian). An index database `<name>_expiry` maps the timestamps to the keys, so purging doesn't
+/// scan all entries. Opening a `TtlDatabase` therefore takes two of the environment's databases.
+#[derive(Debug)]
+pub struct TtlDatabase<'env> {
+    db: Database<'env>,
+    expiry_idx: Database<'env>,
+}
+
+impl<'env> TtlDatabase<'env> {
+    const EXPIRY_SIZE: usize = 8;
+
+    pub fn open(env: &'env Environment, name: String) -> Self {
+        let expiry_idx = env.open_database_with_flags(format!("{}_expiry", name), DatabaseFlags::DUPLICATE_KEYS);
+        let db = env.open_database(name);
+        TtlDatabase { db, expiry_idx }
+    }
+
+    /// Puts `value`, which expires after `ttl`.
+    pub fn put<K, V>(&self, txn: &mut WriteTransaction, key: &K, value: &V, ttl: Duration) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
+        let ttl = ttl.as_secs() * 1000 + u64::from(ttl.subsec_millis());
+        self.put_until(txn, key, value, now().saturating_add(ttl));
+    }
+
+    /// Puts `value`, which expires at `expires_at` (milliseconds since the Unix epoch).
+    pub fn put_until<K, V>(&self, txn: &mut WriteTransaction, key: &K, value: &V, expires_at: u64) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
+        // Drop the index entry of the value this one replaces. A malformed value has no expiry we
+        // could look up, so it is simply overwritten.
+        if let Ok(Some(old_expiry)) = self.expires_at(txn, key) {
+            txn.remove_item(&self.expiry_idx, &old_expiry.to_be_bytes()[..], key);
+        }
+
+        let value = value.as_database_bytes();
+        let mut bytes = Vec::with_capacity(Self::EXPIRY_SIZE + value.len());
+        bytes.extend_from_slice(&expires_at.to_be_bytes());
+        bytes.extend_from_slice(&value);
+        txn.put(&self.db, key, &bytes[..]);
+        txn.put(&self.expiry_idx, &expires_at.to_be_bytes()[..], key);
+    }
+
+    /// Returns the value of `key`, unless it has expired. Fails if the stored value is malformed.
+    pub fn get<K, V>(&self, txn: &Transaction, key: &K) -> io::Result<Option<V>> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
+        let bytes = match txn.get_cow(&self.db, key) {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        if Self::decode_expiry(&bytes)? <= now() {
+            return Ok(None);
+        }
+        V::copy_from_database(&bytes[Self::EXPIRY_SIZE..]).map(Some)
+    }
+
+    /// Returns when the value of `key` expires, even if it already has.
+    pub fn expires_at<K>(&self, txn: &Transaction, key: &K) -> io::Result<Option<u64>> where K: AsDatabaseBytes + ?Sized {
+        match txn.get_cow(&self.db, key) {
+            Some(bytes) => Self::decode_expiry(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the keys of all values that haven't expired yet, together with their expiry, in
+    /// the order in which they expire.
+    pub fn unexpired<K>(&self, txn: &Transaction) -> io::Result<Vec<(K, u64)>> where K: FromDatabaseValue {
+        let mut cursor = txn.cursor(&self.expiry_idx);
+        let mut entries = Vec::new();
+        for (RawBytes(expiry), key) in cursor.iter_from::<[u8], RawBytes, K>(&now().saturating_add(1).to_be_bytes()[..]) {
+            entries.push((key, Self::decode_expiry(&expiry)?));
+        }
+        Ok(entries)
+    }
+
+    /// Removes the value of `key`. Malformed values, for which `get` fails, can be removed as well.
+    pub fn remove<K>(&self, txn: &mut WriteTransaction, key: &K) where K: AsDatabaseBytes + ?Sized {
+        if let Ok(Some(expiry)) = self.expires_at(txn, key) {
+            txn.remove_item(&self.expiry_idx, &expiry.to_be_bytes()[..], key);
+        }
+        txn.remove(&self.db, key);
+    }
+
+    /// Removes all expired values and returns their number.
+    pub fn purge_expired(&self, txn: &mut WriteTransaction) -> usize {
+        let now = now();
+        let mut expired = Vec::new();
+        {
+            let mut cursor = txn.cursor(&self.expiry_idx);
+            let mut entry = cursor.first::<RawBytes, RawBytes>();
+            while let Some((RawBytes(expiry), RawBytes(key))) = entry {
+                // Malformed index keys sort before all valid ones.
+                if Self::decode_expiry(&expiry).map_or(false, |expiry| expiry > now) {
+                    break;
+                }
+                expired.push((expiry, key));
+                entry = cursor.next::<RawBytes, RawBytes>();
+            }
+        }
+
+        for (expiry, key) in expired.iter() {
+            txn.remove_item(&self.expiry_idx, &expiry[..], &key[..]);
+            txn.remove(&self.db, &key[..]);
+        }
+        expired.len()
+    }
+
+    pub fn database(&self) -> &Database<'env> {
+        &self.db
+    }
+
+    fn decode_expiry(bytes: &[u8]) -> io::Result<u64> {
+        if bytes.len() < Self::EXPIRY_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Value is too short to contain an expiry"));
+        }
+        let mut expiry = [0u8; Self::EXPIRY_SIZE];
+        expiry.copy_from_slice(&bytes[..Self::EXPIRY_SIZE]);
+        Ok(u64::from_be_bytes(expiry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ReadTransaction;
+    use crate::volatile::VolatileEnvironment;
+
+    use super::*;
+
+    #[test]
+    fn it_hides_and_purges_expired_values() {
+        let env = VolatileEnvironment::new(2).unwrap();
+        let db = TtlDatabase::open(&env, "bans".to_string());
+
+        let mut txn = WriteTransaction::new(&env);
+        db.put(&mut txn, "expired", "1", Duration::from_secs(0));
+        db.put(&mut txn, "valid", "2", Duration::from_secs(3600));
+        // Replacing a value moves it in the expiry index.
+        db.put(&mut txn, "renewed", "3", Duration::from_secs(0));
+        db.put(&mut txn, "renewed", "4", Duration::from_secs(3600));
+        txn.commit();
+
+        let txn = ReadTransaction::new(&env);
+        assert_eq!(db.get::<str, String>(&txn, "expired").unwrap(), None);
+        assert!(db.expires_at(&txn, "expired").unwrap().is_some());
+        let unexpired = db.unexpired::<String>(&txn).unwrap();
+        assert_eq!(unexpired.len(), 2);
+        assert!(unexpired.iter().any(|(key, _)| key == "valid"));
+        assert!(unexpired.iter().any(|(key, _)| key == "renewed"));
+        assert_eq!(db.get::<str, String>(&txn, "valid").unwrap(), Some("2".to_string()));
+        assert_eq!(db.get::<str, String>(&txn, "renewed").unwrap(), Some("4".to_string()));
+        drop(txn);
+
+        let mut txn = WriteTransaction::new(&env);
+        assert_eq!(db.purge_expired(&mut txn), 1);
+        assert_eq!(db.purge_expired(&mut txn), 0);
+        txn.commit();
+
+        let txn = ReadTransaction::new(&env);
+        assert_eq!(db.expires_at(&txn, "expired").unwrap(), None);
+        assert_eq!(db.get::<str, String>(&txn, "valid").unwrap(), Some("2".to_string()));
+        assert_eq!(db.get::<str, String>(&txn, "renewed").unwrap(), Some("4".to_string()));
+    }
+
+    #[test]
+    fn it_rejects_values_without_an_expiry() {
+        let env = VolatileEnvironment::new(2).unwrap();
+        let db = TtlDatabase::open(&env, "bans".to_string());
+
+        let mut txn = WriteTransaction::new(&env);
+        txn.put(db.database(), "short", "1234");
+        txn.commit();
+
+        let txn = ReadTransaction::new(&env);
+        assert!(db.get::<str, String>(&txn, "short").is_err());
+        assert!(db.expires_at(&txn, "short").is_err());
+        drop(txn);
+
+        // Malformed values can still be replaced and removed.
+        let mut txn = WriteTransaction::new(&env);
+        db.put(&mut txn, "short", "1", Duration::from_secs(3600));
+        txn.commit();
+        let txn = ReadTransaction::new(&env);
+        assert_eq!(db.get::<str, String>(&txn, "short").unwrap(), Some("1".to_string()));
+        drop(txn);
+
+        let mut txn = WriteTransaction::new(&env);
+        txn.put(db.database(), "short", "1234");
+        db.remove(&mut txn, "short");
+        txn.commit();
+        let txn = ReadTransaction::new(&env);
+        assert_eq!(db.get::<str, String>(&txn, "short").unwrap(), None);
+    }
+}
@@ -0,0 +1,100 @@
+//! Databases whose key and value types are fixed when they are opened.
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use crate::{AsDatabaseBytes, Database, DatabaseFlags, Environment, FromDatabaseValue, IntoDatabaseValue, Transaction, WriteTransaction};
+
+/// A `Database` that only stores values of type `V` under keys of type `K`. Reads and writes
+/// don't need type annotations, and call sites can't disagree on the encoding of an entry.
+///
+/// Values are written with `put` if they implement `AsDatabaseBytes` and with `put_reserve` if
+/// they implement `IntoDatabaseValue`, like on `WriteTransaction`. Cursors and other untyped
+/// operations work on `database()`.
+#[derive(Debug)]
+pub struct TypedDatabase<'env, K: ?Sized, V> {
+    db: Database<'env>,
+    types: PhantomData<fn(&K) -> V>,
+}
+
+impl<'env, K: AsDatabaseBytes + ?Sized, V> TypedDatabase<'env, K, V> {
+    pub fn open(env: &'env Environment, name: String) -> Self {
+        Self::from_database(env.open_database(name))
+    }
+
+    pub fn open_with_flags(env: &'env Environment, name: String, flags: DatabaseFlags) -> Self {
+        Self::from_database(env.open_database_with_flags(name, flags))
+    }
+
+    /// Wraps a database that was opened already. Its entries must have been written with the
+    /// encodings of `K` and `V`.
+    pub fn from_database(db: Database<'env>) -> Self {
+        TypedDatabase { db, types: PhantomData }
+    }
+
+    pub fn get(&self, txn: &Transaction, key: &K) -> Option<V> where V: FromDatabaseValue {
+        txn.get(&self.db, key)
+    }
+
+    /// Returns the serialized value of `key` without decoding it.
+    pub fn get_cow<'txn>(&self, txn: &'txn Transaction, key: &K) -> Option<Cow<'txn, [u8]>> {
+        txn.get_cow(&self.db, key)
+    }
+
+    pub fn contains(&self, txn: &Transaction, key: &K) -> bool {
+        txn.get_cow(&self.db, key).is_some()
+    }
+
+    pub fn put(&self, txn: &mut WriteTransaction, key: &K, value: &V) where V: AsDatabaseBytes {
+        txn.put(&self.db, key, value);
+    }
+
+    pub fn put_reserve(&self, txn: &mut WriteTransaction, key: &K, value: &V) where V: IntoDatabaseValue {
+        txn.put_reserve(&self.db, key, value);
+    }
+
+    pub fn remove(&self, txn: &mut WriteTransaction, key: &K) {
+        txn.remove(&self.db, key);
+    }
+
+    /// Removes a single value of `key`, for databases with `DatabaseFlags::DUPLICATE_KEYS`.
+    pub fn remove_item(&self, txn: &mut WriteTransaction, key: &K, value: &V) where V: AsDatabaseBytes {
+        txn.remove_item(&self.db, key, value);
+    }
+
+    pub fn clear(&self, txn: &mut WriteTransaction) {
+        txn.clear_database(&self.db);
+    }
+
+    pub fn database(&self) -> &Database<'env> {
+        &self.db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ReadTransaction;
+    use crate::volatile::VolatileEnvironment;
+
+    use super::*;
+
+    #[test]
+    fn it_reads_and_writes_without_annotations() {
+        let env = VolatileEnvironment::new(2).unwrap();
+        let balances: TypedDatabase<str, u64> = TypedDatabase::open(&env, "balances".to_string());
+        let heights: TypedDatabase<u32, u64> = TypedDatabase::open_with_flags(&env, "heights".to_string(), DatabaseFlags::UINT_KEYS);
+
+        let mut txn = WriteTransaction::new(&env);
+        balances.put(&mut txn, "alice", &42);
+        heights.put(&mut txn, &1, &100);
+        heights.put(&mut txn, &2, &200);
+        heights.remove(&mut txn, &2);
+        txn.commit();
+
+        let txn = ReadTransaction::new(&env);
+        assert_eq!(balances.get(&txn, "alice"), Some(42));
+        assert_eq!(balances.get(&txn, "bob"), None);
+        assert_eq!(heights.get(&txn, &1), Some(100));
+        assert!(!heights.contains(&txn, &2));
+    }
+}
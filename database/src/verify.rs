@@ -0,0 +1,226 @@
+//! Offline checks of the entries of databases, e.g. after an unclean shutdown.
+//!
+//! This module only knows raw entries. The crates owning the databases decide what a valid
+//! entry is, report issues through a `Verifier` and optionally `prune` them.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+
+use crate::{AsDatabaseBytes, Database, DatabaseFlags, Environment, FromDatabaseValue, Transaction, WriteTransaction};
+use crate::cursor::ReadCursor;
+
+/// The raw bytes of a key or value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawBytes(pub Vec<u8>);
+
+impl FromDatabaseValue for RawBytes {
+    fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
+        Ok(RawBytes(bytes.to_vec()))
+    }
+}
+
+impl AsDatabaseBytes for RawBytes {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(&self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The key can't be decoded.
+    InvalidKey,
+    /// The value can't be decoded.
+    InvalidValue,
+    /// The entry refers to an entry that doesn't exist.
+    Orphaned,
+    /// The entry contradicts other entries.
+    Inconsistent,
+}
+
+impl IssueKind {
+    /// Whether the entry can be removed without losing valid data. Only orphaned entries can,
+    /// entries that can't be decoded might still be the only copy of their data.
+    pub fn is_prunable(self) -> bool {
+        self == IssueKind::Orphaned
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    pub database: String,
+    pub key: Vec<u8>,
+    /// The value, for databases with duplicate keys.
+    pub value: Option<Vec<u8>>,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} in {} at key {}: {}", self.kind, self.database, hex(&self.key), self.message)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Number of checked entries.
+    pub checked: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn prunable(&self) -> impl Iterator<Item=&Issue> {
+        self.issues.iter().filter(|issue| issue.kind.is_prunable())
+    }
+}
+
+/// Collects the issues of the databases checked in one transaction.
+pub struct Verifier<'txn, 'env> {
+    txn: &'txn Transaction<'env>,
+    report: Report,
+}
+
+impl<'txn, 'env> Verifier<'txn, 'env> {
+    pub fn new(txn: &'txn Transaction<'env>) -> Self {
+        Verifier {
+            txn,
+            report: Report::default(),
+        }
+    }
+
+    pub fn txn(&self) -> &'txn Transaction<'env> {
+        self.txn
+    }
+
+    /// Calls `check` with the key and value of each entry of `db` and records the issues it
+    /// returns.
+    pub fn check_entries<F>(&mut self, db: &Database<'env>, mut check: F)
+        where F: FnMut(&[u8], &[u8]) -> Result<(), (IssueKind, String)>
+    {
+        let duplicates = db.flags().contains(DatabaseFlags::DUPLICATE_KEYS);
+        let mut cursor = self.txn.cursor(db);
+        let mut entry = cursor.first::<RawBytes, RawBytes>();
+        while let Some((RawBytes(key), RawBytes(value))) = entry {
+            self.report.checked += 1;
+            if let Err((kind, message)) = check(&key, &value) {
+                self.report.issues.push(Issue {
+                    database: db.name().to_string(),
+                    key,
+                    value: if duplicates { Some(value) } else { None },
+                    kind,
+                    message,
+                });
+            }
+            entry = cursor.next::<RawBytes, RawBytes>();
+        }
+    }
+
+    /// Records an issue that isn't tied to a single entry, e.g. a missing entry.
+    pub fn report(&mut self, database: &str, key: &[u8], kind: IssueKind, message: String) {
+        self.report.issues.push(Issue {
+            database: database.to_string(),
+            key: key.to_vec(),
+            value: None,
+            kind,
+            message,
+        });
+    }
+
+    pub fn finish(self) -> Report {
+        self.report
+    }
+}
+
+/// Checks that `bytes` is a `T` of exactly `len` bytes.
+pub fn decode_fixed<T: FromDatabaseValue>(bytes: &[u8], len: usize) -> Result<T, String> {
+    if bytes.len() != len {
+        return Err(format!("Expected {} bytes, got {}", len, bytes.len()));
+    }
+    T::copy_from_database(bytes).map_err(|e| e.to_string())
+}
+
+/// Decodes `bytes` as `T`.
+pub fn decode<T: FromDatabaseValue>(bytes: &[u8]) -> Result<T, String> {
+    T::copy_from_database(bytes).map_err(|e| e.to_string())
+}
+
+/// Removes the entries of the prunable issues of `report` from `databases` in a single write
+/// transaction and returns the number of removed entries. Issues in other databases are skipped,
+/// so only databases whose entries can be rebuilt should be passed.
+pub fn prune(env: &Environment, databases: &[&Database], report: &Report) -> usize {
+    let mut txn = WriteTransaction::new(env);
+    let mut pruned = 0;
+    for issue in report.prunable() {
+        let db = match databases.iter().find(|db| db.name() == issue.database) {
+            Some(db) => db,
+            None => continue,
+        };
+        let key = RawBytes(issue.key.clone());
+        match issue.value {
+            Some(ref value) => txn.remove_item(db, &key, &RawBytes(value.clone())),
+            None => txn.remove(db, &key),
+        }
+        pruned += 1;
+    }
+    txn.commit();
+    pruned
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ReadTransaction;
+    use crate::volatile::VolatileEnvironment;
+
+    use super::*;
+
+    #[test]
+    fn it_reports_and_prunes_invalid_entries() {
+        let env = VolatileEnvironment::new(2).unwrap();
+        let db = env.open_database("values".to_string());
+        let idx = env.open_database_with_flags("index".to_string(), DatabaseFlags::DUPLICATE_KEYS);
+
+        let mut txn = WriteTransaction::new(&env);
+        txn.put(&db, "a", &1u32);
+        txn.put(&db, "b", "x");
+        txn.put(&idx, &1u32, "a");
+        txn.put(&idx, &1u32, "c");
+        txn.commit();
+
+        let txn = ReadTransaction::new(&env);
+        let mut verifier = Verifier::new(&txn);
+        verifier.check_entries(&db, |_, value| {
+            decode_fixed::<u32>(value, 4).map(|_| ()).map_err(|e| (IssueKind::InvalidValue, e))
+        });
+        verifier.check_entries(&idx, |_, value| {
+            match txn.get::<RawBytes, RawBytes>(&db, &RawBytes(value.to_vec())) {
+                Some(_) => Ok(()),
+                None => Err((IssueKind::Orphaned, "Missing value".to_string())),
+            }
+        });
+        let report = verifier.finish();
+        drop(txn);
+
+        assert_eq!(report.checked, 4);
+        assert_eq!(report.issues.len(), 2);
+        assert_eq!(report.issues[0].key, b"b".to_vec());
+        assert_eq!(report.issues[1].value, Some(b"c".to_vec()));
+
+        // Only the orphaned index entry is removed, the invalid value is only reported.
+        assert_eq!(prune(&env, &[&db, &idx], &report), 1);
+        let txn = ReadTransaction::new(&env);
+        assert_eq!(txn.get::<str, RawBytes>(&db, "b"), Some(RawBytes(b"x".to_vec())));
+        assert_eq!(txn.get::<u32, RawBytes>(&idx, &1u32), Some(RawBytes(b"a".to_vec())));
+        let mut cursor = txn.cursor(&idx);
+        assert_eq!(cursor.seek_key::<u32, RawBytes>(&1u32), Some(RawBytes(b"a".to_vec())));
+        assert_eq!(cursor.count_duplicates(), 1);
+    }
+}
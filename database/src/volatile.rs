@@ -1,296 +1,217 @@
+use std::any::Any;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
 use std::io;
+use std::ops::Bound;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use tempdir::TempDir;
+use parking_lot::{Mutex, RwLock};
 
-use crate::cursor::{ReadCursor, WriteCursor as WriteCursorTrait};
+use crate::backend::{Backend, BackendTransaction, BackendWriteTransaction};
+use crate::encoding::{META_PREFIX, Overlay, PrefixedKeys};
+use crate::prefixed::{CommittedEntries, PrefixedDatabase, PrefixedReadTransaction, PrefixedWriteTransaction, Snapshot};
 
 use super::*;
-use super::lmdb::*;
 
-#[derive(Debug)]
+/// The committed entries of all databases in an environment.
+type Entries = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// An environment that only lives in memory.
+///
+/// All databases share one ordered map and are encoded as described in `PrefixedKeys`, so
+/// keys and duplicate values are sorted exactly like LMDB sorts them.
+///
+/// Read transactions keep the map they started with, so a commit only copies the map if a read
+/// transaction still uses the previous state.
 pub struct VolatileEnvironment {
-    temp_dir: TempDir,
-    env: LmdbEnvironment,
+    entries: RwLock<Arc<Entries>>,
+    max_dbs: u32,
+    /// Serialises the creation of databases.
+    creation_lock: Mutex<()>,
+    write_lock: Mutex<()>,
+    last_transaction_id: AtomicUsize,
 }
 
+/// Creating a volatile environment doesn't fail anymore since it doesn't use LMDB. The error is
+/// kept, so that callers don't have to change.
 #[derive(Debug)]
 pub enum VolatileDatabaseError {
     IoError(io::Error),
-    LmdbError(lmdb_zero::Error),
 }
 
-
 impl fmt::Display for VolatileDatabaseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             VolatileDatabaseError::IoError(e) => e.fmt(f),
-            VolatileDatabaseError::LmdbError(e) => e.fmt(f)
         }
     }
 }
 
 impl Error for VolatileDatabaseError {
-    fn description(&self) -> &str {
-        match self {
-            VolatileDatabaseError::IoError(e) => e.description(),
-            VolatileDatabaseError::LmdbError(e) => e.description()
-        }
-    }
-
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             VolatileDatabaseError::IoError(e) => Some(e),
-            VolatileDatabaseError::LmdbError(e) => Some(e)
         }
     }
 }
 
-
 impl VolatileEnvironment {
     #[allow(clippy::new_ret_no_self)]
     pub fn new(max_dbs: u32) -> Result<Environment, VolatileDatabaseError> {
-        let temp_dir = TempDir::new("volatile-core").map_err(VolatileDatabaseError::IoError)?;
-        let path = temp_dir.path().to_str().ok_or_else(|| VolatileDatabaseError::IoError(io::Error::new(io::ErrorKind::InvalidInput, "Path cannot be converted into a string.")))?.to_string();
-        Ok(Environment::Volatile(VolatileEnvironment {
-            temp_dir,
-            env: LmdbEnvironment::new_lmdb_environment(&path, 0, max_dbs, open::NOSYNC | open::WRITEMAP).map_err(VolatileDatabaseError::LmdbError)?,
-        }))
-    }
-
-    pub fn new_with_lmdb_flags(max_dbs: u32, flags: open::Flags) -> Result<Environment, VolatileDatabaseError> {
-        let temp_dir = TempDir::new("volatile-core").map_err(VolatileDatabaseError::IoError)?;
-        let path = temp_dir.path().to_str().ok_or_else(|| VolatileDatabaseError::IoError(io::Error::new(io::ErrorKind::InvalidInput, "Path cannot be converted into a string.")))?.to_string();
-        Ok(Environment::Volatile(VolatileEnvironment {
-            temp_dir,
-            env: LmdbEnvironment::new_lmdb_environment(&path, 0, max_dbs, flags | open::NOSYNC | open::WRITEMAP).map_err(VolatileDatabaseError::LmdbError)?,
+        Ok(Environment::from_backend(VolatileEnvironment {
+            entries: RwLock::new(Arc::new(Entries::new())),
+            max_dbs,
+            creation_lock: Mutex::new(()),
+            write_lock: Mutex::new(()),
+            last_transaction_id: AtomicUsize::new(0),
         }))
     }
 
-    pub(in super) fn open_database(&self, name: String, flags: DatabaseFlags) -> VolatileDatabase {
-        VolatileDatabase(self.env.open_database(name, flags))
+    fn snapshot(&self) -> Arc<Entries> {
+        Arc::clone(&self.entries.read())
     }
 
-    pub(in super) fn drop_database(self) -> io::Result<()> {
-        Ok(())
-    }
-}
-
-#[derive(Debug)]
-pub struct VolatileDatabase<'env>(LmdbDatabase<'env>);
-
-impl<'env> VolatileDatabase<'env> {
-    pub(in super) fn as_lmdb<'a>(&'a self) -> &'a LmdbDatabase<'env> { &self.0 }
-}
-
-
-#[derive(Debug)]
-pub struct VolatileReadTransaction<'env>(LmdbReadTransaction<'env>);
-
-impl<'env> VolatileReadTransaction<'env> {
-    pub(in super) fn new(env: &'env VolatileEnvironment) -> Self {
-        VolatileReadTransaction(LmdbReadTransaction::new(&env.env))
-    }
-
-    pub(in super) fn get<K, V>(&self, db: &VolatileDatabase, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        self.0.get(&db.0, key)
-    }
-
-    pub(in super) fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> VolatileCursor<'txn, 'db> {
-        VolatileCursor(self.0.cursor(db))
+    /// Applies the changes of a write transaction.
+    fn apply(&self, changes: Overlay) {
+        let mut entries = self.entries.write();
+        let entries = Arc::make_mut(&mut entries);
+        for (raw_key, value) in changes {
+            match value {
+                Some(value) => entries.insert(raw_key, value),
+                None => entries.remove(&raw_key),
+            };
+        }
+        self.last_transaction_id.fetch_add(1, Ordering::Relaxed);
     }
 }
 
-#[derive(Debug)]
-pub struct VolatileWriteTransaction<'env>(LmdbWriteTransaction<'env>);
-
-impl<'env> VolatileWriteTransaction<'env> {
-    #[allow(clippy::new_ret_no_self)]
-    pub(in super) fn new(env: &'env VolatileEnvironment) -> Self {
-        VolatileWriteTransaction(LmdbWriteTransaction::new(&env.env))
-    }
-
-    pub(in super) fn get<K, V>(&self, db: &VolatileDatabase, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        self.0.get(&db.0, key)
+impl Backend for VolatileEnvironment {
+    fn name(&self) -> &'static str {
+        "volatile"
+    }
+
+    fn open_database(&self, name: &str, flags: DatabaseFlags) -> Box<dyn Any + Send + Sync> {
+        let _guard = self.creation_lock.lock();
+        let meta_key = [&META_PREFIX[..], name.as_bytes()].concat();
+
+        let existing = self.entries.read().get(&meta_key).cloned();
+        let id = match existing {
+            Some(id) => {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&id);
+                u32::from_be_bytes(bytes)
+            },
+            None => {
+                let mut entries = self.entries.write();
+                // Ids are assigned in order, so the next id is one more than the number of databases.
+                let id = num_databases(&entries) + 1;
+                assert!(id <= self.max_dbs, "Maximum number of databases reached");
+                Arc::make_mut(&mut entries).insert(meta_key, id.to_be_bytes().to_vec());
+                id
+            },
+        };
+
+        Box::new(PrefixedDatabase::new(id, flags))
+    }
+
+    /// Nothing is mapped, so sizes are reported in bytes, i.e. with a page size of one, and the
+    /// map size is the size of all entries.
+    fn stats(&self) -> EnvironmentStats {
+        let entries = self.entries.read();
+        let size = entries.iter().map(|(key, value)| key.len() + value.len()).sum();
+        EnvironmentStats {
+            map_size: size,
+            page_size: 1,
+            used_pages: size,
+            free_pages: 0,
+            databases: num_databases(&entries) as usize,
+            last_transaction_id: self.last_transaction_id.load(Ordering::Relaxed),
+            // Every transaction holds a reference to the entries it started with.
+            readers: (Arc::strong_count(&entries) - 1) as u32,
+            max_readers: 0,
+        }
     }
 
-    pub(in super) fn put_reserve<K, V>(&mut self, db: &VolatileDatabase, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: IntoDatabaseValue + ?Sized {
-        self.0.put_reserve(&db.0, key, value)
-    }
+    /// Counts the entries of the database, so this takes time linear in its size.
+    /// Like the environment stats, sizes are reported in bytes.
+    fn database_stats(&self, db: &Database) -> DatabaseStats {
+        let db: &PrefixedDatabase = db.handle();
+        let entries = self.snapshot();
+        let mut count = 0;
+        let mut size = 0;
+        for (key, value) in entries.range(db.start()..db.end()) {
+            count += 1;
+            size += key.len() + value.len();
+        }
 
-    pub(in super) fn put<K, V>(&mut self, db: &VolatileDatabase, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        self.0.put(&db.0, key, value)
+        DatabaseStats {
+            page_size: 1,
+            depth: 0,
+            branch_pages: 0,
+            leaf_pages: size,
+            overflow_pages: 0,
+            entries: count,
+        }
     }
 
-    pub(in super) fn remove<K>(&mut self, db: &VolatileDatabase, key: &K) where K: AsDatabaseBytes + ?Sized {
-        self.0.remove(&db.0, key)
+    /// Nothing is persisted, so there is nothing to flush.
+    fn sync(&self) -> io::Result<()> {
+        Ok(())
     }
 
-    pub(in super) fn remove_item<K, V>(&mut self, db: &VolatileDatabase, key: &K, value: &V) where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        self.0.remove_item(&db.0, key, value)
+    fn read_transaction<'env>(&'env self) -> Box<dyn BackendTransaction + 'env> {
+        Box::new(PrefixedReadTransaction::new(self.snapshot()))
     }
 
-    pub(in super) fn commit(self) {
-        self.0.commit()
+    fn write_transaction<'env>(&'env self) -> Box<dyn BackendWriteTransaction + 'env> {
+        // Take the snapshot after the lock, so that it contains all committed changes.
+        let guard = self.write_lock.lock();
+        Box::new(PrefixedWriteTransaction::new(self.snapshot(), move |changes| {
+            self.apply(changes);
+            drop(guard);
+        }))
     }
 
-    pub(in super) fn cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> VolatileCursor<'txn, 'db> {
-        VolatileCursor(self.0.cursor(db))
+    fn drop_database(self: Box<Self>) -> io::Result<()> {
+        Ok(())
     }
 
-    pub(in super) fn write_cursor<'txn, 'db>(&'txn self, db: &'db Database<'env>) -> VolatileWriteCursor<'txn, 'db> {
-        VolatileWriteCursor(self.0.write_cursor(db))
+    /// Nothing is stored on disk, so there is nothing to reclaim.
+    fn compact(self: Box<Self>) -> io::Result<Box<dyn Backend>> {
+        Ok(self)
     }
 }
 
-pub struct VolatileCursor<'txn, 'db>(LmdbCursor<'txn, 'db>);
-
-impl<'txn, 'db> ReadCursor for VolatileCursor<'txn, 'db> {
-    fn first<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.first()
-    }
-
-    fn first_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-        self.0.first_duplicate()
-    }
-
-    fn last<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.last()
-    }
-
-    fn last_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-        self.0.last_duplicate()
-    }
-
-    fn seek_key_value<K, V>(&mut self, key: &K, value: &V) -> bool where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        self.0.seek_key_value(key, value)
-    }
-
-    fn seek_key_nearest_value<K, V>(&mut self, key: &K, value: &V) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + FromDatabaseValue {
-        self.0.seek_key_nearest_value(key, value)
-    }
-
-    fn get_current<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.get_current()
-    }
-
-    fn next<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.next()
-    }
-
-    fn next_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.next_duplicate()
-    }
-
-    fn next_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.next_no_duplicate()
+impl Snapshot for Arc<Entries> {
+    fn get(&self, raw_key: &[u8]) -> Option<Vec<u8>> {
+        Entries::get(self, raw_key).cloned()
     }
 
-    fn prev<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.prev()
+    fn range_forward<'a>(&'a self, raw_key: &[u8], inclusive: bool, end: &[u8]) -> CommittedEntries<'a> {
+        let start = if inclusive { Bound::Included(raw_key) } else { Bound::Excluded(raw_key) };
+        Box::new(self.range::<[u8], _>((start, Bound::Excluded(end)))
+            .map(|(key, value)| (key.clone(), value.clone())))
     }
 
-    fn prev_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.prev_duplicate()
-    }
-
-    fn prev_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.prev_no_duplicate()
-    }
-
-    fn seek_key<K, V>(&mut self, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        self.0.seek_key(key)
-    }
-
-    fn seek_key_both<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        self.0.seek_key_both(key)
-    }
-
-    fn seek_range_key<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        self.0.seek_range_key(key)
-    }
-
-    fn count_duplicates(&mut self) -> usize {
-        self.0.count_duplicates()
+    fn range_backward<'a>(&'a self, raw_key: &[u8], inclusive: bool, start: &[u8]) -> CommittedEntries<'a> {
+        let end = if inclusive { Bound::Included(raw_key) } else { Bound::Excluded(raw_key) };
+        Box::new(self.range::<[u8], _>((Bound::Included(start), end))
+            .rev()
+            .map(|(key, value)| (key.clone(), value.clone())))
     }
 }
 
-pub struct VolatileWriteCursor<'txn, 'db>(LmdbWriteCursor<'txn, 'db>);
-
-impl<'txn, 'db> ReadCursor for VolatileWriteCursor<'txn, 'db> {
-    fn first<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.first()
-    }
-
-    fn first_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-        self.0.first_duplicate()
-    }
-
-    fn last<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.last()
-    }
-
-    fn last_duplicate<V>(&mut self) -> Option<(V)> where V: FromDatabaseValue {
-        self.0.last_duplicate()
-    }
-
-    fn seek_key_value<K, V>(&mut self, key: &K, value: &V) -> bool where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + ?Sized {
-        self.0.seek_key_value(key, value)
-    }
-
-    fn seek_key_nearest_value<K, V>(&mut self, key: &K, value: &V) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: AsDatabaseBytes + FromDatabaseValue {
-        self.0.seek_key_nearest_value(key, value)
-    }
-
-    fn get_current<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.get_current()
-    }
-
-    fn next<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.next()
-    }
-
-    fn next_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.next_duplicate()
-    }
-
-    fn next_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.next_no_duplicate()
-    }
-
-    fn prev<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.prev()
-    }
-
-    fn prev_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.prev_duplicate()
-    }
-
-    fn prev_no_duplicate<K, V>(&mut self) -> Option<(K, V)> where K: FromDatabaseValue, V: FromDatabaseValue {
-        self.0.prev_no_duplicate()
-    }
-
-    fn seek_key<K, V>(&mut self, key: &K) -> Option<V> where K: AsDatabaseBytes + ?Sized, V: FromDatabaseValue {
-        self.0.seek_key(key)
-    }
-
-    fn seek_key_both<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        self.0.seek_key_both(key)
-    }
-
-    fn seek_range_key<K, V>(&mut self, key: &K) -> Option<(K, V)> where K: AsDatabaseBytes + FromDatabaseValue, V: FromDatabaseValue {
-        self.0.seek_range_key(key)
-    }
-
-    fn count_duplicates(&mut self) -> usize {
-        self.0.count_duplicates()
-    }
+fn num_databases(entries: &Entries) -> u32 {
+    entries.range(META_PREFIX.to_vec()..)
+        .take_while(|(key, _)| key.starts_with(&META_PREFIX))
+        .count() as u32
 }
 
-impl<'txn, 'db> WriteCursorTrait for VolatileWriteCursor<'txn, 'db> {
-    fn remove(&mut self) {
-        self.0.remove()
+impl fmt::Debug for VolatileEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VolatileEnvironment {{ entries: {} }}", self.entries.read().len())
     }
 }
 
@@ -299,198 +220,192 @@ mod tests {
     use super::*;
 
     #[test]
-    fn it_can_save_basic_objects() {
+    fn stats_test() {
         let env = VolatileEnvironment::new(1).unwrap();
         {
             let db = env.open_database("test".to_string());
+            assert_eq!(db.stats().entries, 0);
 
-            // Read non-existent value.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert!(tx.get::<str, String>(&db, "test").is_none());
-            }
-
-            // Read non-existent value.
-            let mut tx = WriteTransaction::new(&env);
-            assert!(tx.get::<str, String>(&db, "test").is_none());
-
-            // Write and read value.
-            tx.put_reserve(&db, "test", "one");
-            assert_eq!(tx.get::<str, String>(&db, "test"), Some("one".to_string()));
-            // Overwrite and read value.
-            tx.put_reserve(&db, "test", "two");
-            assert_eq!(tx.get::<str, String>(&db, "test"), Some("two".to_string()));
-            tx.commit();
-
-            // Read value.
-            let tx = ReadTransaction::new(&env);
-            assert_eq!(tx.get::<str, String>(&db, "test"), Some("two".to_string()));
-            tx.close();
-
-            // Remove value.
-            let mut tx = WriteTransaction::new(&env);
-            tx.remove(&db, "test");
-            assert!(tx.get::<str, String>(&db, "test").is_none());
-            tx.commit();
-
-            // Check removal.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert!(tx.get::<str, String>(&db, "test").is_none());
+            let mut txw = WriteTransaction::new(&env);
+            for i in 0u32..100 {
+                txw.put::<u32, u32>(&db, &i, &i);
             }
+            txw.commit();
 
-            // Write and abort.
-            let mut tx = WriteTransaction::new(&env);
-            tx.put_reserve(&db, "test", "one");
-            tx.abort();
+            let stats = db.stats();
+            assert_eq!(stats.entries, 100);
+            assert!(stats.pages() > 0);
 
-            // Check aborted transaction.
-            let tx = ReadTransaction::new(&env);
-            assert!(tx.get::<str, String>(&db, "test").is_none());
+            let env_stats = env.stats();
+            assert_eq!(env_stats.databases, 1);
+            assert_eq!(env_stats.page_size, stats.page_size);
+            assert_eq!(env_stats.used_size() + env_stats.free_size(), env_stats.map_size / env_stats.page_size as usize * env_stats.page_size as usize);
         }
 
         env.drop_database().unwrap();
     }
 
-    #[test]
-    fn isolation_test() {
-        let env = VolatileEnvironment::new_with_lmdb_flags( 1, open::NOTLS).unwrap();
-        {
-            let db = env.open_database("test".to_string());
-
-            // Read non-existent value.
-            let tx = ReadTransaction::new(&env);
-            assert!(tx.get::<str, String>(&db, "test").is_none());
-
-            // WriteTransaction.
-            let mut txw = WriteTransaction::new(&env);
-            assert!(txw.get::<str, String>(&db, "test").is_none());
-            txw.put_reserve(&db, "test", "one");
-            assert_eq!(txw.get::<str, String>(&db, "test"), Some("one".to_string()));
+    /// Checks that the volatile backend behaves exactly like LMDB.
+    #[cfg(feature = "lmdb")]
+    mod lmdb_parity {
+        use rand::{Rng, SeedableRng};
+        use rand::rngs::StdRng;
+        use tempdir::TempDir;
 
-            // ReadTransaction should still have the old state.
-            assert!(tx.get::<str, String>(&db, "test").is_none());
+        use crate::cursor::ReadCursor;
+        use crate::lmdb::LmdbEnvironmentBuilder;
 
-            // Commit WriteTransaction.
-            txw.commit();
+        use super::*;
 
-            // ReadTransaction should still have the old state.
-            assert!(tx.get::<str, String>(&db, "test").is_none());
+        /// Arbitrary bytes, so that keys and values can contain zero bytes and share prefixes.
+        #[derive(Clone, Debug, PartialEq)]
+        struct Bytes(Vec<u8>);
 
-            // Have a new ReadTransaction read the new state.
-            let tx2 = ReadTransaction::new(&env);
-            assert_eq!(tx2.get::<str, String>(&db, "test"), Some("one".to_string()));
+        impl AsDatabaseBytes for Bytes {
+            fn as_database_bytes(&self) -> Cow<[u8]> {
+                Cow::Borrowed(&self.0)
+            }
         }
 
-        env.drop_database().unwrap();
-    }
-
-    #[test]
-    fn duplicates_test() {
-        let env = VolatileEnvironment::new_with_lmdb_flags( 1, open::NOTLS).unwrap();
-        {
-            let db = env.open_database_with_flags("test".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_UINT_VALUES);
-
-            // Write one value.
-            let mut txw = WriteTransaction::new(&env);
-            assert!(txw.get::<str, u32>(&db, "test").is_none());
-            txw.put::<str, u32>(&db, "test", &125);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
-            txw.commit();
-
-            // Have a new ReadTransaction read the new state.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert_eq!(tx.get::<str, u32>(&db, "test"), Some(125));
+        impl FromDatabaseValue for Bytes {
+            fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
+                Ok(Bytes(bytes.to_vec()))
             }
+        }
 
-            // Write a second smaller value.
-            let mut txw = WriteTransaction::new(&env);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
-            txw.put::<str, u32>(&db, "test", &12);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(12));
-            txw.commit();
+        trait Random {
+            fn random(rng: &mut StdRng) -> Self;
+        }
 
-            // Have a new ReadTransaction read the smaller value.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert_eq!(tx.get::<str, u32>(&db, "test"), Some(12));
+        impl Random for Bytes {
+            /// Short byte strings over a small alphabet, which contains the bytes used for escaping.
+            fn random(rng: &mut StdRng) -> Self {
+                let len = rng.gen_range(1, 4);
+                Bytes((0..len).map(|_| [0x00, 0x01, 0x7f, 0xff][rng.gen_range(0, 4)]).collect())
             }
+        }
 
-            // Remove smaller value and write larger value.
-            let mut txw = WriteTransaction::new(&env);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(12));
-            txw.remove_item::<str, u32>(&db, "test", &12);
-            txw.put::<str, u32>(&db, "test", &5783);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
-            txw.commit();
+        impl Random for u32 {
+            /// Integers whose bytes in native order sort differently than their values.
+            fn random(rng: &mut StdRng) -> Self {
+                [0, 1, 255, 256, 257, 65_536][rng.gen_range(0, 6)]
+            }
+        }
 
-            // Have a new ReadTransaction read the smallest value.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert_eq!(tx.get::<str, u32>(&db, "test"), Some(125));
+        impl Random for u64 {
+            fn random(rng: &mut StdRng) -> Self {
+                [0, 1, 255, 256, 1 << 32, (1 << 32) + 1][rng.gen_range(0, 6)]
             }
+        }
 
-            // Remove everything.
-            let mut txw = WriteTransaction::new(&env);
-            assert_eq!(txw.get::<str, u32>(&db, "test"), Some(125));
-            txw.remove::<str>(&db, "test");
-            assert!(txw.get::<str, u32>(&db, "test").is_none());
-            txw.commit();
+        /// Records the result of a cursor operation and returns whether it found an entry.
+        fn record<T: fmt::Debug>(results: &mut Vec<String>, result: Option<T>) -> bool {
+            let found = result.is_some();
+            results.push(format!("{:?}", result));
+            found
+        }
 
-            // Have a new ReadTransaction read the new state.
-            {
-                let tx = ReadTransaction::new(&env);
-                assert!(tx.get::<str, u32>(&db, "test").is_none());
+        /// Applies the same random changes to both databases and checks that their entries and
+        /// random cursor walks over them are the same. Walks stop at the first missing entry, where
+        /// LMDB leaves the cursor in a backend specific state.
+        fn assert_parity<K, V>(rng: &mut StdRng, envs: [&Environment; 2], dbs: [&Database; 2])
+            where K: Random + AsDatabaseBytes + FromDatabaseValue + fmt::Debug + PartialEq,
+                  V: Random + AsDatabaseBytes + FromDatabaseValue + fmt::Debug + PartialEq {
+            let duplicates = dbs[0].flags().contains(DatabaseFlags::DUPLICATE_KEYS);
+
+            for _ in 0..20 {
+                let changes: Vec<(u32, K, V)> = (0..20)
+                    .map(|_| (rng.gen_range(0, 5), K::random(rng), V::random(rng)))
+                    .collect();
+                for (env, db) in envs.iter().zip(dbs.iter()) {
+                    let mut txn = WriteTransaction::new(env);
+                    for (op, key, value) in changes.iter() {
+                        match op {
+                            0 => txn.remove(db, key),
+                            1 => txn.remove_item(db, key, value),
+                            _ => txn.put(db, key, value),
+                        }
+                    }
+                    txn.commit();
+                }
+
+                let entries: Vec<Vec<(K, V)>> = envs.iter().zip(dbs.iter())
+                    .map(|(env, db)| ReadTransaction::new(env).cursor(db).iter().collect())
+                    .collect();
+                assert_eq!(entries[0], entries[1]);
+
+                for _ in 0..20 {
+                    let key = K::random(rng);
+                    let value = V::random(rng);
+                    let start = rng.gen_range(0, if duplicates { 6 } else { 4 });
+                    let moves: Vec<u32> = (0..8).map(|_| rng.gen_range(0, if duplicates { 10 } else { 5 })).collect();
+
+                    let walks: Vec<Vec<String>> = envs.iter().zip(dbs.iter()).map(|(env, db)| {
+                        let txn = ReadTransaction::new(env);
+                        let mut cursor = txn.cursor(db);
+                        let mut results = Vec::new();
+                        let mut found = match start {
+                            0 => record(&mut results, cursor.first::<K, V>()),
+                            1 => record(&mut results, cursor.last::<K, V>()),
+                            2 => record(&mut results, cursor.seek_key::<K, V>(&key)),
+                            3 => record(&mut results, cursor.seek_range::<K, K, V>(&key)),
+                            4 => record(&mut results, cursor.seek_key_nearest_value::<K, V>(&key, &value)),
+                            _ => {
+                                let found = cursor.seek_key_value(&key, &value);
+                                results.push(format!("{:?}", found));
+                                found
+                            },
+                        };
+                        for &m in moves.iter() {
+                            if !found {
+                                break;
+                            }
+                            found = match m {
+                                0 => record(&mut results, cursor.next::<K, V>()),
+                                1 => record(&mut results, cursor.prev::<K, V>()),
+                                2 => record(&mut results, cursor.next_no_duplicate::<K, V>()),
+                                3 => record(&mut results, cursor.prev_no_duplicate::<K, V>()),
+                                4 => record(&mut results, cursor.get_current::<K, V>()),
+                                5 => record(&mut results, cursor.next_duplicate::<K, V>()),
+                                6 => record(&mut results, cursor.prev_duplicate::<K, V>()),
+                                7 => record(&mut results, cursor.first_duplicate::<V>()),
+                                8 => record(&mut results, cursor.last_duplicate::<V>()),
+                                _ => {
+                                    results.push(cursor.count_duplicates().to_string());
+                                    true
+                                },
+                            };
+                        }
+                        results
+                    }).collect();
+                    assert_eq!(walks[0], walks[1]);
+                }
             }
         }
 
-        env.drop_database().unwrap();
-    }
+        #[test]
+        fn lmdb_parity_test() {
+            let temp_dir = TempDir::new("volatile-parity").unwrap();
+            let lmdb_env = LmdbEnvironmentBuilder::new(temp_dir.path().to_str().unwrap()).with_max_dbs(4).with_no_sync(true).with_write_map(true).build().unwrap();
+            let volatile_env = VolatileEnvironment::new(4).unwrap();
+            let envs = [&lmdb_env, &volatile_env];
+            let mut rng = StdRng::from_seed([42; 32]);
+            {
+                let open_both = |name: &str, flags| [lmdb_env.open_database_with_flags(name.to_string(), flags),
+                                                volatile_env.open_database_with_flags(name.to_string(), flags)];
 
-    #[test]
-    fn cursor_test() {
-        let env = VolatileEnvironment::new_with_lmdb_flags( 1, open::NOTLS).unwrap();
-        {
-            let db = env.open_database_with_flags("test".to_string(), DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::DUP_UINT_VALUES);
+                let dbs = open_both("plain", DatabaseFlags::empty());
+                assert_parity::<Bytes, Bytes>(&mut rng, envs, [&dbs[0], &dbs[1]]);
 
-            let test1: String = "test1".to_string();
-            let test2: String = "test2".to_string();
+                let dbs = open_both("dup", DatabaseFlags::DUPLICATE_KEYS);
+                assert_parity::<Bytes, Bytes>(&mut rng, envs, [&dbs[0], &dbs[1]]);
 
-            // Write some values.
-            let mut txw = WriteTransaction::new(&env);
-            assert!(txw.get::<str, u32>(&db, "test").is_none());
-            txw.put::<str, u32>(&db, "test1", &125);
-            txw.put::<str, u32>(&db, "test1", &12);
-            txw.put::<str, u32>(&db, "test1", &5783);
-            txw.put::<str, u32>(&db, "test2", &5783);
-            txw.commit();
+                let dbs = open_both("uint", DatabaseFlags::DUPLICATE_KEYS | DatabaseFlags::UINT_KEYS | DatabaseFlags::DUP_FIXED_SIZE_VALUES | DatabaseFlags::DUP_UINT_VALUES);
+                assert_parity::<u32, u32>(&mut rng, envs, [&dbs[0], &dbs[1]]);
 
-            // Have a new ReadTransaction read the new state.
-            let tx = ReadTransaction::new(&env);
-            let mut cursor = tx.cursor(&db);
-            assert_eq!(cursor.first::<String, u32>(), Some((test1.clone(), 12)));
-            assert_eq!(cursor.last::<String, u32>(), Some((test2.clone(), 5783)));
-            assert_eq!(cursor.prev::<String, u32>(), Some((test1.clone(), 5783)));
-            assert_eq!(cursor.first_duplicate::<u32>(), Some(12));
-            assert_eq!(cursor.next_duplicate::<String, u32>(), Some((test1.clone(), 125)));
-            assert_eq!(cursor.prev_duplicate::<String, u32>(), Some((test1.clone(), 12)));
-            assert_eq!(cursor.next_no_duplicate::<String, u32>(), Some((test2.clone(), 5783)));
-            assert!(cursor.seek_key::<str, u32>("test").is_none());
-            assert_eq!(cursor.seek_key::<str, u32>("test1"), Some(12));
-            assert_eq!(cursor.count_duplicates(), 3);
-            assert_eq!(cursor.last_duplicate::<u32>(), Some(5783));
-//            assert_eq!(cursor.seek_key_both::<String, u32>(&test1), Some((test1.clone(), 12)));
-            assert!(!cursor.seek_key_value::<str, u32>("test1", &15));
-            assert!(cursor.seek_key_value::<str, u32>("test1", &125));
-            assert_eq!(cursor.get_current::<String, u32>(), Some((test1.clone(), 125)));
-            assert_eq!(cursor.seek_key_nearest_value::<str, u32>("test1", &126), Some(5783));
-            assert_eq!(cursor.get_current::<String, u32>(), Some((test1.clone(), 5783)));
-            assert!(cursor.prev_no_duplicate::<String, u32>().is_none());
-            assert_eq!(cursor.next::<String, u32>(), Some((test2.clone(), 5783)));
-//            assert_eq!(cursor.seek_range_key::<String, u32>("test"), Some((test1.clone(), 12)));
+                let dbs = open_both("u64", DatabaseFlags::U64_KEYS);
+                assert_parity::<u64, Bytes>(&mut rng, envs, [&dbs[0], &dbs[1]]);
+            }
         }
-
-        env.drop_database().unwrap();
     }
 }
@@ -0,0 +1,217 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use beserial::{BigEndian, ReadBytesExt, WriteBytesExt};
+use parking_lot::Mutex;
+
+use crate::{Environment, WriteBatch};
+
+/// Writes to several environments, staged in one `WriteBatch` per environment.
+#[derive(Debug)]
+pub struct AtomicWrite<'db, 'env> {
+    environments: Vec<&'env Environment>,
+    batches: Vec<WriteBatch<'db, 'env>>,
+}
+
+impl<'db, 'env> AtomicWrite<'db, 'env> {
+    /// The batch of the databases in `env`.
+    ///
+    /// Panics if `env` isn't one of the environments of the log.
+    pub fn batch(&mut self, env: &Environment) -> &mut WriteBatch<'db, 'env> {
+        let index = self.environments.iter()
+            .position(|other| std::ptr::eq(*other, env))
+            .expect("Environment isn't part of the write-ahead log");
+        &mut self.batches[index]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.iter().all(WriteBatch::is_empty)
+    }
+}
+
+/// Commits writes to several environments atomically: after a crash, either all environments
+/// contain the writes or none of them.
+///
+/// The batches of an `AtomicWrite` are written to a log file first, which is only renamed to
+/// `path` once it is complete and synced. Then each environment commits its batch, and the log is
+/// removed afterwards. If the process crashes before all environments have committed, `open`
+/// replays the log. Replaying is idempotent, since a batch only puts and removes entries.
+///
+/// The log isn't encrypted, so it shouldn't be used for encrypted environments.
+#[derive(Debug)]
+pub struct WriteAheadLog<'env> {
+    path: PathBuf,
+    environments: Vec<&'env Environment>,
+    /// Only one write can use the log at a time.
+    lock: Mutex<()>,
+}
+
+impl<'env> WriteAheadLog<'env> {
+    const VERSION: u8 = 1;
+
+    /// Opens the log at `path` and replays a write that wasn't completely committed.
+    /// The environments must be given in the same order every time the log is opened.
+    pub fn open<P: Into<PathBuf>>(path: P, environments: Vec<&'env Environment>) -> io::Result<Self> {
+        let wal = WriteAheadLog {
+            path: path.into(),
+            environments,
+            lock: Mutex::new(()),
+        };
+        wal.recover()?;
+        Ok(wal)
+    }
+
+    pub fn begin<'db>(&self) -> AtomicWrite<'db, 'env> {
+        AtomicWrite {
+            environments: self.environments.clone(),
+            batches: self.environments.iter().map(|_| WriteBatch::new()).collect(),
+        }
+    }
+
+    /// Commits the batches of `write` to their environments.
+    pub fn commit(&self, write: AtomicWrite) -> io::Result<()> {
+        let _guard = self.lock.lock();
+
+        // A single environment commits atomically by itself.
+        if write.batches.iter().filter(|batch| !batch.is_empty()).count() <= 1 {
+            for (env, batch) in write.environments.into_iter().zip(write.batches) {
+                if !batch.is_empty() {
+                    batch.commit(env);
+                }
+            }
+            return Ok(());
+        }
+
+        self.write_log(&write)?;
+        for (env, batch) in write.environments.into_iter().zip(write.batches) {
+            batch.commit(env);
+        }
+        fs::remove_file(&self.path)
+    }
+
+    fn write_log(&self, write: &AtomicWrite) -> io::Result<()> {
+        let tmp_path = self.tmp_path();
+        let file = File::create(&tmp_path)?;
+        let mut writer = BufWriter::new(file);
+        writer.write_u8(Self::VERSION)?;
+        let batches: Vec<_> = write.batches.iter().enumerate()
+            .filter(|(_, batch)| !batch.is_empty())
+            .collect();
+        writer.write_u32::<BigEndian>(batches.len() as u32)?;
+        for (index, batch) in batches {
+            writer.write_u32::<BigEndian>(index as u32)?;
+            batch.write_to(&mut writer)?;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+
+        // The rename makes the complete log visible at once.
+        fs::rename(&tmp_path, &self.path)?;
+        sync_dir(&self.path)
+    }
+
+    fn recover(&self) -> io::Result<()> {
+        let tmp_path = self.tmp_path();
+        if tmp_path.exists() {
+            // The log wasn't complete, so no environment has been written to.
+            fs::remove_file(&tmp_path)?;
+        }
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        warn!("Replaying write-ahead log {}", self.path.display());
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        if reader.read_u8()? != Self::VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown write-ahead log version"));
+        }
+        for _ in 0..reader.read_u32::<BigEndian>()? {
+            let index = reader.read_u32::<BigEndian>()? as usize;
+            let env = self.environments.get(index)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Write-ahead log refers to unknown environment"))?;
+            WriteBatch::replay(&mut reader, env)?;
+        }
+        fs::remove_file(&self.path)
+    }
+
+    fn tmp_path(&self) -> PathBuf {
+        self.path.with_extension("tmp")
+    }
+}
+
+/// Syncs the directory containing `path`, so that a rename in it is durable.
+fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => OpenOptions::new().read(true).open(dir)?.sync_all(),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use crate::ReadTransaction;
+    use crate::volatile::VolatileEnvironment;
+
+    use super::*;
+
+    #[test]
+    fn it_commits_to_all_environments() {
+        let dir = TempDir::new("wal").unwrap();
+        let env1 = VolatileEnvironment::new(1).unwrap();
+        let env2 = VolatileEnvironment::new(1).unwrap();
+        let db1 = env1.open_database("test".to_string());
+        let db2 = env2.open_database("test".to_string());
+
+        let wal = WriteAheadLog::open(dir.path().join("wal"), vec![&env1, &env2]).unwrap();
+        let mut write = wal.begin();
+        write.batch(&env1).put(&db1, "a", "1");
+        write.batch(&env2).put(&db2, "b", "2");
+        wal.commit(write).unwrap();
+
+        assert_eq!(ReadTransaction::new(&env1).get::<str, String>(&db1, "a"), Some("1".to_string()));
+        assert_eq!(ReadTransaction::new(&env2).get::<str, String>(&db2, "b"), Some("2".to_string()));
+        assert!(!dir.path().join("wal").exists());
+    }
+
+    #[test]
+    fn it_replays_an_interrupted_write() {
+        let dir = TempDir::new("wal").unwrap();
+        let path = dir.path().join("wal");
+        let env1 = VolatileEnvironment::new(1).unwrap();
+        let env2 = VolatileEnvironment::new(1).unwrap();
+        let db1 = env1.open_database("test".to_string());
+        let db2 = env2.open_database("test".to_string());
+
+        {
+            let wal = WriteAheadLog::open(&path, vec![&env1, &env2]).unwrap();
+            let mut write = wal.begin();
+            write.batch(&env1).put(&db1, "a", "1");
+            write.batch(&env2).put(&db2, "b", "2");
+            write.batch(&env2).remove(&db2, "c");
+            // Crash after the log was written, with only the first environment committed.
+            wal.write_log(&write).unwrap();
+            let mut batches = write.batches.into_iter();
+            batches.next().unwrap().commit(&env1);
+        }
+        assert_eq!(ReadTransaction::new(&env2).get::<str, String>(&db2, "b"), None);
+
+        WriteAheadLog::open(&path, vec![&env1, &env2]).unwrap();
+        assert_eq!(ReadTransaction::new(&env1).get::<str, String>(&db1, "a"), Some("1".to_string()));
+        assert_eq!(ReadTransaction::new(&env2).get::<str, String>(&db2, "b"), Some("2".to_string()));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn it_discards_an_incomplete_log() {
+        let dir = TempDir::new("wal").unwrap();
+        let path = dir.path().join("wal");
+        let env = VolatileEnvironment::new(1).unwrap();
+        File::create(path.with_extension("tmp")).unwrap().write_all(&[1, 0, 0]).unwrap();
+
+        WriteAheadLog::open(&path, vec![&env]).unwrap();
+        assert!(!path.with_extension("tmp").exists());
+    }
+}
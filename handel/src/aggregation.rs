@@ -52,6 +52,10 @@ pub struct Aggregation<P: Protocol> {
     /// Levels
     levels: Vec<Level>,
 
+    /// Whether levels are skipped and individual signatures are exchanged directly with all other
+    /// nodes (see `Config::direct_threshold`)
+    direct: bool,
+
     /// Signatures that still need to be processed
     todos: Arc<TodoList<P::Evaluator>>,
 
@@ -75,12 +79,14 @@ pub struct Aggregation<P: Protocol> {
 impl<P: Protocol + fmt::Debug> Aggregation<P> {
     pub fn new(protocol: P, config: Config) -> Arc<Self> {
         let levels = Level::create_levels(protocol.partitioner());
+        let direct = protocol.partitioner().size() <= config.direct_threshold;
         let todos = Arc::new(TodoList::new(protocol.evaluator()));
 
         // create aggregation
         let this = Arc::new(Self {
             config,
             levels,
+            direct,
             todos,
             protocol,
             timers: Timers::new(),
@@ -118,6 +124,10 @@ impl<P: Protocol + fmt::Debug> Aggregation<P> {
             self.check_completed_level(&signature, 0);
             self.check_final_signature();
 
+            if self.direct {
+                self.send_contribution();
+            }
+
             // send level 0
             // This will be done by check_completed level
             //let level = self.levels.get(0).expect("Level 0 missing");
@@ -128,9 +138,82 @@ impl<P: Protocol + fmt::Debug> Aggregation<P> {
         }
     }
 
+    /// Puts signatures that were already verified, e.g. by a previous aggregation over the same
+    /// message, into the store. Our own contribution must be pushed with `push_contribution`
+    /// instead.
+    ///
+    /// The level of each signature is derived from its signers, so the signatures may come from an
+    /// aggregation with different levels. Multi-signatures whose signers span several levels are
+    /// skipped.
+    pub fn push_verified_signatures(&self, signatures: Vec<Signature>) {
+        let node_id = self.protocol.node_id();
+
+        for signature in signatures {
+            let level = match &signature {
+                Signature::Individual(individual) if individual.signer == node_id => continue,
+                Signature::Individual(individual) => self.level_of(individual.signer),
+                Signature::Multi(multisig) => {
+                    multisig.signers.iter().next()
+                        .and_then(|signer| self.level_of(signer))
+                        .filter(|&level| multisig.signers.iter().all(|signer| self.levels[level].peer_ids.contains(&signer)))
+                },
+            };
+            let level = match level {
+                // Level 0 only contains our own contribution.
+                Some(level) if level > 0 => level,
+                _ => {
+                    warn!("Verified signature doesn't belong to a level: {:?}", signature);
+                    continue;
+                },
+            };
+
+            self.protocol.store().write().put(signature.clone(), level);
+            self.check_completed_level(&signature, level);
+        }
+
+        self.check_final_signature();
+    }
+
+    /// Returns all verified signatures, i.e. the individual signatures, including our own
+    /// contribution, and the best multi-signature of each level
+    pub fn verified_signatures(&self) -> Vec<Signature> {
+        let store = self.protocol.store();
+        let store = store.read();
+
+        let mut signatures = Vec::new();
+        for level in &self.levels {
+            for peer_id in store.individual_verified(level.id).iter() {
+                if let Some(individual) = store.individual_signature(level.id, peer_id) {
+                    signatures.push(Signature::Individual(individual.clone()));
+                }
+            }
+            // NOTE: Level 0 only contains our own contribution
+            if let Some(best) = store.best(level.id).filter(|_| level.id > 0) {
+                signatures.push(Signature::Multi(best.clone()));
+            }
+        }
+        signatures
+    }
+
     fn init_background(this: &Arc<Self>) {
         unsafe { this.self_weak.replace(Arc::downgrade(&this)) };
 
+        if this.direct {
+            // Without levels there is nothing to update or time out. We only re-send our
+            // contribution, in case it got lost or a peer started its aggregation late.
+            let weak = Arc::downgrade(this);
+            this.timers.set_interval(AggregationTimer::Update, move || {
+                let this = upgrade_weak!(weak);
+                if this.result().is_some() {
+                    this.timers.clear_interval(&AggregationTimer::Update);
+                }
+                else {
+                    this.send_contribution();
+                }
+            }, this.config.timeout);
+            return;
+        }
+
         // register timer for updates
         let weak = Arc::downgrade(this);
         this.timers.set_interval(AggregationTimer::Update, move || {
@@ -204,8 +287,45 @@ impl<P: Protocol + fmt::Debug> Aggregation<P> {
         }
     }
 
+    /// Send our contribution to all other nodes. This is only used if levels are skipped.
+    ///
+    /// Each update is sent at the level at which the peer stores our signature, such that peers
+    /// that don't skip levels can handle it like any other level update.
+    fn send_contribution(&self) {
+        let contribution = match self.state.read().contribution.clone() {
+            Some(contribution) => contribution,
+            None => return,
+        };
+
+        let node_id = self.protocol.node_id();
+
+        trace!("Sending contribution directly to all peers");
+        for peer_id in 0 .. self.protocol.partitioner().size() {
+            if peer_id == node_id {
+                continue;
+            }
+            // Levels are symmetric: the peer is at the same level from our view as we are from its view.
+            if let Some(level) = self.level_of(peer_id) {
+                let update = LevelUpdate::new(contribution.as_multisig(), Some(contribution.clone()), level, node_id);
+                self.protocol.sender().send_to(peer_id, update);
+            }
+        }
+    }
+
+    /// Returns the level at which the signature of `peer_id` is stored
+    fn level_of(&self, peer_id: usize) -> Option<usize> {
+        self.levels.iter()
+            .find(|level| level.peer_ids.contains(&peer_id))
+            .map(|level| level.id)
+    }
+
     /// Check if a level was completed
     fn check_completed_level(&self, signature: &Signature, level: usize) {
+        if self.direct {
+            // levels are never started, so there is nothing to complete
+            return;
+        }
+
         let level = self.levels.get(level)
             .unwrap_or_else(|| panic!("Invalid level: {}", level));
 
@@ -305,6 +425,11 @@ impl<P: Protocol + fmt::Debug> Aggregation<P> {
 
         trace!("Level Update: origin={}, level={}, has_individual={}", origin, level, individual.is_some());
 
+        if self.direct {
+            self.push_direct_update(multisig, individual, level as usize);
+            return;
+        }
+
         // Future that verifies the individual signature and puts it into the TODO list
         // NOTE: We use `map` instead of `and_then`, because `and_then` needs to return a future,
         //       and `upgrade_weak!` might return `()`.
@@ -381,4 +506,394 @@ impl<P: Protocol + fmt::Debug> Aggregation<P> {
 
         tokio::spawn(process_fut);
     }
+
+    /// Handles an update if levels are skipped. The individual signature is stored at the level its
+    /// signer belongs to, such that the combined signature covers it.
+    ///
+    /// Peers that don't skip levels send their combined signature for `level` instead, and omit
+    /// their individual signature once that level is complete. The multi-signature is therefore
+    /// stored at `level` as well, unless it only contains the individual signature.
+    fn push_direct_update(&self, multisig: MultiSignature, individual: Option<IndividualSignature>, level: usize) {
+        let only_individual = individual.as_ref()
+            .map_or(false, |individual| multisig.len() == 1 && multisig.signers.contains(individual.signer));
+        let mut todos = Vec::new();
+
+        if let Some(individual) = individual {
+            match self.level_of(individual.signer) {
+                Some(level) => todos.push((Signature::Individual(individual), level)),
+                None => warn!("Direct update from unknown signer: {}", individual.signer),
+            }
+        }
+
+        if !only_individual {
+            // Level 0 only contains our own signature.
+            if level > 0 && level < self.num_levels() {
+                todos.push((Signature::Multi(multisig), level));
+            }
+            else {
+                warn!("Direct update for invalid level: {}", level);
+            }
+        }
+
+        self.process_direct_todos(todos);
+    }
+
+    /// Verifies the signatures of a direct update and puts the valid ones into the store.
+    fn process_direct_todos(&self, todos: Vec<(Signature, usize)>) {
+        if todos.is_empty() {
+            return;
+        }
+
+        let verify_futs = todos.into_iter()
+            .map(|(sig, level)| {
+                let weak = Weak::clone(&self.self_weak);
+                self.protocol
+                    .verify(&sig)
+                    .map(move |result| {
+                        if result.is_ok() {
+                            let this = upgrade_weak!(weak);
+                            this.todos.put(sig, level);
+                        }
+                        else {
+                            warn!("Invalid signature: {:?}", result);
+                        }
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let weak = Weak::clone(&self.self_weak);
+        let process_fut = future::join_all(verify_futs)
+            .map(move |_| {
+                let this = upgrade_weak!(weak);
+                let store = this.protocol.store();
+                while let Some((signature, level, score)) = this.todos.get_best() {
+                    trace!("Processing: score={}, level={}: {:?}", score, level, signature);
+                    store.write().put(signature, level);
+                    this.check_final_signature();
+                }
+            })
+            .map_err(|e| {
+                warn!("The signature processing future somehow failed: {:?}", e);
+                e
+            });
+
+        tokio::spawn(process_fut);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::io::Error as IoError;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use parking_lot::Mutex;
+    use futures_cpupool::CpuPool;
+    use tokio::runtime::Runtime;
+
+    use bls::bls12_381::{KeyPair, PublicKey};
+    use hash::{Blake2bHash, Hash};
+
+    use crate::identity::{IdentityRegistry, WeightRegistry};
+    use crate::verifier::MultithreadedVerifier;
+    use crate::store::ReplaceStore;
+    use crate::evaluator::WeightedVote;
+    use crate::partitioner::{Partitioner, BinomialPartitioner};
+    use super::*;
+
+    struct TestRegistry(Vec<PublicKey>);
+
+    impl IdentityRegistry for TestRegistry {
+        fn public_key(&self, id: usize) -> Option<PublicKey> {
+            self.0.get(id).cloned()
+        }
+    }
+
+    impl WeightRegistry for TestRegistry {
+        fn weight(&self, id: usize) -> Option<usize> {
+            self.0.get(id).map(|_| 1)
+        }
+    }
+
+    /// Collects the updates, such that the test can deliver them
+    #[derive(Default)]
+    struct TestSender(Mutex<Vec<(usize, LevelUpdate)>>);
+
+    impl Sender for TestSender {
+        type Error = IoError;
+
+        fn send_to(&self, peer_id: usize, update: LevelUpdate) {
+            self.0.lock().push((peer_id, update));
+        }
+    }
+
+    struct TestProtocol {
+        node_id: usize,
+        registry: Arc<TestRegistry>,
+        verifier: Arc<MultithreadedVerifier<TestRegistry>>,
+        partitioner: Arc<BinomialPartitioner>,
+        store: Arc<RwLock<ReplaceStore<BinomialPartitioner>>>,
+        evaluator: Arc<WeightedVote<ReplaceStore<BinomialPartitioner>, TestRegistry, BinomialPartitioner>>,
+        sender: Arc<TestSender>,
+    }
+
+    impl TestProtocol {
+        fn new(node_id: usize, registry: Arc<TestRegistry>, message_hash: Blake2bHash, cpu_pool: Arc<CpuPool>) -> Self {
+            let num_ids = registry.0.len();
+            let verifier = Arc::new(MultithreadedVerifier::new(message_hash, Arc::clone(&registry), cpu_pool));
+            let partitioner = Arc::new(BinomialPartitioner::new(node_id, num_ids));
+            let store = Arc::new(RwLock::new(ReplaceStore::new(Arc::clone(&partitioner))));
+            let evaluator = Arc::new(WeightedVote::new(
+                Arc::clone(&store),
+                Arc::clone(&registry),
+                Arc::clone(&partitioner),
+                num_ids,
+            ));
+            Self {
+                node_id,
+                registry,
+                verifier,
+                partitioner,
+                store,
+                evaluator,
+                sender: Arc::new(TestSender::default()),
+            }
+        }
+    }
+
+    impl fmt::Debug for TestProtocol {
+        fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+            write!(f, "TestProtocol {{ node_id: {} }}", self.node_id)
+        }
+    }
+
+    impl Protocol for TestProtocol {
+        type Registry = TestRegistry;
+        type Verifier = MultithreadedVerifier<TestRegistry>;
+        type Store = ReplaceStore<BinomialPartitioner>;
+        type Evaluator = WeightedVote<ReplaceStore<BinomialPartitioner>, TestRegistry, BinomialPartitioner>;
+        type Partitioner = BinomialPartitioner;
+        type Sender = TestSender;
+
+        fn registry(&self) -> Arc<Self::Registry> {
+            Arc::clone(&self.registry)
+        }
+
+        fn verifier(&self) -> Arc<Self::Verifier> {
+            Arc::clone(&self.verifier)
+        }
+
+        fn store(&self) -> Arc<RwLock<Self::Store>> {
+            Arc::clone(&self.store)
+        }
+
+        fn evaluator(&self) -> Arc<Self::Evaluator> {
+            Arc::clone(&self.evaluator)
+        }
+
+        fn partitioner(&self) -> Arc<Self::Partitioner> {
+            Arc::clone(&self.partitioner)
+        }
+
+        fn sender(&self) -> Arc<Self::Sender> {
+            Arc::clone(&self.sender)
+        }
+
+        fn node_id(&self) -> usize {
+            self.node_id
+        }
+    }
+
+    fn test_config(direct_threshold: usize) -> Config {
+        Config {
+            update_interval: Duration::from_millis(10),
+            timeout: Duration::from_millis(20),
+            direct_threshold,
+            ..Config::default()
+        }
+    }
+
+    fn create_key_pairs(num_ids: usize) -> Vec<KeyPair> {
+        let mut rng = rand::thread_rng();
+        (0 .. num_ids).map(|_| KeyPair::generate(&mut rng)).collect()
+    }
+
+    /// Creates an aggregation for each of the `direct_thresholds` and pushes the contributions
+    fn create_aggregations(rt: &mut Runtime, direct_thresholds: Vec<usize>) -> Vec<Arc<Aggregation<TestProtocol>>> {
+        let key_pairs = create_key_pairs(direct_thresholds.len());
+        create_aggregations_with_keys(rt, &key_pairs, direct_thresholds)
+    }
+
+    fn create_aggregations_with_keys(rt: &mut Runtime, key_pairs: &[KeyPair], direct_thresholds: Vec<usize>) -> Vec<Arc<Aggregation<TestProtocol>>> {
+        let key_pairs = key_pairs.to_vec();
+        let registry = Arc::new(TestRegistry(key_pairs.iter().map(|key_pair| key_pair.public.clone()).collect()));
+        let message_hash = "foobar".hash::<Blake2bHash>();
+        let cpu_pool = Arc::new(CpuPool::new(2));
+
+        rt.block_on(future::lazy(move || {
+            let aggregations = direct_thresholds.into_iter().enumerate()
+                .map(|(node_id, direct_threshold)| {
+                    let protocol = TestProtocol::new(node_id, Arc::clone(&registry), message_hash.clone(), Arc::clone(&cpu_pool));
+                    let aggregation = Aggregation::new(protocol, test_config(direct_threshold));
+                    let signature = key_pairs[node_id].sign_hash(message_hash.clone());
+                    aggregation.push_contribution(IndividualSignature::new(signature, node_id));
+                    aggregation
+                })
+                .collect::<Vec<_>>();
+            Ok::<_, ()>(aggregations)
+        })).unwrap()
+    }
+
+    /// Combines the contributions of `signers`
+    fn combine_contributions(aggregations: &[Arc<Aggregation<TestProtocol>>], signers: &[usize]) -> MultiSignature {
+        let mut multisig: Option<MultiSignature> = None;
+        for &signer in signers {
+            let contribution = aggregations[signer].state.read().contribution.clone().unwrap();
+            multisig = Some(match multisig {
+                Some(mut multisig) => {
+                    multisig.add_individual(&contribution).unwrap();
+                    multisig
+                },
+                None => contribution.as_multisig(),
+            });
+        }
+        multisig.unwrap()
+    }
+
+    /// Delivers the sent updates until all aggregations are complete. Returns whether they completed.
+    fn run_aggregations(rt: &mut Runtime, aggregations: &[Arc<Aggregation<TestProtocol>>]) -> bool {
+        for _ in 0 .. 500 {
+            if aggregations.iter().all(|aggregation| aggregation.result().is_some()) {
+                return true;
+            }
+
+            let aggregations = aggregations.to_vec();
+            rt.block_on(future::lazy(move || {
+                for aggregation in &aggregations {
+                    let updates = std::mem::replace(&mut *aggregation.protocol.sender.0.lock(), Vec::new());
+                    for (peer_id, update) in updates {
+                        aggregations[peer_id].push_update(update);
+                    }
+                }
+                Ok::<_, ()>(())
+            })).unwrap();
+
+            sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn it_sends_direct_contributions_at_the_level_of_the_peer() {
+        let mut rt = Runtime::new().unwrap();
+        let aggregations = create_aggregations(&mut rt, vec![8; 5]);
+
+        let aggregation = &aggregations[0];
+        let updates = aggregation.protocol.sender.0.lock().clone();
+        assert_eq!(updates.len(), 4);
+        for (peer_id, update) in updates {
+            assert_ne!(peer_id, 0);
+            assert_eq!(update.origin, 0);
+            assert_eq!(Some(update.level as usize), aggregation.level_of(peer_id));
+            assert_eq!(update.individual.map(|individual| individual.signer), Some(0));
+        }
+    }
+
+    #[test]
+    fn it_aggregates_in_direct_mode() {
+        let mut rt = Runtime::new().unwrap();
+        let aggregations = create_aggregations(&mut rt, vec![8; 5]);
+
+        assert!(run_aggregations(&mut rt, &aggregations));
+        for aggregation in &aggregations {
+            assert_eq!(aggregation.result().unwrap().len(), 5);
+        }
+    }
+
+    #[test]
+    fn it_aggregates_with_nodes_that_use_levels() {
+        let mut rt = Runtime::new().unwrap();
+        // Nodes with a lower threshold run the regular level protocol.
+        let aggregations = create_aggregations(&mut rt, vec![8, 0, 8, 0, 0, 8]);
+        assert!(aggregations[0].direct);
+        assert!(!aggregations[1].direct);
+
+        assert!(run_aggregations(&mut rt, &aggregations));
+        for aggregation in &aggregations {
+            assert_eq!(aggregation.result().unwrap().len(), 6);
+        }
+    }
+
+    #[test]
+    fn it_uses_the_multisig_of_a_direct_update() {
+        let mut rt = Runtime::new().unwrap();
+        let aggregations = create_aggregations(&mut rt, vec![8; 4]);
+
+        // Combine the contributions of the peers at level 2 of node 0, like a node that uses
+        // levels does once that level is complete.
+        let level = 2;
+        let multisig = combine_contributions(&aggregations, &aggregations[0].levels[level].peer_ids);
+        let origin = multisig.signers.iter().next().unwrap();
+        let num_signers = multisig.len();
+        assert!(num_signers > 1);
+
+        let aggregation = Arc::clone(&aggregations[0]);
+        rt.block_on(future::lazy(move || {
+            aggregation.push_update(LevelUpdate::new(multisig, None, level, origin));
+            Ok::<_, ()>(())
+        })).unwrap();
+
+        for _ in 0 .. 100 {
+            if aggregations[0].protocol.store.read().best(level).map(|best| best.len()) == Some(num_signers) {
+                return;
+            }
+            sleep(Duration::from_millis(10));
+        }
+        panic!("Multi-signature of direct update was not stored");
+    }
+
+    #[test]
+    fn it_completes_with_the_verified_signatures_of_a_previous_aggregation() {
+        let mut rt = Runtime::new().unwrap();
+        let key_pairs = create_key_pairs(6);
+        let aggregations = create_aggregations_with_keys(&mut rt, &key_pairs, vec![0; 6]);
+        assert!(run_aggregations(&mut rt, &aggregations));
+
+        // The best multi-signatures are carried over as well.
+        let signatures = aggregations[0].verified_signatures();
+        assert!(signatures.iter().any(|signature| match signature {
+            Signature::Multi(multisig) => multisig.len() > 1,
+            Signature::Individual(_) => false,
+        }));
+
+        let restarted = create_aggregations_with_keys(&mut rt, &key_pairs, vec![0; 6]);
+        let aggregation = Arc::clone(&restarted[0]);
+        let result = rt.block_on(future::lazy(move || {
+            aggregation.push_verified_signatures(signatures);
+            Ok::<_, ()>(aggregation.result())
+        })).unwrap();
+
+        assert_eq!(result.unwrap().len(), 6);
+    }
+
+    #[test]
+    fn it_skips_verified_multisigs_that_span_several_levels() {
+        let mut rt = Runtime::new().unwrap();
+        let aggregations = create_aggregations(&mut rt, vec![0; 4]);
+
+        let level_1 = aggregations[0].levels[1].peer_ids[0];
+        let level_2 = aggregations[0].levels[2].peer_ids[0];
+        let multisig = combine_contributions(&aggregations, &[level_1, level_2]);
+
+        let aggregation = Arc::clone(&aggregations[0]);
+        rt.block_on(future::lazy(move || {
+            aggregation.push_verified_signatures(vec![Signature::Multi(multisig)]);
+            Ok::<_, ()>(())
+        })).unwrap();
+
+        let store = aggregations[0].protocol.store.read();
+        assert!(store.best(1).is_none());
+        assert!(store.best(2).is_none());
+    }
 }
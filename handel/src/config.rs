@@ -15,6 +15,11 @@ pub struct Config {
     /// How many peers are contacted at each level
     pub peer_count: usize,
 
+    /// Up to this number of identities, levels are skipped and every node sends its individual
+    /// signature directly to all other nodes. Nodes with different thresholds can still aggregate
+    /// together: direct updates are sent at the level of the receiving node, and updates from nodes
+    /// that use levels are stored at their level.
+    pub direct_threshold: usize,
 }
 
 
@@ -25,6 +30,7 @@ impl Default for Config {
             update_interval: Duration::from_millis(100),
             timeout: Duration::from_millis(500),
             peer_count: 10,
+            direct_threshold: 8,
         }
     }
 }
@@ -1,8 +1,9 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use futures::{future, Future};
 use futures::future::FutureResult;
-use futures_cpupool::{CpuPool, CpuFuture};
+use futures_cpupool::{Builder, CpuPool, CpuFuture};
 
 use hash::Blake2bHash;
 use bls::bls12_381::AggregatePublicKey;
@@ -12,9 +13,34 @@ use crate::identity::IdentityRegistry;
 
 
 
+/// Number of threads of the shared CPU pool. Zero means one thread per CPU core.
+static SHARED_CPU_POOL_SIZE: AtomicUsize = AtomicUsize::new(0);
+static SHARED_CPU_POOL_CREATED: AtomicBool = AtomicBool::new(false);
+
 lazy_static! {
     /// CPU pool that is shared between all Handel instances (that use it)
-    static ref SHARED_CPU_POOL: Arc<CpuPool> = Arc::new(CpuPool::new_num_cpus());
+    static ref SHARED_CPU_POOL: Arc<CpuPool> = {
+        SHARED_CPU_POOL_CREATED.store(true, Ordering::SeqCst);
+        let mut builder = Builder::new();
+        builder.name_prefix("handel-");
+        let pool_size = SHARED_CPU_POOL_SIZE.load(Ordering::SeqCst);
+        if pool_size > 0 {
+            builder.pool_size(pool_size);
+        }
+        Arc::new(builder.create())
+    };
+}
+
+/// Creates the CPU pool that is shared between all Handel instances with `pool_size` threads.
+/// Returns `false` if the pool was created before, e.g. by the first shared verifier, and thus
+/// keeps its size.
+pub fn init_shared_cpu_pool(pool_size: usize) -> bool {
+    if SHARED_CPU_POOL_SIZE.compare_exchange(0, pool_size, Ordering::SeqCst, Ordering::SeqCst).is_err()
+        || SHARED_CPU_POOL_CREATED.load(Ordering::SeqCst) {
+        return false;
+    }
+    lazy_static::initialize(&SHARED_CPU_POOL);
+    true
 }
 
 
@@ -128,3 +154,16 @@ impl<I: IdentityRegistry + Sync + Send + 'static> Verifier for MultithreadedVeri
         })
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_initializes_the_shared_cpu_pool_once() {
+        assert!(init_shared_cpu_pool(2));
+        assert!(!init_shared_cpu_pool(4));
+        assert_eq!(SHARED_CPU_POOL_SIZE.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,46 @@
+//! Reports which implementations of the hash functions are used on this CPU.
+//!
+//! Blake2b and Argon2d select the fastest implementation the CPU supports at runtime, so a single
+//! binary runs on all CPUs of an architecture.
+
+pub use libargon2_sys::{argon2_implementation, set_argon2_implementation, Argon2Implementation};
+
+/// Returns the CPU features that are used by one of the optimized implementations.
+pub fn cpu_features() -> Vec<&'static str> {
+    #[allow(unused_mut)]
+    let mut features = Vec::new();
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("sse2") {
+            features.push("sse2");
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            features.push("sse4.1");
+        }
+        if is_x86_feature_detected!("avx2") {
+            features.push("avx2");
+        }
+    }
+
+    // NEON is part of every AArch64 CPU. On 32-bit ARM it can only be detected at compile time.
+    #[cfg(any(target_arch = "aarch64", all(target_arch = "arm", target_feature = "neon")))]
+    features.push("neon");
+
+    features
+}
+
+/// Returns the Blake2b implementation in use. `blake2b_simd` picks the fastest one on its own,
+/// this mirrors its choice.
+pub fn blake2b_implementation() -> &'static str {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return "avx2";
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            return "sse4.1";
+        }
+    }
+    "portable"
+}
@@ -5,8 +5,9 @@ pub mod hmac;
 pub mod pbkdf2;
 pub mod sha512;
 pub mod argon2kdf;
+pub mod backend;
 
-use blake2_rfc::blake2b::Blake2b;
+use blake2b_simd::State as Blake2bState;
 use libargon2_sys::argon2d_hash;
 use sha2::{Sha256, Sha512, Digest};
 use beserial::{Serialize, Deserialize};
@@ -99,7 +100,7 @@ impl<H> SerializeContent for H where H: HashOutput {
 const BLAKE2B_LENGTH : usize = 32;
 create_typed_array!(Blake2bHash, u8, BLAKE2B_LENGTH);
 add_hex_io_fns_typed_arr!(Blake2bHash, BLAKE2B_LENGTH);
-pub struct Blake2bHasher(Blake2b);
+pub struct Blake2bHasher(Blake2bState);
 impl HashOutput for Blake2bHash {
     type Builder = Blake2bHasher;
 
@@ -111,7 +112,7 @@ impl HashOutput for Blake2bHash {
 
 impl Blake2bHasher {
     pub fn new() -> Self {
-        Blake2bHasher(Blake2b::new(BLAKE2B_LENGTH))
+        Blake2bHasher(blake2b_simd::Params::new().hash_length(BLAKE2B_LENGTH).to_state())
     }
 }
 
@@ -11,6 +11,7 @@ pub use self::address::*;
 pub use self::key_pair::*;
 pub use self::private_key::*;
 pub use self::public_key::*;
+pub use self::secret_bytes::*;
 pub use self::signature::*;
 pub use self::errors::*;
 
@@ -65,4 +66,5 @@ mod errors;
 mod key_pair;
 mod private_key;
 mod public_key;
+mod secret_bytes;
 mod signature;
@@ -8,6 +8,7 @@ use ed25519_dalek;
 use hex;
 use hex::FromHex;
 use rand::rngs::OsRng;
+use subtle::ConstantTimeEq;
 
 use beserial::{Deserialize, ReadBytesExt, Serialize, SerializingError, WriteBytesExt};
 use hash::{Hash, SerializeContent};
@@ -87,7 +88,7 @@ impl Debug for PrivateKey {
 
 impl PartialEq for PrivateKey {
     fn eq(&self, other: &PrivateKey) -> bool {
-        self.as_bytes() == other.as_bytes()
+        bool::from(self.as_bytes().ct_eq(other.as_bytes()))
     }
 }
 
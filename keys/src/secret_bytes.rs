@@ -0,0 +1,81 @@
+use std::fmt::{Debug, Error, Formatter};
+
+use clear_on_drop::clear::Clear;
+use subtle::ConstantTimeEq;
+
+/// Secret material like passwords or serialized private keys.
+///
+/// The bytes are overwritten with zeros when dropped, are never printed by `Debug` and are
+/// compared in constant time.
+#[derive(Default)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Compares the secret with `other` in constant time. Only the length of the secret leaks.
+    pub fn ct_eq(&self, other: &[u8]) -> bool {
+        bool::from(self.0.as_slice().ct_eq(other))
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.0.as_mut_slice().clear();
+    }
+}
+
+impl Clone for SecretBytes {
+    fn clone(&self) -> Self {
+        SecretBytes(self.0.clone())
+    }
+}
+
+impl Debug for SecretBytes {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "SecretBytes")
+    }
+}
+
+impl PartialEq for SecretBytes {
+    fn eq(&self, other: &SecretBytes) -> bool {
+        self.ct_eq(other.as_bytes())
+    }
+}
+
+impl Eq for SecretBytes {}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+}
+
+impl From<String> for SecretBytes {
+    fn from(s: String) -> Self {
+        SecretBytes(s.into_bytes())
+    }
+}
+
+impl<'a> From<&'a str> for SecretBytes {
+    fn from(s: &'a str) -> Self {
+        SecretBytes(s.as_bytes().to_vec())
+    }
+}
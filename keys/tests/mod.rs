@@ -1,6 +1,6 @@
 mod multisig;
 
-use nimiq_keys::{PrivateKey,PublicKey,Signature,KeyPair,Address};
+use nimiq_keys::{PrivateKey,PublicKey,Signature,KeyPair,Address,SecretBytes};
 
 #[test]
 fn verify_created_signature() {
@@ -101,3 +101,13 @@ fn it_computes_friendly_addresses() {
     assert_eq!(addr.as_bytes(), addr2.as_bytes());
     assert_eq!(addr.to_user_friendly_address(), addr2.to_user_friendly_address());
 }
+
+#[test]
+fn secret_bytes_are_not_printed() {
+    let secret = SecretBytes::from("hunter2");
+    assert_eq!(format!("{:?}", secret), "SecretBytes");
+    assert!(secret.ct_eq(b"hunter2"));
+    assert!(!secret.ct_eq(b"hunter3"));
+    assert!(!secret.ct_eq(b"hunter"));
+    assert_eq!(secret, SecretBytes::from(b"hunter2".to_vec()));
+}
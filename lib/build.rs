@@ -0,0 +1,17 @@
+use std::process::Command;
+
+fn main() {
+    // Embed the git commit hash, so that it can be advertised in the user agent.
+    let git_hash = Command::new("git")
+        .args(&["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string());
+
+    if let Some(git_hash) = git_hash {
+        println!("cargo:rustc-env=NIMIQ_GIT_HASH={}", git_hash);
+    }
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+}
@@ -27,28 +27,98 @@ pub mod albatross {
     use std::sync::Arc;
 
     use consensus::{AlbatrossConsensusProtocol, Consensus};
+    use keys::Address;
+    use network_primitives::heartbeat::ValidatorLiveness;
+    pub use block_production_albatross::blacklist::Blacklist;
+    pub use block_production_albatross::selector::{FilteredMempoolOrder, MempoolOrder, TransactionSelector};
+    pub use block_production_albatross::validator_keys::ValidatorKeys;
+    pub use validator::extra_data::ExtraDataProvider;
     use validator::validator::Validator;
     use validator::error::Error as ValidatorError;
-    use bls::bls12_381::KeyPair;
 
     use super::BlockProducer;
     use crate::error::ClientError;
+    use crate::gc::GarbageCollector;
+    use crate::rewards::{RewardSweepConfig, RewardWatcher};
 
     #[derive(Clone)]
     pub struct ValidatorConfig {
-        pub validator_key: KeyPair,
+        /// The validator's keys. Shared, such that a key rotation can be started while the
+        /// validator is running.
+        pub validator_keys: Arc<ValidatorKeys>,
+        /// If set, the node checks that its slots pay to this address.
+        pub reward_address: Option<Address>,
+        pub reward_sweep: Option<RewardSweepConfig>,
+        /// Heartbeats received from the other validators. Shared, such that it can be reported
+        /// outside of the validator.
+        pub liveness: Arc<ValidatorLiveness>,
+        /// Check produced blocks against the blockchain before relaying them.
+        pub verify_blocks: bool,
+        /// Extra data of the produced micro blocks
+        pub extra_data: ExtraDataProvider,
+        /// Transactions left out of the produced micro blocks. Shared, such that it can be changed
+        /// while the validator is running.
+        pub blacklist: Arc<Blacklist>,
+        /// Bytes of the produced micro blocks reserved for locally submitted transactions
+        pub local_transactions_size: usize,
+        /// Selects the other transactions of the produced micro blocks, e.g. `MempoolOrder`
+        pub transaction_selector: Arc<dyn TransactionSelector>,
+        /// Threads that verify the signatures of the other validators
+        pub verification_threads: usize,
+        /// Threads that handle the blockchain events for the validator
+        pub blockchain_event_threads: usize,
+        /// The node's garbage collector, which removes old fork proofs, validator infos and view
+        /// changes of the validator.
+        pub gc: Option<Arc<GarbageCollector>>,
+        /// Only produce blocks through the test RPC methods, see `Validator::new`.
+        pub manual_block_production: bool,
     }
 
     pub struct AlbatrossBlockProducer {
         pub validator: Arc<Validator>,
+        pub rewards: Option<Arc<RewardWatcher>>,
+    }
+
+    impl AlbatrossBlockProducer {
+        fn register_gc(validator: &Arc<Validator>, gc: &GarbageCollector) {
+            let settings = gc.settings();
+            let weak = Arc::downgrade(validator);
+            gc.register("fork proofs", settings.fork_proofs(), move |_, epoch| {
+                if let Some(validator) = weak.upgrade() {
+                    validator.prune_fork_proofs(epoch);
+                }
+            });
+            let weak = Arc::downgrade(validator);
+            gc.register("validator infos", settings.validator_infos(), move |_, epoch| {
+                if let Some(validator) = weak.upgrade() {
+                    validator.prune_validator_infos(epoch);
+                }
+            });
+            let weak = Arc::downgrade(validator);
+            gc.register("view changes", settings.view_changes(), move |_, epoch| {
+                if let Some(validator) = weak.upgrade() {
+                    validator.prune_view_changes(epoch);
+                }
+            });
+        }
     }
 
     impl BlockProducer<AlbatrossConsensusProtocol> for AlbatrossBlockProducer {
         type Config = ValidatorConfig;
 
         fn new(config: Self::Config, consensus: Arc<Consensus<AlbatrossConsensusProtocol>>) -> Result<Self, ClientError> {
+            let ValidatorConfig { validator_keys, reward_address, reward_sweep, liveness, verify_blocks, extra_data, blacklist, local_transactions_size, transaction_selector, verification_threads, blockchain_event_threads, gc, manual_block_production } = config;
+            let rewards = reward_address.map(|reward_address| {
+                RewardWatcher::watch(&consensus, Arc::clone(&validator_keys), reward_address, reward_sweep)
+            });
+            let validator = Validator::new(Arc::clone(&consensus), validator_keys, liveness, verify_blocks, extra_data, blacklist, local_transactions_size, transaction_selector, verification_threads, blockchain_event_threads, manual_block_production)?;
+            if let Some(ref gc) = gc {
+                Self::register_gc(&validator, gc);
+            }
+
             Ok(Self {
-                validator: Validator::new(consensus, config.validator_key)?
+                validator,
+                rewards,
             })
         }
     }
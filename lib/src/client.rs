@@ -6,7 +6,7 @@ use futures::{Async, Future, Poll};
 use consensus::{Consensus, ConsensusProtocol, AlbatrossConsensusProtocol, NimiqConsensusProtocol};
 use database::Environment;
 use mempool::MempoolConfig;
-use network::network_config::{NetworkConfig, ReverseProxyConfig, Seed};
+use network::network_config::{NetworkConfig, NodeRole, PeerCountTargets, ReverseProxyConfig, Seed};
 use network_primitives::address::NetAddress;
 use network_primitives::protocol::Protocol;
 use primitives::networks::NetworkId;
@@ -17,7 +17,12 @@ use crate::error::ClientError;
 use crate::block_producer::BlockProducer;
 
 lazy_static! {
-    pub static ref DEFAULT_USER_AGENT: String = format!("core-rs/{} (native; {} {})", env!("CARGO_PKG_VERSION"), env::consts::OS, env::consts::ARCH);
+    /// Semantic version of this build, including the git commit hash as build metadata if available.
+    pub static ref VERSION: String = match option_env!("NIMIQ_GIT_HASH") {
+        Some(git_hash) => format!("{}+{}", env!("CARGO_PKG_VERSION"), git_hash),
+        None => env!("CARGO_PKG_VERSION").to_string(),
+    };
+    pub static ref DEFAULT_USER_AGENT: String = format!("core-rs/{} (native; {} {})", *VERSION, env::consts::OS, env::consts::ARCH);
 }
 
 
@@ -37,6 +42,10 @@ pub struct ClientBuilder {
     identity_password: Option<String>,
     mempool_config: Option<MempoolConfig>,
     service_flags: Option<ServiceFlags>,
+    node_role: Option<NodeRole>,
+    peer_count_targets: Vec<(NodeRole, PeerCountTargets)>,
+    memory_budget: Option<usize>,
+    sync_rate_limit: Option<usize>,
 }
 
 impl ClientBuilder {
@@ -56,6 +65,10 @@ impl ClientBuilder {
             identity_password: None,
             mempool_config: None,
             service_flags: None,
+            node_role: None,
+            peer_count_targets: Vec::new(),
+            memory_budget: None,
+            sync_rate_limit: None,
         }
     }
 
@@ -99,8 +112,9 @@ impl ClientBuilder {
         self
     }
 
+    /// Adds `service_flags` to the provided and accepted services.
     pub fn with_service_flags(&mut self, service_flags: ServiceFlags) -> &mut Self {
-        self.service_flags = Some(service_flags);
+        self.service_flags = Some(self.service_flags.unwrap_or(ServiceFlags::NONE) | service_flags);
         self
     }
 
@@ -109,6 +123,29 @@ impl ClientBuilder {
         self
     }
 
+    pub fn with_node_role(&mut self, node_role: NodeRole) -> &mut Self {
+        self.node_role = Some(node_role);
+        self
+    }
+
+    /// Overrides the default peer count targets for `node_role`.
+    pub fn with_peer_count_targets(&mut self, node_role: NodeRole, targets: PeerCountTargets) -> &mut Self {
+        self.peer_count_targets.push((node_role, targets));
+        self
+    }
+
+    /// Sets the memory budget in bytes. When usage approaches it, load is shed.
+    pub fn with_memory_budget(&mut self, memory_budget: usize) -> &mut Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    /// Limits the number of blocks applied per second until consensus is established.
+    pub fn with_sync_rate_limit(&mut self, sync_rate_limit: usize) -> &mut Self {
+        self.sync_rate_limit = Some(sync_rate_limit);
+        self
+    }
+
     pub fn build_client<P, BP>(self, block_producer_config: BP::Config) -> Result<ClientInitializeFuture<P, BP>, ClientError>
         where P: ConsensusProtocol + 'static,
               BP: BlockProducer<P> + 'static
@@ -151,6 +188,10 @@ impl ClientBuilder {
             user_agent,
             additional_seeds,
             service_flags,
+            node_role,
+            peer_count_targets,
+            memory_budget,
+            sync_rate_limit,
         } = self;
 
         // build network config
@@ -181,6 +222,12 @@ impl ClientBuilder {
         };
         network_config.set_user_agent(user_agent);
         network_config.set_additional_seeds(additional_seeds);
+        if let Some(node_role) = node_role {
+            network_config.set_role(node_role);
+        }
+        for (node_role, targets) in peer_count_targets {
+            network_config.set_peer_count_targets(node_role, targets);
+        }
         network_config.init_persistent(&peer_key_store)?;
 
         if let Some(flags) = service_flags {
@@ -191,7 +238,7 @@ impl ClientBuilder {
         }
 
         let mempool_config = mempool_config.unwrap_or_else(MempoolConfig::default);
-        Ok(Consensus::new(environment, network_id, network_config, mempool_config)?)
+        Ok(Consensus::new(environment, network_id, network_config, mempool_config, memory_budget, sync_rate_limit)?)
     }
 }
 
@@ -216,6 +263,17 @@ pub struct ClientInitializeFuture<P, BP>
     initialized: bool
 }
 
+impl<P, BP> ClientInitializeFuture<P, BP>
+    where P: ConsensusProtocol + 'static,
+          BP: BlockProducer<P> + 'static
+{
+    /// The config the block producer is created with once the client is initialized. It can be
+    /// completed with state that needs the consensus, like the garbage collector.
+    pub fn block_producer_config_mut(&mut self) -> &mut BP::Config {
+        &mut self.block_producer_config
+    }
+}
+
 impl<P, BP> Future for ClientInitializeFuture<P, BP>
     where P: ConsensusProtocol + 'static,
           BP: BlockProducer<P> + 'static
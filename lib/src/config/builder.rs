@@ -0,0 +1,156 @@
+use log::LevelFilter;
+
+use super::*;
+
+/// Builder for a `ClientConfig`. Starts with the same defaults as an empty config file.
+#[derive(Clone, Debug, Default)]
+pub struct ClientConfigBuilder {
+    config: ClientConfig,
+}
+
+impl ClientConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_network(&mut self, network: Network) -> &mut Self {
+        self.config.consensus.network = network;
+        self
+    }
+
+    pub fn with_node_type(&mut self, node_type: NodeType) -> &mut Self {
+        self.config.consensus.node_type = node_type;
+        self
+    }
+
+    /// Limits the number of blocks applied per second during the initial sync.
+    pub fn with_sync_rate_limit(&mut self, sync_rate_limit: usize) -> &mut Self {
+        self.config.consensus.sync_rate_limit = Some(sync_rate_limit);
+        self
+    }
+
+    pub fn with_protocol(&mut self, protocol: Protocol) -> &mut Self {
+        self.config.network.protocol = protocol;
+        self
+    }
+
+    pub fn with_hostname(&mut self, hostname: &str) -> &mut Self {
+        self.config.network.host = Some(String::from(hostname));
+        self
+    }
+
+    pub fn with_port(&mut self, port: u16) -> &mut Self {
+        self.config.network.port = Some(port);
+        self
+    }
+
+    pub fn with_user_agent(&mut self, user_agent: &str) -> &mut Self {
+        self.config.network.user_agent = Some(String::from(user_agent));
+        self
+    }
+
+    pub fn with_tls_identity(&mut self, identity_file: &str, identity_password: &str) -> &mut Self {
+        self.config.network.tls = Some(TlsSettings {
+            identity_file: String::from(identity_file),
+            identity_password: String::from(identity_password),
+        });
+        self
+    }
+
+    pub fn with_seed_node(&mut self, seed: Seed) -> &mut Self {
+        self.config.network.seed_nodes.push(seed);
+        self
+    }
+
+    pub fn with_instant_inbound(&mut self, instant_inbound: bool) -> &mut Self {
+        self.config.network.instant_inbound = Some(instant_inbound);
+        self
+    }
+
+    pub fn with_node_role(&mut self, role: NodeRole) -> &mut Self {
+        self.config.network.role = Some(role);
+        self
+    }
+
+    pub fn with_reverse_proxy(&mut self, reverse_proxy: ReverseProxySettings) -> &mut Self {
+        self.config.reverse_proxy = Some(reverse_proxy);
+        self
+    }
+
+    pub fn with_rpc_server(&mut self, rpc_server: RpcServerSettings) -> &mut Self {
+        self.config.rpc_server = Some(rpc_server);
+        self
+    }
+
+    pub fn with_metrics_server(&mut self, metrics_server: MetricsServerSettings) -> &mut Self {
+        self.config.metrics_server = Some(metrics_server);
+        self
+    }
+
+    pub fn with_log_level(&mut self, level: LevelFilter) -> &mut Self {
+        self.config.log.level = Some(level);
+        self
+    }
+
+    pub fn with_database(&mut self, database: DatabaseSettings) -> &mut Self {
+        self.config.database = database;
+        self
+    }
+
+    pub fn with_threads(&mut self, threads: ThreadSettings) -> &mut Self {
+        self.config.threads = threads;
+        self
+    }
+
+    pub fn with_gc(&mut self, gc: GcSettings) -> &mut Self {
+        self.config.gc = gc;
+        self
+    }
+
+    pub fn with_mempool(&mut self, mempool: MempoolSettings) -> &mut Self {
+        self.config.mempool = Some(mempool);
+        self
+    }
+
+    pub fn with_peer_key_file(&mut self, peer_key_file: &str) -> &mut Self {
+        self.config.peer_key_file = Some(String::from(peer_key_file));
+        self
+    }
+
+    /// Sets the memory budget in MiB.
+    pub fn with_memory_budget(&mut self, memory_budget: usize) -> &mut Self {
+        self.config.memory_budget = Some(memory_budget);
+        self
+    }
+
+    pub fn with_validator(&mut self, validator: ValidatorSettings) -> &mut Self {
+        self.config.validator = Some(validator);
+        self
+    }
+
+    pub fn with_updater(&mut self, updater: UpdaterSettings) -> &mut Self {
+        self.config.updater = Some(updater);
+        self
+    }
+
+    pub fn with_sandbox(&mut self, sandbox: SandboxSettings) -> &mut Self {
+        self.config.sandbox = Some(sandbox);
+        self
+    }
+
+    pub fn with_replica(&mut self, replica: ReplicaSettings) -> &mut Self {
+        self.config.replica = Some(replica);
+        self
+    }
+
+    pub fn with_webhook(&mut self, webhook: WebhookSettings) -> &mut Self {
+        self.config.webhook.push(webhook);
+        self
+    }
+
+    /// Validates and returns the configuration.
+    pub fn build(&self) -> Result<ClientConfig, ConfigErrors> {
+        self.config.validate()?;
+        Ok(self.config.clone())
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+use std::io;
+
+use failure::Fail;
+
+use network_primitives::address::NetSubnetParseError;
+
+use super::{DatabaseBackend, Network, NodeType};
+use super::serialization::SeedError;
+use crate::webhooks::WebhookError;
+
+#[derive(Debug, Fail)]
+pub enum ConfigError {
+    #[fail(display = "Config file not found")]
+    MissingConfigFile,
+    #[fail(display = "Failed to read config file: {}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "Failed to parse config file: {}", _0)]
+    Parse(#[cause] toml::de::Error),
+    #[fail(display = "Failed to serialize config: {}", _0)]
+    Serialize(#[cause] toml::ser::Error),
+    #[fail(display = "Only full consensus is implemented right now, not {:?}.", _0)]
+    UnsupportedNodeType(NodeType),
+    #[fail(display = "Rtc is not implemented.")]
+    RtcNotImplemented,
+    #[fail(display = "Please configure a TLS identity file and the password in the `[tls]` section.")]
+    NoTlsIdentityFile,
+    #[fail(display = "Please configure a hostname in the `[network]` section.")]
+    NoHostname,
+    #[fail(display = "Reverse proxy can only be configured on Ws.")]
+    ReverseProxyRequiresWs,
+    #[fail(display = "Please configure the header containing the client's IP address in the `[reverse-proxy]` section.")]
+    MissingReverseProxyHeader,
+    #[fail(display = "A validator must run full consensus.")]
+    ValidatorRequiresFullNode,
+    #[fail(display = "A validator must run on an Albatross network, not {:?}.", _0)]
+    ValidatorRequiresAlbatross(Network),
+    #[fail(display = "Invalid validator reward address: {}", _0)]
+    InvalidRewardAddress(#[cause] keys::AddressParseError),
+    #[fail(display = "Invalid reward sweep address: {}", _0)]
+    InvalidRewardSweepAddress(#[cause] keys::AddressParseError),
+    #[fail(display = "The reward sweep interval must not be zero.")]
+    InvalidRewardSweepInterval,
+    #[fail(display = "The reward sweep key belongs to {}, not to the configured reward address {}.", _0, _1)]
+    RewardSweepKeyMismatch(String, String),
+    #[fail(display = "The validator's extra data must not exceed 255 bytes, but is {} bytes long.", _0)]
+    ExtraDataTooLong(usize),
+    #[fail(display = "Invalid blacklisted address: {}", _0)]
+    InvalidBlacklistAddress(#[cause] keys::AddressParseError),
+    #[fail(display = "Invalid blacklisted transaction hash: {}", _0)]
+    InvalidBlacklistTransaction(String),
+    #[fail(display = "A minimal validator doesn't index transactions, so it can't deliver webhooks.")]
+    MinimalValidatorWithWebhooks,
+    #[fail(display = "Manual block production is only allowed on dev networks, not {:?}.", _0)]
+    ManualBlockProductionRequiresDev(Network),
+    #[fail(display = "Username or password missing for RPC server.")]
+    MissingRpcCredentials,
+    #[fail(display = "A username for the metrics server requires a password.")]
+    MissingMetricsPassword,
+    #[fail(display = "Invalid IP address or subnet {:?}: {}", _0, _1)]
+    InvalidAllowIp(String, #[cause] NetSubnetParseError),
+    #[fail(display = "The public key for a seed node is missing. Seed nodes without public_key are currently not implemented.")]
+    MissingPublicKey,
+    #[fail(display = "Invalid seed node: {}", _0)]
+    InvalidSeed(#[cause] SeedError),
+    #[fail(display = "Webhooks require an Albatross network, not {:?}.", _0)]
+    WebhooksRequireAlbatross(Network),
+    #[fail(display = "Invalid webhook URL: {}", _0)]
+    InvalidWebhookUrl(#[cause] url::ParseError),
+    #[fail(display = "{}", _0)]
+    InvalidWebhookEvent(#[cause] WebhookError),
+    #[fail(display = "Invalid webhook address: {}", _0)]
+    InvalidWebhookAddress(#[cause] keys::AddressParseError),
+    #[fail(display = "Invalid updater manifest URL: {}", _0)]
+    InvalidManifestUrl(#[cause] url::ParseError),
+    #[fail(display = "Invalid updater public key: {}", _0)]
+    InvalidUpdaterPublicKey(#[cause] keys::ParseError),
+    #[fail(display = "Sandboxing is only supported on Linux.")]
+    SandboxUnsupported,
+    #[fail(display = "The memory budget must be at least 1 MiB and fit into the address space.")]
+    InvalidMemoryBudget,
+    #[fail(display = "The sync rate limit must not be zero.")]
+    InvalidSyncRateLimit,
+    #[fail(display = "The number of `{}` threads must not be zero.", _0)]
+    InvalidThreadCount(&'static str),
+    #[fail(display = "The replacement fee factor must be at least 1, but is {}.", _0)]
+    InvalidReplacementFeeFactor(f64),
+    #[fail(display = "The transaction TTL must not be zero.")]
+    InvalidTransactionTtl,
+    #[fail(display = "The mempool sender limit must not be zero.")]
+    InvalidSenderLimit,
+    #[fail(display = "Database backend {:?} is not available, the client must be built with the `{}` feature.", _0, _1)]
+    DatabaseBackendUnavailable(DatabaseBackend, &'static str),
+    #[fail(display = "Database backups are only supported by the LMDB backend, not {:?}.", _0)]
+    BackupRequiresLmdb(DatabaseBackend),
+    #[fail(display = "Database encryption is only supported by the LMDB backend, not {:?}.", _0)]
+    EncryptionRequiresLmdb(DatabaseBackend),
+    #[fail(display = "The database is encrypted, but no passphrase is configured.")]
+    DatabasePassphraseMissing,
+    #[fail(display = "A read replica must run on an Albatross network, not {:?}.", _0)]
+    ReplicaRequiresAlbatross(Network),
+    #[fail(display = "A read replica requires the LMDB backend, not {:?}.", _0)]
+    ReplicaRequiresLmdb(DatabaseBackend),
+    #[fail(display = "A read replica only serves RPC queries, please configure the `[rpc-server]` section.")]
+    ReplicaRequiresRpcServer,
+    #[fail(display = "A read replica can't write to the database, so it can't run a validator or deliver webhooks.")]
+    ReplicaIsReadOnly,
+    #[fail(display = "The replica refresh interval must not be zero.")]
+    InvalidReplicaRefreshInterval,
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(e: toml::ser::Error) -> Self {
+        ConfigError::Serialize(e)
+    }
+}
+
+/// All problems found by `ClientConfig::validate`.
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for error in &self.0 {
+            write!(f, "\n - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Fail for ConfigErrors {}
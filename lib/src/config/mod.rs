@@ -0,0 +1,952 @@
+//! Typed configuration of a client, as read from a `client.toml` file.
+//!
+//! The client binary and embedders of this library share these types: A configuration can either
+//! be parsed from TOML with `ClientConfig::from_file`, or assembled in code with
+//! `ClientConfig::builder()`. In both cases `ClientConfig::validate` reports all problems at once.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+
+use hex::FromHex;
+use log::LevelFilter;
+use url::Url;
+
+use block_albatross::MicroExtrinsics;
+use hash::Blake2bHash;
+use keys::{Address, PublicKey, SecretBytes};
+use network::network_config::Seed as NetworkSeed;
+use network_primitives::address::{NetAddress, NetSubnet};
+use primitives::coin::Coin;
+use primitives::networks::NetworkId;
+
+use self::serialization::*;
+use crate::webhooks::WebhookEvent;
+
+pub mod builder;
+pub mod error;
+pub mod serialization;
+
+pub use self::builder::ClientConfigBuilder;
+pub use self::error::{ConfigError, ConfigErrors};
+
+pub const DEFAULT_REVERSE_PROXY_PORT: u16 = 8444;
+pub const DEFAULT_RPC_PORT: u16 = 8648;
+pub const DEFAULT_METRICS_PORT: u16 = 8649;
+pub const DEFAULT_REWARD_SWEEP_INTERVAL: u32 = 10;
+/// Number of peers a local transaction is relayed to with `TransactionBroadcast::Random`.
+pub const DEFAULT_BROADCAST_PEERS: usize = 8;
+/// Maximum number of transactions in the mempool of a minimal validator.
+pub const MINIMAL_VALIDATOR_MEMPOOL_SIZE: usize = 5_000;
+
+/// Configuration of a client. Each field corresponds to a section in the config file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    // NOTE: Plain values have to come before sections, otherwise the config can't be serialized
+    // to TOML.
+    #[serde(default)]
+    pub peer_key_file: Option<String>,
+    /// Memory budget in MiB. If usage approaches it, the node sheds load.
+    #[serde(default)]
+    pub memory_budget: Option<usize>,
+    #[serde(default)]
+    pub network: NetworkSettings,
+    #[serde(default)]
+    pub consensus: ConsensusSettings,
+    pub rpc_server: Option<RpcServerSettings>,
+    pub metrics_server: Option<MetricsServerSettings>,
+    pub reverse_proxy: Option<ReverseProxySettings>,
+    #[serde(default)]
+    pub log: LogSettings,
+    #[serde(default)]
+    pub database: DatabaseSettings,
+    #[serde(default)]
+    pub threads: ThreadSettings,
+    #[serde(default)]
+    pub gc: GcSettings,
+    pub mempool: Option<MempoolSettings>,
+    #[serde(default)]
+    pub validator: Option<ValidatorSettings>,
+    pub updater: Option<UpdaterSettings>,
+    pub sandbox: Option<SandboxSettings>,
+    /// Serves RPC queries from the database of a node in another process instead of running a
+    /// node.
+    pub replica: Option<ReplicaSettings>,
+    /// Webhooks that blockchain events are POSTed to.
+    #[serde(default)]
+    pub webhook: Vec<WebhookSettings>,
+}
+
+impl ClientConfig {
+    pub fn builder() -> ClientConfigBuilder {
+        ClientConfigBuilder::new()
+    }
+
+    /// Reads the configuration from a TOML file. The configuration is not validated.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ClientConfig, ConfigError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(ConfigError::MissingConfigFile);
+        }
+        read_to_string(path)?.parse()
+    }
+
+    /// Serializes the configuration to TOML, in the format of the config file.
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// The memory budget in bytes, or `None` if it isn't set or doesn't fit into a `usize`.
+    pub fn memory_budget_bytes(&self) -> Option<usize> {
+        self.memory_budget.and_then(|memory_budget| memory_budget.checked_mul(1024 * 1024))
+    }
+
+    /// Checks for problems that can't be caught while parsing, e.g. settings that are only valid
+    /// in combination with others. Returns all problems that were found.
+    pub fn validate(&self) -> Result<(), ConfigErrors> {
+        let mut errors = Vec::new();
+
+        // We only allow full nodes right now.
+        if self.consensus.node_type != NodeType::Full {
+            errors.push(ConfigError::UnsupportedNodeType(self.consensus.node_type));
+        }
+
+        let protocol = self.network.protocol;
+        if protocol == Protocol::Rtc {
+            errors.push(ConfigError::RtcNotImplemented);
+        }
+        if self.network.host.is_none() && (protocol == Protocol::Ws || protocol == Protocol::Wss) {
+            errors.push(ConfigError::NoHostname);
+        }
+        if self.network.tls.is_none() && protocol == Protocol::Wss {
+            errors.push(ConfigError::NoTlsIdentityFile);
+        }
+        if let Some(ref reverse_proxy_settings) = self.reverse_proxy {
+            if protocol != Protocol::Ws {
+                errors.push(ConfigError::ReverseProxyRequiresWs);
+            }
+            if reverse_proxy_settings.header.is_empty() {
+                errors.push(ConfigError::MissingReverseProxyHeader);
+            }
+        }
+
+        if let Some(ref validator_settings) = self.validator {
+            if self.consensus.node_type != NodeType::Full {
+                errors.push(ConfigError::ValidatorRequiresFullNode);
+            }
+            if !NetworkId::from(self.consensus.network).is_albatross() {
+                errors.push(ConfigError::ValidatorRequiresAlbatross(self.consensus.network));
+            }
+            if let Some(ref reward_address) = validator_settings.reward_address {
+                if let Err(e) = Address::from_user_friendly_address(reward_address) {
+                    errors.push(ConfigError::InvalidRewardAddress(e));
+                }
+            }
+            if let Some(ref sweep_settings) = validator_settings.reward_sweep {
+                if let Err(e) = Address::from_user_friendly_address(&sweep_settings.address) {
+                    errors.push(ConfigError::InvalidRewardSweepAddress(e));
+                }
+                if sweep_settings.interval == Some(0) {
+                    errors.push(ConfigError::InvalidRewardSweepInterval);
+                }
+            }
+            if let Some(ref extra_data) = validator_settings.extra_data {
+                if extra_data.len() > MicroExtrinsics::MAX_EXTRA_DATA_SIZE {
+                    errors.push(ConfigError::ExtraDataTooLong(extra_data.len()));
+                }
+            }
+            for address in &validator_settings.blacklist.addresses {
+                if let Err(e) = Address::from_user_friendly_address(address) {
+                    errors.push(ConfigError::InvalidBlacklistAddress(e));
+                }
+            }
+            for hash in &validator_settings.blacklist.transactions {
+                if Blake2bHash::from_str(hash).is_err() {
+                    errors.push(ConfigError::InvalidBlacklistTransaction(hash.clone()));
+                }
+            }
+            if validator_settings.is_minimal() && !self.webhook.is_empty() {
+                errors.push(ConfigError::MinimalValidatorWithWebhooks);
+            }
+            if validator_settings.manual_block_production && !NetworkId::from(self.consensus.network).is_dev() {
+                errors.push(ConfigError::ManualBlockProductionRequiresDev(self.consensus.network));
+            }
+        }
+
+        for seed in &self.network.seed_nodes {
+            match NetworkSeed::try_from(seed.clone()) {
+                Ok(NetworkSeed::Peer(ref uri)) if uri.public_key().is_none() => errors.push(ConfigError::MissingPublicKey),
+                Ok(_) => {},
+                Err(e) => errors.push(ConfigError::InvalidSeed(e)),
+            }
+        }
+
+        if let Some(ref rpc_settings) = self.rpc_server {
+            if rpc_settings.username.is_some() != rpc_settings.password.is_some() {
+                errors.push(ConfigError::MissingRpcCredentials);
+            }
+        }
+
+        if let Some(ref metrics_settings) = self.metrics_server {
+            if metrics_settings.username.is_some() && metrics_settings.password.is_none() {
+                errors.push(ConfigError::MissingMetricsPassword);
+            }
+            for subnet in &metrics_settings.allowip {
+                if let Err(e) = NetSubnet::from_str(subnet) {
+                    errors.push(ConfigError::InvalidAllowIp(subnet.clone(), e));
+                }
+            }
+        }
+
+        if let Some(ref updater_settings) = self.updater {
+            if let Err(e) = Url::parse(&updater_settings.manifest_url) {
+                errors.push(ConfigError::InvalidManifestUrl(e));
+            }
+            if let Err(e) = PublicKey::from_hex(&updater_settings.public_key) {
+                errors.push(ConfigError::InvalidUpdaterPublicKey(e));
+            }
+        }
+
+        if !self.webhook.is_empty() && !NetworkId::from(self.consensus.network).is_albatross() {
+            errors.push(ConfigError::WebhooksRequireAlbatross(self.consensus.network));
+        }
+        for webhook_settings in &self.webhook {
+            if let Err(e) = Url::parse(&webhook_settings.url) {
+                errors.push(ConfigError::InvalidWebhookUrl(e));
+            }
+            for event in &webhook_settings.events {
+                if let Err(e) = WebhookEvent::from_str(event) {
+                    errors.push(ConfigError::InvalidWebhookEvent(e));
+                }
+            }
+            for address in &webhook_settings.addresses {
+                if let Err(e) = Address::from_user_friendly_address(address) {
+                    errors.push(ConfigError::InvalidWebhookAddress(e));
+                }
+            }
+        }
+
+        if self.memory_budget.is_some() && self.memory_budget_bytes().map_or(true, |bytes| bytes == 0) {
+            errors.push(ConfigError::InvalidMemoryBudget);
+        }
+
+        if self.consensus.sync_rate_limit == Some(0) {
+            errors.push(ConfigError::InvalidSyncRateLimit);
+        }
+
+        for (name, num_threads) in self.threads.iter() {
+            if num_threads == Some(0) {
+                errors.push(ConfigError::InvalidThreadCount(name));
+            }
+        }
+
+        if self.mempool.as_ref().and_then(|mempool| mempool.transaction_ttl) == Some(0) {
+            errors.push(ConfigError::InvalidTransactionTtl);
+        }
+
+        if self.mempool.as_ref().and_then(|mempool| mempool.sender_limit) == Some(0) {
+            errors.push(ConfigError::InvalidSenderLimit);
+        }
+
+        if let Some(factor) = self.mempool.as_ref().and_then(|mempool| mempool.replacement_fee_factor) {
+            if !(factor >= 1.0) {
+                errors.push(ConfigError::InvalidReplacementFeeFactor(factor));
+            }
+        }
+
+        if self.database.backup_dir.is_some() && self.database.backend.unwrap_or_default() != DatabaseBackend::Lmdb {
+            errors.push(ConfigError::BackupRequiresLmdb(self.database.backend.unwrap_or_default()));
+        }
+
+        if self.database.passphrase().is_some() && self.database.backend.unwrap_or_default() != DatabaseBackend::Lmdb {
+            errors.push(ConfigError::EncryptionRequiresLmdb(self.database.backend.unwrap_or_default()));
+        }
+
+        if let Some(ref replica_settings) = self.replica {
+            if !NetworkId::from(self.consensus.network).is_albatross() {
+                errors.push(ConfigError::ReplicaRequiresAlbatross(self.consensus.network));
+            }
+            if self.database.backend.unwrap_or_default() != DatabaseBackend::Lmdb {
+                errors.push(ConfigError::ReplicaRequiresLmdb(self.database.backend.unwrap_or_default()));
+            }
+            if self.rpc_server.is_none() {
+                errors.push(ConfigError::ReplicaRequiresRpcServer);
+            }
+            if self.validator.is_some() || !self.webhook.is_empty() {
+                errors.push(ConfigError::ReplicaIsReadOnly);
+            }
+            if replica_settings.refresh_interval == Some(0) {
+                errors.push(ConfigError::InvalidReplicaRefreshInterval);
+            }
+        }
+
+        if self.sandbox.is_some() && !cfg!(target_os = "linux") {
+            errors.push(ConfigError::SandboxUnsupported);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(errors))
+        }
+    }
+}
+
+impl FromStr for ClientConfig {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(toml::from_str(s)?)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkSettings {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    pub instant_inbound: Option<bool>,
+    pub role: Option<NodeRole>,
+    #[serde(default)]
+    pub seed_nodes: Vec<Seed>,
+    pub tls: Option<TlsSettings>,
+    #[serde(default)]
+    pub peer_targets: PeerTargetsSettings,
+}
+
+/// An additional seed node or seed list.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Seed {
+    Uri(SeedUri),
+    Info(SeedInfo),
+    List(SeedList),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeedUri {
+    pub uri: String
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeedInfo {
+    pub host: String,
+    pub port: Option<u16>,
+    pub public_key: Option<String>,
+    pub peer_id: Option<String>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeedList {
+    pub list: String,
+    pub public_key: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Wss,
+    Ws,
+    Dumb,
+    Rtc,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Protocol::Ws
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeRole {
+    FullNode,
+    HistoryServer,
+    Validator,
+}
+
+/// Peer count targets per node role. Roles without a section use the defaults.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PeerTargetsSettings {
+    pub full_node: Option<PeerTargetSettings>,
+    pub history_server: Option<PeerTargetSettings>,
+    pub validator: Option<PeerTargetSettings>,
+    pub active_validator: Option<PeerTargetSettings>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PeerTargetSettings {
+    pub min_outbound: Option<usize>,
+    pub min_full_ws_outbound: Option<usize>,
+    pub recycling_threshold: Option<usize>,
+    pub prefer_validators: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsSettings {
+    pub identity_file: String,
+    pub identity_password: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ConsensusSettings {
+    #[serde(rename = "type")]
+    #[serde(default)]
+    pub node_type: NodeType,
+    #[serde(default)]
+    pub network: Network,
+    /// Maximum number of blocks applied per second until consensus is established.
+    #[serde(rename = "sync-rate-limit")]
+    #[serde(default)]
+    pub sync_rate_limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeType {
+    Full,
+    Light,
+    Nano,
+}
+
+impl Default for NodeType {
+    fn default() -> Self {
+        NodeType::Full
+    }
+}
+
+impl FromStr for NodeType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<NodeType, ()> {
+        Ok(match s.to_lowercase().as_str() {
+            "full" => NodeType::Full,
+            "light" => NodeType::Light,
+            "nano" => NodeType::Nano,
+            _ => return Err(())
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Network {
+    Main,
+    Test,
+    Dev,
+
+    TestAlbatross,
+    DevAlbatross,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Network::Main
+    }
+}
+
+impl FromStr for Network {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Network, ()> {
+        Ok(match s.to_lowercase().as_str() {
+            "main" => Network::Main,
+            "test" => Network::Test,
+            "dev" => Network::Dev,
+
+            "test-albatross" => Network::TestAlbatross,
+            "dev-albatross" => Network::DevAlbatross,
+            _ => return Err(())
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RpcServerSettings {
+    #[serde(deserialize_with = "deserialize_string_option")]
+    #[serde(serialize_with = "serialize_string_option")]
+    #[serde(default)]
+    pub bind: Option<NetAddress>,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub corsdomain: Vec<String>,
+    #[serde(default)]
+    pub allowip: Vec<String>,
+    #[serde(default)]
+    pub methods: Vec<String>,
+    pub username: Option<String>,
+    #[serde(deserialize_with = "deserialize_secret_option")]
+    #[serde(serialize_with = "serialize_secret_option")]
+    #[serde(default)]
+    pub password: Option<SecretBytes>,
+    /// Maximum number of method calls per minute, for each client IP address.
+    pub rate_limit: Option<usize>,
+    /// Terminate TLS in the RPC server instead of serving plain HTTP.
+    pub tls: Option<ServerTlsSettings>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerTlsSettings {
+    /// PEM file with the certificate chain of the server.
+    pub certificate_file: String,
+    /// PEM file with the private key of the server.
+    pub private_key_file: String,
+    /// PEM file with the CAs whose client certificates are accepted. If set, clients without
+    /// such a certificate are rejected.
+    pub client_ca_file: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsServerSettings {
+    #[serde(deserialize_with = "deserialize_string_option")]
+    #[serde(serialize_with = "serialize_string_option")]
+    #[serde(default)]
+    pub bind: Option<NetAddress>,
+    pub port: Option<u16>,
+    /// Only clients from these IP addresses or subnets, e.g. `10.0.0.0/8`, may connect.
+    #[serde(default)]
+    pub allowip: Vec<String>,
+    /// Username for basic authentication. Defaults to `metrics`.
+    pub username: Option<String>,
+    #[serde(deserialize_with = "deserialize_secret_option")]
+    #[serde(serialize_with = "serialize_secret_option")]
+    #[serde(default)]
+    pub password: Option<SecretBytes>,
+    /// Terminate TLS in the metrics server instead of serving plain HTTP.
+    pub tls: Option<ServerTlsSettings>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReverseProxySettings {
+    pub port: Option<u16>,
+    #[serde(deserialize_with = "deserialize_string")]
+    #[serde(serialize_with = "serialize_string")]
+    pub address: NetAddress,
+    #[serde(default)]
+    pub header: String,
+    #[serde(default)]
+    pub with_tls_termination: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LogSettings {
+    #[serde(deserialize_with = "deserialize_string_option")]
+    #[serde(serialize_with = "serialize_string_option")]
+    #[serde(default)]
+    pub level: Option<LevelFilter>,
+    #[serde(default)]
+    pub timestamps: bool,
+    #[serde(default)]
+    pub statistics: u64,
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_tags")]
+    #[serde(serialize_with = "serialize_tags")]
+    pub tags: HashMap<String, LevelFilter>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseSettings {
+    pub backend: Option<DatabaseBackend>,
+    pub path: Option<String>,
+    pub size: Option<usize>,
+    pub max_dbs: Option<u32>,
+    pub no_lmdb_sync: Option<bool>,
+    /// Defaults to true, i.e. the last commit may be undone by a crash.
+    pub no_lmdb_meta_sync: Option<bool>,
+    pub lmdb_write_map: Option<bool>,
+    pub no_lmdb_read_ahead: Option<bool>,
+    pub lmdb_max_readers: Option<u32>,
+    pub backup_dir: Option<String>,
+    /// Encrypts the database values with a key derived from this passphrase. Use `passphrase()`,
+    /// which also reads it from the environment.
+    #[serde(deserialize_with = "deserialize_secret_option")]
+    #[serde(serialize_with = "serialize_secret_option")]
+    #[serde(default)]
+    pub passphrase: Option<SecretBytes>,
+    /// Directory of the per-epoch environments that the block bodies of old epochs are moved to
+    /// (Albatross only).
+    pub history_dir: Option<String>,
+    /// Map size of each per-epoch environment.
+    pub history_size: Option<usize>,
+    /// Number of finalized epochs whose block bodies stay in the main database.
+    pub history_keep_epochs: Option<u32>,
+}
+
+impl DatabaseSettings {
+    /// Environment variable with the database passphrase, so that it doesn't have to be written
+    /// to the config file.
+    pub const PASSPHRASE_ENV_VAR: &'static str = "NIMIQ_DATABASE_PASSPHRASE";
+
+    /// The configured passphrase, or the one in `NIMIQ_DATABASE_PASSPHRASE`.
+    pub fn passphrase(&self) -> Option<SecretBytes> {
+        self.passphrase.clone()
+            .or_else(|| std::env::var(DatabaseSettings::PASSPHRASE_ENV_VAR).ok().map(SecretBytes::from))
+    }
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        DatabaseSettings {
+            backend: None,
+            path: None,
+            size: Some(1024 * 1024 * 50),
+            max_dbs: Some(16),
+            no_lmdb_sync: None,
+            no_lmdb_meta_sync: None,
+            lmdb_write_map: None,
+            no_lmdb_read_ahead: None,
+            lmdb_max_readers: None,
+            backup_dir: None,
+            passphrase: None,
+            history_dir: None,
+            history_size: None,
+            history_keep_epochs: None,
+        }
+    }
+}
+
+/// Storage backend of the database. RocksDB, MDBX and sled are only available if the client was
+/// built with the `rocksdb`, `mdbx` and `sled` feature respectively.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    Lmdb,
+    Rocksdb,
+    Mdbx,
+    Sled,
+}
+
+impl Default for DatabaseBackend {
+    fn default() -> Self {
+        DatabaseBackend::Lmdb
+    }
+}
+
+/// Number of threads of the thread pools. Unset values are derived from the number of CPU cores.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ThreadSettings {
+    /// Worker threads of the runtime that runs networking, consensus and the RPC server.
+    pub tokio_workers: Option<usize>,
+    /// Threads that verify the signatures of the validators' votes.
+    pub handel_verification: Option<usize>,
+    /// Threads that handle blockchain events for the validator.
+    pub blockchain_events: Option<usize>,
+}
+
+impl ThreadSettings {
+    pub fn tokio_workers(&self) -> usize {
+        self.tokio_workers.unwrap_or_else(num_cpus::get)
+    }
+
+    pub fn handel_verification(&self) -> usize {
+        self.handel_verification.unwrap_or_else(num_cpus::get)
+    }
+
+    /// Blockchain events mostly arrive one after another, so fewer threads are needed by default.
+    pub fn blockchain_events(&self) -> usize {
+        self.blockchain_events.unwrap_or_else(|| (num_cpus::get() / 4).max(1))
+    }
+
+    /// The configured values by their name in the config file.
+    fn iter(&self) -> impl Iterator<Item=(&'static str, Option<usize>)> {
+        vec![
+            ("tokio_workers", self.tokio_workers),
+            ("handel_verification", self.handel_verification),
+            ("blockchain_events", self.blockchain_events),
+        ].into_iter()
+    }
+}
+
+/// Number of epochs the derived data in the database is kept for, before it's garbage collected.
+/// A value of 0 keeps the data forever.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct GcSettings {
+    /// Rewards paid per epoch.
+    pub reward_log: Option<u32>,
+    /// Statistics per epoch.
+    pub epoch_stats: Option<u32>,
+    /// Indexed transactions of watched addresses.
+    pub watch_activity: Option<u32>,
+    /// Fork proofs the validator hasn't included yet.
+    pub fork_proofs: Option<u32>,
+    /// Infos of validators that aren't active.
+    pub validator_infos: Option<u32>,
+    /// Proofs of the view changes the validator took part in.
+    pub view_changes: Option<u32>,
+}
+
+impl GcSettings {
+    /// Rewards are kept by default, wallets show them.
+    pub fn reward_log(&self) -> u32 {
+        self.reward_log.unwrap_or(0)
+    }
+
+    pub fn epoch_stats(&self) -> u32 {
+        self.epoch_stats.unwrap_or(64)
+    }
+
+    /// The activity of unwatched addresses is removed regardless.
+    pub fn watch_activity(&self) -> u32 {
+        self.watch_activity.unwrap_or(0)
+    }
+
+    /// Fork proofs can only be included during the epoch after the fork.
+    pub fn fork_proofs(&self) -> u32 {
+        self.fork_proofs.unwrap_or(2)
+    }
+
+    pub fn validator_infos(&self) -> u32 {
+        self.validator_infos.unwrap_or(2)
+    }
+
+    /// View changes only matter for the block they happen at.
+    pub fn view_changes(&self) -> u32 {
+        self.view_changes.unwrap_or(1)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MempoolSettings {
+    pub blacklist_limit: Option<usize>,
+    pub filter: Option<MempoolFilterSettings>,
+    /// Factor by which a transaction must pay more fee than the transaction it replaces.
+    pub replacement_fee_factor: Option<f64>,
+    /// Seconds after which transactions that weren't included are evicted.
+    pub transaction_ttl: Option<u64>,
+    /// Maximum number of transactions of a single sender.
+    pub sender_limit: Option<usize>,
+    /// Maximum serialized size of all transactions in bytes.
+    pub bytes_limit: Option<usize>,
+    /// How transactions that were submitted locally are relayed to peers.
+    pub broadcast: Option<TransactionBroadcast>,
+    /// Number of peers for `TransactionBroadcast::Random`.
+    pub broadcast_peers: Option<usize>,
+    /// Number of blocks after which local transactions that weren't mined are relayed again.
+    pub rebroadcast_blocks: Option<u32>,
+}
+
+/// How transactions that were submitted locally are relayed to peers.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionBroadcast {
+    /// To all peers.
+    Flood,
+    /// To a random subset of the peers.
+    Random,
+    /// To the peers that are validators.
+    Validators,
+    /// To no one, such that only this node's validator includes them.
+    Private,
+}
+
+impl Default for TransactionBroadcast {
+    fn default() -> Self {
+        TransactionBroadcast::Flood
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MempoolFilterSettings {
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    #[serde(default)]
+    pub tx_fee: Coin,
+    #[serde(default)]
+    pub tx_fee_per_byte: f64,
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    #[serde(default)]
+    pub tx_value: Coin,
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    #[serde(default)]
+    pub tx_value_total: Coin,
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    #[serde(default)]
+    pub contract_fee: Coin,
+    #[serde(default)]
+    pub contract_fee_per_byte: f64,
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    #[serde(default)]
+    pub contract_value: Coin,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    pub creation_fee: Coin,
+    #[serde(default)]
+    pub creation_fee_per_byte: f64,
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    #[serde(default)]
+    pub creation_value: Coin,
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    #[serde(default)]
+    pub recipient_balance: Coin,
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    #[serde(default)]
+    pub sender_balance: Coin,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ValidatorSettings {
+    pub key_file: Option<String>,
+    /// File containing the validator key that `key_file` replaces during a key rotation. It keeps
+    /// signing until the new key is elected. The file is written by `newValidatorKey` and removed
+    /// once the key isn't used anymore. Defaults to `key_file` with a `.previous` suffix.
+    pub previous_key_file: Option<String>,
+    /// The address the validator's rewards are paid to, if it isn't the staker address.
+    pub reward_address: Option<String>,
+    pub reward_sweep: Option<RewardSweepSettings>,
+    #[serde(default)]
+    pub profile: ValidatorProfile,
+    /// Check produced blocks against the blockchain before relaying them.
+    #[serde(default)]
+    pub verify_blocks: bool,
+    /// Text put into the extra data of the produced micro blocks, e.g. the name of a pool.
+    pub extra_data: Option<String>,
+    /// Transactions that are left out of the produced micro blocks. They are still relayed.
+    #[serde(default)]
+    pub blacklist: BlacklistSettings,
+    /// Bytes of each produced micro block that are reserved for transactions submitted to this
+    /// node via RPC, ahead of the transactions that pay more fees.
+    #[serde(default)]
+    pub local_transactions_size: usize,
+    /// Leave contract creations out of the produced micro blocks. They are still relayed.
+    #[serde(default)]
+    pub exclude_contract_creations: bool,
+    /// Only produce blocks and view changes when asked to through the `test_*` RPC methods, which
+    /// are only available with this setting. Only allowed on dev networks.
+    #[serde(default)]
+    pub manual_block_production: bool,
+}
+
+impl ValidatorSettings {
+    pub fn is_minimal(&self) -> bool {
+        self.profile == ValidatorProfile::Minimal
+    }
+}
+
+/// Which services a validator runs besides producing blocks.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidatorProfile {
+    /// Runs all services of a full node.
+    Standard,
+    /// Only keeps a bounded mempool to fill its blocks and doesn't relay transactions, index
+    /// watched addresses or serve the watch RPC methods.
+    Minimal,
+}
+
+impl Default for ValidatorProfile {
+    fn default() -> Self {
+        ValidatorProfile::Standard
+    }
+}
+
+/// Transactions a validator doesn't include in its blocks.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BlacklistSettings {
+    /// Transactions from or to these addresses are left out.
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// Hashes of transactions that are left out.
+    #[serde(default)]
+    pub transactions: Vec<String>,
+}
+
+/// Periodically moves the accumulated rewards from the reward address to a cold address.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewardSweepSettings {
+    /// Key file of the reward address, which signs the sweep transactions.
+    pub key_file: String,
+    pub address: String,
+    /// Number of epochs between two sweeps.
+    pub interval: Option<u32>,
+    #[serde(deserialize_with = "deserialize_coin")]
+    #[serde(serialize_with = "serialize_coin")]
+    #[serde(default)]
+    pub fee: Coin,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdaterSettings {
+    pub manifest_url: String,
+    pub public_key: String,
+    pub interval: Option<u64>,
+    pub download_dir: Option<String>,
+}
+
+/// Read replica of a node on the same host. The `[database]` section must point to the node's
+/// database.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ReplicaSettings {
+    /// Seconds between two reloads of the node's head.
+    pub refresh_interval: Option<u64>,
+    /// RPC queries fail while the head is older than this many seconds.
+    pub max_staleness: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookSettings {
+    pub url: String,
+    /// Events to deliver, see `WebhookEvent`. If empty, all events are delivered.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Only deliver transactions from or to these addresses. If empty, all are delivered.
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// Secret to sign the requests with.
+    #[serde(deserialize_with = "deserialize_secret_option")]
+    #[serde(serialize_with = "serialize_secret_option")]
+    #[serde(default)]
+    pub secret: Option<SecretBytes>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxSettings {
+    /// Restrict filesystem access to the data directory and the configured files (Landlock).
+    pub filesystem: Option<bool>,
+    /// Deny syscalls the node never needs, e.g. `execve` or `ptrace` (seccomp).
+    pub syscalls: Option<bool>,
+    /// Additional paths the node may read from.
+    #[serde(default)]
+    pub read_paths: Vec<String>,
+}
@@ -1,25 +1,27 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt::Display;
 use std::str::FromStr;
+use std::time::Duration;
 
-use serde::{Deserialize, Deserializer};
+use failure::Fail;
+use hex::FromHex;
+use serde::{Deserialize, Deserializer, Serializer};
 use serde::de::Error;
 use url::Url;
-use hex::FromHex;
-use failure::Fail;
 
+use keys::{PublicKey, SecretBytes};
 use mempool::filter::{MempoolFilter, Rules};
-use mempool::MempoolConfig;
-use network_primitives::protocol::Protocol;
-use network_primitives::address::SeedList;
+use mempool::{BLOCK_TRANSACTIONS_SIZE, BroadcastStrategy, MempoolConfig, ORPHANS_MAX, REBROADCAST_BLOCKS, REPLACEMENT_FEE_FACTOR, SIZE_BYTES_MAX, SIZE_MAX, TRANSACTIONS_PER_SENDER_MAX};
+use network::network_config::{NodeRole, PeerCountTargets, Seed};
+use network_primitives::address::peer_uri::PeerUriError;
 use network_primitives::address::PeerUri;
-use network::network_config::Seed;
+use network_primitives::address::SeedList;
+use network_primitives::protocol::Protocol;
 use primitives::coin::Coin;
 use primitives::networks::NetworkId;
-use keys::PublicKey;
 
-use crate::settings as s;
-use std::collections::HashMap;
+use crate::config as s;
 
 /// Converts protocol from settings into 'normal' protocol
 impl From<s::Protocol> for Protocol {
@@ -33,6 +35,44 @@ impl From<s::Protocol> for Protocol {
     }
 }
 
+/// Converts node role from settings into 'normal' node role
+impl From<s::NodeRole> for NodeRole {
+    fn from(role: s::NodeRole) -> NodeRole {
+        match role {
+            s::NodeRole::FullNode => NodeRole::FullNode,
+            s::NodeRole::HistoryServer => NodeRole::HistoryServer,
+            s::NodeRole::Validator => NodeRole::Validator,
+        }
+    }
+}
+
+impl s::PeerTargetSettings {
+    /// Applies the configured values on top of `defaults`.
+    pub fn to_targets(&self, defaults: PeerCountTargets) -> PeerCountTargets {
+        PeerCountTargets {
+            min_outbound: self.min_outbound.unwrap_or(defaults.min_outbound),
+            min_full_ws_outbound: self.min_full_ws_outbound.unwrap_or(defaults.min_full_ws_outbound),
+            recycling_threshold: self.recycling_threshold.unwrap_or(defaults.recycling_threshold),
+            prefer_validators: self.prefer_validators.unwrap_or(defaults.prefer_validators),
+        }
+    }
+}
+
+impl s::PeerTargetsSettings {
+    /// Returns the configured peer count targets per role.
+    pub fn to_targets(&self) -> Vec<(NodeRole, PeerCountTargets)> {
+        vec![
+            (NodeRole::FullNode, &self.full_node),
+            (NodeRole::HistoryServer, &self.history_server),
+            (NodeRole::Validator, &self.validator),
+            (NodeRole::ActiveValidator, &self.active_validator),
+        ].into_iter()
+            .filter_map(|(role, settings)| settings.as_ref()
+                .map(|settings| (role, settings.to_targets(PeerCountTargets::default_for(role)))))
+            .collect()
+    }
+}
+
 /// Converts the network ID from settings into 'normal' network ID
 impl From<s::Network> for NetworkId {
     fn from(network: s::Network) -> NetworkId {
@@ -66,15 +106,29 @@ impl From<s::MempoolSettings> for MempoolConfig {
                 recipient_balance: f.recipient_balance,
             }
         } else { Rules::default() };
+        let broadcast_strategy = match mempool_settings.broadcast.unwrap_or_default() {
+            s::TransactionBroadcast::Flood => BroadcastStrategy::Flood,
+            s::TransactionBroadcast::Random => BroadcastStrategy::Random(mempool_settings.broadcast_peers.unwrap_or(s::DEFAULT_BROADCAST_PEERS)),
+            s::TransactionBroadcast::Validators => BroadcastStrategy::ValidatorsFirst,
+            s::TransactionBroadcast::Private => BroadcastStrategy::Private,
+        };
         MempoolConfig {
             filter_rules: rules,
-            filter_limit: mempool_settings.blacklist_limit.unwrap_or(MempoolFilter::DEFAULT_BLACKLIST_SIZE)
+            filter_limit: mempool_settings.blacklist_limit.unwrap_or(MempoolFilter::DEFAULT_BLACKLIST_SIZE),
+            block_transactions_size: BLOCK_TRANSACTIONS_SIZE,
+            size_limit: SIZE_MAX,
+            bytes_limit: mempool_settings.bytes_limit.unwrap_or(SIZE_BYTES_MAX),
+            sender_limit: mempool_settings.sender_limit.unwrap_or(TRANSACTIONS_PER_SENDER_MAX),
+            orphan_limit: ORPHANS_MAX,
+            relay_transactions: true,
+            broadcast_strategy,
+            rebroadcast_blocks: mempool_settings.rebroadcast_blocks.unwrap_or(REBROADCAST_BLOCKS),
+            replacement_fee_factor: mempool_settings.replacement_fee_factor.unwrap_or(REPLACEMENT_FEE_FACTOR),
+            transaction_ttl: mempool_settings.transaction_ttl.map(Duration::from_secs),
         }
     }
 }
 
-use network_primitives::address::peer_uri::PeerUriError;
-
 #[derive(Debug, Fail)]
 pub enum SeedError {
     #[fail(display = "Failed to parse peer URI: {}", _0)]
@@ -103,8 +157,11 @@ impl From<keys::ParseError> for SeedError {
     }
 }
 
-impl s::Seed {
-    pub fn try_from(seed: s::Seed) -> Result<Seed, SeedError> {
+/// Converts a seed from settings into a 'normal' seed
+impl TryFrom<s::Seed> for Seed {
+    type Error = SeedError;
+
+    fn try_from(seed: s::Seed) -> Result<Seed, SeedError> {
         Ok(match seed {
             s::Seed::Uri(s::SeedUri{uri}) => {
                 Seed::Peer(Box::new(PeerUri::from_str(&uri)?))
@@ -127,6 +184,10 @@ pub(crate) fn deserialize_coin<'de, D>(deserializer: D) -> Result<Coin, D::Error
     Coin::try_from(value).map_err(Error::custom)
 }
 
+pub(crate) fn serialize_coin<S>(coin: &Coin, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    serializer.serialize_u64(u64::from(*coin))
+}
+
 pub(crate) fn deserialize_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
     where D: Deserializer<'de>,
           T: FromStr,
@@ -135,6 +196,12 @@ pub(crate) fn deserialize_string<'de, D, T>(deserializer: D) -> Result<T, D::Err
     T::from_str(&value).map_err(Error::custom)
 }
 
+pub(crate) fn serialize_string<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer,
+          T: Display {
+    serializer.collect_str(value)
+}
+
 // NOTE: This is currently unused, but might be used in future.
 #[allow(dead_code)]
 pub(crate) fn deserialize_string_vec<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
@@ -156,6 +223,28 @@ pub(crate) fn deserialize_string_option<'de, D, T>(deserializer: D) -> Result<Op
     }
 }
 
+pub(crate) fn serialize_string_option<S, T>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer,
+          T: Display {
+    match value {
+        None => serializer.serialize_none(),
+        Some(value) => serializer.collect_str(value),
+    }
+}
+
+pub(crate) fn deserialize_secret_option<'de, D>(deserializer: D) -> Result<Option<SecretBytes>, D::Error>
+    where D: Deserializer<'de> {
+    Ok(Option::<String>::deserialize(deserializer)?.map(SecretBytes::from))
+}
+
+pub(crate) fn serialize_secret_option<S>(value: &Option<SecretBytes>, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+    match value {
+        None => serializer.serialize_none(),
+        Some(value) => serializer.serialize_str(&String::from_utf8_lossy(value.as_bytes())),
+    }
+}
+
 pub(crate) fn deserialize_tags<'de, D, T>(deserializer: D) -> Result<HashMap<String, T>, D::Error>
     where D: Deserializer<'de>,
           T: FromStr,
@@ -167,3 +256,9 @@ pub(crate) fn deserialize_tags<'de, D, T>(deserializer: D) -> Result<HashMap<Str
     }
     Ok(tags)
 }
+
+pub(crate) fn serialize_tags<S, T>(tags: &HashMap<String, T>, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer,
+          T: Display {
+    serializer.collect_map(tags.iter().map(|(k, v)| (k, v.to_string())))
+}
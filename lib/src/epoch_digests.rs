@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use failure::Fail;
+
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::blockchain::BlockchainEvent;
+use hash::Blake2bHash;
+use primitives::policy;
+use utils::observer::Subscription;
+
+
+#[derive(Debug, Fail)]
+pub enum EpochDigestsError {
+    #[fail(display = "Failed to read epoch digests: {}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "Invalid epoch digest in line {}", _0)]
+    InvalidLine(usize),
+}
+
+impl From<io::Error> for EpochDigestsError {
+    fn from(e: io::Error) -> Self {
+        EpochDigestsError::Io(e)
+    }
+}
+
+/// Expected epoch state digests, e.g. as published by a trusted node. Comparing them to the
+/// digests of our own macro blocks detects a silently corrupted chain or state.
+///
+/// There is one epoch per line, given as the epoch number and the hex-encoded digest separated by
+/// whitespace. Empty lines and lines starting with `#` are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpochDigests(BTreeMap<u32, Blake2bHash>);
+
+impl EpochDigests {
+    pub fn load(path: &str) -> Result<Self, EpochDigestsError> {
+        Self::from_str(&fs::read_to_string(path)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns whether `digest` is the expected digest of `epoch`, or `None` if no digest is
+    /// expected for it.
+    pub fn check(&self, epoch: u32, digest: &Blake2bHash) -> Option<bool> {
+        self.0.get(&epoch).map(|expected| expected == digest)
+    }
+
+    /// Checks all epochs `blockchain` finalized already and all epochs it will finalize while the
+    /// returned subscription is kept. The results are logged.
+    pub fn watch(self, blockchain: &Arc<Blockchain<'static>>) -> Subscription<'static, BlockchainEvent> {
+        info!("Cross-checking the state digests of {} epochs", self.len());
+
+        let finalized_epoch = policy::epoch_at(blockchain.macro_head().header.block_number);
+        for &epoch in self.0.range(..=finalized_epoch).map(|(epoch, _)| epoch) {
+            if let Some(digest) = blockchain.epoch_state_digest(epoch) {
+                self.log_check(epoch, &digest);
+            }
+        }
+
+        let weak = Arc::downgrade(blockchain);
+        blockchain.notifier.write().subscribe(move |event: &BlockchainEvent| {
+            if let BlockchainEvent::Finalized(hash) = event {
+                if let Some(blockchain) = weak.upgrade() {
+                    let epoch = match blockchain.get_block(hash, false, false) {
+                        Some(block) => policy::epoch_at(block.block_number()),
+                        None => return,
+                    };
+                    if let Some(digest) = blockchain.epoch_state_digest(epoch) {
+                        self.log_check(epoch, &digest);
+                    }
+                }
+            }
+        })
+    }
+
+    fn log_check(&self, epoch: u32, digest: &Blake2bHash) {
+        match self.check(epoch, digest) {
+            Some(true) => info!("Epoch {} state digest matches the expected digest", epoch),
+            Some(false) => error!("Epoch {} state digest {} doesn't match the expected digest {}. The chain or state might be corrupted!",
+                epoch, digest, self.0[&epoch]),
+            None => {},
+        }
+    }
+}
+
+impl FromStr for EpochDigests {
+    type Err = EpochDigestsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut digests = BTreeMap::new();
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || EpochDigestsError::InvalidLine(index + 1);
+            let mut parts = line.split_whitespace();
+            let epoch = parts.next().and_then(|epoch| u32::from_str(epoch).ok()).ok_or_else(invalid)?;
+            let digest = parts.next().and_then(|digest| Blake2bHash::from_str(digest).ok()).ok_or_else(invalid)?;
+            if parts.next().is_some() {
+                return Err(invalid());
+            }
+            digests.insert(epoch, digest);
+        }
+        Ok(EpochDigests(digests))
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use futures_cpupool::{Builder, CpuPool};
+use parking_lot::{Mutex, RwLock};
+
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::blockchain::BlockchainEvent;
+use blockchain_albatross::watch_registry::WatchRegistry;
+use consensus::{AlbatrossConsensusProtocol, Consensus};
+use database::{Environment, WriteTransaction};
+use primitives::policy;
+use utils::observer::Subscription;
+
+use crate::config::GcSettings;
+
+
+/// Removes the data of a store that is older than the given epoch.
+type PruneFn = Box<dyn Fn(&mut WriteTransaction, u32) + Send + Sync>;
+
+struct Store {
+    name: &'static str,
+    /// `None` for stores whose entries expire on their own, which are purged at each collection.
+    retention_epochs: Option<u32>,
+    prune: PruneFn,
+}
+
+/// Removes derived data, like the reward log or the epoch statistics, once it is older than the
+/// configured number of epochs, and entries that expired on their own, like IP bans. Collection
+/// runs after each finalized epoch, in a single database transaction for all stores.
+///
+/// A node has a single collector, with which all stores are registered. It stops once it is
+/// dropped, together with its subscription to the blockchain.
+pub struct GarbageCollector {
+    settings: GcSettings,
+    env: &'static Environment,
+    blockchain: Arc<Blockchain<'static>>,
+    stores: RwLock<Vec<Store>>,
+    /// Runs the collections, which wait for the database writer and lock state that is held
+    /// while pushing blocks.
+    pool: CpuPool,
+    subscription: Mutex<Option<Subscription<'static, BlockchainEvent>>>,
+}
+
+impl GarbageCollector {
+    /// Starts the collector for the blockchain of `consensus`, without any stores. They are added
+    /// with `register`.
+    pub fn start(consensus: &Arc<Consensus<AlbatrossConsensusProtocol>>, settings: GcSettings) -> Arc<Self> {
+        Self::new(consensus.env, &consensus.blockchain, settings)
+    }
+
+    /// Starts the collector for `blockchain`, whose data is stored in `env`.
+    pub fn new(env: &'static Environment, blockchain: &Arc<Blockchain<'static>>, settings: GcSettings) -> Arc<Self> {
+        let this = Arc::new(GarbageCollector {
+            settings,
+            env,
+            blockchain: Arc::clone(blockchain),
+            stores: RwLock::new(Vec::new()),
+            pool: Builder::new()
+                .pool_size(1)
+                .name_prefix("gc-")
+                .create(),
+            subscription: Mutex::new(None),
+        });
+
+        let weak = Arc::downgrade(&this);
+        let subscription = blockchain.notifier.write().subscribe(move |event: &BlockchainEvent| {
+            if let BlockchainEvent::Finalized(hash) = event {
+                if let Some(this) = weak.upgrade() {
+                    let epoch = match this.blockchain.get_block(hash, false, false) {
+                        Some(block) => policy::epoch_at(block.block_number()),
+                        None => return,
+                    };
+                    // The blockchain still holds its push lock while notifying us.
+                    let collector = Arc::clone(&this);
+                    this.pool.spawn_fn(move || -> Result<(), ()> {
+                        collector.collect(epoch);
+                        Ok(())
+                    }).forget();
+                }
+            }
+        });
+        *this.subscription.lock() = Some(subscription);
+
+        this
+    }
+
+    /// How long the data of the stores is kept for.
+    pub fn settings(&self) -> &GcSettings {
+        &self.settings
+    }
+
+    /// Registers the reward log and the epoch statistics of the blockchain.
+    pub fn register_blockchain(&self) {
+        let blockchain = Arc::clone(&self.blockchain);
+        self.register("reward log", self.settings.reward_log(), move |txn, epoch| {
+            blockchain.state().reward_registry().prune_epoch_rewards(txn, epoch);
+        });
+        let blockchain = Arc::clone(&self.blockchain);
+        self.register("epoch stats", self.settings.epoch_stats(), move |_, epoch| {
+            blockchain.stats_cache().prune(epoch);
+        });
+    }
+
+    /// Registers the expired IP bans of the network of `consensus`.
+    pub fn register_network(&self, consensus: &Arc<Consensus<AlbatrossConsensusProtocol>>) {
+        let connections = Arc::clone(&consensus.network.connections);
+        self.register_expiring("IP bans", move |txn| {
+            connections.purge_expired_bans(txn);
+        });
+    }
+
+    /// Registers the indexed transactions of the watched addresses. The activity of addresses
+    /// that are no longer watched is removed at each collection.
+    pub fn register_watch_registry(&self, registry: Arc<WatchRegistry<'static>>) {
+        let unwatched = Arc::clone(&registry);
+        self.register_expiring("activity of unwatched addresses", move |txn| {
+            unwatched.purge_unwatched(txn);
+        });
+        self.register("watch activity", self.settings.watch_activity(), move |txn, epoch| {
+            registry.prune(txn, policy::first_block_of(epoch));
+        });
+    }
+
+    /// Keeps the data `prune` is responsible for during `retention_epochs` epochs, including the
+    /// finalized one. `prune` is called with the first epoch to keep. A retention of 0 keeps the
+    /// data forever.
+    pub fn register<F>(&self, name: &'static str, retention_epochs: u32, prune: F)
+        where F: Fn(&mut WriteTransaction, u32) + Send + Sync + 'static {
+        if retention_epochs == 0 {
+            debug!("Keeping {} forever", name);
+            return;
+        }
+        self.stores.write().push(Store {
+            name,
+            retention_epochs: Some(retention_epochs),
+            prune: Box::new(prune),
+        });
+    }
+
+    /// Calls `purge` at each collection, for data that expires on its own instead of with the
+    /// epochs.
+    pub fn register_expiring<F>(&self, name: &'static str, purge: F)
+        where F: Fn(&mut WriteTransaction) + Send + Sync + 'static {
+        self.stores.write().push(Store {
+            name,
+            retention_epochs: None,
+            prune: Box::new(move |txn, _| purge(txn)),
+        });
+    }
+
+    /// Prunes all stores after `epoch` was finalized. This runs on its own after each finalized
+    /// epoch.
+    pub fn collect(&self, epoch: u32) {
+        let stores = self.stores.read();
+        if stores.is_empty() {
+            return;
+        }
+
+        let mut txn = WriteTransaction::new(self.env);
+        for store in stores.iter() {
+            match store.retention_epochs {
+                Some(retention_epochs) => {
+                    // Epoch 0 only contains the genesis block.
+                    let keep_from = (epoch + 1).saturating_sub(retention_epochs).max(1);
+                    trace!("Pruning {} before epoch {}", store.name, keep_from);
+                    (store.prune)(&mut txn, keep_from);
+                },
+                None => {
+                    trace!("Purging expired {}", store.name);
+                    (store.prune)(&mut txn, epoch);
+                },
+            }
+        }
+        txn.commit();
+        debug!("Collected garbage of {} stores after epoch {}", stores.len(), epoch);
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use failure::Fail;
+use parking_lot::{Mutex, RwLock};
+
+use block_albatross::{MacroHeader, MacroHeaderDiff, MacroHeaderDiffError};
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::blockchain::BlockchainEvent;
+use consensus::{AlbatrossConsensusProtocol, Consensus};
+use hash::{Blake2bHash, Hash};
+use network::{NetworkEvent, Peer};
+use network::connection::close_type::CloseType;
+use network::peer_channel::PeerChannel;
+use network_messages::Message;
+use primitives::policy;
+use utils::observer::{weak_passthru_listener, Notifier, Subscription};
+
+
+#[derive(Debug, Fail)]
+pub enum HeaderDiffError {
+    #[fail(display = "Block {} is not a macro block", _0)]
+    NotAMacroBlock(u32),
+    #[fail(display = "Validators elected in epoch {} are unknown", _0)]
+    UnknownValidators(u32),
+    #[fail(display = "{}", _0)]
+    InvalidDiff(#[cause] MacroHeaderDiffError),
+}
+
+impl From<MacroHeaderDiffError> for HeaderDiffError {
+    fn from(e: MacroHeaderDiffError) -> Self {
+        HeaderDiffError::InvalidDiff(e)
+    }
+}
+
+/// Sends the header of each finalized macro block to light and nano peers, with the validators
+/// encoded as the diff to those of the previous macro block (see `MacroHeaderDiff`).
+///
+/// Older peers don't know the message, so it is only sent to peers that announce
+/// `ServiceFlags::MACRO_HEADER_DIFFS`. Peers that can't rebuild the header, e.g. because they
+/// missed the previous one, can still request the full header.
+///
+/// Received diffs are rebuilt against our own chain, and headers we don't have yet are passed to
+/// `notifier`. They are not verified beyond their validators.
+pub struct MacroHeaderDiffRelay {
+    blockchain: Arc<Blockchain<'static>>,
+    peers: RwLock<HashSet<Arc<Peer>>>,
+    pub notifier: RwLock<Notifier<'static, MacroHeader>>,
+    network_subscription: Mutex<Option<Subscription<'static, NetworkEvent>>>,
+    blockchain_subscription: Mutex<Option<Subscription<'static, BlockchainEvent>>>,
+}
+
+impl MacroHeaderDiffRelay {
+    pub fn new(blockchain: Arc<Blockchain<'static>>) -> Arc<Self> {
+        Arc::new(MacroHeaderDiffRelay {
+            blockchain,
+            peers: RwLock::new(HashSet::new()),
+            notifier: RwLock::new(Notifier::new()),
+            network_subscription: Mutex::new(None),
+            blockchain_subscription: Mutex::new(None),
+        })
+    }
+
+    pub fn start(consensus: &Arc<Consensus<AlbatrossConsensusProtocol>>) -> Arc<Self> {
+        let this = Self::new(Arc::clone(&consensus.blockchain));
+
+        let weak = Arc::downgrade(&this);
+        let subscription = consensus.network.notifier.write().subscribe(move |event: &NetworkEvent| {
+            if let Some(this) = weak.upgrade() {
+                match event {
+                    NetworkEvent::PeerJoined(peer) => Self::on_peer_joined(&this, peer),
+                    NetworkEvent::PeerLeft(peer) => { this.peers.write().remove(peer); },
+                    _ => {},
+                }
+            }
+        });
+        *this.network_subscription.lock() = Some(subscription);
+
+        let weak = Arc::downgrade(&this);
+        let subscription = consensus.blockchain.notifier.write().subscribe(move |event: &BlockchainEvent| {
+            if let BlockchainEvent::Finalized(hash) = event {
+                if let Some(this) = weak.upgrade() {
+                    let epoch = match this.blockchain.get_block(hash, false, false) {
+                        Some(block) => policy::epoch_at(block.block_number()),
+                        None => return,
+                    };
+                    this.on_epoch_finalized(epoch);
+                }
+            }
+        });
+        *this.blockchain_subscription.lock() = Some(subscription);
+
+        this
+    }
+
+    fn on_peer_joined(this: &Arc<Self>, peer: &Arc<Peer>) {
+        // The channel owns the listener, so only hold a weak reference to it.
+        let channel = Arc::downgrade(&peer.channel);
+        peer.channel.msg_notifier.macro_header_diff.write().register(weak_passthru_listener(
+            Arc::downgrade(this),
+            move |this, header_diff: MacroHeaderDiff| {
+                if let Some(channel) = channel.upgrade() {
+                    this.on_header_diff(&channel, header_diff);
+                }
+            }));
+
+        let services = peer.peer_address().services;
+        if services.supports_macro_header_diffs() && (services.is_light_node() || services.is_nano_node()) {
+            this.peers.write().insert(Arc::clone(peer));
+        }
+    }
+
+    fn on_epoch_finalized(&self, epoch: u32) {
+        let peers = self.peers.read();
+        if peers.is_empty() {
+            return;
+        }
+
+        let header_diff = match self.blockchain.get_macro_header_diff(epoch) {
+            Some(header_diff) => header_diff,
+            None => return,
+        };
+        trace!("Sending macro header diff of epoch {} to {} peers", epoch, peers.len());
+        for peer in peers.iter() {
+            peer.channel.send_or_close(Message::MacroHeaderDiff(Box::new(header_diff.clone())));
+        }
+    }
+
+    fn on_header_diff(&self, channel: &PeerChannel, header_diff: MacroHeaderDiff) {
+        match self.receive(header_diff) {
+            Ok(_) => {},
+            // We can't rebuild the header, but the peer did nothing wrong.
+            Err(HeaderDiffError::UnknownValidators(epoch)) => {
+                debug!("Ignoring macro header diff from {}: validators of epoch {} are unknown", channel.address_info, epoch);
+            },
+            Err(e) => {
+                debug!("Invalid macro header diff from {}: {}", channel.address_info, e);
+                channel.close(CloseType::ReceivedInvalidHeader);
+            },
+        }
+    }
+
+    /// Rebuilds the header of a received macro header diff from the validators elected by the
+    /// previous macro block of our chain. Returns the header if it isn't on our chain yet, after
+    /// passing it to `notifier`.
+    pub fn receive(&self, header_diff: MacroHeaderDiff) -> Result<Option<MacroHeader>, HeaderDiffError> {
+        let block_number = header_diff.header.block_number;
+        let epoch = policy::epoch_at(block_number);
+        if epoch == 0 || policy::macro_block_of(epoch) != block_number {
+            return Err(HeaderDiffError::NotAMacroBlock(block_number));
+        }
+
+        let prev_validators = self.blockchain.get_elected_validators(epoch - 1)
+            .ok_or(HeaderDiffError::UnknownValidators(epoch - 1))?;
+        let header = header_diff.into_header(&prev_validators)?;
+
+        // Headers we already have are only sent to keep light peers up to date. A different header
+        // at the same height belongs to a fork that the regular block sync deals with.
+        if let Some(block) = self.blockchain.get_block_at(block_number, false) {
+            if block.hash() != header.hash::<Blake2bHash>() {
+                debug!("Received macro header diff #{} of another chain", block_number);
+            }
+            return Ok(None);
+        }
+
+        self.notifier.read().notify(header.clone());
+        Ok(Some(header))
+    }
+}
@@ -0,0 +1,61 @@
+use std::cmp;
+use std::sync::Arc;
+
+use futures::future;
+
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::blockchain::BlockchainEvent;
+use primitives::policy;
+use utils::observer::Subscription;
+
+
+/// Moves the block bodies of finalized epochs into the history shards of the blockchain, once
+/// `keep_epochs` newer epochs are finalized, see `Blockchain::archive_epoch`.
+///
+/// Archiving stops when the archiver is dropped.
+pub struct HistoryArchiver {
+    _subscription: Subscription<'static, BlockchainEvent>,
+}
+
+impl HistoryArchiver {
+    /// The blocks of the transaction validity window are loaded with their bodies on startup,
+    /// before the history shards are set, so their epochs are always kept.
+    pub const MIN_KEEP_EPOCHS: u32 = policy::TRANSACTION_VALIDITY_WINDOW_ALBATROSS / policy::EPOCH_LENGTH + 1;
+
+    /// The blockchain must have history shards, see `Blockchain::set_history`. At least
+    /// `MIN_KEEP_EPOCHS` are kept.
+    pub fn start(blockchain: &Arc<Blockchain<'static>>, keep_epochs: u32) -> Self {
+        let keep_epochs = cmp::max(keep_epochs, Self::MIN_KEEP_EPOCHS);
+        let weak = Arc::downgrade(blockchain);
+        let subscription = blockchain.notifier.write().subscribe(move |event: &BlockchainEvent| {
+            let hash = match event {
+                BlockchainEvent::Finalized(hash) => hash,
+                _ => return,
+            };
+            let blockchain = match weak.upgrade() {
+                Some(blockchain) => blockchain,
+                None => return,
+            };
+            let finalized_epoch = match blockchain.get_block(hash, false, false) {
+                Some(block) => policy::epoch_at(block.block_number()),
+                None => return,
+            };
+            let epoch = match finalized_epoch.checked_sub(keep_epochs) {
+                Some(epoch) => epoch,
+                None => return,
+            };
+
+            // Copying the bodies of an epoch takes a while, and the blockchain still holds its
+            // push lock while notifying us.
+            tokio::spawn(future::poll_fn(move || {
+                tokio_threadpool::blocking(|| match blockchain.archive_epoch(epoch) {
+                    Ok(0) => {},
+                    Ok(count) => info!("Archived {} blocks of epoch {}", count, epoch),
+                    Err(e) => warn!("Failed to archive epoch {}: {}", epoch, e),
+                }).map_err(|e| error!("Failed to archive epoch {}: {}", epoch, e))
+            }));
+        });
+
+        HistoryArchiver { _subscription: subscription }
+    }
+}
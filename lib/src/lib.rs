@@ -1,20 +1,47 @@
 #[macro_use]
+extern crate json;
+#[macro_use]
 extern crate lazy_static;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
 
+extern crate nimiq_block_albatross as block_albatross;
+extern crate nimiq_blockchain_albatross as blockchain_albatross;
 extern crate nimiq_consensus as consensus;
 extern crate nimiq_database as database;
+extern crate nimiq_hash as hash;
+extern crate nimiq_keys as keys;
 extern crate nimiq_network as network;
+extern crate nimiq_messages as network_messages;
 extern crate nimiq_network_primitives as network_primitives;
 extern crate nimiq_primitives as primitives;
 extern crate nimiq_mempool as mempool;
+extern crate nimiq_transaction as transaction;
 extern crate nimiq_utils as utils;
 
 #[cfg(feature = "validator")]
 extern crate nimiq_validator as validator;
 #[cfg(feature = "validator")]
+extern crate nimiq_block_production_albatross as block_production_albatross;
+#[cfg(feature = "validator")]
 extern crate nimiq_bls as bls;
+#[cfg(feature = "validator")]
+extern crate nimiq_wallet as wallet;
 
 pub mod prelude;
 pub mod client;
+pub mod config;
 pub mod error;
-pub mod block_producer;
\ No newline at end of file
+pub mod block_producer;
+pub mod payment;
+pub mod replica;
+pub mod epoch_digests;
+pub mod gc;
+pub mod header_diffs;
+pub mod history;
+#[cfg(feature = "validator")]
+pub mod rewards;
+pub mod updater;
+pub mod webhooks;
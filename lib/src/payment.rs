@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use failure::Fail;
+use futures::{Async, Future, Poll};
+use futures::sync::oneshot;
+use parking_lot::Mutex;
+
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::confirmations::{ConfirmationPolicy, ConfirmationTracker, ConfirmedBlock};
+use hash::Blake2bHash;
+use keys::Address;
+use primitives::coin::Coin;
+use transaction::Transaction;
+use utils::observer::Subscription;
+
+
+#[derive(Debug, Fail)]
+pub enum PaymentError {
+    #[fail(display = "Payment watcher was dropped")]
+    Cancelled,
+}
+
+/// A transaction that satisfied a payment request.
+#[derive(Clone, Debug)]
+pub struct Payment {
+    pub transaction: Transaction,
+    pub block_hash: Blake2bHash,
+    pub block_number: u32,
+    pub timestamp: u64,
+    pub transaction_index: usize,
+}
+
+/// Future that resolves once a transaction paying at least `min_value` to `recipient` has been
+/// confirmed according to the given `ConfirmationPolicy`.
+///
+/// Only transactions in blocks that weren't confirmed yet at creation time are considered.
+pub struct PaymentFuture {
+    receiver: oneshot::Receiver<Payment>,
+    // Keep the tracker and our listener alive until the payment arrived.
+    _subscription: Subscription<'static, ConfirmedBlock>,
+    _tracker: Arc<ConfirmationTracker<'static>>,
+}
+
+impl PaymentFuture {
+    pub fn new(blockchain: Arc<Blockchain<'static>>, recipient: Address, min_value: Coin, policy: ConfirmationPolicy) -> Self {
+        Self::with_tracker(ConfirmationTracker::from_head(blockchain, policy), recipient, min_value)
+    }
+
+    /// Waits for a payment in the blocks emitted by `tracker`.
+    pub fn with_tracker(tracker: Arc<ConfirmationTracker<'static>>, recipient: Address, min_value: Coin) -> Self {
+        let (sender, receiver) = oneshot::channel();
+        let sender = Mutex::new(Some(sender));
+
+        let subscription = tracker.notifier.write().subscribe(move |block: &ConfirmedBlock| {
+            if let Some((index, transaction)) = block.transactions_to(&recipient, min_value).next() {
+                if let Some(sender) = sender.lock().take() {
+                    // The receiver might have been dropped already, which is fine.
+                    sender.send(Payment {
+                        transaction: transaction.clone(),
+                        block_hash: block.hash.clone(),
+                        block_number: block.block.block_number(),
+                        timestamp: block.block.timestamp(),
+                        transaction_index: index,
+                    }).ok();
+                }
+            }
+        });
+
+        PaymentFuture {
+            receiver,
+            _subscription: subscription,
+            _tracker: tracker,
+        }
+    }
+}
+
+impl Future for PaymentFuture {
+    type Item = Payment;
+    type Error = PaymentError;
+
+    fn poll(&mut self) -> Poll<Payment, PaymentError> {
+        match self.receiver.poll() {
+            Ok(Async::Ready(payment)) => Ok(Async::Ready(payment)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(_) => Err(PaymentError::Cancelled),
+        }
+    }
+}
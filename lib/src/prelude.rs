@@ -1,4 +1,5 @@
 pub use crate::client::{Client, ClientBuilder};
+pub use crate::config::ClientConfig;
 pub use crate::error::ClientError;
 pub use database::Environment;
 pub use primitives::networks::NetworkId;
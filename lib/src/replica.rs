@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use failure::Fail;
+use futures::{future, Future, Stream};
+use tokio::timer::Interval;
+
+use blockchain_albatross::Blockchain;
+use consensus::error::Error as ConsensusError;
+use database::Environment;
+use primitives::networks::NetworkId;
+
+use crate::error::ClientError;
+
+
+#[derive(Debug, Fail)]
+pub enum ReplicaError {
+    #[fail(display = "Replica is stale, its head is {} seconds old", _0)]
+    Stale(u64),
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplicaConfig {
+    /// How often the head is reloaded from the database.
+    pub refresh_interval: Duration,
+    /// Queries are refused while the head is older than this.
+    pub max_staleness: Duration,
+}
+
+impl ReplicaConfig {
+    pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+    pub const DEFAULT_MAX_STALENESS: Duration = Duration::from_secs(60);
+}
+
+impl Default for ReplicaConfig {
+    fn default() -> Self {
+        ReplicaConfig {
+            refresh_interval: Self::DEFAULT_REFRESH_INTERVAL,
+            max_staleness: Self::DEFAULT_MAX_STALENESS,
+        }
+    }
+}
+
+
+/// Follows the blockchain of a node in another process, whose LMDB environment is opened
+/// read-only, so RPC queries can be served without loading the node itself.
+///
+/// The replica only sees blocks the node has committed, and reloads the head every
+/// `refresh_interval`. If the node stops producing or syncing blocks, the head gets older; queries
+/// should be refused once it is older than `max_staleness`, see `check_staleness`.
+pub struct Replica {
+    pub blockchain: Arc<Blockchain<'static>>,
+    config: ReplicaConfig,
+}
+
+impl Replica {
+    /// Opens the blockchain in `env`, which must have been initialized by the node.
+    pub fn new(env: &'static Environment, network_id: NetworkId, config: ReplicaConfig) -> Result<Self, ClientError> {
+        let blockchain = Blockchain::new(env, network_id).map_err(ConsensusError::from)?;
+        Ok(Replica {
+            blockchain: Arc::new(blockchain),
+            config,
+        })
+    }
+
+    /// Reloads the head from the database.
+    pub fn refresh(&self) {
+        match self.blockchain.reload() {
+            Ok(true) => debug!("Replica head is now #{}", self.blockchain.block_number()),
+            Ok(false) => {},
+            Err(e) => warn!("Failed to reload the replicated blockchain: {}", e),
+        }
+    }
+
+    /// Time since the head block was produced.
+    pub fn head_age(&self) -> Duration {
+        let timestamp = Duration::from_millis(self.blockchain.head().timestamp());
+        SystemTime::now().duration_since(UNIX_EPOCH)
+            .ok()
+            .and_then(|now| now.checked_sub(timestamp))
+            .unwrap_or_default()
+    }
+
+    /// Fails if the head is older than `max_staleness`.
+    pub fn check_staleness(&self) -> Result<(), ReplicaError> {
+        let age = self.head_age();
+        if age > self.config.max_staleness {
+            return Err(ReplicaError::Stale(age.as_secs()));
+        }
+        Ok(())
+    }
+
+    /// Returns a future that reloads the head every `refresh_interval` and never resolves.
+    ///
+    /// Reloading reads from the database, so it runs on the runtime's blocking threads. Ticks
+    /// that are missed while a reload takes longer than the interval are skipped.
+    pub fn run(this: Arc<Self>) -> impl Future<Item=(), Error=()> {
+        Interval::new(Instant::now(), this.config.refresh_interval)
+            .map_err(|e| error!("Replica timer failed: {}", e))
+            .for_each(move |_| {
+                let this = Arc::clone(&this);
+                future::poll_fn(move || tokio_threadpool::blocking(|| this.refresh()))
+                    .map_err(|e| error!("Failed to reload the replicated blockchain: {}", e))
+            })
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use futures::future;
+use parking_lot::Mutex;
+
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::blockchain::BlockchainEvent;
+use block_production_albatross::validator_keys::ValidatorKeys;
+use consensus::{AlbatrossConsensusProtocol, Consensus};
+use keys::{Address, KeyPair};
+use mempool::{Mempool, ReturnCode};
+use primitives::coin::Coin;
+use primitives::policy;
+use utils::observer::Subscription;
+use wallet::WalletAccount;
+
+
+/// Moves the rewards accumulated on the reward address to `address` every `interval` epochs.
+#[derive(Clone, Debug)]
+pub struct RewardSweepConfig {
+    /// The key pair of the reward address.
+    pub key_pair: KeyPair,
+    pub address: Address,
+    pub interval: u32,
+    pub fee: Coin,
+}
+
+/// Watches the rewards of a validator: At each macro block, it checks that the slots the validator
+/// was elected for pay to the expected reward address and optionally sweeps the rewards. During a
+/// key rotation, the slots of both keys are checked.
+///
+/// The watcher stops once it is dropped, together with its subscription to the blockchain.
+pub struct RewardWatcher {
+    blockchain: Arc<Blockchain<'static>>,
+    mempool: Arc<Mempool<'static, Blockchain<'static>>>,
+    validator_keys: Arc<ValidatorKeys>,
+    reward_address: Address,
+    sweep: Option<RewardSweepConfig>,
+    subscription: Mutex<Option<Subscription<'static, BlockchainEvent>>>,
+}
+
+impl RewardWatcher {
+    pub fn watch(consensus: &Arc<Consensus<AlbatrossConsensusProtocol>>, validator_keys: Arc<ValidatorKeys>,
+                 reward_address: Address, sweep: Option<RewardSweepConfig>) -> Arc<Self> {
+        if let Some(ref sweep) = sweep {
+            info!("Sweeping rewards from {} to {} every {} epochs", reward_address.to_user_friendly_address(),
+                  sweep.address.to_user_friendly_address(), sweep.interval);
+        }
+
+        let this = Arc::new(RewardWatcher {
+            blockchain: Arc::clone(&consensus.blockchain),
+            mempool: Arc::clone(&consensus.mempool),
+            validator_keys,
+            reward_address,
+            sweep,
+            subscription: Mutex::new(None),
+        });
+        this.check_slots();
+
+        let weak = Arc::downgrade(&this);
+        let subscription = consensus.blockchain.notifier.write().subscribe(move |event: &BlockchainEvent| {
+            if let BlockchainEvent::Finalized(hash) = event {
+                if let Some(this) = weak.upgrade() {
+                    let epoch = match this.blockchain.get_block(hash, false, false) {
+                        Some(block) => policy::epoch_at(block.block_number()),
+                        None => return,
+                    };
+                    // The blockchain still holds its push lock while notifying us, but pushing to
+                    // the mempool needs it.
+                    tokio::spawn(future::lazy(move || {
+                        this.on_epoch_finalized(epoch);
+                        Ok(())
+                    }));
+                }
+            }
+        });
+        *this.subscription.lock() = Some(subscription);
+
+        this
+    }
+
+    fn on_epoch_finalized(&self, epoch: u32) {
+        self.check_slots();
+
+        if let Some(ref sweep) = self.sweep {
+            if epoch % sweep.interval == 0 {
+                self.sweep(sweep);
+            }
+        }
+    }
+
+    /// Checks the reward addresses of the slots this validator was elected for.
+    fn check_slots(&self) {
+        let state = self.blockchain.state();
+        let slots = match state.current_slots() {
+            Some(slots) => slots,
+            None => return,
+        };
+
+        let public_keys = self.validator_keys.public_keys();
+        let mut num_slots = 0;
+        for slot in slots.iter().filter(|slot| public_keys.contains(slot.public_key.compressed())) {
+            num_slots += 1;
+            if slot.reward_address() != &self.reward_address {
+                warn!("Validator was elected with reward address {}, but {} is configured",
+                      slot.reward_address().to_user_friendly_address(), self.reward_address.to_user_friendly_address());
+                return;
+            }
+        }
+
+        if num_slots > 0 {
+            info!("Validator was elected for {} slots, rewards are paid to {}", num_slots,
+                  self.reward_address.to_user_friendly_address());
+        }
+    }
+
+    fn sweep(&self, sweep: &RewardSweepConfig) {
+        let wallet = WalletAccount::from(sweep.key_pair.clone());
+        let balance = self.blockchain.state().accounts().get(&wallet.address, None).balance();
+        let value = match balance.checked_sub(sweep.fee) {
+            Some(value) if value > Coin::ZERO => value,
+            _ => {
+                debug!("No rewards to sweep from {}", wallet.address.to_user_friendly_address());
+                return;
+            },
+        };
+
+        let transaction = wallet.create_transaction(sweep.address.clone(), value, sweep.fee,
+            self.blockchain.block_number(), self.blockchain.network_id);
+        match self.mempool.push_local_transaction(transaction) {
+            ReturnCode::Accepted => info!("Sweeping {} rewards to {}", value, sweep.address.to_user_friendly_address()),
+            code => warn!("Failed to sweep rewards: Transaction was rejected ({:?})", code),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+use std::fs;
+use std::io::Error as IoError;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use failure::Fail;
+use futures::{future, Future, Stream};
+use hex::FromHex;
+use reqwest::r#async::{Chunk, Client, Response};
+use tokio::timer::Interval;
+use url::Url;
+
+use hash::{Hasher, Sha256Hash, Sha256Hasher};
+use keys::{PublicKey, Signature};
+
+use crate::client::VERSION;
+
+
+#[derive(Debug, Fail)]
+pub enum UpdaterError {
+    #[fail(display = "Fetching failed: {}", _0)]
+    FetchError(#[cause] reqwest::Error),
+    #[fail(display = "The remote server responded with status code '{}'", _0)]
+    UnexpectedHttpStatus(reqwest::StatusCode),
+    #[fail(display = "The release manifest is not valid UTF-8")]
+    InvalidEncoding,
+    #[fail(display = "Invalid line in release manifest: {}", _0)]
+    InvalidLine(String),
+    #[fail(display = "Invalid version: {}", _0)]
+    InvalidVersion(String),
+    #[fail(display = "The release manifest doesn't contain a version")]
+    VersionMissing,
+    #[fail(display = "The release manifest doesn't contain a signature")]
+    SignatureMissing,
+    #[fail(display = "The signature verification for the release manifest failed")]
+    SignatureVerificationFailed,
+    #[fail(display = "The release manifest doesn't contain a download URL and checksum")]
+    DownloadMissing,
+    #[fail(display = "The checksum of the downloaded release doesn't match")]
+    ChecksumMismatch,
+    #[fail(display = "Failed to write release: {}", _0)]
+    IoError(#[cause] IoError),
+}
+
+impl From<reqwest::Error> for UpdaterError {
+    fn from(e: reqwest::Error) -> Self {
+        UpdaterError::FetchError(e)
+    }
+}
+
+impl From<IoError> for UpdaterError {
+    fn from(e: IoError) -> Self {
+        UpdaterError::IoError(e)
+    }
+}
+
+
+/// A semantic version. Pre-release and build metadata are ignored for comparison.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl FromStr for Version {
+    type Err = UpdaterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let core = s.trim().splitn(2, |c| c == '-' || c == '+').next().unwrap_or_default();
+        let parts = core.split('.')
+            .map(u32::from_str)
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| UpdaterError::InvalidVersion(s.to_string()))?;
+
+        match parts.as_slice() {
+            &[major, minor, patch] => Ok(Version { major, minor, patch }),
+            _ => Err(UpdaterError::InvalidVersion(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+
+/// A signed release manifest.
+///
+/// The manifest is a list of `key: value` lines. Empty lines and lines starting with `#` are
+/// ignored. The last line contains the signature over all other lines, joined by `\n`.
+///
+/// ```text
+/// version: 0.2.0
+/// url: https://example.com/nimiq-client-0.2.0.tar.gz
+/// sha256: <hex>
+/// <signature hex>
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReleaseManifest {
+    pub version: Version,
+    pub url: Option<Url>,
+    pub sha256: Option<Sha256Hash>,
+}
+
+impl ReleaseManifest {
+    pub fn parse(manifest: &str, public_key: &PublicKey) -> Result<Self, UpdaterError> {
+        let mut lines = manifest.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect::<Vec<&str>>();
+
+        let signature = lines.pop()
+            .and_then(|line| Signature::from_hex(line).ok())
+            .ok_or(UpdaterError::SignatureMissing)?;
+        if !public_key.verify(&signature, lines.join("\n").as_bytes()) {
+            return Err(UpdaterError::SignatureVerificationFailed);
+        }
+
+        let mut version = None;
+        let mut url = None;
+        let mut sha256 = None;
+        for line in lines {
+            let mut kv = line.splitn(2, ':');
+            let key = kv.next().unwrap_or_default().trim();
+            let value = kv.next()
+                .ok_or_else(|| UpdaterError::InvalidLine(line.to_string()))?
+                .trim();
+            match key {
+                "version" => version = Some(Version::from_str(value)?),
+                "url" => url = Some(Url::parse(value)
+                    .map_err(|_| UpdaterError::InvalidLine(line.to_string()))?),
+                "sha256" => sha256 = Some(Sha256Hash::from_str(value)
+                    .map_err(|_| UpdaterError::InvalidLine(line.to_string()))?),
+                // Ignore unknown keys for forward-compatibility.
+                _ => {},
+            }
+        }
+
+        Ok(ReleaseManifest {
+            version: version.ok_or(UpdaterError::VersionMissing)?,
+            url,
+            sha256,
+        })
+    }
+}
+
+
+#[derive(Clone, Debug)]
+pub struct UpdaterConfig {
+    /// URL of the signed release manifest.
+    pub manifest_url: Url,
+    /// Public key that the release manifest must be signed with.
+    pub public_key: PublicKey,
+    /// How often to check for updates.
+    pub interval: Duration,
+    /// If set, new releases will be downloaded into this directory.
+    pub download_dir: Option<PathBuf>,
+}
+
+impl UpdaterConfig {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+}
+
+
+/// Periodically checks a signed release manifest and warns if the running node is outdated.
+pub struct Updater {
+    config: UpdaterConfig,
+    current: Version,
+}
+
+impl Updater {
+    pub fn new(config: UpdaterConfig) -> Self {
+        Updater {
+            config,
+            current: Version::from_str(&VERSION).expect("Invalid crate version"),
+        }
+    }
+
+    /// Returns a future that checks for updates every `interval` and never resolves.
+    pub fn run(self) -> impl Future<Item=(), Error=()> {
+        Interval::new(Instant::now(), self.config.interval)
+            .map_err(|e| error!("Updater timer failed: {}", e))
+            .for_each(move |_| {
+                let download_dir = self.config.download_dir.clone();
+                self.check()
+                    .and_then(move |release| -> Box<dyn Future<Item=(), Error=UpdaterError> + Send> {
+                        match (release, download_dir) {
+                            (Some(release), Some(download_dir)) => Box::new(Self::download(release, download_dir)),
+                            _ => Box::new(future::ok(())),
+                        }
+                    })
+                    .or_else(|e| {
+                        warn!("Update check failed: {}", e);
+                        Ok(())
+                    })
+            })
+    }
+
+    /// Fetches the release manifest and returns it if it announces a newer version.
+    pub fn check(&self) -> impl Future<Item=Option<ReleaseManifest>, Error=UpdaterError> {
+        let public_key = self.config.public_key;
+        let current = self.current;
+
+        Self::fetch(self.config.manifest_url.clone())
+            .and_then(move |body| {
+                let manifest = std::str::from_utf8(&body)
+                    .map_err(|_| UpdaterError::InvalidEncoding)?;
+                let release = ReleaseManifest::parse(manifest, &public_key)?;
+
+                if release.version > current {
+                    warn!("!!!!");
+                    warn!("!!!! A new version is available: {} (running {})", release.version, current);
+                    warn!("!!!!");
+                    Ok(Some(release))
+                }
+                else {
+                    debug!("Running latest version {} (released {})", current, release.version);
+                    Ok(None)
+                }
+            })
+    }
+
+    /// Downloads a release and verifies its checksum before writing it to `download_dir`.
+    fn download(release: ReleaseManifest, download_dir: PathBuf) -> impl Future<Item=(), Error=UpdaterError> {
+        let version = release.version;
+        future::result(match (release.url, release.sha256) {
+            (Some(url), Some(sha256)) => Ok((url, sha256)),
+            _ => Err(UpdaterError::DownloadMissing),
+        })
+            .and_then(|(url, sha256)| {
+                let file_name = url.path_segments()
+                    .and_then(Iterator::last)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("nimiq-{}", version));
+
+                Self::fetch(url)
+                    .and_then(move |body| {
+                        if Sha256Hasher::default().digest(&body) != sha256 {
+                            return Err(UpdaterError::ChecksumMismatch);
+                        }
+
+                        fs::create_dir_all(&download_dir)?;
+                        let path = download_dir.join(file_name);
+                        fs::write(&path, &body)?;
+                        info!("Downloaded release {} to {}", version, path.display());
+                        Ok(())
+                    })
+            })
+    }
+
+    fn fetch(url: Url) -> impl Future<Item=Chunk, Error=UpdaterError> {
+        Client::new().get(url).send()
+            .map_err(UpdaterError::from)
+            .and_then(Self::fetch_callback)
+    }
+
+    // Note: this is a standalone function to help the compiler infer the types.
+    fn fetch_callback(res: Response) -> Box<dyn Future<Item=Chunk, Error=UpdaterError> + Send> {
+        let status = res.status();
+
+        if status == 200 {
+            Box::new(res.into_body().concat2().map_err(UpdaterError::from))
+        } else {
+            Box::new(future::err(UpdaterError::UnexpectedHttpStatus(status)))
+        }
+    }
+}
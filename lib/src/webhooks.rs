@@ -0,0 +1,689 @@
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use beserial::{Deserialize, DeserializeWithLength, ReadBytesExt, Serialize, SerializeWithLength, SerializingError, WriteBytesExt};
+use failure::Fail;
+use futures::{Future, Stream};
+use futures::sync::mpsc;
+use json::JsonValue;
+use parking_lot::Mutex;
+use reqwest::r#async::Client;
+use tokio::timer::{Interval, Timeout};
+use url::Url;
+
+use block_albatross::Block;
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::blockchain::BlockchainEvent;
+use blockchain_albatross::pruned_accounts::{PrunedAccounts, PrunedAccountsTracker};
+use database::{AsDatabaseBytes, Environment, FromDatabaseValue, ReadTransaction, WriteTransaction};
+use database::cursor::ReadCursor;
+use database::typed::TypedDatabase;
+use hash::{Blake2bHash, Hash};
+use hash::hmac::compute_hmac_sha512;
+use keys::{Address, SecretBytes};
+use transaction::Transaction;
+use utils::observer::Subscription;
+
+
+#[derive(Debug, Fail)]
+pub enum WebhookError {
+    #[fail(display = "Unknown webhook event: {}", _0)]
+    UnknownEvent(String),
+}
+
+/// Kinds of events that are delivered to webhooks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum WebhookEvent {
+    /// A block was added to the main chain.
+    Block = 0,
+    /// A transaction from or to one of the webhook's addresses was included in a main chain block.
+    Transaction = 1,
+    /// A block contains a fork proof, which slashes the validator that produced the fork.
+    ForkProof = 2,
+    /// A block was produced after a view change, i.e. the validators skipped a slot.
+    ViewChange = 3,
+    /// A block was removed from the main chain by a rebranch. The events of the block that were
+    /// delivered before are void.
+    Revert = 4,
+    /// A block pruned accounts, because their balance dropped to zero. The data contains what is
+    /// needed to recreate pruned contracts.
+    PrunedAccounts = 5,
+}
+
+impl WebhookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::Block => "block",
+            WebhookEvent::Transaction => "transaction",
+            WebhookEvent::ForkProof => "forkProof",
+            WebhookEvent::ViewChange => "viewChange",
+            WebhookEvent::Revert => "revert",
+            WebhookEvent::PrunedAccounts => "prunedAccounts",
+        }
+    }
+}
+
+impl Serialize for WebhookEvent {
+    fn serialize<W: WriteBytesExt>(&self, writer: &mut W) -> Result<usize, SerializingError> {
+        (*self as u8).serialize(writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        1
+    }
+}
+
+impl Deserialize for WebhookEvent {
+    fn deserialize<R: ReadBytesExt>(reader: &mut R) -> Result<Self, SerializingError> {
+        match u8::deserialize(reader)? {
+            0 => Ok(WebhookEvent::Block),
+            1 => Ok(WebhookEvent::Transaction),
+            2 => Ok(WebhookEvent::ForkProof),
+            3 => Ok(WebhookEvent::ViewChange),
+            4 => Ok(WebhookEvent::Revert),
+            5 => Ok(WebhookEvent::PrunedAccounts),
+            _ => Err(SerializingError::InvalidValue),
+        }
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = WebhookError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(WebhookEvent::Block),
+            "transaction" => Ok(WebhookEvent::Transaction),
+            "forkProof" => Ok(WebhookEvent::ForkProof),
+            "viewChange" => Ok(WebhookEvent::ViewChange),
+            "revert" => Ok(WebhookEvent::Revert),
+            "prunedAccounts" => Ok(WebhookEvent::PrunedAccounts),
+            _ => Err(WebhookError::UnknownEvent(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    /// URL the events are POSTed to.
+    pub url: Url,
+    /// Events to deliver. If empty, all events are delivered.
+    pub events: HashSet<WebhookEvent>,
+    /// Only transactions from or to these addresses and only these pruned accounts are delivered.
+    /// If empty, all transactions and pruned accounts are delivered.
+    pub addresses: HashSet<Address>,
+    /// If set, each request carries an HMAC-SHA512 of its body with this secret in the
+    /// `X-Nimiq-Signature` header.
+    pub secret: Option<SecretBytes>,
+}
+
+impl WebhookConfig {
+    pub fn accepts(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+
+    pub fn accepts_transaction(&self, transaction: &Transaction) -> bool {
+        self.accepts(WebhookEvent::Transaction) && (self.addresses.is_empty()
+            || self.addresses.contains(&transaction.sender)
+            || self.addresses.contains(&transaction.recipient))
+    }
+
+    /// The hex encoded HMAC-SHA512 of `body`, if a secret is configured.
+    pub fn sign(&self, body: &str) -> Option<String> {
+        self.secret.as_ref().map(|secret| {
+            let mac: [u8; 64] = compute_hmac_sha512(secret.as_bytes(), body.as_bytes()).into();
+            hex::encode(&mac[..])
+        })
+    }
+}
+
+
+/// A request that still has to be delivered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delivery {
+    pub url: String,
+    pub event: WebhookEvent,
+    pub body: String,
+    pub signature: Option<String>,
+    /// Number of failed attempts.
+    pub attempts: u32,
+    /// UNIX time in seconds before which the delivery isn't attempted again.
+    pub next_attempt: u64,
+}
+
+impl Serialize for Delivery {
+    fn serialize<W: WriteBytesExt>(&self, writer: &mut W) -> Result<usize, SerializingError> {
+        let mut size = SerializeWithLength::serialize::<u16, W>(&self.url, writer)?;
+        size += self.event.serialize(writer)?;
+        size += SerializeWithLength::serialize::<u32, W>(&self.body, writer)?;
+        size += SerializeWithLength::serialize::<u8, W>(&self.signature, writer)?;
+        size += self.attempts.serialize(writer)?;
+        size += self.next_attempt.serialize(writer)?;
+        Ok(size)
+    }
+
+    fn serialized_size(&self) -> usize {
+        SerializeWithLength::serialized_size::<u16>(&self.url)
+            + self.event.serialized_size()
+            + SerializeWithLength::serialized_size::<u32>(&self.body)
+            + SerializeWithLength::serialized_size::<u8>(&self.signature)
+            + self.attempts.serialized_size()
+            + self.next_attempt.serialized_size()
+    }
+}
+
+impl Deserialize for Delivery {
+    fn deserialize<R: ReadBytesExt>(reader: &mut R) -> Result<Self, SerializingError> {
+        Ok(Delivery {
+            url: DeserializeWithLength::deserialize::<u16, R>(reader)?,
+            event: Deserialize::deserialize(reader)?,
+            body: DeserializeWithLength::deserialize::<u32, R>(reader)?,
+            signature: DeserializeWithLength::deserialize::<u8, R>(reader)?,
+            attempts: Deserialize::deserialize(reader)?,
+            next_attempt: Deserialize::deserialize(reader)?,
+        })
+    }
+}
+
+impl AsDatabaseBytes for Delivery {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(self.serialize_to_vec())
+    }
+}
+
+impl FromDatabaseValue for Delivery {
+    fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
+        let mut cursor = io::Cursor::new(bytes);
+        Ok(Deserialize::deserialize(&mut cursor)?)
+    }
+}
+
+
+/// Key of a queued delivery. Serialized big-endian, so the queue is sorted by the time the
+/// deliveries are due and then by the order they were queued in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DeliveryKey {
+    pub next_attempt: u64,
+    pub id: u64,
+}
+
+impl Serialize for DeliveryKey {
+    fn serialize<W: WriteBytesExt>(&self, writer: &mut W) -> Result<usize, SerializingError> {
+        Ok(self.next_attempt.serialize(writer)? + self.id.serialize(writer)?)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.next_attempt.serialized_size() + self.id.serialized_size()
+    }
+}
+
+impl Deserialize for DeliveryKey {
+    fn deserialize<R: ReadBytesExt>(reader: &mut R) -> Result<Self, SerializingError> {
+        Ok(DeliveryKey {
+            next_attempt: Deserialize::deserialize(reader)?,
+            id: Deserialize::deserialize(reader)?,
+        })
+    }
+}
+
+impl AsDatabaseBytes for DeliveryKey {
+    fn as_database_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(self.serialize_to_vec())
+    }
+}
+
+impl FromDatabaseValue for DeliveryKey {
+    fn copy_from_database(bytes: &[u8]) -> io::Result<Self> where Self: Sized {
+        let mut cursor = io::Cursor::new(bytes);
+        Ok(Deserialize::deserialize(&mut cursor)?)
+    }
+}
+
+
+/// Deliveries that weren't acknowledged yet, persisted so they survive restarts.
+pub struct WebhookQueue<'env> {
+    env: &'env Environment,
+    db: TypedDatabase<'env, DeliveryKey, Delivery>,
+    next_id: Mutex<u64>,
+}
+
+impl<'env> WebhookQueue<'env> {
+    const QUEUE_DB_NAME: &'static str = "WebhookQueue";
+
+    pub fn new(env: &'env Environment) -> Self {
+        let db = TypedDatabase::open(env, Self::QUEUE_DB_NAME.to_string());
+
+        // The queue is sorted by due time, so the greatest id has to be searched. The queue only
+        // holds undelivered events, so this is cheap.
+        let next_id = {
+            let txn = ReadTransaction::new(env);
+            let mut cursor = txn.cursor(db.database());
+            let mut next_id = 0;
+            let mut entry: Option<(DeliveryKey, Delivery)> = cursor.first();
+            while let Some((key, _)) = entry {
+                next_id = cmp::max(next_id, key.id + 1);
+                entry = cursor.next();
+            }
+            next_id
+        };
+
+        WebhookQueue {
+            env,
+            db,
+            next_id: Mutex::new(next_id),
+        }
+    }
+
+    /// Adds deliveries to the queue in a single transaction.
+    pub fn push(&self, deliveries: &[Delivery]) {
+        let mut next_id = self.next_id.lock();
+        let mut txn = WriteTransaction::new(self.env);
+        for delivery in deliveries {
+            let key = DeliveryKey { next_attempt: delivery.next_attempt, id: *next_id };
+            self.db.put(&mut txn, &key, delivery);
+            *next_id += 1;
+        }
+        txn.commit();
+    }
+
+    /// Deliveries that are due at `now`, in the order they became due.
+    pub fn due(&self, now: u64) -> Vec<(DeliveryKey, Delivery)> {
+        let txn = ReadTransaction::new(self.env);
+        let mut cursor = txn.cursor(self.db.database());
+        let mut deliveries = Vec::new();
+        let mut entry: Option<(DeliveryKey, Delivery)> = cursor.first();
+        while let Some((key, delivery)) = entry {
+            if key.next_attempt > now {
+                break;
+            }
+            deliveries.push((key, delivery));
+            entry = cursor.next();
+        }
+        deliveries
+    }
+
+    /// Replaces the delivery at `key`, which moves it to its new due time.
+    pub fn update(&self, key: DeliveryKey, delivery: &Delivery) {
+        let mut txn = WriteTransaction::new(self.env);
+        self.db.remove(&mut txn, &key);
+        self.db.put(&mut txn, &DeliveryKey { next_attempt: delivery.next_attempt, id: key.id }, delivery);
+        txn.commit();
+    }
+
+    pub fn remove(&self, key: DeliveryKey) {
+        let mut txn = WriteTransaction::new(self.env);
+        self.db.remove(&mut txn, &key);
+        txn.commit();
+    }
+
+    pub fn len(&self) -> usize {
+        self.db.database().stats().entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+
+/// What `WebhookDispatcher::run` reacts to.
+enum DispatcherEvent {
+    Blockchain(BlockchainEvent),
+    PrunedAccounts(PrunedAccounts),
+    Tick,
+}
+
+/// POSTs blockchain events to the configured webhooks.
+///
+/// Deliveries are queued in the database before they are sent and only removed once the webhook
+/// responded with a success status, so each event is delivered at least once, even across
+/// restarts. Failed deliveries are retried with exponential backoff. Blocks reverted by a
+/// rebranch are signalled with a `revert` event, and blocks that are adopted again are delivered
+/// again, so receivers should deduplicate events by their `id`.
+///
+/// Blockchain events are only passed on by the notifier and queued by `run`, so the blockchain
+/// doesn't wait for the database.
+pub struct WebhookDispatcher {
+    blockchain: Arc<Blockchain<'static>>,
+    webhooks: Vec<WebhookConfig>,
+    queue: WebhookQueue<'static>,
+    client: Client,
+    /// Blockchain events that weren't queued yet. Taken by `run`.
+    events: Mutex<Option<mpsc::UnboundedReceiver<BlockchainEvent>>>,
+    /// Emits the accounts pruned by main chain blocks while the blocks are applied, so they
+    /// belong to the right block even if the chain moved on before the event is queued.
+    pruned_accounts: Arc<PrunedAccountsTracker<'static>>,
+    /// Pruned accounts that weren't queued yet. Taken by `run`.
+    pruned_events: Mutex<Option<mpsc::UnboundedReceiver<PrunedAccounts>>>,
+    /// Hashes of the blocks that were queued last, so that a block that is announced twice isn't
+    /// delivered twice.
+    recent_blocks: Mutex<VecDeque<Blake2bHash>>,
+    /// Deliveries that are currently being sent.
+    in_flight: Mutex<HashSet<u64>>,
+    _blockchain_subscription: Subscription<'static, BlockchainEvent>,
+    _pruned_subscription: Subscription<'static, PrunedAccounts>,
+}
+
+impl WebhookDispatcher {
+    /// How often the queue is checked for due deliveries.
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+    const MAX_CONCURRENT_DELIVERIES: usize = 16;
+    const MAX_RECENT_BLOCKS: usize = 128;
+    const MIN_RETRY_DELAY: u64 = 5;
+    const MAX_RETRY_DELAY: u64 = 60 * 60;
+    /// Deliveries are dropped after this many failed attempts, which takes about two days.
+    pub const MAX_ATTEMPTS: u32 = 60;
+
+    pub fn new(env: &'static Environment, blockchain: Arc<Blockchain<'static>>, webhooks: Vec<WebhookConfig>) -> Arc<Self> {
+        let (sender, receiver) = mpsc::unbounded();
+        let (pruned_sender, pruned_receiver) = mpsc::unbounded();
+        let pruned_accounts = PrunedAccountsTracker::new(Arc::clone(&blockchain));
+
+        // Sending fails once the dispatcher stopped running, which is fine.
+        let blockchain_subscription = blockchain.notifier.write().subscribe(move |event: &BlockchainEvent| {
+            sender.unbounded_send(event.clone()).ok();
+        });
+        let pruned_subscription = pruned_accounts.notifier.write().subscribe(move |pruned: &PrunedAccounts| {
+            pruned_sender.unbounded_send(pruned.clone()).ok();
+        });
+
+        let this = Arc::new(WebhookDispatcher {
+            blockchain: Arc::clone(&blockchain),
+            webhooks,
+            queue: WebhookQueue::new(env),
+            client: Client::new(),
+            events: Mutex::new(Some(receiver)),
+            pruned_accounts: Arc::clone(&pruned_accounts),
+            pruned_events: Mutex::new(Some(pruned_receiver)),
+            recent_blocks: Mutex::new(VecDeque::new()),
+            in_flight: Mutex::new(HashSet::new()),
+            _blockchain_subscription: blockchain_subscription,
+            _pruned_subscription: pruned_subscription,
+        });
+
+        if !this.queue.is_empty() {
+            info!("Resuming {} pending webhook deliveries", this.queue.len());
+        }
+        this
+    }
+
+    /// The delay before the next attempt after `attempts` failed attempts.
+    pub fn retry_delay(attempts: u32) -> u64 {
+        let delay = Self::MIN_RETRY_DELAY.checked_shl(attempts.saturating_sub(1)).unwrap_or(Self::MAX_RETRY_DELAY);
+        cmp::min(delay, Self::MAX_RETRY_DELAY)
+    }
+
+    /// Returns a future that queues the blockchain events, sends due deliveries and never
+    /// resolves. Panics if the dispatcher is run twice.
+    pub fn run(this: Arc<Self>) -> impl Future<Item=(), Error=()> {
+        let events = this.events.lock().take().expect("Webhook dispatcher is already running");
+        let pruned_events = this.pruned_events.lock().take().expect("Webhook dispatcher is already running");
+        let ticks = Interval::new(Instant::now(), Self::POLL_INTERVAL)
+            .map(|_| DispatcherEvent::Tick)
+            .map_err(|e| error!("Webhook timer failed: {}", e));
+        events.map(DispatcherEvent::Blockchain)
+            .select(pruned_events.map(DispatcherEvent::PrunedAccounts))
+            .select(ticks)
+            .for_each(move |event| {
+                match event {
+                    DispatcherEvent::Blockchain(event) => this.handle_event(&event),
+                    DispatcherEvent::PrunedAccounts(pruned) => this.handle_pruned_accounts(&pruned),
+                    DispatcherEvent::Tick => Self::send_due(&this),
+                }
+                Ok(())
+            })
+    }
+
+    /// The tracker whose pruned accounts are delivered.
+    pub fn pruned_accounts(&self) -> &Arc<PrunedAccountsTracker<'static>> {
+        &self.pruned_accounts
+    }
+
+    /// Queues the deliveries for the accounts pruned by a block. If a webhook filters addresses,
+    /// only those accounts are delivered.
+    pub fn handle_pruned_accounts(&self, pruned: &PrunedAccounts) {
+        let block_hash = pruned.block_hash.to_hex();
+        let deliveries: Vec<Delivery> = self.webhooks.iter()
+            .filter(|webhook| webhook.accepts(WebhookEvent::PrunedAccounts))
+            .filter_map(|webhook| {
+                let accounts: Vec<JsonValue> = pruned.accounts.iter()
+                    .filter(|account| webhook.addresses.is_empty() || webhook.addresses.contains(&account.address))
+                    .map(|account| object!{
+                        "address" => account.address.to_user_friendly_address(),
+                        "type" => account.account.account_type() as u8,
+                        "restoration" => account.restoration().map(|restoration| object!{
+                            "type" => restoration.account_type as u8,
+                            "value" => u64::from(restoration.value),
+                            "data" => hex::encode(&restoration.data),
+                        }).unwrap_or(JsonValue::Null),
+                    })
+                    .collect();
+                if accounts.is_empty() {
+                    return None;
+                }
+                Some(Self::delivery(webhook, WebhookEvent::PrunedAccounts, format!("prunedAccounts:{}", block_hash), object!{
+                    "blockHash" => block_hash.as_str(),
+                    "blockNumber" => pruned.block_number,
+                    "accounts" => accounts,
+                }))
+            })
+            .collect();
+
+        if !deliveries.is_empty() {
+            self.queue.push(&deliveries);
+        }
+    }
+
+    /// Queues the deliveries for a blockchain event.
+    pub fn handle_event(&self, event: &BlockchainEvent) {
+        let mut deliveries = Vec::new();
+        let mut recent_blocks = self.recent_blocks.lock();
+
+        let blocks = match event {
+            BlockchainEvent::Extended(hash) | BlockchainEvent::Finalized(hash) => {
+                match self.blockchain.get_block(hash, false, true) {
+                    Some(block) => vec![(hash.clone(), block)],
+                    None => return,
+                }
+            },
+            BlockchainEvent::Rebranched(reverted_blocks, adopted_blocks) => {
+                for (hash, block) in reverted_blocks.iter() {
+                    // The block is delivered again if it is adopted again.
+                    recent_blocks.retain(|recent| recent != hash);
+                    deliveries.extend(self.deliveries_for_revert(hash, block));
+                }
+                adopted_blocks.clone()
+            },
+        };
+
+        for (hash, block) in blocks.iter() {
+            if recent_blocks.contains(hash) {
+                continue;
+            }
+            if recent_blocks.len() >= Self::MAX_RECENT_BLOCKS {
+                recent_blocks.pop_front();
+            }
+            recent_blocks.push_back(hash.clone());
+            deliveries.extend(self.deliveries_for_block(hash, block));
+        }
+
+        if !deliveries.is_empty() {
+            self.queue.push(&deliveries);
+        }
+    }
+
+    /// A delivery of `data` to `webhook`, which receivers can deduplicate by `id`.
+    fn delivery(webhook: &WebhookConfig, event: WebhookEvent, id: String, data: JsonValue) -> Delivery {
+        let body = json::stringify(object!{
+            "id" => id,
+            "event" => event.as_str(),
+            "data" => data,
+        });
+        Delivery {
+            url: webhook.url.to_string(),
+            event,
+            signature: webhook.sign(&body),
+            body,
+            attempts: 0,
+            next_attempt: 0,
+        }
+    }
+
+    fn deliveries_for_revert(&self, hash: &Blake2bHash, block: &Block) -> Vec<Delivery> {
+        let block_hash = hash.to_hex();
+        self.webhooks.iter()
+            .filter(|webhook| webhook.accepts(WebhookEvent::Revert))
+            .map(|webhook| Self::delivery(webhook, WebhookEvent::Revert, format!("revert:{}", block_hash), object!{
+                "hash" => block_hash.as_str(),
+                "blockNumber" => block.block_number(),
+                "viewNumber" => block.view_number(),
+            }))
+            .collect()
+    }
+
+    fn deliveries_for_block(&self, hash: &Blake2bHash, block: &Block) -> Vec<Delivery> {
+        let mut deliveries = Vec::new();
+        let block_hash = hash.to_hex();
+
+        for webhook in self.webhooks.iter() {
+            let mut push = |event: WebhookEvent, id: String, data: JsonValue| {
+                deliveries.push(Self::delivery(webhook, event, id, data));
+            };
+
+            if webhook.accepts(WebhookEvent::Block) {
+                push(WebhookEvent::Block, format!("block:{}", block_hash), object!{
+                    "hash" => block_hash.as_str(),
+                    "blockNumber" => block.block_number(),
+                    "viewNumber" => block.view_number(),
+                    "type" => match block { Block::Macro(_) => "macro", Block::Micro(_) => "micro" },
+                    "parentHash" => block.parent_hash().to_hex(),
+                    "timestamp" => block.timestamp(),
+                    "transactionCount" => block.transactions().map(Vec::len).unwrap_or(0),
+                });
+            }
+
+            if block.view_number() > 0 && webhook.accepts(WebhookEvent::ViewChange) {
+                push(WebhookEvent::ViewChange, format!("viewChange:{}", block_hash), object!{
+                    "blockHash" => block_hash.as_str(),
+                    "blockNumber" => block.block_number(),
+                    "viewNumber" => block.view_number(),
+                });
+            }
+
+            if let Block::Micro(ref micro_block) = block {
+                let extrinsics = match micro_block.extrinsics {
+                    Some(ref extrinsics) => extrinsics,
+                    None => continue,
+                };
+
+                if webhook.accepts(WebhookEvent::ForkProof) {
+                    for fork_proof in extrinsics.fork_proofs.iter() {
+                        let hash1 = fork_proof.header1.hash::<Blake2bHash>().to_hex();
+                        let hash2 = fork_proof.header2.hash::<Blake2bHash>().to_hex();
+                        push(WebhookEvent::ForkProof, format!("forkProof:{}:{}", hash1, hash2), object!{
+                            "blockHash" => block_hash.as_str(),
+                            "blockNumber" => block.block_number(),
+                            "forkBlockNumber" => fork_proof.header1.block_number,
+                            "forkViewNumber" => fork_proof.header1.view_number,
+                            "hashes" => vec![hash1, hash2],
+                        });
+                    }
+                }
+
+                for (index, transaction) in extrinsics.transactions.iter().enumerate() {
+                    if !webhook.accepts_transaction(transaction) {
+                        continue;
+                    }
+                    let transaction_hash = transaction.hash::<Blake2bHash>().to_hex();
+                    push(WebhookEvent::Transaction, format!("transaction:{}:{}", transaction_hash, block_hash), object!{
+                        "hash" => transaction_hash.as_str(),
+                        "blockHash" => block_hash.as_str(),
+                        "blockNumber" => block.block_number(),
+                        "transactionIndex" => index,
+                        "fromAddress" => transaction.sender.to_user_friendly_address(),
+                        "toAddress" => transaction.recipient.to_user_friendly_address(),
+                        "value" => u64::from(transaction.value),
+                        "fee" => u64::from(transaction.fee),
+                    });
+                }
+            }
+        }
+
+        deliveries
+    }
+
+    fn send_due(this: &Arc<Self>) {
+        let now = unix_time();
+        let mut in_flight = this.in_flight.lock();
+        for (key, delivery) in this.queue.due(now) {
+            if in_flight.len() >= Self::MAX_CONCURRENT_DELIVERIES {
+                break;
+            }
+            if in_flight.insert(key.id) {
+                tokio::spawn(Self::send(this, key, delivery));
+            }
+        }
+    }
+
+    fn send(this: &Arc<Self>, key: DeliveryKey, mut delivery: Delivery) -> impl Future<Item=(), Error=()> {
+        let mut request = this.client.post(delivery.url.as_str())
+            .header("Content-Type", "application/json")
+            .header("X-Nimiq-Event", delivery.event.as_str())
+            .body(delivery.body.clone());
+        if let Some(ref signature) = delivery.signature {
+            request = request.header("X-Nimiq-Signature", signature.as_str());
+        }
+
+        let this = Arc::clone(this);
+        Timeout::new(request.send(), Self::REQUEST_TIMEOUT)
+            .then(move |result| {
+                let error = match result {
+                    Ok(ref response) if response.status().is_success() => None,
+                    Ok(response) => Some(format!("Unexpected status code {}", response.status())),
+                    Err(e) => Some(e.to_string()),
+                };
+
+                match error {
+                    None => {
+                        trace!("Delivered {} event to {}", delivery.event, delivery.url);
+                        this.queue.remove(key);
+                    },
+                    Some(e) => {
+                        delivery.attempts += 1;
+                        if delivery.attempts >= Self::MAX_ATTEMPTS {
+                            warn!("Dropping {} event for {} after {} failed attempts: {}", delivery.event, delivery.url, delivery.attempts, e);
+                            this.queue.remove(key);
+                        } else {
+                            debug!("Failed to deliver {} event to {} (attempt {}): {}", delivery.event, delivery.url, delivery.attempts, e);
+                            delivery.next_attempt = unix_time() + Self::retry_delay(delivery.attempts);
+                            this.queue.update(key, &delivery);
+                        }
+                    },
+                }
+
+                this.in_flight.lock().remove(&key.id);
+                Ok(())
+            })
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,382 @@
+use std::str::FromStr;
+
+use log::LevelFilter;
+
+use lib::config::{BlacklistSettings, ClientConfig, ConfigError, MempoolSettings, Network, NodeType, Protocol, ReplicaSettings, RewardSweepSettings, RpcServerSettings, ThreadSettings, TransactionBroadcast, ValidatorProfile, ValidatorSettings, WebhookSettings};
+
+#[test]
+fn it_parses_the_example_config() {
+    let config = ClientConfig::from_str(include_str!("../../client/client.example.toml")).unwrap();
+    assert_eq!(config.network.host, Some("my.domain".to_string()));
+    assert_eq!(config.network.protocol, Protocol::Ws);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn it_rejects_unknown_fields() {
+    match ClientConfig::from_str("[network]\nfoo = 42\n") {
+        Err(ConfigError::Parse(_)) => {},
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn it_reports_all_problems_at_once() {
+    let errors = ClientConfig::builder()
+        .with_protocol(Protocol::Wss)
+        .with_node_type(NodeType::Light)
+        .with_rpc_server(RpcServerSettings {
+            username: Some("user".to_string()),
+            ..Default::default()
+        })
+        .build()
+        .unwrap_err();
+
+    assert_eq!(errors.0.len(), 4);
+    assert!(errors.0.iter().any(|e| match e { ConfigError::UnsupportedNodeType(NodeType::Light) => true, _ => false }));
+    assert!(errors.0.iter().any(|e| match e { ConfigError::NoHostname => true, _ => false }));
+    assert!(errors.0.iter().any(|e| match e { ConfigError::NoTlsIdentityFile => true, _ => false }));
+    assert!(errors.0.iter().any(|e| match e { ConfigError::MissingRpcCredentials => true, _ => false }));
+}
+
+#[test]
+fn it_requires_validators_to_run_on_albatross() {
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_validator(ValidatorSettings::default());
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    match errors.0[0] {
+        ConfigError::ValidatorRequiresAlbatross(Network::Main) => {},
+        ref e => panic!("Unexpected error: {}", e),
+    }
+
+    builder.with_network(Network::DevAlbatross);
+    assert!(builder.build().is_ok());
+}
+
+#[test]
+fn it_validates_reward_addresses() {
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_network(Network::DevAlbatross)
+        .with_validator(ValidatorSettings {
+            reward_address: Some("NQ00 invalid".to_string()),
+            reward_sweep: Some(RewardSweepSettings {
+                key_file: "reward_key.dat".to_string(),
+                address: "NQ07 0000 0000 0000 0000 0000 0000 0000 0000".to_string(),
+                interval: Some(0),
+                fee: Default::default(),
+            }),
+            ..Default::default()
+        });
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 2);
+    assert!(errors.0.iter().any(|e| match e { ConfigError::InvalidRewardAddress(_) => true, _ => false }));
+    assert!(errors.0.iter().any(|e| match e { ConfigError::InvalidRewardSweepInterval => true, _ => false }));
+}
+
+#[test]
+fn it_parses_the_validator_profile() {
+    let config = ClientConfig::from_str("[validator]\nprofile = \"minimal\"\n").unwrap();
+    assert!(config.validator.unwrap().is_minimal());
+
+    let config = ClientConfig::from_str("[validator]\n").unwrap();
+    assert_eq!(config.validator.unwrap().profile, ValidatorProfile::Standard);
+}
+
+#[test]
+fn it_parses_the_block_verification_setting() {
+    let config = ClientConfig::from_str("[validator]\nverify_blocks = true\n").unwrap();
+    assert!(config.validator.unwrap().verify_blocks);
+
+    let config = ClientConfig::from_str("[validator]\n").unwrap();
+    assert!(!config.validator.unwrap().verify_blocks);
+}
+
+#[test]
+fn it_parses_the_local_transactions_size() {
+    let config = ClientConfig::from_str("[validator]\nlocal_transactions_size = 10000\n").unwrap();
+    assert_eq!(config.validator.unwrap().local_transactions_size, 10000);
+
+    let config = ClientConfig::from_str("[validator]\n").unwrap();
+    assert_eq!(config.validator.unwrap().local_transactions_size, 0);
+}
+
+#[test]
+fn it_parses_exclude_contract_creations() {
+    let config = ClientConfig::from_str("[validator]\nexclude_contract_creations = true\n").unwrap();
+    assert!(config.validator.unwrap().exclude_contract_creations);
+
+    let config = ClientConfig::from_str("[validator]\n").unwrap();
+    assert!(!config.validator.unwrap().exclude_contract_creations);
+}
+
+#[test]
+fn it_only_allows_manual_block_production_on_dev_networks() {
+    let config = ClientConfig::from_str("[validator]\nmanual_block_production = true\n").unwrap();
+    assert!(config.validator.unwrap().manual_block_production);
+
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_network(Network::TestAlbatross)
+        .with_validator(ValidatorSettings {
+            manual_block_production: true,
+            ..Default::default()
+        });
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    match errors.0[0] {
+        ConfigError::ManualBlockProductionRequiresDev(Network::TestAlbatross) => {},
+        ref e => panic!("Unexpected error: {}", e),
+    }
+
+    builder.with_network(Network::DevAlbatross);
+    assert!(builder.build().is_ok());
+}
+
+#[test]
+fn it_parses_the_thread_settings() {
+    let config = ClientConfig::from_str("[threads]\ntokio_workers = 2\nhandel_verification = 8\n").unwrap();
+    assert_eq!(config.threads.tokio_workers(), 2);
+    assert_eq!(config.threads.handel_verification(), 8);
+    assert_eq!(config.threads.blockchain_events, None);
+    assert!(config.threads.blockchain_events() >= 1);
+}
+
+#[test]
+fn it_parses_the_gc_settings() {
+    let config = ClientConfig::from_str("[gc]\nreward_log = 16\nepoch_stats = 0\n").unwrap();
+    assert_eq!(config.gc.reward_log(), 16);
+    assert_eq!(config.gc.epoch_stats(), 0);
+    assert_eq!(config.gc.fork_proofs(), 2);
+    assert_eq!(config.gc.view_changes(), 1);
+}
+
+#[test]
+fn it_parses_the_history_settings() {
+    let config = ClientConfig::from_str("[database]\nhistory_dir = \"/tmp/history\"\nhistory_keep_epochs = 64\n").unwrap();
+    assert_eq!(config.database.history_dir, Some("/tmp/history".to_string()));
+    assert_eq!(config.database.history_keep_epochs, Some(64));
+    assert_eq!(config.database.history_size, None);
+}
+
+#[test]
+fn it_rejects_zero_threads() {
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_threads(ThreadSettings {
+            blockchain_events: Some(0),
+            ..Default::default()
+        });
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    match errors.0[0] {
+        ConfigError::InvalidThreadCount("blockchain_events") => {},
+        ref e => panic!("Unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn it_rejects_an_overflowing_memory_budget() {
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_memory_budget(usize::max_value() / 1024);
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    match errors.0[0] {
+        ConfigError::InvalidMemoryBudget => {},
+        ref e => panic!("Unexpected error: {}", e),
+    }
+
+    builder.with_memory_budget(512);
+    assert!(builder.build().is_ok());
+}
+
+#[test]
+fn it_hides_the_rpc_password() {
+    let config = ClientConfig::from_str("[rpc-server]\nusername = \"user\"\npassword = \"hunter2\"\n").unwrap();
+    let rpc_server = config.rpc_server.unwrap();
+    assert!(rpc_server.password.as_ref().unwrap().ct_eq(b"hunter2"));
+    assert!(!format!("{:?}", rpc_server).contains("hunter2"));
+}
+
+#[test]
+fn it_parses_the_rpc_tls_settings() {
+    let config = ClientConfig::from_str("[rpc-server]\n[rpc-server.tls]\ncertificate_file = \"rpc.crt\"\nprivate_key_file = \"rpc.key\"\n").unwrap();
+    let tls = config.rpc_server.unwrap().tls.unwrap();
+    assert_eq!(tls.certificate_file, "rpc.crt");
+    assert_eq!(tls.private_key_file, "rpc.key");
+    assert_eq!(tls.client_ca_file, None);
+}
+
+#[test]
+fn it_rejects_an_invalid_metrics_allowip() {
+    let config = ClientConfig::from_str("[metrics-server]\nallowip = [\"10.0.0.0/8\", \"10.0.0.1/33\"]\n[metrics-server.tls]\ncertificate_file = \"metrics.crt\"\nprivate_key_file = \"metrics.key\"\n").unwrap();
+    assert!(config.metrics_server.as_ref().unwrap().tls.is_some());
+
+    let errors = config.validate().unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    match errors.0[0] {
+        ConfigError::InvalidAllowIp(ref subnet, _) => assert_eq!(subnet, "10.0.0.1/33"),
+        ref e => panic!("Unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn it_parses_the_broadcast_strategy() {
+    let config = ClientConfig::from_str("[mempool]\nbroadcast = \"random\"\nbroadcast_peers = 4\n").unwrap();
+    let mempool = config.mempool.unwrap();
+    assert_eq!(mempool.broadcast, Some(TransactionBroadcast::Random));
+    assert_eq!(mempool.broadcast_peers, Some(4));
+
+    assert!(ClientConfig::from_str("[mempool]\nbroadcast = \"gossip\"\n").is_err());
+}
+
+#[test]
+fn it_rejects_a_zero_sender_limit() {
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_mempool(MempoolSettings {
+            blacklist_limit: None,
+            filter: None,
+            replacement_fee_factor: None,
+            transaction_ttl: None,
+            sender_limit: Some(0),
+            bytes_limit: None,
+            broadcast: None,
+            broadcast_peers: None,
+            rebroadcast_blocks: None,
+        });
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    match errors.0[0] {
+        ConfigError::InvalidSenderLimit => {},
+        ref e => panic!("Unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn it_rejects_long_extra_data() {
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_network(Network::DevAlbatross)
+        .with_validator(ValidatorSettings {
+            extra_data: Some("x".repeat(256)),
+            ..Default::default()
+        });
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    match errors.0[0] {
+        ConfigError::ExtraDataTooLong(256) => {},
+        ref e => panic!("Unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn it_rejects_invalid_blacklist_entries() {
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_network(Network::DevAlbatross)
+        .with_validator(ValidatorSettings {
+            blacklist: BlacklistSettings {
+                addresses: vec!["NQ07 0000 0000 0000 0000 0000 0000 0000 0000".to_string()],
+                transactions: vec!["not a hash".to_string()],
+            },
+            ..Default::default()
+        });
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    match errors.0[0] {
+        ConfigError::InvalidBlacklistTransaction(ref hash) => assert_eq!(hash, "not a hash"),
+        ref e => panic!("Unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn it_parses_the_lmdb_settings() {
+    let config = ClientConfig::from_str("[database]\nno_lmdb_meta_sync = false\nlmdb_write_map = true\nlmdb_max_readers = 512\n").unwrap();
+    assert_eq!(config.database.no_lmdb_meta_sync, Some(false));
+    assert_eq!(config.database.lmdb_write_map, Some(true));
+    assert_eq!(config.database.no_lmdb_read_ahead, None);
+    assert_eq!(config.database.lmdb_max_readers, Some(512));
+}
+
+#[test]
+fn it_rejects_webhooks_on_minimal_validators() {
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_network(Network::DevAlbatross)
+        .with_validator(ValidatorSettings {
+            profile: ValidatorProfile::Minimal,
+            ..Default::default()
+        })
+        .with_webhook(WebhookSettings {
+            url: "http://localhost:8080/hook".to_string(),
+            events: Vec::new(),
+            addresses: Vec::new(),
+            secret: None,
+        });
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    match errors.0[0] {
+        ConfigError::MinimalValidatorWithWebhooks => {},
+        ref e => panic!("Unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn it_validates_replicas() {
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_network(Network::DevAlbatross)
+        .with_validator(ValidatorSettings::default())
+        .with_replica(ReplicaSettings {
+            refresh_interval: Some(0),
+            ..Default::default()
+        });
+
+    let errors = builder.build().unwrap_err();
+    assert_eq!(errors.0.len(), 3);
+    assert!(errors.0.iter().any(|e| match e { ConfigError::ReplicaRequiresRpcServer => true, _ => false }));
+    assert!(errors.0.iter().any(|e| match e { ConfigError::ReplicaIsReadOnly => true, _ => false }));
+    assert!(errors.0.iter().any(|e| match e { ConfigError::InvalidReplicaRefreshInterval => true, _ => false }));
+
+    let mut builder = ClientConfig::builder();
+    builder.with_protocol(Protocol::Dumb)
+        .with_network(Network::DevAlbatross)
+        .with_rpc_server(RpcServerSettings::default())
+        .with_replica(ReplicaSettings::default());
+    assert!(builder.build().is_ok());
+}
+
+#[test]
+fn it_serializes_to_toml() {
+    let config = ClientConfig::builder()
+        .with_protocol(Protocol::Wss)
+        .with_hostname("my.domain")
+        .with_port(8443)
+        .with_tls_identity("identity.p12", "secret")
+        .with_log_level(LevelFilter::Debug)
+        .with_peer_key_file("peer_key.dat")
+        .build()
+        .unwrap();
+
+    let serialized = toml::to_string(&config).unwrap();
+    let deserialized = ClientConfig::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.network.host, Some("my.domain".to_string()));
+    assert_eq!(deserialized.network.port, Some(8443));
+    assert_eq!(deserialized.network.protocol, Protocol::Wss);
+    assert_eq!(deserialized.log.level, Some(LevelFilter::Debug));
+    assert_eq!(deserialized.peer_key_file, Some("peer_key.dat".to_string()));
+    assert!(deserialized.validate().is_ok());
+}
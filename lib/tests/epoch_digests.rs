@@ -0,0 +1,32 @@
+use std::str::FromStr;
+
+use hash::{Blake2bHash, Hash};
+use lib::epoch_digests::{EpochDigests, EpochDigestsError};
+
+#[test]
+fn it_parses_and_checks_epoch_digests() {
+    let digest1 = "epoch 1".hash::<Blake2bHash>();
+    let digest2 = "epoch 2".hash::<Blake2bHash>();
+    let digests = EpochDigests::from_str(&format!("# Published digests\n\n1 {}\n  2\t{}  \n", digest1, digest2)).unwrap();
+
+    assert_eq!(digests.len(), 2);
+    assert_eq!(digests.check(1, &digest1), Some(true));
+    assert_eq!(digests.check(2, &digest1), Some(false));
+    assert_eq!(digests.check(3, &digest1), None);
+}
+
+#[test]
+fn it_rejects_invalid_epoch_digests() {
+    let digest = "epoch 1".hash::<Blake2bHash>();
+    for (s, line) in &[
+        (format!("1 {}\n2", digest), 2),
+        (format!("one {}", digest), 1),
+        ("1 abcdef".to_string(), 1),
+        (format!("1 {} 2", digest), 1),
+    ] {
+        match EpochDigests::from_str(s) {
+            Err(EpochDigestsError::InvalidLine(l)) => assert_eq!(l, *line),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
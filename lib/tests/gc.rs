@@ -0,0 +1,45 @@
+use std::sync::{Arc, Mutex};
+
+use blockchain_albatross::Blockchain;
+use database::volatile::VolatileEnvironment;
+use lib::config::GcSettings;
+use lib::gc::GarbageCollector;
+use primitives::networks::NetworkId;
+
+fn collector() -> Arc<GarbageCollector> {
+    // The collector outlives any borrow of a local environment.
+    let env: &'static VolatileEnvironment = Box::leak(Box::new(VolatileEnvironment::new(10).unwrap()));
+    let blockchain = Arc::new(Blockchain::new(env, NetworkId::UnitAlbatross).unwrap());
+    GarbageCollector::new(env, &blockchain, GcSettings::default())
+}
+
+#[test]
+fn it_prunes_stores_by_their_retention() {
+    let gc = collector();
+
+    let pruned = Arc::new(Mutex::new(Vec::new()));
+    let pruned_clone = Arc::clone(&pruned);
+    gc.register("store", 2, move |_, epoch| pruned_clone.lock().unwrap().push(epoch));
+    gc.register("kept forever", 0, |_, _| panic!("Stores with a retention of 0 are never pruned"));
+
+    gc.collect(5);
+    // Epoch 4 and the finalized epoch 5 are kept.
+    assert_eq!(*pruned.lock().unwrap(), vec![4]);
+
+    // The genesis epoch is never pruned.
+    gc.collect(1);
+    assert_eq!(*pruned.lock().unwrap(), vec![4, 1]);
+}
+
+#[test]
+fn it_purges_expiring_stores_at_each_collection() {
+    let gc = collector();
+
+    let purged = Arc::new(Mutex::new(0));
+    let purged_clone = Arc::clone(&purged);
+    gc.register_expiring("expiring", move |_| *purged_clone.lock().unwrap() += 1);
+
+    gc.collect(1);
+    gc.collect(2);
+    assert_eq!(*purged.lock().unwrap(), 2);
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use block_albatross::{Block, MacroHeader, MacroHeaderDiff};
+use blockchain_albatross::Blockchain;
+use collections::compressed_list::CompressedList;
+use collections::grouped_list::{Group, GroupedList};
+use database::volatile::VolatileEnvironment;
+use hash::Blake2bHash;
+use lib::header_diffs::{HeaderDiffError, MacroHeaderDiffRelay};
+use primitives::networks::NetworkId;
+use primitives::policy;
+use primitives::validators::Validators;
+
+fn genesis_header(blockchain: &Blockchain) -> MacroHeader {
+    match blockchain.get_block_at(0, false).unwrap() {
+        Block::Macro(macro_block) => macro_block.header,
+        Block::Micro(_) => panic!("Genesis block must be a macro block"),
+    }
+}
+
+/// A header for the macro block of epoch 1 that gives the genesis validator half of the slots.
+fn next_macro_header(genesis: &MacroHeader) -> MacroHeader {
+    let validators: Validators = genesis.validators.into_iter().cloned().collect();
+    let Group(_, public_key) = validators.groups()[0].clone();
+
+    let mut header = genesis.clone();
+    header.block_number = policy::macro_block_of(1);
+    header.validators = CompressedList::from(GroupedList(vec![Group(policy::SLOTS / 2, public_key)]));
+    header
+}
+
+#[test]
+fn it_rebuilds_received_macro_headers() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let relay = MacroHeaderDiffRelay::new(Arc::clone(&blockchain));
+
+    let notified = Arc::new(AtomicUsize::new(0));
+    let notified_clone = Arc::clone(&notified);
+    relay.notifier.write().register(move |_: &MacroHeader| { notified_clone.fetch_add(1, Ordering::SeqCst); });
+
+    let genesis = genesis_header(&blockchain);
+    let header = next_macro_header(&genesis);
+    let prev_validators = blockchain.get_elected_validators(0).unwrap();
+    let header_diff = MacroHeaderDiff::new(&header, &prev_validators);
+    assert!(header_diff.header.validators.is_empty());
+
+    assert_eq!(relay.receive(header_diff).unwrap(), Some(header.clone()));
+    assert_eq!(notified.load(Ordering::SeqCst), 1);
+
+    // The genesis block has no previous validators to diff against
+    let header_diff = MacroHeaderDiff::new(&genesis, &prev_validators);
+    match relay.receive(header_diff) {
+        Err(HeaderDiffError::NotAMacroBlock(0)) => {},
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    assert_eq!(notified.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn it_rejects_invalid_macro_header_diffs() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+    let relay = MacroHeaderDiffRelay::new(Arc::clone(&blockchain));
+    let prev_validators = blockchain.get_elected_validators(0).unwrap();
+    let header = next_macro_header(&genesis_header(&blockchain));
+
+    // Validators that don't match the hash
+    let mut header_diff = MacroHeaderDiff::new(&header, &prev_validators);
+    header_diff.validators_hash = Blake2bHash::default();
+    match relay.receive(header_diff) {
+        Err(HeaderDiffError::InvalidDiff(_)) => {},
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    // Not the block number of a macro block
+    let mut micro_header = header.clone();
+    micro_header.block_number += 1;
+    match relay.receive(MacroHeaderDiff::new(&micro_header, &prev_validators)) {
+        Err(HeaderDiffError::NotAMacroBlock(129)) => {},
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    // We don't know the validators elected in epoch 1 yet
+    let mut later_header = header.clone();
+    later_header.block_number = policy::macro_block_of(2);
+    match relay.receive(MacroHeaderDiff::new(&later_header, &prev_validators)) {
+        Err(HeaderDiffError::UnknownValidators(1)) => {},
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
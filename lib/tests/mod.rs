@@ -0,0 +1,21 @@
+extern crate beserial;
+extern crate nimiq_account as account;
+extern crate nimiq_block_albatross as block_albatross;
+extern crate nimiq_block_production_albatross as block_production_albatross;
+extern crate nimiq_blockchain_albatross as blockchain_albatross;
+extern crate nimiq_bls as bls;
+extern crate nimiq_collections as collections;
+extern crate nimiq_database as database;
+extern crate nimiq_hash as hash;
+extern crate nimiq_keys as keys;
+extern crate nimiq_lib as lib;
+extern crate nimiq_primitives as primitives;
+extern crate nimiq_transaction as transaction;
+
+mod config;
+mod epoch_digests;
+mod gc;
+mod header_diffs;
+mod payment;
+mod updater;
+mod webhooks;
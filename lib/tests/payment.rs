@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use futures::Future;
+
+use beserial::Deserialize;
+use block_albatross::Block;
+use block_production_albatross::BlockProducer;
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::confirmations::{ConfirmationPolicy, ConfirmationTracker, ConfirmedBlock};
+use bls::{KeyPair, SecretKey};
+use database::volatile::VolatileEnvironment;
+use keys::Address;
+use lib::payment::PaymentFuture;
+use primitives::coin::Coin;
+use primitives::networks::NetworkId;
+use transaction::Transaction;
+
+/// Secret key of validator. Tests run with `network-primitives/src/genesis/unit-albatross.toml`
+const SECRET_KEY: &'static str = "49ea68eb6b8afdf4ca4d4c0a0b295c76ca85225293693bc30e755476492b707f";
+
+fn confirmed_block(producer: &BlockProducer, transactions: Vec<Transaction>) -> ConfirmedBlock {
+    let mut block = Block::Micro(producer.next_micro_block(vec![], 1565713920000, 0, vec![0x42], None));
+    *block.transactions_mut().unwrap() = transactions;
+    ConfirmedBlock {
+        hash: block.hash(),
+        block,
+        finalized: false,
+    }
+}
+
+#[test]
+fn it_resolves_with_the_first_sufficient_payment() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::UnitAlbatross).unwrap());
+
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let producer = BlockProducer::new_without_mempool(Arc::clone(&blockchain), keypair);
+
+    let recipient = Address::from([1u8; 20]);
+    let other = Address::from([2u8; 20]);
+    let tracker = ConfirmationTracker::new(Arc::clone(&blockchain), ConfirmationPolicy::Depth(0), 0);
+    let payment = PaymentFuture::with_tracker(Arc::clone(&tracker), recipient.clone(), Coin::from_u64_unchecked(100));
+
+    let too_small = Transaction::new_basic(other.clone(), recipient.clone(), Coin::from_u64_unchecked(99), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    let unrelated = Transaction::new_basic(other.clone(), other.clone(), Coin::from_u64_unchecked(500), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    let sufficient = Transaction::new_basic(other.clone(), recipient.clone(), Coin::from_u64_unchecked(100), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    let later = Transaction::new_basic(other, recipient, Coin::from_u64_unchecked(200), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+
+    tracker.notifier.read().notify(confirmed_block(&producer, vec![too_small.clone()]));
+    let block = confirmed_block(&producer, vec![unrelated, too_small, sufficient.clone()]);
+    let block_hash = block.hash.clone();
+    tracker.notifier.read().notify(block);
+    tracker.notifier.read().notify(confirmed_block(&producer, vec![later]));
+
+    let payment = payment.wait().unwrap();
+    assert_eq!(payment.transaction, sufficient);
+    assert_eq!(payment.block_hash, block_hash);
+    assert_eq!(payment.block_number, 1);
+    assert_eq!(payment.timestamp, 1565713920000);
+    assert_eq!(payment.transaction_index, 2);
+}
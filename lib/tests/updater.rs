@@ -0,0 +1,53 @@
+use std::str::FromStr;
+
+use keys::KeyPair;
+use lib::updater::{ReleaseManifest, UpdaterError, Version};
+
+fn sign_manifest(key_pair: &KeyPair, lines: &[&str]) -> String {
+    let data = lines.join("\n");
+    let signature = key_pair.sign(data.as_bytes());
+    format!("# Release manifest\n{}\n{}\n", data, hex::encode(&signature.to_bytes()[..]))
+}
+
+#[test]
+fn it_parses_and_compares_versions() {
+    assert_eq!(Version::from_str("0.1.0").unwrap(), Version { major: 0, minor: 1, patch: 0 });
+    assert_eq!(Version::from_str("1.2.3+abcdef").unwrap(), Version { major: 1, minor: 2, patch: 3 });
+    assert_eq!(Version::from_str("1.2.3-rc1").unwrap(), Version { major: 1, minor: 2, patch: 3 });
+    assert!(Version::from_str("1.2").is_err());
+    assert!(Version::from_str("a.b.c").is_err());
+
+    assert!(Version::from_str("0.2.0").unwrap() > Version::from_str("0.1.9").unwrap());
+    assert!(Version::from_str("1.0.0").unwrap() > Version::from_str("0.10.10").unwrap());
+}
+
+#[test]
+fn it_parses_signed_manifests() {
+    let key_pair = KeyPair::generate();
+    let manifest = sign_manifest(&key_pair, &[
+        "version: 0.2.0",
+        "url: https://example.com/nimiq-client-0.2.0.tar.gz",
+        "sha256: 2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae",
+    ]);
+
+    let release = ReleaseManifest::parse(&manifest, &key_pair.public).unwrap();
+    assert_eq!(release.version, Version { major: 0, minor: 2, patch: 0 });
+    assert_eq!(release.url.unwrap().as_str(), "https://example.com/nimiq-client-0.2.0.tar.gz");
+    assert!(release.sha256.is_some());
+}
+
+#[test]
+fn it_rejects_invalid_signatures() {
+    let key_pair = KeyPair::generate();
+    let other_key_pair = KeyPair::generate();
+    let manifest = sign_manifest(&key_pair, &["version: 0.2.0"]);
+
+    match ReleaseManifest::parse(&manifest, &other_key_pair.public) {
+        Err(UpdaterError::SignatureVerificationFailed) => {},
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    match ReleaseManifest::parse("version: 0.2.0\n", &key_pair.public) {
+        Err(UpdaterError::SignatureMissing) => {},
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
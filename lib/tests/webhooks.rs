@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use url::Url;
+
+use account::{Account, BasicAccount, PrunedAccount};
+use beserial::Deserialize;
+use block_albatross::Block;
+use block_production_albatross::BlockProducer;
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::blockchain::{BlockchainEvent, PushResult};
+use blockchain_albatross::pruned_accounts::PrunedAccounts;
+use bls::{KeyPair, SecretKey};
+use database::Environment;
+use database::volatile::VolatileEnvironment;
+use hash::Blake2bHash;
+use keys::{Address, SecretBytes};
+use lib::webhooks::{Delivery, WebhookConfig, WebhookDispatcher, WebhookEvent, WebhookQueue};
+use primitives::coin::Coin;
+use primitives::networks::NetworkId;
+use transaction::Transaction;
+
+/// Secret key of validator. Tests run with `network-primitives/src/genesis/unit-albatross.toml`
+const SECRET_KEY: &'static str = "49ea68eb6b8afdf4ca4d4c0a0b295c76ca85225293693bc30e755476492b707f";
+
+fn delivery(body: &str, next_attempt: u64) -> Delivery {
+    Delivery {
+        url: "https://example.com/hook".to_string(),
+        event: WebhookEvent::Block,
+        body: body.to_string(),
+        signature: None,
+        attempts: 0,
+        next_attempt,
+    }
+}
+
+#[test]
+fn it_filters_events_and_transactions() {
+    let watched = Address::from([1u8; 20]);
+    let other = Address::from([2u8; 20]);
+    let mut config = WebhookConfig {
+        url: Url::parse("https://example.com/hook").unwrap(),
+        events: HashSet::new(),
+        addresses: HashSet::new(),
+        secret: None,
+    };
+
+    let transaction = Transaction::new_basic(other.clone(), other.clone(), Coin::ZERO, Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    assert!(config.accepts(WebhookEvent::Block));
+    assert!(config.accepts_transaction(&transaction));
+
+    config.events.insert(WebhookEvent::Transaction);
+    config.addresses.insert(watched.clone());
+    assert!(!config.accepts(WebhookEvent::Block));
+    assert!(!config.accepts_transaction(&transaction));
+    let transaction = Transaction::new_basic(other, watched, Coin::ZERO, Coin::ZERO, 1, NetworkId::UnitAlbatross);
+    assert!(config.accepts_transaction(&transaction));
+
+    assert_eq!(config.sign("body"), None);
+    config.secret = Some(SecretBytes::from("secret"));
+    let signature = config.sign("body").unwrap();
+    assert_eq!(signature.len(), 128);
+    assert_ne!(config.sign("other body"), Some(signature));
+}
+
+#[test]
+fn it_parses_events() {
+    for event in &[WebhookEvent::Block, WebhookEvent::Transaction, WebhookEvent::ForkProof, WebhookEvent::ViewChange, WebhookEvent::Revert, WebhookEvent::PrunedAccounts] {
+        assert_eq!(WebhookEvent::from_str(event.as_str()).unwrap(), *event);
+    }
+    assert!(WebhookEvent::from_str("blocks").is_err());
+}
+
+#[test]
+fn it_backs_off_exponentially() {
+    assert_eq!(WebhookDispatcher::retry_delay(1), 5);
+    assert_eq!(WebhookDispatcher::retry_delay(2), 10);
+    assert_eq!(WebhookDispatcher::retry_delay(3), 20);
+    assert_eq!(WebhookDispatcher::retry_delay(20), 60 * 60);
+    assert_eq!(WebhookDispatcher::retry_delay(WebhookDispatcher::MAX_ATTEMPTS), 60 * 60);
+}
+
+#[test]
+fn it_persists_the_queue() {
+    let env = VolatileEnvironment::new(1).unwrap();
+    {
+        let queue = WebhookQueue::new(&env);
+        queue.push(&[delivery("first", 0), delivery("second", 100)]);
+        assert_eq!(queue.len(), 2);
+    }
+
+    let queue = WebhookQueue::new(&env);
+    let due = queue.due(50);
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].1, delivery("first", 0));
+
+    let mut retry = due[0].1.clone();
+    retry.attempts = 1;
+    retry.next_attempt = 200;
+    queue.update(due[0].0, &retry);
+    assert_eq!(queue.due(150).iter().map(|(_, d)| d.body.as_str()).collect::<Vec<_>>(), vec!["second"]);
+
+    // New deliveries get new ids and deliveries are sorted by the time they are due.
+    queue.push(&[delivery("third", 0)]);
+    let due = queue.due(200);
+    assert_eq!(due.iter().map(|(_, d)| d.body.as_str()).collect::<Vec<_>>(), vec!["third", "second", "first"]);
+    assert!(due[0].0.id > due[2].0.id);
+
+    for (key, _) in due {
+        queue.remove(key);
+    }
+    assert!(queue.is_empty());
+
+    // Ids aren't reused after a restart.
+    queue.push(&[delivery("fourth", 0)]);
+    let id = queue.due(0)[0].0.id;
+    let queue = WebhookQueue::new(&env);
+    queue.push(&[delivery("fifth", 0)]);
+    assert_eq!(queue.due(0)[1].0.id, id + 1);
+}
+
+#[test]
+fn it_delivers_each_block_once_and_signals_reverts() {
+    let env: &'static Environment = Box::leak(Box::new(VolatileEnvironment::new(10).unwrap()));
+    let blockchain = Arc::new(Blockchain::new(env, NetworkId::UnitAlbatross).unwrap());
+
+    let keypair = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+    let producer = BlockProducer::new_without_mempool(Arc::clone(&blockchain), keypair);
+
+    let webhook = WebhookConfig {
+        url: Url::parse("https://example.com/hook").unwrap(),
+        events: [WebhookEvent::Block, WebhookEvent::Revert].iter().cloned().collect(),
+        addresses: HashSet::new(),
+        secret: None,
+    };
+    let dispatcher = WebhookDispatcher::new(env, Arc::clone(&blockchain), vec![webhook]);
+    let queue = WebhookQueue::new(env);
+
+    let block = Block::Micro(producer.next_micro_block(vec![], 1565713920000, 0, vec![0x42], None));
+    let hash = block.hash();
+    assert_eq!(blockchain.push(block.clone()), Ok(PushResult::Extended));
+
+    // Pushing only passes the event on, it is queued by the dispatcher.
+    assert!(queue.is_empty());
+    dispatcher.handle_event(&BlockchainEvent::Extended(hash.clone()));
+    dispatcher.handle_event(&BlockchainEvent::Finalized(hash.clone()));
+    let due = queue.due(0);
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].1.event, WebhookEvent::Block);
+    assert!(due[0].1.body.contains(&format!("block:{}", hash.to_hex())));
+
+    // Reverted blocks are signalled and delivered again once they are adopted again.
+    dispatcher.handle_event(&BlockchainEvent::Rebranched(vec![(hash.clone(), block.clone())], vec![]));
+    dispatcher.handle_event(&BlockchainEvent::Rebranched(vec![], vec![(hash.clone(), block)]));
+    let events: Vec<WebhookEvent> = queue.due(0).iter().map(|(_, d)| d.event).collect();
+    assert_eq!(events, vec![WebhookEvent::Block, WebhookEvent::Revert, WebhookEvent::Block]);
+}
+
+#[test]
+fn it_delivers_pruned_accounts_of_watched_addresses() {
+    let env: &'static Environment = Box::leak(Box::new(VolatileEnvironment::new(10).unwrap()));
+    let blockchain = Arc::new(Blockchain::new(env, NetworkId::UnitAlbatross).unwrap());
+
+    let watched = Address::from([1u8; Address::SIZE]);
+    let other = Address::from([2u8; Address::SIZE]);
+    let webhook = |events: &[WebhookEvent], addresses: &[Address]| WebhookConfig {
+        url: Url::parse("https://example.com/hook").unwrap(),
+        events: events.iter().cloned().collect(),
+        addresses: addresses.iter().cloned().collect(),
+        secret: None,
+    };
+    let dispatcher = WebhookDispatcher::new(env, Arc::clone(&blockchain), vec![
+        webhook(&[WebhookEvent::PrunedAccounts], &[watched.clone()]),
+        webhook(&[WebhookEvent::PrunedAccounts], &[Address::from([3u8; Address::SIZE])]),
+        webhook(&[WebhookEvent::Block], &[]),
+    ]);
+    let queue = WebhookQueue::new(env);
+
+    let pruned_account = |address: &Address| PrunedAccount {
+        address: address.clone(),
+        account: Account::Basic(BasicAccount { balance: Coin::ZERO }),
+    };
+    let block_hash = Blake2bHash::from([7u8; Blake2bHash::SIZE]);
+    dispatcher.handle_pruned_accounts(&PrunedAccounts {
+        block_hash: block_hash.clone(),
+        block_number: 3,
+        accounts: vec![pruned_account(&watched), pruned_account(&other)],
+    });
+
+    // Only the webhook watching one of the pruned accounts gets a delivery, and only for that account.
+    let due = queue.due(0);
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].1.event, WebhookEvent::PrunedAccounts);
+    let body = json::parse(&due[0].1.body).unwrap();
+    assert_eq!(body["id"], format!("prunedAccounts:{}", block_hash.to_hex()).as_str());
+    assert_eq!(body["data"]["blockNumber"], 3);
+    assert_eq!(body["data"]["accounts"].len(), 1);
+    assert_eq!(body["data"]["accounts"][0]["address"], watched.to_user_friendly_address().as_str());
+    assert!(body["data"]["accounts"][0]["restoration"].is_null());
+}
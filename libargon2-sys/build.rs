@@ -1,9 +1,29 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Compiles one implementation of `fill_segment` into its own library, renamed to `name`.
+fn compile_fill_segment(file: &str, name: &str, flags: &[&str]) {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap()).join(name);
+
+    let mut config = cc::Build::new();
+    config.file(file);
+    config.opt_level(2);
+    config.include("native");
+    config.define("fill_segment", Some(name));
+    for flag in flags {
+        config.flag(flag);
+    }
+    config.out_dir(out_dir);
+
+    config.compile(&format!("lib{}.a", name));
+}
+
 fn main() {
     let mut config = cc::Build::new();
     config.file("native/argon2.c")
         .file("native/core.c")
         .file("native/blake2/blake2b.c")
-        .file("native/opt.c");
+        .file("native/dispatch.c");
 
     config.opt_level(2);
 
@@ -11,4 +31,14 @@ fn main() {
     config.flag("-DARGON2_NO_THREADS");
 
     config.compile("libargon2.a");
+
+    // The portable implementation is always available. On x86, the SSE2 and AVX2 implementations
+    // are built as well and selected at runtime depending on the CPU.
+    compile_fill_segment("native/ref.c", "fill_segment_ref", &[]);
+
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    if target_arch == "x86_64" || target_arch == "x86" {
+        compile_fill_segment("native/opt.c", "fill_segment_sse2", &["-msse2"]);
+        compile_fill_segment("native/opt.c", "fill_segment_avx2", &["-mavx2"]);
+    }
 }
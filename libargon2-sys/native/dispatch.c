@@ -0,0 +1,50 @@
+/*
+ * Selects the implementation of fill_segment at runtime. The implementations are compiled from
+ * ref.c and opt.c with fill_segment renamed, see build.rs.
+ */
+
+#include "argon2.h"
+#include "core.h"
+
+#define ARGON2_IMPL_REF 0
+#define ARGON2_IMPL_SSE2 1
+#define ARGON2_IMPL_AVX2 2
+
+typedef void (*fill_segment_fn)(const argon2_instance_t *instance,
+                                argon2_position_t position);
+
+void fill_segment_ref(const argon2_instance_t *instance,
+                      argon2_position_t position);
+#if defined(__x86_64__) || defined(__i386__) || defined(_M_X64) || defined(_M_IX86)
+void fill_segment_sse2(const argon2_instance_t *instance,
+                       argon2_position_t position);
+void fill_segment_avx2(const argon2_instance_t *instance,
+                       argon2_position_t position);
+#endif
+
+static fill_segment_fn fill_segment_impl = fill_segment_ref;
+
+/*
+ * Returns the selected implementation, which may differ from the requested one if it isn't
+ * available on this architecture.
+ */
+int argon2_select_impl(int impl) {
+    switch (impl) {
+#if defined(__x86_64__) || defined(__i386__) || defined(_M_X64) || defined(_M_IX86)
+    case ARGON2_IMPL_SSE2:
+        fill_segment_impl = fill_segment_sse2;
+        return ARGON2_IMPL_SSE2;
+    case ARGON2_IMPL_AVX2:
+        fill_segment_impl = fill_segment_avx2;
+        return ARGON2_IMPL_AVX2;
+#endif
+    default:
+        fill_segment_impl = fill_segment_ref;
+        return ARGON2_IMPL_REF;
+    }
+}
+
+void fill_segment(const argon2_instance_t *instance,
+                  argon2_position_t position) {
+    fill_segment_impl(instance, position);
+}
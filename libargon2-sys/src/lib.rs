@@ -1,5 +1,8 @@
 #![allow(dead_code)]
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+
 use libc::{c_int, size_t};
 
 extern {
@@ -8,6 +11,72 @@ extern {
         pwdlen: size_t, salt: *const u8,
         saltlen: size_t, hash: *mut u8,
         hashlen: size_t, flags: u32) -> c_int;
+
+    fn argon2_select_impl(implementation: c_int) -> c_int;
+}
+
+/// Implementation of the memory filling function, which is where Argon2 spends its time.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Argon2Implementation {
+    /// Portable C implementation.
+    Reference = 0,
+    Sse2 = 1,
+    Avx2 = 2,
+}
+
+impl Argon2Implementation {
+    /// Returns the fastest implementation the CPU supports.
+    pub fn detect() -> Self {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Argon2Implementation::Avx2;
+            }
+            if is_x86_feature_detected!("sse2") {
+                return Argon2Implementation::Sse2;
+            }
+        }
+        Argon2Implementation::Reference
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Argon2Implementation::Reference => "portable",
+            Argon2Implementation::Sse2 => "sse2",
+            Argon2Implementation::Avx2 => "avx2",
+        }
+    }
+
+    fn from_c_int(value: c_int) -> Self {
+        match value {
+            1 => Argon2Implementation::Sse2,
+            2 => Argon2Implementation::Avx2,
+            _ => Argon2Implementation::Reference,
+        }
+    }
+}
+
+static SELECT_IMPLEMENTATION: Once = Once::new();
+static IMPLEMENTATION: AtomicUsize = AtomicUsize::new(Argon2Implementation::Reference as usize);
+
+/// Selects `implementation` for all following hashes. Falls back to the reference implementation
+/// if it isn't available on this architecture. Returns the selected implementation.
+///
+/// Selecting an implementation the CPU doesn't support crashes the process on the next hash.
+pub fn set_argon2_implementation(implementation: Argon2Implementation) -> Argon2Implementation {
+    // Prevent the detection from overriding this choice.
+    SELECT_IMPLEMENTATION.call_once(|| {});
+    let selected = Argon2Implementation::from_c_int(unsafe { argon2_select_impl(implementation as c_int) });
+    IMPLEMENTATION.store(selected as usize, Ordering::SeqCst);
+    selected
+}
+
+/// Returns the implementation in use. The fastest implementation is selected on first use.
+pub fn argon2_implementation() -> Argon2Implementation {
+    SELECT_IMPLEMENTATION.call_once(|| {
+        set_argon2_implementation(Argon2Implementation::detect());
+    });
+    Argon2Implementation::from_c_int(IMPLEMENTATION.load(Ordering::SeqCst) as c_int)
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -124,6 +193,7 @@ impl From<c_int> for Argon2Error {
 }
 
 pub fn argon2d_hash(t_cost: u32, m_cost: u32, parallelism: u32, pwd: &[u8], salt: &[u8], out: &mut [u8], flags: u32) -> Result<(), Argon2Error> {
+    argon2_implementation();
     let return_value = unsafe { argon2d_hash_raw_flags(t_cost, m_cost, parallelism,pwd.as_ptr(), pwd.len(), salt.as_ptr(), salt.len(), out.as_mut_ptr(), out.len(), flags) };
     if return_value == 0 {
         Ok(())
@@ -149,4 +219,23 @@ mod tests {
         assert!(argon2d_hash(1, 512, 1,b"test", b"nimiqrocks!", &mut res, 0).is_ok());
         assert_eq!(res, [140, 37, 159, 220, 194, 173, 103, 153, 223, 114, 140, 17, 232, 149, 163, 54, 158, 157, 186, 230, 163, 22, 110, 188, 59, 53, 51, 153, 252, 86, 85, 36]);
     }
+
+    #[test]
+    fn implementations_agree() {
+        let detected = Argon2Implementation::detect();
+        let mut implementations = vec![Argon2Implementation::Reference];
+        if detected != Argon2Implementation::Reference {
+            implementations.push(Argon2Implementation::Sse2);
+        }
+        if detected == Argon2Implementation::Avx2 {
+            implementations.push(Argon2Implementation::Avx2);
+        }
+
+        for implementation in implementations {
+            assert_eq!(set_argon2_implementation(implementation), implementation);
+            let mut res = [0u8; 32];
+            assert!(argon2d_hash(1, 512, 1,b"test", b"nimiqrocks!", &mut res, 0).is_ok());
+            assert_eq!(res, [140, 37, 159, 220, 194, 173, 103, 153, 223, 114, 140, 17, 232, 149, 163, 54, 158, 157, 186, 230, 163, 22, 110, 188, 59, 53, 51, 153, 252, 86, 85, 36]);
+        }
+    }
 }
@@ -0,0 +1,52 @@
+use block::{MacroHeader, PbftProof};
+use hash::{Blake2bHash, Hash};
+use primitives::policy;
+use transaction::Transaction;
+use utils::merkle::MerkleProof;
+
+use crate::{MacroChainVerifier, VerifyError};
+
+/// A macro header together with the proof that the validators of its epoch finalized it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JustifiedMacroHeader {
+    pub header: MacroHeader,
+    pub justification: PbftProof,
+}
+
+/// A self-contained proof that a transaction is part of a finalized epoch.
+///
+/// The attestation starts at a macro block the verifier already trusts. `macro_chain` contains all
+/// following macro headers up to the one finalizing the transaction's epoch, which also proves the
+/// lineage of the validator sets. `transactions_proof` proves the inclusion of the transaction in
+/// that header's transactions root.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    pub transaction: Transaction,
+    pub block_number: u32,
+    #[beserial(len_type(u16))]
+    pub macro_chain: Vec<JustifiedMacroHeader>,
+    pub transactions_proof: MerkleProof<Blake2bHash>,
+}
+
+impl Attestation {
+    /// Verifies the attestation starting from `trusted`. Returns the verifier advanced to the
+    /// macro block finalizing the transaction, so that it can be used for subsequent attestations.
+    pub fn verify(&self, trusted: &MacroChainVerifier) -> Result<MacroChainVerifier, VerifyError> {
+        let mut verifier = trusted.clone();
+        for justified in &self.macro_chain {
+            verifier.push(&justified.header, &justified.justification)?;
+        }
+
+        if policy::epoch_at(self.block_number) != policy::epoch_at(verifier.block_number()) {
+            return Err(VerifyError::InvalidTransactionsProof);
+        }
+
+        let transactions_root = self.transactions_proof.compute_root(vec![self.transaction.hash()])
+            .map_err(|_| VerifyError::InvalidTransactionsProof)?;
+        if &transactions_root != verifier.transactions_root() {
+            return Err(VerifyError::InvalidTransactionsProof);
+        }
+
+        Ok(verifier)
+    }
+}
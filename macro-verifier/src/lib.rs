@@ -0,0 +1,122 @@
+//! Light verification of Albatross macro block headers.
+//!
+//! Given a trusted macro header and its validator set, every following macro header can be
+//! verified using only its `PbftProof`, since each macro header commits to the validators of the
+//! next epoch. This crate doesn't depend on a database or the network and can be used by bridges
+//! and other embedded verifiers. The blockchain uses it to verify macro block justifications.
+//!
+//! Note: This crate can't be built for `no_std` targets yet, since `nimiq-hash`, `beserial`,
+//! `nimiq-bls` and `failure` depend on `std`.
+
+#[macro_use]
+extern crate beserial_derive;
+#[macro_use]
+extern crate failure;
+
+extern crate nimiq_block_albatross as block;
+extern crate nimiq_hash as hash;
+extern crate nimiq_primitives as primitives;
+extern crate nimiq_transaction as transaction;
+extern crate nimiq_utils as utils;
+
+pub mod attestation;
+
+use block::{MacroHeader, PbftProof};
+use block::signed::AggregateProofError;
+use hash::{Blake2bHash, Hash};
+use primitives::policy;
+use primitives::validators::Validators;
+
+#[derive(Clone, Debug, PartialEq, Eq, Fail)]
+pub enum VerifyError {
+    #[fail(display = "Wrong block number: expected {}, got {}", expected, actual)]
+    InvalidBlockNumber { expected: u32, actual: u32 },
+    #[fail(display = "Parent macro hash doesn't match the previous macro header")]
+    InvalidParentMacroHash,
+    #[fail(display = "Header contains an invalid validator list")]
+    InvalidValidators,
+    #[fail(display = "Invalid justification: {:?}", _0)]
+    InvalidJustification(AggregateProofError),
+    #[fail(display = "Invalid transactions proof")]
+    InvalidTransactionsProof,
+}
+
+impl From<AggregateProofError> for VerifyError {
+    fn from(e: AggregateProofError) -> Self {
+        VerifyError::InvalidJustification(e)
+    }
+}
+
+/// Verifies that `justification` proves that at least two thirds of the slots of `validators`
+/// prepared and committed to `header`.
+pub fn verify_justification(header: &MacroHeader, justification: &PbftProof, validators: &Validators) -> Result<(), VerifyError> {
+    justification.verify(header.hash(), validators, policy::TWO_THIRD_SLOTS)?;
+    Ok(())
+}
+
+/// Returns the validators that sign the macro block of the epoch following `header`.
+pub fn next_validators(header: &MacroHeader) -> Result<Validators, VerifyError> {
+    if !header.validators.verify() || header.validators.len() != policy::SLOTS as usize {
+        return Err(VerifyError::InvalidValidators);
+    }
+    Ok(header.validators.into_iter().cloned().collect())
+}
+
+/// Follows the chain of macro headers starting from a trusted header.
+#[derive(Clone, Debug)]
+pub struct MacroChainVerifier {
+    head_hash: Blake2bHash,
+    block_number: u32,
+    transactions_root: Blake2bHash,
+    validators: Validators,
+}
+
+impl MacroChainVerifier {
+    /// Creates a verifier that trusts `header`, e.g. the genesis block or a checkpoint.
+    pub fn new(header: &MacroHeader) -> Result<Self, VerifyError> {
+        Ok(MacroChainVerifier {
+            head_hash: header.hash(),
+            block_number: header.block_number,
+            transactions_root: header.transactions_root.clone(),
+            validators: next_validators(header)?,
+        })
+    }
+
+    /// Verifies the macro header of the next epoch and advances to it if it is valid.
+    pub fn push(&mut self, header: &MacroHeader, justification: &PbftProof) -> Result<(), VerifyError> {
+        let expected = policy::macro_block_after(self.block_number);
+        if header.block_number != expected {
+            return Err(VerifyError::InvalidBlockNumber { expected, actual: header.block_number });
+        }
+        if header.parent_macro_hash != self.head_hash {
+            return Err(VerifyError::InvalidParentMacroHash);
+        }
+
+        let validators = next_validators(header)?;
+        verify_justification(header, justification, &self.validators)?;
+
+        self.head_hash = header.hash();
+        self.block_number = header.block_number;
+        self.transactions_root = header.transactions_root.clone();
+        self.validators = validators;
+        Ok(())
+    }
+
+    pub fn head_hash(&self) -> &Blake2bHash {
+        &self.head_hash
+    }
+
+    pub fn block_number(&self) -> u32 {
+        self.block_number
+    }
+
+    /// The merkle root over all transactions of the epoch finalized by the head.
+    pub fn transactions_root(&self) -> &Blake2bHash {
+        &self.transactions_root
+    }
+
+    /// The validators of the current epoch, which must sign the next macro header.
+    pub fn validators(&self) -> &Validators {
+        &self.validators
+    }
+}
@@ -0,0 +1,112 @@
+use std::iter::repeat;
+
+use beserial::{Deserialize, Serialize};
+use nimiq_block_albatross::{MacroHeader, PbftProofBuilder};
+use nimiq_bls::bls12_381::Signature;
+use nimiq_bls::bls12_381::lazy::LazyPublicKey;
+use nimiq_collections::compressed_list::CompressedList;
+use nimiq_hash::{Blake2bHash, Blake2bHasher, Hash, Hasher};
+use nimiq_keys::Address;
+use nimiq_macro_verifier::{MacroChainVerifier, VerifyError};
+use nimiq_macro_verifier::attestation::Attestation;
+use nimiq_primitives::coin::Coin;
+use nimiq_primitives::networks::NetworkId;
+use nimiq_primitives::policy;
+use nimiq_transaction::Transaction;
+use nimiq_utils::merkle::{self, MerkleProof};
+
+fn validators(count: usize) -> CompressedList<LazyPublicKey> {
+    let key_bytes = hex::decode("828aa810f80b9e200bb3310a3f837a8b1642e14440ec65ba8eaf801b9e1b81e69adc706b1ba6ed844cc793621dbd5e220ab235eac6d2b03c14e7a4da200759fee5f15903b9ef07602f7d50346fb25202f399affec1878cbfaa64cccdf0054cc6").unwrap();
+    let key = LazyPublicKey::deserialize(&mut &key_bytes[..]).unwrap();
+    repeat(key).take(count).collect()
+}
+
+fn macro_header(block_number: u32, parent_macro_hash: Blake2bHash, validators: CompressedList<LazyPublicKey>) -> MacroHeader {
+    let hash = Blake2bHasher::default().digest(&[]);
+    let signature_bytes = hex::decode("b9674ac1bbb4770ad291acc2b860e9120c609893a3840fbfdf2946911f00255f8995974c9b0ef3835ab4442ccbac9739").unwrap();
+    let signature = Signature::deserialize_from_vec(&signature_bytes).unwrap();
+
+    MacroHeader {
+        version: 1,
+        validators,
+        block_number,
+        view_number: 0,
+        parent_macro_hash,
+        seed: signature.compress(),
+        parent_hash: hash.clone(),
+        state_root: hash.clone(),
+        extrinsics_root: hash.clone(),
+        transactions_root: hash,
+        timestamp: 0,
+        extension: Vec::new(),
+    }
+}
+
+#[test]
+fn it_trusts_the_initial_header() {
+    let genesis = macro_header(0, Blake2bHash::default(), validators(policy::SLOTS as usize));
+    let verifier = MacroChainVerifier::new(&genesis).unwrap();
+    assert_eq!(verifier.block_number(), 0);
+    assert_eq!(verifier.head_hash(), &genesis.hash());
+    assert_eq!(verifier.validators().len(), policy::SLOTS as usize);
+}
+
+#[test]
+fn it_rejects_invalid_validator_lists() {
+    let genesis = macro_header(0, Blake2bHash::default(), validators(policy::SLOTS as usize - 1));
+    assert_eq!(MacroChainVerifier::new(&genesis).unwrap_err(), VerifyError::InvalidValidators);
+}
+
+#[test]
+fn it_rejects_headers_that_dont_extend_the_macro_chain() {
+    let genesis = macro_header(0, Blake2bHash::default(), validators(policy::SLOTS as usize));
+    let mut verifier = MacroChainVerifier::new(&genesis).unwrap();
+    let justification = PbftProofBuilder::new().build();
+
+    let header = macro_header(policy::EPOCH_LENGTH - 1, genesis.hash(), validators(policy::SLOTS as usize));
+    assert_eq!(verifier.push(&header, &justification).unwrap_err(), VerifyError::InvalidBlockNumber {
+        expected: policy::EPOCH_LENGTH,
+        actual: policy::EPOCH_LENGTH - 1,
+    });
+
+    let header = macro_header(policy::EPOCH_LENGTH, Blake2bHash::default(), validators(policy::SLOTS as usize));
+    assert_eq!(verifier.push(&header, &justification).unwrap_err(), VerifyError::InvalidParentMacroHash);
+
+    // Without any signatures, the justification can't reach the threshold.
+    let header = macro_header(policy::EPOCH_LENGTH, genesis.hash(), validators(policy::SLOTS as usize));
+    match verifier.push(&header, &justification) {
+        Err(VerifyError::InvalidJustification(_)) => {},
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    assert_eq!(verifier.block_number(), 0);
+}
+
+#[test]
+fn it_verifies_transaction_attestations() {
+    let transactions: Vec<Transaction> = (0..5u64)
+        .map(|i| Transaction::new_basic(Address::from([1u8; 20]), Address::from([2u8; 20]), Coin::from_u64_unchecked(1000 + i), Coin::ZERO, 1, NetworkId::UnitAlbatross))
+        .collect();
+    let hashes: Vec<Blake2bHash> = transactions.iter().map(|tx| tx.hash()).collect();
+
+    // Trust the macro block finalizing the transactions' epoch directly.
+    let mut checkpoint = macro_header(policy::EPOCH_LENGTH, Blake2bHash::default(), validators(policy::SLOTS as usize));
+    checkpoint.transactions_root = merkle::compute_root_from_hashes::<Blake2bHash>(&hashes);
+    let verifier = MacroChainVerifier::new(&checkpoint).unwrap();
+
+    let attestation = Attestation {
+        transaction: transactions[3].clone(),
+        block_number: 5,
+        macro_chain: vec![],
+        transactions_proof: MerkleProof::new(hashes.clone(), vec![hashes[3].clone()]),
+    };
+    let attestation = Attestation::deserialize_from_vec(&attestation.serialize_to_vec()).unwrap();
+    assert_eq!(attestation.verify(&verifier).unwrap().head_hash(), &checkpoint.hash());
+
+    let mut wrong_transaction = attestation.clone();
+    wrong_transaction.transaction = transactions[2].clone();
+    assert_eq!(wrong_transaction.verify(&verifier).unwrap_err(), VerifyError::InvalidTransactionsProof);
+
+    let mut wrong_epoch = attestation.clone();
+    wrong_epoch.block_number = policy::EPOCH_LENGTH + 1;
+    assert_eq!(wrong_epoch.verify(&verifier).unwrap_err(), VerifyError::InvalidTransactionsProof);
+}
@@ -1,3 +1,4 @@
+use beserial::Serialize;
 use collections::LimitHashSet;
 use nimiq_hash::Blake2bHash;
 use primitives::coin::Coin;
@@ -33,6 +34,16 @@ impl MempoolFilter {
         self.blacklist.contains(hash)
     }
 
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Replaces the rules. The blacklist is cleared, as it was built with the previous rules.
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+        self.blacklist.clear();
+    }
+
     pub fn accepts_transaction(&self, tx: &Transaction) -> bool {
          tx.fee >= self.rules.tx_fee &&
              tx.value >= self.rules.tx_value &&
@@ -47,6 +58,31 @@ impl MempoolFilter {
          )
     }
 
+    /// Returns the minimum fee that `tx` needs to pay to pass `accepts_transaction`, or `None` if
+    /// no fee can make it pass, e.g. because its value is too low.
+    pub fn min_fee(&self, tx: &Transaction) -> Option<Coin> {
+        if tx.value < self.rules.tx_value {
+            return None;
+        }
+
+        let size = tx.serialized_size();
+        let mut min_fee = self.rules.tx_fee
+            .max(Self::fee_for(self.rules.tx_fee_per_byte, size))
+            .max(self.rules.tx_value_total.checked_sub(tx.value).unwrap_or(Coin::ZERO));
+
+        if tx.flags.contains(TransactionFlags::CONTRACT_CREATION) && tx.value < self.rules.contract_value {
+            let contract_fee = self.rules.contract_fee.min(Self::fee_for(self.rules.contract_fee_per_byte, size));
+            min_fee = min_fee.max(contract_fee);
+        }
+
+        Some(min_fee)
+    }
+
+    /// Smallest fee that reaches `fee_per_byte` for a transaction of `size` bytes.
+    pub(crate) fn fee_for(fee_per_byte: f64, size: usize) -> Coin {
+        Coin::from_u64_unchecked((fee_per_byte * size as f64).ceil() as u64)
+    }
+
     pub fn accepts_recipient_balance(&self, tx: &Transaction, old_balance: Coin, new_balance: Coin) -> bool {
         new_balance >= self.rules.recipient_balance && (
             // XXX This does not precisely capture Account::is_initial() as it will always classify
@@ -10,20 +10,25 @@ extern crate nimiq_primitives as primitives;
 extern crate nimiq_transaction as transaction;
 extern crate nimiq_utils as utils;
 
+use std::cmp;
 use std::cmp::Ordering;
-use std::collections::{BTreeSet, HashMap, HashSet};
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::Range;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime};
 
-use parking_lot::{Mutex, RwLock, RwLockUpgradableReadGuard};
+use parking_lot::{Mutex, MutexGuard, RwLock, RwLockUpgradableReadGuard};
 
 use account::{Account, AccountTransactionInteraction};
 use beserial::Serialize;
 use block_base::Block;
 use blockchain_base::{AbstractBlockchain, BlockchainEvent};
+use collections::LimitHashSet;
 use hash::{Blake2bHash, Hash};
 use keys::Address;
+use primitives::coin::Coin;
 use transaction::{Transaction, TransactionFlags};
-use utils::observer::Notifier;
+use utils::observer::{Notifier, Subscription, weak_listener};
 
 use crate::filter::{MempoolFilter, Rules};
 use primitives::networks::NetworkId;
@@ -32,9 +37,19 @@ pub mod filter;
 
 pub struct Mempool<'env, B: AbstractBlockchain<'env> + 'env> {
     blockchain: Arc<B>,
+    block_transactions_size: usize,
+    size_limit: usize,
+    bytes_limit: usize,
+    sender_limit: usize,
+    orphan_limit: usize,
+    rebroadcast_blocks: u32,
+    replacement_fee_factor: f64,
+    transaction_ttl: Option<Duration>,
+    broadcast_strategy: BroadcastStrategy,
     pub notifier: RwLock<Notifier<'env, MempoolEvent>>,
     state: RwLock<MempoolState>,
     mut_lock: Mutex<()>,
+    subscription: Mutex<Option<Subscription<'env, BlockchainEvent<B::Block>>>>,
 }
 
 struct MempoolState {
@@ -43,6 +58,31 @@ struct MempoolState {
     transactions_by_recipient: HashMap<Address, BTreeSet<Arc<Transaction>>>,
     transactions_sorted_fee: BTreeSet<Arc<Transaction>>, // sorted by fee, ascending
     filter: MempoolFilter,
+    /// Hashes of the transactions that were submitted locally, e.g. via RPC.
+    local_transactions: HashSet<Blake2bHash>,
+    /// When the transactions were added to the mempool.
+    added_at: HashMap<Blake2bHash, Instant>,
+    /// When the local transactions are rebroadcast next, see `stale_local_transactions`.
+    rebroadcasts: HashMap<Blake2bHash, Rebroadcast>,
+    /// Lowest fee per byte that was paid to get into each of the recent blocks by their hash,
+    /// oldest first. It's 0 for blocks that weren't full.
+    fee_history: VecDeque<(Blake2bHash, f64)>,
+    /// Transactions that aren't valid yet, sorted by fee, ascending. They are not part of the
+    /// mempool until `promote_orphans` moves them into it.
+    orphans: BTreeSet<Arc<Transaction>>,
+    orphans_by_hash: HashMap<Blake2bHash, Arc<Transaction>>,
+    /// Hashes of the transactions that `evict_expired` evicted because of the `transaction_ttl`
+    /// while they were still valid, such that peers can't relay them again.
+    expired: LimitHashSet<Blake2bHash>,
+    /// Serialized size of all transactions in bytes.
+    size: usize,
+}
+
+/// Block height at which a local transaction is relayed again, and the number of blocks between
+/// it and the previous relay.
+struct Rebroadcast {
+    block_height: u32,
+    interval: u32,
 }
 
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
@@ -51,27 +91,79 @@ pub enum MempoolEvent {
     TransactionRestored(Arc<Transaction>),
     TransactionMined(Arc<Transaction>),
     TransactionEvicted(Arc<Transaction>),
+    /// The first transaction was replaced by the second one, which pays a higher fee. The
+    /// replacement is announced with `TransactionAdded` as well.
+    TransactionReplaced(Arc<Transaction>, Arc<Transaction>),
 }
 
 #[derive(Debug, Clone)]
 pub struct MempoolConfig {
     pub filter_rules: Rules,
     pub filter_limit: usize,
+    /// Serialized size of the transactions that fit into a block. Used to estimate when a
+    /// transaction will be included.
+    pub block_transactions_size: usize,
+    /// Maximum number of transactions in the mempool.
+    pub size_limit: usize,
+    /// Maximum serialized size of all transactions in the mempool in bytes. If the mempool is
+    /// full, the transactions paying the lowest fee per byte are evicted.
+    pub bytes_limit: usize,
+    /// Maximum number of transactions of a single sender in the mempool. If a sender exceeds it,
+    /// its transactions paying the lowest fee per byte are evicted. Must be at least 1.
+    pub sender_limit: usize,
+    /// Maximum number of transactions in the orphan pool, see `ReturnCode::Orphan`. If the pool
+    /// is full, the orphans paying the lowest fee per byte are dropped. 0 disables the pool.
+    pub orphan_limit: usize,
+    /// Whether accepted transactions are relayed to peers.
+    pub relay_transactions: bool,
+    /// How transactions that were submitted locally are relayed to peers.
+    pub broadcast_strategy: BroadcastStrategy,
+    /// Number of blocks after which local transactions that weren't mined are relayed again. The
+    /// number doubles after each rebroadcast. 0 disables rebroadcasts.
+    pub rebroadcast_blocks: u32,
+    /// Factor by which a transaction must pay more fee than the transaction it replaces, see
+    /// `Mempool::push_transaction`. Must be at least 1.
+    pub replacement_fee_factor: f64,
+    /// Transactions that weren't included for this long are evicted by `evict_expired`, even if
+    /// their validity window didn't end yet.
+    pub transaction_ttl: Option<Duration>,
 }
 
 impl Default for MempoolConfig {
     fn default() -> MempoolConfig {
         MempoolConfig {
             filter_rules: Rules::default(),
-            filter_limit: MempoolFilter::DEFAULT_BLACKLIST_SIZE
+            filter_limit: MempoolFilter::DEFAULT_BLACKLIST_SIZE,
+            block_transactions_size: BLOCK_TRANSACTIONS_SIZE,
+            size_limit: SIZE_MAX,
+            bytes_limit: SIZE_BYTES_MAX,
+            sender_limit: TRANSACTIONS_PER_SENDER_MAX,
+            orphan_limit: ORPHANS_MAX,
+            relay_transactions: true,
+            broadcast_strategy: BroadcastStrategy::Flood,
+            rebroadcast_blocks: REBROADCAST_BLOCKS,
+            replacement_fee_factor: REPLACEMENT_FEE_FACTOR,
+            transaction_ttl: None,
         }
     }
 }
 
 impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
+    /// Panics if `config.sender_limit` is 0.
     pub fn new(blockchain: Arc<B>, config: MempoolConfig) -> Arc<Self> {
+        assert!(config.sender_limit >= 1, "The sender limit of the mempool must be at least 1");
+
         let arc = Arc::new(Self {
             blockchain: blockchain.clone(),
+            block_transactions_size: config.block_transactions_size,
+            size_limit: config.size_limit,
+            bytes_limit: config.bytes_limit,
+            sender_limit: config.sender_limit,
+            orphan_limit: config.orphan_limit,
+            rebroadcast_blocks: config.rebroadcast_blocks,
+            replacement_fee_factor: config.replacement_fee_factor,
+            transaction_ttl: config.transaction_ttl,
+            broadcast_strategy: config.broadcast_strategy,
             notifier: RwLock::new(Notifier::new()),
             state: RwLock::new(MempoolState {
                 transactions_by_hash: HashMap::new(),
@@ -79,12 +171,23 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
                 transactions_by_recipient: HashMap::new(),
                 transactions_sorted_fee: BTreeSet::new(),
                 filter: MempoolFilter::new(config.filter_rules, config.filter_limit),
+                local_transactions: HashSet::new(),
+                added_at: HashMap::new(),
+                rebroadcasts: HashMap::new(),
+                fee_history: VecDeque::with_capacity(FEE_HISTORY_BLOCKS),
+                orphans: BTreeSet::new(),
+                orphans_by_hash: HashMap::new(),
+                expired: LimitHashSet::new(EXPIRED_TRANSACTIONS_MAX),
+                size: 0,
             }),
             mut_lock: Mutex::new(()),
+            subscription: Mutex::new(None),
         });
 
-        let arc_self = arc.clone();
-        blockchain.register_listener(move |event: &BlockchainEvent<B::Block>| arc_self.on_blockchain_event(event));
+        let weak: Weak<Self> = Arc::downgrade(&arc);
+        let subscription = blockchain.subscribe_listener(weak_listener(weak,
+            |this, event: &BlockchainEvent<B::Block>| this.on_blockchain_event(event)));
+        *arc.subscription.lock() = Some(subscription);
         arc
     }
 
@@ -92,17 +195,77 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
         self.state.read().filter.blacklisted(hash)
     }
 
-    pub fn push_transaction(&self, mut transaction: Transaction) -> ReturnCode {
-        let hash: Blake2bHash = transaction.hash();
+    pub fn filter_rules(&self) -> Rules {
+        self.state.read().filter.rules().clone()
+    }
+
+    /// Replaces the filter rules at runtime. Transactions in the mempool that don't pass the fee
+    /// and value rules anymore are evicted, and filtered transactions may be pushed again.
+    /// Returns the number of evicted transactions.
+    pub fn set_filter_rules(&self, rules: Rules) -> usize {
+        // Only one mutating operation at a time.
+        let _lock = self.mut_lock.lock();
+
+        let txs_filtered: Vec<Arc<Transaction>> = {
+            let mut state = self.state.write();
+            state.filter.set_rules(rules);
+            let txs_filtered: Vec<Arc<Transaction>> = state.transactions_by_hash.values()
+                .filter(|tx| !state.filter.accepts_transaction(tx))
+                .cloned()
+                .collect();
+            for tx in txs_filtered.iter() {
+                Self::remove_transaction(&mut state, tx);
+            }
+            txs_filtered
+        };
+
+        let num_filtered = txs_filtered.len();
+        for tx in txs_filtered {
+            trace!("Transaction was filtered: {:?}", tx);
+            self.notifier.read().notify(MempoolEvent::TransactionEvicted(tx));
+        }
+        num_filtered
+    }
+
+    /// Pushes a transaction into the mempool.
+    ///
+    /// A transaction that only differs in its fee from one of the transactions of its sender in
+    /// the mempool replaces it, if the sender can't afford both of them and it pays at least
+    /// `replacement_fee_factor` times the fee. Otherwise it's rejected with `FeeTooLow`. If the
+    /// sender can afford both, they are both valid and kept, since there are no nonces.
+    ///
+    /// A transaction whose validity window starts within the next validity window is kept in the
+    /// orphan pool and pushed again once the chain caught up, if its sender account exists and
+    /// can afford it together with the sender's other orphans.
+    pub fn push_transaction(&self, transaction: Transaction) -> ReturnCode {
+        self.push(transaction, false)
+    }
 
+    /// Same as `push_transaction`, but marks the transaction as submitted locally (e.g. via RPC),
+    /// such that block producers can include it with priority. A known transaction is marked as
+    /// well.
+    pub fn push_local_transaction(&self, transaction: Transaction) -> ReturnCode {
+        self.push(transaction, true)
+    }
+
+    fn push(&self, transaction: Transaction, local: bool) -> ReturnCode {
         // Synchronize with `Blockchain::push`
-        let _push_lock = self.blockchain.lock();
+        let push_lock = self.blockchain.lock();
+        self.push_locked(transaction, local, Some(push_lock))
+    }
+
+    /// Pushes a transaction while `Blockchain::push` is synchronized by the caller. The lock, if
+    /// given, is released before the listeners are notified.
+    fn push_locked(&self, mut transaction: Transaction, local: bool, push_lock: Option<MutexGuard<()>>) -> ReturnCode {
+        let hash: Blake2bHash = transaction.hash();
 
         // Only one mutating operation at a time.
         let _lock = self.mut_lock.lock();
 
         // Transactions that are invalidated by the new transaction are stored here.
         let mut txs_to_remove = Vec::new();
+        // The transaction that is replaced by the new transaction.
+        let replaced;
 
         {
             let state = self.state.upgradable_read();
@@ -117,16 +280,39 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
 
             // Check if we already know this transaction.
             if state.transactions_by_hash.contains_key(&hash) {
+                if local && !state.local_transactions.contains(&hash) {
+                    RwLockUpgradableReadGuard::upgrade(state).local_transactions.insert(hash);
+                }
                 return ReturnCode::Known;
             };
+            if state.orphans_by_hash.contains_key(&hash) {
+                if local && !state.local_transactions.contains(&hash) {
+                    RwLockUpgradableReadGuard::upgrade(state).local_transactions.insert(hash);
+                }
+                return ReturnCode::Known;
+            }
+
+            // A transaction that expired is only accepted again if it's submitted locally.
+            if !local && state.expired.contains(&hash) {
+                return ReturnCode::Known;
+            }
 
             // Intrinsic transaction verification.
             if transaction.verify_mut(self.blockchain.network_id()).is_err() {
                 return ReturnCode::Invalid;
             }
 
-            // Check limit for free transactions.
+            // Check if the transaction replaces one of the sender's transactions.
             let txs_by_sender_opt = state.transactions_by_sender.get(&transaction.sender);
+            replaced = txs_by_sender_opt
+                .and_then(|transactions| self.replaced_transaction(transactions, &transaction));
+            if let Some(ref replaced) = replaced {
+                if transaction.fee < self.replacement_fee(replaced) {
+                    return ReturnCode::FeeTooLow;
+                }
+            }
+
+            // Check limit for free transactions.
             if transaction.fee_per_byte() < TRANSACTION_RELAY_FEE_MIN {
                 let mut num_free_tx = 0;
                 if let Some(transactions) = txs_by_sender_opt {
@@ -144,12 +330,37 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
                 }
             }
 
-            // Check if transaction is valid at the next block height.
+            // Reject the transaction if the mempool is full and it doesn't pay more than the
+            // transactions it would evict. A replacement only grows the mempool by the difference
+            // in size.
+            let count_to_evict = if replaced.is_none() && state.transactions_sorted_fee.len() >= self.size_limit { 1 } else { 0 };
+            let replaced_size = replaced.as_ref().map_or(0, |tx| tx.serialized_size());
+            let bytes_to_evict = (state.size + transaction.serialized_size())
+                .saturating_sub(replaced_size)
+                .saturating_sub(self.bytes_limit);
+            if !Self::outbids(&state, &transaction, replaced.as_ref(), count_to_evict, bytes_to_evict) {
+                return ReturnCode::FeeTooLow;
+            }
+
+            // Check if transaction is valid at the next block height. If it will be valid soon,
+            // keep it until then.
             let block_height = self.blockchain.head_height() + 1;
+            if transaction.validity_start_height > block_height
+                && transaction.validity_start_height - block_height < self.validity_window_length() {
+                let sender_account = self.blockchain.get_account(&transaction.sender);
+                let mut state = RwLockUpgradableReadGuard::upgrade(state);
+                return self.add_orphan(&mut state, hash, transaction, &sender_account, local);
+            }
             if !transaction.is_valid_at(block_height) {
                 return ReturnCode::Invalid;
             }
 
+            // Reject the transaction if it would likely expire before the transactions paying
+            // more have been included.
+            if self.estimated_inclusion_height(&state, &transaction, block_height) >= transaction.validity_end_height() {
+                return ReturnCode::Expiring;
+            }
+
             // Check if transaction has already been mined.
             if self.blockchain.contains_tx_in_validity_window(&hash) {
                 return ReturnCode::Invalid;
@@ -205,6 +416,11 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
             // These are not affected by the new transaction and should never fail to apply.
             let mut tx_opt = tx_iter.next_back();
             while let Some(tx) = tx_opt {
+                // The replaced transaction won't be applied.
+                if replaced.as_ref() == Some(tx) {
+                    tx_opt = tx_iter.next_back();
+                    continue;
+                }
                 // Break on the first transaction with a lower fee/byte.
                 if transaction.cmp(tx) == Ordering::Greater {
                     break;
@@ -218,7 +434,7 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
             }
 
             // If we are already at the transaction limit, reject the new transaction.
-            if tx_count >= TRANSACTIONS_PER_SENDER_MAX {
+            if tx_count >= self.sender_limit {
                 return ReturnCode::FeeTooLow;
             }
 
@@ -239,7 +455,11 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
             // Finally, check the remaining transactions with lower fee/byte and evict them if necessary.
             // tx_opt already contains the first lower/fee byte transaction to check (if there is one remaining).
             while let Some(tx) = tx_opt {
-                if tx_count < TRANSACTIONS_PER_SENDER_MAX {
+                if replaced.as_ref() == Some(tx) {
+                    tx_opt = tx_iter.next_back();
+                    continue;
+                }
+                if tx_count < self.sender_limit {
                     if sender_account.commit_outgoing_transaction(tx, block_height).is_ok() {
                         tx_count += 1;
                     } else {
@@ -260,6 +480,17 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
             let mut state = self.state.write();
             Self::add_transaction(&mut state, hash.clone(), tx_arc.clone());
 
+            // Remove the replaced transaction. Its replacement stays local, if it was.
+            let mut local = local;
+            if let Some(ref replaced) = replaced {
+                local |= state.local_transactions.contains(&replaced.hash::<Blake2bHash>());
+                Self::remove_transaction(&mut state, replaced);
+            }
+            if local {
+                state.local_transactions.insert(hash.clone());
+                state.expired.remove(&hash);
+            }
+
             // Evict transactions that were invalidated by the new transaction.
             for tx in txs_to_remove.iter() {
                 Self::remove_transaction(&mut *state, tx);
@@ -268,19 +499,20 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
             // Rename variable.
             removed_transactions = txs_to_remove;
 
-            // Remove the lowest fee transaction if mempool max size is reached.
-            if state.transactions_sorted_fee.len() > SIZE_MAX {
-                let tx = state.transactions_sorted_fee.iter().next().unwrap().clone();
-                Self::remove_transaction(&mut state, &tx);
-                removed_transactions.push(tx);
-            }
+            // Remove the lowest fee transactions if mempool max size is reached.
+            removed_transactions.extend(self.evict_overflow(&mut state));
         }
 
         // Drop the lock on blockchain::push
-        drop(_push_lock);
+        drop(push_lock);
 
         // Tell listeners about the new transaction we received.
-        self.notifier.read().notify(MempoolEvent::TransactionAdded(hash, tx_arc));
+        self.notifier.read().notify(MempoolEvent::TransactionAdded(hash, Arc::clone(&tx_arc)));
+
+        // Tell listeners about the transaction it replaced.
+        if let Some(replaced) = replaced {
+            self.notifier.read().notify(MempoolEvent::TransactionReplaced(replaced, tx_arc));
+        }
 
         // Tell listeners about the transactions we evicted.
         for tx in removed_transactions {
@@ -290,10 +522,147 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
         ReturnCode::Accepted
     }
 
+    /// Explains why `transaction` was (or would be) rejected because of its fee. Returns `None` if
+    /// the fee of `transaction` is not the reason for a rejection.
+    ///
+    /// This should be called after `push_transaction` returned `FeeTooLow` or `Filtered`.
+    pub fn fee_feedback(&self, transaction: &Transaction) -> Option<FeeFeedback> {
+        let state = self.state.read();
+        let size = transaction.serialized_size();
+
+        let mut feedback: Option<FeeFeedback> = None;
+        let mut require = |reason: FeeRejectionReason, min_fee: Coin| {
+            let is_higher = feedback.as_ref().map(|f| min_fee > f.min_fee).unwrap_or(true);
+            if min_fee > transaction.fee && is_higher {
+                feedback = Some(FeeFeedback { reason, min_fee, size });
+            }
+        };
+
+        if let Some(min_fee) = state.filter.min_fee(transaction) {
+            require(FeeRejectionReason::FilterRules, min_fee);
+        }
+
+        if let Some(transactions) = state.transactions_by_sender.get(&transaction.sender) {
+            if let Some(tx) = self.replaced_transaction(transactions, transaction) {
+                require(FeeRejectionReason::Replacement, self.replacement_fee(&tx));
+            }
+
+            let num_free_tx = transactions.iter()
+                .filter(|tx| tx.fee_per_byte() < TRANSACTION_RELAY_FEE_MIN)
+                .count();
+            if num_free_tx >= FREE_TRANSACTIONS_PER_SENDER_MAX as usize {
+                require(FeeRejectionReason::FreeTransactionLimit, MempoolFilter::fee_for(TRANSACTION_RELAY_FEE_MIN, size));
+            }
+
+            // The new transaction must beat the cheapest of the sender's transactions that would be kept.
+            if let Some(tx) = transactions.iter().rev().nth(self.sender_limit - 1) {
+                require(FeeRejectionReason::SenderTransactionLimit, fee_above(tx.fee_per_byte(), size));
+            }
+        }
+
+        // The new transaction must beat the most expensive of the transactions it would evict.
+        let count_to_evict = if state.transactions_sorted_fee.len() >= self.size_limit { 1 } else { 0 };
+        let bytes_to_evict = (state.size + size).saturating_sub(self.bytes_limit);
+        if count_to_evict > 0 || bytes_to_evict > 0 {
+            let mut freed_count = 0;
+            let mut freed_bytes = 0;
+            for tx in state.transactions_sorted_fee.iter() {
+                freed_count += 1;
+                freed_bytes += tx.serialized_size();
+                if freed_count >= count_to_evict && freed_bytes >= bytes_to_evict {
+                    require(FeeRejectionReason::MempoolFull, fee_above(tx.fee_per_byte(), size));
+                    break;
+                }
+            }
+        }
+
+        feedback
+    }
+
+    /// Explains why `transaction` was (or would be) rejected because it would expire before it is
+    /// likely included. Returns `None` if the transaction would likely be included in time.
+    ///
+    /// This should be called after `push_transaction` returned `Expiring`.
+    pub fn validity_feedback(&self, transaction: &Transaction) -> Option<ValidityFeedback> {
+        let state = self.state.read();
+        let block_height = self.blockchain.head_height() + 1;
+        let estimated_inclusion_height = self.estimated_inclusion_height(&state, transaction, block_height);
+        let validity_end_height = transaction.validity_end_height();
+        if estimated_inclusion_height < validity_end_height {
+            return None;
+        }
+
+        // A transaction starting at the next block height has the longest possible validity.
+        let suggested_validity_start_height = if estimated_inclusion_height < block_height.saturating_add(self.validity_window_length()) {
+            Some(block_height)
+        } else {
+            None
+        };
+
+        Some(ValidityFeedback {
+            validity_end_height,
+            estimated_inclusion_height,
+            suggested_validity_start_height,
+        })
+    }
+
+    /// The transaction of `transactions` of the same sender that `transaction` would replace.
+    /// That's one that only differs in its fee, if the sender can't afford it and the other
+    /// transactions together with `transaction`. Otherwise all of them can be applied.
+    fn replaced_transaction(&self, transactions: &BTreeSet<Arc<Transaction>>, transaction: &Transaction) -> Option<Arc<Transaction>> {
+        let replaced = transactions.iter().find(|tx| is_replaced_by(tx, transaction))?;
+
+        let block_height = self.blockchain.head_height() + 1;
+        let mut sender_account = self.blockchain.get_account(&transaction.sender);
+        let affords_all = transactions.iter()
+            .all(|tx| sender_account.commit_outgoing_transaction(tx, block_height).is_ok())
+            && sender_account.commit_outgoing_transaction(transaction, block_height).is_ok();
+        if affords_all {
+            None
+        } else {
+            Some(Arc::clone(replaced))
+        }
+    }
+
+    /// Minimum fee a transaction must pay to replace `transaction`, i.e. its fee times the
+    /// replacement fee factor (rounded), but at least one Luna more.
+    pub fn replacement_fee(&self, transaction: &Transaction) -> Coin {
+        let fee = u64::from(transaction.fee);
+        let bumped_fee = (fee as f64 * self.replacement_fee_factor).round() as u64;
+        Coin::from_u64_unchecked(cmp::max(bumped_fee, fee + 1))
+    }
+
+    /// Number of blocks a transaction is valid.
+    pub fn validity_window_length(&self) -> u32 {
+        self.blockchain.transaction_validity_window()
+    }
+
+    /// The range of validity start heights a transaction must have to be valid in the next
+    /// block.
+    pub fn validity_window(&self) -> Range<u32> {
+        let block_height = self.blockchain.head_height() + 1;
+        block_height.saturating_sub(self.validity_window_length() - 1)..block_height + 1
+    }
+
     pub fn contains(&self, hash: &Blake2bHash) -> bool {
         self.state.read().transactions_by_hash.contains_key(hash)
     }
 
+    /// Whether the transaction is in the orphan pool, i.e. was accepted, but isn't valid yet.
+    pub fn is_orphan(&self, hash: &Blake2bHash) -> bool {
+        self.state.read().orphans_by_hash.contains_key(hash)
+    }
+
+    /// Whether the transaction was submitted locally, see `push_local_transaction`.
+    pub fn is_local(&self, hash: &Blake2bHash) -> bool {
+        self.state.read().local_transactions.contains(hash)
+    }
+
+    /// Number of transactions in the orphan pool.
+    pub fn orphan_count(&self) -> usize {
+        self.state.read().orphans.len()
+    }
+
     pub fn get_transaction(&self, hash: &Blake2bHash) -> Option<Arc<Transaction>> {
         self.state.read().transactions_by_hash.get(hash).cloned()
     }
@@ -306,12 +675,86 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
             .collect()
     }
 
+    /// How transactions that were submitted locally are relayed to peers.
+    pub fn broadcast_strategy(&self) -> BroadcastStrategy {
+        self.broadcast_strategy
+    }
+
+    /// Removes the transactions that mustn't be announced to peers that request our mempool, i.e.
+    /// the local ones unless they are flooded. Otherwise any peer could learn them regardless of
+    /// the broadcast strategy.
+    pub fn retain_announceable(&self, transactions: &mut Vec<Arc<Transaction>>) {
+        if self.broadcast_strategy == BroadcastStrategy::Flood {
+            return;
+        }
+        let state = self.state.read();
+        if !state.local_transactions.is_empty() {
+            transactions.retain(|tx| !state.local_transactions.contains(&tx.hash::<Blake2bHash>()));
+        }
+    }
+
+    /// The transactions that were submitted locally, in the same order as `get_transactions`.
+    pub fn get_local_transactions(&self) -> Vec<Arc<Transaction>> {
+        let state = self.state.read();
+        if state.local_transactions.is_empty() {
+            return Vec::new();
+        }
+        state.transactions_sorted_fee.iter()
+            .filter(|tx| state.local_transactions.contains(&tx.hash::<Blake2bHash>()))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the local transactions that weren't mined within `rebroadcast_blocks` blocks of
+    /// being relayed, so that they can be relayed again. Each of them is due again after twice as
+    /// many blocks as before. Transactions are first scheduled when this is called after they
+    /// were added, so it should be called for every block.
+    pub fn stale_local_transactions(&self, block_height: u32) -> Vec<Arc<Transaction>> {
+        if self.rebroadcast_blocks == 0 {
+            return Vec::new();
+        }
+
+        let mut state = self.state.write();
+        if state.local_transactions.is_empty() {
+            return Vec::new();
+        }
+
+        let mut local: Vec<(Blake2bHash, Arc<Transaction>)> = state.local_transactions.iter()
+            .filter_map(|hash| state.transactions_by_hash.get(hash)
+                .map(|tx| (hash.clone(), Arc::clone(tx))))
+            .collect();
+        // Highest fee first, like the rest of the mempool.
+        local.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        let mut stale = Vec::new();
+        for (hash, tx) in local {
+            let rebroadcast_blocks = self.rebroadcast_blocks;
+            let rebroadcast = state.rebroadcasts.entry(hash).or_insert_with(|| Rebroadcast {
+                block_height: block_height.saturating_add(rebroadcast_blocks),
+                interval: rebroadcast_blocks,
+            });
+            if block_height >= rebroadcast.block_height {
+                rebroadcast.interval = rebroadcast.interval.saturating_mul(2);
+                rebroadcast.block_height = block_height.saturating_add(rebroadcast.interval);
+                stale.push(tx);
+            }
+        }
+        stale
+    }
+
     pub fn get_transactions_for_block(&self, max_size: usize) -> Vec<Transaction> {
+        self.get_filtered_transactions_for_block(max_size, |_| true)
+    }
+
+    /// Like `get_transactions_for_block`, but skips the transactions for which `filter` returns
+    /// `false`. Only the selected transactions are cloned.
+    pub fn get_filtered_transactions_for_block<F>(&self, max_size: usize, filter: F) -> Vec<Transaction>
+        where F: Fn(&Transaction) -> bool {
         let mut txs = Vec::new();
         let mut size = 0;
 
         let state = self.state.read();
-        for tx in state.transactions_sorted_fee.iter() {
+        for tx in state.transactions_sorted_fee.iter().filter(|tx| filter(tx)) {
             let tx_size = tx.serialized_size();
             if size + tx_size <= max_size {
                 txs.push(Transaction::clone(tx));
@@ -348,6 +791,182 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
         txs
     }
 
+    /// Returns up to `limit` transactions of the mempool in `order`, skipping the first `offset`
+    /// ones, together with their metadata. All transactions are taken from the same state of the
+    /// mempool, but the lock is only held while they are collected, not while they are sorted.
+    pub fn snapshot(&self, offset: usize, limit: usize, order: SnapshotOrder) -> MempoolSnapshot {
+        let (total, mut entries) = {
+            let state = self.state.read();
+            let total = state.transactions_sorted_fee.len();
+            let entry = |hash: Blake2bHash, tx: &Arc<Transaction>| {
+                let added_at = state.added_at.get(&hash).cloned();
+                let local = state.local_transactions.contains(&hash);
+                (hash, Arc::clone(tx), added_at, local)
+            };
+            let entries: Vec<_> = match order {
+                SnapshotOrder::HighestFeeFirst => state.transactions_sorted_fee.iter().rev()
+                    .skip(offset)
+                    .take(limit)
+                    .map(|tx| entry(tx.hash(), tx))
+                    .collect(),
+                SnapshotOrder::LowestFeeFirst => state.transactions_sorted_fee.iter()
+                    .skip(offset)
+                    .take(limit)
+                    .map(|tx| entry(tx.hash(), tx))
+                    .collect(),
+                // The page is picked after sorting by time, outside of the lock.
+                SnapshotOrder::OldestFirst | SnapshotOrder::NewestFirst => state.transactions_by_hash.iter()
+                    .map(|(hash, tx)| entry(hash.clone(), tx))
+                    .collect(),
+            };
+            (total, entries)
+        };
+
+        if order == SnapshotOrder::OldestFirst || order == SnapshotOrder::NewestFirst {
+            entries.sort_by_key(|(_, _, added_at, _)| *added_at);
+            if order == SnapshotOrder::NewestFirst {
+                entries.reverse();
+            }
+            entries = entries.into_iter().skip(offset).take(limit).collect();
+        }
+
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let entries = entries.into_iter()
+            .map(|(hash, transaction, added_at, local)| MempoolEntry {
+                hash,
+                fee_per_byte: transaction.fee_per_byte(),
+                size: transaction.serialized_size(),
+                received_at: added_at.map_or(system_now, |added_at| system_now - now.duration_since(added_at)),
+                local,
+                transaction,
+            })
+            .collect();
+
+        MempoolSnapshot { total, entries }
+    }
+
+    /// Serialized size of all transactions in the mempool in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.state.read().size
+    }
+
+    /// Evicts the transactions with the lowest fee per byte until at least `bytes` have been
+    /// freed or the mempool is empty. Returns the number of bytes freed.
+    pub fn evict_bytes(&self, bytes: usize) -> usize {
+        // Only one mutating operation at a time.
+        let _lock = self.mut_lock.lock();
+
+        let mut freed = 0;
+        let mut txs_evicted = Vec::new();
+        {
+            let mut state = self.state.write();
+            while freed < bytes {
+                let tx = match state.transactions_sorted_fee.iter().next() {
+                    Some(tx) => tx.clone(),
+                    None => break,
+                };
+                freed += tx.serialized_size();
+                Self::remove_transaction(&mut state, &tx);
+                txs_evicted.push(tx);
+            }
+        }
+
+        // Notify listeners.
+        for tx in txs_evicted {
+            trace!("Transaction evicted: {:?}", tx);
+            self.notifier.read().notify(MempoolEvent::TransactionEvicted(tx));
+        }
+
+        freed
+    }
+
+    /// Evicts the transactions whose validity window ended before the next block, and those that
+    /// are in the mempool for longer than the `transaction_ttl`. Unlike the eviction after each
+    /// block, the accounts aren't checked, so this is cheap enough to run periodically. Returns
+    /// the number of evicted transactions.
+    ///
+    /// Transactions that are evicted because of the `transaction_ttl` are only accepted again if
+    /// they are submitted locally.
+    pub fn evict_expired(&self) -> usize {
+        self.evict_expired_at(self.blockchain.head_height() + 1)
+    }
+
+    /// Same as `evict_expired`, but evicts the transactions that aren't valid at `block_height`.
+    pub fn evict_expired_at(&self, block_height: u32) -> usize {
+        // Only one mutating operation at a time.
+        let _lock = self.mut_lock.lock();
+
+        let now = Instant::now();
+        let txs_expired: Vec<Arc<Transaction>> = {
+            let state = self.state.read();
+            state.transactions_by_hash.iter()
+                .filter(|(hash, tx)| {
+                    !tx.is_valid_at(block_height) || self.transaction_ttl.map_or(false, |ttl| {
+                        state.added_at.get(*hash).map_or(false, |added_at| now.duration_since(*added_at) >= ttl)
+                    })
+                })
+                .map(|(_, tx)| Arc::clone(tx))
+                .collect()
+        };
+
+        if !txs_expired.is_empty() {
+            let mut state = self.state.write();
+            for tx in txs_expired.iter() {
+                Self::remove_transaction(&mut state, tx);
+                if tx.is_valid_at(block_height) {
+                    state.expired.insert(tx.hash());
+                }
+            }
+        }
+
+        let num_expired = txs_expired.len();
+        for tx in txs_expired {
+            trace!("Transaction expired: {:?}", tx);
+            self.notifier.read().notify(MempoolEvent::TransactionEvicted(tx));
+        }
+        num_expired
+    }
+
+    /// Estimates the fee per byte that a transaction must exceed to be included within the next
+    /// `target_blocks` blocks. This is the higher of two estimates:
+    ///
+    /// * The fee per byte of the transaction at the end of the next `target_blocks` blocks, if the
+    ///   current mempool contents would fill them.
+    /// * The lowest fee per byte that was paid to get into recent blocks, picked such that a
+    ///   transaction paying more would have been included within `target_blocks` blocks with a
+    ///   probability of `FEE_ESTIMATE_CONFIDENCE`.
+    pub fn estimate_fee_per_byte(&self, target_blocks: u32) -> f64 {
+        let target_blocks = cmp::max(target_blocks, 1);
+        let state = self.state.read();
+
+        let target_size = self.block_transactions_size.saturating_mul(target_blocks as usize);
+        let mut size = 0;
+        let mut mempool_estimate = 0f64;
+        for tx in state.transactions_sorted_fee.iter().rev() {
+            size += tx.serialized_size();
+            if size >= target_size {
+                mempool_estimate = tx.fee_per_byte();
+                break;
+            }
+        }
+
+        let mut history_estimate = 0f64;
+        if !state.fee_history.is_empty() {
+            let mut fees: Vec<f64> = state.fee_history.iter().map(|(_, fee)| *fee).collect();
+            fees.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+            // A transaction is missing a block with the probability of the share of blocks it
+            // didn't outbid, so it must outbid this share of blocks to miss all of them with a
+            // probability of at most `1 - FEE_ESTIMATE_CONFIDENCE`.
+            let share = 1f64 - (1f64 - FEE_ESTIMATE_CONFIDENCE).powf(1f64 / f64::from(target_blocks));
+            let index = (share * fees.len() as f64).ceil() as usize;
+            history_estimate = fees[cmp::min(index.saturating_sub(1), fees.len() - 1)];
+        }
+
+        mempool_estimate.max(history_estimate)
+    }
+
     pub fn current_height(&self) -> u32 {
         self.blockchain.head_height()
     }
@@ -356,18 +975,85 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
         self.blockchain.network_id()
     }
 
+    /// Estimates the block height at which `transaction` would be included, assuming that all
+    /// transactions paying more per byte are included first.
+    fn estimated_inclusion_height(&self, state: &MempoolState, transaction: &Transaction, block_height: u32) -> u32 {
+        // Only count the transactions ahead if they could fill the remaining validity window.
+        let max_blocks_ahead = (state.size / self.block_transactions_size) as u32;
+        if block_height.saturating_add(max_blocks_ahead) < transaction.validity_end_height() {
+            return block_height;
+        }
+
+        let size_ahead: usize = state.transactions_sorted_fee.iter().rev()
+            .take_while(|tx| transaction.cmp(tx) != Ordering::Greater)
+            .map(|tx| tx.serialized_size())
+            .sum();
+        block_height.saturating_add((size_ahead / self.block_transactions_size) as u32)
+    }
+
     fn on_blockchain_event(&self, event: &BlockchainEvent<B::Block>) {
         match event {
-            BlockchainEvent::Extended(_) | BlockchainEvent::Finalized(_) => {
-                self.evict_transactions()
+            BlockchainEvent::Extended(hash) | BlockchainEvent::Finalized(hash) => {
+                if let Some(block) = self.blockchain.get_block(hash, true) {
+                    self.record_fees(hash, &block);
+                }
+                self.evict_transactions();
+                self.promote_orphans();
             },
-            BlockchainEvent::Rebranched(reverted_blocks, _) => {
+            BlockchainEvent::Rebranched(reverted_blocks, adopted_blocks) => {
+                self.revert_fees(reverted_blocks);
+                for (hash, block) in adopted_blocks {
+                    self.record_fees(hash, block);
+                }
                 self.restore_transactions(reverted_blocks);
                 self.evict_transactions();
+                self.promote_orphans();
             },
         }
     }
 
+    /// Adds the lowest fee per byte that was paid to get into `block` to the fee history.
+    fn record_fees(&self, hash: &Blake2bHash, block: &B::Block) {
+        // Blocks without a body, e.g. macro blocks, say nothing about the fees.
+        let transactions = match block.transactions() {
+            Some(transactions) => transactions,
+            None => return,
+        };
+
+        let mut state = self.state.write();
+
+        // If the block had room left, any fee would have been enough.
+        let size: usize = transactions.iter().map(|tx| tx.serialized_size()).sum();
+        let is_full = !transactions.is_empty()
+            && size as f64 >= self.block_transactions_size as f64 * FULL_BLOCK_FACTOR;
+        let min_fee_per_byte = if is_full {
+            // Free transactions and our local transactions can be included with priority, so they
+            // didn't outbid anything.
+            let min_fee_per_byte = transactions.iter()
+                .filter(|tx| tx.fee != Coin::ZERO && !state.local_transactions.contains(&tx.hash::<Blake2bHash>()))
+                .map(|tx| tx.fee_per_byte())
+                .fold(std::f64::INFINITY, f64::min);
+            // A block that is full of prioritized transactions says nothing about the fees.
+            if min_fee_per_byte.is_infinite() {
+                return;
+            }
+            min_fee_per_byte
+        } else {
+            0f64
+        };
+
+        if state.fee_history.len() >= FEE_HISTORY_BLOCKS {
+            state.fee_history.pop_front();
+        }
+        state.fee_history.push_back((hash.clone(), min_fee_per_byte));
+    }
+
+    /// Removes the fees of blocks that were reverted by a rebranch from the fee history.
+    fn revert_fees(&self, reverted_blocks: &[(Blake2bHash, B::Block)]) {
+        let mut state = self.state.write();
+        state.fee_history.retain(|(hash, _)| !reverted_blocks.iter().any(|(reverted, _)| reverted == hash));
+    }
+
     /// Evict all transactions from the pool that have become invalid due to changes in the
     /// account state (i.e. typically because the were included in a newly mined block). No need to re-check signatures.
     fn evict_transactions(&self) {
@@ -495,7 +1181,7 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
 
                 // TODO Eliminate copy.
                 let sender_account = self.blockchain.get_account(&sender);
-                let (txs_to_add, txs_to_remove) = Self::merge_transactions(sender_account, block_height, existing_txs, &restored_txs);
+                let (txs_to_add, txs_to_remove) = Self::merge_transactions(sender_account, block_height, self.sender_limit, existing_txs, &restored_txs);
                 for tx in txs_to_add {
                     let transaction = Arc::new(tx.clone());
                     Self::add_transaction(&mut state, tx.hash(), transaction.clone());
@@ -508,18 +1194,7 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
             }
 
             // Evict lowest fee transactions if the mempool has grown too large.
-            let size = state.transactions_sorted_fee.len();
-            if size > SIZE_MAX {
-                let mut txs_to_remove = Vec::with_capacity(size - SIZE_MAX);
-                let mut iter = state.transactions_sorted_fee.iter();
-                for _ in 0..size - SIZE_MAX {
-                    txs_to_remove.push(iter.next().unwrap().clone());
-                }
-                for tx in txs_to_remove.iter() {
-                    Self::remove_transaction(&mut state, tx);
-                }
-                removed_transactions.extend(txs_to_remove);
-            }
+            removed_transactions.extend(self.evict_overflow(&mut state));
         }
 
         // Notify listeners.
@@ -532,8 +1207,145 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
         }
     }
 
+    /// Pushes the orphans that became valid into the mempool and drops those that expired.
+    fn promote_orphans(&self) {
+        let block_height = self.blockchain.head_height() + 1;
+
+        // Push the orphans paying the most first, as they would be kept over the others.
+        let txs_ready: Vec<Arc<Transaction>> = {
+            let state = self.state.read();
+            state.orphans.iter().rev()
+                .filter(|tx| tx.validity_start_height <= block_height)
+                .cloned()
+                .collect()
+        };
+        if txs_ready.is_empty() {
+            return;
+        }
+
+        let mut txs_to_push = Vec::with_capacity(txs_ready.len());
+        {
+            let mut state = self.state.write();
+            for tx in txs_ready {
+                let local = state.local_transactions.contains(&tx.hash::<Blake2bHash>());
+                Self::remove_orphan(&mut state, &tx);
+                if tx.is_valid_at(block_height) {
+                    txs_to_push.push((tx, local));
+                } else {
+                    trace!("Orphan transaction expired: {:?}", tx);
+                }
+            }
+        }
+
+        // We are notified by `Blockchain::push`, which holds the lock already.
+        for (tx, local) in txs_to_push {
+            let result = self.push_locked(Transaction::clone(&tx), local, None);
+            trace!("Orphan transaction {} pushed: {:?}", tx.hash::<Blake2bHash>(), result);
+        }
+    }
+
+    /// Keeps `transaction` in the orphan pool until it is valid. The sender account must exist
+    /// and afford all of the sender's orphans, such that the claimed fees are backed by funds. If
+    /// the sender has `ORPHANS_PER_SENDER_MAX` orphans or the pool is full, the orphan paying the
+    /// lowest fee per byte is dropped, which might be `transaction` itself.
+    fn add_orphan(&self, state: &mut MempoolState, hash: Blake2bHash, transaction: Transaction, sender_account: &Account, local: bool) -> ReturnCode {
+        if self.orphan_limit == 0 || sender_account.is_initial() || sender_account.account_type() != transaction.sender_type {
+            return ReturnCode::Invalid;
+        }
+
+        // The sender's orphans, sorted by fee per byte, ascending.
+        let mut sender_orphans: Vec<Arc<Transaction>> = state.orphans.iter()
+            .filter(|tx| tx.sender == transaction.sender)
+            .cloned()
+            .collect();
+        let mut sender_lowest = None;
+        if sender_orphans.len() >= ORPHANS_PER_SENDER_MAX {
+            if transaction.cmp(&sender_orphans[0]) != Ordering::Greater {
+                return ReturnCode::FeeTooLow;
+            }
+            sender_lowest = Some(sender_orphans.remove(0));
+        }
+
+        let total_value = sender_orphans.iter()
+            .map(|tx| tx.total_value())
+            .chain(std::iter::once(transaction.total_value()))
+            .try_fold(Coin::ZERO, |sum, value| sum.checked_add(value.ok()?));
+        if total_value.map_or(true, |total_value| total_value > sender_account.balance()) {
+            return ReturnCode::Invalid;
+        }
+
+        if let Some(lowest) = sender_lowest {
+            trace!("Orphan transaction dropped: {:?}", lowest);
+            Self::remove_orphan(state, &lowest);
+        }
+
+        if state.orphans.len() >= self.orphan_limit {
+            let lowest = state.orphans.iter().next().unwrap().clone();
+            if transaction.cmp(&lowest) != Ordering::Greater {
+                return ReturnCode::FeeTooLow;
+            }
+            trace!("Orphan transaction dropped: {:?}", lowest);
+            Self::remove_orphan(state, &lowest);
+        }
+
+        let tx = Arc::new(transaction);
+        state.orphans_by_hash.insert(hash.clone(), Arc::clone(&tx));
+        state.orphans.insert(tx);
+        if local {
+            state.local_transactions.insert(hash);
+        }
+        ReturnCode::Orphan
+    }
+
+    fn remove_orphan(state: &mut MempoolState, tx: &Transaction) {
+        let hash: Blake2bHash = tx.hash();
+        state.orphans_by_hash.remove(&hash);
+        state.orphans.remove(tx);
+        state.local_transactions.remove(&hash);
+    }
+
+    /// Whether `transaction` pays more per byte than each of the cheapest transactions that need
+    /// to be evicted to make room for `count` transactions and `bytes` bytes. `replaced` is
+    /// removed anyway, so it doesn't count.
+    fn outbids(state: &MempoolState, transaction: &Transaction, replaced: Option<&Arc<Transaction>>, count: usize, bytes: usize) -> bool {
+        let mut freed_count = 0;
+        let mut freed_bytes = 0;
+        for tx in state.transactions_sorted_fee.iter() {
+            if freed_count >= count && freed_bytes >= bytes {
+                return true;
+            }
+            if replaced == Some(tx) {
+                continue;
+            }
+            if transaction.cmp(tx) != Ordering::Greater {
+                return false;
+            }
+            freed_count += 1;
+            freed_bytes += tx.serialized_size();
+        }
+        freed_count >= count && freed_bytes >= bytes
+    }
+
+    /// Evicts the transactions paying the lowest fee per byte until the mempool is within its
+    /// limits again. Returns the evicted transactions.
+    fn evict_overflow(&self, state: &mut MempoolState) -> Vec<Arc<Transaction>> {
+        let mut txs_evicted = Vec::new();
+        while state.transactions_sorted_fee.len() > self.size_limit || state.size > self.bytes_limit {
+            let tx = match state.transactions_sorted_fee.iter().next() {
+                Some(tx) => tx.clone(),
+                None => break,
+            };
+            Self::remove_transaction(state, &tx);
+            txs_evicted.push(tx);
+        }
+        txs_evicted
+    }
+
     fn add_transaction(state: &mut MempoolState, hash: Blake2bHash, tx: Arc<Transaction>) {
-        state.transactions_by_hash.insert(hash, tx.clone());
+        if state.transactions_by_hash.insert(hash.clone(), tx.clone()).is_none() {
+            state.size += tx.serialized_size();
+        }
+        state.added_at.entry(hash).or_insert_with(Instant::now);
         state.transactions_sorted_fee.insert(tx.clone());
 
         let txs_by_recipient = state.transactions_by_recipient
@@ -548,7 +1360,13 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
     }
 
     fn remove_transaction(state: &mut MempoolState, tx: &Transaction) {
-        state.transactions_by_hash.remove(&tx.hash());
+        let hash: Blake2bHash = tx.hash();
+        if state.transactions_by_hash.remove(&hash).is_some() {
+            state.size -= tx.serialized_size();
+        }
+        state.local_transactions.remove(&hash);
+        state.added_at.remove(&hash);
+        state.rebroadcasts.remove(&hash);
         state.transactions_sorted_fee.remove(tx);
 
         let mut remove_key = false;
@@ -570,7 +1388,7 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
         }
     }
 
-    fn merge_transactions<'a>(mut sender_account: Account, block_height: u32, old_txs: &BTreeSet<Arc<Transaction>>, new_txs: &BTreeSet<&'a Transaction>) -> (Vec<&'a Transaction>, Vec<Arc<Transaction>>) {
+    fn merge_transactions<'a>(mut sender_account: Account, block_height: u32, sender_limit: usize, old_txs: &BTreeSet<Arc<Transaction>>, new_txs: &BTreeSet<&'a Transaction>) -> (Vec<&'a Transaction>, Vec<Arc<Transaction>>) {
         let mut txs_to_add = Vec::new();
         let mut txs_to_remove = Vec::new();
 
@@ -591,7 +1409,7 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
             };
 
             if new_is_next {
-                if tx_count < TRANSACTIONS_PER_SENDER_MAX {
+                if tx_count < sender_limit {
                     let tx = new_tx.unwrap();
                     if sender_account.commit_outgoing_transaction(*tx, block_height).is_ok() {
                         tx_count += 1;
@@ -601,7 +1419,7 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
                 new_tx = iter_new.next_back();
             } else {
                 let tx = old_tx.unwrap();
-                if tx_count < TRANSACTIONS_PER_SENDER_MAX {
+                if tx_count < sender_limit {
                     if sender_account.commit_outgoing_transaction(tx, block_height).is_ok() {
                         tx_count += 1;
                     } else {
@@ -618,6 +1436,53 @@ impl<'env, B: AbstractBlockchain<'env> + 'env> Mempool<'env, B> {
     }
 }
 
+/// Order of the transactions in a `Mempool::snapshot`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotOrder {
+    HighestFeeFirst,
+    LowestFeeFirst,
+    OldestFirst,
+    NewestFirst,
+}
+
+/// A page of the transactions in the mempool, see `Mempool::snapshot`.
+#[derive(Debug, Clone)]
+pub struct MempoolSnapshot {
+    /// Number of transactions in the mempool when the snapshot was taken.
+    pub total: usize,
+    pub entries: Vec<MempoolEntry>,
+}
+
+/// A transaction in a `MempoolSnapshot` with its metadata.
+#[derive(Debug, Clone)]
+pub struct MempoolEntry {
+    pub hash: Blake2bHash,
+    pub transaction: Arc<Transaction>,
+    pub fee_per_byte: f64,
+    /// Serialized size in bytes.
+    pub size: usize,
+    /// When the transaction was added to the mempool.
+    pub received_at: SystemTime,
+    /// Whether the transaction was submitted locally, see `Mempool::push_local_transaction`.
+    pub local: bool,
+}
+
+/// How transactions that were submitted locally are relayed to peers. Transactions received from
+/// peers are always relayed to all peers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastStrategy {
+    /// Relay to all peers.
+    Flood,
+    /// Relay to this many randomly chosen peers, which relay it further.
+    Random(usize),
+    /// Relay to the peers that are validators, which relay it further. If no validator is
+    /// connected, relay to all peers.
+    ValidatorsFirst,
+    /// Don't relay at all, such that the transaction is only included in blocks produced by this
+    /// node.
+    Private,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ReturnCode {
     FeeTooLow,
@@ -625,16 +1490,116 @@ pub enum ReturnCode {
     Accepted,
     Known,
     Filtered,
+    /// The transaction would likely expire before it is included.
+    Expiring,
+    /// The transaction isn't valid yet, because its validity window didn't start. It was added to
+    /// the orphan pool and will be pushed again once the chain caught up.
+    Orphan,
+}
+
+/// Reason why a transaction was rejected because of its fee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeRejectionReason {
+    /// The fee doesn't satisfy the filter rules of this node.
+    FilterRules,
+    /// The sender already has the maximum number of free transactions in the mempool.
+    FreeTransactionLimit,
+    /// The sender already has the maximum number of transactions in the mempool.
+    SenderTransactionLimit,
+    /// The mempool is full and the fee per byte isn't higher than the ones of the transactions
+    /// that would be evicted.
+    MempoolFull,
+    /// The transaction would replace one of the sender's transactions, but doesn't pay enough
+    /// more fee than it.
+    Replacement,
+}
+
+/// Minimum fee a rejected transaction needs to pay to be accepted, given the current state of the mempool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeFeedback {
+    pub reason: FeeRejectionReason,
+    pub min_fee: Coin,
+    /// Serialized size of the rejected transaction.
+    pub size: usize,
+}
+
+impl FeeFeedback {
+    pub fn min_fee_per_byte(&self) -> f64 {
+        u64::from(self.min_fee) as f64 / self.size as f64
+    }
 }
 
+/// Validity start height a rejected transaction needs to be included before it expires, given the
+/// current state of the mempool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidityFeedback {
+    /// The first block height at which the rejected transaction is not valid anymore.
+    pub validity_end_height: u32,
+    /// The block height at which the rejected transaction would likely be included.
+    pub estimated_inclusion_height: u32,
+    /// The validity start height to use instead, or `None` if the transaction would expire even
+    /// with the longest possible validity. In this case, it needs a higher fee.
+    pub suggested_validity_start_height: Option<u32>,
+}
+
+/// Smallest fee that exceeds `fee_per_byte` for a transaction of `size` bytes.
+fn fee_above(fee_per_byte: f64, size: usize) -> Coin {
+    Coin::from_u64_unchecked((fee_per_byte * size as f64).floor() as u64 + 1)
+}
+
+/// Whether `replacement` differs from `transaction` only in its fee (and signature), such that
+/// it would pay for the same transfer again.
+fn is_replaced_by(transaction: &Transaction, replacement: &Transaction) -> bool {
+    transaction.sender == replacement.sender
+        && transaction.sender_type == replacement.sender_type
+        && transaction.recipient == replacement.recipient
+        && transaction.recipient_type == replacement.recipient_type
+        && transaction.value == replacement.value
+        && transaction.validity_start_height == replacement.validity_start_height
+        && transaction.network_id == replacement.network_id
+        && transaction.flags == replacement.flags
+        && transaction.data == replacement.data
+        && transaction.fee != replacement.fee
+}
+
+/// Default number of blocks after which local transactions are relayed again, see `MempoolConfig`.
+pub const REBROADCAST_BLOCKS : u32 = 10;
+
+/// Default factor by which a replacement must pay more fee, see `MempoolConfig`.
+pub const REPLACEMENT_FEE_FACTOR : f64 = 1.1;
+
 /// Fee threshold in sat/byte below which transactions are considered "free".
 const TRANSACTION_RELAY_FEE_MIN : f64 = 1f64;
 
-/// Maximum number of transactions per sender.
-const TRANSACTIONS_PER_SENDER_MAX : u32 = 500;
+/// Default maximum number of transactions per sender, see `MempoolConfig`.
+pub const TRANSACTIONS_PER_SENDER_MAX : usize = 500;
+
+/// Default maximum number of transactions in the orphan pool, see `MempoolConfig`.
+pub const ORPHANS_MAX : usize = 1_000;
+
+/// Maximum number of transactions per sender in the orphan pool.
+pub const ORPHANS_PER_SENDER_MAX : usize = 10;
 
 /// Maximum number of "free" transactions per sender.
 const FREE_TRANSACTIONS_PER_SENDER_MAX : u32 = 10;
 
-/// Maximum number of transactions in the mempool.
+/// Default maximum number of transactions in the mempool.
 pub const SIZE_MAX : usize = 100_000;
+
+/// Default maximum serialized size of all transactions in the mempool in bytes.
+pub const SIZE_BYTES_MAX : usize = 32 * 1024 * 1024;
+
+/// Default serialized size of the transactions that fit into a block.
+pub const BLOCK_TRANSACTIONS_SIZE : usize = 100_000;
+
+/// Maximum number of expired transactions that are remembered, see `Mempool::evict_expired`.
+const EXPIRED_TRANSACTIONS_MAX : usize = 10_000;
+
+/// Number of recent blocks whose fees are used by `Mempool::estimate_fee_per_byte`.
+const FEE_HISTORY_BLOCKS : usize = 20;
+
+/// Share of `block_transactions_size` above which a block is considered full.
+const FULL_BLOCK_FACTOR : f64 = 0.9;
+
+/// Probability with which a transaction paying the estimated fee is included in time.
+pub const FEE_ESTIMATE_CONFIDENCE : f64 = 0.95;
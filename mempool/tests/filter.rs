@@ -1,5 +1,6 @@
 use std::convert::TryFrom;
 
+use beserial::Serialize;
 use nimiq_hash::{Hash, Blake2bHash};
 use nimiq_keys::Address;
 use nimiq_mempool::filter::{MempoolFilter, Rules};
@@ -47,3 +48,31 @@ fn it_accepts_and_rejects_transactions() {
     tx.fee = Coin::try_from(1).unwrap();
     assert!(f.accepts_transaction(&tx));
 }
+
+#[test]
+fn it_computes_min_fee() {
+    let mut s: Rules = Rules::default();
+    s.tx_fee = Coin::try_from(10).unwrap();
+    s.tx_fee_per_byte = 2.0;
+    s.tx_value = Coin::try_from(5).unwrap();
+
+    let f = MempoolFilter::new(s, MempoolFilter::DEFAULT_BLACKLIST_SIZE);
+
+    let mut tx = Transaction::new_basic(
+        Address::from([32u8; Address::SIZE]),
+        Address::from([213u8; Address::SIZE]),
+        Coin::try_from(5).unwrap(),
+        Coin::try_from(0).unwrap(),
+        0,
+        NetworkId::Main,
+    );
+
+    let min_fee = f.min_fee(&tx).unwrap();
+    assert_eq!(u64::from(min_fee), 2 * tx.serialized_size() as u64);
+    tx.fee = min_fee;
+    assert!(f.accepts_transaction(&tx));
+
+    // No fee is high enough if the value is too low.
+    tx.value = Coin::try_from(1).unwrap();
+    assert_eq!(f.min_fee(&tx), None);
+}
@@ -1,5 +1,7 @@
 use std::convert::TryFrom;
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use hex;
 
@@ -12,7 +14,8 @@ use nimiq_database::WriteTransaction;
 use nimiq_hash::Hash;
 use nimiq_keys::Address;
 use nimiq_keys::KeyPair;
-use nimiq_mempool::{Mempool, MempoolConfig, ReturnCode};
+use nimiq_mempool::{BroadcastStrategy, FeeRejectionReason, Mempool, MempoolConfig, ORPHANS_PER_SENDER_MAX, ReturnCode, SnapshotOrder};
+use nimiq_mempool::filter::Rules;
 use nimiq_network_primitives::time::NetworkTime;
 use nimiq_primitives::coin::Coin;
 use nimiq_primitives::networks::NetworkId;
@@ -134,6 +137,158 @@ fn push_and_get_two_tx_same_user() {
     assert_eq!(Arc::new(tx2_copy), mempool.get_transaction(&hash2).unwrap());
 }
 
+#[test]
+fn push_and_get_local_tx() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let mempool = Mempool::new(blockchain.clone(), MempoolConfig::default());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    // Push a transaction from a peer and a local one
+    let mut tx1 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    tx1.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx1.serialize_content())).serialize_to_vec();
+    let mut tx2 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(9).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    tx2.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx2.serialize_content())).serialize_to_vec();
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Accepted);
+    assert_eq!(mempool.push_local_transaction(tx2.clone()), ReturnCode::Accepted);
+    assert_eq!(mempool.get_local_transactions(), vec![Arc::new(tx2.clone())]);
+
+    // Submitting a known transaction locally marks it as local
+    assert_eq!(mempool.push_local_transaction(tx1.clone()), ReturnCode::Known);
+    let local_transactions = mempool.get_local_transactions();
+    assert_eq!(local_transactions.len(), 2);
+    assert!(local_transactions.contains(&Arc::new(tx1)));
+    assert!(local_transactions.contains(&Arc::new(tx2)));
+}
+
+#[test]
+fn announce_local_tx_only_if_flooded() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    let mut tx1 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    tx1.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx1.serialize_content())).serialize_to_vec();
+    let mut tx2 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(9).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    tx2.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx2.serialize_content())).serialize_to_vec();
+
+    for strategy in vec![BroadcastStrategy::Flood, BroadcastStrategy::Random(1), BroadcastStrategy::ValidatorsFirst, BroadcastStrategy::Private] {
+        let config = MempoolConfig { broadcast_strategy: strategy, ..MempoolConfig::default() };
+        let mempool = Mempool::new(blockchain.clone(), config);
+
+        // Push a transaction from a peer and a local one
+        assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Accepted);
+        assert_eq!(mempool.push_local_transaction(tx2.clone()), ReturnCode::Accepted);
+
+        // A peer requesting the mempool only learns about the local transaction if it is flooded
+        let mut announced = mempool.get_transactions(usize::max_value(), 0f64);
+        mempool.retain_announceable(&mut announced);
+        assert!(announced.contains(&Arc::new(tx1.clone())));
+        assert_eq!(announced.contains(&Arc::new(tx2.clone())), strategy == BroadcastStrategy::Flood);
+    }
+}
+
+#[test]
+fn replace_tx_paying_higher_fee() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let mempool = Mempool::new(blockchain.clone(), MempoolConfig::default());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    // Generate the same transaction with different fees, such that address_a can only afford one of them
+    let value = u64::from(blockchain.state().accounts().get(&address_a, None).balance()) - 2000;
+    let create_tx = |fee: u64| {
+        let mut tx = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(value).unwrap(), Coin::try_from(fee).unwrap(), 1, NetworkId::Main );
+        tx.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx.serialize_content())).serialize_to_vec();
+        tx
+    };
+    let tx1 = create_tx(1000);
+    let tx2 = create_tx(1050);
+    let tx3 = create_tx(1100);
+
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Accepted);
+
+    // The fee must be raised by at least the replacement fee factor
+    assert_eq!(mempool.push_transaction(tx2.clone()), ReturnCode::FeeTooLow);
+    let feedback = mempool.fee_feedback(&tx2).unwrap();
+    assert_eq!(feedback.reason, FeeRejectionReason::Replacement);
+    assert_eq!(feedback.min_fee, Coin::try_from(1100).unwrap());
+
+    assert_eq!(mempool.push_transaction(tx3.clone()), ReturnCode::Accepted);
+    assert!(!mempool.contains(&tx1.hash()));
+    assert!(mempool.contains(&tx3.hash()));
+    assert_eq!(mempool.get_transactions(usize::max_value(), 0.0).len(), 1);
+}
+
+#[test]
+fn keep_affordable_txs_differing_in_fee() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let mempool = Mempool::new(blockchain.clone(), MempoolConfig::default());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    // Generate the same payment with different fees, address_a can afford both of them
+    let create_tx = |fee: u64| {
+        let mut tx = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(fee).unwrap(), 1, NetworkId::Main );
+        tx.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx.serialize_content())).serialize_to_vec();
+        tx
+    };
+    let tx1 = create_tx(1000);
+    let tx2 = create_tx(1050);
+
+    // Both transactions are valid on chain, so the second one is a repeated payment, not a replacement
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Accepted);
+    assert_eq!(mempool.push_transaction(tx2.clone()), ReturnCode::Accepted);
+    assert!(mempool.contains(&tx1.hash()));
+    assert!(mempool.contains(&tx2.hash()));
+    assert_eq!(mempool.fee_feedback(&tx2), None);
+}
+
+#[test]
+#[should_panic]
+fn reject_zero_sender_limit() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let config = MempoolConfig { sender_limit: 0, ..MempoolConfig::default() };
+    Mempool::new(blockchain, config);
+}
+
 #[test]
 fn reject_free_tx_beyond_limit() {
     let env = VolatileEnvironment::new(10).unwrap();
@@ -157,7 +312,544 @@ fn reject_free_tx_beyond_limit() {
         if i < 10 {
             assert_eq!(mempool.push_transaction(tx1), ReturnCode::Accepted);
         } else {
-            assert_eq!(mempool.push_transaction(tx1), ReturnCode::FeeTooLow);
+            assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::FeeTooLow);
+
+            let feedback = mempool.fee_feedback(&tx1).unwrap();
+            assert_eq!(feedback.reason, FeeRejectionReason::FreeTransactionLimit);
+            assert!(feedback.min_fee_per_byte() >= 1.0);
         }
     }
 }
+
+#[test]
+fn evict_bytes_removes_lowest_fee_tx() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let mempool = Mempool::new(blockchain.clone(), MempoolConfig::default());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    // Push a free and a paying transaction from address_a
+    let mut tx1 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    let signature_proof1 = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx1.serialize_content()));
+    tx1.proof = signature_proof1.serialize_to_vec();
+    let hash1 = tx1.hash();
+    let size1 = tx1.serialized_size();
+    assert_eq!(mempool.push_transaction(tx1), ReturnCode::Accepted);
+
+    let mut tx2 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(1000).unwrap(), 1, NetworkId::Main );
+    let signature_proof2 = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx2.serialize_content()));
+    tx2.proof = signature_proof2.serialize_to_vec();
+    let hash2 = tx2.hash();
+    let size2 = tx2.serialized_size();
+    assert_eq!(mempool.push_transaction(tx2), ReturnCode::Accepted);
+
+    assert_eq!(mempool.size_bytes(), size1 + size2);
+
+    // Evicting a single byte frees the whole free transaction.
+    assert_eq!(mempool.evict_bytes(1), size1);
+    assert!(!mempool.contains(&hash1));
+    assert!(mempool.contains(&hash2));
+    assert_eq!(mempool.size_bytes(), size2);
+}
+
+#[test]
+fn reject_tx_expiring_before_inclusion() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    // Every byte of the transactions paying more delays the inclusion by one block.
+    let config = MempoolConfig { block_transactions_size: 1, ..MempoolConfig::default() };
+    let mempool = Mempool::new(blockchain.clone(), config);
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    let mut tx1 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(1000).unwrap(), 1, NetworkId::Main );
+    let signature_proof1 = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx1.serialize_content()));
+    tx1.proof = signature_proof1.serialize_to_vec();
+    let size1 = tx1.serialized_size();
+    assert_eq!(mempool.push_transaction(tx1), ReturnCode::Accepted);
+
+    // A cheaper transaction would only be included after its validity window.
+    let mut tx2 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(20).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    let signature_proof2 = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx2.serialize_content()));
+    tx2.proof = signature_proof2.serialize_to_vec();
+    assert_eq!(mempool.push_transaction(tx2.clone()), ReturnCode::Expiring);
+
+    let feedback = mempool.validity_feedback(&tx2).unwrap();
+    assert_eq!(feedback.validity_end_height, 1 + mempool.validity_window_length());
+    assert_eq!(feedback.estimated_inclusion_height, 2 + size1 as u32);
+    // The transactions ahead exceed even the longest possible validity window.
+    assert_eq!(feedback.suggested_validity_start_height, None);
+
+    // A transaction paying more is included first.
+    let mut tx3 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(30).unwrap(), Coin::try_from(2000).unwrap(), 1, NetworkId::Main );
+    let signature_proof3 = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx3.serialize_content()));
+    tx3.proof = signature_proof3.serialize_to_vec();
+    assert!(mempool.validity_feedback(&tx3).is_none());
+    assert_eq!(mempool.push_transaction(tx3), ReturnCode::Accepted);
+}
+
+#[test]
+fn evict_tx_after_ttl() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let config = MempoolConfig { transaction_ttl: Some(Duration::from_millis(50)), ..MempoolConfig::default() };
+    let mempool = Mempool::new(blockchain.clone(), config);
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    let mut tx = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    let signature_proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx.serialize_content()));
+    tx.proof = signature_proof.serialize_to_vec();
+    assert_eq!(mempool.push_transaction(tx.clone()), ReturnCode::Accepted);
+
+    // The transaction is still valid and younger than the TTL.
+    assert_eq!(mempool.evict_expired(), 0);
+    assert!(mempool.contains(&tx.hash()));
+
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(mempool.evict_expired(), 1);
+    assert!(!mempool.contains(&tx.hash()));
+    assert_eq!(mempool.size_bytes(), 0);
+
+    // Peers can't relay the evicted transaction again, but it can be resubmitted locally.
+    assert_eq!(mempool.push_transaction(tx.clone()), ReturnCode::Known);
+    assert!(!mempool.contains(&tx.hash()));
+    assert_eq!(mempool.push_local_transaction(tx.clone()), ReturnCode::Accepted);
+    assert!(mempool.contains(&tx.hash()));
+}
+
+#[test]
+fn evict_tx_after_validity_window() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let mempool = Mempool::new(blockchain.clone(), MempoolConfig::default());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    let mut tx = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    let signature_proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx.serialize_content()));
+    tx.proof = signature_proof.serialize_to_vec();
+    assert_eq!(mempool.push_transaction(tx.clone()), ReturnCode::Accepted);
+
+    // The transaction is still valid in the last block of its validity window.
+    assert_eq!(mempool.evict_expired_at(2), 0);
+    assert_eq!(mempool.evict_expired_at(mempool.validity_window_length()), 0);
+    assert!(mempool.contains(&tx.hash()));
+
+    assert_eq!(mempool.evict_expired_at(1 + mempool.validity_window_length()), 1);
+    assert!(!mempool.contains(&tx.hash()));
+    assert_eq!(mempool.size_bytes(), 0);
+
+    // Transactions that aren't valid anymore aren't remembered as expired.
+    assert_eq!(mempool.push_transaction(tx.clone()), ReturnCode::Accepted);
+}
+
+#[test]
+fn estimate_fee_per_byte_from_mempool() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    let mut tx1 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(1000).unwrap(), 1, NetworkId::Main );
+    let signature_proof1 = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx1.serialize_content()));
+    tx1.proof = signature_proof1.serialize_to_vec();
+
+    let mut tx2 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(20).unwrap(), Coin::try_from(500).unwrap(), 1, NetworkId::Main );
+    let signature_proof2 = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx2.serialize_content()));
+    tx2.proof = signature_proof2.serialize_to_vec();
+
+    // Each block fits a single transaction.
+    let config = MempoolConfig { block_transactions_size: tx1.serialized_size(), ..MempoolConfig::default() };
+    let mempool = Mempool::new(blockchain.clone(), config);
+
+    // Any fee is enough while the mempool is empty.
+    assert_eq!(mempool.estimate_fee_per_byte(1), 0f64);
+
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Accepted);
+    assert_eq!(mempool.push_transaction(tx2.clone()), ReturnCode::Accepted);
+
+    // The next block is taken by tx1, the one after it by tx2.
+    assert_eq!(mempool.estimate_fee_per_byte(0), tx1.fee_per_byte());
+    assert_eq!(mempool.estimate_fee_per_byte(1), tx1.fee_per_byte());
+    assert_eq!(mempool.estimate_fee_per_byte(2), tx2.fee_per_byte());
+    assert_eq!(mempool.estimate_fee_per_byte(3), 0f64);
+}
+
+#[test]
+fn evict_lowest_fee_tx_beyond_sender_limit() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let config = MempoolConfig { sender_limit: 2, ..MempoolConfig::default() };
+    let mempool = Mempool::new(blockchain.clone(), config);
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    let mut txs = Vec::new();
+    for (value, fee) in [(10, 2000), (20, 3000), (30, 4000), (40, 1000)].iter() {
+        let mut tx = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(*value).unwrap(), Coin::try_from(*fee).unwrap(), 1, NetworkId::Main );
+        let signature_proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx.serialize_content()));
+        tx.proof = signature_proof.serialize_to_vec();
+        txs.push(tx);
+    }
+
+    assert_eq!(mempool.push_transaction(txs[0].clone()), ReturnCode::Accepted);
+    assert_eq!(mempool.push_transaction(txs[1].clone()), ReturnCode::Accepted);
+
+    // A transaction paying more evicts the one paying the least.
+    assert_eq!(mempool.push_transaction(txs[2].clone()), ReturnCode::Accepted);
+    assert!(!mempool.contains(&txs[0].hash()));
+    assert!(mempool.contains(&txs[1].hash()));
+    assert!(mempool.contains(&txs[2].hash()));
+
+    // A transaction paying less than the sender's kept transactions is rejected.
+    assert_eq!(mempool.push_transaction(txs[3].clone()), ReturnCode::FeeTooLow);
+    let feedback = mempool.fee_feedback(&txs[3]).unwrap();
+    assert_eq!(feedback.reason, FeeRejectionReason::SenderTransactionLimit);
+}
+
+#[test]
+fn keep_not_yet_valid_tx_as_orphan() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let config = MempoolConfig { orphan_limit: 1, ..MempoolConfig::default() };
+    let mempool = Mempool::new(blockchain.clone(), config);
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let keypair_c = KeyPair::generate();
+    let address_c = Address::from(&keypair_c.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    // A transaction whose validity window didn't start yet.
+    let mut tx1 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(0).unwrap(), 5, NetworkId::Main );
+    let signature_proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx1.serialize_content()));
+    tx1.proof = signature_proof.serialize_to_vec();
+
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Orphan);
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Known);
+    assert!(mempool.is_orphan(&tx1.hash()));
+    assert!(!mempool.contains(&tx1.hash()));
+
+    // A transaction whose sender account doesn't exist can't pay its fee, so it isn't kept.
+    let mut tx3 = Transaction::new_basic( address_c.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(1000).unwrap(), 5, NetworkId::Main );
+    let signature_proof = SignatureProof::from(keypair_c.public.clone(), keypair_c.sign(&tx3.serialize_content()));
+    tx3.proof = signature_proof.serialize_to_vec();
+
+    assert_eq!(mempool.push_transaction(tx3.clone()), ReturnCode::Invalid);
+    assert!(!mempool.is_orphan(&tx3.hash()));
+    assert!(mempool.is_orphan(&tx1.hash()));
+
+    // An orphan paying more replaces the orphan paying less.
+    let mut tx2 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(1000).unwrap(), 5, NetworkId::Main );
+    let signature_proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx2.serialize_content()));
+    tx2.proof = signature_proof.serialize_to_vec();
+
+    assert_eq!(mempool.push_transaction(tx2.clone()), ReturnCode::Orphan);
+    assert!(!mempool.is_orphan(&tx1.hash()));
+    assert!(mempool.is_orphan(&tx2.hash()));
+    assert_eq!(mempool.orphan_count(), 1);
+
+    // If the orphan pool is full, an orphan paying less is rejected.
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::FeeTooLow);
+}
+
+#[test]
+fn limit_orphans_per_sender() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let mempool = Mempool::new(blockchain.clone(), MempoolConfig::default());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+    let balance = u64::from(blockchain.state().accounts().get(&address_a, None).balance());
+
+    let sign = |value: u64, fee: u64| {
+        let mut tx = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(value).unwrap(), Coin::try_from(fee).unwrap(), 5, NetworkId::Main );
+        let signature_proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx.serialize_content()));
+        tx.proof = signature_proof.serialize_to_vec();
+        tx
+    };
+
+    let txs: Vec<Transaction> = (1..=ORPHANS_PER_SENDER_MAX as u64).map(|i| sign(10, i * 100)).collect();
+    for tx in txs.iter() {
+        assert_eq!(mempool.push_transaction(tx.clone()), ReturnCode::Orphan);
+    }
+
+    // If the sender has the maximum number of orphans, an orphan paying less is rejected.
+    assert_eq!(mempool.push_transaction(sign(10, 0)), ReturnCode::FeeTooLow);
+
+    // An orphan the sender can't afford together with the others is rejected.
+    assert_eq!(mempool.push_transaction(sign(balance - 101_000, 100_000)), ReturnCode::Invalid);
+    assert!(mempool.is_orphan(&txs[0].hash()));
+
+    // An orphan paying more replaces the sender's orphan paying the least.
+    let tx = sign(10, 100_000);
+    assert_eq!(mempool.push_transaction(tx.clone()), ReturnCode::Orphan);
+    assert!(mempool.is_orphan(&tx.hash()));
+    assert!(!mempool.is_orphan(&txs[0].hash()));
+    assert_eq!(mempool.orphan_count(), ORPHANS_PER_SENDER_MAX);
+}
+
+#[test]
+fn evict_lowest_fee_tx_beyond_bytes_limit() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    let mut txs = Vec::new();
+    for (value, fee) in [(10, 2000), (20, 3000), (30, 4000), (40, 1000)].iter() {
+        let mut tx = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(*value).unwrap(), Coin::try_from(*fee).unwrap(), 1, NetworkId::Main );
+        let signature_proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx.serialize_content()));
+        tx.proof = signature_proof.serialize_to_vec();
+        txs.push(tx);
+    }
+
+    // Room for two transactions.
+    let config = MempoolConfig { bytes_limit: 2 * txs[0].serialized_size(), ..MempoolConfig::default() };
+    let mempool = Mempool::new(blockchain.clone(), config);
+
+    assert_eq!(mempool.push_transaction(txs[0].clone()), ReturnCode::Accepted);
+    assert_eq!(mempool.push_transaction(txs[1].clone()), ReturnCode::Accepted);
+
+    // A transaction paying more evicts the one paying the least.
+    assert_eq!(mempool.push_transaction(txs[2].clone()), ReturnCode::Accepted);
+    assert!(!mempool.contains(&txs[0].hash()));
+    assert_eq!(mempool.size_bytes(), 2 * txs[0].serialized_size());
+
+    // A transaction paying less than the transaction it would evict is rejected.
+    assert_eq!(mempool.push_transaction(txs[3].clone()), ReturnCode::FeeTooLow);
+    let feedback = mempool.fee_feedback(&txs[3]).unwrap();
+    assert_eq!(feedback.reason, FeeRejectionReason::MempoolFull);
+    assert!(feedback.min_fee > txs[1].fee);
+}
+
+#[test]
+fn snapshot_pages_through_the_mempool() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let mempool = Mempool::new(blockchain.clone(), MempoolConfig::default());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    let mut txs = Vec::new();
+    for (value, fee) in [(10, 3000), (20, 1000), (30, 2000)].iter() {
+        let mut tx = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(*value).unwrap(), Coin::try_from(*fee).unwrap(), 1, NetworkId::Main );
+        let signature_proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx.serialize_content()));
+        tx.proof = signature_proof.serialize_to_vec();
+        assert_eq!(mempool.push_transaction(tx.clone()), ReturnCode::Accepted);
+        txs.push(tx);
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(mempool.push_local_transaction(txs[1].clone()), ReturnCode::Known);
+
+    let snapshot = mempool.snapshot(0, 2, SnapshotOrder::HighestFeeFirst);
+    assert_eq!(snapshot.total, 3);
+    assert_eq!(snapshot.entries.len(), 2);
+    assert_eq!(snapshot.entries[0].hash, txs[0].hash());
+    assert_eq!(snapshot.entries[1].hash, txs[2].hash());
+    assert_eq!(snapshot.entries[0].size, txs[0].serialized_size());
+
+    let snapshot = mempool.snapshot(2, 2, SnapshotOrder::HighestFeeFirst);
+    assert_eq!(snapshot.entries.len(), 1);
+    assert_eq!(snapshot.entries[0].hash, txs[1].hash());
+    assert!(snapshot.entries[0].local);
+
+    let snapshot = mempool.snapshot(1, 1, SnapshotOrder::NewestFirst);
+    assert_eq!(snapshot.entries[0].hash, txs[1].hash());
+    let snapshot = mempool.snapshot(0, 3, SnapshotOrder::OldestFirst);
+    assert_eq!(snapshot.entries[0].hash, txs[0].hash());
+    assert!(snapshot.entries[0].received_at <= snapshot.entries[1].received_at);
+}
+
+#[test]
+fn rebroadcast_stale_local_tx_with_backoff() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let config = MempoolConfig { rebroadcast_blocks: 10, ..MempoolConfig::default() };
+    let mempool = Mempool::new(blockchain.clone(), config);
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    // Push a transaction from a peer and a local one
+    let mut tx1 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    tx1.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx1.serialize_content())).serialize_to_vec();
+    let mut tx2 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(9).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    tx2.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx2.serialize_content())).serialize_to_vec();
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Accepted);
+    assert_eq!(mempool.push_local_transaction(tx2.clone()), ReturnCode::Accepted);
+
+    // The local transaction is due after 10 blocks, then after 20 more.
+    assert!(mempool.stale_local_transactions(1).is_empty());
+    assert!(mempool.stale_local_transactions(10).is_empty());
+    assert_eq!(mempool.stale_local_transactions(11), vec![Arc::new(tx2.clone())]);
+    assert!(mempool.stale_local_transactions(12).is_empty());
+    assert!(mempool.stale_local_transactions(30).is_empty());
+    assert_eq!(mempool.stale_local_transactions(31), vec![Arc::new(tx2.clone())]);
+
+    // Rebroadcasts can be disabled.
+    let config = MempoolConfig { rebroadcast_blocks: 0, ..MempoolConfig::default() };
+    let mempool = Mempool::new(blockchain.clone(), config);
+    assert_eq!(mempool.push_local_transaction(tx2.clone()), ReturnCode::Accepted);
+    assert!(mempool.stale_local_transactions(1).is_empty());
+    assert!(mempool.stale_local_transactions(100).is_empty());
+}
+
+#[test]
+fn update_filter_rules_at_runtime() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let mempool = Mempool::new(blockchain.clone(), MempoolConfig::default());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    let mut tx1 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(10).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+    tx1.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx1.serialize_content())).serialize_to_vec();
+    let mut tx2 = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(9).unwrap(), Coin::try_from(1000).unwrap(), 1, NetworkId::Main );
+    tx2.proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx2.serialize_content())).serialize_to_vec();
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Accepted);
+    assert_eq!(mempool.push_transaction(tx2.clone()), ReturnCode::Accepted);
+
+    // Raising the minimum fee evicts the free transaction and filters it from now on.
+    let rules = Rules { tx_fee: Coin::try_from(100).unwrap(), ..Rules::default() };
+    assert_eq!(mempool.set_filter_rules(rules), 1);
+    assert_eq!(mempool.filter_rules().tx_fee, Coin::try_from(100).unwrap());
+    assert!(!mempool.contains(&tx1.hash()));
+    assert!(mempool.contains(&tx2.hash()));
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Filtered);
+    assert!(mempool.is_filtered(&tx1.hash()));
+
+    // Lowering it again clears the blacklist.
+    assert_eq!(mempool.set_filter_rules(Rules::default()), 0);
+    assert!(!mempool.is_filtered(&tx1.hash()));
+    assert_eq!(mempool.push_transaction(tx1.clone()), ReturnCode::Accepted);
+}
+
+#[test]
+fn get_filtered_transactions_for_block() {
+    let env = VolatileEnvironment::new(10).unwrap();
+    let blockchain = Arc::new(Blockchain::new(&env, NetworkId::Main, Arc::new(NetworkTime::new())).unwrap());
+    let mempool = Mempool::new(blockchain.clone(), MempoolConfig::default());
+
+    let keypair_a = KeyPair::generate();
+    let address_a = Address::from(&keypair_a.public);
+    let address_b = Address::from([2u8; Address::SIZE]);
+
+    // Give address_a balance
+    let body = BlockBody { miner: address_a.clone(), extra_data: Vec::new(), transactions: Vec::new(), receipts: Receipts::default() };
+    let mut txn = WriteTransaction::new(&env);
+    blockchain.state().accounts().commit(&mut txn, &body.transactions, &vec![body.get_reward_inherent(1)], 1).unwrap();
+    txn.commit();
+
+    for value in 9..11 {
+        let mut tx = Transaction::new_basic( address_a.clone(), address_b.clone(), Coin::try_from(value).unwrap(), Coin::try_from(0).unwrap(), 1, NetworkId::Main );
+        let signature_proof = SignatureProof::from(keypair_a.public.clone(), keypair_a.sign(&tx.serialize_content()));
+        tx.proof = signature_proof.serialize_to_vec();
+        assert_eq!(mempool.push_transaction(tx), ReturnCode::Accepted);
+    }
+
+    assert_eq!(mempool.get_transactions_for_block(10000).len(), 2);
+
+    let txs = mempool.get_filtered_transactions_for_block(10000, |tx| tx.value == Coin::try_from(10).unwrap());
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0].value, Coin::try_from(10).unwrap());
+
+    // Skipped transactions don't count towards the size
+    let size = txs[0].serialized_size();
+    assert_eq!(mempool.get_filtered_transactions_for_block(size, |tx| tx.value == Coin::try_from(10).unwrap()).len(), 1);
+}
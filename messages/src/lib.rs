@@ -23,7 +23,7 @@ extern crate nimiq_handel as handel;
 
 use std::fmt::Display;
 use std::io;
-use std::io::{Read, Cursor, Seek, SeekFrom};
+use std::io::{Read, Cursor, Seek, SeekFrom, Write};
 
 use parking_lot::RwLock;
 use rand::Rng;
@@ -32,13 +32,14 @@ use rand::rngs::OsRng;
 use beserial::{Deserialize, DeserializeWithLength, ReadBytesExt, Serialize, SerializeWithLength, SerializingError, uvar, WriteBytesExt};
 use block::{Block, BlockHeader};
 use block::proof::ChainProof;
-use block_albatross::{Block as BlockAlbatross, BlockHeader as BlockHeaderAlbatross, ForkProof, SignedPbftProposal, ViewChange, PbftPrepareMessage, PbftCommitMessage, ViewChangeProof};
-use hash::Blake2bHash;
+use block_albatross::{Block as BlockAlbatross, BlockHeader as BlockHeaderAlbatross, ForkProof, MacroHeaderDiff, SignedPbftProposal, ViewChange, PbftPrepareMessage, PbftCommitMessage, ViewChangeProof};
+use hash::{Blake2bHash, Blake2bHasher, Hasher};
 use keys::{Address, KeyPair, PublicKey, Signature};
 use network_primitives::address::{PeerAddress, PeerId};
 use network_primitives::protocol::ProtocolFlags;
 use network_primitives::services::ServiceFlags;
 use network_primitives::subscription::Subscription;
+use network_primitives::heartbeat::SignedHeartbeat;
 use network_primitives::validator_info::SignedValidatorInfo;
 use network_primitives::version;
 use transaction::{Transaction, TransactionReceipt, TransactionsProof};
@@ -66,6 +67,7 @@ pub enum MessageType {
     Mempool = 9,
     Reject = 10,
     Subscribe = 11,
+    MempoolDigest = 12,
 
     Addr = 20,
     GetAddr = 21,
@@ -103,6 +105,8 @@ pub enum MessageType {
     PbftPrepare = 121,
     PbftCommit = 122,
     GetMacroBlocks = 123,
+    MacroHeaderDiff = 124,
+    Heartbeat = 125,
 }
 
 #[derive(Clone, Debug)]
@@ -119,6 +123,7 @@ pub enum Message {
     Mempool,
     Reject(Box<RejectMessage>),
     Subscribe(Box<Subscription>),
+    MempoolDigest(Box<MempoolDigestMessage>),
 
     Addr(Box<AddrMessage>),
     GetAddr(Box<GetAddrMessage>),
@@ -156,6 +161,8 @@ pub enum Message {
     PbftPrepare(Box<LevelUpdateMessage<PbftPrepareMessage>>),
     PbftCommit(Box<LevelUpdateMessage<PbftCommitMessage>>),
     GetMacroBlocks(Box<GetBlocksMessage>),
+    MacroHeaderDiff(Box<MacroHeaderDiff>),
+    Heartbeat(Box<SignedHeartbeat>),
 }
 
 impl Message {
@@ -173,6 +180,7 @@ impl Message {
             Message::Mempool => MessageType::Mempool,
             Message::Reject(_) => MessageType::Reject,
             Message::Subscribe(_) => MessageType::Subscribe,
+            Message::MempoolDigest(_) => MessageType::MempoolDigest,
             Message::Addr(_) => MessageType::Addr,
             Message::GetAddr(_) => MessageType::GetAddr,
             Message::Ping(_) => MessageType::Ping,
@@ -204,6 +212,8 @@ impl Message {
             Message::PbftPrepare(_) => MessageType::PbftPrepare,
             Message::PbftCommit(_) => MessageType::PbftCommit,
             Message::GetMacroBlocks(_) => MessageType::GetMacroBlocks,
+            Message::MacroHeaderDiff(_) => MessageType::MacroHeaderDiff,
+            Message::Heartbeat(_) => MessageType::Heartbeat,
         }
     }
 
@@ -284,6 +294,7 @@ impl Deserialize for Message {
             MessageType::Mempool => Message::Mempool,
             MessageType::Reject => Message::Reject(Deserialize::deserialize(&mut crc32_reader)?),
             MessageType::Subscribe => Message::Subscribe(Deserialize::deserialize(&mut crc32_reader)?),
+            MessageType::MempoolDigest => Message::MempoolDigest(Deserialize::deserialize(&mut crc32_reader)?),
             MessageType::Addr => Message::Addr(Deserialize::deserialize(&mut crc32_reader)?),
             MessageType::GetAddr => Message::GetAddr(Deserialize::deserialize(&mut crc32_reader)?),
             MessageType::Ping => Message::Ping(Deserialize::deserialize(&mut crc32_reader)?),
@@ -315,6 +326,8 @@ impl Deserialize for Message {
             MessageType::PbftPrepare => Message::PbftPrepare(Deserialize::deserialize(&mut crc32_reader)?),
             MessageType::PbftCommit => Message::PbftCommit(Deserialize::deserialize(&mut crc32_reader)?),
             MessageType::GetMacroBlocks => Message::GetMacroBlocks(Deserialize::deserialize(&mut crc32_reader)?),
+            MessageType::MacroHeaderDiff => Message::MacroHeaderDiff(Deserialize::deserialize(&mut crc32_reader)?),
+            MessageType::Heartbeat => Message::Heartbeat(Deserialize::deserialize(&mut crc32_reader)?),
         };
 
         // XXX Consume any leftover bytes in the message before computing the checksum.
@@ -354,6 +367,7 @@ impl Serialize for Message {
             Message::Mempool => 0,
             Message::Reject(reject_message) => reject_message.serialize(&mut v)?,
             Message::Subscribe(subscribe_message) => subscribe_message.serialize(&mut v)?,
+            Message::MempoolDigest(digest_message) => digest_message.serialize(&mut v)?,
             Message::Addr(addr_message) => addr_message.serialize(&mut v)?,
             Message::GetAddr(get_addr_message) => get_addr_message.serialize(&mut v)?,
             Message::Ping(nonce) => nonce.serialize(&mut v)?,
@@ -385,6 +399,8 @@ impl Serialize for Message {
             Message::PbftPrepare(pbft_prepare) => pbft_prepare.serialize(&mut v)?,
             Message::PbftCommit(pbft_commit) => pbft_commit.serialize(&mut v)?,
             Message::GetMacroBlocks(get_blocks_message) => get_blocks_message.serialize(&mut v)?,
+            Message::MacroHeaderDiff(header_diff) => header_diff.serialize(&mut v)?,
+            Message::Heartbeat(heartbeat) => heartbeat.serialize(&mut v)?,
         };
 
         // write checksum to placeholder
@@ -413,6 +429,7 @@ impl Serialize for Message {
             Message::Mempool => 0,
             Message::Reject(reject_message) => reject_message.serialized_size(),
             Message::Subscribe(subscribe_message) => subscribe_message.serialized_size(),
+            Message::MempoolDigest(digest_message) => digest_message.serialized_size(),
             Message::Addr(addr_message) => addr_message.serialized_size(),
             Message::GetAddr(get_addr_message) => get_addr_message.serialized_size(),
             Message::Ping(nonce) => nonce.serialized_size(),
@@ -444,6 +461,8 @@ impl Serialize for Message {
             Message::PbftPrepare(pbft_prepare) => pbft_prepare.serialized_size(),
             Message::PbftCommit(pbft_commit) => pbft_commit.serialized_size(),
             Message::GetMacroBlocks(get_blocks_message) => get_blocks_message.serialized_size(),
+            Message::MacroHeaderDiff(header_diff) => header_diff.serialized_size(),
+            Message::Heartbeat(heartbeat) => heartbeat.serialized_size(),
         };
         size
     }
@@ -464,6 +483,7 @@ pub struct MessageNotifier {
     pub mempool: RwLock<PassThroughNotifier<'static, ()>>,
     pub reject: RwLock<PassThroughNotifier<'static, RejectMessage>>,
     pub subscribe: RwLock<PassThroughNotifier<'static, Subscription>>,
+    pub mempool_digest: RwLock<PassThroughNotifier<'static, MempoolDigestMessage>>,
     pub addr: RwLock<PassThroughNotifier<'static, AddrMessage>>,
     pub get_addr: RwLock<PassThroughNotifier<'static, GetAddrMessage>>,
     pub ping: RwLock<PassThroughNotifier<'static, /*nonce*/ u32>>,
@@ -494,6 +514,8 @@ pub struct MessageNotifier {
     pub pbft_prepare: RwLock<PassThroughNotifier<'static, LevelUpdateMessage<PbftPrepareMessage>>>,
     pub pbft_commit: RwLock<PassThroughNotifier<'static, LevelUpdateMessage<PbftCommitMessage>>>,
     pub get_macro_blocks: RwLock<PassThroughNotifier<'static, GetBlocksMessage>>,
+    pub macro_header_diff: RwLock<PassThroughNotifier<'static, MacroHeaderDiff>>,
+    pub heartbeat: RwLock<PassThroughNotifier<'static, SignedHeartbeat>>,
 }
 
 impl MessageNotifier {
@@ -516,6 +538,7 @@ impl MessageNotifier {
             Message::Mempool => self.mempool.read().notify(()),
             Message::Reject(msg) => self.reject.read().notify(*msg),
             Message::Subscribe(msg) => self.subscribe.read().notify(*msg),
+            Message::MempoolDigest(msg) => self.mempool_digest.read().notify(*msg),
             Message::Addr(msg) => self.addr.read().notify(*msg),
             Message::GetAddr(msg) => self.get_addr.read().notify(*msg),
             Message::Ping(nonce) => self.ping.read().notify(nonce),
@@ -546,6 +569,8 @@ impl MessageNotifier {
             Message::PbftPrepare(prepare) => self.pbft_prepare.read().notify(*prepare),
             Message::PbftCommit(commit) => self.pbft_commit.read().notify(*commit),
             Message::GetMacroBlocks(msg) => self.get_macro_blocks.read().notify(*msg),
+            Message::MacroHeaderDiff(header_diff) => self.macro_header_diff.read().notify(*header_diff),
+            Message::Heartbeat(heartbeat) => self.heartbeat.read().notify(*heartbeat),
         }
     }
 }
@@ -745,6 +770,42 @@ impl GetBlocksMessage {
     }
 }
 
+/// Compact summary of the transactions in a mempool, used to reconcile mempools when connecting.
+/// Transactions are identified by short IDs that are derived from their hash and a random salt,
+/// so that an attacker can't craft transactions with colliding short IDs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MempoolDigestMessage {
+    pub salt: u64,
+    #[beserial(len_type(u16))]
+    pub short_ids: Vec<u64>,
+}
+
+impl MempoolDigestMessage {
+    pub const SHORT_IDS_MAX_COUNT: usize = 10_000;
+
+    pub fn new<'a, I: Iterator<Item=&'a Blake2bHash>>(hashes: I) -> Message {
+        let salt: u64 = OsRng::new().unwrap().gen();
+        let short_ids = hashes
+            .take(Self::SHORT_IDS_MAX_COUNT)
+            .map(|hash| Self::short_id(salt, hash))
+            .collect();
+        Message::MempoolDigest(Box::new(Self {
+            salt,
+            short_ids,
+        }))
+    }
+
+    pub fn short_id(salt: u64, hash: &Blake2bHash) -> u64 {
+        let mut hasher = Blake2bHasher::default();
+        hasher.write_all(&salt.to_be_bytes()).unwrap();
+        hasher.write_all(hash.as_bytes()).unwrap();
+        let digest = hasher.finish();
+        let mut short_id = [0u8; 8];
+        short_id.copy_from_slice(&digest.as_bytes()[..8]);
+        u64::from_be_bytes(short_id)
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum RejectMessageCode {
@@ -1,4 +1,5 @@
 use beserial::{Deserialize, Serialize};
+use nimiq_hash::Blake2bHash;
 use nimiq_messages::*;
 
 const VERSION_MESSAGE: &str = "42042042000000010ee4e19ae300000001040000000400000167aaa7c40d02a84eaf654fe5f3b0bb45d0dd9a70c78fc24d134f5e302aa8270ea107752a6b860053e4c4966637a7de44500e8df82d7b541f578ab25a9e147fed9066361081826337f5511fa27762ecd0e328488e48bcbc4c6e2ded7b552039832768e4f137d809096c6f63616c686f737420fb264aaf8a4f9828a76c550635da078eb466306a189fcc03710bee9f649c869d12c6efcae1d34d135ff562bd75a62ffbcaab81f578ad23da8a02ccf59c7f8b6baa97fabe9dbd9db0acb5e1539bf3155ca1c9565f3363c5c8f1e1cc5b99ba3902c921636f72652d6a732f312e342e3120286e6f64656a733b204c696e75782078363429";
@@ -116,6 +117,22 @@ fn parse_mempool_message() {
     match message { Message::Mempool => assert!(true), _ => assert!(false) };
 }
 
+#[test]
+fn mempool_digest_message_roundtrip() {
+    let hashes = vec![Blake2bHash::from([1u8; 32]), Blake2bHash::from([2u8; 32])];
+    let message = MempoolDigestMessage::new(hashes.iter());
+    let vec = message.serialize_to_vec();
+    let message: Message = Deserialize::deserialize(&mut &vec[..]).unwrap();
+    match message {
+        Message::MempoolDigest(digest) => {
+            assert_eq!(digest.short_ids.len(), 2);
+            assert_eq!(digest.short_ids[0], MempoolDigestMessage::short_id(digest.salt, &hashes[0]));
+            assert_eq!(digest.short_ids[1], MempoolDigestMessage::short_id(digest.salt, &hashes[1]));
+        },
+        _ => assert!(false),
+    };
+}
+
 #[test]
 fn parse_reject_message() {
     let vec = ::hex::decode(REJECT_MESSAGE).unwrap();
@@ -1,10 +1,17 @@
+use std::io::Error as IoError;
+
 use failure::Fail;
 use hyper::Error as HyperError;
+use openssl::error::ErrorStack as TlsError;
 
 #[derive(Fail, Debug)]
 pub enum Error {
     #[fail(display = "{}", _0)]
     HyperError(#[cause] HyperError),
+    #[fail(display = "{}", _0)]
+    IoError(#[cause] IoError),
+    #[fail(display = "TLS certificate, key or client CA could not be loaded: {}", _0)]
+    TlsError(#[cause] TlsError),
 }
 
 impl From<HyperError> for Error {
@@ -12,3 +19,15 @@ impl From<HyperError> for Error {
         Error::HyperError(e)
     }
 }
+
+impl From<IoError> for Error {
+    fn from(e: IoError) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl From<TlsError> for Error {
+    fn from(e: TlsError) -> Self {
+        Error::TlsError(e)
+    }
+}
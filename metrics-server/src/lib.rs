@@ -4,23 +4,36 @@ extern crate nimiq_blockchain as blockchain;
 extern crate nimiq_blockchain_albatross as blockchain_albatross;
 extern crate nimiq_blockchain_base as blockchain_base;
 extern crate nimiq_consensus as consensus;
+extern crate nimiq_database as database;
+extern crate nimiq_keys as keys;
 extern crate nimiq_mempool as mempool;
 extern crate nimiq_network as network;
+extern crate nimiq_network_primitives as network_primitives;
 extern crate nimiq_block as block;
 extern crate nimiq_block_albatross as block_albatross;
+extern crate nimiq_utils as utils;
 
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
-use futures::{future::Future};
-use hyper::Server;
+use futures::{future::Future, stream::Stream};
+use hyper::server::conn::{AddrIncoming, Http};
 
 use consensus::{Consensus, ConsensusProtocol};
+use keys::SecretBytes;
+use network_primitives::address::{NetAddress, NetSubnet};
+use network_primitives::heartbeat::ValidatorLiveness;
+use utils::tls::{self, tls_acceptor};
 
 use crate::error::Error;
-use crate::metrics::mempool::MempoolMetrics;
+use crate::metrics::database::DatabaseMetrics;
+use crate::metrics::mempool::{MempoolMetrics, TxRelayMetrics};
 use crate::metrics::network::NetworkMetrics;
+use crate::metrics::rate_limit::RateLimitMetrics;
+use crate::metrics::sync::SyncMetrics;
+use crate::metrics::validator::ValidatorMetrics;
 pub use crate::metrics::chain::{AbstractChainMetrics, NimiqChainMetrics, AlbatrossChainMetrics};
+pub use utils::tls::TlsConfig;
 
 macro_rules! attributes {
     // Empty attributes.
@@ -59,20 +72,84 @@ pub mod server;
 pub mod metrics;
 pub mod error;
 
-pub fn metrics_server<P, CM>(consensus: Arc<Consensus<P>>, ip: IpAddr, port: u16, password: Option<String>) -> Result<Box<dyn Future<Item=(), Error=()> + Send + Sync>, Error>
+/// Access control of the metrics server.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsServerConfig {
+    /// Username for basic authentication. Defaults to `metrics`.
+    pub username: Option<String>,
+    /// If set, clients must authenticate with this password.
+    pub password: Option<SecretBytes>,
+    /// Only clients from these subnets may connect. All clients may connect if this is empty.
+    pub allowip: Vec<NetSubnet>,
+    /// Terminate TLS instead of serving plain HTTP.
+    pub tls: Option<TlsConfig>,
+}
+
+impl MetricsServerConfig {
+    fn allows(&self, ip: IpAddr) -> bool {
+        let address = NetAddress::from(ip);
+        self.allowip.is_empty() || self.allowip.iter().any(|subnet| subnet.contains(&address))
+    }
+}
+
+/// `validator_liveness` is only given for validators, which track the heartbeats of the other
+/// validators.
+pub fn metrics_server<P, CM>(consensus: Arc<Consensus<P>>, validator_liveness: Option<Arc<ValidatorLiveness>>, ip: IpAddr, port: u16, config: MetricsServerConfig) -> Result<Box<dyn Future<Item=(), Error=()> + Send + Sync>, Error>
     where P: ConsensusProtocol + 'static,
           CM: AbstractChainMetrics<P> + server::Metrics + 'static
 {
-    Ok(Box::new(Server::try_bind(&SocketAddr::new(ip, port))?
-        .serve(move || {
-            server::MetricsServer::new(
-                vec![
-                    Arc::new(CM::new(consensus.blockchain.clone())),
-                    Arc::new(MempoolMetrics::new(consensus.mempool.clone())),
-                    Arc::new(NetworkMetrics::new(consensus.network.clone()))
-                ],
-                attributes!{ "peer" => consensus.network.network_config.peer_address() },
+    let acceptor = match config.tls {
+        Some(ref tls) => Some(Arc::new(tls_acceptor(tls)?)),
+        None => None,
+    };
+    let username = config.username.clone().unwrap_or_else(|| "metrics".to_string());
+    let password = config.password.clone();
+    let new_service = move || {
+        let mut metrics: Vec<Arc<dyn server::Metrics>> = vec![
+            Arc::new(CM::new(consensus.blockchain.clone())),
+            Arc::new(MempoolMetrics::new(consensus.mempool.clone())),
+            Arc::new(TxRelayMetrics::new(consensus.tx_relay.clone())),
+            Arc::new(NetworkMetrics::new(consensus.network.clone())),
+            Arc::new(SyncMetrics::new(consensus.blockchain.clone(), consensus.sync_throttle.clone())),
+            Arc::new(DatabaseMetrics::new()),
+            Arc::new(RateLimitMetrics::new()),
+        ];
+        if let Some(ref liveness) = validator_liveness {
+            metrics.push(Arc::new(ValidatorMetrics::new(Arc::clone(liveness))));
+        }
+
+        server::MetricsServer::new(
+            metrics,
+            attributes!{ "peer" => consensus.network.network_config.peer_address() },
+            username.clone(),
             password.clone())
-        })
-        .map_err(|e| error!("Metrics server failed: {}", e) ))) // as Box<dyn Future<Item=(), Error=()> + Send + Sync>
+    };
+
+    let http = Http::new();
+    // Like hyper's `Server`, the incoming connections back off on errors like EMFILE instead of
+    // failing.
+    Ok(Box::new(AddrIncoming::bind(&SocketAddr::new(ip, port))?
+        .map_err(|e| error!("Metrics server failed: {}", e))
+        .for_each(move |socket| {
+            let client = socket.remote_addr().ip();
+            if !config.allows(client) {
+                debug!("Metrics server rejected connection from {}", client);
+                return Ok(());
+            }
+
+            let service = new_service();
+            let http = http.clone();
+            match acceptor {
+                // Handshake in a separate task, such that a slow client doesn't block the others.
+                Some(ref acceptor) => tokio::spawn(tls::accept(acceptor, socket, tls::HANDSHAKE_TIMEOUT)
+                    .map_err(|e| debug!("TLS handshake with metrics client failed: {}", e))
+                    .and_then(move |stream| {
+                        http.serve_connection(stream, service)
+                            .map_err(|e| debug!("Metrics connection failed: {}", e))
+                    })),
+                None => tokio::spawn(http.serve_connection(socket, service)
+                    .map_err(|e| debug!("Metrics connection failed: {}", e))),
+            };
+            Ok(())
+        })))
 }
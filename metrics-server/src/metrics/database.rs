@@ -0,0 +1,57 @@
+use std::io;
+
+use database::metrics::{DATABASE_METRICS, Histogram};
+
+use crate::server;
+use crate::server::SerializationType;
+
+/// Metrics of the database transactions of this process.
+#[derive(Default)]
+pub struct DatabaseMetrics;
+
+impl DatabaseMetrics {
+    pub fn new() -> Self {
+        DatabaseMetrics
+    }
+
+    /// Serializes `histogram` in the Prometheus histogram format. Observed values are divided by
+    /// `scale`, e.g. to report microseconds as seconds.
+    fn histogram(serializer: &mut server::MetricsSerializer<SerializationType>, key: &str, histogram: &Histogram, scale: f64) -> Result<(), io::Error> {
+        let count = histogram.count();
+        for (bound, observations) in histogram.cumulative_buckets() {
+            serializer.metric_with_attributes(
+                format!("{}_bucket", key),
+                observations,
+                attributes!{"le" => bound as f64 / scale}
+            )?;
+        }
+        serializer.metric_with_attributes(format!("{}_bucket", key), count, attributes!{"le" => "+Inf"})?;
+        serializer.metric(format!("{}_sum", key), histogram.sum() as f64 / scale)?;
+        serializer.metric(format!("{}_count", key), count)?;
+        Ok(())
+    }
+}
+
+impl server::Metrics for DatabaseMetrics {
+    fn metrics(&self, serializer: &mut server::MetricsSerializer<SerializationType>) -> Result<(), io::Error> {
+        Self::histogram(serializer, "database_read_transaction_seconds", &DATABASE_METRICS.read_transaction_duration, 1_000_000f64)?;
+        Self::histogram(serializer, "database_write_transaction_seconds", &DATABASE_METRICS.write_transaction_duration, 1_000_000f64)?;
+        Self::histogram(serializer, "database_commit_bytes", &DATABASE_METRICS.commit_size, 1f64)?;
+        serializer.metric("database_transaction_aborts", DATABASE_METRICS.abort_count())?;
+
+        for (name, counts) in DATABASE_METRICS.database_counts() {
+            serializer.metric_with_attributes(
+                "database_operations",
+                counts.reads,
+                attributes!{"database" => &name, "kind" => "read"}
+            )?;
+            serializer.metric_with_attributes(
+                "database_operations",
+                counts.writes,
+                attributes!{"database" => &name, "kind" => "write"}
+            )?;
+        }
+
+        Ok(())
+    }
+}
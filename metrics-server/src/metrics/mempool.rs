@@ -3,6 +3,7 @@ use std::sync::Arc;
 
 use beserial::Serialize;
 use blockchain_base::AbstractBlockchain;
+use consensus::tx_relay::{TxRejection, TxRelayMonitor};
 use mempool::{Mempool, SIZE_MAX};
 
 use crate::server;
@@ -47,3 +48,45 @@ impl<B: AbstractBlockchain<'static> + 'static> server::Metrics for MempoolMetric
         Ok(())
     }
 }
+
+pub struct TxRelayMetrics {
+    tx_relay: Arc<TxRelayMonitor>,
+}
+
+impl TxRelayMetrics {
+    pub fn new(tx_relay: Arc<TxRelayMonitor>) -> Self {
+        TxRelayMetrics {
+            tx_relay,
+        }
+    }
+}
+
+impl server::Metrics for TxRelayMetrics {
+    fn metrics(&self, serializer: &mut server::MetricsSerializer<SerializationType>) -> Result<(), io::Error> {
+        let rejections = [
+            (TxRejection::Invalid, "invalid"),
+            (TxRejection::Duplicate, "duplicate"),
+            (TxRejection::BelowFee, "below_fee"),
+            (TxRejection::OverBudget, "over_budget"),
+        ];
+        for (rejection, reason) in rejections.iter() {
+            serializer.metric_with_attributes(
+                "mempool_relay_rejected_transactions",
+                self.tx_relay.rejections(*rejection),
+                attributes!{"reason" => *reason}
+            )?;
+        }
+        serializer.metric_with_attributes(
+            "mempool_relay_punished_peers",
+            self.tx_relay.disconnected_peers(),
+            attributes!{"action" => "disconnect"}
+        )?;
+        serializer.metric_with_attributes(
+            "mempool_relay_punished_peers",
+            self.tx_relay.banned_peers(),
+            attributes!{"action" => "ban"}
+        )?;
+
+        Ok(())
+    }
+}
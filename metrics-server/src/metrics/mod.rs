@@ -1,3 +1,7 @@
 pub(crate) mod chain;
+pub(crate) mod database;
 pub(crate) mod mempool;
 pub(crate) mod network;
+pub(crate) mod rate_limit;
+pub(crate) mod sync;
+pub(crate) mod validator;
@@ -40,6 +40,22 @@ impl<B: AbstractBlockchain<'static> + 'static> server::Metrics for NetworkMetric
             )?;
         }
 
+        let version_statistics = self.network.connections.version_statistics();
+        for (version, count) in version_statistics.versions() {
+            serializer.metric_with_attributes(
+                "network_peer_versions",
+                count,
+                attributes!{"version" => version}
+            )?;
+        }
+        for (user_agent, count) in version_statistics.user_agents() {
+            serializer.metric_with_attributes(
+                "network_peer_user_agents",
+                count,
+                attributes!{"user_agent" => user_agent.replace('"', "\\\"")}
+            )?;
+        }
+
         let num_addresses = self.network.addresses.known_addresses_count();
         let num_ws_addresses = self.network.addresses.known_ws_addresses_count();
         let num_wss_addresses = self.network.addresses.known_wss_addresses_count();
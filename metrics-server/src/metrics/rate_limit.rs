@@ -0,0 +1,26 @@
+use std::io;
+
+use utils::rate_limit::rate_limit_counters;
+
+use crate::server;
+use crate::server::SerializationType;
+
+/// Actions allowed and denied by the rate limiters of this process, by the names of the limiters.
+#[derive(Default)]
+pub struct RateLimitMetrics;
+
+impl RateLimitMetrics {
+    pub fn new() -> Self {
+        RateLimitMetrics
+    }
+}
+
+impl server::Metrics for RateLimitMetrics {
+    fn metrics(&self, serializer: &mut server::MetricsSerializer<SerializationType>) -> Result<(), io::Error> {
+        for (name, counters) in rate_limit_counters() {
+            serializer.metric_with_attributes("rate_limit_allowed", counters.allowed(), attributes!{"limit" => name})?;
+            serializer.metric_with_attributes("rate_limit_limited", counters.limited(), attributes!{"limit" => name})?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use std::io;
+use std::sync::Arc;
+
+use blockchain_base::AbstractBlockchain;
+use consensus::sync_throttle::SyncThrottle;
+
+use crate::server;
+use crate::server::SerializationType;
+
+pub struct SyncMetrics<B: AbstractBlockchain<'static> + 'static> {
+    blockchain: Arc<B>,
+    sync_throttle: Arc<SyncThrottle>,
+}
+
+impl<B: AbstractBlockchain<'static> + 'static> SyncMetrics<B> {
+    pub fn new(blockchain: Arc<B>, sync_throttle: Arc<SyncThrottle>) -> Self {
+        SyncMetrics {
+            blockchain,
+            sync_throttle,
+        }
+    }
+}
+
+impl<B: AbstractBlockchain<'static> + 'static> server::Metrics for SyncMetrics<B> {
+    fn metrics(&self, serializer: &mut server::MetricsSerializer<SerializationType>) -> Result<(), io::Error> {
+        serializer.metric("sync_rate_limit", self.sync_throttle.blocks_per_second().unwrap_or(0))?;
+
+        match self.sync_throttle.progress(self.blockchain.head_height()) {
+            Some(progress) => {
+                serializer.metric("sync_active", 1)?;
+                serializer.metric("sync_starting_height", progress.starting_block)?;
+                serializer.metric("sync_highest_height", progress.highest_block)?;
+                serializer.metric("sync_blocks_per_second", progress.blocks_per_second)?;
+                if let Some(eta) = progress.eta {
+                    serializer.metric("sync_eta_seconds", eta.as_secs())?;
+                }
+            },
+            None => serializer.metric("sync_active", 0)?,
+        }
+
+        Ok(())
+    }
+}
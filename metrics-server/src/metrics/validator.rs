@@ -0,0 +1,40 @@
+use std::io;
+use std::sync::Arc;
+
+use network_primitives::heartbeat::ValidatorLiveness;
+
+use crate::server;
+use crate::server::SerializationType;
+
+/// Liveness of the validators of the current epoch, as seen from their heartbeats.
+pub struct ValidatorMetrics {
+    liveness: Arc<ValidatorLiveness>,
+}
+
+impl ValidatorMetrics {
+    pub fn new(liveness: Arc<ValidatorLiveness>) -> Self {
+        ValidatorMetrics {
+            liveness,
+        }
+    }
+}
+
+impl server::Metrics for ValidatorMetrics {
+    fn metrics(&self, serializer: &mut server::MetricsSerializer<SerializationType>) -> Result<(), io::Error> {
+        let validators = self.liveness.validators();
+        let offline = validators.iter().filter(|liveness| liveness.offline).count();
+
+        serializer.metric("validator_count", validators.len())?;
+        serializer.metric("validator_offline_count", offline)?;
+        for (pk_idx, liveness) in validators.iter().enumerate() {
+            serializer.metric_with_attributes("validator_online", if liveness.offline { 0 } else { 1 },
+                attributes!{"validator" => pk_idx})?;
+            if let Some(last_heartbeat) = liveness.last_heartbeat {
+                serializer.metric_with_attributes("validator_heartbeat_age_seconds", last_heartbeat.as_secs(),
+                    attributes!{"validator" => pk_idx})?;
+            }
+        }
+
+        Ok(())
+    }
+}
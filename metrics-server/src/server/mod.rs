@@ -6,7 +6,9 @@ use futures::{future, Future, stream, stream::Stream};
 use hyper::{Body, Request, Response, StatusCode};
 use hyper::Chunk;
 use hyper::header::{AUTHORIZATION, WWW_AUTHENTICATE, LOCATION};
-use base64::encode;
+use base64::{encode_config_buf, STANDARD};
+
+use keys::SecretBytes;
 
 use crate::server::attributes::{CachedAttributes, VecAttributes};
 use futures::IntoFuture;
@@ -68,15 +70,17 @@ impl std::fmt::Display for Never {
 pub struct MetricsServer {
     metrics: Vec<Arc<dyn Metrics>>,
     common_attributes: CachedAttributes,
-    password: Option<String>,
+    username: String,
+    password: Option<SecretBytes>,
 }
 
 impl MetricsServer {
     #[inline]
-    pub fn new<A: Into<CachedAttributes>>(metrics: Vec<Arc<dyn Metrics>>, common_attributes: A, password: Option<String>) -> Self{
+    pub fn new<A: Into<CachedAttributes>>(metrics: Vec<Arc<dyn Metrics>>, common_attributes: A, username: String, password: Option<SecretBytes>) -> Self{
         MetricsServer {
             metrics,
             common_attributes: common_attributes.into(),
+            username,
             password,
         }
     }
@@ -101,12 +105,17 @@ impl MetricsServer {
     }
 }
 
-fn check_auth(req: &Request<Body>, password: &Option<String>) -> bool {
+fn check_auth(req: &Request<Body>, username: &str, password: &Option<SecretBytes>) -> bool {
     match (password, req.headers().get(AUTHORIZATION).and_then(|header| header.to_str().ok())) {
         (None, _) => true,
         (_, None) => false,
         (Some(ref password), Some(authorization)) => {
-            authorization == format!("Basic {}", encode(&format!("metrics:{}", password)))
+            let credentials = SecretBytes::from([username.as_bytes(), b":", password.as_bytes()].concat());
+            // Reserve the final size, so that no copy of the password is left behind by growing.
+            let mut expected = String::with_capacity(6 + (credentials.len() + 2) / 3 * 4);
+            expected.push_str("Basic ");
+            encode_config_buf(credentials.as_bytes(), STANDARD, &mut expected);
+            SecretBytes::from(expected).ct_eq(authorization.as_bytes())
         },
     }
 }
@@ -140,11 +149,11 @@ impl hyper::service::Service for MetricsServer {
         }
 
         // Check authentication.
-        if !check_auth(&req, &self.password) {
+        if !check_auth(&req, &self.username, &self.password) {
             return Box::new(future::ok(
                 Response::builder()
                     .status(StatusCode::UNAUTHORIZED)
-                    .header(WWW_AUTHENTICATE, "Basic realm=\"Use username metrics and user-defined password to access metrics.\" charset=\"UTF-8\"")
+                    .header(WWW_AUTHENTICATE, "Basic realm=\"Use the configured username and password to access metrics.\" charset=\"UTF-8\"")
                     .body(Body::empty())
                     .unwrap()
             ));
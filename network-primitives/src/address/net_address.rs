@@ -140,6 +140,15 @@ impl fmt::Display for NetAddress {
     }
 }
 
+impl From<IpAddr> for NetAddress {
+    fn from(addr: IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(addr) => NetAddress::IPv4(addr),
+            IpAddr::V6(addr) => NetAddress::IPv6(addr),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Fail)]
 #[fail(display = "{}", _0)]
 pub struct NetAddressParseError(#[cause] AddrParseError);
@@ -149,9 +158,65 @@ impl FromStr for NetAddress {
 
     fn from_str(s: &str) -> Result<Self, <Self as FromStr>::Err> {
         let addr: IpAddr = s.parse().map_err(NetAddressParseError)?;
-        match addr {
-            IpAddr::V4(addr) => Ok(NetAddress::IPv4(addr)),
-            IpAddr::V6(addr) => Ok(NetAddress::IPv6(addr)),
-        }
+        Ok(NetAddress::from(addr))
+    }
+}
+/// A subnet in CIDR notation, e.g. `192.168.0.0/24`. A plain address is parsed as a subnet that only
+/// contains itself.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct NetSubnet {
+    address: NetAddress,
+    bit_count: u8,
+}
+
+impl NetSubnet {
+    /// IPv4-mapped IPv6 addresses, which dual-stack sockets report for IPv4 peers, are matched as
+    /// IPv4 addresses.
+    pub fn contains(&self, address: &NetAddress) -> bool {
+        let address = match address {
+            NetAddress::IPv6(ipv6) if ipv6.segments()[..6] == [0, 0, 0, 0, 0, 0xffff] => {
+                NetAddress::IPv4(ipv6.to_ipv4().unwrap())
+            },
+            address => *address,
+        };
+        address.get_type() == self.address.get_type() && address.subnet(self.bit_count) == self.address
+    }
+}
+
+impl fmt::Display for NetSubnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.bit_count)
+    }
+}
+
+#[derive(Debug, Clone, Fail)]
+pub enum NetSubnetParseError {
+    #[fail(display = "{}", _0)]
+    InvalidAddress(#[cause] NetAddressParseError),
+    #[fail(display = "Invalid prefix length: {}", _0)]
+    InvalidBitCount(String),
+}
+
+impl FromStr for NetSubnet {
+    type Err = NetSubnetParseError;
+
+    fn from_str(s: &str) -> Result<Self, <Self as FromStr>::Err> {
+        let mut parts = s.splitn(2, '/');
+        let address = NetAddress::from_str(parts.next().unwrap_or(""))
+            .map_err(NetSubnetParseError::InvalidAddress)?;
+        let max_bit_count = match address {
+            NetAddress::IPv4(_) => 32,
+            _ => 128,
+        };
+        let bit_count = match parts.next() {
+            Some(bits) => bits.parse::<u8>().ok()
+                .filter(|&bit_count| bit_count <= max_bit_count)
+                .ok_or_else(|| NetSubnetParseError::InvalidBitCount(bits.to_string()))?,
+            None => max_bit_count,
+        };
+        Ok(NetSubnet {
+            address: address.subnet(bit_count),
+            bit_count,
+        })
     }
-}
\ No newline at end of file
+}
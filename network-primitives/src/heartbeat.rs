@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
+
+use beserial::{Serialize, Deserialize};
+use block_albatross::signed::{SignedMessage, PREFIX_HEARTBEAT, Message};
+use bls::bls12_381::CompressedPublicKey;
+use bls::bls12_381::lazy::LazyPublicKey;
+use hash::SerializeContent;
+
+
+
+/// A heartbeat that active validators periodically send to each other, to show that they're online
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, SerializeContent)]
+pub struct Heartbeat {
+    /// The number of the next block (i.e. the block number the validator is at + 1). Heartbeats
+    /// are only accepted for the current epoch.
+    pub block_number: u32,
+
+    /// The (network) time at which the heartbeat was sent. Heartbeats that are not newer than the
+    /// last one of the same validator are ignored, so they can't be replayed.
+    pub timestamp: u64,
+}
+
+impl Message for Heartbeat {
+    const PREFIX: u8 = PREFIX_HEARTBEAT;
+}
+
+/// The signed version of a Heartbeat. `signer_idx` is the index of the validator in the current
+/// epoch (a.k.a. `pk_idx`).
+pub type SignedHeartbeat = SignedMessage<Heartbeat>;
+
+
+/// Liveness of a single validator, as seen by this node
+#[derive(Clone, Debug)]
+pub struct Liveness {
+    pub public_key: CompressedPublicKey,
+
+    /// Time since the last heartbeat, or `None` if we didn't receive one in this epoch
+    pub last_heartbeat: Option<Duration>,
+
+    /// Whether the validator is considered offline (see `ValidatorLiveness::is_offline`)
+    pub offline: bool,
+}
+
+struct LivenessEntry {
+    /// Uncompressed on the first heartbeat, such that this is only done once per epoch
+    public_key: LazyPublicKey,
+    last_timestamp: u64,
+    last_received: Option<Instant>,
+}
+
+#[derive(Default)]
+struct LivenessState {
+    /// When we started tracking the current epoch
+    since: Option<Instant>,
+
+    /// Entries of the validators of the current epoch, indexed by `pk_idx`
+    validators: Vec<LivenessEntry>,
+}
+
+/// Tracks the heartbeats of the validators of the current epoch
+#[derive(Default)]
+pub struct ValidatorLiveness {
+    state: RwLock<LivenessState>,
+}
+
+impl ValidatorLiveness {
+    /// A validator is considered offline, if we didn't receive a heartbeat for this long
+    pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets all heartbeats and starts tracking `validators`, which must be ordered by `pk_idx`
+    pub fn reset(&self, validators: Vec<CompressedPublicKey>) {
+        let mut state = self.state.write();
+        state.since = Some(Instant::now());
+        state.validators = validators.into_iter()
+            .map(|public_key| LivenessEntry {
+                public_key: LazyPublicKey::from(public_key),
+                last_timestamp: 0,
+                last_received: None,
+            })
+            .collect();
+    }
+
+    /// Verifies a heartbeat received from another validator and records it. Returns `false`, if
+    /// the heartbeat is invalid or not newer than the last one.
+    ///
+    /// Heartbeats of unknown validators and replayed heartbeats are dropped before the signature
+    /// is verified.
+    pub fn verify_heartbeat(&self, heartbeat: &SignedHeartbeat) -> bool {
+        {
+            let state = self.state.read();
+            let entry = match state.validators.get(heartbeat.signer_idx as usize) {
+                Some(entry) if entry.last_timestamp < heartbeat.message.timestamp => entry,
+                _ => return false,
+            };
+
+            let public_key = match entry.public_key.uncompress() {
+                Some(public_key) => public_key,
+                None => return false,
+            };
+            if !heartbeat.verify(&public_key) {
+                return false;
+            }
+        }
+
+        // Another copy of the heartbeat might have been recorded in the meantime, so this checks
+        // the timestamp again.
+        self.on_heartbeat(heartbeat.signer_idx, &heartbeat.message)
+    }
+
+    /// Records a heartbeat (which must have been verified) of the validator with `pk_idx`.
+    /// Returns `false`, if the heartbeat is not newer than the last one.
+    pub fn on_heartbeat(&self, pk_idx: u16, heartbeat: &Heartbeat) -> bool {
+        let mut state = self.state.write();
+        match state.validators.get_mut(pk_idx as usize) {
+            Some(entry) if entry.last_timestamp < heartbeat.timestamp => {
+                entry.last_timestamp = heartbeat.timestamp;
+                entry.last_received = Some(Instant::now());
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the validator with `pk_idx` is known to be offline, i.e. we didn't receive a
+    /// heartbeat from it within `TIMEOUT`, while we've been tracking this epoch for longer than
+    /// that.
+    pub fn is_offline(&self, pk_idx: u16) -> bool {
+        let state = self.state.read();
+        match state.validators.get(pk_idx as usize) {
+            Some(entry) => Self::entry_offline(&state, entry),
+            None => false,
+        }
+    }
+
+    /// Returns the liveness of all validators of the current epoch, ordered by `pk_idx`
+    pub fn validators(&self) -> Vec<Liveness> {
+        let state = self.state.read();
+        state.validators.iter()
+            .map(|entry| Liveness {
+                public_key: entry.public_key.compressed().clone(),
+                last_heartbeat: entry.last_received.map(|received| received.elapsed()),
+                offline: Self::entry_offline(&state, entry),
+            })
+            .collect()
+    }
+
+    fn entry_offline(state: &LivenessState, entry: &LivenessEntry) -> bool {
+        entry.last_received.or(state.since)
+            .map_or(false, |last| last.elapsed() > Self::TIMEOUT)
+    }
+}
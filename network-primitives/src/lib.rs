@@ -38,6 +38,8 @@ pub mod subscription;
 pub mod time;
 #[cfg(feature = "validator")]
 pub mod validator_info;
+#[cfg(feature = "validator")]
+pub mod heartbeat;
 
 pub const IPV4_SUBNET_MASK: u8 = 24;
 pub const IPV6_SUBNET_MASK: u8 = 96;
@@ -9,6 +9,8 @@ bitflags! {
         const FULL  = 0b0000_0100;
         // Node supports validator protocol
         const VALIDATOR  = 0b0100_0000_0000;
+        // Node understands diff-encoded macro headers (MacroHeaderDiff messages)
+        const MACRO_HEADER_DIFFS = 0b1000_0000_0000;
     }
 }
 
@@ -26,6 +28,8 @@ impl ServiceFlags {
     }
 
     pub fn is_validator(self) -> bool { self.contains(ServiceFlags::VALIDATOR) }
+
+    pub fn supports_macro_header_diffs(self) -> bool { self.contains(ServiceFlags::MACRO_HEADER_DIFFS) }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
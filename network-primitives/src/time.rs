@@ -6,7 +6,10 @@ use atomic::Ordering;
 
 #[derive(Debug, Default)]
 pub struct NetworkTime {
-    offset: Atomic<i64>
+    offset: Atomic<i64>,
+    /// Offset set manually (e.g. by tests on dev networks), which is added to the offset
+    /// negotiated with the peers.
+    manual_offset: Atomic<i64>,
 }
 
 impl NetworkTime {
@@ -16,7 +19,8 @@ impl NetworkTime {
 
     pub fn with_offset(offset: i64) -> Self {
         NetworkTime {
-            offset: Atomic::new(offset)
+            offset: Atomic::new(offset),
+            manual_offset: Atomic::new(0),
         }
     }
 
@@ -24,8 +28,18 @@ impl NetworkTime {
         self.offset.store(new_offset, Ordering::Relaxed);
     }
 
+    /// Sets an offset that is added to the network offset. Unlike the network offset, it isn't
+    /// overwritten when the peers' time offsets change.
+    pub fn set_manual_offset(&self, new_offset: i64) {
+        self.manual_offset.store(new_offset, Ordering::Relaxed);
+    }
+
+    pub fn manual_offset(&self) -> i64 {
+        self.manual_offset.load(Ordering::Relaxed)
+    }
+
     pub fn now(&self) -> u64 {
-        let offset = self.offset.load(Ordering::Relaxed);
+        let offset = self.offset.load(Ordering::Relaxed) + self.manual_offset.load(Ordering::Relaxed);
         let abs_offset = offset.abs() as u64;
         let system_time = if offset > 0 {
             SystemTime::now() + Duration::from_millis(abs_offset)
@@ -1,6 +1,12 @@
-pub const CODE: u32 = 1;
+pub const CODE: u32 = 2;
+
+/// Oldest protocol version we can talk to.
+pub const MIN_CODE: u32 = 1;
+
+/// First protocol version that supports `MempoolDigest` messages.
+pub const MEMPOOL_DIGEST: u32 = 2;
 
 pub fn is_compatible(code: u32) -> bool {
     // Allow future, backwards-compatible versions.
-    code >= CODE
+    code >= MIN_CODE
 }
@@ -1,2 +1,3 @@
+mod net_subnet;
 mod peer_address;
 mod peer_uri;
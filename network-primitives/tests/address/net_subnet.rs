@@ -0,0 +1,26 @@
+use std::str::FromStr;
+
+use network_primitives::address::{NetAddress, NetSubnet};
+
+#[test]
+fn test_subnet_contains() {
+    let subnet = NetSubnet::from_str("192.168.1.17/24").unwrap();
+    assert_eq!(subnet.to_string(), "192.168.1.0/24");
+    assert!(subnet.contains(&NetAddress::from_str("192.168.1.1").unwrap()));
+    assert!(!subnet.contains(&NetAddress::from_str("192.168.2.1").unwrap()));
+    assert!(subnet.contains(&NetAddress::from_str("::ffff:c0a8:101").unwrap()));
+    assert!(!subnet.contains(&NetAddress::from_str("::ffff:c0a8:201").unwrap()));
+    // Only IPv4-mapped addresses are matched as IPv4.
+    assert!(!subnet.contains(&NetAddress::from_str("::c0a8:101").unwrap()));
+
+    let subnet = NetSubnet::from_str("fd00::1").unwrap();
+    assert!(subnet.contains(&NetAddress::from_str("fd00::1").unwrap()));
+    assert!(!subnet.contains(&NetAddress::from_str("fd00::2").unwrap()));
+}
+
+#[test]
+fn test_parse_invalid_subnet() {
+    assert!(NetSubnet::from_str("192.168.1.0/33").is_err());
+    assert!(NetSubnet::from_str("192.168.1.0/").is_err());
+    assert!(NetSubnet::from_str("my.domain/24").is_err());
+}
@@ -0,0 +1,68 @@
+extern crate nimiq_bls as bls;
+
+use beserial::Deserialize;
+use bls::bls12_381::{CompressedPublicKey, KeyPair};
+use network_primitives::heartbeat::{Heartbeat, SignedHeartbeat, ValidatorLiveness};
+
+fn public_key(i: u8) -> CompressedPublicKey {
+    let mut bytes = [0u8; CompressedPublicKey::SIZE];
+    bytes[0] = i;
+    CompressedPublicKey::deserialize_from_vec(&bytes[..]).unwrap()
+}
+
+#[test]
+fn it_tracks_heartbeats() {
+    let liveness = ValidatorLiveness::new();
+    liveness.reset(vec![public_key(1), public_key(2)]);
+
+    let heartbeat = Heartbeat { block_number: 1, timestamp: 1000 };
+    assert!(liveness.on_heartbeat(0, &heartbeat));
+
+    // replayed and unknown validators are ignored
+    assert!(!liveness.on_heartbeat(0, &heartbeat));
+    assert!(!liveness.on_heartbeat(2, &heartbeat));
+
+    let validators = liveness.validators();
+    assert_eq!(validators.len(), 2);
+    assert!(validators[0].last_heartbeat.is_some());
+    assert!(validators[1].last_heartbeat.is_none());
+
+    // we only just started tracking, so nobody is known to be offline yet
+    assert!(!validators[1].offline);
+    assert!(!liveness.is_offline(1));
+
+    // a new epoch forgets all heartbeats
+    liveness.reset(vec![public_key(1)]);
+    assert!(liveness.validators()[0].last_heartbeat.is_none());
+    assert!(liveness.on_heartbeat(0, &heartbeat));
+}
+
+fn test_key_pair(first_byte: &str) -> KeyPair {
+    let raw_key = hex::decode(format!("{}480bdb948113a00dc9afbc83699944c23aa1005fa4f62c654517912adfa1cf", first_byte)).unwrap();
+    KeyPair::deserialize_from_vec(&raw_key).unwrap()
+}
+
+#[test]
+fn it_verifies_heartbeats_before_recording_them() {
+    let key_pair = test_key_pair("03");
+    let other_key_pair = test_key_pair("04");
+
+    let liveness = ValidatorLiveness::new();
+    liveness.reset(vec![key_pair.public.compress(), public_key(2)]);
+
+    let heartbeat = Heartbeat { block_number: 1, timestamp: 1000 };
+
+    // forged heartbeats and heartbeats of unknown validators are not recorded
+    assert!(!liveness.verify_heartbeat(&SignedHeartbeat::from_message(heartbeat.clone(), &other_key_pair.secret, 0)));
+    assert!(!liveness.verify_heartbeat(&SignedHeartbeat::from_message(heartbeat.clone(), &key_pair.secret, 2)));
+    assert!(liveness.validators()[0].last_heartbeat.is_none());
+
+    let signed = SignedHeartbeat::from_message(heartbeat.clone(), &key_pair.secret, 0);
+    assert!(liveness.verify_heartbeat(&signed));
+    assert!(liveness.validators()[0].last_heartbeat.is_some());
+
+    // replays are dropped
+    assert!(!liveness.verify_heartbeat(&signed));
+    let newer = Heartbeat { block_number: 1, timestamp: 1001 };
+    assert!(liveness.verify_heartbeat(&SignedHeartbeat::from_message(newer, &key_pair.secret, 0)));
+}
@@ -736,6 +736,18 @@ impl PeerAddressBook {
         self.seeded.load(Ordering::Acquire)
     }
 
+    /// Returns the known WebSocket addresses that aren't seeds and that we didn't fail to connect
+    /// to, e.g. to persist them. They can be restored with `add`, which drops those that have
+    /// become too old in the meantime.
+    pub fn export(&self) -> Vec<PeerAddress> {
+        let state = self.state.read();
+        state.info_by_address.values()
+            .filter(|info| info.state != PeerAddressState::Banned && info.state != PeerAddressState::Failed)
+            .filter(|info| !info.peer_address.is_seed() && (info.peer_address.protocol() == Protocol::Ws || info.peer_address.protocol() == Protocol::Wss))
+            .map(|info| PeerAddress::clone(&info.peer_address))
+            .collect()
+    }
+
     pub fn known_addresses_count(&self) -> usize { self.state.read().info_by_address.len() }
     pub fn known_ws_addresses_count(&self) -> usize { self.state.read().ws_addresses.len() }
     pub fn known_wss_addresses_count(&self) -> usize { self.state.read().wss_addresses.len() }
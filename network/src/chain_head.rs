@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use blockchain_base::AbstractBlockchain;
+use hash::Blake2bHash;
+use network_primitives::networks::{NetworkId, NetworkInfo};
+
+/// The state of the blockchain that the network needs, i.e. what is exchanged in the handshake.
+pub trait ChainHead: Send + Sync {
+    fn network_id(&self) -> NetworkId;
+
+    fn head_hash(&self) -> Blake2bHash;
+}
+
+impl<B: AbstractBlockchain<'static>> ChainHead for B {
+    fn network_id(&self) -> NetworkId {
+        AbstractBlockchain::network_id(self)
+    }
+
+    fn head_hash(&self) -> Blake2bHash {
+        AbstractBlockchain::head_hash(self)
+    }
+}
+
+/// A chain head that always is the genesis block, for nodes that run the network without a
+/// blockchain, e.g. seed nodes.
+pub struct GenesisHead {
+    network_id: NetworkId,
+    genesis_hash: Blake2bHash,
+}
+
+impl GenesisHead {
+    pub fn new(network_id: NetworkId) -> Arc<Self> {
+        let genesis_hash = NetworkInfo::from_network_id(network_id).genesis_hash().clone();
+        Arc::new(GenesisHead { network_id, genesis_hash })
+    }
+}
+
+impl ChainHead for GenesisHead {
+    fn network_id(&self) -> NetworkId {
+        self.network_id
+    }
+
+    fn head_hash(&self) -> Blake2bHash {
+        self.genesis_hash.clone()
+    }
+}
@@ -2,12 +2,15 @@ use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::{Arc, Weak};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use parking_lot::{ReentrantMutex, RwLock, RwLockReadGuard};
 
-use blockchain_base::AbstractBlockchain;
+use beserial::{Deserialize, Serialize};
+use crate::chain_head::ChainHead;
 use collections::SparseVec;
+use database::{Environment, ReadTransaction, TtlDatabase, WriteTransaction};
+use database::verify::RawBytes;
 use network_messages::SignalMessage;
 use network_primitives::address::net_address::{NetAddress, NetAddressType};
 use network_primitives::address::peer_address::PeerAddress;
@@ -45,7 +48,7 @@ macro_rules! update_checked {
 
 pub type ConnectionId = usize;
 
-pub struct ConnectionPoolState<B: AbstractBlockchain<'static> + 'static> {
+pub struct ConnectionPoolState<B: ChainHead + 'static> {
     connections: SparseVec<ConnectionInfo<B>>,
     connections_by_peer_address: HashMap<Arc<PeerAddress>, ConnectionId>,
     connections_by_net_address: HashMap<NetAddress, HashSet<ConnectionId>>,
@@ -71,9 +74,11 @@ pub struct ConnectionPoolState<B: AbstractBlockchain<'static> + 'static> {
     pub allow_inbound_exchange: bool,
 
     banned_ips: HashMap<NetAddress, SystemTime>,
+    /// Persists `banned_ips` across restarts, see `ConnectionPool::persist_bans`.
+    ban_store: Option<(&'static Environment, TtlDatabase<'static>)>,
 }
 
-impl<B: AbstractBlockchain<'static> + 'static> ConnectionPoolState<B> {
+impl<B: ChainHead + 'static> ConnectionPoolState<B> {
     pub fn connection_iter(&self) -> Vec<&ConnectionInfo<B>> {
         self.connections_by_peer_address.values().map(|connection_id| {
             self.connections.get(*connection_id).expect("Missing connection")
@@ -281,6 +286,12 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPoolState<B> {
             };
             let unban_time = SystemTime::now() + ConnectionPool::<B>::DEFAULT_BAN_TIME;
             self.banned_ips.insert(banned_address, unban_time);
+
+            if let Some((env, ref db)) = self.ban_store {
+                let mut txn = WriteTransaction::new(env);
+                db.put(&mut txn, &banned_address.serialize_to_vec()[..], &b""[..], ConnectionPool::<B>::DEFAULT_BAN_TIME);
+                txn.commit();
+            }
         }
     }
 
@@ -289,7 +300,8 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPoolState<B> {
         !net_address.is_pseudo() && self.banned_ips.contains_key(net_address)
     }
 
-    /// Called to regularly unban IPs.
+    /// Called to regularly unban IPs. Expired bans are removed from the database by the garbage
+    /// collector.
     fn check_unban_ips(&mut self) {
         let now = SystemTime::now();
         self.banned_ips.retain(|_net_address, unban_time| {
@@ -331,7 +343,7 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPoolState<B> {
     }
 }
 
-enum Connection<'a, B: AbstractBlockchain<'static> + 'static> {
+enum Connection<'a, B: ChainHead + 'static> {
     Id(ConnectionId),
     Info(&'a ConnectionInfo<B>),
 }
@@ -341,7 +353,7 @@ enum ConnectionPoolTimer {
     UnbanIps,
 }
 
-pub struct ConnectionPool<B: AbstractBlockchain<'static> + 'static> {
+pub struct ConnectionPool<B: ChainHead + 'static> {
     blockchain: Arc<B>,
     network_config: Arc<NetworkConfig>,
     addresses: Arc<PeerAddressBook>,
@@ -358,9 +370,10 @@ pub struct ConnectionPool<B: AbstractBlockchain<'static> + 'static> {
     self_weak: MutableOnce<Weak<ConnectionPool<B>>>,
 }
 
-impl<B: AbstractBlockchain<'static> + 'static> ConnectionPool<B> {
+impl<B: ChainHead + 'static> ConnectionPool<B> {
     const DEFAULT_BAN_TIME: Duration = Duration::from_secs(60 * 10); // seconds
     const UNBAN_IPS_INTERVAL: Duration = Duration::from_secs(60); // seconds
+    const BANNED_IPS_DB_NAME: &'static str = "BannedIps";
 
     /// Constructor.
     pub fn new(peer_address_book: Arc<PeerAddressBook>, network_config: Arc<NetworkConfig>, blockchain: Arc<B>) -> Result<Arc<Self>, Error> {
@@ -403,6 +416,7 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPool<B> {
                 allow_inbound_exchange: false,
 
                 banned_ips: HashMap::new(),
+                ban_store: None,
             }),
             change_lock: ReentrantMutex::new(()),
 
@@ -429,6 +443,49 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPool<B> {
         Ok(pool)
     }
 
+    /// Persists banned IPs in `env`, so that bans survive a restart. Bans that haven't expired yet
+    /// are loaded from it. Bans that expired while the node was down are removed, since nodes
+    /// without a garbage collector never remove them otherwise.
+    pub fn persist_bans(&self, env: &'static Environment) {
+        let db = TtlDatabase::open(env, Self::BANNED_IPS_DB_NAME.to_string());
+        let mut state = self.state.write();
+
+        let mut txn = WriteTransaction::new(env);
+        let purged = db.purge_expired(&mut txn);
+        txn.commit();
+        if purged > 0 {
+            debug!("Removed {} expired IP bans", purged);
+        }
+
+        let txn = ReadTransaction::new(env);
+        match db.unexpired::<RawBytes>(&txn) {
+            Ok(bans) => {
+                for (RawBytes(bytes), expires_at) in bans {
+                    match NetAddress::deserialize_from_vec(&bytes) {
+                        Ok(net_address) => {
+                            let unban_time = UNIX_EPOCH + Duration::from_millis(expires_at);
+                            state.banned_ips.insert(net_address, unban_time);
+                        },
+                        Err(e) => warn!("Ignoring malformed IP ban: {:?}", e),
+                    }
+                }
+            },
+            Err(e) => warn!("Failed to load IP bans: {}", e),
+        }
+        drop(txn);
+
+        state.ban_store = Some((env, db));
+    }
+
+    /// Removes the bans that expired from the database, if bans are persisted. Returns the number
+    /// of removed bans.
+    pub fn purge_expired_bans(&self, txn: &mut WriteTransaction) -> usize {
+        match self.state.read().ban_store {
+            Some((_, ref db)) => db.purge_expired(txn),
+            None => 0,
+        }
+    }
+
     /// Initialises necessary threads.
     pub fn initialize(&self) -> Result<(), Error> {
         // Start accepting incoming connections.
@@ -494,7 +551,7 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPool<B> {
     }
 
     /// Checks the validity of a connection from `on_connection`.
-    fn check_connection(state: &ConnectionPoolState<B>, connection_id: ConnectionId) -> bool {
+    fn check_connection(state: &ConnectionPoolState<B>, connection_id: ConnectionId, peer_count_max: usize) -> bool {
         let info = state.connections.get(connection_id).unwrap();
         let conn = info.network_connection();
         assert!(conn.is_some(), "Connection must be established");
@@ -531,7 +588,7 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPool<B> {
         // Reject peer if we have reached max peer count.
         // There are two exceptions to this: outbound connections
         // and inbound connections with inbound exchange set.
-        if state.peer_count() >= network_primitives::PEER_COUNT_MAX
+        if state.peer_count() >= peer_count_max
             && !conn.outbound()
             && !(conn.inbound() && state.allow_inbound_exchange) {
 
@@ -595,7 +652,7 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPool<B> {
                 arc.on_close(connection_id, ty.clone());
             });
 
-            if !Self::check_connection(&state, connection_id) {
+            if !Self::check_connection(&state, connection_id, self.network_config.peer_count_max()) {
                 return;
             }
 
@@ -700,7 +757,7 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPool<B> {
 
                 if network_connection.inbound() {
                     // Re-check allowInboundExchange as it might have changed.
-                    if state.peer_count() >= network_primitives::PEER_COUNT_MAX && !state.allow_inbound_exchange {
+                    if state.peer_count() >= self.network_config.peer_count_max() && !state.allow_inbound_exchange {
                         Self::close(info.network_connection(), CloseType::MaxPeerCountReached);
                         return;
                     }
@@ -776,7 +833,7 @@ impl<B: AbstractBlockchain<'static> + 'static> ConnectionPool<B> {
         // Handshake accepted.
 
         // Check if we need to recycle a connection.
-        if self.peer_count() >= network_primitives::PEER_COUNT_MAX {
+        if self.peer_count() >= self.network_config.peer_count_max() {
             // This will most likely lead to reentering the guard.
             self.notifier.read().notify(ConnectionPoolEvent::RecyclingRequest);
         }
@@ -9,7 +9,7 @@ use parking_lot::RwLock;
 use rand::{Rng, rngs::OsRng};
 
 use beserial::Serialize;
-use blockchain_base::AbstractBlockchain;
+use crate::chain_head::ChainHead;
 use network_messages::*;
 use network_primitives::address::peer_address::PeerAddress;
 use network_primitives::address::PeerId;
@@ -17,7 +17,7 @@ use network_primitives::networks::NetworkInfo;
 use network_primitives::protocol::Protocol;
 use network_primitives::version;
 use utils::observer::{Notifier, weak_listener, weak_passthru_listener};
-use utils::rate_limit::RateLimit;
+use utils::rate_limit::RateLimiter;
 use utils::time::systemtime_to_timestamp;
 use utils::timers::Timers;
 use utils::unique_ptr::UniquePtr;
@@ -28,7 +28,7 @@ use crate::network_config::NetworkConfig;
 use crate::Peer;
 use crate::peer_channel::PeerChannel;
 
-pub struct NetworkAgent<B: AbstractBlockchain<'static> + 'static> {
+pub struct NetworkAgent<B: ChainHead + 'static> {
     blockchain: Arc<B>,
     addresses: Arc<PeerAddressBook>,
     network_config: Arc<NetworkConfig>,
@@ -47,7 +47,7 @@ pub struct NetworkAgent<B: AbstractBlockchain<'static> + 'static> {
 
     peer_challenge_nonce: Option<ChallengeNonce>,
     address_request: Option<AddressRequest>,
-    get_address_limit: RateLimit,
+    get_address_limit: Arc<RateLimiter>,
 
     challenge_nonce: ChallengeNonce,
 
@@ -74,7 +74,7 @@ pub enum NetworkAgentEvent {
     PingPong(Duration),
 }
 
-impl<B: AbstractBlockchain<'static> + 'static> NetworkAgent<B> {
+impl<B: ChainHead + 'static> NetworkAgent<B> {
     const VERSION_ATTEMPTS_MAX: usize = 10;
     const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(4); // 4 seconds
     pub const PING_TIMEOUT: Duration = Duration::from_secs(10); // 10 seconds
@@ -106,7 +106,7 @@ impl<B: AbstractBlockchain<'static> + 'static> NetworkAgent<B> {
 
             peer_challenge_nonce: None,
             address_request: None,
-            get_address_limit: RateLimit::new_per_minute(Self::GETADDR_RATE_LIMIT),
+            get_address_limit: RateLimiter::new_per_minute("network_peer_get_addr", Self::GETADDR_RATE_LIMIT),
 
             challenge_nonce: ChallengeNonce::generate(),
 
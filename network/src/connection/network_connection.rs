@@ -1,12 +1,12 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use futures::prelude::*;
-use futures::stream::Forward;
 use futures::sync::mpsc::*;
 use parking_lot::Mutex;
 use parking_lot::RwLock;
 
+use beserial::Serialize;
 use network_primitives::address::net_address::NetAddress;
 use network_primitives::address::peer_address::PeerAddress;
 use utils::observer::PassThroughNotifier;
@@ -71,13 +71,22 @@ impl NetworkConnection {
         let closed_flag = ClosedFlag::new();
         let (tx, rx) = unbounded(); // TODO: use bounded channel?
 
-        let forward_future = rx.forward(stream.clone());
+        // Keep track of the size of the messages waiting to be sent.
+        let queued_bytes = Arc::new(AtomicUsize::new(0));
+        let queued_bytes_inner = Arc::clone(&queued_bytes);
+        let forward_future = rx
+            .inspect(move |msg| {
+                if let Message::Message(msg) = msg {
+                    queued_bytes_inner.fetch_sub(msg.serialized_size(), Ordering::Relaxed);
+                }
+            })
+            .forward(stream.clone());
 
         let notifier = Arc::new(RwLock::new(PassThroughNotifier::new()));
         let peer_stream = PeerStream::new(stream.clone(), notifier.clone(), closed_flag.clone());
         let process_connection = ProcessConnectionFuture::new(peer_stream, forward_future, id);
 
-        let peer_sink = PeerSink::new(tx, id, closed_flag.clone());
+        let peer_sink = PeerSink::new(tx, id, closed_flag.clone(), queued_bytes);
 
         let network_connection = NetworkConnection {
             peer_sink,
@@ -136,7 +145,8 @@ pub struct ProcessConnectionFuture {
 }
 
 impl ProcessConnectionFuture {
-    pub fn new(peer_stream: PeerStream, forward_future: Forward<UnboundedReceiver<Message>, SharedNimiqMessageStream>, _id: UniqueId) -> Self {
+    pub fn new<F>(peer_stream: PeerStream, forward_future: F, _id: UniqueId) -> Self
+        where F: Future + Send + Sync + 'static {
         // `select` required Item/Error to be the same, that's why we need to map them both to ().
         // TODO We're discarding any errors here, especially those coming from the forward future.
         // Results by the peer_stream have been processes already.
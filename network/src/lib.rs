@@ -12,19 +12,23 @@ extern crate nimiq_blockchain_base as blockchain_base;
 extern crate nimiq_hash as hash;
 extern crate nimiq_keys as keys;
 extern crate nimiq_collections as collections;
+extern crate nimiq_database as database;
 
 pub mod address;
+pub mod chain_head;
 pub mod websocket;
 pub mod peer_channel;
 pub mod peer_scorer;
 pub mod connection;
 pub mod peer;
+pub mod peer_versions;
 pub mod network_config;
 pub mod network;
 pub mod error;
 #[cfg(feature = "metrics")]
 mod network_metrics;
 
+pub use crate::chain_head::{ChainHead, GenesisHead};
 pub use crate::peer::Peer;
 pub use crate::network::{Network, NetworkEvent};
 pub use crate::network_config::NetworkConfig;
@@ -9,7 +9,7 @@ use parking_lot::RwLockReadGuard;
 use rand::Rng;
 use rand::rngs::OsRng;
 
-use blockchain_base::AbstractBlockchain;
+use crate::chain_head::ChainHead;
 use network_primitives::networks::NetworkId;
 use network_primitives::time::NetworkTime;
 use utils::mutable_once::MutableOnce;
@@ -23,7 +23,7 @@ use crate::connection::connection_pool::ConnectionId;
 use crate::connection::connection_pool::ConnectionPool;
 use crate::connection::connection_pool::ConnectionPoolEvent;
 use crate::error::Error;
-use crate::network_config::NetworkConfig;
+use crate::network_config::{NetworkConfig, NodeRole};
 use crate::Peer;
 use crate::peer_scorer::PeerScorer;
 
@@ -41,7 +41,7 @@ pub enum NetworkEvent {
     PeersChanged,
 }
 
-pub struct Network<B: AbstractBlockchain<'static> + 'static> {
+pub struct Network<B: ChainHead + 'static> {
     pub network_config: Arc<NetworkConfig>,
     pub network_time: Arc<NetworkTime>,
     auto_connect: Atomic<bool>,
@@ -50,14 +50,13 @@ pub struct Network<B: AbstractBlockchain<'static> + 'static> {
     pub addresses: Arc<PeerAddressBook>,
     pub connections: Arc<ConnectionPool<B>>,
     scorer: Arc<RwLock<PeerScorer<B>>>,
+    role: RwLock<NodeRole>,
     timers: Timers<NetworkTimer>,
     pub notifier: RwLock<Notifier<'static, NetworkEvent>>,
     self_weak: MutableOnce<Weak<Network<B>>>,
 }
 
-impl<B: AbstractBlockchain<'static> + 'static> Network<B> {
-    const PEER_COUNT_MAX: usize = 4000;
-    const PEER_COUNT_RECYCLING_ACTIVE: usize = 1000;
+impl<B: ChainHead + 'static> Network<B> {
     const RECYCLING_PERCENTAGE_MIN: f64 = 0.01;
     const RECYCLING_PERCENTAGE_MAX: f64 = 0.20;
     const CONNECTING_COUNT_MAX: usize = 2;
@@ -76,6 +75,7 @@ impl<B: AbstractBlockchain<'static> + 'static> Network<B> {
             return Err(Error::UninitializedPeerKey);
         }
 
+        let role = network_config.role();
         let net_config = Arc::new(network_config);
         let addresses = Arc::new(PeerAddressBook::new(net_config.clone(), network_id)?);
         let connections = ConnectionPool::new(addresses.clone(), net_config.clone(), blockchain)?;
@@ -88,6 +88,7 @@ impl<B: AbstractBlockchain<'static> + 'static> Network<B> {
             addresses: addresses.clone(),
             connections: connections.clone(),
             scorer: Arc::new(RwLock::new(PeerScorer::new(net_config, addresses, connections.clone()))),
+            role: RwLock::new(role),
             timers: Timers::new(),
             notifier: RwLock::new(Notifier::new()),
             self_weak: MutableOnce::new(Weak::new()),
@@ -125,9 +126,10 @@ impl<B: AbstractBlockchain<'static> + 'static> Network<B> {
 
         let connections = Arc::clone(&self.connections);
         let scorer = Arc::clone(&self.scorer);
+        let peer_count_max = self.network_config.peer_count_max();
 
         self.timers.set_interval(NetworkTimer::Housekeeping, move || {
-            Self::housekeeping(Arc::clone(&connections), Arc::clone(&scorer));
+            Self::housekeeping(Arc::clone(&connections), Arc::clone(&scorer), peer_count_max);
         }, Self::HOUSEKEEPING_INTERVAL);
 
         // Start connecting to peers.
@@ -259,14 +261,15 @@ impl<B: AbstractBlockchain<'static> + 'static> Network<B> {
         self.network_time.set_offset(time_offset);
     }
 
-    fn housekeeping(connections: Arc<ConnectionPool<B>>, scorer: Arc<RwLock<PeerScorer<B>>>) {
+    fn housekeeping(connections: Arc<ConnectionPool<B>>, scorer: Arc<RwLock<PeerScorer<B>>>, peer_count_max: usize) {
         scorer.write().score_connections();
 
         // Recycle.
         let peer_count = connections.peer_count();
-        if peer_count > Self::PEER_COUNT_RECYCLING_ACTIVE {
-            // recycle 1% at PEER_COUNT_RECYCLING_ACTIVE, 20% at PEER_COUNT_MAX
-            let percentage_to_recycle = (peer_count as f64 - Self::PEER_COUNT_RECYCLING_ACTIVE as f64) * (Self::RECYCLING_PERCENTAGE_MAX - Self::RECYCLING_PERCENTAGE_MIN) / (Self::PEER_COUNT_MAX - Self::PEER_COUNT_RECYCLING_ACTIVE) as f64 + Self::RECYCLING_PERCENTAGE_MIN as f64;
+        let recycling_threshold = cmp::min(scorer.read().targets().recycling_threshold, peer_count_max - 1);
+        if peer_count > recycling_threshold {
+            // recycle 1% at the recycling threshold, 20% at peer_count_max
+            let percentage_to_recycle = (peer_count as f64 - recycling_threshold as f64) * (Self::RECYCLING_PERCENTAGE_MAX - Self::RECYCLING_PERCENTAGE_MIN) / (peer_count_max - recycling_threshold) as f64 + Self::RECYCLING_PERCENTAGE_MIN as f64;
             let connections_to_recycle = f64::ceil(peer_count as f64 * percentage_to_recycle) as u32;
             scorer.write().recycle_connections(connections_to_recycle, CloseType::PeerConnectionRecycled, "Peer connection recycled");
         }
@@ -351,4 +354,39 @@ impl<B: AbstractBlockchain<'static> + 'static> Network<B> {
     pub fn scorer(&self) -> RwLockReadGuard<PeerScorer<B>> {
         self.scorer.read()
     }
+
+    /// Total size of the messages waiting to be sent to our peers.
+    pub fn queued_bytes(&self) -> usize {
+        self.connections.state().connection_iter().iter()
+            .filter_map(|connection| connection.peer_channel())
+            .map(|channel| channel.queued_bytes())
+            .sum()
+    }
+
+    /// Closes the connections to the `count` peers with the lowest score to free memory.
+    pub fn shed_peers(&self, count: u32) {
+        let mut scorer = self.scorer.write();
+        scorer.score_connections();
+        scorer.recycle_connections(count, CloseType::MemoryPressure, "Memory pressure");
+    }
+
+    pub fn role(&self) -> NodeRole {
+        *self.role.read()
+    }
+
+    /// Changes the role of this node and adjusts the peer count targets accordingly.
+    pub fn set_role(&self, role: NodeRole) {
+        {
+            let mut current_role = self.role.write();
+            if *current_role == role {
+                return;
+            }
+            *current_role = role;
+        }
+
+        info!("Node role changed to {:?}", role);
+        self.scorer.write().set_targets(self.network_config.peer_count_targets(role));
+
+        self.check_peer_count();
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::time::SystemTime;
 
 use keys::{KeyPair, PublicKey, PrivateKey};
@@ -22,6 +23,68 @@ pub enum Seed {
 }
 
 
+/// The role of this node in the network, which determines the peers it maintains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeRole {
+    FullNode,
+    HistoryServer,
+    /// A validator that isn't active in the current epoch.
+    Validator,
+    /// A validator that holds slots in the current epoch.
+    ActiveValidator,
+}
+
+impl Default for NodeRole {
+    fn default() -> Self {
+        NodeRole::FullNode
+    }
+}
+
+/// Peer count targets and connection priorities for a `NodeRole`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeerCountTargets {
+    /// Minimum number of outbound connections we try to maintain.
+    pub min_outbound: usize,
+    /// Minimum number of outbound WebSocket connections to full nodes we try to maintain.
+    pub min_full_ws_outbound: usize,
+    /// Peer count above which connections are recycled.
+    pub recycling_threshold: usize,
+    /// Whether connections to validators are preferred when recycling connections.
+    pub prefer_validators: bool,
+}
+
+impl PeerCountTargets {
+    pub fn default_for(role: NodeRole) -> Self {
+        match role {
+            NodeRole::FullNode => PeerCountTargets {
+                min_outbound: 6,
+                min_full_ws_outbound: 1,
+                recycling_threshold: 1000,
+                prefer_validators: false,
+            },
+            NodeRole::HistoryServer => PeerCountTargets {
+                min_outbound: 6,
+                min_full_ws_outbound: 1,
+                recycling_threshold: 2000,
+                prefer_validators: false,
+            },
+            NodeRole::Validator => PeerCountTargets {
+                min_outbound: 8,
+                min_full_ws_outbound: 2,
+                recycling_threshold: 1000,
+                prefer_validators: true,
+            },
+            NodeRole::ActiveValidator => PeerCountTargets {
+                min_outbound: 12,
+                min_full_ws_outbound: 4,
+                recycling_threshold: 500,
+                prefer_validators: true,
+            },
+        }
+    }
+}
+
+
 #[derive(Clone, Debug)]
 pub struct NetworkConfig {
     protocol_mask: ProtocolFlags,
@@ -31,6 +94,9 @@ pub struct NetworkConfig {
     protocol_config: ProtocolConfig,
     user_agent: Option<String>,
     additional_seeds: Vec<Seed>,
+    role: NodeRole,
+    peer_count_targets: HashMap<NodeRole, PeerCountTargets>,
+    peer_count_max: usize,
     pub instant_inbound: bool,
 }
 
@@ -48,6 +114,9 @@ impl NetworkConfig {
             },
             user_agent: None,
             additional_seeds: Vec::new(),
+            role: NodeRole::default(),
+            peer_count_targets: HashMap::new(),
+            peer_count_max: network_primitives::PEER_COUNT_MAX,
             instant_inbound,
         }
     }
@@ -66,6 +135,9 @@ impl NetworkConfig {
             },
             user_agent: None,
             additional_seeds: Vec::new(),
+            role: NodeRole::default(),
+            peer_count_targets: HashMap::new(),
+            peer_count_max: network_primitives::PEER_COUNT_MAX,
             instant_inbound,
         }
     }
@@ -79,6 +151,9 @@ impl NetworkConfig {
             protocol_config: ProtocolConfig::Dumb,
             user_agent: None,
             additional_seeds: Vec::new(),
+            role: NodeRole::default(),
+            peer_count_targets: HashMap::new(),
+            peer_count_max: network_primitives::PEER_COUNT_MAX,
             instant_inbound: true,
         }
     }
@@ -157,6 +232,38 @@ impl NetworkConfig {
         self.additional_seeds = seeds
     }
 
+    /// The role the node starts with. The role can change at runtime, see `Network::set_role`.
+    pub fn role(&self) -> NodeRole {
+        self.role
+    }
+
+    pub fn set_role(&mut self, role: NodeRole) {
+        self.role = role;
+    }
+
+    /// Returns the configured peer count targets for `role`, or the defaults if not configured.
+    pub fn peer_count_targets(&self, role: NodeRole) -> PeerCountTargets {
+        self.peer_count_targets.get(&role)
+            .cloned()
+            .unwrap_or_else(|| PeerCountTargets::default_for(role))
+    }
+
+    pub fn set_peer_count_targets(&mut self, role: NodeRole, targets: PeerCountTargets) {
+        self.peer_count_targets.insert(role, targets);
+    }
+
+    /// Peer count above which inbound connections are rejected, unless they may replace a
+    /// connection with a low score.
+    pub fn peer_count_max(&self) -> usize {
+        self.peer_count_max
+    }
+
+    /// Panics if `peer_count_max` is 0.
+    pub fn set_peer_count_max(&mut self, peer_count_max: usize) {
+        assert!(peer_count_max >= 1, "The peer count max must be at least 1");
+        self.peer_count_max = peer_count_max;
+    }
+
     pub fn protocol_config(&self) -> &ProtocolConfig {
         &self.protocol_config
     }
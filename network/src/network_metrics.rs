@@ -4,7 +4,7 @@ use std::default::Default;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use blockchain_base::AbstractBlockchain;
+use crate::chain_head::ChainHead;
 use network_messages::MessageType;
 use network_primitives::protocol::Protocol;
 
@@ -54,7 +54,7 @@ pub struct MessageMetrics {
 
 impl MessageMetrics {
     // New message types need to be added here to occur in the metrics!
-    const MESSAGE_TYPES: [MessageType; 43] = [
+    const MESSAGE_TYPES: [MessageType; 45] = [
         MessageType::Version,
         MessageType::Inv,
         MessageType::GetData,
@@ -98,6 +98,8 @@ impl MessageMetrics {
         MessageType::PbftProposal,
         MessageType::PbftPrepare,
         MessageType::PbftCommit,
+        MessageType::MacroHeaderDiff,
+        MessageType::Heartbeat,
     ];
 
     pub fn new() -> Self {
@@ -206,7 +208,7 @@ impl PeerMetrics {
     }
 }
 
-impl<B: AbstractBlockchain<'static> + 'static> ConnectionPool<B> {
+impl<B: ChainHead + 'static> ConnectionPool<B> {
     pub fn metrics(&self) -> (MessageMetrics, NetworkMetrics, PeerMetrics) {
         let mut bytes_sent: usize = 0;
         let mut bytes_received: usize = 0;
@@ -109,6 +109,11 @@ impl PeerChannel {
         self.closed_flag.is_closed()
     }
 
+    /// Size of the messages waiting to be sent to the peer.
+    pub fn queued_bytes(&self) -> usize {
+        self.peer_sink.queued_bytes()
+    }
+
     pub fn close(&self, ty: CloseType) {
         self.peer_sink.close(ty, None);
         let notifier = self.close_notifier.clone();
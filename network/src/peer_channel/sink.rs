@@ -2,9 +2,12 @@ use std::fmt;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use futures::sync::mpsc::*;
 
+use beserial::Serialize;
 use network_messages::Message;
 use utils::unique_id::UniqueId;
 
@@ -17,24 +20,37 @@ pub struct PeerSink {
     sink: UnboundedSender<WebSocketMessage>,
     unique_id: UniqueId,
     closed_flag: ClosedFlag,
+    /// Size of the messages that have been sent, but not handed to the websocket yet.
+    queued_bytes: Arc<AtomicUsize>,
 }
 
 impl PeerSink {
-    pub fn new(channel: UnboundedSender<WebSocketMessage>, unique_id: UniqueId, closed_flag: ClosedFlag) -> Self {
+    pub fn new(channel: UnboundedSender<WebSocketMessage>, unique_id: UniqueId, closed_flag: ClosedFlag, queued_bytes: Arc<AtomicUsize>) -> Self {
         PeerSink {
             sink: channel,
             unique_id,
             closed_flag,
+            queued_bytes,
         }
     }
 
+    pub fn queued_bytes(&self) -> usize {
+        self.queued_bytes.load(Ordering::Relaxed)
+    }
+
     pub fn send(&self, msg: Message) -> Result<(), SendError<WebSocketMessage>> {
         // Do not send messages over already closed connections.
         // Stop sending silently until connection is really closed.
         if self.closed_flag.is_closed() {
             return Ok(());
         }
-        self.sink.unbounded_send(WebSocketMessage::Message(msg))
+        let size = msg.serialized_size();
+        self.queued_bytes.fetch_add(size, Ordering::Relaxed);
+        let result = self.sink.unbounded_send(WebSocketMessage::Message(msg));
+        if result.is_err() {
+            self.queued_bytes.fetch_sub(size, Ordering::Relaxed);
+        }
+        result
     }
 
     /// Closes the connection.
@@ -3,7 +3,7 @@ use std::{sync::Arc, time::Duration};
 use rand::Rng;
 use rand::rngs::OsRng;
 
-use blockchain_base::AbstractBlockchain;
+use crate::chain_head::ChainHead;
 use network_primitives::{
     address::peer_address::PeerAddress,
     protocol::Protocol,
@@ -21,24 +21,22 @@ use crate::{
         connection_pool::{ConnectionId, ConnectionPool},
         network_agent::NetworkAgent,
     },
-    network_config::NetworkConfig,
+    network_config::{NetworkConfig, PeerCountTargets},
 };
 use crate::address::peer_address_book::PeerAddressBookState;
 use parking_lot::RwLockReadGuard;
 
 pub type Score = f64;
 
-pub struct PeerScorer<B: AbstractBlockchain<'static> + 'static> {
+pub struct PeerScorer<B: ChainHead + 'static> {
     network_config: Arc<NetworkConfig>,
     addresses: Arc<PeerAddressBook>,
     connections: Arc<ConnectionPool<B>>,
     connection_scores: Vec<(ConnectionId, Score)>,
+    targets: PeerCountTargets,
 }
 
-impl<B: AbstractBlockchain<'static> + 'static> PeerScorer<B> {
-    const PEER_COUNT_MIN_FULL_WS_OUTBOUND: usize = 1; // FIXME: this is fixed to the "node.js" value since we don't support browsers in the Rust impl yet
-    const PEER_COUNT_MIN_OUTBOUND: usize = 6; // FIXME: this is fixed to the "node.js" value since we don't support browsers in the Rust impl yet
-
+impl<B: ChainHead + 'static> PeerScorer<B> {
     const PICK_SELECTION_SIZE: usize = 100;
 
     const MIN_AGE_FULL: Duration = Duration::from_secs(5 * 60); // 5 minutes
@@ -56,14 +54,24 @@ impl<B: AbstractBlockchain<'static> + 'static> PeerScorer<B> {
 
 
     pub fn new(network_config: Arc<NetworkConfig>, addresses: Arc<PeerAddressBook>, connections: Arc<ConnectionPool<B>>) -> Self {
+        let targets = network_config.peer_count_targets(network_config.role());
         PeerScorer {
             network_config,
             addresses,
             connections,
             connection_scores: Vec::new(),
+            targets,
         }
     }
 
+    pub fn targets(&self) -> &PeerCountTargets {
+        &self.targets
+    }
+
+    pub fn set_targets(&mut self, targets: PeerCountTargets) {
+        self.targets = targets;
+    }
+
     pub fn pick_address(&self) -> Option<Arc<PeerAddress>> {
         let mut candidates = self.find_candidates(1000, false);
         if candidates.is_empty() {
@@ -162,11 +170,11 @@ impl<B: AbstractBlockchain<'static> + 'static> PeerScorer<B> {
     }
 
     pub fn needs_good_peers(&self) -> bool {
-        self.connections.state().get_peer_count_full_ws_outbound() < Self::PEER_COUNT_MIN_FULL_WS_OUTBOUND
+        self.connections.state().get_peer_count_full_ws_outbound() < self.targets.min_full_ws_outbound
     }
 
     pub fn needs_more_peers(&self) -> bool {
-        self.connections.state().get_peer_count_outbound() < Self::PEER_COUNT_MIN_OUTBOUND
+        self.connections.state().get_peer_count_outbound() < self.targets.min_outbound
     }
 
     pub fn is_good_peer(&self, peer_address: &Arc<PeerAddress>) -> bool {
@@ -184,7 +192,7 @@ impl<B: AbstractBlockchain<'static> + 'static> PeerScorer<B> {
         for connection in connections {
             if connection.1.state() == ConnectionState::Established
                 && connection.1.age_established() > self.get_min_age(connection.1.peer_address().expect("No peer address")) {
-                let score = Self::score_connection(connection.1, distribution, peer_count_full_ws_outbound);
+                let score = Self::score_connection(connection.1, distribution, peer_count_full_ws_outbound, &self.targets);
                 connection_scores.push((connection.0, score));
             }
         }
@@ -207,7 +215,7 @@ impl<B: AbstractBlockchain<'static> + 'static> PeerScorer<B> {
         }
     }
 
-    fn score_connection(connection_info: &ConnectionInfo<B>, distribution: f64, peer_count_full_ws_outbound: usize) -> Score {
+    fn score_connection(connection_info: &ConnectionInfo<B>, distribution: f64, peer_count_full_ws_outbound: usize, targets: &PeerCountTargets) -> Score {
         // Connection age
         let score_age = Self::score_connection_age(connection_info);
 
@@ -234,7 +242,7 @@ impl<B: AbstractBlockchain<'static> + 'static> PeerScorer<B> {
         let score_protocol: Score = match peer_address.protocol() {
             Protocol::Wss | Protocol::Ws => {
                 // Boost WebSocket score when low on WebSocket connections.
-                if distribution < Self::BEST_PROTOCOL_WS_DISTRIBUTION || peer_count_full_ws_outbound <= Self::PEER_COUNT_MIN_FULL_WS_OUTBOUND {
+                if distribution < Self::BEST_PROTOCOL_WS_DISTRIBUTION || peer_count_full_ws_outbound <= targets.min_full_ws_outbound {
                     1.0
                 } else {
                     0.6
@@ -250,7 +258,14 @@ impl<B: AbstractBlockchain<'static> + 'static> PeerScorer<B> {
             1.0 - median_latency / NetworkAgent::<B>::PING_TIMEOUT.as_secs() as f64
         } else { 0.0 };
 
-        0.15 * score_age + 0.25 * score_outbound + 0.2 * score_type + 0.2 * score_protocol + 0.2 * score_speed
+        let score = 0.15 * score_age + 0.25 * score_outbound + 0.2 * score_type + 0.2 * score_protocol + 0.2 * score_speed;
+
+        // Validators keep their connections to other validators.
+        if targets.prefer_validators && peer_address.services.is_validator() {
+            score + 1.0
+        } else {
+            score
+        }
     }
 
     fn score_by_age(age: u128, best_age: u128, max_age: u128) -> Score {
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use crate::chain_head::ChainHead;
+
+use crate::connection::connection_info::ConnectionState;
+use crate::connection::connection_pool::ConnectionPool;
+
+/// Aggregated protocol versions and user agents of all established peers.
+#[derive(Clone, Debug, Default)]
+pub struct PeerVersionStatistics {
+    versions: HashMap<u32, usize>,
+    user_agents: HashMap<String, usize>,
+}
+
+impl PeerVersionStatistics {
+    pub const UNKNOWN_USER_AGENT: &'static str = "unknown";
+
+    fn add_peer(&mut self, version: u32, user_agent: Option<&str>) {
+        *self.versions.entry(version)
+            .or_insert(0) += 1;
+        *self.user_agents.entry(Self::product(user_agent))
+            .or_insert(0) += 1;
+    }
+
+    /// Reduces a user agent to its product token, e.g. `core-rs/0.1.0+abcdef (native; linux x86_64)`
+    /// becomes `core-rs/0.1.0+abcdef`, so that peers only differing in their platform are grouped.
+    pub fn product(user_agent: Option<&str>) -> String {
+        user_agent
+            .and_then(|user_agent| user_agent.split_whitespace().next())
+            .unwrap_or(Self::UNKNOWN_USER_AGENT)
+            .to_string()
+    }
+
+    pub fn versions(&self) -> impl Iterator<Item=(&u32, &usize)> {
+        self.versions.iter()
+    }
+
+    pub fn user_agents(&self) -> impl Iterator<Item=(&String, &usize)> {
+        self.user_agents.iter()
+    }
+
+    pub fn peer_count(&self) -> usize {
+        self.versions.values().sum()
+    }
+}
+
+impl<B: ChainHead + 'static> ConnectionPool<B> {
+    /// Collects version statistics over all established connections.
+    pub fn version_statistics(&self) -> PeerVersionStatistics {
+        let mut statistics = PeerVersionStatistics::default();
+
+        let state = self.state();
+        for connection in state.connection_iter() {
+            if connection.state() != ConnectionState::Established {
+                continue;
+            }
+            if let Some(peer) = connection.peer() {
+                statistics.add_peer(peer.version, peer.user_agent.as_ref().map(String::as_str));
+            }
+        }
+
+        statistics
+    }
+}
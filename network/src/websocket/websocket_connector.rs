@@ -126,7 +126,7 @@ pub struct WebSocketConnector {
 }
 
 impl WebSocketConnector {
-    const CONNECTIONS_MAX: usize = 4050; // A little more than Network.PEER_COUNT_MAX to allow for inbound exchange
+    const CONNECTIONS_EXTRA: usize = 50; // Allow a few more than the peer count max for inbound exchange
     const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
     const WAIT_TIME_ON_ERROR: Duration = Duration::from_millis(100);
 
@@ -185,7 +185,7 @@ impl WebSocketConnector {
                     future::ok(())
                 })
             })
-            .listen(Self::CONNECTIONS_MAX)
+            .listen(self.network_config.peer_count_max() + Self::CONNECTIONS_EXTRA)
             .then(#[allow(unreachable_code)] |_result| {
                 panic!("WebSocket stream ended unexpectedly");
                 _result
@@ -0,0 +1,9 @@
+use network::{ChainHead, GenesisHead};
+use network_primitives::networks::{NetworkId, NetworkInfo};
+
+#[test]
+fn it_stays_at_the_genesis_block() {
+    let head = GenesisHead::new(NetworkId::Main);
+    assert_eq!(head.network_id(), NetworkId::Main);
+    assert_eq!(&head.head_hash(), NetworkInfo::from_network_id(NetworkId::Main).genesis_hash());
+}
@@ -0,0 +1,6 @@
+extern crate nimiq_network as network;
+extern crate nimiq_network_primitives as network_primitives;
+
+mod chain_head;
+mod network_config;
+mod peer_versions;
@@ -0,0 +1,37 @@
+use network::network_config::{NetworkConfig, NodeRole, PeerCountTargets};
+
+#[test]
+fn it_uses_default_peer_count_targets() {
+    let config = NetworkConfig::new_dumb_network_config();
+    assert_eq!(config.role(), NodeRole::FullNode);
+    assert_eq!(config.peer_count_targets(NodeRole::Validator), PeerCountTargets::default_for(NodeRole::Validator));
+    assert!(config.peer_count_targets(NodeRole::ActiveValidator).min_outbound > config.peer_count_targets(NodeRole::FullNode).min_outbound);
+}
+
+#[test]
+fn it_overrides_peer_count_targets() {
+    let mut config = NetworkConfig::new_dumb_network_config();
+    let targets = PeerCountTargets {
+        min_outbound: 20,
+        min_full_ws_outbound: 5,
+        recycling_threshold: 300,
+        prefer_validators: true,
+    };
+    config.set_peer_count_targets(NodeRole::ActiveValidator, targets);
+    assert_eq!(config.peer_count_targets(NodeRole::ActiveValidator), targets);
+    assert_eq!(config.peer_count_targets(NodeRole::FullNode), PeerCountTargets::default_for(NodeRole::FullNode));
+}
+
+#[test]
+fn it_overrides_peer_count_max() {
+    let mut config = NetworkConfig::new_dumb_network_config();
+    config.set_peer_count_max(1);
+    assert_eq!(config.peer_count_max(), 1);
+}
+
+#[test]
+#[should_panic]
+fn it_rejects_a_zero_peer_count_max() {
+    let mut config = NetworkConfig::new_dumb_network_config();
+    config.set_peer_count_max(0);
+}
@@ -0,0 +1,9 @@
+use network::peer_versions::PeerVersionStatistics;
+
+#[test]
+fn it_reduces_user_agents_to_product() {
+    assert_eq!(PeerVersionStatistics::product(Some("core-rs/0.1.0+abcdef (native; linux x86_64)")), "core-rs/0.1.0+abcdef");
+    assert_eq!(PeerVersionStatistics::product(Some("core-js/1.4.3")), "core-js/1.4.3");
+    assert_eq!(PeerVersionStatistics::product(Some("")), PeerVersionStatistics::UNKNOWN_USER_AGENT);
+    assert_eq!(PeerVersionStatistics::product(None), PeerVersionStatistics::UNKNOWN_USER_AGENT);
+}
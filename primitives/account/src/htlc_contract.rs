@@ -39,6 +39,19 @@ impl HashedTimeLockedContract {
             total_amount: self.total_amount,
         }
     }
+
+    /// Returns the data of a contract creation transaction for a contract with the same
+    /// parameters.
+    pub fn creation_data(&self) -> Vec<u8> {
+        CreationTransactionData {
+            sender: self.sender.clone(),
+            recipient: self.recipient.clone(),
+            hash_algorithm: self.hash_algorithm,
+            hash_root: self.hash_root.clone(),
+            hash_count: self.hash_count,
+            timeout: self.timeout,
+        }.serialize_to_vec()
+    }
 }
 
 impl AccountTransactionInteraction for HashedTimeLockedContract {
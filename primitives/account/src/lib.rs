@@ -409,12 +409,43 @@ impl From<Vec<Receipt>> for Receipts {
     }
 }
 
+/// The contract creation transaction that recreates a pruned contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContractRestoration {
+    pub account_type: AccountType,
+    /// The value of the transaction, i.e. the total amount of the original contract
+    pub value: Coin,
+    /// The data of the transaction
+    pub data: Vec<u8>,
+}
+
 #[derive(Clone, Eq, Debug, Serialize, Deserialize)]
 pub struct PrunedAccount {
     pub address: Address,
     pub account: Account,
 }
 
+impl PrunedAccount {
+    /// Returns how to recreate the pruned account, if it is a contract. The new contract has the
+    /// same parameters, but a different address, since that is derived from the creation
+    /// transaction.
+    pub fn restoration(&self) -> Option<ContractRestoration> {
+        match self.account {
+            Account::Vesting(ref contract) => Some(ContractRestoration {
+                account_type: AccountType::Vesting,
+                value: contract.total_amount,
+                data: contract.creation_data(),
+            }),
+            Account::HTLC(ref contract) => Some(ContractRestoration {
+                account_type: AccountType::HTLC,
+                value: contract.total_amount,
+                data: contract.creation_data(),
+            }),
+            Account::Basic(_) | Account::Staking(_) => None,
+        }
+    }
+}
+
 impl SerializeContent for PrunedAccount {
     fn serialize_content<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> { Ok(self.serialize(writer)?) }
 }
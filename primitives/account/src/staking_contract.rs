@@ -1,4 +1,5 @@
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::collections::btree_set::BTreeSet;
 use std::collections::HashMap;
 use std::io::Write;
@@ -17,8 +18,8 @@ use primitives::{
     coin::Coin,
     validators::{Slot, Slots},
 };
-use transaction::{SignatureProof, Transaction};
-use transaction::account::staking_contract::StakingTransactionData;
+use transaction::{SignatureProof, Transaction, TransactionFlags};
+use transaction::account::staking_contract::{StakingTransactionData, ValidatorKeyRotationData};
 
 use crate::{Account, AccountError, AccountTransactionInteraction, AccountType};
 use crate::inherent::{AccountInherentInteraction, Inherent, InherentType};
@@ -33,9 +34,22 @@ pub struct ActiveStake {
 }
 
 impl ActiveStake {
+    pub fn staker_address(&self) -> &Address {
+        &self.staker_address
+    }
+
+    pub fn balance(&self) -> Coin {
+        self.balance
+    }
+
     pub fn validator_key(&self) -> &BlsPublicKey {
         &self.validator_key
     }
+
+    /// The address rewards are paid to, which defaults to the staker address.
+    pub fn reward_address(&self) -> &Address {
+        self.reward_address.as_ref().unwrap_or(&self.staker_address)
+    }
 }
 
 impl PartialEq for ActiveStake {
@@ -67,6 +81,16 @@ pub struct InactiveStake {
     retire_time: u32,
 }
 
+impl InactiveStake {
+    pub fn balance(&self) -> Coin {
+        self.balance
+    }
+
+    pub fn retire_time(&self) -> u32 {
+        self.retire_time
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct ActiveStakeReceipt {
     validator_key: BlsPublicKey,
@@ -100,7 +124,16 @@ struct SlashReceipt {
     - If a staker retires multiple times, balance is added to the existing entry and
       retire_time is reset.
     - Signed by staking/sender address
- 3. Unstake:
+ 3. Rotate key:
+    - Transaction from staking contract to itself, flagged as signalling, with the new validator
+      key and its proof of knowledge as data
+    - The sender side removes the transaction value and fee from the active stake like a retire,
+      but the stake must remain active afterwards
+    - The recipient side puts the value back into the active stake and replaces its validator_key,
+      which must not be used by another staker
+    - The new key is elected from the next macro block on, the old one keeps signing until then
+    - Signed by staking/sender address
+ 4. Unstake:
     - Transaction from the contract to an external address
     - If condition of block_height ≥ next_macro_block_after(retire_time) + UNSTAKE_DELAY is met,
       transfers value from inactive_validators entry/entries
@@ -146,6 +179,40 @@ impl StakingContract {
         self.inactive_stake_by_address.get(staker_address).map(|stake| stake.balance).unwrap_or(Coin::ZERO)
     }
 
+    /// Iterates over the active stakes from the highest to the lowest balance. Use `skip` and
+    /// `take` to page through them.
+    pub fn iter_active_stakes(&self) -> impl Iterator<Item=&ActiveStake> {
+        self.active_stake_sorted.iter().map(|stake| stake.as_ref())
+    }
+
+    /// Iterates over the inactive stakes and their staker addresses, in no particular order.
+    pub fn iter_inactive_stakes(&self) -> impl Iterator<Item=(&Address, &InactiveStake)> {
+        self.inactive_stake_by_address.iter()
+    }
+
+    /// Returns the total active stake of each validator key.
+    pub fn validator_stakes(&self) -> BTreeMap<&BlsPublicKey, Coin> {
+        let mut stakes = BTreeMap::new();
+        for stake in self.iter_active_stakes() {
+            *stakes.entry(&stake.validator_key).or_insert(Coin::ZERO) += stake.balance;
+        }
+        stakes
+    }
+
+    /// Returns the total active stake of a validator key.
+    pub fn validator_stake(&self, validator_key: &BlsPublicKey) -> Coin {
+        // FIXME: Inefficient linear scan.
+        self.iter_active_stakes()
+            .filter(|stake| &stake.validator_key == validator_key)
+            .fold(Coin::ZERO, |total, stake| total + stake.balance)
+    }
+
+    /// Returns whether any active stake is staked for a validator key.
+    pub fn has_validator(&self, validator_key: &BlsPublicKey) -> bool {
+        // FIXME: Inefficient linear scan.
+        self.iter_active_stakes().any(|stake| &stake.validator_key == validator_key)
+    }
+
     /// Adds funds to stake of `address`.
     /// XXX This is public to fill the genesis staking contract
     pub fn stake(&mut self, staker_address: &Address, value: Coin, validator_key: BlsPublicKey, reward_address: Option<Address>) -> Result<Option<ActiveStakeReceipt>, AccountError> {
@@ -321,6 +388,62 @@ impl StakingContract {
         Ok(())
     }
 
+    /// Checks that a staker can rotate to `validator_key`, i.e. that no other staker uses it. A
+    /// stake can't be added to another validator by rotating to its key.
+    fn check_key_rotation(&self, staker_address: &Address, validator_key: &BlsPublicKey) -> Result<(), AccountError> {
+        // FIXME: Inefficient linear scan.
+        if self.iter_active_stakes().any(|stake| &stake.validator_key == validator_key && &stake.staker_address != staker_address) {
+            return Err(AccountError::InvalidForRecipient);
+        }
+        Ok(())
+    }
+
+    /// Puts the value of a key rotation back into the active stake and replaces its validator key.
+    fn rotate_key(&mut self, staker_address: &Address, value: Coin, validator_key: BlsPublicKey) -> Result<ActiveStakeReceipt, AccountError> {
+        self.check_key_rotation(staker_address, &validator_key)?;
+
+        self.balance = Account::balance_add(self.balance, value)?;
+
+        let active_stake = self.active_stake_by_address.remove(staker_address)
+            .ok_or(AccountError::InvalidForRecipient)?;
+
+        let new_active_stake = Arc::new(ActiveStake {
+            staker_address: staker_address.clone(),
+            balance: Account::balance_add(active_stake.balance, value)?,
+            validator_key,
+            reward_address: active_stake.reward_address.clone(),
+        });
+
+        self.active_stake_sorted.remove(&active_stake);
+        self.active_stake_sorted.insert(Arc::clone(&new_active_stake));
+        self.active_stake_by_address.insert(staker_address.clone(), new_active_stake);
+
+        Ok(ActiveStakeReceipt {
+            validator_key: active_stake.validator_key.clone(),
+            reward_address: active_stake.reward_address.clone(),
+        })
+    }
+
+    /// Reverts the recipient side of a key rotation.
+    fn revert_rotate_key(&mut self, staker_address: &Address, value: Coin, receipt: ActiveStakeReceipt) -> Result<(), AccountError> {
+        self.balance = Account::balance_sub(self.balance, value)?;
+
+        let active_stake = self.active_stake_by_address.remove(staker_address)
+            .ok_or(AccountError::InvalidForRecipient)?;
+
+        let new_active_stake = Arc::new(ActiveStake {
+            staker_address: staker_address.clone(),
+            balance: Account::balance_sub(active_stake.balance, value)?,
+            validator_key: receipt.validator_key,
+            reward_address: receipt.reward_address,
+        });
+
+        self.active_stake_sorted.remove(&active_stake);
+        self.active_stake_sorted.insert(Arc::clone(&new_active_stake));
+        self.active_stake_by_address.insert(staker_address.clone(), new_active_stake);
+        Ok(())
+    }
+
     /// Removes stake from the inactive stake list.
     fn unstake(&mut self, staker_address: &Address, total_value: Coin) -> Result<Option<InactiveStakeReceipt>, AccountError> {
         self.balance = Account::balance_sub(self.balance, total_value)?;
@@ -476,7 +599,13 @@ impl AccountTransactionInteraction for StakingContract {
         Err(AccountError::InvalidForRecipient)
     }
 
-    fn check_incoming_transaction(&self, _: &Transaction, _: u32) -> Result<(), AccountError> {
+    fn check_incoming_transaction(&self, transaction: &Transaction, _: u32) -> Result<(), AccountError> {
+        if transaction.sender == transaction.recipient && transaction.flags.contains(TransactionFlags::SIGNALLING) {
+            // Key rotation transaction
+            let staker_address = Self::get_signer(transaction)?;
+            let data = ValidatorKeyRotationData::parse(transaction)?;
+            self.check_key_rotation(&staker_address, &data.validator_key)?;
+        }
         Ok(())
     }
 
@@ -486,6 +615,12 @@ impl AccountTransactionInteraction for StakingContract {
             let data = StakingTransactionData::parse(transaction)?;
             Ok(self.stake(&transaction.sender, transaction.value, data.validator_key, data.reward_address)?
                 .map(|receipt| receipt.serialize_to_vec()))
+        } else if transaction.flags.contains(TransactionFlags::SIGNALLING) {
+            // Key rotation transaction
+            // Like for retire transactions, the staker address is taken from the proof.
+            let staker_address = Self::get_signer(transaction)?;
+            let data = ValidatorKeyRotationData::parse(transaction)?;
+            Ok(Some(self.rotate_key(&staker_address, transaction.value, data.validator_key)?.serialize_to_vec()))
         } else {
             // Retire transaction
             // XXX Get staker address from transaction proof. This violates the model that only the
@@ -505,6 +640,11 @@ impl AccountTransactionInteraction for StakingContract {
                 _ => None
             };
             self.revert_stake(&transaction.sender, transaction.value, receipt)
+        } else if transaction.flags.contains(TransactionFlags::SIGNALLING) {
+            // Key rotation transaction
+            let staker_address = Self::get_signer(transaction)?;
+            let receipt = Deserialize::deserialize_from_vec(receipt.ok_or(AccountError::InvalidReceipt)?)?;
+            self.revert_rotate_key(&staker_address, transaction.value, receipt)
         } else {
             // Retire transaction
             let staker_address = Self::get_signer(transaction)?;
@@ -530,11 +670,17 @@ impl AccountTransactionInteraction for StakingContract {
 
             Account::balance_sufficient(inactive_stake.balance, transaction.total_value()?)
         } else {
-            // Retire transaction
+            // Retire or key rotation transaction
             let active_stake = self.active_stake_by_address.get(&staker_address)
                 .ok_or(AccountError::InvalidForSender)?;
 
-            Account::balance_sufficient(active_stake.balance, transaction.total_value()?)
+            // The stake of a key rotation must stay active in between its sender and recipient side.
+            let total_value = transaction.total_value()?;
+            if transaction.flags.contains(TransactionFlags::SIGNALLING) && active_stake.balance <= total_value {
+                return Err(AccountError::InvalidForSender);
+            }
+
+            Account::balance_sufficient(active_stake.balance, total_value)
         }
     }
 
@@ -547,7 +693,7 @@ impl AccountTransactionInteraction for StakingContract {
             Ok(self.unstake(&staker_address, transaction.total_value()?)?
                 .map(|receipt| receipt.serialize_to_vec()))
         } else {
-            // Retire transaction
+            // Retire or key rotation transaction
             Ok(self.retire_sender(&staker_address, transaction.total_value()?, block_height)?
                 .map(|receipt| receipt.serialize_to_vec()))
         }
@@ -564,7 +710,7 @@ impl AccountTransactionInteraction for StakingContract {
             };
             self.revert_unstake(&staker_address, transaction.total_value()?, receipt)
         } else {
-            // Retire transaction
+            // Retire or key rotation transaction
             let receipt = match receipt {
                 Some(v) => Some(Deserialize::deserialize_from_vec(v)?),
                 _ => None
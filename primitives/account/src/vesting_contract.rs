@@ -35,6 +35,18 @@ impl VestingContract {
         }
     }
 
+    /// Returns the data of a contract creation transaction for a contract with the same
+    /// parameters (in the format that includes the total amount).
+    pub fn creation_data(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Address::SIZE + 24);
+        Serialize::serialize(&self.owner, &mut data).unwrap();
+        Serialize::serialize(&self.start, &mut data).unwrap();
+        Serialize::serialize(&self.step_blocks, &mut data).unwrap();
+        Serialize::serialize(&self.step_amount, &mut data).unwrap();
+        Serialize::serialize(&self.total_amount, &mut data).unwrap();
+        data
+    }
+
     pub fn min_cap(&self, block_height: u32) -> Coin {
         if self.step_blocks > 0 && self.step_amount > Coin::ZERO {
             let steps = (f64::from(block_height - self.start) / f64::from(self.step_blocks)).floor();
@@ -44,6 +56,56 @@ impl VestingContract {
             Coin::ZERO
         }
     }
+
+    /// Returns the part of the total amount that is still locked at `block_height`. Unlike
+    /// `min_cap`, this is also defined for heights before the start of the contract.
+    pub fn locked_amount(&self, block_height: u32) -> Coin {
+        if block_height < self.start && self.step_blocks > 0 && self.step_amount > Coin::ZERO {
+            self.total_amount
+        } else {
+            self.min_cap(block_height)
+        }
+    }
+
+    /// Returns the height of the next release after `block_height` together with the amount that
+    /// is released, or `None` if the total amount is vested already.
+    pub fn next_release(&self, block_height: u32) -> Option<(u32, Coin)> {
+        let locked = self.locked_amount(block_height);
+        if locked == Coin::ZERO {
+            return None;
+        }
+
+        let passed_steps = block_height.saturating_sub(self.start) / self.step_blocks;
+        let height = (passed_steps + 1).checked_mul(self.step_blocks)
+            .and_then(|blocks| self.start.checked_add(blocks))?;
+        Some((height, Coin::min(locked, self.step_amount)))
+    }
+
+    /// Returns the release schedule as the heights of the first `limit` releases, together with
+    /// the part of the total amount that is vested from that height on.
+    pub fn schedule(&self, limit: usize) -> Vec<(u32, Coin)> {
+        let mut schedule = Vec::new();
+        if self.step_blocks == 0 || self.step_amount == Coin::ZERO {
+            return schedule;
+        }
+
+        let total_amount = u64::from(self.total_amount);
+        let step_amount = u64::from(self.step_amount);
+        let mut step: u32 = 1;
+        while schedule.len() < limit {
+            let height = match step.checked_mul(self.step_blocks).and_then(|blocks| self.start.checked_add(blocks)) {
+                Some(height) => height,
+                None => break,
+            };
+            let vested = u64::min(total_amount, u64::from(step) * step_amount);
+            schedule.push((height, vested.try_into().unwrap()));
+            if vested == total_amount {
+                break;
+            }
+            step += 1;
+        }
+        schedule
+    }
 }
 
 impl AccountTransactionInteraction for VestingContract {
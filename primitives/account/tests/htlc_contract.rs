@@ -3,12 +3,12 @@ use std::convert::TryInto;
 use beserial::{Deserialize, Serialize, SerializingError};
 use nimiq_hash::{Blake2bHasher, Hasher, Sha256Hasher};
 use nimiq_keys::{Address, KeyPair, PrivateKey};
-use nimiq_account::{AccountError, AccountTransactionInteraction, AccountType, HashedTimeLockedContract};
+use nimiq_account::{Account, AccountError, AccountTransactionInteraction, AccountType, HashedTimeLockedContract, PrunedAccount};
 use nimiq_primitives::coin::Coin;
 use nimiq_primitives::networks::NetworkId;
 use nimiq_transaction::{SignatureProof, Transaction, TransactionError, TransactionFlags};
 use nimiq_transaction::account::AccountTransactionVerification;
-use nimiq_transaction::account::htlc_contract::{AnyHash, HashAlgorithm, ProofType};
+use nimiq_transaction::account::htlc_contract::{AnyHash, HashAlgorithm, ProofType, RevealedPreImage};
 
 const HTLC: &str = "00000000000000001b215589344cf570d36bec770825eae30b73213924786862babbdb05e7c4430612135eb2a836812303daebe368963c60d22098a5e9f1ebcb8e54d0b7beca942a2a0a9d95391804fe8f01000296350000000000000001";
 
@@ -129,6 +129,36 @@ fn it_can_create_contract_from_transaction() {
     }
 }
 
+#[test]
+fn it_can_restore_a_pruned_htlc() {
+    let bytes: Vec<u8> = hex::decode(HTLC).unwrap();
+    let htlc: HashedTimeLockedContract = Deserialize::deserialize(&mut &bytes[..]).unwrap();
+    let pruned = PrunedAccount {
+        address: Address::from([1u8; 20]),
+        account: Account::HTLC(htlc.clone()),
+    };
+
+    let restoration = pruned.restoration().unwrap();
+    assert_eq!(restoration.account_type, AccountType::HTLC);
+    assert_eq!(restoration.value, htlc.total_amount);
+
+    let mut transaction = Transaction::new_contract_creation(
+        restoration.data,
+        htlc.sender.clone(),
+        AccountType::Basic,
+        restoration.account_type,
+        restoration.value,
+        0.try_into().unwrap(),
+        0,
+        NetworkId::Dummy,
+    );
+    transaction.recipient = transaction.contract_creation_address();
+    assert_eq!(AccountType::verify_incoming_transaction(&transaction), Ok(()));
+
+    let restored = HashedTimeLockedContract::create(restoration.value, &transaction, 0).unwrap();
+    assert_eq!(restored, htlc.with_balance(restoration.value));
+}
+
 
 #[test]
 fn it_does_not_support_incoming_transactions() {
@@ -217,6 +247,12 @@ fn it_can_verify_regular_transfer() {
     Serialize::serialize(&recipient_signature_proof, &mut proof);
     tx.proof = proof;
     assert_eq!(AccountType::verify_outgoing_transaction(&tx), Ok(()));
+    assert_eq!(RevealedPreImage::from_proof(&tx.proof), Some(RevealedPreImage {
+        hash_algorithm: HashAlgorithm::Sha256,
+        hash_depth: 1,
+        hash_root: AnyHash::from(<[u8; 32]>::from(Sha256Hasher::default().digest(&[0u8; 32]))),
+        pre_image: AnyHash::from([0u8; 32]),
+    }));
 
     // regular: invalid hash
     let bak = tx.proof[35];
@@ -9,9 +9,9 @@ use nimiq_keys::{Address, KeyPair, PrivateKey};
 use nimiq_primitives::coin::Coin;
 use nimiq_primitives::networks::NetworkId;
 use nimiq_account::{AccountError, AccountTransactionInteraction, AccountType, StakingContract};
-use nimiq_transaction::{SignatureProof, Transaction, TransactionError};
+use nimiq_transaction::{SignatureProof, Transaction, TransactionError, TransactionFlags};
 use nimiq_transaction::account::AccountTransactionVerification;
-use nimiq_transaction::account::staking_contract::StakingTransactionData;
+use nimiq_transaction::account::staking_contract::{StakingTransactionData, ValidatorKeyRotationData};
 use nimiq_account::inherent::{AccountInherentInteraction, Inherent, InherentType};
 
 const CONTRACT_1: &str = "00000000000000000000000000000000";
@@ -44,6 +44,28 @@ fn it_can_de_serialize_a_staking_contract() {
     assert_eq!(hex::encode(bytes_2_out), CONTRACT_2);
 }
 
+#[test]
+fn it_can_iterate_over_stakes() {
+    let bytes: Vec<u8> = hex::decode(CONTRACT_2).unwrap();
+    let contract: StakingContract = Deserialize::deserialize(&mut &bytes[..]).unwrap();
+
+    let active_stakes: Vec<_> = contract.iter_active_stakes().collect();
+    assert_eq!(active_stakes.len(), 2);
+    assert_eq!(active_stakes[0].staker_address(), &Address::from([2u8; 20]));
+    assert_eq!(active_stakes[0].balance(), Coin::from_u64_unchecked(450_000_000u64));
+    assert_eq!(active_stakes[1].staker_address(), &Address::from([0x5eu8; 20]));
+    assert_eq!(active_stakes[1].balance(), Coin::from_u64_unchecked(150_000_000u64));
+    assert_eq!(contract.iter_inactive_stakes().count(), 0);
+
+    let validator_stakes = contract.validator_stakes();
+    assert_eq!(validator_stakes.len(), 2);
+    for stake in &active_stakes {
+        assert_eq!(validator_stakes[stake.validator_key()], stake.balance());
+        assert_eq!(contract.validator_stake(stake.validator_key()), stake.balance());
+        assert!(contract.has_validator(stake.validator_key()));
+    }
+}
+
 #[test]
 fn it_does_not_support_contract_creation() {
     let data: Vec<u8> = Vec::with_capacity(0);
@@ -272,6 +294,144 @@ fn it_can_apply_retiring_transaction() {
     assert_eq!(contract.get_balance(&Address::from(&key_pair.public)), 300_000_000.try_into().unwrap());
 }
 
+#[test]
+fn it_can_apply_key_rotation_transaction() {
+    let key_pair = KeyPair::generate();
+    let bls_pair = BlsKeyPair::generate(&mut thread_rng());
+    let new_bls_pair = BlsKeyPair::generate(&mut thread_rng());
+    let mut contract = make_sample_contract(&key_pair, &bls_pair);
+    let staker_address = Address::from(&key_pair.public);
+
+    let mut data = ValidatorKeyRotationData {
+        validator_key: new_bls_pair.public.compress(),
+        proof_of_knowledge: new_bls_pair.sign(&new_bls_pair.public).compress(),
+    };
+    let mut tx = make_outgoing_transaction();
+    tx.value = 1.try_into().unwrap();
+    tx.recipient = tx.sender.clone();
+    tx.recipient_type = AccountType::Staking;
+    tx.flags = TransactionFlags::SIGNALLING;
+    tx.data = data.serialize_to_vec();
+    tx.proof = SignatureProof::from(key_pair.public, key_pair.sign(&tx.serialize_content())).serialize_to_vec();
+    assert_eq!(tx.verify(NetworkId::Dummy), Ok(()));
+    assert_eq!(AccountType::verify_incoming_transaction(&tx), Ok(()));
+    assert_eq!(AccountType::verify_outgoing_transaction(&tx), Ok(()));
+
+    // Only the fee leaves the stake, which keeps its reward address
+    assert_eq!(contract.commit_outgoing_transaction(&tx, 2), Ok(None));
+    let receipt = contract.commit_incoming_transaction(&tx, 2).unwrap().unwrap();
+    assert_eq!(contract.active_stake_by_address.len(), 1);
+    assert_eq!(contract.active_stake_sorted.len(), 1);
+    assert_eq!(contract.inactive_stake_by_address.len(), 0);
+    assert_eq!(contract.get_balance(&staker_address), 299_999_766.try_into().unwrap());
+    assert!(contract.has_validator(&new_bls_pair.public.compress()));
+    assert!(!contract.has_validator(&bls_pair.public.compress()));
+    let stake = contract.iter_active_stakes().next().unwrap();
+    assert_eq!(stake.reward_address(), &Address::from([3u8; 20]));
+
+    // Revert to original state
+    assert_eq!(contract.revert_incoming_transaction(&tx, 2, None), Err(AccountError::InvalidReceipt));
+    assert_eq!(contract.revert_incoming_transaction(&tx, 2, Some(&receipt)), Ok(()));
+    assert_eq!(contract.revert_outgoing_transaction(&tx, 2, None), Ok(()));
+    assert_eq!(contract.get_balance(&staker_address), 300_000_000.try_into().unwrap());
+    assert_eq!(contract.balance, 300_000_000.try_into().unwrap());
+    assert!(contract.has_validator(&bls_pair.public.compress()));
+
+    // The whole stake can't be rotated, since it would be retired in between
+    let mut tx_2 = tx.clone();
+    tx_2.value = 299_999_766.try_into().unwrap();
+    tx_2.proof = SignatureProof::from(key_pair.public, key_pair.sign(&tx_2.serialize_content())).serialize_to_vec();
+    assert_eq!(contract.check_outgoing_transaction(&tx_2, 3), Err(AccountError::InvalidForSender));
+
+    // Invalid proof of knowledge
+    data.proof_of_knowledge = bls_pair.sign(&new_bls_pair.public).compress();
+    tx.data = data.serialize_to_vec();
+    assert_eq!(AccountType::verify_incoming_transaction(&tx), Err(TransactionError::InvalidData));
+}
+
+#[test]
+fn it_only_rotates_keys_with_the_signalling_flag() {
+    let key_pair = KeyPair::generate();
+    let bls_pair = BlsKeyPair::generate(&mut thread_rng());
+    let new_bls_pair = BlsKeyPair::generate(&mut thread_rng());
+    let mut contract = make_sample_contract(&key_pair, &bls_pair);
+
+    // A self transaction with data but without the flag still retires stake
+    let data = ValidatorKeyRotationData {
+        validator_key: new_bls_pair.public.compress(),
+        proof_of_knowledge: new_bls_pair.sign(&new_bls_pair.public).compress(),
+    };
+    let mut tx = make_outgoing_transaction();
+    tx.recipient = tx.sender.clone();
+    tx.recipient_type = AccountType::Staking;
+    tx.data = data.serialize_to_vec();
+    tx.proof = SignatureProof::from(key_pair.public, key_pair.sign(&tx.serialize_content())).serialize_to_vec();
+    assert_eq!(contract.commit_outgoing_transaction(&tx, 2), Ok(None));
+    assert_eq!(contract.commit_incoming_transaction(&tx, 2), Ok(None));
+    assert_eq!(contract.inactive_stake_by_address.len(), 1);
+    assert!(contract.has_validator(&bls_pair.public.compress()));
+    assert!(!contract.has_validator(&new_bls_pair.public.compress()));
+
+    // Only self transactions of the staking contract can signal
+    let mut tx = make_incoming_transaction();
+    tx.flags = TransactionFlags::SIGNALLING;
+    tx.proof = SignatureProof::from(key_pair.public, key_pair.sign(&tx.serialize_content())).serialize_to_vec();
+    assert_eq!(tx.verify(NetworkId::Dummy), Err(TransactionError::InvalidForRecipient));
+
+    let mut tx = make_outgoing_transaction();
+    tx.recipient_type = AccountType::Basic;
+    tx.flags = TransactionFlags::SIGNALLING;
+    tx.proof = SignatureProof::from(key_pair.public, key_pair.sign(&tx.serialize_content())).serialize_to_vec();
+    assert_eq!(tx.verify(NetworkId::Dummy), Err(TransactionError::InvalidForRecipient));
+}
+
+#[test]
+fn it_cannot_rotate_to_the_key_of_another_staker() {
+    let key_pair = KeyPair::generate();
+    let bls_pair = BlsKeyPair::generate(&mut thread_rng());
+    let mut contract = make_sample_contract(&key_pair, &bls_pair);
+
+    // Another staker with its own validator key
+    let other_key_pair = KeyPair::generate();
+    let other_bls_pair = BlsKeyPair::generate(&mut thread_rng());
+    let mut stake_tx = make_incoming_transaction();
+    stake_tx.sender = Address::from(&other_key_pair.public);
+    stake_tx.data = StakingTransactionData {
+        validator_key: other_bls_pair.public.compress(),
+        reward_address: None,
+        proof_of_knowledge: other_bls_pair.sign(&other_bls_pair.public).compress(),
+    }.serialize_to_vec();
+    assert_eq!(contract.commit_incoming_transaction(&stake_tx, 2), Ok(None));
+
+    let data = ValidatorKeyRotationData {
+        validator_key: other_bls_pair.public.compress(),
+        proof_of_knowledge: other_bls_pair.sign(&other_bls_pair.public).compress(),
+    };
+    let mut tx = make_outgoing_transaction();
+    tx.value = 1.try_into().unwrap();
+    tx.recipient = tx.sender.clone();
+    tx.recipient_type = AccountType::Staking;
+    tx.flags = TransactionFlags::SIGNALLING;
+    tx.data = data.serialize_to_vec();
+    tx.proof = SignatureProof::from(key_pair.public, key_pair.sign(&tx.serialize_content())).serialize_to_vec();
+
+    // The proof of knowledge is valid, but the key belongs to another staker
+    assert_eq!(AccountType::verify_incoming_transaction(&tx), Ok(()));
+    assert_eq!(contract.check_incoming_transaction(&tx, 3), Err(AccountError::InvalidForRecipient));
+    assert_eq!(contract.commit_outgoing_transaction(&tx, 3), Ok(None));
+    assert_eq!(contract.commit_incoming_transaction(&tx, 3), Err(AccountError::InvalidForRecipient));
+    assert!(contract.has_validator(&bls_pair.public.compress()));
+
+    // Rotating to its own key is allowed
+    let data = ValidatorKeyRotationData {
+        validator_key: bls_pair.public.compress(),
+        proof_of_knowledge: bls_pair.sign(&bls_pair.public).compress(),
+    };
+    tx.data = data.serialize_to_vec();
+    tx.proof = SignatureProof::from(key_pair.public, key_pair.sign(&tx.serialize_content())).serialize_to_vec();
+    assert_eq!(contract.check_incoming_transaction(&tx, 3), Ok(()));
+}
+
 #[test]
 fn it_can_verify_unstaking_transaction() {
     let incoming = false;
@@ -2,7 +2,7 @@ use std::convert::{TryFrom, TryInto};
 
 use beserial::{Deserialize, Serialize, SerializingError};
 use nimiq_keys::{Address, KeyPair, PrivateKey};
-use nimiq_account::{AccountError, AccountTransactionInteraction, AccountType, VestingContract};
+use nimiq_account::{Account, AccountError, AccountTransactionInteraction, AccountType, PrunedAccount, VestingContract};
 use nimiq_primitives::coin::Coin;
 use nimiq_primitives::networks::NetworkId;
 use nimiq_transaction::{SignatureProof, Transaction, TransactionError, TransactionFlags};
@@ -172,6 +172,72 @@ fn it_can_create_contract_from_transaction() {
     assert_eq!(VestingContract::create(0.try_into().unwrap(), &transaction, 0), Err(AccountError::InvalidTransaction(TransactionError::InvalidData)))
 }
 
+#[test]
+fn it_can_restore_a_pruned_vesting_contract() {
+    let bytes: Vec<u8> = hex::decode(CONTRACT).unwrap();
+    let contract: VestingContract = Deserialize::deserialize(&mut &bytes[..]).unwrap();
+    let pruned = PrunedAccount {
+        address: Address::from([1u8; 20]),
+        account: Account::Vesting(contract.with_balance(Coin::ZERO)),
+    };
+
+    let restoration = pruned.restoration().unwrap();
+    assert_eq!(restoration.account_type, AccountType::Vesting);
+    assert_eq!(restoration.value, contract.total_amount);
+
+    let mut transaction = Transaction::new_contract_creation(
+        restoration.data,
+        contract.owner.clone(),
+        AccountType::Basic,
+        restoration.account_type,
+        restoration.value,
+        0.try_into().unwrap(),
+        0,
+        NetworkId::Dummy,
+    );
+    transaction.recipient = transaction.contract_creation_address();
+    assert_eq!(AccountType::verify_incoming_transaction(&transaction), Ok(()));
+
+    let restored = VestingContract::create(restoration.value, &transaction, 0).unwrap();
+    assert_eq!(restored, contract);
+
+    // Basic accounts can't be restored
+    let pruned = PrunedAccount {
+        address: Address::from([1u8; 20]),
+        account: Account::INITIAL,
+    };
+    assert_eq!(pruned.restoration(), None);
+}
+
+#[test]
+fn it_can_compute_the_vesting_schedule() {
+    let contract = VestingContract::new(
+        Coin::try_from(250).unwrap(),
+        Address::from([0u8; 20]),
+        100,
+        10,
+        Coin::try_from(100).unwrap(),
+        Coin::try_from(250).unwrap(),
+    );
+
+    assert_eq!(contract.locked_amount(0), Coin::try_from(250).unwrap());
+    assert_eq!(contract.locked_amount(109), Coin::try_from(250).unwrap());
+    assert_eq!(contract.locked_amount(110), Coin::try_from(150).unwrap());
+    assert_eq!(contract.locked_amount(130), Coin::ZERO);
+
+    assert_eq!(contract.next_release(0), Some((110, Coin::try_from(100).unwrap())));
+    assert_eq!(contract.next_release(110), Some((120, Coin::try_from(100).unwrap())));
+    assert_eq!(contract.next_release(125), Some((130, Coin::try_from(50).unwrap())));
+    assert_eq!(contract.next_release(130), None);
+
+    assert_eq!(contract.schedule(10), vec![
+        (110, Coin::try_from(100).unwrap()),
+        (120, Coin::try_from(200).unwrap()),
+        (130, Coin::try_from(250).unwrap()),
+    ]);
+    assert_eq!(contract.schedule(2).len(), 2);
+}
+
 #[test]
 fn it_does_not_support_incoming_transactions() {
     let mut contract = VestingContract {
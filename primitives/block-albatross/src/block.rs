@@ -25,6 +25,7 @@ pub enum Block {
 }
 
 impl Block {
+    /// The latest block version this implementation supports.
     pub const VERSION: u16 = 1;
 
     pub fn verify(&self, network_id: NetworkId) -> Result<(), BlockError> {
@@ -25,7 +25,7 @@ mod view_change;
 pub mod signed;
 
 pub use block::{Block, BlockType, BlockHeader};
-pub use macro_block::{MacroBlock, MacroHeader, MacroExtrinsics, SlotAddresses};
+pub use macro_block::{MacroBlock, MacroHeader, MacroHeaderDiff, MacroHeaderDiffError, MacroExtrinsics, SlotAddresses};
 pub use micro_block::{MicroBlock, MicroHeader, MicroJustification, MicroExtrinsics};
 pub use view_change::{ViewChange, SignedViewChange, ViewChangeProof, ViewChangeProofBuilder, ViewChanges};
 pub use fork_proof::ForkProof;
@@ -37,6 +37,10 @@ use crate::transaction::TransactionError;
 pub enum BlockError {
     #[fail(display = "Unsupported version")]
     UnsupportedVersion,
+    #[fail(display = "Version not active at block number")]
+    InvalidVersion,
+    #[fail(display = "Contains data of unknown extensions")]
+    UnknownExtension,
     #[fail(display = "Block is from the future")]
     FromTheFuture,
     #[fail(display = "Block size exceeded")]
@@ -4,18 +4,18 @@ use std::io;
 
 use failure::Fail;
 
-use beserial::{Deserialize, Serialize};
+use beserial::{Deserialize, DeserializeWithLength, ReadBytesExt, Serialize, SerializeWithLength, SerializingError, WriteBytesExt};
 use bls::bls12_381::CompressedSignature;
 use bls::bls12_381::lazy::LazyPublicKey;
 use collections::bitset::BitSet;
 use collections::compressed_list::CompressedList;
-use hash::{Blake2bHash, Hash, SerializeContent};
+use hash::{Blake2bHash, Blake2bHasher, Hash, Hasher, SerializeContent};
 use keys::Address;
 use primitives::coin::Coin;
 use primitives::policy;
-use primitives::validators::{Slot, Slots};
+use primitives::validators::{Slot, Slots, Validators, ValidatorsDiff};
 
-use crate::BlockError;
+use crate::{Block, BlockError};
 use crate::pbft::PbftProof;
 use crate::signed;
 
@@ -25,14 +25,22 @@ pub enum IntoSlotsError {
     MissingExtrinsics,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Fail)]
+pub enum MacroHeaderDiffError {
+    #[fail(display = "Validators diff doesn't fit the validators of the previous epoch")]
+    InvalidDiff,
+    #[fail(display = "Rebuilt validators don't match the validators hash")]
+    ValidatorsHashMismatch,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MacroBlock {
     pub header: MacroHeader,
     pub justification: Option<PbftProof>,
     pub extrinsics: Option<MacroExtrinsics>
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MacroHeader {
     pub version: u16,
 
@@ -52,16 +60,28 @@ pub struct MacroHeader {
     pub transactions_root: Blake2bHash,
 
     pub timestamp: u64,
+
+    /// Fields introduced by later versions, serialized. Nodes that don't know them yet can still
+    /// deserialize, hash and relay the header. Only encoded from `MacroHeader::EXTENSION_VERSION`
+    /// on, so version 1 headers keep their encoding and hash. Must be empty for all versions known
+    /// so far.
+    pub extension: Vec<u8>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MacroExtrinsics {
+    /// The version of the header these extrinsics belong to. It decides the encoding, but isn't
+    /// serialized itself.
+    pub version: u16,
     /// Staker and reward addresses for the next epoch's validators.
     pub slot_addresses: CompressedList<SlotAddresses>,
     /// The slash fine for the next epoch.
     pub slash_fine: Coin,
     /// The final list of slashes from the previous epoch.
     pub slashed_set: BitSet,
+    /// Fields introduced by later versions, serialized. Only encoded from
+    /// `MacroHeader::EXTENSION_VERSION` on. Must be empty for all versions known so far.
+    pub extension: Vec<u8>,
 }
 
 impl TryInto<Slots> for MacroBlock {
@@ -96,16 +116,19 @@ impl TryInto<Slots> for MacroBlock {
 
 // CHECKME: Check for performance
 impl MacroExtrinsics {
-    pub fn from(slots: Slots, slashed_set: BitSet) -> Self {
+    /// The extrinsics of a macro block with header version `version`.
+    pub fn from(version: u16, slots: Slots, slashed_set: BitSet) -> Self {
         let addresses = slots.iter().map(|slot| SlotAddresses {
             staker_address: slot.staker_address.clone(),
             reward_address: slot.reward_address_opt.as_ref().unwrap_or(&slot.staker_address).clone(),
         });
         let slash_fine = slots.slash_fine();
         MacroExtrinsics {
+            version,
             slot_addresses: addresses.collect(),
             slash_fine,
             slashed_set,
+            extension: Vec::new(),
         }
     }
 }
@@ -116,12 +139,85 @@ pub struct SlotAddresses {
     pub reward_address: Address,
 }
 
+impl MacroHeader {
+    /// The first version whose headers and extrinsics encode the `extension` field.
+    pub const EXTENSION_VERSION: u16 = 2;
+
+    fn has_extension(version: u16) -> bool {
+        version >= Self::EXTENSION_VERSION
+    }
+
+    /// Digest of the state at the end of the epoch this block finalizes. It commits to the state
+    /// root, the transactions root and the validators of the next epoch, so nodes can compare it
+    /// to detect a corrupted chain or state.
+    pub fn epoch_state_digest(&self) -> Blake2bHash {
+        Blake2bHasher::default()
+            .chain(&self.state_root)
+            .chain(&self.transactions_root)
+            .chain(&self.validators_hash())
+            .finish()
+    }
+
+    /// Hash of the serialized list of validators for the next epoch.
+    pub fn validators_hash(&self) -> Blake2bHash {
+        let mut hasher = Blake2bHasher::default();
+        self.validators.serialize(&mut hasher).unwrap();
+        hasher.finish()
+    }
+}
+
+/// A macro header whose validators are encoded as the diff to the validators elected by the
+/// previous macro block. Light clients that know those can rebuild the header, which saves
+/// sending the full list of all slots each epoch.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MacroHeaderDiff {
+    /// The header with an empty list of validators.
+    pub header: MacroHeader,
+    pub validators_diff: ValidatorsDiff,
+    /// `MacroHeader::validators_hash` of the full header, to verify the rebuilt validators.
+    pub validators_hash: Blake2bHash,
+}
+
+impl MacroHeaderDiff {
+    /// Encodes `header` with the validators of the previous macro block `prev_validators`.
+    pub fn new(header: &MacroHeader, prev_validators: &Validators) -> Self {
+        let validators: Validators = header.validators.into_iter().cloned().collect();
+        let mut header_without_validators = header.clone();
+        header_without_validators.validators = CompressedList::empty();
+        MacroHeaderDiff {
+            header: header_without_validators,
+            validators_diff: ValidatorsDiff::between(prev_validators, &validators),
+            validators_hash: header.validators_hash(),
+        }
+    }
+
+    /// Rebuilds the full header from the validators of the previous macro block.
+    pub fn into_header(self, prev_validators: &Validators) -> Result<MacroHeader, MacroHeaderDiffError> {
+        let validators = self.validators_diff.apply(prev_validators)
+            .ok_or(MacroHeaderDiffError::InvalidDiff)?;
+        let mut header = self.header;
+        header.validators = CompressedList::from(validators);
+        if header.validators_hash() != self.validators_hash {
+            return Err(MacroHeaderDiffError::ValidatorsHashMismatch);
+        }
+        Ok(header)
+    }
+}
+
 impl signed::Message for MacroHeader {
     const PREFIX: u8 = signed::PREFIX_PBFT_PROPOSAL;
 }
 
 impl MacroBlock {
     pub fn verify(&self) -> Result<(), BlockError> {
+        // Which version is active at the block's height depends on the network and is checked
+        // by the blockchain.
+        if self.header.version > Block::VERSION {
+            return Err(BlockError::UnsupportedVersion);
+        }
+        if !self.header.extension.is_empty() {
+            return Err(BlockError::UnknownExtension);
+        }
         if self.header.block_number >= 1 && self.justification.is_none() {
             return Err(BlockError::NoJustification);
         }
@@ -129,6 +225,12 @@ impl MacroBlock {
             return Err(BlockError::InvalidValidators);
         }
         if let Some(ref extrinsics) = self.extrinsics {
+            if extrinsics.version != self.header.version {
+                return Err(BlockError::UnsupportedVersion);
+            }
+            if !extrinsics.extension.is_empty() {
+                return Err(BlockError::UnknownExtension);
+            }
             let addr = &extrinsics.slot_addresses;
             if !addr.verify() || addr.len() != policy::SLOTS as usize {
                 return Err(BlockError::InvalidValidators);
@@ -142,6 +244,151 @@ impl MacroBlock {
     }
 }
 
+impl Serialize for MacroHeader {
+    fn serialize<W: WriteBytesExt>(&self, writer: &mut W) -> Result<usize, SerializingError> {
+        if !MacroHeader::has_extension(self.version) && !self.extension.is_empty() {
+            return Err(SerializingError::InvalidValue);
+        }
+        let mut size = 0;
+        size += Serialize::serialize(&self.version, writer)?;
+        size += Serialize::serialize(&self.validators, writer)?;
+        size += Serialize::serialize(&self.block_number, writer)?;
+        size += Serialize::serialize(&self.view_number, writer)?;
+        size += Serialize::serialize(&self.parent_macro_hash, writer)?;
+        size += Serialize::serialize(&self.seed, writer)?;
+        size += Serialize::serialize(&self.parent_hash, writer)?;
+        size += Serialize::serialize(&self.state_root, writer)?;
+        size += Serialize::serialize(&self.extrinsics_root, writer)?;
+        size += Serialize::serialize(&self.transactions_root, writer)?;
+        size += Serialize::serialize(&self.timestamp, writer)?;
+        if MacroHeader::has_extension(self.version) {
+            size += SerializeWithLength::serialize::<u16, W>(&self.extension, writer)?;
+        }
+        Ok(size)
+    }
+
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        size += Serialize::serialized_size(&self.version);
+        size += Serialize::serialized_size(&self.validators);
+        size += Serialize::serialized_size(&self.block_number);
+        size += Serialize::serialized_size(&self.view_number);
+        size += Serialize::serialized_size(&self.parent_macro_hash);
+        size += Serialize::serialized_size(&self.seed);
+        size += Serialize::serialized_size(&self.parent_hash);
+        size += Serialize::serialized_size(&self.state_root);
+        size += Serialize::serialized_size(&self.extrinsics_root);
+        size += Serialize::serialized_size(&self.transactions_root);
+        size += Serialize::serialized_size(&self.timestamp);
+        if MacroHeader::has_extension(self.version) {
+            size += SerializeWithLength::serialized_size::<u16>(&self.extension);
+        }
+        size
+    }
+}
+
+impl Deserialize for MacroHeader {
+    fn deserialize<R: ReadBytesExt>(reader: &mut R) -> Result<Self, SerializingError> {
+        let version: u16 = Deserialize::deserialize(reader)?;
+        Ok(MacroHeader {
+            version,
+            validators: Deserialize::deserialize(reader)?,
+            block_number: Deserialize::deserialize(reader)?,
+            view_number: Deserialize::deserialize(reader)?,
+            parent_macro_hash: Deserialize::deserialize(reader)?,
+            seed: Deserialize::deserialize(reader)?,
+            parent_hash: Deserialize::deserialize(reader)?,
+            state_root: Deserialize::deserialize(reader)?,
+            extrinsics_root: Deserialize::deserialize(reader)?,
+            transactions_root: Deserialize::deserialize(reader)?,
+            timestamp: Deserialize::deserialize(reader)?,
+            extension: if MacroHeader::has_extension(version) {
+                DeserializeWithLength::deserialize::<u16, R>(reader)?
+            } else {
+                Vec::new()
+            },
+        })
+    }
+}
+
+impl Serialize for MacroExtrinsics {
+    fn serialize<W: WriteBytesExt>(&self, writer: &mut W) -> Result<usize, SerializingError> {
+        if !MacroHeader::has_extension(self.version) && !self.extension.is_empty() {
+            return Err(SerializingError::InvalidValue);
+        }
+        let mut size = 0;
+        size += Serialize::serialize(&self.slot_addresses, writer)?;
+        size += Serialize::serialize(&self.slash_fine, writer)?;
+        size += Serialize::serialize(&self.slashed_set, writer)?;
+        if MacroHeader::has_extension(self.version) {
+            size += SerializeWithLength::serialize::<u16, W>(&self.extension, writer)?;
+        }
+        Ok(size)
+    }
+
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        size += Serialize::serialized_size(&self.slot_addresses);
+        size += Serialize::serialized_size(&self.slash_fine);
+        size += Serialize::serialized_size(&self.slashed_set);
+        if MacroHeader::has_extension(self.version) {
+            size += SerializeWithLength::serialized_size::<u16>(&self.extension);
+        }
+        size
+    }
+}
+
+impl MacroExtrinsics {
+    /// Extrinsics don't carry their version, so they can only be deserialized with the version
+    /// of their header.
+    pub fn deserialize_with_version<R: ReadBytesExt>(version: u16, reader: &mut R) -> Result<Self, SerializingError> {
+        Ok(MacroExtrinsics {
+            version,
+            slot_addresses: Deserialize::deserialize(reader)?,
+            slash_fine: Deserialize::deserialize(reader)?,
+            slashed_set: Deserialize::deserialize(reader)?,
+            extension: if MacroHeader::has_extension(version) {
+                DeserializeWithLength::deserialize::<u16, R>(reader)?
+            } else {
+                Vec::new()
+            },
+        })
+    }
+}
+
+impl Serialize for MacroBlock {
+    fn serialize<W: WriteBytesExt>(&self, writer: &mut W) -> Result<usize, SerializingError> {
+        let mut size = 0;
+        size += Serialize::serialize(&self.header, writer)?;
+        size += Serialize::serialize(&self.justification, writer)?;
+        size += Serialize::serialize(&self.extrinsics, writer)?;
+        Ok(size)
+    }
+
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        size += Serialize::serialized_size(&self.header);
+        size += Serialize::serialized_size(&self.justification);
+        size += Serialize::serialized_size(&self.extrinsics);
+        size
+    }
+}
+
+impl Deserialize for MacroBlock {
+    fn deserialize<R: ReadBytesExt>(reader: &mut R) -> Result<Self, SerializingError> {
+        let header: MacroHeader = Deserialize::deserialize(reader)?;
+        let justification = Deserialize::deserialize(reader)?;
+        // Encoded like an `Option`.
+        let is_present: u8 = Deserialize::deserialize(reader)?;
+        let extrinsics = match is_present {
+            0 => None,
+            1 => Some(MacroExtrinsics::deserialize_with_version(header.version, reader)?),
+            _ => return Err(SerializingError::InvalidValue),
+        };
+        Ok(MacroBlock { header, justification, extrinsics })
+    }
+}
+
 impl SerializeContent for MacroHeader {
     fn serialize_content<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> { Ok(self.serialize(writer)?) }
 }
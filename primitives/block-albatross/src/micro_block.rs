@@ -70,6 +70,9 @@ impl MicroHeader {
 }
 
 impl MicroExtrinsics {
+    /// The extra data is prefixed with its length as `u8`.
+    pub const MAX_EXTRA_DATA_SIZE: usize = 255;
+
     pub fn verify(&self, block_height: u32, network_id: NetworkId) -> Result<(), BlockError> {
         // Verify fork proofs.
         let mut previous_proof: Option<&ForkProof> = None;
@@ -54,6 +54,16 @@ impl<M: Message> SignedMessage<M> {
             signature,
         }
     }
+
+    /// Create SignedMessage from message and a signature over its `signing_payload`, e.g. by an
+    /// external signer. The signature is not verified.
+    pub fn from_signature(message: M, signature: Signature, signer_idx: u16) -> Self {
+        Self {
+            message,
+            signer_idx,
+            signature,
+        }
+    }
 }
 
 
@@ -74,6 +84,8 @@ pub const PREFIX_PBFT_COMMIT: u8 = 0x04;
 pub const PREFIX_POKOSK: u8 = 0x05;
 /// prefix to sign a validator info
 pub const PREFIX_VALIDATOR_INFO: u8 = 0x06;
+/// prefix to sign validator heartbeats
+pub const PREFIX_HEARTBEAT: u8 = 0x07;
 
 
 pub trait Message: Clone + Debug + Serialize + Deserialize + SerializeContent + Send + Sync + Sized + PartialEq + 'static {
@@ -86,6 +98,15 @@ pub trait Message: Clone + Debug + Serialize + Deserialize + SerializeContent +
         h.finish()
     }
 
+    /// The bytes that are signed, i.e. the prefix followed by the message. The signature is
+    /// over their Blake2b hash, which is `hash_with_prefix`. This lets an external signer sign the
+    /// message without knowing its format.
+    fn signing_payload(&self) -> Vec<u8> {
+        let mut payload = vec![Self::PREFIX];
+        self.serialize_content(&mut payload).expect("Failed to write message to signing payload.");
+        payload
+    }
+
     fn sign(&self, secret_key: &SecretKey) -> Signature {
         secret_key.sign_hash(self.hash_with_prefix())
     }
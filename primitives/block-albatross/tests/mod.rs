@@ -1,16 +1,16 @@
 use std::convert::{TryFrom, TryInto};
 use std::iter::repeat;
 
-use beserial::Deserialize;
-use nimiq_block_albatross::{MacroBlock, MacroExtrinsics, MacroHeader, SlotAddresses};
+use beserial::{Deserialize, Serialize};
+use nimiq_block_albatross::{Block, BlockError, MacroBlock, MacroExtrinsics, MacroHeader, MacroHeaderDiff, MacroHeaderDiffError, SlotAddresses};
 use nimiq_bls::bls12_381::lazy::LazyPublicKey;
 use nimiq_bls::bls12_381::Signature;
 use nimiq_collections::bitset::BitSet;
 use nimiq_collections::compressed_list::CompressedList;
-use nimiq_hash::{Blake2bHasher, Hasher};
+use nimiq_hash::{Blake2bHash, Blake2bHasher, Hash, Hasher};
 use nimiq_keys::Address;
 use nimiq_primitives::coin::Coin;
-use nimiq_primitives::validators::Slots;
+use nimiq_primitives::validators::{Slots, Validators};
 
 #[test]
 fn it_can_convert_macro_block_into_slots() {
@@ -67,12 +67,15 @@ fn it_can_convert_macro_block_into_slots() {
             extrinsics_root: hash.clone(),
             transactions_root: [0u8; 32].into(),
             timestamp: 0,
+            extension: Vec::new(),
         },
         justification: None,
         extrinsics: Some(MacroExtrinsics {
+            version: 1,
             slot_addresses: slot_addresses.clone(),
             slash_fine: Coin::try_from(8u64).unwrap(),
             slashed_set: BitSet::new(),
+            extension: Vec::new(),
         }),
     };
 
@@ -87,3 +90,179 @@ fn it_can_convert_macro_block_into_slots() {
             assert_eq!(&addresses.reward_address, slot.reward_address());
         });
 }
+
+#[test]
+fn epoch_state_digest_ignores_unrelated_fields() {
+    let hash = Blake2bHasher::default().digest(&vec![]);
+    let signature_bytes = hex::decode("b9674ac1bbb4770ad291acc2b860e9120c609893a3840fbfdf2946911f00255f8995974c9b0ef3835ab4442ccbac9739").unwrap();
+    let signature = Signature::deserialize_from_vec(&signature_bytes).unwrap();
+
+    let header = MacroHeader {
+        version: 1,
+        validators: Vec::<LazyPublicKey>::new().into_iter().collect(),
+        block_number: 42,
+        view_number: 0,
+        parent_macro_hash: hash.clone(),
+        seed: signature.compress(),
+        parent_hash: hash.clone(),
+        state_root: hash.clone(),
+        extrinsics_root: hash.clone(),
+        transactions_root: [0u8; 32].into(),
+        timestamp: 0,
+        extension: Vec::new(),
+    };
+    let digest = header.epoch_state_digest();
+
+    let mut other_view = header.clone();
+    other_view.view_number = 1;
+    other_view.timestamp = 1000;
+    assert_eq!(other_view.epoch_state_digest(), digest);
+
+    let mut other_state = header.clone();
+    other_state.state_root = [1u8; 32].into();
+    assert_ne!(other_state.epoch_state_digest(), digest);
+
+    let mut other_transactions = header;
+    other_transactions.transactions_root = hash;
+    assert_ne!(other_transactions.epoch_state_digest(), digest);
+}
+
+fn genesis_header() -> MacroHeader {
+    let signature_bytes = hex::decode("b9674ac1bbb4770ad291acc2b860e9120c609893a3840fbfdf2946911f00255f8995974c9b0ef3835ab4442ccbac9739").unwrap();
+    let signature = Signature::deserialize_from_vec(&signature_bytes).unwrap();
+
+    MacroHeader {
+        version: Block::VERSION,
+        validators: Vec::<LazyPublicKey>::new().into_iter().collect(),
+        block_number: 0,
+        view_number: 0,
+        parent_macro_hash: [0u8; 32].into(),
+        seed: signature.compress(),
+        parent_hash: [0u8; 32].into(),
+        state_root: [0u8; 32].into(),
+        extrinsics_root: [0u8; 32].into(),
+        transactions_root: [0u8; 32].into(),
+        timestamp: 0,
+        extension: Vec::new(),
+    }
+}
+
+#[test]
+fn it_keeps_the_encoding_of_version_1_headers() {
+    let header = genesis_header();
+    let mut bytes = Vec::new();
+    header.version.serialize(&mut bytes).unwrap();
+    header.validators.serialize(&mut bytes).unwrap();
+    header.block_number.serialize(&mut bytes).unwrap();
+    header.view_number.serialize(&mut bytes).unwrap();
+    header.parent_macro_hash.serialize(&mut bytes).unwrap();
+    header.seed.serialize(&mut bytes).unwrap();
+    header.parent_hash.serialize(&mut bytes).unwrap();
+    header.state_root.serialize(&mut bytes).unwrap();
+    header.extrinsics_root.serialize(&mut bytes).unwrap();
+    header.transactions_root.serialize(&mut bytes).unwrap();
+    header.timestamp.serialize(&mut bytes).unwrap();
+    assert_eq!(header.serialize_to_vec(), bytes);
+    assert_eq!(header.serialized_size(), bytes.len());
+    assert_eq!(MacroHeader::deserialize_from_vec(&bytes).unwrap(), header);
+
+    // Version 1 headers can't carry extension data.
+    let mut extended = header;
+    extended.extension = vec![1, 2, 3];
+    assert!(extended.serialize(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn it_deserializes_unknown_extensions() {
+    let mut header = genesis_header();
+    header.version = MacroHeader::EXTENSION_VERSION;
+    let mut extended = header.clone();
+    extended.extension = vec![1, 2, 3];
+
+    let bytes = extended.serialize_to_vec();
+    assert_eq!(bytes.len(), header.serialized_size() + 3);
+    let deserialized = MacroHeader::deserialize_from_vec(&bytes).unwrap();
+    assert_eq!(deserialized, extended);
+    assert_ne!(deserialized.hash::<Blake2bHash>(), header.hash::<Blake2bHash>());
+
+    let block = MacroBlock { header: extended, justification: None, extrinsics: None };
+    assert_eq!(block.verify(), Err(BlockError::UnsupportedVersion));
+}
+
+#[test]
+fn it_deserializes_extrinsics_with_the_header_version() {
+    let extrinsics = MacroExtrinsics {
+        version: 1,
+        slot_addresses: CompressedList::empty(),
+        slash_fine: Coin::ZERO,
+        slashed_set: BitSet::new(),
+        extension: Vec::new(),
+    };
+    let block = MacroBlock { header: genesis_header(), justification: None, extrinsics: Some(extrinsics.clone()) };
+    let bytes = block.serialize_to_vec();
+    assert_eq!(bytes.len(), block.header.serialized_size() + 1 + 1 + extrinsics.serialized_size());
+    assert_eq!(MacroBlock::deserialize_from_vec(&bytes).unwrap(), block);
+
+    let mut header = genesis_header();
+    header.version = MacroHeader::EXTENSION_VERSION;
+    let extrinsics = MacroExtrinsics { version: MacroHeader::EXTENSION_VERSION, extension: vec![4, 5], ..extrinsics };
+    let block = MacroBlock { header, justification: None, extrinsics: Some(extrinsics) };
+    let bytes = block.serialize_to_vec();
+    assert_eq!(MacroBlock::deserialize_from_vec(&bytes).unwrap(), block);
+}
+
+#[test]
+fn it_rejects_unknown_macro_block_versions() {
+    let mut header = genesis_header();
+    header.version = Block::VERSION + 1;
+    let block = MacroBlock { header, justification: None, extrinsics: None };
+    assert_eq!(block.verify(), Err(BlockError::UnsupportedVersion));
+}
+
+fn validators(allocation: &[(u16, &str)]) -> Validators {
+    let mut allocation: Vec<(u16, LazyPublicKey)> = allocation.iter()
+        .map(|(num, entry)| (*num, LazyPublicKey::deserialize_from_vec(&hex::decode(entry).unwrap()).unwrap()))
+        .collect();
+    // Validators are sorted by descending public key.
+    allocation.sort_by(|(_, a), (_, b)| b.cmp(a));
+    allocation.into_iter()
+        .flat_map(|(num, key)| repeat(key).take(num as usize))
+        .collect()
+}
+
+#[test]
+fn it_rebuilds_macro_headers_from_validator_diffs() {
+    let key_a = "828aa810f80b9e200bb3310a3f837a8b1642e14440ec65ba8eaf801b9e1b81e69adc706b1ba6ed844cc793621dbd5e220ab235eac6d2b03c14e7a4da200759fee5f15903b9ef07602f7d50346fb25202f399affec1878cbfaa64cccdf0054cc6";
+    let key_b = "8338ee9e2ff9a21e07ecccf5fb2bc184db64b560389b6ef1ef215e3747b1934e40337a18baa012b11181944764cfd87104d83eb43629adaee0ac158552edc4fe2a171d6bdd2144b97aa845511fd4c12608bba777546ffeaf7782885d281d4e43";
+    let key_c = "accc156ac10d2d1cc7fc0c565acea9295e2d258608f280c076b4679c5a465fb9fcd8f22c6f9179cd8f7d63aaa04b9d3a088b1f3764cb93c67dc3a21c94666f5b729fa9f058ad65eb023aeaaaa2c39112bac4c613374d82a0e3407df4595d1535";
+    let key_d = "abdaf5ac13036550362c2d3c5f1848fd6ab1898c75311381bd022d6a2a7909d526ad7a6aaafbaf8f64f11a3af5f220fa0a150b022394ff5da765016b7e6a2525fbe63c65b2e382989de3ecb04038e24c9f782e7965c2b3ec179c7715ecf7f191";
+    let prev_validators = validators(&[(127, key_a), (129, key_b), (256, key_c)]);
+    let next_validators = validators(&[(127, key_a), (130, key_c), (255, key_d)]);
+
+    let mut header = genesis_header();
+    header.validators = next_validators.iter().collect();
+
+    let header_diff = MacroHeaderDiff::new(&header, &prev_validators);
+    assert_eq!(header_diff.validators_diff.added.len(), 1);
+    assert_eq!(header_diff.validators_diff.removed.len(), 1);
+    assert_eq!(header_diff.validators_diff.changed.len(), 1);
+    // Unchanged validators are only sent as their hash.
+    let unchanged = MacroHeaderDiff::new(&header, &next_validators);
+    assert!(unchanged.validators_diff.is_empty());
+    assert!(unchanged.serialized_size() < header.serialized_size());
+    assert_eq!(header_diff.clone().into_header(&prev_validators).unwrap(), header);
+
+    // The diff doesn't fit validators without `key_b`.
+    let other_validators = validators(&[(127, key_a), (385, key_c)]);
+    match header_diff.clone().into_header(&other_validators) {
+        Err(MacroHeaderDiffError::InvalidDiff) => {},
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    let mut wrong_hash = header_diff;
+    wrong_hash.validators_hash = [0u8; 32].into();
+    match wrong_hash.into_header(&prev_validators) {
+        Err(MacroHeaderDiffError::ValidatorsHashMismatch) => {},
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
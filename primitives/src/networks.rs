@@ -24,6 +24,15 @@ impl NetworkId {
             _ => false,
         }
     }
+
+    /// Whether this is a local Albatross network for development and testing, on which e.g. the
+    /// chain may be controlled via RPC.
+    pub fn is_dev(self) -> bool {
+        match self {
+            NetworkId::DevAlbatross | NetworkId::UnitAlbatross => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Fail, Debug)]
@@ -5,6 +5,8 @@ use num_traits::pow;
 use parking_lot::RwLock;
 #[cfg(feature = "coin")]
 use crate::coin::Coin;
+#[cfg(feature = "networks")]
+use crate::networks::NetworkId;
 use fixed_unsigned::types::FixedUnsigned10;
 
 
@@ -30,6 +32,16 @@ pub const TRANSACTION_VALIDITY_WINDOW: u32 = 120;
 /// Number of blocks a transaction is valid with Albatross consensus.
 pub const TRANSACTION_VALIDITY_WINDOW_ALBATROSS: u32 = 7200;
 
+/// Number of blocks a transaction is valid on the given network.
+#[cfg(feature = "networks")]
+pub fn transaction_validity_window(network_id: NetworkId) -> u32 {
+    if network_id.is_albatross() {
+        TRANSACTION_VALIDITY_WINDOW_ALBATROSS
+    } else {
+        TRANSACTION_VALIDITY_WINDOW
+    }
+}
+
 /// Total supply in units.
 pub const TOTAL_SUPPLY: u64 = 2_100_000_000_000_000;
 
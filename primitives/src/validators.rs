@@ -1,6 +1,8 @@
 extern crate nimiq_bls as bls;
 extern crate nimiq_keys as keys;
 
+use std::collections::BTreeMap;
+
 use beserial::{Deserialize, Serialize};
 use bls::bls12_381::lazy::LazyPublicKey;
 use keys::Address;
@@ -97,3 +99,74 @@ impl From<Slots> for Validators {
             .collect()
     }
 }
+
+/// The changes from the validators of one epoch to those of the next.
+///
+/// Validator lists are sorted by public key (see `StakingContract::select_validators`), so
+/// `apply` can rebuild the new list from the previous one and the diff alone.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorsDiff {
+    /// Validators that didn't have slots in the previous epoch, with their number of slots.
+    #[beserial(len_type(u16))]
+    pub added: Vec<Validator>,
+    /// Validators that don't have slots anymore.
+    #[beserial(len_type(u16))]
+    pub removed: Vec<LazyPublicKey>,
+    /// Validators of both epochs whose number of slots changed, with their new number of slots.
+    #[beserial(len_type(u16))]
+    pub changed: Vec<Validator>,
+}
+
+impl ValidatorsDiff {
+    pub fn between(old: &Validators, new: &Validators) -> Self {
+        let old_slots = Self::slots_by_key(old);
+        let new_slots = Self::slots_by_key(new);
+
+        let mut diff = ValidatorsDiff::default();
+        for (key, &num_slots) in new_slots.iter() {
+            match old_slots.get(key) {
+                None => diff.added.push(Group(num_slots, (*key).clone())),
+                Some(&old_num_slots) if old_num_slots != num_slots => diff.changed.push(Group(num_slots, (*key).clone())),
+                Some(_) => {},
+            }
+        }
+        diff.removed = old_slots.keys()
+            .filter(|key| !new_slots.contains_key(*key))
+            .map(|key| (*key).clone())
+            .collect();
+        diff
+    }
+
+    /// Rebuilds the validators of the next epoch from `old`. Returns `None` if the diff doesn't
+    /// fit `old`, e.g. because it removes a validator that `old` doesn't contain.
+    pub fn apply(&self, old: &Validators) -> Option<Validators> {
+        let mut slots: BTreeMap<LazyPublicKey, u16> = Self::slots_by_key(old).into_iter()
+            .map(|(key, num_slots)| (key.clone(), num_slots))
+            .collect();
+        for key in self.removed.iter() {
+            slots.remove(key)?;
+        }
+        for Group(num_slots, key) in self.changed.iter() {
+            *slots.get_mut(key)? = *num_slots;
+        }
+        for Group(num_slots, key) in self.added.iter() {
+            if slots.insert(key.clone(), *num_slots).is_some() {
+                return None;
+            }
+        }
+        // Validators are sorted by descending public key.
+        Some(GroupedList(slots.into_iter().rev().map(|(key, num_slots)| Group(num_slots, key)).collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    fn slots_by_key(validators: &Validators) -> BTreeMap<&LazyPublicKey, u16> {
+        let mut slots = BTreeMap::new();
+        for Group(num_slots, key) in validators.iter_groups() {
+            *slots.entry(key).or_insert(0) += *num_slots;
+        }
+        slots
+    }
+}
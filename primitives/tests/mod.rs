@@ -7,3 +7,5 @@ extern crate fixed_unsigned;
 
 #[cfg(feature = "coin")]
 mod coin;
+#[cfg(feature = "networks")]
+mod networks;
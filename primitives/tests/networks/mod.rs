@@ -0,0 +1,12 @@
+use primitives::networks::NetworkId;
+
+#[test]
+fn only_albatross_dev_networks_are_dev_networks() {
+    assert!(NetworkId::DevAlbatross.is_dev());
+    assert!(NetworkId::UnitAlbatross.is_dev());
+
+    assert!(!NetworkId::Dev.is_dev());
+    assert!(!NetworkId::Test.is_dev());
+    assert!(!NetworkId::TestAlbatross.is_dev());
+    assert!(!NetworkId::Main.is_dev());
+}
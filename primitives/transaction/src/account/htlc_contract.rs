@@ -111,6 +111,35 @@ impl AccountTransactionVerification for HashedTimeLockedContractVerifier {
     }
 }
 
+/// The pre-image revealed by a regular transfer from an HTLC.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RevealedPreImage {
+    pub hash_algorithm: HashAlgorithm,
+    /// How often `pre_image` has to be hashed to get `hash_root`
+    pub hash_depth: u8,
+    pub hash_root: AnyHash,
+    pub pre_image: AnyHash,
+}
+
+impl RevealedPreImage {
+    /// Extracts the pre-image from the proof of a transaction from an HTLC. Returns `None`, if the
+    /// proof isn't a regular transfer. The proof is not verified.
+    pub fn from_proof(proof: &[u8]) -> Option<Self> {
+        let proof_buf = &mut &proof[..];
+        let proof_type: ProofType = Deserialize::deserialize(proof_buf).ok()?;
+        if proof_type != ProofType::RegularTransfer {
+            return None;
+        }
+
+        Some(RevealedPreImage {
+            hash_algorithm: Deserialize::deserialize(proof_buf).ok()?,
+            hash_depth: Deserialize::deserialize(proof_buf).ok()?,
+            hash_root: Deserialize::deserialize(proof_buf).ok()?,
+            pre_image: Deserialize::deserialize(proof_buf).ok()?,
+        })
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum HashAlgorithm {
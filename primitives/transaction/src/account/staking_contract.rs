@@ -28,6 +28,9 @@ impl AccountTransactionVerification for StakingContractVerifier {
                 warn!("Stake value below minimum");
                 return Err(TransactionError::InvalidForRecipient);
             }
+        } else if transaction.flags.contains(TransactionFlags::SIGNALLING) {
+            // Key rotation transaction
+            ValidatorKeyRotationData::parse(transaction)?.verify()?;
         }
 
         Ok(())
@@ -73,11 +76,43 @@ impl StakingTransactionData {
     /// public keys `pk_B + (pk_A - pk_B) = pk_B`.
     /// Alternatives would be to replace the proof of knowledge by a zero-knowledge proof.
     pub fn verify(&self) -> Result<(), TransactionError> {
-        if !self.validator_key.uncompress().map_err(|_| TransactionError::InvalidData)?
-            .verify(&self.validator_key,
-                    &self.proof_of_knowledge.uncompress().map_err(|_| TransactionError::InvalidData)?) {
-            return Err(TransactionError::InvalidData)
+        verify_proof_of_knowledge(&self.validator_key, &self.proof_of_knowledge)
+    }
+}
+
+/// Data of a self transaction of the staking contract that replaces the validator key of a stake.
+/// Such a transaction is marked with `TransactionFlags::SIGNALLING`, without it a self transaction
+/// retires stake, whatever its data.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ValidatorKeyRotationData {
+    pub validator_key: BlsPublicKey,
+    pub proof_of_knowledge: BlsSignature,
+}
+
+impl ValidatorKeyRotationData {
+    pub fn parse(transaction: &Transaction) -> Result<Self, TransactionError> {
+        let reader = &mut &transaction.data[..];
+        let data = Deserialize::deserialize(reader)?;
+
+        // Ensure that transaction data has been fully read.
+        if reader.read_u8().is_ok() {
+            return Err(TransactionError::InvalidData);
         }
-        Ok(())
+
+        Ok(data)
+    }
+
+    /// The proof of knowledge has the same caveats as for `StakingTransactionData`.
+    pub fn verify(&self) -> Result<(), TransactionError> {
+        verify_proof_of_knowledge(&self.validator_key, &self.proof_of_knowledge)
+    }
+}
+
+fn verify_proof_of_knowledge(validator_key: &BlsPublicKey, proof_of_knowledge: &BlsSignature) -> Result<(), TransactionError> {
+    if !validator_key.uncompress().map_err(|_| TransactionError::InvalidData)?
+        .verify(validator_key,
+                &proof_of_knowledge.uncompress().map_err(|_| TransactionError::InvalidData)?) {
+        return Err(TransactionError::InvalidData)
     }
+    Ok(())
 }
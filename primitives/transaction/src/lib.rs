@@ -58,6 +58,9 @@ bitflags! {
     #[derive(Default, Serialize)]
     pub struct TransactionFlags: u8 {
         const CONTRACT_CREATION = 0b1;
+        /// A self transaction of the staking contract that signals a change to a stake instead of
+        /// retiring it, e.g. a validator key rotation.
+        const SIGNALLING = 0b10;
     }
 }
 
@@ -252,6 +255,12 @@ impl Transaction {
             None => return Err(TransactionError::Overflow),
         }
 
+        // Only self transactions of the staking contract can signal.
+        if self.flags.contains(TransactionFlags::SIGNALLING)
+            && (self.recipient_type != AccountType::Staking || self.sender != self.recipient) {
+            return Err(TransactionError::InvalidForRecipient);
+        }
+
         // Check transaction validity for sender account.
         AccountType::verify_outgoing_transaction(&self)?;
 
@@ -268,14 +277,13 @@ impl Transaction {
     }
 
     pub fn is_valid_at(&self, block_height: u32) -> bool {
-        let window = if self.network_id.is_albatross() {
-            policy::TRANSACTION_VALIDITY_WINDOW_ALBATROSS
-        }
-        else {
-            policy::TRANSACTION_VALIDITY_WINDOW
-        };
         block_height >= self.validity_start_height
-            && block_height < self.validity_start_height + window
+            && block_height < self.validity_end_height()
+    }
+
+    /// The first block height at which the transaction is not valid anymore.
+    pub fn validity_end_height(&self) -> u32 {
+        self.validity_start_height.saturating_add(policy::transaction_validity_window(self.network_id))
     }
 
     pub fn contract_creation_address(&self) -> Address {
@@ -72,3 +72,17 @@ fn it_can_serialize_basic_transaction() {
     assert_eq!(size, t.serialized_size());
     assert_eq!(hex::encode(v2), BASIC_TRANSACTION);
 }
+
+#[test]
+fn it_is_valid_within_its_validity_window() {
+    let v: Vec<u8> = hex::decode(BASIC_TRANSACTION).unwrap();
+    let t: Transaction = Deserialize::deserialize(&mut &v[..]).unwrap();
+    assert_eq!(t.validity_end_height(), 104000 + 120);
+    assert!(!t.is_valid_at(103999));
+    assert!(t.is_valid_at(104000));
+    assert!(t.is_valid_at(104119));
+    assert!(!t.is_valid_at(104120));
+
+    let t = Transaction::new_basic(t.sender, t.recipient, t.value, t.fee, 104000, NetworkId::DevAlbatross);
+    assert_eq!(t.validity_end_height(), 104000 + 7200);
+}
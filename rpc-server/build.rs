@@ -0,0 +1,156 @@
+//! Extracts the RPC methods of all handler modules and their doc comments into
+//! `$OUT_DIR/rpc_methods.json`, from which the OpenAPI description is generated (see `openapi.rs`).
+//!
+//! The documentation of a method is the doc comment of the function it maps to in
+//! `rpc_module_methods!`. The doc comments are only extracted here, they are parsed by
+//! `openapi::MethodDoc`, which is tested against the handlers.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+struct Method {
+    name: String,
+    function: String,
+    /// Whether the function is called on a field, e.g. `generic.block_number`.
+    delegated: bool,
+}
+
+/// The name of the function declared on `line`, if any. Visibility and qualifiers like `async`
+/// may precede `fn`.
+fn function_name(line: &str) -> Option<String> {
+    let mut words = line.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == "fn" {
+            let name: String = words.next()?.chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            return if name.is_empty() { None } else { Some(name) };
+        }
+        let qualifier = word == "pub" || word.starts_with("pub(") || word == "const" || word == "unsafe"
+            || word == "async" || word == "extern" || word.starts_with('"');
+        if !qualifier {
+            return None;
+        }
+    }
+    None
+}
+
+/// Doc comments by function name. A doc comment belongs to the function that follows it,
+/// attributes in between are skipped. Any other item in between discards it.
+fn parse_docs(source: &str) -> HashMap<String, Vec<String>> {
+    let mut docs = HashMap::new();
+    let mut buffer: Vec<String> = Vec::new();
+    for line in source.lines() {
+        let line = line.trim();
+        if line.starts_with("///") {
+            let doc = &line[3..];
+            buffer.push(if doc.starts_with(' ') { &doc[1..] } else { doc }.to_string());
+        }
+        else if line.starts_with("#[") || line.is_empty() && !buffer.is_empty() {
+            continue;
+        }
+        else {
+            if let Some(name) = function_name(line) {
+                if !buffer.is_empty() {
+                    docs.insert(name, buffer.clone());
+                }
+            }
+            buffer.clear();
+        }
+    }
+    docs
+}
+
+fn parse_methods(source: &str) -> Vec<Method> {
+    let mut methods = Vec::new();
+    let mut in_methods = false;
+    for line in source.lines() {
+        let line = line.trim();
+        if line.starts_with("rpc_module_methods!") {
+            in_methods = true;
+        }
+        else if in_methods && line.starts_with('}') {
+            in_methods = false;
+        }
+        else if in_methods && line.starts_with('"') {
+            let mut parts = line.splitn(2, "=>");
+            let name = parts.next().unwrap().trim().trim_matches('"').to_string();
+            let target = parts.next().unwrap_or("").trim().trim_end_matches(',').trim();
+            methods.push(Method {
+                name,
+                function: target.rsplit('.').next().unwrap().to_string(),
+                delegated: target.contains('.'),
+            });
+        }
+    }
+    methods
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn main() {
+    let handlers_dir = Path::new("src/handlers");
+    println!("cargo:rerun-if-changed={}", handlers_dir.display());
+
+    let mut sources = Vec::new();
+    for entry in fs::read_dir(handlers_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map(|ext| ext == "rs").unwrap_or(false) {
+            println!("cargo:rerun-if-changed={}", path.display());
+            let module = path.file_stem().unwrap().to_str().unwrap().to_string();
+            let source = fs::read_to_string(&path).unwrap();
+            sources.push((module, source));
+        }
+    }
+    sources.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let docs: Vec<(String, HashMap<String, Vec<String>>)> = sources.iter()
+        .map(|(module, source)| (module.clone(), parse_docs(source)))
+        .collect();
+
+    let mut modules = Vec::new();
+    for (module, source) in &sources {
+        let methods: Vec<String> = parse_methods(source).iter()
+            .map(|method| {
+                // Delegated methods are usually implemented by the generic handler, e.g.
+                // `blockchain` for `blockchain_albatross`.
+                let mut candidates: Vec<&(String, HashMap<String, Vec<String>>)> = docs.iter()
+                    .filter(|(other, _)| !method.delegated || other != module)
+                    .collect();
+                candidates.sort_by_key(|(other, _)| (other != module, !module.starts_with(other.as_str())));
+                let doc = candidates.iter()
+                    .filter_map(|(_, docs)| docs.get(&method.function))
+                    .next()
+                    .map(|lines| lines.join("\n"))
+                    .unwrap_or_else(|| {
+                        println!("cargo:warning=RPC method {} ({}::{}) isn't documented", method.name, module, method.function);
+                        String::new()
+                    });
+                format!("{{\"name\":{},\"doc\":{}}}", json_string(&method.name), json_string(&doc))
+            })
+            .collect();
+        if !methods.is_empty() {
+            modules.push(format!("{}:[{}]", json_string(module), methods.join(",")));
+        }
+    }
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("rpc_methods.json"), format!("{{{}}}", modules.join(","))).unwrap();
+}
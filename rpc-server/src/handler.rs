@@ -1,39 +1,60 @@
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
 
-use parking_lot::RwLock;
+use futures::{future, IntoFuture};
+use parking_lot::{Mutex, RwLock};
 use json::{Array, JsonValue};
 
+use utils::rate_limit::RateLimiter;
+
 use crate::JsonRpcConfig;
 use crate::error::AuthenticationError;
 use crate::jsonrpc;
 use crate::handlers::Module;
+use crate::openapi;
+
+/// Result of a method call. Methods that wait for something return a future that resolves
+/// later, instead of blocking the server.
+pub type MethodFuture = jsonrpc::CallFuture;
 
 pub struct Method {
-    f: Box<dyn Fn(&[JsonValue]) -> Result<JsonValue, JsonValue> + Send + Sync>
+    f: Box<dyn Fn(&[JsonValue]) -> MethodFuture + Send + Sync>
 }
 
 impl Method {
-    pub fn new<F>(f: F) -> Self
-        where F: Fn(&[JsonValue]) -> Result<JsonValue, JsonValue> + Send + Sync + 'static
+    /// Creates a method from a function returning either a `Result` or a `MethodFuture`.
+    pub fn new<F, R>(f: F) -> Self
+        where F: Fn(&[JsonValue]) -> R + Send + Sync + 'static,
+              R: IntoFuture<Item=JsonValue, Error=JsonValue>,
+              R::Future: Send + 'static,
     {
-        Self { f: Box::new(f) }
+        Self { f: Box::new(move |params| Box::new(f(params).into_future())) }
     }
 
-    pub fn call(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+    pub fn call(&self, params: &[JsonValue]) -> MethodFuture {
         (self.f)(params)
     }
 }
 
 pub struct Handler {
     pub methods: RwLock<HashMap<&'static str, Method>>,
+    /// Names of the added modules, see `Module::name`
+    pub modules: RwLock<Vec<&'static str>>,
     pub config: Arc<JsonRpcConfig>,
+    /// Limit the method calls of each client if `JsonRpcConfig::rate_limit` is set
+    rate_limits: Mutex<HashMap<IpAddr, Arc<RateLimiter>>>,
 }
 
 impl Handler {
+    /// Number of clients above which the rate limits that refilled completely are dropped
+    const MAX_RATE_LIMITED_CLIENTS: usize = 1024;
+
     pub fn new(config: JsonRpcConfig) -> Self {
         Handler {
             methods: RwLock::new(HashMap::new()),
+            modules: RwLock::new(Vec::new()),
+            rate_limits: Mutex::new(HashMap::new()),
             config: Arc::new(config),
         }
     }
@@ -44,14 +65,51 @@ impl Handler {
     }
 
     pub fn add_module<M: Module>(&self, module: M) {
+        self.modules.write().push(module.name());
         for (name, method) in module.methods() {
             self.register_method(name, method)
         }
     }
+
+    /// Like `add_module`, but the methods fail with the message returned by `guard` instead of
+    /// being called while `guard` fails.
+    pub fn add_guarded_module<M, G>(&self, module: M, guard: G)
+        where M: Module,
+              G: Fn() -> Result<(), String> + Send + Sync + 'static
+    {
+        self.modules.write().push(module.name());
+        let guard = Arc::new(guard);
+        for (name, method) in module.methods() {
+            let guard = Arc::clone(&guard);
+            self.register_method(name, Method::new(move |params| -> MethodFuture {
+                match guard() {
+                    Ok(()) => method.call(params),
+                    Err(message) => Box::new(future::err(object!{"message" => message})),
+                }
+            }))
+        }
+    }
+
+    /// Determines whether a method call of `client` is still within its rate limit.
+    fn note_call(&self, client: IpAddr) -> bool {
+        let rate_limit = match self.config.rate_limit {
+            Some(rate_limit) => rate_limit,
+            None => return true,
+        };
+
+        let mut rate_limits = self.rate_limits.lock();
+        if rate_limits.len() >= Self::MAX_RATE_LIMITED_CLIENTS && !rate_limits.contains_key(&client) {
+            // A limit that refilled completely is no different from a new one.
+            rate_limits.retain(|_, limiter| limiter.num_allowed() < rate_limit);
+        }
+        rate_limits.entry(client)
+            .or_insert_with(|| RateLimiter::new_per_minute("rpc_client_calls", rate_limit))
+            .note_single()
+    }
 }
 
 impl jsonrpc::Handler for Handler {
-    fn call_method(&self, name: &str, params: Array) -> Option<Result<JsonValue, JsonValue>> {
+    fn call_method(&self, client: IpAddr, name: &str, params: Array) -> Option<MethodFuture> {
         trace!("RPC method called: {}", name);
 
         if !self.config.methods.is_empty() && !self.config.methods.contains(name) {
@@ -60,7 +118,20 @@ impl jsonrpc::Handler for Handler {
             return None
         }
 
-        self.methods.read().get(name).map(|h| h.call(&params))
+        let methods = self.methods.read();
+        let method = methods.get(name)?;
+        if !self.note_call(client) {
+            info!("RPC call to {} from {} rejected - rate limit exceeded", name, client);
+            return Some(Box::new(future::err(object!{"message" => "Rate limit exceeded"})));
+        }
+        Some(method.call(&params))
+    }
+
+    fn describe(&self) -> Option<JsonValue> {
+        let methods = self.methods.read();
+        Some(openapi::describe(&self.modules.read(), |name| {
+            methods.contains_key(name) && (self.config.methods.is_empty() || self.config.methods.contains(name))
+        }))
     }
 
     fn authorize(&self, username: &str, password: &str) -> Result<(), AuthenticationError> {
@@ -71,3 +142,56 @@ impl jsonrpc::Handler for Handler {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use futures::Future;
+
+    use crate::jsonrpc::Handler as _;
+
+    use super::*;
+
+    fn handler(rate_limit: Option<usize>) -> Handler {
+        let handler = Handler::new(JsonRpcConfig {
+            credentials: None,
+            methods: HashSet::new(),
+            allowip: (),
+            corsdomain: Vec::new(),
+            rate_limit,
+        });
+        handler.register_method("ping", Method::new(|_params| -> Result<JsonValue, JsonValue> { Ok("pong".into()) }));
+        handler
+    }
+
+    fn ping(handler: &Handler, client: IpAddr) -> Result<JsonValue, JsonValue> {
+        handler.call_method(client, "ping", Vec::new()).unwrap().wait()
+    }
+
+    #[test]
+    fn it_limits_the_calls_of_each_client() {
+        let handler = handler(Some(2));
+        let client1 = IpAddr::from([10, 0, 0, 1]);
+        let client2 = IpAddr::from([10, 0, 0, 2]);
+
+        assert!(ping(&handler, client1).is_ok());
+        assert!(ping(&handler, client1).is_ok());
+        assert_eq!(ping(&handler, client1), Err(object!{"message" => "Rate limit exceeded"}));
+
+        // The calls of one client don't count towards the limit of another one.
+        assert!(ping(&handler, client2).is_ok());
+        assert!(ping(&handler, client2).is_ok());
+        assert!(ping(&handler, client2).is_err());
+    }
+
+    #[test]
+    fn it_does_not_limit_calls_without_a_rate_limit() {
+        let handler = handler(None);
+        let client = IpAddr::from([10, 0, 0, 1]);
+        for _ in 0..100 {
+            assert!(ping(&handler, client).is_ok());
+        }
+        assert!(handler.rate_limits.lock().is_empty());
+    }
+}
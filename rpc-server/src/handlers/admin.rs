@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use json::JsonValue;
+
+use consensus::ConsensusProtocol;
+use nimiq_mempool::Mempool;
+
+use crate::handler::Method;
+use crate::handlers::Module;
+use crate::handlers::mempool::{filter_rules_to_obj, json_to_coin, json_to_fee_per_byte};
+
+/// Methods that change the configuration of a running node.
+pub struct AdminHandler<P: ConsensusProtocol + 'static> {
+    mempool: Arc<Mempool<'static, P::Blockchain>>,
+}
+
+impl<P: ConsensusProtocol + 'static> AdminHandler<P> {
+    pub fn new(mempool: Arc<Mempool<'static, P::Blockchain>>) -> Self {
+        AdminHandler {
+            mempool,
+        }
+    }
+
+    /// Updates the filter rules of the mempool, which evicts the transactions that don't pass
+    /// them anymore, and returns the new rules. Rules that aren't given are kept.
+    /// Parameters:
+    /// - rules (object): The rules to update, see `mempoolFilterRules`.
+    ///
+    /// ```text
+    /// {
+    ///     txFee: number, // in Luna
+    ///     txFeePerByte: number,
+    ///     txValue: number,
+    ///     txValueTotal: number,
+    ///     contractFee: number,
+    ///     contractFeePerByte: number,
+    ///     contractValue: number,
+    ///     creationFee: number,
+    ///     creationFeePerByte: number,
+    ///     creationValue: number,
+    ///     senderBalance: number,
+    ///     recipientBalance: number,
+    /// }
+    /// ```
+    pub(crate) fn set_mempool_filter_rules(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let update = params.get(0).unwrap_or(&JsonValue::Null);
+        if !update.is_object() {
+            return Err(object!{"message" => "Filter rules must be an object"});
+        }
+
+        let mut rules = self.mempool.filter_rules();
+        for (key, value) in update.entries() {
+            match key {
+                "txFee" => rules.tx_fee = json_to_coin(key, value)?,
+                "txFeePerByte" => rules.tx_fee_per_byte = json_to_fee_per_byte(key, value)?,
+                "txValue" => rules.tx_value = json_to_coin(key, value)?,
+                "txValueTotal" => rules.tx_value_total = json_to_coin(key, value)?,
+                "contractFee" => rules.contract_fee = json_to_coin(key, value)?,
+                "contractFeePerByte" => rules.contract_fee_per_byte = json_to_fee_per_byte(key, value)?,
+                "contractValue" => rules.contract_value = json_to_coin(key, value)?,
+                "creationFee" => rules.creation_fee = json_to_coin(key, value)?,
+                "creationFeePerByte" => rules.creation_fee_per_byte = json_to_fee_per_byte(key, value)?,
+                "creationValue" => rules.creation_value = json_to_coin(key, value)?,
+                "senderBalance" => rules.sender_balance = json_to_coin(key, value)?,
+                "recipientBalance" => rules.recipient_balance = json_to_coin(key, value)?,
+                _ => return Err(object!{"message" => format!("Unknown filter rule '{}'", key)}),
+            }
+        }
+
+        let evicted = self.mempool.set_filter_rules(rules);
+        info!("Updated mempool filter rules, evicted {} transactions", evicted);
+
+        Ok(filter_rules_to_obj(&self.mempool.filter_rules()))
+    }
+}
+
+impl<P: ConsensusProtocol + 'static> Module for AdminHandler<P> {
+    rpc_module_methods! {
+        "setMempoolFilterRules" => set_mempool_filter_rules,
+    }
+}
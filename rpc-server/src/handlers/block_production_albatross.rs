@@ -1,34 +1,133 @@
 // TODO I need help cleaning this up
 // How do I get the reference to a validator to here?
 
+use std::str::FromStr;
 use std::sync::Arc;
 
-use bls::bls12_381::{CompressedPublicKey, CompressedSignature};
-use json::JsonValue;
+use rand::rngs::OsRng;
+
+use block_production_albatross::blacklist::Blacklist;
+use block_production_albatross::validator_keys::ValidatorKeys;
+use bls::bls12_381::KeyPair;
+use hash::Blake2bHash;
+use json::{JsonValue, Null};
+use keys::Address;
+use network_primitives::heartbeat::ValidatorLiveness;
 
 use crate::handler::Method;
 use crate::handlers::Module;
 
 pub struct BlockProductionAlbatrossHandler {
-    key: CompressedPublicKey,
-    pok: CompressedSignature,
+    keys: Arc<ValidatorKeys>,
+    liveness: Arc<ValidatorLiveness>,
+    blacklist: Arc<Blacklist>,
+}
+
+/// An address or a transaction hash on the blacklist.
+enum BlacklistEntry {
+    Address(Address),
+    Transaction(Blake2bHash),
 }
 
 impl BlockProductionAlbatrossHandler {
-    pub fn new(key: CompressedPublicKey, pok: CompressedSignature) -> Self {
-        Self { key, pok }
+    pub fn new(keys: Arc<ValidatorKeys>, liveness: Arc<ValidatorLiveness>, blacklist: Arc<Blacklist>) -> Self {
+        Self { keys, liveness, blacklist }
     }
 
+    /// Returns the validator key and its proof of knowledge. During a key rotation, this is the
+    /// new key and `previousValidatorKey` is the key it replaces.
     fn validator_key(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let key_pair = self.keys.current();
+        let public_key = key_pair.public.compress();
         Ok(object! {
-            "validatorKey" => hex::encode(&self.key),
-            "proofOfKnowledge" => hex::encode(&self.pok),
+            "validatorKey" => hex::encode(&public_key),
+            "proofOfKnowledge" => hex::encode(&key_pair.sign(&public_key).compress()),
+            "previousValidatorKey" => self.keys.previous().map(|key_pair| hex::encode(&key_pair.public.compress())),
         })
     }
+
+    /// Generates a new validator key for a key rotation and returns it like `validatorKey`. The
+    /// validator keeps its current key until the new key is elected. To rotate the key, pass the
+    /// new key and its proof of knowledge to `rotateValidatorKey`.
+    fn new_validator_key(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let mut rng = OsRng::new()
+            .map_err(|e| object! {"message" => format!("Failed to generate key: {}", e)})?;
+        self.keys.rotate(KeyPair::generate(&mut rng))
+            .map_err(|e| object! {"message" => e.to_string()})?;
+        self.validator_key(&[])
+    }
+
+    /// Returns the liveness of the validators of the current epoch, as seen from their heartbeats.
+    /// `lastHeartbeat` is the number of seconds since the last heartbeat, or `null`.
+    fn validator_liveness(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        Ok(JsonValue::Array(self.liveness.validators().into_iter()
+            .enumerate()
+            .map(|(pk_idx, liveness)| object! {
+                "index" => pk_idx,
+                "validatorKey" => hex::encode(&liveness.public_key),
+                "lastHeartbeat" => liveness.last_heartbeat.map(|duration| duration.as_secs()),
+                "offline" => liveness.offline,
+            })
+            .collect()))
+    }
+
+    /// Returns the addresses and transaction hashes whose transactions the validator leaves out of
+    /// its blocks.
+    fn get_blacklist(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        Ok(object! {
+            "addresses" => self.blacklist.addresses().iter()
+                .map(Address::to_user_friendly_address)
+                .collect::<Vec<String>>(),
+            "transactions" => self.blacklist.transactions().iter()
+                .map(Blake2bHash::to_hex)
+                .collect::<Vec<String>>(),
+        })
+    }
+
+    /// Leaves transactions from or to an address, or a single transaction, out of the validator's
+    /// blocks. The transactions are still relayed.
+    /// Parameters:
+    /// - entry (string): User friendly address or transaction hash
+    ///
+    /// Returns `false` if the entry was already blacklisted.
+    fn add_to_blacklist(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        Ok(match Self::blacklist_entry(params)? {
+            BlacklistEntry::Address(address) => self.blacklist.add_address(address),
+            BlacklistEntry::Transaction(hash) => self.blacklist.add_transaction(hash),
+        }.into())
+    }
+
+    /// Removes an address or transaction hash from the blacklist.
+    /// Parameters:
+    /// - entry (string): User friendly address or transaction hash
+    ///
+    /// Returns `false` if the entry wasn't blacklisted.
+    fn remove_from_blacklist(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        Ok(match Self::blacklist_entry(params)? {
+            BlacklistEntry::Address(address) => self.blacklist.remove_address(&address),
+            BlacklistEntry::Transaction(hash) => self.blacklist.remove_transaction(&hash),
+        }.into())
+    }
+
+    fn blacklist_entry(params: &[JsonValue]) -> Result<BlacklistEntry, JsonValue> {
+        let entry = params.get(0).unwrap_or(&Null).as_str()
+            .ok_or_else(|| object!{"message" => "Entry must be a string"})?;
+        if let Ok(address) = Address::from_user_friendly_address(entry) {
+            return Ok(BlacklistEntry::Address(address));
+        }
+        Blake2bHash::from_str(entry)
+            .map(BlacklistEntry::Transaction)
+            .map_err(|_| object!{"message" => "Entry must be an address or a transaction hash"})
+    }
 }
 
 impl Module for BlockProductionAlbatrossHandler {
     rpc_module_methods! {
         "validatorKey" => validator_key,
+        "newValidatorKey" => new_validator_key,
+        "validatorLiveness" => validator_liveness,
+        "blacklist" => get_blacklist,
+        "addToBlacklist" => add_to_blacklist,
+        "removeFromBlacklist" => remove_from_blacklist,
     }
 }
@@ -1,17 +1,34 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::iter::FromIterator;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
+use beserial::{Deserialize, Serialize};
+use futures::{future, Future};
 use json::{JsonValue, Null};
+use tokio::timer::Timeout;
 
+use account::{Account, PrunedAccount, Receipt};
+use account::staking_contract::{ActiveStake, InactiveStake};
 use block_albatross::{Block, ForkProof};
 use blockchain_albatross::Blockchain;
-use blockchain_albatross::reward_registry::SlashedSlots;
+use blockchain_albatross::chain_stats::EpochStats;
+use blockchain_albatross::confirmations::ConfirmationPolicy;
+use blockchain_albatross::htlc_settlements::HtlcSettlement;
+use blockchain_albatross::reward_registry::{RecipientReward, SlashedSlots};
+use blockchain_base::{AbstractBlockchain, Direction};
 use hash::{Blake2bHash, Hash};
+use keys::Address;
+use lib::payment::PaymentFuture;
+use network_primitives::networks::NetworkInfo;
+use primitives::coin::Coin;
 use primitives::policy;
-use primitives::validators::{IndexedSlot, Slots};
+use primitives::validators::{IndexedSlot, Slots, Validators};
+use transaction::Transaction;
+use transaction::account::htlc_contract::{HashAlgorithm, ProofType, RevealedPreImage};
 
-use crate::handler::Method;
+use crate::handler::{Method, MethodFuture};
 use crate::handlers::Module;
 use crate::handlers::blockchain::BlockchainHandler;
 use crate::handlers::mempool::{transaction_to_obj, TransactionContext};
@@ -23,6 +40,13 @@ pub struct BlockchainAlbatrossHandler {
 }
 
 impl BlockchainAlbatrossHandler {
+    const MAX_CHAIN_STATS_EPOCHS: u32 = 100;
+    const MAX_ATTESTATION_EPOCHS: u32 = 100;
+    const MAX_PAYMENT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+    const MAX_STAKES: usize = 1000;
+    const MAX_MACRO_BLOCK_ANCESTORS: u32 = 100;
+    const MAX_VESTING_STEPS: usize = 1000;
+
     pub fn new(blockchain: Arc<Blockchain<'static>>) -> Self {
         BlockchainAlbatrossHandler {
             generic: BlockchainHandler::new(blockchain.clone()),
@@ -168,6 +192,388 @@ impl BlockchainAlbatrossHandler {
         })
     }
 
+    /// Returns statistics about a range of epochs.
+    /// Parameters:
+    /// - fromEpoch (number, optional): Default is the current epoch.
+    /// - toEpoch (number, optional): Default is the current epoch.
+    ///
+    /// The stats object contains:
+    /// ```text
+    /// {
+    ///     blockCount: number,
+    ///     viewChanges: number,
+    ///     transactionCount: number,
+    ///     fees: number,
+    ///     averageBlockTime: number|null, (milliseconds)
+    ///     transactionsPerSecond: number|null,
+    ///     epochs: Array<{
+    ///         epoch: number,
+    ///         complete: boolean,
+    ///         blockCount: number,
+    ///         startTimestamp: number,
+    ///         endTimestamp: number,
+    ///         viewChanges: number,
+    ///         transactionCount: number,
+    ///         fees: number,
+    ///         averageBlockTime: number|null,
+    ///         transactionsPerSecond: number|null,
+    ///     }>,
+    /// }
+    /// ```
+    pub(crate) fn get_chain_stats(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let current_epoch = policy::epoch_at(self.blockchain.height());
+        let parse_epoch = |value: Option<&JsonValue>| match value {
+            None | Some(JsonValue::Null) => Ok(current_epoch),
+            Some(value) => value.as_u32().ok_or_else(|| object!{"message" => "Invalid epoch number"}),
+        };
+        let from_epoch = parse_epoch(params.get(0))?;
+        let to_epoch = parse_epoch(params.get(1))?;
+
+        if from_epoch > to_epoch {
+            return Err(object!{"message" => "fromEpoch must not be greater than toEpoch"});
+        }
+        if to_epoch - from_epoch >= Self::MAX_CHAIN_STATS_EPOCHS {
+            return Err(object!{"message" => format!("At most {} epochs can be requested", Self::MAX_CHAIN_STATS_EPOCHS)});
+        }
+
+        let stats = self.blockchain.chain_stats(from_epoch, to_epoch);
+        Ok(object!{
+            "blockCount" => stats.block_count(),
+            "viewChanges" => stats.view_changes(),
+            "transactionCount" => stats.transaction_count(),
+            "fees" => u64::from(stats.fees()),
+            "averageBlockTime" => stats.average_block_time().map(JsonValue::from).unwrap_or(Null),
+            "transactionsPerSecond" => stats.transactions_per_second().map(JsonValue::from).unwrap_or(Null),
+            "epochs" => JsonValue::Array(stats.epochs.iter().map(Self::epoch_stats_to_obj).collect()),
+        })
+    }
+
+    /// Returns the state digest of a finalized epoch. It commits to the state root, the
+    /// transactions root and the next validators, so it can be compared with other nodes.
+    /// Parameters:
+    /// - epoch (number, optional): Defaults to the last finalized epoch.
+    ///
+    /// Returns:
+    /// ```text
+    /// {
+    ///     epoch: number,
+    ///     macroBlockNumber: number,
+    ///     digest: string,
+    /// }
+    /// ```
+    pub(crate) fn get_epoch_state_digest(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let epoch = match params.get(0) {
+            None | Some(JsonValue::Null) => policy::epoch_at(self.blockchain.macro_head().header.block_number),
+            Some(value) => value.as_u32().ok_or_else(|| object!{"message" => "Invalid epoch number"})?,
+        };
+        let digest = self.blockchain.epoch_state_digest(epoch)
+            .ok_or_else(|| object!{"message" => "Epoch not finalized"})?;
+        Ok(object!{
+            "epoch" => epoch,
+            "macroBlockNumber" => policy::macro_block_of(epoch),
+            "digest" => digest.to_hex(),
+        })
+    }
+
+    /// Returns how the reward pot of an epoch was distributed. The rewards are paid by the macro
+    /// block ending the following epoch. Only payouts this node processed itself are known.
+    /// Parameters:
+    /// - epoch (number, optional): Defaults to the last epoch that paid out rewards.
+    ///
+    /// Returns:
+    /// ```text
+    /// {
+    ///     epoch: number,
+    ///     rewardPot: number,
+    ///     eligibleSlots: number,
+    ///     slashedSlots: number,
+    ///     slotReward: number,
+    ///     redistributedReward: number, // per accepting slot
+    ///     rewards: Array<{
+    ///         address: string,
+    ///         slots: number,
+    ///         value: number,
+    ///     }>,
+    ///     rejected: Array<{ address: string, slots: number, value: number }>,
+    /// }
+    /// ```
+    pub(crate) fn get_epoch_rewards(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let epoch = match params.get(0) {
+            None | Some(JsonValue::Null) => policy::epoch_at(self.blockchain.macro_head().header.block_number)
+                .checked_sub(1)
+                .ok_or_else(|| object!{"message" => "No rewards were paid yet"})?,
+            Some(value) => value.as_u32().ok_or_else(|| object!{"message" => "Invalid epoch number"})?,
+        };
+        let rewards = self.blockchain.epoch_rewards(epoch)
+            .ok_or_else(|| object!{"message" => "No rewards recorded for this epoch"})?;
+        Ok(object!{
+            "epoch" => rewards.epoch,
+            "rewardPot" => u64::from(rewards.reward_pot),
+            "eligibleSlots" => rewards.num_eligible_slots,
+            "slashedSlots" => rewards.num_slashed_slots,
+            "slotReward" => u64::from(rewards.slot_reward),
+            "redistributedReward" => u64::from(rewards.redistributed_reward),
+            "rewards" => JsonValue::Array(rewards.rewards.iter().map(Self::recipient_reward_to_obj).collect()),
+            "rejected" => JsonValue::Array(rewards.rejected.iter().map(Self::recipient_reward_to_obj).collect()),
+        })
+    }
+
+    fn recipient_reward_to_obj(reward: &RecipientReward) -> JsonValue {
+        object!{
+            "address" => reward.address.to_user_friendly_address(),
+            "slots" => reward.num_slots,
+            "value" => u64::from(reward.value),
+        }
+    }
+
+    /// Returns an overview of an epoch: the macro block that elected its validators, the
+    /// validators, the slashed set and the reward totals.
+    /// Parameters:
+    /// - epoch (number): Must be at least 1, epoch 0 only contains the genesis block.
+    ///
+    /// Returns:
+    /// ```text
+    /// {
+    ///     epoch: number,
+    ///     firstBlockNumber: number,
+    ///     macroBlockNumber: number, // The macro block ending the epoch
+    ///     complete: bool,
+    ///     electionBlock: block_object, // The macro block ending the previous epoch
+    ///     validators: Array<{
+    ///         publicKey: string,
+    ///         slots: number,
+    ///     }>,
+    ///     slashedSet: {
+    ///         final: bool, // Whether the slashed set was finalized by a macro block yet
+    ///         slots: Array<number>,
+    ///     } | null, // If the epoch is too old to still be tracked
+    ///     rewards: {
+    ///         rewardPot: number,
+    ///         slotReward: number,
+    ///         paid: number,
+    ///         rejected: number,
+    ///     } | null, // If the rewards weren't paid yet or weren't processed by this node
+    /// }
+    /// ```
+    pub(crate) fn get_epoch_by_number(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let epoch = params.get(0).and_then(JsonValue::as_u32)
+            .filter(|epoch| *epoch > 0)
+            .ok_or_else(|| object!{"message" => "Invalid epoch number"})?;
+        let macro_block_number = policy::macro_block_of(epoch);
+        let complete = macro_block_number <= self.blockchain.macro_head().header.block_number;
+
+        let election_block = match self.blockchain.get_block_at(policy::macro_block_of(epoch - 1), true) {
+            Some(Block::Macro(block)) => block,
+            _ => return Err(object!{"message" => "Epoch not started yet"}),
+        };
+        let validators: Validators = (&election_block.header.validators).into_iter().cloned().collect();
+
+        // The final slashed set of an epoch is included in the macro block ending the next epoch.
+        let slashed_set = match self.blockchain.get_block_at(policy::macro_block_of(epoch + 1), true) {
+            Some(Block::Macro(block)) => block.extrinsics.map(|extrinsics| (true, extrinsics.slashed_set)),
+            _ => self.blockchain.slashed_set_at(epoch, self.blockchain.height() + 1).ok()
+                .map(|slashed_set| (false, slashed_set)),
+        };
+
+        let rewards = self.blockchain.epoch_rewards(epoch);
+
+        Ok(object!{
+            "epoch" => epoch,
+            "firstBlockNumber" => policy::first_block_of(epoch),
+            "macroBlockNumber" => macro_block_number,
+            "complete" => complete,
+            "electionBlock" => self.block_to_obj(&Block::Macro(election_block), false),
+            "validators" => JsonValue::Array(validators.groups().iter()
+                .map(|group| object!{
+                    "publicKey" => hex::encode(&group.1),
+                    "slots" => group.0,
+                })
+                .collect()),
+            "slashedSet" => slashed_set.map(|(is_final, slashed_set)| object!{
+                "final" => is_final,
+                "slots" => JsonValue::Array(slashed_set.iter().map(JsonValue::from).collect()),
+            }).unwrap_or(Null),
+            "rewards" => rewards.map(|rewards| object!{
+                "rewardPot" => u64::from(rewards.reward_pot),
+                "slotReward" => u64::from(rewards.slot_reward),
+                "paid" => rewards.rewards.iter().map(|reward| u64::from(reward.value)).sum::<u64>(),
+                "rejected" => rewards.rejected.iter().map(|reward| u64::from(reward.value)).sum::<u64>(),
+            }).unwrap_or(Null),
+        })
+    }
+
+    /// Returns the macro blocks preceding a macro block, starting with its parent macro block.
+    /// Parameters:
+    /// - hash (string): Hash of a macro block.
+    /// - count (number, optional): Number of ancestors. Default and maximum is 100.
+    ///
+    /// Returns an array of block objects (see `getBlockByHash`), ordered from the newest to the
+    /// oldest block.
+    pub(crate) fn get_macro_block_ancestors(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let hash = params.get(0).and_then(JsonValue::as_str)
+            .ok_or_else(|| object!{"message" => "Invalid block hash"})
+            .and_then(|s| Blake2bHash::from_str(s)
+                .map_err(|_| object!{"message" => "Invalid block hash"}))?;
+        let count = match params.get(1) {
+            None | Some(JsonValue::Null) => Self::MAX_MACRO_BLOCK_ANCESTORS,
+            Some(value) => value.as_u32()
+                .filter(|count| *count <= Self::MAX_MACRO_BLOCK_ANCESTORS)
+                .ok_or_else(|| object!{"message" => format!("Count must be at most {}", Self::MAX_MACRO_BLOCK_ANCESTORS)})?,
+        };
+
+        if self.blockchain.get_block(&hash, false, false).is_none() {
+            return Err(object!{"message" => "Unknown block"});
+        }
+        let blocks = self.blockchain.get_macro_blocks(&hash, count, false, Direction::Backward)
+            .ok_or_else(|| object!{"message" => "Block is not a macro block"})?;
+        Ok(JsonValue::Array(blocks.iter().map(|block| self.block_to_obj(block, false)).collect()))
+    }
+
+    /// Returns an attestation proving that a transaction is part of a finalized epoch. It can be
+    /// verified with `nimiq-macro-verifier` by anyone trusting the given macro block.
+    /// Parameters:
+    /// - hash (string): Transaction hash.
+    /// - blockNumber (number): Number of the block containing the transaction.
+    /// - trustedMacroBlock (number, optional): Number of the macro block the verifier trusts.
+    ///   Defaults to the genesis block.
+    ///
+    /// Returns:
+    /// ```text
+    /// {
+    ///     macroBlockNumber: number, // The macro block finalizing the transaction
+    ///     macroBlockHash: string,
+    ///     attestation: string, // hex encoded
+    /// }
+    /// ```
+    pub(crate) fn get_transaction_attestation(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let hash = params.get(0).and_then(JsonValue::as_str)
+            .ok_or_else(|| object!{"message" => "Invalid transaction hash"})
+            .and_then(|s| Blake2bHash::from_str(s)
+                .map_err(|_| object!{"message" => "Invalid transaction hash"}))?;
+        let block_number = params.get(1).and_then(JsonValue::as_u32)
+            .ok_or_else(|| object!{"message" => "Invalid block number"})?;
+        let trusted_macro_block = match params.get(2) {
+            None | Some(JsonValue::Null) => 0,
+            Some(value) => value.as_u32()
+                .ok_or_else(|| object!{"message" => "Invalid trusted macro block"})?,
+        };
+
+        let macro_block_number = policy::macro_block_of(policy::epoch_at(block_number));
+        if macro_block_number.saturating_sub(trusted_macro_block) / policy::EPOCH_LENGTH > Self::MAX_ATTESTATION_EPOCHS {
+            return Err(object!{"message" => format!("Attestations can span at most {} epochs", Self::MAX_ATTESTATION_EPOCHS)});
+        }
+
+        let attestation = self.blockchain.transaction_attestation(&hash, block_number, trusted_macro_block)
+            .map_err(|e| object!{"message" => e.to_string()})?;
+        let macro_block_hash = attestation.macro_chain.last()
+            .map(|justified| justified.header.hash::<Blake2bHash>())
+            .or_else(|| self.blockchain.get_block_at(macro_block_number, false).map(|block| block.hash()))
+            .ok_or_else(|| object!{"message" => "Macro block not found"})?;
+
+        Ok(object!{
+            "macroBlockNumber" => macro_block_number,
+            "macroBlockHash" => macro_block_hash.to_hex(),
+            "attestation" => hex::encode(attestation.serialize_to_vec()),
+        })
+    }
+
+    /// Returns the replay protection state for transactions sent from `address`: all of its
+    /// transactions whose hashes are still tracked by the blockchain. A new transaction with the
+    /// same hash as one of these will be rejected as a duplicate until `expiresAt`.
+    /// Parameters:
+    /// - address (string)
+    ///
+    /// Returns:
+    /// ```text
+    /// {
+    ///     address: string,
+    ///     blockNumber: number,
+    ///     validityWindow: number,
+    ///     transactions: Array<{
+    ///         hash: string,
+    ///         blockNumber: number,
+    ///         validityStartHeight: number,
+    ///         expiresAt: number,
+    ///     }>,
+    /// }
+    /// ```
+    pub(crate) fn get_pending_validity_info(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let address = params.get(0).and_then(JsonValue::as_str)
+            .ok_or_else(|| object!{"message" => "Invalid address"})
+            .and_then(|s| Address::from_any_str(s)
+                .map_err(|_| object!{"message" => "Invalid address"}))?;
+
+        let state = self.blockchain.state();
+        let transactions = state.transaction_cache()
+            .transactions_from(&address)
+            .map(|tx| object!{
+                "hash" => tx.hash.to_hex(),
+                "blockNumber" => tx.block_number,
+                "validityStartHeight" => tx.validity_start_height,
+                "expiresAt" => tx.expires_at(),
+            })
+            .collect();
+
+        Ok(object!{
+            "address" => address.to_user_friendly_address(),
+            "blockNumber" => state.block_number(),
+            "validityWindow" => policy::TRANSACTION_VALIDITY_WINDOW_ALBATROSS,
+            "transactions" => JsonValue::Array(transactions),
+        })
+    }
+
+    /// Waits until a transaction paying at least `minValue` to `address` is confirmed.
+    /// Only transactions in blocks that aren't confirmed yet when the call is made are considered.
+    /// Parameters:
+    /// - address (string)
+    /// - minValue (number): Minimum value in Luna.
+    /// - timeout (number): Timeout in seconds, at most 600.
+    /// - confirmations (number, optional): Number of blocks on top of the transaction's block.
+    ///   If omitted, the transaction must be finalized by a macro block.
+    ///
+    /// Returns the transaction (see `getTransactionByHash`), or `null` if the timeout expired.
+    pub(crate) fn wait_for_transaction_to(&self, params: &[JsonValue]) -> MethodFuture {
+        let blockchain = Arc::clone(&self.blockchain);
+        Box::new(future::result(Self::payment_request(params)).and_then(move |(address, min_value, timeout, policy)| {
+            let payment = PaymentFuture::new(blockchain, address, min_value, policy)
+                .map(|payment| transaction_to_obj(&payment.transaction, Some(&TransactionContext {
+                    block_hash: &payment.block_hash.to_hex(),
+                    block_number: payment.block_number,
+                    index: payment.transaction_index as u16,
+                    timestamp: payment.timestamp,
+                }), None))
+                .map_err(|e| object!{"message" => e.to_string()});
+            Timeout::new(payment, timeout).then(|result| match result {
+                Ok(transaction) => Ok(transaction),
+                Err(ref e) if e.is_elapsed() => Ok(Null),
+                Err(e) => Err(e.into_inner().unwrap_or_else(|| object!{"message" => "Timer failed"})),
+            })
+        }))
+    }
+
+    /// Parses the parameters of `waitForTransactionTo`.
+    fn payment_request(params: &[JsonValue]) -> Result<(Address, Coin, Duration, ConfirmationPolicy), JsonValue> {
+        let address = params.get(0).and_then(JsonValue::as_str)
+            .ok_or_else(|| object!{"message" => "Invalid address"})
+            .and_then(|s| Address::from_any_str(s)
+                .map_err(|_| object!{"message" => "Invalid address"}))?;
+        let min_value = params.get(1).and_then(JsonValue::as_u64)
+            .and_then(|value| Coin::try_from(value).ok())
+            .ok_or_else(|| object!{"message" => "Invalid minimum value"})?;
+        let timeout = params.get(2).and_then(JsonValue::as_u64)
+            .map(Duration::from_secs)
+            .ok_or_else(|| object!{"message" => "Invalid timeout"})?;
+        if timeout > Self::MAX_PAYMENT_TIMEOUT {
+            return Err(object!{"message" => format!("Timeout must not exceed {} seconds", Self::MAX_PAYMENT_TIMEOUT.as_secs())});
+        }
+        let policy = match params.get(3) {
+            None | Some(JsonValue::Null) => ConfirmationPolicy::Finalized,
+            Some(value) => ConfirmationPolicy::Depth(value.as_u32()
+                .ok_or_else(|| object!{"message" => "Invalid number of confirmations"})?),
+        };
+        Ok((address, min_value, timeout, policy))
+    }
+
     // Transactions
 
     /// Retrieves information about a transaction from its hex encoded form.
@@ -279,8 +685,411 @@ impl BlockchainAlbatrossHandler {
         rpc_not_implemented()
     }
 
+    /// Returns the accounts that were pruned by a main chain block, because their balance dropped
+    /// to zero. For contracts, the data of a creation transaction that recreates a contract with
+    /// the same parameters (at a new address) is included. Only known for the micro blocks of the
+    /// current epoch.
+    /// Parameters:
+    /// - blockNumber (number)
+    ///
+    /// Returns:
+    /// ```text
+    /// Array<{
+    ///     address: string,
+    ///     type: number,
+    ///     restoration: {
+    ///         type: number,
+    ///         value: number,
+    ///         data: string, // hex encoded
+    ///     } | null,
+    /// }>
+    /// ```
+    pub(crate) fn get_pruned_accounts(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let block_number = params.get(0).and_then(JsonValue::as_u32)
+            .ok_or_else(|| object!{"message" => "Invalid block number"})?;
+        let pruned_accounts = self.blockchain.get_pruned_accounts(block_number)
+            .ok_or_else(|| object!{"message" => "Pruned accounts not known for this block"})?;
+        Ok(JsonValue::Array(pruned_accounts.iter().map(Self::pruned_account_to_obj).collect()))
+    }
+
+    fn pruned_account_to_obj(pruned: &PrunedAccount) -> JsonValue {
+        object!{
+            "address" => pruned.address.to_user_friendly_address(),
+            "type" => pruned.account.account_type() as u8,
+            "restoration" => pruned.restoration().map(|restoration| object!{
+                "type" => restoration.account_type as u8,
+                "value" => u64::from(restoration.value),
+                "data" => hex::encode(&restoration.data),
+            }).unwrap_or(Null),
+        }
+    }
+
+    /// Applies a transaction to the current state and returns its effects, without broadcasting
+    /// it or changing the state. Proofs for contracts (e.g. HTLCs) are part of the transaction.
+    /// Like the mempool, this rejects transactions outside of their validity window and those
+    /// that were already included in a block.
+    /// Parameters:
+    /// - transaction (string): Hex encoded transaction.
+    ///
+    /// Returns:
+    /// ```text
+    /// {
+    ///     hash: string,
+    ///     blockNumber: number, // The block number the transaction was simulated at
+    ///     valid: bool,
+    ///     error: string | null,
+    ///     accounts: Array<{ // Sender and recipient
+    ///         address: string,
+    ///         typeBefore: number,
+    ///         balanceBefore: number,
+    ///         type: number,
+    ///         balance: number,
+    ///     }>,
+    ///     prunedAccounts: Array<string>,
+    /// }
+    /// ```
+    pub(crate) fn simulate_transaction(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let transaction: Transaction = params.get(0).unwrap_or(&Null).as_str()
+            .ok_or_else(|| object!{"message" => "Raw transaction data must be a string"})
+            .and_then(|s| hex::decode(s)
+                .map_err(|_| object!{"message" => "Raw transaction data must be hex-encoded"}))
+            .and_then(|b| Deserialize::deserialize_from_vec(&b)
+                .map_err(|_| object!{"message" => "Invalid transaction data"}))?;
+
+        let mut addresses = vec![transaction.sender.clone()];
+        if transaction.recipient != transaction.sender {
+            addresses.push(transaction.recipient.clone());
+        }
+
+        // The state can't change while we hold it, so everything below sees the same head.
+        let state = self.blockchain.state();
+        let block_number = state.block_number() + 1;
+        let accounts_before: Vec<Account> = addresses.iter()
+            .map(|address| state.accounts().get(address, None))
+            .collect();
+
+        let result = transaction.verify(self.blockchain.network_id)
+            .map_err(|e| e.to_string())
+            .and_then(|_| if transaction.is_valid_at(block_number) {
+                Ok(())
+            } else {
+                Err(format!("Transaction is not valid at block {}", block_number))
+            })
+            .and_then(|_| if state.transaction_cache().contains(&transaction.hash::<Blake2bHash>()) {
+                Err("Transaction was already included in a block".to_string())
+            } else {
+                Ok(())
+            })
+            .and_then(|_| state.accounts().simulate(&[transaction.clone()], block_number, &addresses)
+                .map_err(|e| e.to_string()));
+        drop(state);
+
+        let (accounts_after, pruned_accounts, error) = match result {
+            Ok((accounts_after, receipts)) => {
+                let pruned_accounts = receipts.receipts.iter()
+                    .filter_map(|receipt| match receipt {
+                        Receipt::PrunedAccount(pruned) => Some(pruned.address.to_user_friendly_address().into()),
+                        _ => None,
+                    })
+                    .collect();
+                (accounts_after, pruned_accounts, None)
+            },
+            Err(e) => (accounts_before.clone(), Vec::new(), Some(e)),
+        };
+
+        Ok(object!{
+            "hash" => transaction.hash::<Blake2bHash>().to_hex(),
+            "blockNumber" => block_number,
+            "valid" => error.is_none(),
+            "error" => error.map(JsonValue::from).unwrap_or(Null),
+            "accounts" => JsonValue::Array(addresses.iter()
+                .zip(accounts_before.iter().zip(accounts_after.iter()))
+                .map(|(address, (before, after))| object!{
+                    "address" => address.to_user_friendly_address(),
+                    "typeBefore" => before.account_type() as u8,
+                    "balanceBefore" => u64::from(before.balance()),
+                    "type" => after.account_type() as u8,
+                    "balance" => u64::from(after.balance()),
+                })
+                .collect()),
+            "prunedAccounts" => JsonValue::Array(pruned_accounts),
+        })
+    }
+
+    // Accounts
+
+    /// Returns the vesting state and schedule of a vesting contract.
+    /// Parameters:
+    /// - address (string)
+    ///
+    /// Returns:
+    /// ```text
+    /// {
+    ///     address: string,
+    ///     blockNumber: number, // The block the amounts apply to, i.e. the next block
+    ///     balance: number,
+    ///     owner: string,
+    ///     start: number,
+    ///     stepBlocks: number,
+    ///     stepAmount: number,
+    ///     totalAmount: number,
+    ///     vested: number, // Part of the total amount that is vested
+    ///     locked: number, // Part of the total amount that is still locked
+    ///     available: number, // Part of the balance that the owner can withdraw
+    ///     nextRelease: {
+    ///         blockNumber: number,
+    ///         amount: number,
+    ///     } | null,
+    ///     schedule: Array<{
+    ///         blockNumber: number,
+    ///         vested: number, // Part of the total amount that is vested from this block on
+    ///     }>, // At most 1000 releases
+    /// }
+    /// ```
+    pub(crate) fn get_vesting_info(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let address = params.get(0).and_then(JsonValue::as_str)
+            .ok_or_else(|| object!{"message" => "Invalid address"})
+            .and_then(|s| Address::from_any_str(s)
+                .map_err(|_| object!{"message" => "Invalid address"}))?;
+
+        let contract = match self.blockchain.state().accounts().get(&address, None) {
+            Account::Vesting(contract) => contract,
+            _ => return Err(object!{"message" => "Account is not a vesting contract"}),
+        };
+
+        let block_number = self.blockchain.height() + 1;
+        let locked = contract.locked_amount(block_number);
+        let available = contract.balance - Coin::min(contract.balance, locked);
+
+        Ok(object!{
+            "address" => address.to_user_friendly_address(),
+            "blockNumber" => block_number,
+            "balance" => u64::from(contract.balance),
+            "owner" => contract.owner.to_user_friendly_address(),
+            "start" => contract.start,
+            "stepBlocks" => contract.step_blocks,
+            "stepAmount" => u64::from(contract.step_amount),
+            "totalAmount" => u64::from(contract.total_amount),
+            "vested" => u64::from(contract.total_amount - locked),
+            "locked" => u64::from(locked),
+            "available" => u64::from(available),
+            "nextRelease" => contract.next_release(block_number).map(|(block_number, amount)| object!{
+                "blockNumber" => block_number,
+                "amount" => u64::from(amount),
+            }).unwrap_or(Null),
+            "schedule" => JsonValue::Array(contract.schedule(Self::MAX_VESTING_STEPS).into_iter()
+                .map(|(block_number, vested)| object!{
+                    "blockNumber" => block_number,
+                    "vested" => u64::from(vested),
+                })
+                .collect()),
+        })
+    }
+
+    /// Returns the state of an HTLC and the transactions that settled it. Once the recipient
+    /// redeems the HTLC, the pre-image is revealed in the proof of the settlement, so the
+    /// counterparty of an atomic swap can use it to redeem its own HTLC.
+    ///
+    /// Settlements are indexed for all HTLCs as blocks are added to the main chain.
+    /// Parameters:
+    /// - address (string)
+    ///
+    /// Returns:
+    /// ```text
+    /// {
+    ///     address: string,
+    ///     state: {
+    ///         sender: string,
+    ///         recipient: string,
+    ///         hashAlgorithm: string, // "blake2b" or "sha256"
+    ///         hashRoot: string, // hex encoded
+    ///         hashCount: number,
+    ///         timeout: number, // block number
+    ///         balance: number,
+    ///         totalAmount: number,
+    ///         expired: boolean, // Whether the sender can resolve the HTLC after the timeout
+    ///     } | null, // null if the HTLC doesn't exist (anymore)
+    ///     settlements: Array<{
+    ///         hash: string,
+    ///         blockHash: string,
+    ///         blockNumber: number,
+    ///         finalized: boolean,
+    ///         proofType: string, // "regular", "earlyResolve" or "timeoutResolve"
+    ///         value: number,
+    ///         preImage: {
+    ///             hashAlgorithm: string,
+    ///             hashDepth: number,
+    ///             preImage: string, // hex encoded
+    ///         } | null, // Only for regular transfers
+    ///     }>,
+    /// }
+    /// ```
+    pub(crate) fn get_htlc_info(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let address = params.get(0).and_then(JsonValue::as_str)
+            .ok_or_else(|| object!{"message" => "Invalid address"})
+            .and_then(|s| Address::from_any_str(s)
+                .map_err(|_| object!{"message" => "Invalid address"}))?;
+
+        let (state, head_height) = {
+            let state = self.blockchain.state();
+            let contract = match state.accounts().get(&address, None) {
+                Account::HTLC(contract) => Some(contract),
+                _ => None,
+            };
+            (contract, state.block_number())
+        };
+        let finalized_height = policy::last_macro_block(head_height);
+        let settlements = self.blockchain.get_htlc_settlements(&address);
+
+        if state.is_none() && settlements.is_empty() {
+            return Err(object!{"message" => "Account is not a HTLC"});
+        }
+
+        Ok(object!{
+            "address" => address.to_user_friendly_address(),
+            "state" => state.map(|contract| object!{
+                "sender" => contract.sender.to_user_friendly_address(),
+                "recipient" => contract.recipient.to_user_friendly_address(),
+                "hashAlgorithm" => Self::hash_algorithm_to_str(contract.hash_algorithm),
+                "hashRoot" => contract.hash_root.to_hex(),
+                "hashCount" => contract.hash_count,
+                "timeout" => contract.timeout,
+                "balance" => u64::from(contract.balance),
+                "totalAmount" => u64::from(contract.total_amount),
+                "expired" => contract.timeout < head_height + 1,
+            }).unwrap_or(Null),
+            "settlements" => JsonValue::Array(settlements.iter()
+                .map(|settlement| Self::htlc_settlement_to_obj(settlement, settlement.block_number <= finalized_height))
+                .collect()),
+        })
+    }
+
+    fn htlc_settlement_to_obj(settlement: &HtlcSettlement, finalized: bool) -> JsonValue {
+        let transaction = &settlement.transaction;
+        let proof_type = match transaction.proof.get(0).cloned() {
+            Some(t) if t == ProofType::RegularTransfer as u8 => "regular".into(),
+            Some(t) if t == ProofType::EarlyResolve as u8 => "earlyResolve".into(),
+            Some(t) if t == ProofType::TimeoutResolve as u8 => "timeoutResolve".into(),
+            _ => Null,
+        };
+        object!{
+            "hash" => transaction.hash::<Blake2bHash>().to_hex(),
+            "blockHash" => settlement.block_hash.to_hex(),
+            "blockNumber" => settlement.block_number,
+            "finalized" => finalized,
+            "proofType" => proof_type,
+            "value" => u64::from(transaction.value),
+            "preImage" => RevealedPreImage::from_proof(&transaction.proof).map(|revealed| object!{
+                "hashAlgorithm" => Self::hash_algorithm_to_str(revealed.hash_algorithm),
+                "hashDepth" => revealed.hash_depth,
+                "preImage" => revealed.pre_image.to_hex(),
+            }).unwrap_or(Null),
+        }
+    }
+
+    fn hash_algorithm_to_str(hash_algorithm: HashAlgorithm) -> &'static str {
+        match hash_algorithm {
+            HashAlgorithm::Blake2b => "blake2b",
+            HashAlgorithm::Sha256 => "sha256",
+        }
+    }
+
+    // Staking
+
+    /// Lists the stakes in the staking contract. Active stakes are ordered from the highest to the
+    /// lowest balance, inactive stakes by staker address.
+    /// Parameters:
+    /// - offset (number, optional): Number of stakes to skip in both lists. Default is 0.
+    /// - limit (number, optional): Maximum number of stakes per list. Default and maximum is 1000.
+    ///
+    /// Returns:
+    /// ```text
+    /// {
+    ///     balance: number,
+    ///     activeCount: number,
+    ///     inactiveCount: number,
+    ///     active: Array<{
+    ///         stakerAddress: string,
+    ///         balance: number,
+    ///         validatorKey: string,
+    ///         rewardAddress: string,
+    ///     }>,
+    ///     inactive: Array<{
+    ///         stakerAddress: string,
+    ///         balance: number,
+    ///         retireTime: number,
+    ///     }>,
+    ///     validators: Array<{
+    ///         validatorKey: string,
+    ///         stake: number,
+    ///     }>,
+    /// }
+    /// ```
+    pub(crate) fn list_stakes(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let offset = match params.get(0) {
+            None | Some(JsonValue::Null) => 0,
+            Some(value) => value.as_usize().ok_or_else(|| object!{"message" => "Invalid offset"})?,
+        };
+        let limit = match params.get(1) {
+            None | Some(JsonValue::Null) => Self::MAX_STAKES,
+            Some(value) => value.as_usize()
+                .filter(|limit| *limit <= Self::MAX_STAKES)
+                .ok_or_else(|| object!{"message" => format!("Limit must be at most {}", Self::MAX_STAKES)})?,
+        };
+
+        let validator_registry = NetworkInfo::from_network_id(self.blockchain.network_id)
+            .validator_registry_address()
+            .ok_or_else(|| object!{"message" => "No staking contract on this network"})?;
+        let contract = match self.blockchain.state().accounts().get(validator_registry, None) {
+            Account::Staking(contract) => contract,
+            _ => return Err(object!{"message" => "Invalid staking contract"}),
+        };
+
+        let mut inactive_stakes: Vec<(&Address, &InactiveStake)> = contract.iter_inactive_stakes().collect();
+        inactive_stakes.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(object!{
+            "balance" => u64::from(contract.balance),
+            "activeCount" => contract.active_stake_sorted.len(),
+            "inactiveCount" => inactive_stakes.len(),
+            "active" => JsonValue::Array(contract.iter_active_stakes()
+                .skip(offset)
+                .take(limit)
+                .map(Self::active_stake_to_obj)
+                .collect()),
+            "inactive" => JsonValue::Array(inactive_stakes.into_iter()
+                .skip(offset)
+                .take(limit)
+                .map(|(staker_address, stake)| Self::inactive_stake_to_obj(staker_address, stake))
+                .collect()),
+            "validators" => JsonValue::Array(contract.validator_stakes().into_iter()
+                .map(|(validator_key, stake)| object!{
+                    "validatorKey" => hex::encode(validator_key),
+                    "stake" => u64::from(stake),
+                })
+                .collect()),
+        })
+    }
+
     // Helper functions
 
+    fn active_stake_to_obj(stake: &ActiveStake) -> JsonValue {
+        object!{
+            "stakerAddress" => stake.staker_address().to_user_friendly_address(),
+            "balance" => u64::from(stake.balance()),
+            "validatorKey" => hex::encode(stake.validator_key()),
+            "rewardAddress" => stake.reward_address().to_user_friendly_address(),
+        }
+    }
+
+    fn inactive_stake_to_obj(staker_address: &Address, stake: &InactiveStake) -> JsonValue {
+        object!{
+            "stakerAddress" => staker_address.to_user_friendly_address(),
+            "balance" => u64::from(stake.balance()),
+            "retireTime" => stake.retire_time(),
+        }
+    }
+
     fn block_to_obj(&self, block: &Block, include_transactions: bool) -> JsonValue {
         let hash = block.hash().to_hex();
         let height = self.blockchain.height();
@@ -327,6 +1136,21 @@ impl BlockchainAlbatrossHandler {
         }
     }
 
+    fn epoch_stats_to_obj(stats: &EpochStats) -> JsonValue {
+        object! {
+            "epoch" => stats.epoch,
+            "complete" => stats.complete,
+            "blockCount" => stats.block_count,
+            "startTimestamp" => stats.start_timestamp,
+            "endTimestamp" => stats.end_timestamp,
+            "viewChanges" => stats.view_changes,
+            "transactionCount" => stats.transaction_count,
+            "fees" => u64::from(stats.fees),
+            "averageBlockTime" => stats.average_block_time().map(JsonValue::from).unwrap_or(Null),
+            "transactionsPerSecond" => stats.transactions_per_second().map(JsonValue::from).unwrap_or(Null),
+        }
+    }
+
     fn slots_to_obj(slots: &Slots) -> JsonValue {
         JsonValue::Array(Vec::from_iter(slots.iter()
             .enumerate()
@@ -383,6 +1207,11 @@ impl Module for BlockchainAlbatrossHandler {
         "getTransactionByBlockHashAndIndex" => generic.get_transaction_by_block_hash_and_index,
         "getTransactionByBlockNumberAndIndex" => generic.get_transaction_by_block_number_and_index,
         "getTransactionsByAddress" => generic.get_transactions_by_address,
+        "waitForTransactionTo" => wait_for_transaction_to,
+        "getPendingValidityInfo" => get_pending_validity_info,
+        "getTransactionAttestation" => get_transaction_attestation,
+        "simulateTransaction" => simulate_transaction,
+        "getPrunedAccounts" => get_pruned_accounts,
 
         // Blockchain
         "blockNumber" => generic.block_number,
@@ -393,8 +1222,18 @@ impl Module for BlockchainAlbatrossHandler {
         "getBlockTransactionCountByHash" => generic.get_block_transaction_count_by_hash,
         "getBlockTransactionCountByNumber" => generic.get_block_transaction_count_by_number,
         "slotState" => slot_state,
+        "getChainStats" => get_chain_stats,
+        "getEpochStateDigest" => get_epoch_state_digest,
+        "getEpochRewards" => get_epoch_rewards,
+        "getEpochByNumber" => get_epoch_by_number,
+        "getMacroBlockAncestors" => get_macro_block_ancestors,
 
         // Accounts
         "getBalance" => generic.get_balance,
+        "getVestingInfo" => get_vesting_info,
+        "getHtlcInfo" => get_htlc_info,
+
+        // Staking
+        "listStakes" => list_stakes,
     }
 }
@@ -4,6 +4,8 @@ use consensus::{Consensus, ConsensusEvent, ConsensusProtocol};
 use parking_lot::RwLock;
 use json::JsonValue;
 
+use utils::observer::Subscription;
+
 use crate::handler::Method;
 use crate::handlers::Module;
 
@@ -12,6 +14,7 @@ pub struct ConsensusHandler<P>
 {
     pub consensus: Arc<Consensus<P>>,
     state: Arc<RwLock<ConsensusHandlerState>>,
+    _subscription: Subscription<'static, ConsensusEvent>,
 }
 
 pub struct ConsensusHandlerState {
@@ -26,31 +29,27 @@ impl<P> ConsensusHandler<P>
             consensus: "syncing",
         };
         let state = Arc::new(RwLock::new(state));
-        let this = Self {
-            consensus: Arc::clone(&consensus),
-            state: Arc::clone(&state),
-        };
 
-        // Register for consensus events.
-        {
-            trace!("Register listener for consensus");
-            let state = Arc::downgrade(&state);
-            consensus.notifier.write().register(move |e: &ConsensusEvent| {
-                trace!("Consensus Event: {:?}", e);
-                if let Some(state) = state.upgrade() {
-                    match e {
-                        ConsensusEvent::Established => { state.write().consensus = "established" },
-                        ConsensusEvent::Lost => { state.write().consensus = "lost" },
-                        ConsensusEvent::Syncing => { state.write().consensus = "syncing" },
-                        _ => ()
-                    }
-                } else {
-                    // TODO Remove listener
+        // Subscribe to consensus events.
+        trace!("Register listener for consensus");
+        let weak = Arc::downgrade(&state);
+        let subscription = consensus.notifier.write().subscribe(move |e: &ConsensusEvent| {
+            trace!("Consensus Event: {:?}", e);
+            if let Some(state) = weak.upgrade() {
+                match e {
+                    ConsensusEvent::Established => { state.write().consensus = "established" },
+                    ConsensusEvent::Lost => { state.write().consensus = "lost" },
+                    ConsensusEvent::Syncing => { state.write().consensus = "syncing" },
+                    _ => ()
                 }
-            });
-        }
+            }
+        });
 
-        this
+        Self {
+            consensus,
+            state,
+            _subscription: subscription,
+        }
     }
 
     fn consensus(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use json::JsonValue;
+
+use blockchain_albatross::slot_schedule::SlotSchedule;
+use nimiq_database::Environment;
+
+use crate::handler::Method;
+use crate::handlers::Module;
+
+/// Maintenance methods for the database of a running node.
+pub struct DatabaseHandler {
+    env: &'static Environment,
+    backup_dir: PathBuf,
+    backup_running: Arc<AtomicBool>,
+    slot_schedule: Option<Arc<SlotSchedule<'static>>>,
+}
+
+impl DatabaseHandler {
+    /// Backups are written to new directories in `backup_dir`. If a validator's `slot_schedule` is
+    /// given, backups wait for the window around its slots to pass before they start, see
+    /// `SlotSchedule::wait_until_idle`.
+    pub fn new(env: &'static Environment, backup_dir: PathBuf, slot_schedule: Option<Arc<SlotSchedule<'static>>>) -> Self {
+        DatabaseHandler {
+            env,
+            backup_dir,
+            backup_running: Arc::new(AtomicBool::new(false)),
+            slot_schedule,
+        }
+    }
+
+    /// Starts a backup of the database and returns the directory it is written to.
+    /// The backup runs in the background and its result is logged.
+    pub(crate) fn backup_database(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_err(|_| object!{"message" => "System time is before the UNIX epoch"})?
+            .as_secs();
+        let path = self.backup_dir.join(format!("backup-{}", timestamp));
+        if path.exists() {
+            return Err(object!{"message" => "Backup directory already exists"});
+        }
+        let path = path.to_str()
+            .ok_or_else(|| object!{"message" => "Invalid backup directory"})?
+            .to_string();
+
+        if self.backup_running.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return Err(object!{"message" => "A backup is already running"});
+        }
+
+        let env = self.env;
+        let backup_running = Arc::clone(&self.backup_running);
+        let backup_path = path.clone();
+        let slot_schedule = self.slot_schedule.clone();
+        thread::spawn(move || {
+            if let Some(slot_schedule) = slot_schedule {
+                if !slot_schedule.wait_until_idle(SlotSchedule::DEFAULT_MAX_WAIT) {
+                    warn!("Validator is still busy, starting the database backup anyway");
+                }
+            }
+            info!("Starting database backup to {}", backup_path);
+            if let Err(e) = env.backup_to(&backup_path) {
+                error!("Database backup to {} failed: {}", backup_path, e);
+            }
+            backup_running.store(false, Ordering::Release);
+        });
+
+        Ok(object!{"path" => path})
+    }
+}
+
+impl Module for DatabaseHandler {
+    rpc_module_methods! {
+        "backupDatabase" => backup_database,
+    }
+}
@@ -1,6 +1,7 @@
 use std::convert::TryFrom;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::UNIX_EPOCH;
 
 use json::{Array, JsonValue, Null};
 use json::object::Object;
@@ -11,7 +12,8 @@ use consensus::ConsensusProtocol;
 use hash::{Blake2bHash, Hash};
 use keys::Address;
 use nimiq_mempool::Mempool;
-use nimiq_mempool::ReturnCode;
+use nimiq_mempool::filter::Rules;
+use nimiq_mempool::{FeeRejectionReason, ReturnCode, SnapshotOrder};
 use primitives::account::AccountType;
 use primitives::coin::Coin;
 use primitives::networks::NetworkId;
@@ -53,6 +55,73 @@ impl<P: ConsensusProtocol + 'static> MempoolHandler<P> {
             .collect::<Array>()))
     }
 
+    /// Returns a page of the mempool content with the metadata of each transaction. All
+    /// transactions of a page are taken from the same state of the mempool.
+    /// Parameters:
+    /// - offset (number, optional): Number of transactions to skip. Default is `0`.
+    /// - limit (number, optional): Maximum number of transactions. Default is `100`.
+    /// - order (string, optional): `"highestFee"`, `"lowestFee"`, `"oldest"` or `"newest"`.
+    ///     Default is `"highestFee"`.
+    /// - includeTransactions (bool, optional): Default is `false`.
+    ///
+    /// ```text
+    /// {
+    ///     total: number,
+    ///     transactions: Array<{
+    ///         hash: string,
+    ///         feePerByte: number,
+    ///         size: number,
+    ///         receivedAt: number, // milliseconds since the Unix epoch
+    ///         local: bool,
+    ///         transaction: object, // only if includeTransactions is set
+    ///     }>,
+    /// }
+    /// ```
+    pub(crate) fn mempool_snapshot(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let offset = match params.get(0) {
+            Some(value) => value.as_usize().ok_or_else(|| object!{"message" => "Invalid offset"})?,
+            None => 0,
+        };
+        let limit = match params.get(1) {
+            Some(value) => value.as_usize().ok_or_else(|| object!{"message" => "Invalid limit"})?,
+            None => 100,
+        };
+        let order = match params.get(2).map(|value| value.as_str()) {
+            Some(Some("highestFee")) | None => SnapshotOrder::HighestFeeFirst,
+            Some(Some("lowestFee")) => SnapshotOrder::LowestFeeFirst,
+            Some(Some("oldest")) => SnapshotOrder::OldestFirst,
+            Some(Some("newest")) => SnapshotOrder::NewestFirst,
+            _ => return Err(object!{"message" => "Invalid order"}),
+        };
+        let include_transactions = params.get(3).and_then(JsonValue::as_bool)
+            .unwrap_or(false);
+
+        let snapshot = self.mempool.snapshot(offset, limit, order);
+        let transactions = snapshot.entries.iter()
+            .map(|entry| {
+                let received_at = entry.received_at.duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_millis() as u64)
+                    .unwrap_or(0);
+                let mut obj = object! {
+                    "hash" => entry.hash.to_hex(),
+                    "feePerByte" => entry.fee_per_byte,
+                    "size" => entry.size,
+                    "receivedAt" => received_at,
+                    "local" => entry.local,
+                };
+                if include_transactions {
+                    obj.insert("transaction", transaction_to_obj(&entry.transaction, None, None)).unwrap();
+                }
+                obj
+            })
+            .collect::<Array>();
+
+        Ok(object! {
+            "total" => snapshot.total,
+            "transactions" => transactions,
+        })
+    }
+
     /// Returns mempool statistics on the number of transactions ordered by fee/byte.
     /// The numbers will be reported for the buckets `[0, 1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000, 10000]`.
     /// ```text
@@ -87,6 +156,28 @@ impl<P: ConsensusProtocol + 'static> MempoolHandler<P> {
         Ok(JsonValue::Object(transactions_per_bucket))
     }
 
+    /// Returns the filter rules of the mempool. They can be changed with `setMempoolFilterRules`.
+    ///
+    /// ```text
+    /// {
+    ///     txFee: number, // in Luna
+    ///     txFeePerByte: number,
+    ///     txValue: number,
+    ///     txValueTotal: number,
+    ///     contractFee: number,
+    ///     contractFeePerByte: number,
+    ///     contractValue: number,
+    ///     creationFee: number,
+    ///     creationFeePerByte: number,
+    ///     creationValue: number,
+    ///     senderBalance: number,
+    ///     recipientBalance: number,
+    /// }
+    /// ```
+    pub(crate) fn mempool_filter_rules(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        Ok(filter_rules_to_obj(&self.mempool.filter_rules()))
+    }
+
     /// Sends a raw transaction.
     /// Parameters:
     /// - transaction (string)
@@ -215,16 +306,140 @@ impl<P: ConsensusProtocol + 'static> MempoolHandler<P> {
         }
     }
 
+    /// Returns the transaction validity window of the next block.
+    /// Transactions must have a validity start height in `[minValidityStartHeight, maxValidityStartHeight]`
+    /// to be accepted. A transaction is valid for `length` blocks from its validity start height on.
+    /// ```text
+    /// {
+    ///     blockNumber: number,
+    ///     length: number,
+    ///     minValidityStartHeight: number,
+    ///     maxValidityStartHeight: number,
+    /// }
+    /// ```
+    pub(crate) fn transaction_validity_window(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let window = self.mempool.validity_window();
+        Ok(object! {
+            "blockNumber" => self.mempool.current_height() + 1,
+            "length" => self.mempool.validity_window_length(),
+            "minValidityStartHeight" => window.start,
+            "maxValidityStartHeight" => window.end - 1,
+        })
+    }
+
+    /// Estimates the fee per byte that a transaction must exceed to be included within the
+    /// next `targetBlocks` blocks, based on the mempool contents and the fees paid in recent blocks.
+    /// Parameters:
+    /// - targetBlocks (number, optional): Default is `1`.
+    pub(crate) fn estimate_fee_per_byte(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let target_blocks = match params.get(0) {
+            Some(value) => value.as_u32()
+                .ok_or_else(|| object!{"message" => "Invalid number of target blocks"})?,
+            None => 1,
+        };
+        Ok(self.mempool.estimate_fee_per_byte(target_blocks).into())
+    }
+
     // Helper functions
 
+    /// Pushes a transaction into the mempool as a locally submitted one, which a block producer
+    /// running on this node includes with priority. A transaction that isn't valid yet is kept
+    /// until it is, which is indicated by the message `Orphan`. If the transaction is rejected because of its fee,
+    /// the error contains the reason and the minimum fee that would be accepted:
+    ///
+    /// ```text
+    /// {
+    ///     message: string,
+    ///     reason: string, // "filterRules" | "freeTransactionLimit" | "senderTransactionLimit" | "mempoolFull" | "replacement"
+    ///     minFee: number,
+    ///     minFeePerByte: number,
+    /// }
+    /// ```
+    ///
+    /// If the transaction is rejected because it would likely expire before it is included, the
+    /// error contains the validity start height to use instead. It is `null` if no validity start
+    /// height is sufficient, so the fee needs to be raised.
+    ///
+    /// ```text
+    /// {
+    ///     message: string,
+    ///     reason: "expiring",
+    ///     validityEndHeight: number,
+    ///     estimatedInclusionHeight: number,
+    ///     suggestedValidityStartHeight: number|null,
+    /// }
+    /// ```
     pub(crate) fn push_transaction(&self, transaction: Transaction) -> Result<JsonValue, JsonValue> {
-        match self.mempool.push_transaction(transaction) {
+        match self.mempool.push_local_transaction(transaction.clone()) {
             ReturnCode::Accepted | ReturnCode::Known => Ok(object! {"message" => "Ok"}),
+            ReturnCode::Orphan => Ok(object! {"message" => "Orphan"}),
+            code @ ReturnCode::FeeTooLow | code @ ReturnCode::Filtered => {
+                match self.mempool.fee_feedback(&transaction) {
+                    Some(feedback) => Err(object! {
+                        "message" => format!("Rejected: {:?}", code),
+                        "reason" => fee_rejection_reason_to_str(feedback.reason),
+                        "minFee" => u64::from(feedback.min_fee),
+                        "minFeePerByte" => feedback.min_fee_per_byte(),
+                    }),
+                    None => Err(object! {"message" => format!("Rejected: {:?}", code)}),
+                }
+            },
+            code @ ReturnCode::Expiring => {
+                match self.mempool.validity_feedback(&transaction) {
+                    Some(feedback) => Err(object! {
+                        "message" => format!("Rejected: {:?}", code),
+                        "reason" => "expiring",
+                        "validityEndHeight" => feedback.validity_end_height,
+                        "estimatedInclusionHeight" => feedback.estimated_inclusion_height,
+                        "suggestedValidityStartHeight" => feedback.suggested_validity_start_height.map(JsonValue::from).unwrap_or(Null),
+                    }),
+                    None => Err(object! {"message" => format!("Rejected: {:?}", code)}),
+                }
+            },
             code => Err(object! {"message" => format!("Rejected: {:?}", code)})
         }
     }
 }
 
+fn fee_rejection_reason_to_str(reason: FeeRejectionReason) -> &'static str {
+    match reason {
+        FeeRejectionReason::FilterRules => "filterRules",
+        FeeRejectionReason::FreeTransactionLimit => "freeTransactionLimit",
+        FeeRejectionReason::SenderTransactionLimit => "senderTransactionLimit",
+        FeeRejectionReason::MempoolFull => "mempoolFull",
+        FeeRejectionReason::Replacement => "replacement",
+    }
+}
+
+pub(crate) fn filter_rules_to_obj(rules: &Rules) -> JsonValue {
+    object! {
+        "txFee" => u64::from(rules.tx_fee),
+        "txFeePerByte" => rules.tx_fee_per_byte,
+        "txValue" => u64::from(rules.tx_value),
+        "txValueTotal" => u64::from(rules.tx_value_total),
+        "contractFee" => u64::from(rules.contract_fee),
+        "contractFeePerByte" => rules.contract_fee_per_byte,
+        "contractValue" => u64::from(rules.contract_value),
+        "creationFee" => u64::from(rules.creation_fee),
+        "creationFeePerByte" => rules.creation_fee_per_byte,
+        "creationValue" => u64::from(rules.creation_value),
+        "senderBalance" => u64::from(rules.sender_balance),
+        "recipientBalance" => u64::from(rules.recipient_balance)
+    }
+}
+
+pub(crate) fn json_to_coin(key: &str, value: &JsonValue) -> Result<Coin, JsonValue> {
+    value.as_u64()
+        .and_then(|luna| Coin::try_from(luna).ok())
+        .ok_or_else(|| object!{"message" => format!("Invalid value for '{}'", key)})
+}
+
+pub(crate) fn json_to_fee_per_byte(key: &str, value: &JsonValue) -> Result<f64, JsonValue> {
+    value.as_f64()
+        .filter(|fee_per_byte| fee_per_byte.is_finite() && *fee_per_byte >= 0.0)
+        .ok_or_else(|| object!{"message" => format!("Invalid value for '{}'", key)})
+}
+
 pub(crate) fn transaction_to_obj(transaction: &Transaction, context: Option<&TransactionContext>, head_height: Option<u32>) -> JsonValue {
     object! {
         "hash" => transaction.hash::<Blake2bHash>().to_hex(),
@@ -330,7 +545,11 @@ impl<P: ConsensusProtocol + 'static> Module for MempoolHandler<P> {
         "createRawTransaction" => create_raw_transaction,
         "sendTransaction" => send_transaction,
         "mempoolContent" => mempool_content,
+        "mempoolSnapshot" => mempool_snapshot,
+        "mempoolFilterRules" => mempool_filter_rules,
         "mempool" => mempool,
         "getTransaction" => get_transaction,
+        "transactionValidityWindow" => transaction_validity_window,
+        "estimateFeePerByte" => estimate_fee_per_byte,
     }
 }
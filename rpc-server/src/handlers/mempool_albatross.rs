@@ -13,8 +13,8 @@ use network_primitives::networks::NetworkInfo;
 use nimiq_mempool::Mempool;
 use primitives::account::AccountType;
 use primitives::coin::Coin;
-use transaction::Transaction;
-use transaction::account::staking_contract::StakingTransactionData;
+use transaction::{Transaction, TransactionFlags};
+use transaction::account::staking_contract::{StakingTransactionData, ValidatorKeyRotationData};
 
 use crate::handler::Method;
 use crate::handlers::Module;
@@ -130,6 +130,58 @@ impl MempoolAlbatrossHandler {
             self.mempool.current_height(),      // validity_start_height
             network_id,                         // network_id
         );
+        // Otherwise the staking contract would treat this as a retire transaction.
+        tx.flags = TransactionFlags::SIGNALLING;
+
+        let unlocked_wallets = self.unlocked_wallets.as_ref()
+            .ok_or_else(|| object! {"message" => "No wallets"})?;
+        let unlocked_wallets = unlocked_wallets.read();
+        let wallet_account = unlocked_wallets.get(&staker_address)
+            .ok_or_else(|| object! {"message" => "Sender account is locked"})?;
+        wallet_account.sign_transaction(&mut tx);
+
+        self.generic.push_transaction(tx)
+    }
+
+    /// Replaces the validator key of a stake. The new key is elected from the next macro block on.
+    /// Parameters:
+    /// - validator_key: New public key of the validator (BLS)
+    /// - proof_of_knowledge: Proof of knowledge of the new validator key
+    /// - staker_address: NIM address used to stake
+    pub(crate) fn rotate_validator_key(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let validator_key = params.get(0)
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| object! {"message" => "Invalid validator key"})
+            .and_then(|it| hex::decode(it)
+                .map_err(|_| object! {"message" => "Validator key must be hex-encoded"}))
+            .and_then(|it| CompressedPublicKey::deserialize_from_vec(&it)
+                .map_err(|_| object! {"message" => "Invalid public key"}))?;
+        let proof_of_knowledge = params.get(1)
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| object! {"message" => "Invalid proof of knowledge"})
+            .and_then(|it| hex::decode(it)
+                .map_err(|_| object! {"message" => "Proof of knowledge must be hex-encoded"}))
+            .and_then(|it| CompressedSignature::deserialize_from_vec(&it)
+                .map_err(|_| object! {"message" => "Invalid proof of knowledge"}))?;
+        let staker_address = Self::parse_address(params.get(2).unwrap_or(&Null), "staker")?;
+
+        let network_id = self.mempool.network_id();
+        let genesis_account = NetworkInfo::from_network_id(network_id)
+            .validator_registry_address().unwrap();
+        let rotation_data = ValidatorKeyRotationData {
+            validator_key,
+            proof_of_knowledge,
+        };
+
+        // Transactions can't have a zero value. The value goes back into the stake.
+        let mut tx = Transaction::new_extended(
+            genesis_account.clone(), AccountType::Staking,
+            genesis_account.clone(), AccountType::Staking,
+            Coin::try_from(1).unwrap(), Coin::try_from(0).unwrap(), // amount, fee
+            rotation_data.serialize_to_vec(),   // data
+            self.mempool.current_height(),      // validity_start_height
+            network_id,                         // network_id
+        );
 
         let unlocked_wallets = self.unlocked_wallets.as_ref()
             .ok_or_else(|| object! {"message" => "No wallets"})?;
@@ -184,10 +236,15 @@ impl Module for MempoolAlbatrossHandler {
         "createRawTransaction" => generic.create_raw_transaction,
         "sendTransaction" => generic.send_transaction,
         "mempoolContent" => generic.mempool_content,
+        "mempoolSnapshot" => generic.mempool_snapshot,
+        "mempoolFilterRules" => generic.mempool_filter_rules,
         "mempool" => generic.mempool,
         "stake" => stake,
         "retire" => retire,
+        "rotateValidatorKey" => rotate_validator_key,
         "unstake" => unstake,
         "getTransaction" => generic.get_transaction,
+        "transactionValidityWindow" => generic.transaction_validity_window,
+        "estimateFeePerByte" => generic.estimate_fee_per_byte,
     }
 }
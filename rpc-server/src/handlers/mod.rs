@@ -5,6 +5,10 @@ use crate::handler::Method;
 macro_rules! rpc_module_methods {
     // trailing comma
     ( $( $k:expr => $($v:ident).+ , )* ) => (
+        fn name(&self) -> &'static str {
+            module_path!().rsplit("::").next().unwrap()
+        }
+
         fn methods(self) -> Vec<(&'static str, Method)> {
             let this_base = Arc::new(self);
             let mut vec = Vec::new();
@@ -23,17 +27,24 @@ macro_rules! rpc_module_methods {
     );
 }
 
+pub mod admin;
 pub mod consensus;
 pub mod block_production_nimiq;
 pub mod block_production_albatross;
 pub mod blockchain;
 pub mod blockchain_nimiq;
 pub mod blockchain_albatross;
+pub mod database;
 pub mod mempool;
 pub mod mempool_albatross;
 pub mod network;
+pub mod test;
 pub mod wallet;
+pub mod watch;
 
 pub trait Module: Send + Sync {
+    /// Name of the module the handler is defined in, e.g. `blockchain_albatross`. Its methods
+    /// are described under this name in the OpenAPI description.
+    fn name(&self) -> &'static str;
     fn methods(self) -> Vec<(&'static str, Method)>;
 }
@@ -3,9 +3,11 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use json::{Array, JsonValue, Null};
+use json::object::Object;
 
 use blockchain_base::AbstractBlockchain;
 use consensus::{ConsensusProtocol, Consensus};
+use hash::backend;
 use network_primitives::address::{PeerId, PeerUri};
 use nimiq_network::address::peer_address_state::{PeerAddressInfo, PeerAddressState};
 use nimiq_network::connection::close_type::CloseType;
@@ -17,20 +19,31 @@ use nimiq_network::peer_scorer::Score;
 use crate::handler::Method;
 use crate::handlers::Module;
 
+/// The client version and the services a node runs besides the network, as reported by
+/// `nodeInfo`.
+#[derive(Clone, Debug, Default)]
+pub struct NodeFeatures {
+    pub version: String,
+    pub validator: bool,
+    pub metrics: bool,
+}
+
 pub struct NetworkHandler<P: ConsensusProtocol + 'static> {
     pub consensus: Arc<Consensus<P>>,
     pub network: Arc<Network<P::Blockchain>>,
     pub blockchain: Arc<P::Blockchain>,
     pub starting_block: u32,
+    pub features: NodeFeatures,
 }
 
 impl<P: ConsensusProtocol + 'static> NetworkHandler<P> {
-    pub fn new(consensus: &Arc<Consensus<P>>) -> Self {
+    pub fn new(consensus: &Arc<Consensus<P>>, features: NodeFeatures) -> Self {
         NetworkHandler {
             consensus: consensus.clone(),
             network: consensus.network.clone(),
             blockchain: consensus.blockchain.clone(),
             starting_block: consensus.blockchain.head_height(),
+            features,
         }
     }
 
@@ -40,7 +53,13 @@ impl<P: ConsensusProtocol + 'static> NetworkHandler<P> {
     }
 
     /// If syncing is true, returns an object
-    /// { starting_block: number, current_block: number, highest_block: number },
+    /// {
+    ///     starting_block: number,
+    ///     current_block: number,
+    ///     highest_block: number,
+    ///     blocks_per_second: number,
+    ///     eta: number|null, // seconds
+    /// },
     /// otherwise returns false.
     pub(crate) fn syncing(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
         Ok(if self.consensus.established() {
@@ -48,10 +67,83 @@ impl<P: ConsensusProtocol + 'static> NetworkHandler<P> {
         }
         else {
             let current_block = self.blockchain.head_height();
-            object! {
-                "starting_block" => self.starting_block,
-                "current_block" => current_block,
-                "highest_block" => current_block // TODO
+            match self.consensus.sync_throttle.progress(current_block) {
+                Some(progress) => object! {
+                    "starting_block" => progress.starting_block,
+                    "current_block" => progress.current_block,
+                    "highest_block" => progress.highest_block,
+                    "blocks_per_second" => progress.blocks_per_second,
+                    "eta" => progress.eta.map(|eta| JsonValue::from(eta.as_secs())).unwrap_or(Null)
+                },
+                None => object! {
+                    "starting_block" => self.starting_block,
+                    "current_block" => current_block,
+                    "highest_block" => current_block,
+                    "blocks_per_second" => 0,
+                    "eta" => Null
+                },
+            }
+        })
+    }
+
+    /// Returns everything monitoring needs to know about this node in one call:
+    /// {
+    ///     peerId: string,
+    ///     peerAddress: string,
+    ///     networkId: string,
+    ///     version: string, // including the git hash, if known
+    ///     features: {
+    ///         validator: boolean,
+    ///         rpc: boolean,
+    ///         metrics: boolean,
+    ///     },
+    ///     database: {
+    ///         backend: string,
+    ///         size: number, // bytes in use
+    ///         mapSize: number,
+    ///     },
+    ///     consensus: {
+    ///         state: string, // "established" or "syncing"
+    ///         blockNumber: number,
+    ///         headHash: string,
+    ///         peerCount: number,
+    ///     },
+    ///     cryptoBackend: {
+    ///         cpuFeatures: Array<string>,
+    ///         blake2b: string,
+    ///         argon2d: string,
+    ///         bls: string,
+    ///     },
+    /// }
+    pub(crate) fn node_info(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let network_config = &self.network.network_config;
+        let database_stats = self.consensus.env.stats();
+        Ok(object! {
+            "peerId" => network_config.peer_id().to_hex(),
+            "peerAddress" => network_config.peer_address().as_uri().to_string(),
+            "networkId" => self.blockchain.network_id().to_string(),
+            "version" => self.features.version.clone(),
+            "features" => object! {
+                "validator" => self.features.validator,
+                "rpc" => true,
+                "metrics" => self.features.metrics
+            },
+            "database" => object! {
+                "backend" => self.consensus.env.backend_name(),
+                "size" => database_stats.used_size(),
+                "mapSize" => database_stats.map_size
+            },
+            "consensus" => object! {
+                "state" => if self.consensus.established() { "established" } else { "syncing" },
+                "blockNumber" => self.blockchain.head_height(),
+                "headHash" => self.blockchain.head_hash().to_hex(),
+                "peerCount" => self.network.peer_count()
+            },
+            "cryptoBackend" => object! {
+                "cpuFeatures" => backend::cpu_features(),
+                "blake2b" => backend::blake2b_implementation(),
+                "argon2d" => backend::argon2_implementation().name(),
+                "bls" => bls::IMPLEMENTATION
             }
         })
     }
@@ -161,6 +253,32 @@ impl<P: ConsensusProtocol + 'static> NetworkHandler<P> {
         }
     }
 
+    /// Returns statistics about the protocol versions and user agents of all connected peers:
+    /// {
+    ///     peerCount: number,
+    ///     versions: { [version: string]: number },
+    ///     userAgents: { [userAgent: string]: number },
+    /// }
+    pub(crate) fn peer_versions(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let statistics = self.network.connections.version_statistics();
+
+        let mut versions = Object::new();
+        for (version, &count) in statistics.versions() {
+            versions.insert(version.to_string().as_str(), count.into());
+        }
+
+        let mut user_agents = Object::new();
+        for (user_agent, &count) in statistics.user_agents() {
+            user_agents.insert(user_agent.as_str(), count.into());
+        }
+
+        Ok(object!{
+            "peerCount" => statistics.peer_count(),
+            "versions" => JsonValue::Object(versions),
+            "userAgents" => JsonValue::Object(user_agents)
+        })
+    }
+
     pub(crate) fn peer_address_info_to_obj(&self, peer_address_info: &PeerAddressInfo, connection_info: Option<&ConnectionInfo<P::Blockchain>>, score: Option<Score>) -> JsonValue {
         let state = self.network.connections.state();
         let connection_info = connection_info.or_else(|| {
@@ -192,5 +310,55 @@ impl<P: ConsensusProtocol + 'static> Module for NetworkHandler<P> {
         "syncing" => syncing,
         "peerList" => peer_list,
         "peerState" => peer_state,
+        "peerVersions" => peer_versions,
+        "nodeInfo" => node_info,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use consensus::AlbatrossConsensusProtocol;
+    use network_primitives::networks::NetworkId;
+    use nimiq_database::volatile::VolatileEnvironment;
+    use nimiq_mempool::MempoolConfig;
+    use nimiq_network::network_config::NetworkConfig;
+
+    use super::*;
+
+    fn network_handler(features: NodeFeatures) -> NetworkHandler<AlbatrossConsensusProtocol> {
+        let env = Box::leak(Box::new(VolatileEnvironment::new(10).unwrap()));
+        let mut network_config = NetworkConfig::new_dumb_network_config();
+        network_config.init_volatile();
+        let consensus = Consensus::new(env, NetworkId::UnitAlbatross, network_config, MempoolConfig::default(), None, None).unwrap();
+        NetworkHandler::new(&consensus, features)
+    }
+
+    #[test]
+    fn it_reports_the_node_info() {
+        let handler = network_handler(NodeFeatures {
+            version: "0.1.0".to_string(),
+            validator: false,
+            metrics: true,
+        });
+
+        let info = handler.node_info(&[]).unwrap();
+        assert_eq!(info["peerId"], handler.network.network_config.peer_id().to_hex());
+        assert_eq!(info["networkId"], NetworkId::UnitAlbatross.to_string());
+        assert_eq!(info["version"], "0.1.0");
+        assert_eq!(info["features"]["validator"], false);
+        assert_eq!(info["features"]["rpc"], true);
+        assert_eq!(info["features"]["metrics"], true);
+        assert_eq!(info["database"]["backend"], "volatile");
+        assert_eq!(info["consensus"]["state"], "syncing");
+        assert_eq!(info["consensus"]["blockNumber"], 0);
+        assert_eq!(info["consensus"]["headHash"], handler.blockchain.head_hash().to_hex());
+        assert_eq!(info["consensus"]["peerCount"], 0);
+        assert!(info["cryptoBackend"]["blake2b"].is_string());
+    }
+
+    #[test]
+    fn it_reports_a_validator() {
+        let handler = network_handler(NodeFeatures { validator: true, ..NodeFeatures::default() });
+        assert_eq!(handler.node_info(&[]).unwrap()["features"]["validator"], true);
     }
 }
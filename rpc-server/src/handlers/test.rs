@@ -0,0 +1,329 @@
+use std::sync::Arc;
+
+use beserial::Deserialize;
+use json::{JsonValue, Null};
+use parking_lot::Mutex;
+
+use block_albatross::{Block, MacroBlock, PbftCommitMessage, PbftPrepareMessage, PbftProofBuilder, SignedPbftCommitMessage, SignedPbftPrepareMessage, SignedViewChange, ViewChange, ViewChangeProof, ViewChangeProofBuilder};
+use block_production_albatross::BlockProducer;
+use block_production_albatross::selector::TransactionSelector;
+use blockchain_albatross::Blockchain;
+use blockchain_base::AbstractBlockchain;
+use bls::bls12_381::KeyPair;
+use collections::grouped_list::Group;
+use hash::{Blake2bHash, Hash};
+use network_primitives::time::NetworkTime;
+use nimiq_mempool::Mempool;
+use primitives::policy;
+use transaction::Transaction;
+
+use crate::handler::Method;
+use crate::handlers::Module;
+
+/// Selects a fixed set of transactions instead of the ones in the mempool.
+struct FixedTransactions(Vec<Transaction>);
+
+impl TransactionSelector for FixedTransactions {
+    fn select_transactions<'env>(&self, _mempool: &Mempool<'env, Blockchain<'env>>, _max_size: usize) -> Vec<Transaction> {
+        self.0.clone()
+    }
+}
+
+/// Methods to drive the chain of a dev network deterministically from application tests. Blocks
+/// are produced and signed with the validator key of the node, so they can only be produced for
+/// slots of this validator. Macro blocks and view changes need at least two thirds of the slots.
+///
+/// Only register this module on dev networks, with the validator's own block production disabled
+/// (see `manual_block_production` in the validator settings). Otherwise its blocks compete with
+/// the blocks produced here.
+pub struct TestHandler {
+    blockchain: Arc<Blockchain<'static>>,
+    mempool: Arc<Mempool<'static, Blockchain<'static>>>,
+    network_time: Arc<NetworkTime>,
+    block_producer: BlockProducer<'static>,
+    /// Held while producing a block, such that concurrent calls don't produce competing blocks.
+    production_lock: Mutex<()>,
+}
+
+impl TestHandler {
+    /// Maximum number of blocks produced by a single `test_mineBlocks` call.
+    const MAX_BLOCKS: u32 = 10_000;
+
+    pub fn new(blockchain: Arc<Blockchain<'static>>, mempool: Arc<Mempool<'static, Blockchain<'static>>>, validator_key: KeyPair, network_time: Arc<NetworkTime>) -> Self {
+        let mut block_producer = BlockProducer::new(Arc::clone(&blockchain), Arc::clone(&mempool), validator_key);
+        block_producer.verify_blocks = true;
+        TestHandler {
+            blockchain,
+            mempool,
+            network_time,
+            block_producer,
+            production_lock: Mutex::new(()),
+        }
+    }
+
+    /// Produces blocks instantly, including macro blocks. Micro blocks include the transactions of
+    /// the mempool.
+    /// Parameters:
+    /// - count (number, optional): Number of blocks to produce (default: 1)
+    ///
+    /// Returns the hashes of the produced blocks.
+    pub(crate) fn mine_blocks(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let count = match params.get(0).unwrap_or(&Null) {
+            Null => 1,
+            value => value.as_u32().ok_or_else(|| object!{"message" => "Block count must be a number"})?,
+        };
+        if count > Self::MAX_BLOCKS {
+            return Err(object!{"message" => format!("Can't produce more than {} blocks at once", Self::MAX_BLOCKS)});
+        }
+
+        let mut hashes = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            hashes.push(self.produce_block(&self.block_producer, false)?.to_hex().into());
+        }
+        Ok(JsonValue::Array(hashes))
+    }
+
+    /// Sets the offset of the node's clock, which is added to the network time, e.g. to produce
+    /// blocks with timestamps in the future.
+    /// Parameters:
+    /// - offset (number): Offset in milliseconds, may be negative
+    ///
+    /// Returns the network time with the new offset.
+    pub(crate) fn set_clock_offset(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let offset = params.get(0).unwrap_or(&Null).as_i64()
+            .ok_or_else(|| object!{"message" => "Offset must be a number"})?;
+        self.network_time.set_manual_offset(offset);
+        Ok(self.network_time.now().into())
+    }
+
+    /// Produces the next block in the view after the current one, skipping the slot of the
+    /// current block producer.
+    ///
+    /// Returns the hash of the produced block.
+    pub(crate) fn force_view_change(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        Ok(self.produce_block(&self.block_producer, true)?.to_hex().into())
+    }
+
+    /// Produces a micro block with exactly the given transactions, bypassing the mempool.
+    /// Parameters:
+    /// - transactions (Array<string>): Serialized transactions (hex)
+    ///
+    /// Returns the hash of the produced block.
+    pub(crate) fn inject_transactions(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let transactions = params.get(0).unwrap_or(&Null);
+        if !transactions.is_array() {
+            return Err(object!{"message" => "Transactions must be an array"});
+        }
+        let transactions = transactions.members()
+            .map(|transaction| {
+                let raw = hex::decode(transaction.as_str()
+                    .ok_or_else(|| object!{"message" => "Raw transaction must be a string"})?)
+                    .map_err(|_| object!{"message" => "Raw transaction must be a hex string"})?;
+                Deserialize::deserialize_from_vec(&raw)
+                    .map_err(|_| object!{"message" => "Transaction can't be deserialized"})
+            })
+            .collect::<Result<Vec<Transaction>, JsonValue>>()?;
+
+        let block_number = self.blockchain.block_number() + 1;
+        if policy::is_macro_block_at(block_number) {
+            return Err(object!{"message" => "Next block is a macro block"});
+        }
+
+        // The block producer expects valid transactions.
+        for transaction in &transactions {
+            transaction.verify(self.blockchain.network_id)
+                .map_err(|e| object!{"message" => format!("Invalid transaction {}: {}", transaction.hash::<Blake2bHash>(), e)})?;
+        }
+        self.blockchain.state().accounts()
+            .collect_receipts(&transactions, &[], block_number)
+            .map_err(|e| object!{"message" => format!("Transactions can't be applied: {}", e)})?;
+
+        let mut block_producer = BlockProducer::with_transaction_selector(
+            Arc::clone(&self.blockchain),
+            Arc::clone(&self.mempool),
+            self.block_producer.validator_key.clone(),
+            FixedTransactions(transactions));
+        block_producer.verify_blocks = true;
+        Ok(self.produce_block(&block_producer, false)?.to_hex().into())
+    }
+
+    /// Produces, signs and pushes the next block with `block_producer`. With `view_change`, the
+    /// block is produced in the next view, justified by a view change signed with our slots.
+    fn produce_block(&self, block_producer: &BlockProducer<'static>, view_change: bool) -> Result<Blake2bHash, JsonValue> {
+        let _lock = self.production_lock.lock();
+
+        let block_number = self.blockchain.block_number() + 1;
+        let mut view_number = self.blockchain.next_view_number();
+        let view_change_proof = if view_change {
+            view_number += 1;
+            Some(self.sign_view_change(block_number, view_number)?)
+        } else {
+            None
+        };
+
+        let producer = self.blockchain.get_next_block_producer(view_number, None);
+        if producer.slot.public_key.compressed() != &self.block_producer.validator_key.public.compress() {
+            return Err(object!{"message" => format!("Not the block producer of block #{}.{}", block_number, view_number)});
+        }
+
+        let timestamp = self.network_time.now();
+        let block = if policy::is_macro_block_at(block_number) {
+            let (proposal, extrinsics) = block_producer.verified_macro_block_proposal(timestamp, view_number, view_change_proof)
+                .map_err(|e| object!{"message" => format!("Produced invalid macro block: {}", e)})?;
+            let (pk_idx, num_slots) = self.own_slots()?;
+            let block_hash = proposal.header.hash::<Blake2bHash>();
+
+            let prepare = SignedPbftPrepareMessage::from_message(
+                PbftPrepareMessage { block_hash: block_hash.clone() },
+                &block_producer.validator_key.secret,
+                pk_idx);
+            let commit = SignedPbftCommitMessage::from_message(
+                PbftCommitMessage { block_hash },
+                &block_producer.validator_key.secret,
+                pk_idx);
+            let mut proof_builder = PbftProofBuilder::new();
+            proof_builder.add_prepare_signature(&block_producer.validator_key.public, num_slots, &prepare);
+            proof_builder.add_commit_signature(&block_producer.validator_key.public, num_slots, &commit);
+
+            Block::Macro(MacroBlock {
+                header: proposal.header,
+                justification: Some(proof_builder.build()),
+                extrinsics: Some(extrinsics),
+            })
+        } else {
+            let block = block_producer.verified_micro_block(vec![], timestamp, view_number, vec![], view_change_proof)
+                .map_err(|e| object!{"message" => format!("Produced invalid micro block: {}", e)})?;
+            Block::Micro(block)
+        };
+
+        let hash = block.hash();
+        self.blockchain.push_block(block, false)
+            .map_err(|e| object!{"message" => format!("Failed to push block: {}", e)})?;
+        Ok(hash)
+    }
+
+    fn sign_view_change(&self, block_number: u32, new_view_number: u32) -> Result<ViewChangeProof, JsonValue> {
+        let (pk_idx, num_slots) = self.own_slots()?;
+        let key = &self.block_producer.validator_key;
+
+        let view_change = ViewChange { block_number, new_view_number };
+        let signed_view_change = SignedViewChange::from_message(view_change, &key.secret, pk_idx);
+
+        let mut proof_builder = ViewChangeProofBuilder::new();
+        proof_builder.add_signature(&key.public, num_slots, &signed_view_change);
+        Ok(proof_builder.build())
+    }
+
+    /// Index and number of slots of our validator in the current epoch. Fails if we don't have
+    /// enough slots to sign view changes and macro blocks on our own.
+    fn own_slots(&self) -> Result<(u16, u16), JsonValue> {
+        let public_key = self.block_producer.validator_key.public.compress();
+        let validators = self.blockchain.current_validators();
+        let (pk_idx, num_slots) = validators.groups().iter().enumerate()
+            .find(|(_, Group(_, validator_key))| validator_key.compressed() == &public_key)
+            .map(|(i, Group(num_slots, _))| (i as u16, *num_slots))
+            .ok_or_else(|| object!{"message" => "Not a validator of the current epoch"})?;
+        if num_slots < policy::TWO_THIRD_SLOTS {
+            return Err(object!{"message" => format!("Validator has only {} of the {} slots required", num_slots, policy::TWO_THIRD_SLOTS)});
+        }
+        Ok((pk_idx, num_slots))
+    }
+}
+
+impl Module for TestHandler {
+    rpc_module_methods! {
+        "test_mineBlocks" => mine_blocks,
+        "test_setClockOffset" => set_clock_offset,
+        "test_forceViewChange" => force_view_change,
+        "test_injectTransactions" => inject_transactions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use beserial::Serialize;
+    use bls::bls12_381::SecretKey;
+    use keys::Address;
+    use network_primitives::networks::NetworkId;
+    use nimiq_database::volatile::VolatileEnvironment;
+    use nimiq_mempool::MempoolConfig;
+    use primitives::coin::Coin;
+
+    use super::*;
+
+    /// Key of the only validator of `network-primitives/src/genesis/unit-albatross.toml`.
+    const SECRET_KEY: &str = "49ea68eb6b8afdf4ca4d4c0a0b295c76ca85225293693bc30e755476492b707f";
+
+    fn test_handler() -> TestHandler {
+        let env = Box::leak(Box::new(VolatileEnvironment::new(10).unwrap()));
+        let blockchain = Arc::new(Blockchain::new(env, NetworkId::UnitAlbatross).unwrap());
+        let mempool = Mempool::new(Arc::clone(&blockchain), MempoolConfig::default());
+        let key = KeyPair::from(SecretKey::deserialize_from_vec(&hex::decode(SECRET_KEY).unwrap()).unwrap());
+        TestHandler::new(blockchain, mempool, key, Arc::new(NetworkTime::new()))
+    }
+
+    #[test]
+    fn it_mines_blocks_up_to_the_next_epoch() {
+        let handler = test_handler();
+
+        let hashes = handler.mine_blocks(&[]).unwrap();
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(handler.blockchain.block_number(), 1);
+
+        let count = policy::macro_block_after(1);
+        let hashes = handler.mine_blocks(&[count.into()]).unwrap();
+        assert_eq!(hashes.len(), count as usize);
+        assert_eq!(handler.blockchain.block_number(), count + 1);
+        assert_eq!(handler.blockchain.macro_head().header.block_number, policy::macro_block_after(1));
+        assert_eq!(hashes[count as usize - 1], handler.blockchain.head_hash().to_hex());
+    }
+
+    #[test]
+    fn it_rejects_invalid_block_counts() {
+        let handler = test_handler();
+        assert!(handler.mine_blocks(&["1".into()]).is_err());
+        assert!(handler.mine_blocks(&[(TestHandler::MAX_BLOCKS + 1).into()]).is_err());
+        assert_eq!(handler.blockchain.block_number(), 0);
+    }
+
+    #[test]
+    fn it_forces_view_changes() {
+        let handler = test_handler();
+        let hash = handler.force_view_change(&[]).unwrap();
+
+        let head = handler.blockchain.head();
+        assert_eq!(head.block_number(), 1);
+        assert_eq!(head.view_number(), 1);
+        assert_eq!(hash, head.hash().to_hex());
+    }
+
+    #[test]
+    fn it_injects_transactions() {
+        let handler = test_handler();
+
+        assert!(handler.inject_transactions(&["00".into()]).is_err());
+        assert!(handler.inject_transactions(&[array!["zz"]]).is_err());
+        assert_eq!(handler.blockchain.block_number(), 0);
+
+        // Unsigned transactions are rejected.
+        let transaction = Transaction::new_basic(Address::default(), Address::default(), Coin::from_u64_unchecked(1), Coin::ZERO, 1, NetworkId::UnitAlbatross);
+        let raw = hex::encode(transaction.serialize_to_vec());
+        assert!(handler.inject_transactions(&[array![raw]]).is_err());
+        assert_eq!(handler.blockchain.block_number(), 0);
+
+        let hash = handler.inject_transactions(&[array![]]).unwrap();
+        assert_eq!(handler.blockchain.block_number(), 1);
+        assert_eq!(hash, handler.blockchain.head_hash().to_hex());
+    }
+
+    #[test]
+    fn it_sets_the_clock_offset() {
+        let handler = test_handler();
+        let now = handler.network_time.now();
+
+        let offset_now = handler.set_clock_offset(&[3_600_000.into()]).unwrap().as_u64().unwrap();
+        assert!(offset_now >= now + 3_600_000);
+        assert_eq!(handler.network_time.manual_offset(), 3_600_000);
+        assert!(handler.set_clock_offset(&[]).is_err());
+    }
+}
@@ -7,7 +7,7 @@ use json::{JsonValue, Null};
 use parking_lot::RwLock;
 
 use beserial::{Deserialize, Serialize};
-use keys::{Address, KeyPair, PrivateKey, PublicKey, Signature};
+use keys::{Address, KeyPair, PrivateKey, PublicKey, SecretBytes, Signature};
 use nimiq_database::Environment;
 use nimiq_wallet::{WalletAccount, WalletStore};
 use utils::otp::{Locked, Unlocked};
@@ -65,10 +65,7 @@ impl WalletHandler {
         let private_key = PrivateKey::from_str(private_key)
             .map_err(|e| object!{"message" => e.to_string()})?;
 
-        // FIXME: We're not clearing the passphrase right now.
-        let passphrase = params.get(1).map(|s: &JsonValue| s.as_str()
-                .ok_or_else(|| object!{"message" => "Passphrase must be a string"})
-            ).unwrap_or_else(|| Ok(""))?;
+        let passphrase = Self::passphrase(params, 1)?;
 
         let wallet_account = WalletAccount::from(KeyPair::from(private_key));
         let address = wallet_account.address.clone();
@@ -106,10 +103,7 @@ impl WalletHandler {
     /// - passphrase (optional, string): The passphrase to lock the key with.
     /// Returns the user friendly address corresponding to the private key.
     pub(crate) fn new_account(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
-        // FIXME: We're not clearing the passphrase right now.
-        let passphrase = params.get(0).map(|s: &JsonValue| s.as_str()
-                .ok_or_else(|| object!{"message" => "Passphrase must be a string"})
-            ).unwrap_or_else(|| Ok(""))?;
+        let passphrase = Self::passphrase(params, 0)?;
 
         let wallet_account = WalletAccount::generate();
         let address = wallet_account.address.clone();
@@ -134,10 +128,7 @@ impl WalletHandler {
             .ok_or_else(|| object!{"message" => "Address must be a string"})?)
             .map_err(|_|  object!{"message" => "Address invalid"})?;
 
-        // FIXME: We're not clearing the passphrase right now.
-        let passphrase = params.get(1).map(|s: &JsonValue| s.as_str()
-                .ok_or_else(|| object!{"message" => "Passphrase must be a string"})
-            ).unwrap_or_else(|| Ok(""))?;
+        let passphrase = Self::passphrase(params, 1)?;
 
         // TODO: duration
 
@@ -188,10 +179,7 @@ impl WalletHandler {
             .ok_or_else(|| object!{"message" => "Address must be a string"})?)
             .map_err(|_|  object!{"message" => "Address invalid"})?;
 
-        // FIXME: We're not clearing the passphrase right now.
-        let passphrase = params.get(1).map(|s: &JsonValue| s.as_str()
-            .ok_or_else(|| object!{"message" => "Passphrase must be a string"}))
-            .unwrap_or_else(|| Ok(""))?;
+        let passphrase = Self::passphrase(params, 2)?;
 
         if let Some(wallet) = self.unlocked_wallets.read().get(&account_address) {
             Ok(self.sign_message(&message, &wallet))
@@ -253,6 +241,15 @@ impl WalletHandler {
         Ok(JsonValue::Boolean(WalletAccount::verify_message(&public_key, &message, &signature)))
     }
 
+    /// Reads the optional passphrase at `index`, which is cleared from memory when dropped.
+    /// The copy in the parsed request can't be cleared.
+    fn passphrase(params: &[JsonValue], index: usize) -> Result<SecretBytes, JsonValue> {
+        params.get(index).map(|s: &JsonValue| s.as_str()
+                .map(SecretBytes::from)
+                .ok_or_else(|| object!{"message" => "Passphrase must be a string"})
+            ).unwrap_or_else(|| Ok(SecretBytes::default()))
+    }
+
     fn sign_message(&self, message: &[u8], wallet: &WalletAccount) -> JsonValue {
         let (public_key, signature) = wallet.sign_message(&message);
         let public_key = Serialize::serialize_to_vec(&public_key);
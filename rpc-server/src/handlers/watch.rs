@@ -0,0 +1,187 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{future, Future};
+use futures::sync::oneshot;
+use json::{JsonValue, Null};
+use parking_lot::Mutex;
+use tokio::timer::Timeout;
+
+use blockchain_albatross::Blockchain;
+use blockchain_albatross::watch_registry::{WatchedTransaction, WatchRegistry};
+use blockchain_base::AbstractBlockchain;
+use keys::Address;
+use nimiq_database::Environment;
+
+use crate::handler::{Method, MethodFuture};
+use crate::handlers::Module;
+use crate::handlers::mempool::{transaction_to_obj, TransactionContext};
+
+/// Methods to watch addresses without a full address history index.
+pub struct WatchHandler {
+    blockchain: Arc<Blockchain<'static>>,
+    registry: Arc<WatchRegistry<'static>>,
+}
+
+impl WatchHandler {
+    const MAX_WAIT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+    /// Opens the `WatchRegistry` in `env`, which starts indexing the watched addresses.
+    pub fn new(env: &'static Environment, blockchain: Arc<Blockchain<'static>>) -> Self {
+        WatchHandler {
+            registry: WatchRegistry::new(env, Arc::clone(&blockchain)),
+            blockchain,
+        }
+    }
+
+    /// The registry of the watched addresses.
+    pub fn registry(&self) -> Arc<WatchRegistry<'static>> {
+        Arc::clone(&self.registry)
+    }
+
+    /// Starts indexing the transactions from and to an address. Transactions are indexed once
+    /// their block is finalized.
+    /// Parameters:
+    /// - address (string)
+    ///
+    /// Returns the block number from which on the address is indexed.
+    pub(crate) fn watch_address(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let address = Self::address_param(params)?;
+        Ok(self.registry.watch(&address).into())
+    }
+
+    /// Stops watching an address and removes its indexed transactions.
+    /// Parameters:
+    /// - address (string)
+    ///
+    /// Returns whether the address was watched.
+    pub(crate) fn unwatch_address(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let address = Self::address_param(params)?;
+        Ok(self.registry.unwatch(&address).into())
+    }
+
+    /// Returns the watched addresses:
+    /// ```text
+    /// Array<{
+    ///     address: string, (user friendly address)
+    ///     since: number,
+    /// }>
+    /// ```
+    pub(crate) fn list_watched_addresses(&self, _params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        Ok(JsonValue::Array(self.registry.watched_addresses().iter()
+            .map(|address| object!{
+                "address" => address.to_user_friendly_address(),
+                "since" => self.registry.watched_since(address).map(JsonValue::from).unwrap_or(Null),
+            })
+            .collect()))
+    }
+
+    /// Returns the indexed transactions from and to a watched address.
+    /// Parameters:
+    /// - address (string)
+    /// - since (number, optional): Only return transactions in blocks from this block number on.
+    ///
+    /// Returns an object:
+    /// ```text
+    /// {
+    ///     lastIndexed: number, (last block number that was indexed)
+    ///     transactions: Array<transaction_objects>, (see `getTransactionByHash`)
+    /// }
+    /// ```
+    pub(crate) fn get_watched_activity(&self, params: &[JsonValue]) -> Result<JsonValue, JsonValue> {
+        let address = Self::address_param(params)?;
+        let since = match params.get(1) {
+            None | Some(JsonValue::Null) => 0,
+            Some(value) => value.as_u32().ok_or_else(|| object!{"message" => "Invalid block number"})?,
+        };
+        if self.registry.watched_since(&address).is_none() {
+            return Err(object!{"message" => "Address is not watched"});
+        }
+
+        let head_height = self.blockchain.head_height();
+        let transactions = self.registry.activity(&address, since).iter()
+            .map(|tx| Self::watched_transaction_to_obj(tx, Some(head_height)))
+            .collect();
+
+        Ok(object!{
+            "lastIndexed" => self.registry.last_indexed(),
+            "transactions" => JsonValue::Array(transactions),
+        })
+    }
+
+    /// Waits until a transaction from or to a watched address is indexed.
+    /// Parameters:
+    /// - address (string)
+    /// - timeout (number): Timeout in seconds, at most 600.
+    ///
+    /// Returns the transaction (see `getTransactionByHash`), or `null` if the timeout expired.
+    pub(crate) fn wait_for_watched_activity(&self, params: &[JsonValue]) -> MethodFuture {
+        let registry = Arc::clone(&self.registry);
+        Box::new(future::result(self.wait_request(params)).and_then(move |(address, timeout)| {
+            let (sender, receiver) = oneshot::channel();
+            let sender = Mutex::new(Some(sender));
+            let subscription = registry.notifier.write().subscribe(move |tx: &WatchedTransaction| {
+                if tx.address == address {
+                    if let Some(sender) = sender.lock().take() {
+                        // The receiver is gone if the call timed out.
+                        sender.send(Self::watched_transaction_to_obj(tx, None)).ok();
+                    }
+                }
+            });
+
+            let transaction = receiver
+                .map_err(|_| object!{"message" => "Watch registry was dropped"})
+                // The listener stays registered until the call returns.
+                .then(move |result| {
+                    drop(subscription);
+                    result
+                });
+            Timeout::new(transaction, timeout).then(|result| match result {
+                Ok(transaction) => Ok(transaction),
+                Err(ref e) if e.is_elapsed() => Ok(Null),
+                Err(e) => Err(e.into_inner().unwrap_or_else(|| object!{"message" => "Timer failed"})),
+            })
+        }))
+    }
+
+    /// Parses the parameters of `waitForWatchedActivity`.
+    fn wait_request(&self, params: &[JsonValue]) -> Result<(Address, Duration), JsonValue> {
+        let address = Self::address_param(params)?;
+        let timeout = params.get(1).and_then(JsonValue::as_u64)
+            .map(Duration::from_secs)
+            .ok_or_else(|| object!{"message" => "Invalid timeout"})?;
+        if timeout > Self::MAX_WAIT_TIMEOUT {
+            return Err(object!{"message" => format!("Timeout must not exceed {} seconds", Self::MAX_WAIT_TIMEOUT.as_secs())});
+        }
+        if self.registry.watched_since(&address).is_none() {
+            return Err(object!{"message" => "Address is not watched"});
+        }
+        Ok((address, timeout))
+    }
+
+    fn address_param(params: &[JsonValue]) -> Result<Address, JsonValue> {
+        params.get(0).and_then(JsonValue::as_str)
+            .ok_or_else(|| object!{"message" => "Invalid address"})
+            .and_then(|s| Address::from_any_str(s)
+                .map_err(|_| object!{"message" => "Invalid address"}))
+    }
+
+    fn watched_transaction_to_obj(tx: &WatchedTransaction, head_height: Option<u32>) -> JsonValue {
+        transaction_to_obj(&tx.transaction, Some(&TransactionContext {
+            block_hash: &tx.block_hash.to_hex(),
+            block_number: tx.block_number,
+            index: tx.index,
+            timestamp: tx.timestamp,
+        }), head_height)
+    }
+}
+
+impl Module for WatchHandler {
+    rpc_module_methods! {
+        "watchAddress" => watch_address,
+        "unwatchAddress" => unwatch_address,
+        "listWatchedAddresses" => list_watched_addresses,
+        "getWatchedActivity" => get_watched_activity,
+        "waitForWatchedActivity" => wait_for_watched_activity,
+    }
+}
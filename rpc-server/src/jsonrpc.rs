@@ -1,3 +1,4 @@
+use std::net::IpAddr;
 use std::sync::Arc;
 
 use futures::{future, Future, IntoFuture, stream::Stream};
@@ -7,21 +8,34 @@ use json::{Array, JsonValue, Null};
 
 use crate::error::AuthenticationError;
 
+/// Future resolving to the result of a method call, or to its error object.
+pub type CallFuture = Box<dyn Future<Item=JsonValue, Error=JsonValue> + Send>;
+
+type ResponseFuture = Box<dyn Future<Item=Response<Body>, Error=hyper::Error> + Send>;
+
 pub trait Handler: Send + Sync {
-    fn call_method(&self, name: &str, params: Array) -> Option<Result<JsonValue, JsonValue>>;
+    /// Calls method `name` on behalf of the client at IP address `client`.
+    fn call_method(&self, client: IpAddr, name: &str, params: Array) -> Option<CallFuture>;
+    /// OpenAPI description of the methods, served at `/openapi.json`.
+    fn describe(&self) -> Option<JsonValue> {
+        None
+    }
     fn authorize(&self, _username: &str, _password: &str) -> Result<(), AuthenticationError> {
         Ok(())
     }
 }
 
 pub struct Service<H> where H: Handler {
-    handler: Arc<H>
+    handler: Arc<H>,
+    /// IP address of the client that is served
+    client: IpAddr,
 }
 
 impl<H> Service<H> where H: Handler {
-    pub fn new(handler: Arc<H>) -> Self {
+    pub fn new(handler: Arc<H>, client: IpAddr) -> Self {
         Service {
             handler,
+            client,
         }
     }
 }
@@ -42,11 +56,11 @@ impl std::fmt::Display for Never {
     }
 }
 
-fn handle_request<H>(handler: Arc<H>, str_o: Result<&str, std::str::Utf8Error>) -> Response<Body> where H: Handler {
+fn handle_request<H>(handler: Arc<H>, client: IpAddr, str_o: Result<&str, std::str::Utf8Error>) -> ResponseFuture where H: Handler {
     let mut builder = Response::builder();
     builder.header("Content-Type", "application/json");
     if str_o.is_err() {
-        return builder
+        return Box::new(future::ok(builder
             .status(StatusCode::BAD_REQUEST)
             .body(Body::from(json::stringify(object! {
                 "jsonrpc" => "2.0",
@@ -56,11 +70,11 @@ fn handle_request<H>(handler: Arc<H>, str_o: Result<&str, std::str::Utf8Error>)
                     "message" => "Invalid encoding"
                 }
             })))
-            .unwrap();
+            .unwrap()));
     }
     let json_o = json::parse(str_o.unwrap());
     if json_o.is_err() {
-        return builder
+        return Box::new(future::ok(builder
             .status(StatusCode::BAD_REQUEST)
             .body(Body::from(json::stringify(object! {
                 "jsonrpc" => "2.0",
@@ -70,7 +84,7 @@ fn handle_request<H>(handler: Arc<H>, str_o: Result<&str, std::str::Utf8Error>)
                     "message" => "Invalid JSON"
                 }
             })))
-            .unwrap();
+            .unwrap()));
     }
     let mut json = json_o.unwrap();
     let single = json.is_object();
@@ -78,7 +92,7 @@ fn handle_request<H>(handler: Arc<H>, str_o: Result<&str, std::str::Utf8Error>)
         json = array![json];
     }
     if !json.is_array() {
-        return builder
+        return Box::new(future::ok(builder
             .status(StatusCode::BAD_REQUEST)
             .body(Body::from(json::stringify(object! {
                 "jsonrpc" => "2.0",
@@ -88,19 +102,20 @@ fn handle_request<H>(handler: Arc<H>, str_o: Result<&str, std::str::Utf8Error>)
                     "message" => "Invalid request"
                 }
             })))
-            .unwrap();
+            .unwrap()));
     }
-    let mut results = vec![];
+    let mut results: Vec<Box<dyn Future<Item=JsonValue, Error=hyper::Error> + Send>> = vec![];
     for msg in json.members() {
+        let id = msg["id"].clone();
         if msg["jsonrpc"] != "2.0" || !msg.has_key("method") || !msg["method"].is_string() {
-            results.push(object! {
+            results.push(Box::new(future::ok(object! {
                 "jsonrpc" => "2.0",
-                "id" => msg["id"].clone(),
+                "id" => id,
                 "error" => object!{
                     "code" => -32600,
                     "message" => "Invalid request"
                 }
-            });
+            })));
             continue;
         }
 
@@ -111,41 +126,44 @@ fn handle_request<H>(handler: Arc<H>, str_o: Result<&str, std::str::Utf8Error>)
         };
 
         let result_o = handler.call_method(
+            client,
             msg["method"].as_str().unwrap(),
             params_array,
         );
         if result_o.is_none() {
             warn!("Unknown method called: {}", msg["method"]);
-            results.push(object! {
+            results.push(Box::new(future::ok(object! {
                 "jsonrpc" => "2.0",
-                "id" => msg["id"].clone(),
+                "id" => id,
                 "error" => object!{
                     "code" => -32601,
                     "message" => "Method not found"
                 }
-            });
+            })));
             continue;
         }
 
-        results.push(match result_o.unwrap() {
+        results.push(Box::new(result_o.unwrap().then(move |result| Ok(match result {
             Ok(result) => object! {
                 "jsonrpc" => "2.0",
-                "id" => msg["id"].clone(),
+                "id" => id,
                 "result" => result
             },
             Err(error) => object! {
                 "jsonrpc" => "2.0",
-                "id" => msg["id"].clone(),
+                "id" => id,
                 "error" => error
             }
-        });
+        }))));
     }
 
-    if single {
-        builder.body(Body::from(results.pop().map(json::stringify).unwrap_or_else(String::new))).unwrap()
-    } else {
-        builder.body(Body::from(json::stringify(JsonValue::Array(results)))).unwrap()
-    }
+    Box::new(future::join_all(results).map(move |mut results| {
+        if single {
+            builder.body(Body::from(results.pop().map(json::stringify).unwrap_or_else(String::new))).unwrap()
+        } else {
+            builder.body(Body::from(json::stringify(JsonValue::Array(results)))).unwrap()
+        }
+    }))
 }
 
 fn check_authentication<H: Handler>(handler: Arc<H>, authorization: Option<&HeaderValue>) -> Result<(), AuthenticationError> {
@@ -188,7 +206,29 @@ impl<H> hyper::service::Service for Service<H> where H: Handler + 'static {
 
     fn call(&mut self, req: Request<<Self as hyper::service::Service>::ReqBody>) -> <Self as hyper::service::Service>::Future {
         let handler = Arc::clone(&self.handler);
+        let client = self.client;
         match *req.method() {
+            Method::GET if req.uri().path() == "/openapi.json" => {
+                // The description lists the enabled methods, so it's only served to clients that
+                // may call them.
+                if let Err(e) = check_authentication(Arc::clone(&handler), req.headers().get("Authorization")) {
+                    info!("Authentication failed: {}", e);
+                    return Box::new(future::ok(Response::builder()
+                        .status(StatusCode::UNAUTHORIZED)
+                        .body(Body::from(""))
+                        .unwrap()));
+                }
+                Box::new(future::ok(match handler.describe() {
+                    Some(description) => Response::builder()
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(json::stringify(description)))
+                        .unwrap(),
+                    None => Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::from(""))
+                        .unwrap(),
+                }))
+            },
             Method::GET => Box::new(future::ok(Response::new(Body::from("Nimiq JSON-RPC Server")))),
             Method::POST => {
                 if let Err(e) = check_authentication(Arc::clone(&handler), req.headers().get("Authorization")) {
@@ -200,9 +240,58 @@ impl<H> hyper::service::Service for Service<H> where H: Handler + 'static {
                         .unwrap()));
                 }
                 Box::new(req.into_body().concat2()
-                    .map(|b| handle_request(handler, std::str::from_utf8(&b))))
+                    .and_then(move |b| handle_request(handler, client, std::str::from_utf8(&b))))
             },
             _ => Box::new(future::ok(Response::new(Body::from(""))))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hyper::service::Service as _;
+
+    use super::*;
+
+    struct TestHandler;
+
+    impl Handler for TestHandler {
+        fn call_method(&self, _client: IpAddr, _name: &str, _params: Array) -> Option<CallFuture> {
+            None
+        }
+
+        fn describe(&self) -> Option<JsonValue> {
+            Some(object!{"openapi" => "3.0.3"})
+        }
+
+        fn authorize(&self, username: &str, password: &str) -> Result<(), AuthenticationError> {
+            if username == "user" && password == "secret" {
+                Ok(())
+            } else {
+                Err(AuthenticationError::IncorrectCredentials)
+            }
+        }
+    }
+
+    fn get_description(authorization: Option<&str>) -> Response<Body> {
+        let mut request = Request::builder();
+        request.method("GET").uri("/openapi.json");
+        if let Some(authorization) = authorization {
+            request.header("Authorization", authorization);
+        }
+        Service::new(Arc::new(TestHandler), IpAddr::from([127, 0, 0, 1])).call(request.body(Body::empty()).unwrap()).wait().unwrap()
+    }
+
+    #[test]
+    fn it_requires_authentication_for_the_description() {
+        assert_eq!(get_description(None).status(), StatusCode::UNAUTHORIZED);
+        let wrong = format!("Basic {}", base64::encode("user:wrong"));
+        assert_eq!(get_description(Some(&wrong)).status(), StatusCode::UNAUTHORIZED);
+
+        let correct = format!("Basic {}", base64::encode("user:secret"));
+        let response = get_description(Some(&correct));
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().concat2().wait().unwrap();
+        assert_eq!(json::parse(std::str::from_utf8(&body).unwrap()).unwrap(), object!{"openapi" => "3.0.3"});
+    }
+}
@@ -4,16 +4,20 @@ extern crate json;
 //extern crate lazy_static;
 #[macro_use]
 extern crate log;
+extern crate nimiq_account as account;
 extern crate nimiq_block as block;
 extern crate nimiq_block_albatross as block_albatross;
 extern crate nimiq_block_base as block_base;
 extern crate nimiq_block_production as block_production;
+extern crate nimiq_block_production_albatross as block_production_albatross;
 extern crate nimiq_blockchain_albatross as blockchain_albatross;
 extern crate nimiq_blockchain_base as blockchain_base;
 extern crate nimiq_bls as bls;
+extern crate nimiq_collections as collections;
 extern crate nimiq_consensus as consensus;
 extern crate nimiq_hash as hash;
 extern crate nimiq_keys as keys;
+extern crate nimiq_lib as lib;
 extern crate nimiq_network_primitives as network_primitives;
 extern crate nimiq_primitives as primitives;
 extern crate nimiq_transaction as transaction;
@@ -24,9 +28,16 @@ use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
 use futures::future::Future;
+use futures::stream::Stream;
 use hyper::Server;
+use hyper::server::conn::{AddrIncoming, AddrStream, Http};
+use hyper::service::make_service_fn;
 use json::JsonValue;
 
+use keys::SecretBytes;
+use utils::tls::{self, tls_acceptor};
+pub use utils::tls::TlsConfig;
+
 use crate::error::Error;
 pub use crate::handler::Handler;
 
@@ -34,6 +45,7 @@ pub mod jsonrpc;
 pub mod error;
 pub mod handler;
 pub mod handlers;
+pub mod openapi;
 
 fn rpc_not_implemented<T>() -> Result<T, JsonValue> {
     Err(object!{"message" => "Not implemented"})
@@ -46,30 +58,60 @@ pub struct JsonRpcConfig {
     pub methods: HashSet<String>,
     pub allowip: (),
     pub corsdomain: Vec<String>,
+    /// Maximum number of method calls per minute, for each client IP address.
+    pub rate_limit: Option<usize>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Credentials {
-    username: String,
-    password: String,
+    username: SecretBytes,
+    password: SecretBytes,
 }
 
 impl Credentials {
-    pub fn new(username: &str, password: &str) -> Credentials {
-        Credentials { username: String::from(username), password: String::from(password) }
+    pub fn new(username: &str, password: SecretBytes) -> Credentials {
+        Credentials { username: SecretBytes::from(username), password }
     }
 
+    /// Compares in constant time. Both are always compared, so the time doesn't tell which one
+    /// was wrong.
     pub fn check(&self, username: &str, password: &str) -> bool {
-        self.username == username && self.password == password
+        self.username.ct_eq(username.as_bytes()) & self.password.ct_eq(password.as_bytes())
     }
 }
 
 type OtherFuture = Box<dyn Future<Item=(), Error=()> + Send + Sync + 'static>;
 
-pub fn rpc_server(ip: IpAddr, port: u16, handler: Arc<Handler>) -> Result<OtherFuture, Error> {
-    Ok(Box::new(Server::try_bind(&SocketAddr::new(ip, port))?
-        .serve(move || {
-            jsonrpc::Service::new(Arc::clone(&handler))
-        })
-        .map_err(|e| error!("RPC server failed: {}", e)))) // as Box<dyn Future<Item=(), Error=()> + Send + Sync>
+pub fn rpc_server(ip: IpAddr, port: u16, handler: Arc<Handler>, tls: Option<TlsConfig>) -> Result<OtherFuture, Error> {
+    let addr = SocketAddr::new(ip, port);
+    let tls = match tls {
+        Some(tls) => tls,
+        None => {
+            return Ok(Box::new(Server::try_bind(&addr)?
+                .serve(make_service_fn(move |socket: &AddrStream| {
+                    jsonrpc::Service::new(Arc::clone(&handler), socket.remote_addr().ip())
+                }))
+                .map_err(|e| error!("RPC server failed: {}", e)))) // as Box<dyn Future<Item=(), Error=()> + Send + Sync>
+        },
+    };
+
+    let acceptor = Arc::new(tls_acceptor(&tls)?);
+    let http = Http::new();
+    // Like `Server`, the incoming connections back off on errors like EMFILE instead of failing.
+    Ok(Box::new(AddrIncoming::bind(&addr)?
+        .map_err(|e| error!("RPC server failed: {}", e))
+        .for_each(move |socket| {
+            let client = socket.remote_addr().ip();
+
+            // Handshake in a separate task, such that a slow client doesn't block the others.
+            let handler = Arc::clone(&handler);
+            let http = http.clone();
+            tokio::spawn(tls::accept(&acceptor, socket, tls::HANDSHAKE_TIMEOUT)
+                .map_err(|e| debug!("TLS handshake with RPC client failed: {}", e))
+                .and_then(move |stream| {
+                    http.serve_connection(stream, jsonrpc::Service::new(handler, client))
+                        .map_err(|e| debug!("RPC connection failed: {}", e))
+                }));
+            Ok(())
+        })))
 }
@@ -0,0 +1,832 @@
+use std::collections::HashMap;
+
+use json::JsonValue;
+
+/// Methods of all handler modules with the doc comments of their functions, extracted by
+/// `build.rs`:
+/// ```text
+/// {
+///     <module>: Array<{
+///         name: string,
+///         doc: string,
+///     }>,
+/// }
+/// ```
+const RPC_METHODS: &str = include_str!(concat!(env!("OUT_DIR"), "/rpc_methods.json"));
+
+/// Generates the OpenAPI description of the methods of `modules` for which `is_enabled` returns
+/// true.
+///
+/// JSON-RPC methods are all called with a `POST` to `/`, so the request body is described as one
+/// of the requests of the methods, discriminated by `method`, and the response as one of their
+/// responses. The schemas of the requests and responses are named `<method>Request` and
+/// `<method>Response`. The positional parameters are listed in the `x-params` extension of the
+/// `params` schema.
+pub fn describe<F: Fn(&str) -> bool>(modules: &[&str], is_enabled: F) -> JsonValue {
+    let rpc_methods = json::parse(RPC_METHODS).expect("Invalid RPC method description");
+
+    let mut schemas = object!{
+        "Error" => object!{
+            "type" => "object",
+            "properties" => object!{
+                "code" => object!{"type" => "integer"},
+                "message" => object!{"type" => "string"},
+                "data" => object!{},
+            },
+        },
+    };
+    let mut requests = Vec::new();
+    let mut responses = Vec::new();
+    let mut mapping = JsonValue::new_object();
+    for module in modules {
+        for method in rpc_methods[*module].members() {
+            let name = method["name"].as_str().unwrap_or_default();
+            if !is_enabled(name) {
+                continue;
+            }
+            let doc = MethodDoc::parse(method["doc"].as_str().unwrap_or_default());
+            let request = format!("{}Request", name);
+            let response = format!("{}Response", name);
+            schemas[request.as_str()] = doc.request_schema(module, name);
+            schemas[response.as_str()] = doc.response_schema();
+            mapping[name] = schema_ref(&request).into();
+            requests.push(object!{"$ref" => schema_ref(&request)});
+            responses.push(object!{"$ref" => schema_ref(&response)});
+        }
+    }
+
+    object!{
+        "openapi" => "3.0.3",
+        "info" => object!{
+            "title" => "Nimiq JSON-RPC",
+            "version" => env!("CARGO_PKG_VERSION"),
+        },
+        "paths" => object!{
+            "/" => object!{
+                "post" => object!{
+                    "operationId" => "call",
+                    "summary" => "Calls a JSON-RPC method",
+                    "requestBody" => object!{
+                        "required" => true,
+                        "content" => object!{
+                            "application/json" => object!{
+                                "schema" => object!{
+                                    "oneOf" => JsonValue::Array(requests),
+                                    "discriminator" => object!{
+                                        "propertyName" => "method",
+                                        "mapping" => mapping,
+                                    },
+                                },
+                            },
+                        },
+                    },
+                    "responses" => object!{
+                        "200" => object!{
+                            "description" => "The result or error of the call",
+                            "content" => object!{
+                                "application/json" => object!{
+                                    "schema" => object!{
+                                        "oneOf" => JsonValue::Array(responses),
+                                    },
+                                },
+                            },
+                        },
+                        "401" => object!{
+                            "description" => "The credentials are missing or incorrect",
+                        },
+                    },
+                },
+            },
+        },
+        "components" => object!{
+            "schemas" => schemas,
+        },
+    }
+}
+
+fn schema_ref(name: &str) -> String {
+    format!("#/components/schemas/{}", name)
+}
+
+/// A parameter as documented in the `Parameters:` list of a method, e.g.
+/// `- epoch (number, optional): Defaults to the last finalized epoch.`
+#[derive(Debug)]
+pub(crate) struct ParamDoc {
+    pub name: String,
+    pub schema: JsonValue,
+    pub optional: bool,
+}
+
+/// The documentation of a method, parsed from the doc comment of its function:
+///
+/// * The first paragraph describes the method.
+/// * A `Parameters:` line starts the list of parameters.
+/// * ```` ```text ```` blocks contain types in the notation of the handlers, like
+///   `{ field: number|null, // comment }` or `Array<string>`. A block belongs to the paragraph
+///   before it, which decides what it describes: `A <Name> is ...` defines a type used by other
+///   blocks, a paragraph mentioning an error describes the data of an error, one mentioning an
+///   object parameter describes that parameter. The first other block is the result.
+#[derive(Debug)]
+pub(crate) struct MethodDoc {
+    pub description: String,
+    pub params: Vec<ParamDoc>,
+    /// The paragraphs after the parameters, without those belonging to blocks that don't
+    /// describe the result.
+    pub result_description: String,
+    pub result: JsonValue,
+    pub errors: Vec<JsonValue>,
+    /// Blocks that couldn't be parsed.
+    pub invalid: Vec<String>,
+}
+
+enum Section {
+    Description,
+    Parameters,
+    Rest,
+}
+
+impl MethodDoc {
+    pub fn parse(doc: &str) -> MethodDoc {
+        let mut description = Vec::new();
+        let mut params = Vec::new();
+        let mut paragraphs: Vec<String> = Vec::new();
+        let mut paragraph: Vec<&str> = Vec::new();
+        // Blocks with the index of the paragraph before them.
+        let mut blocks: Vec<(Option<usize>, String)> = Vec::new();
+        // Paragraphs before this index already belong to a block or precede the parameters.
+        let mut unclaimed = 0;
+
+        let mut section = Section::Description;
+        let mut lines = doc.lines();
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") {
+                if !paragraph.is_empty() {
+                    paragraphs.push(paragraph.join(" "));
+                    paragraph.clear();
+                }
+                let context = match section {
+                    Section::Rest if paragraphs.len() > unclaimed => Some(paragraphs.len() - 1),
+                    _ => None,
+                };
+                unclaimed = paragraphs.len();
+                let code: Vec<&str> = lines.by_ref().take_while(|line| !line.trim().starts_with("```")).collect();
+                blocks.push((context, code.join("\n")));
+                section = Section::Rest;
+                continue;
+            }
+
+            match section {
+                Section::Description if trimmed == "Parameters:" => section = Section::Parameters,
+                Section::Description if trimmed.is_empty() => section = Section::Rest,
+                Section::Description => description.push(trimmed),
+                Section::Parameters if trimmed.starts_with("- ") => {
+                    params.push(Self::parse_param(&trimmed[2..]));
+                },
+                Section::Parameters if line.starts_with(char::is_whitespace) && !trimmed.is_empty() && !params.is_empty() => {
+                    let param: &mut ParamDoc = params.last_mut().unwrap();
+                    let description = format!("{} {}", param.schema["description"].as_str().unwrap_or_default(), trimmed);
+                    param.schema["description"] = description.trim().into();
+                },
+                Section::Parameters if trimmed.is_empty() => section = Section::Rest,
+                Section::Parameters => {
+                    // Not a parameter, e.g. a `TODO`.
+                    section = Section::Rest;
+                },
+                Section::Rest if trimmed == "Parameters:" => section = Section::Parameters,
+                Section::Rest if trimmed.is_empty() => {
+                    if !paragraph.is_empty() {
+                        paragraphs.push(paragraph.join(" "));
+                        paragraph.clear();
+                    }
+                },
+                Section::Rest => paragraph.push(trimmed),
+            }
+        }
+        if !paragraph.is_empty() {
+            paragraphs.push(paragraph.join(" "));
+        }
+
+        let mut invalid = Vec::new();
+
+        // Definitions first, they may be used by any other block.
+        let mut definitions = HashMap::new();
+        let mut is_definition = vec![false; blocks.len()];
+        for (i, (context, code)) in blocks.iter().enumerate() {
+            let context = match context {
+                Some(context) => &paragraphs[*context],
+                None => continue,
+            };
+            let words: Vec<&str> = context.split_whitespace().collect();
+            if words.len() >= 3 && (words[0] == "A" || words[0] == "An") && words[2] == "is" {
+                let schema = match parse_type(code, &definitions) {
+                    Ok(schema) => schema,
+                    Err(e) => {
+                        invalid.push(e);
+                        JsonValue::new_object()
+                    },
+                };
+                let schema = if context.contains(" array ") {
+                    object!{"type" => "array", "items" => schema}
+                } else {
+                    schema
+                };
+                definitions.insert(words[1].to_string(), schema);
+                is_definition[i] = true;
+            }
+        }
+
+        let mut result = None;
+        let mut errors = Vec::new();
+        let mut excluded_paragraphs = Vec::new();
+        for (i, (context, code)) in blocks.iter().enumerate() {
+            if is_definition[i] {
+                excluded_paragraphs.extend(context);
+                continue;
+            }
+            let mut schema = match parse_type(code, &definitions) {
+                Ok(schema) => schema,
+                Err(e) => {
+                    invalid.push(e);
+                    JsonValue::new_object()
+                },
+            };
+            let context_text = context.map(|context| paragraphs[context].to_lowercase()).unwrap_or_default();
+            let context_words: Vec<&str> = context_text.split(|c: char| !c.is_alphanumeric()).collect();
+            let param = params.iter_mut()
+                .find(|param| param.schema["type"] == "object" && context_words.contains(&param.name.to_lowercase().as_str()));
+
+            if context_words.contains(&"error") {
+                excluded_paragraphs.extend(context);
+                errors.push(schema);
+            }
+            else if let Some(param) = param {
+                excluded_paragraphs.extend(context);
+                if param.schema.has_key("description") {
+                    schema["description"] = param.schema["description"].take();
+                }
+                param.schema = schema;
+            }
+            else if result.is_none() {
+                result = Some(schema);
+            }
+        }
+
+        let result_description = paragraphs.iter()
+            .enumerate()
+            .filter(|(i, _)| !excluded_paragraphs.contains(i))
+            .map(|(_, paragraph)| paragraph.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n\n");
+
+        MethodDoc {
+            description: description.join(" "),
+            params,
+            result_description,
+            result: result.unwrap_or_else(JsonValue::new_object),
+            errors,
+            invalid,
+        }
+    }
+
+    /// Parses `name (type, optional): description`.
+    fn parse_param(line: &str) -> ParamDoc {
+        let name_end = line.find(|c: char| c.is_whitespace() || c == '(' || c == ':').unwrap_or_else(|| line.len());
+        let name = line[..name_end].to_string();
+        let mut rest = line[name_end..].trim_start();
+
+        let mut ty = "";
+        let mut optional = false;
+        while rest.starts_with('(') {
+            let end = rest.find(')').unwrap_or_else(|| rest.len() - 1);
+            for part in rest[1..end].split(',') {
+                let part = part.trim();
+                if part == "optional" {
+                    optional = true;
+                } else if ty.is_empty() {
+                    ty = part;
+                }
+            }
+            rest = rest[end + 1..].trim_start();
+        }
+
+        let mut schema = parse_type(ty, &HashMap::new()).unwrap_or_else(|_| object!{"x-type" => ty});
+        if rest.starts_with(':') {
+            let description = rest[1..].trim();
+            if !description.is_empty() {
+                schema["description"] = description.into();
+            }
+        }
+
+        ParamDoc {
+            name,
+            schema,
+            optional,
+        }
+    }
+
+    fn request_schema(&self, module: &str, name: &str) -> JsonValue {
+        let params: Vec<JsonValue> = self.params.iter()
+            .map(|param| object!{
+                "name" => param.name.as_str(),
+                "required" => !param.optional,
+                "schema" => param.schema.clone(),
+            })
+            .collect();
+        let required_params = self.params.iter()
+            .take_while(|param| !param.optional)
+            .count();
+
+        object!{
+            "type" => "object",
+            "description" => self.description.as_str(),
+            "x-module" => module,
+            "required" => array!["jsonrpc", "method"],
+            "properties" => object!{
+                "jsonrpc" => object!{"type" => "string", "enum" => array!["2.0"]},
+                "id" => object!{},
+                "method" => object!{"type" => "string", "enum" => array![name]},
+                "params" => object!{
+                    "type" => "array",
+                    "minItems" => required_params,
+                    "maxItems" => params.len(),
+                    "x-params" => JsonValue::Array(params),
+                },
+            },
+        }
+    }
+
+    fn response_schema(&self) -> JsonValue {
+        let mut result = self.result.clone();
+        if !self.result_description.is_empty() && !result.has_key("description") {
+            result["description"] = self.result_description.as_str().into();
+        }
+        let error = if self.errors.is_empty() {
+            object!{"$ref" => schema_ref("Error")}
+        } else {
+            let data: Vec<JsonValue> = self.errors.clone();
+            object!{
+                "allOf" => array![
+                    object!{"$ref" => schema_ref("Error")},
+                    object!{
+                        "properties" => object!{
+                            "data" => object!{"oneOf" => JsonValue::Array(data)},
+                        },
+                    },
+                ],
+            }
+        };
+
+        object!{
+            "type" => "object",
+            "required" => array!["jsonrpc"],
+            "properties" => object!{
+                "jsonrpc" => object!{"type" => "string", "enum" => array!["2.0"]},
+                "id" => object!{},
+                "result" => result,
+                "error" => error,
+            },
+        }
+    }
+}
+
+/// Parses a type in the notation of the handler docs to a JSON schema. Types named in
+/// `definitions` are replaced by their schemas, other unknown names are kept in `x-type`.
+pub(crate) fn parse_type(text: &str, definitions: &HashMap<String, JsonValue>) -> Result<JsonValue, String> {
+    let mut parser = TypeParser {
+        chars: text.chars().collect(),
+        pos: 0,
+        definitions,
+    };
+    parser.skip_whitespace();
+    let mut schema = parser.parse_union()?;
+    let notes = parser.notes();
+    if !notes.is_empty() {
+        schema["description"] = notes.into();
+    }
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(schema),
+        Some(c) => Err(parser.error(&format!("Unexpected `{}`", c))),
+    }
+}
+
+struct TypeParser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    definitions: &'a HashMap<String, JsonValue>,
+}
+
+impl<'a> TypeParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected `{}`", c)))
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        let line = self.chars[..self.pos].iter().filter(|c| **c == '\n').count() + 1;
+        format!("{} in line {} of:\n{}", message, line, self.chars.iter().collect::<String>())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map(char::is_whitespace).unwrap_or(false) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_inline_whitespace(&mut self) {
+        while self.peek().map(|c| c.is_whitespace() && c != '\n').unwrap_or(false) {
+            self.pos += 1;
+        }
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> String {
+        let start = self.pos;
+        while self.peek().map(&f).unwrap_or(false) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn identifier(&mut self) -> String {
+        self.take_while(|c| c.is_alphanumeric() || c == '_')
+    }
+
+    /// The rest of the line after `//`.
+    fn line_comment(&mut self) -> String {
+        self.pos += 2;
+        self.take_while(|c| c != '\n').trim().to_string()
+    }
+
+    /// Comments and parenthesized notes following a type on the same line, including the comma
+    /// separating it from the next field.
+    fn notes(&mut self) -> String {
+        let mut notes = Vec::new();
+        loop {
+            self.skip_inline_whitespace();
+            if self.eat(',') {
+                continue;
+            }
+            if self.starts_with("//") {
+                notes.push(self.line_comment());
+            } else if self.eat('(') {
+                let mut depth = 1;
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if c == '(' {
+                        depth += 1;
+                    } else if c == ')' {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    self.pos += 1;
+                }
+                notes.push(self.chars[start..self.pos].iter().collect::<String>().trim().to_string());
+                self.eat(')');
+            } else {
+                return notes.join(", ");
+            }
+        }
+    }
+
+    /// `type | type | ...`, where a `null` member makes the type nullable.
+    fn parse_union(&mut self) -> Result<JsonValue, String> {
+        let mut members = vec![self.parse_primary()?];
+        loop {
+            let pos = self.pos;
+            self.skip_whitespace();
+            if self.eat('|') {
+                self.skip_whitespace();
+                members.push(self.parse_primary()?);
+            } else {
+                self.pos = pos;
+                break;
+            }
+        }
+        if members.len() == 1 {
+            return Ok(members.pop().unwrap());
+        }
+
+        let nullable = members.iter().any(is_null);
+        members.retain(|member| !is_null(member));
+        let mut schema = if members.is_empty() {
+            return Ok(null_schema());
+        } else if members.len() == 1 {
+            members.pop().unwrap()
+        } else if members.iter().all(|member| member["type"] == "string" && member.has_key("enum")) {
+            let values: Vec<JsonValue> = members.iter_mut()
+                .flat_map(|member| member["enum"].take().members().cloned().collect::<Vec<JsonValue>>())
+                .collect();
+            object!{"type" => "string", "enum" => JsonValue::Array(values)}
+        } else {
+            object!{"oneOf" => JsonValue::Array(members)}
+        };
+        if nullable {
+            schema["nullable"] = true.into();
+        }
+        Ok(schema)
+    }
+
+    fn parse_primary(&mut self) -> Result<JsonValue, String> {
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('"') => {
+                self.pos += 1;
+                let value = self.take_while(|c| c != '"');
+                self.expect('"')?;
+                Ok(object!{"type" => "string", "enum" => array![value]})
+            },
+            Some(c) if c.is_ascii_digit() || c == '-' => {
+                let literal = self.take_while(|c| c.is_ascii_digit() || c == '-' || c == '.');
+                let value: f64 = literal.parse().map_err(|_| self.error("Invalid number"))?;
+                Ok(object!{"type" => "number", "example" => value})
+            },
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.identifier();
+                if name == "Array" && self.eat('<') {
+                    self.skip_whitespace();
+                    let items = self.parse_union()?;
+                    self.skip_whitespace();
+                    self.expect('>')?;
+                    return Ok(object!{"type" => "array", "items" => items});
+                }
+                Ok(match name.as_str() {
+                    "string" => object!{"type" => "string"},
+                    "number" => object!{"type" => "number"},
+                    "integer" => object!{"type" => "integer"},
+                    "bool" | "boolean" => object!{"type" => "boolean"},
+                    "object" => object!{"type" => "object"},
+                    "array" => object!{"type" => "array", "items" => object!{}},
+                    "null" => null_schema(),
+                    "any" => object!{},
+                    name => match self.definitions.get(name) {
+                        Some(schema) => schema.clone(),
+                        None => object!{"x-type" => name},
+                    },
+                })
+            },
+            Some(c) => Err(self.error(&format!("Unexpected `{}`", c))),
+            None => Err(self.error("Unexpected end")),
+        }
+    }
+
+    /// `{ name: type, optional?: type, <key>: type }`, where `<key>` describes the values of
+    /// arbitrary keys.
+    fn parse_object(&mut self) -> Result<JsonValue, String> {
+        self.expect('{')?;
+        let mut properties = JsonValue::new_object();
+        let mut required = Vec::new();
+        let mut additional_properties = None;
+        loop {
+            self.skip_whitespace();
+            if self.starts_with("//") {
+                self.line_comment();
+                continue;
+            }
+            if self.eat('}') {
+                break;
+            }
+
+            let (name, placeholder) = if self.eat('<') {
+                let name = self.take_while(|c| c != '>');
+                self.expect('>')?;
+                (name, true)
+            } else if self.eat('"') {
+                let name = self.take_while(|c| c != '"');
+                self.expect('"')?;
+                (name, false)
+            } else {
+                (self.identifier(), false)
+            };
+            if name.is_empty() {
+                return Err(match self.peek() {
+                    Some(c) => self.error(&format!("Unexpected `{}`", c)),
+                    None => self.error("Unexpected end"),
+                });
+            }
+            let optional = self.eat('?');
+            self.skip_inline_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+
+            let mut schema = self.parse_union()?;
+            let notes = self.notes();
+            if placeholder {
+                let description = if notes.is_empty() { name } else { format!("{}: {}", name, notes) };
+                schema["description"] = description.into();
+                additional_properties = Some(schema);
+            } else {
+                if !notes.is_empty() {
+                    schema["description"] = notes.into();
+                }
+                if !optional {
+                    required.push(JsonValue::from(name.as_str()));
+                }
+                properties[name.as_str()] = schema;
+            }
+        }
+
+        let mut schema = object!{"type" => "object"};
+        if !properties.is_empty() {
+            schema["properties"] = properties;
+        }
+        if !required.is_empty() {
+            schema["required"] = JsonValue::Array(required);
+        }
+        if let Some(additional_properties) = additional_properties {
+            schema["additionalProperties"] = additional_properties;
+        }
+        Ok(schema)
+    }
+}
+
+fn null_schema() -> JsonValue {
+    object!{"nullable" => true, "enum" => array![json::Null]}
+}
+
+fn is_null(schema: &JsonValue) -> bool {
+    schema["enum"].len() == 1 && schema["enum"][0].is_null()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> JsonValue {
+        parse_type(text, &HashMap::new()).unwrap()
+    }
+
+    #[test]
+    fn it_parses_simple_types() {
+        assert_eq!(parse("string"), object!{"type" => "string"});
+        assert_eq!(parse("bool"), object!{"type" => "boolean"});
+        assert_eq!(parse("Array<number>"), object!{"type" => "array", "items" => object!{"type" => "number"}});
+        assert_eq!(parse("number|null"), object!{"type" => "number", "nullable" => true});
+        assert_eq!(parse("\"a\" | \"b\""), object!{"type" => "string", "enum" => array!["a", "b"]});
+        assert_eq!(parse("1200000")["example"].as_f64(), Some(1_200_000.0));
+        assert_eq!(parse("transaction_objects"), object!{"x-type" => "transaction_objects"});
+    }
+
+    #[test]
+    fn it_parses_objects() {
+        let schema = parse("{
+    number: number,
+    minerAddress: string, (user friendly address)
+    transactions: Array<transaction_objects> | Array<string>, (depends on includeTransactions),
+    slashedSet: {
+        epoch: number,
+        slots: Array<number>,
+    } | null, // If the epoch is too old to still be tracked
+    rejected: Array<{ address: string, value: number }>,
+    accounts: Array<{ // Sender and recipient
+        address: string,
+    }>,
+    limit?: number,
+
+    // Not applicable:
+    blockHash: null,
+    reason: \"expiring\",
+    <module>: Array<string>
+}");
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["required"].len(), 8);
+        assert!(!schema["required"].members().any(|name| name == "limit"));
+
+        let properties = &schema["properties"];
+        assert_eq!(properties["number"], object!{"type" => "number"});
+        assert_eq!(properties["minerAddress"], object!{"type" => "string", "description" => "user friendly address"});
+        assert_eq!(properties["transactions"]["oneOf"].len(), 2);
+        assert_eq!(properties["transactions"]["oneOf"][1]["items"], object!{"type" => "string"});
+        assert_eq!(properties["transactions"]["description"], "depends on includeTransactions");
+        assert_eq!(properties["slashedSet"]["nullable"], true);
+        assert_eq!(properties["slashedSet"]["description"], "If the epoch is too old to still be tracked");
+        assert_eq!(properties["slashedSet"]["properties"]["slots"]["items"]["type"], "number");
+        assert_eq!(properties["rejected"]["items"]["properties"]["value"]["type"], "number");
+        assert_eq!(properties["accounts"]["items"]["properties"]["address"]["type"], "string");
+        assert!(is_null(&properties["blockHash"]));
+        assert_eq!(properties["reason"]["enum"], array!["expiring"]);
+        assert_eq!(schema["additionalProperties"]["items"]["type"], "string");
+        assert_eq!(schema["additionalProperties"]["description"], "module");
+    }
+
+    #[test]
+    fn it_rejects_malformed_types() {
+        assert!(parse_type("{ a: number", &HashMap::new()).is_err());
+        assert!(parse_type("Array<number", &HashMap::new()).is_err());
+        assert!(parse_type("{ a number }", &HashMap::new()).is_err());
+        assert!(parse_type("number number", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn it_parses_method_docs() {
+        let doc = MethodDoc::parse("Returns the state of the slots.
+Parameters:
+- epoch (number, optional): Defaults to the last
+  finalized epoch.
+- transaction (object)
+- hash (string)
+
+The transaction looks like the following:
+```text
+{
+    value: number,
+}
+```
+
+The state object contains:
+```text
+{
+    currentSlots: SlotList,
+}
+```
+
+A SlotList is an array with objects looking like:
+```text
+{
+    index: number,
+}
+```
+
+If the transaction is rejected, the error contains:
+```text
+{
+    minFee: number,
+}
+```");
+        assert_eq!(doc.description, "Returns the state of the slots.");
+        assert!(doc.invalid.is_empty());
+
+        assert_eq!(doc.params.len(), 3);
+        assert_eq!(doc.params[0].name, "epoch");
+        assert!(doc.params[0].optional);
+        assert_eq!(doc.params[0].schema, object!{"type" => "number", "description" => "Defaults to the last finalized epoch."});
+        assert_eq!(doc.params[1].schema["properties"]["value"]["type"], "number");
+        assert!(!doc.params[2].optional);
+        assert_eq!(doc.params[2].schema, object!{"type" => "string"});
+
+        assert_eq!(doc.result_description, "The state object contains:");
+        assert_eq!(doc.result["properties"]["currentSlots"]["type"], "array");
+        assert_eq!(doc.result["properties"]["currentSlots"]["items"]["properties"]["index"]["type"], "number");
+        assert_eq!(doc.errors.len(), 1);
+        assert_eq!(doc.errors[0]["properties"]["minFee"]["type"], "number");
+    }
+
+    #[test]
+    fn all_handler_docs_parse() {
+        let rpc_methods = json::parse(RPC_METHODS).unwrap();
+        for (module, methods) in rpc_methods.entries() {
+            for method in methods.members() {
+                let doc = MethodDoc::parse(method["doc"].as_str().unwrap());
+                assert!(doc.invalid.is_empty(), "{}.{}: {:?}", module, method["name"], doc.invalid);
+            }
+        }
+    }
+
+    #[test]
+    fn it_describes_methods_as_calls_to_the_root_path() {
+        let description = describe(&["blockchain_albatross"], |name| name != "getBlockByHash");
+
+        assert_eq!(description["paths"].len(), 1);
+        let operation = &description["paths"]["/"]["post"];
+        let requests = &operation["requestBody"]["content"]["application/json"]["schema"];
+        assert!(requests["oneOf"].len() > 1);
+        assert_eq!(requests["discriminator"]["mapping"]["getHtlcInfo"], "#/components/schemas/getHtlcInfoRequest");
+        assert!(requests["discriminator"]["mapping"]["getBlockByHash"].is_null());
+        assert_eq!(operation["responses"]["200"]["content"]["application/json"]["schema"]["oneOf"].len(), requests["oneOf"].len());
+
+        let schemas = &description["components"]["schemas"];
+        let request = &schemas["getBlockByNumberRequest"];
+        assert_eq!(request["properties"]["method"]["enum"], array!["getBlockByNumber"]);
+        assert_eq!(request["properties"]["params"]["minItems"], 1);
+        assert_eq!(request["properties"]["params"]["maxItems"], 2);
+
+        let result = &schemas["getBlockByNumberResponse"]["properties"]["result"];
+        assert_eq!(result["type"], "object");
+        assert_eq!(result["properties"]["number"]["type"], "number");
+        let result = &schemas["getHtlcInfoResponse"]["properties"]["result"];
+        assert_eq!(result["properties"]["settlements"]["type"], "array");
+        assert!(schemas["getBlockByHashRequest"].is_null());
+    }
+}
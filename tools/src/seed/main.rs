@@ -0,0 +1,281 @@
+#[macro_use]
+extern crate clap;
+#[macro_use]
+extern crate log;
+extern crate failure;
+extern crate futures;
+extern crate tokio;
+extern crate beserial;
+extern crate nimiq_network as network;
+extern crate nimiq_network_primitives as network_primitives;
+extern crate nimiq_utils as utils;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::exit;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{App, Arg};
+use failure::{err_msg, Error};
+use futures::{future, Future, Stream};
+use tokio::timer::Interval;
+
+use beserial::{DeserializeWithLength, SerializeWithLength};
+use network::{GenesisHead, Network, NetworkConfig};
+use network::network_config::{NodeRole, PeerCountTargets};
+use network_primitives::address::PeerAddress;
+use network_primitives::networks::NetworkId;
+use network_primitives::services::{Services, ServiceFlags};
+use network_primitives::time::NetworkTime;
+use utils::key_store::KeyStore;
+
+
+/// Interval in which the address book is written to the peers file.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+fn run_app() -> Result<(), Error> {
+    let matches = App::new("Seed node")
+        .version(crate_version!())
+        .author(crate_authors!())
+        .about("Runs a seed node that only relays peer addresses.")
+        .arg(Arg::with_name("host")
+            .short("H")
+            .long("host")
+            .value_name("HOSTNAME")
+            .help("Hostname under which the seed node is reachable.")
+            .takes_value(true)
+            .required(true))
+        .arg(Arg::with_name("port")
+            .short("p")
+            .long("port")
+            .value_name("PORT")
+            .help("Port to listen on.")
+            .takes_value(true)
+            .default_value("8443"))
+        .arg(Arg::with_name("identity_file")
+            .long("identity-file")
+            .value_name("FILE")
+            .help("Use the PKCS#12 identity in FILE and accept secure WebSocket connections.")
+            .takes_value(true)
+            .requires("identity_password_file"))
+        .arg(Arg::with_name("identity_password_file")
+            .long("identity-password-file")
+            .value_name("FILE")
+            .help("Read the password of the identity file from the first line of FILE.")
+            .takes_value(true))
+        .arg(Arg::with_name("network")
+            .short("N")
+            .long("network")
+            .value_name("NETWORK")
+            .help("Specify the network to join.")
+            .takes_value(true)
+            .default_value("main"))
+        .arg(Arg::with_name("max_peers")
+            .short("m")
+            .long("max-peers")
+            .value_name("COUNT")
+            .help("Maximum number of connected peers.")
+            .takes_value(true)
+            .default_value("20000"))
+        .arg(Arg::with_name("peer_key_file")
+            .long("peer-key-file")
+            .value_name("FILE")
+            .help("Read the peer key from FILE, or create it there.")
+            .takes_value(true)
+            .default_value("peer_key.dat"))
+        .arg(Arg::with_name("peers_file")
+            .long("peers-file")
+            .value_name("FILE")
+            .help("Persist the known peer addresses in FILE.")
+            .takes_value(true)
+            .default_value("peers.dat"))
+        .get_matches();
+
+    let host = matches.value_of("host").unwrap().to_string();
+    let port = u16::from_str(matches.value_of("port").unwrap())?;
+    let network_id = NetworkId::from_str(matches.value_of("network").unwrap())?;
+    let max_peers = parse_max_peers(matches.value_of("max_peers").unwrap())?;
+
+    let mut network_config = match matches.value_of("identity_file") {
+        Some(identity_file) => {
+            let identity_password = read_password(matches.value_of("identity_password_file").unwrap())?;
+            NetworkConfig::new_wss_network_config(host, port, false, identity_file.to_string(), identity_password)
+        },
+        None => NetworkConfig::new_ws_network_config(host, port, false, None),
+    };
+    // We don't provide any services, such that peers don't try to sync from us, but we accept
+    // everyone.
+    network_config.set_services(Services::new(ServiceFlags::NONE, ServiceFlags::NANO | ServiceFlags::LIGHT | ServiceFlags::FULL | ServiceFlags::VALIDATOR));
+    network_config.set_peer_count_max(max_peers);
+    // Keep a few outbound connections to learn about new addresses, but never recycle inbound ones.
+    network_config.set_peer_count_targets(NodeRole::FullNode, PeerCountTargets {
+        min_outbound: 4,
+        min_full_ws_outbound: 1,
+        recycling_threshold: max_peers,
+        prefer_validators: false,
+    });
+    network_config.init_persistent(&KeyStore::new(matches.value_of("peer_key_file").unwrap().to_string()))?;
+
+    let peers_file = matches.value_of("peers_file").unwrap().to_string();
+    run(network_id, network_config, peers_file)
+}
+
+fn run(network_id: NetworkId, network_config: NetworkConfig, peers_file: String) -> Result<(), Error> {
+    // The network only needs the chain head for the handshake, so we stay at the genesis block.
+    let network = Network::new(GenesisHead::new(network_id), network_config, Arc::new(NetworkTime::new()), network_id)?;
+
+    let addresses = load_peers(&peers_file)?;
+    if !addresses.is_empty() {
+        info!("Restored {} peer addresses from {}", addresses.len(), peers_file);
+        network.addresses.add(None, addresses);
+    }
+
+    tokio::run(future::lazy(move || {
+        if let Err(e) = network.initialize().and_then(|_| network.connect()) {
+            error!("Failed to start the network: {}", e);
+            return future::Either::A(future::ok(()));
+        }
+        network.set_allow_inbound_connections(true);
+
+        future::Either::B(Interval::new_interval(SAVE_INTERVAL)
+            .map_err(|e| error!("Address book timer failed: {}", e))
+            .for_each(move |_| {
+                let addresses = network.addresses.export();
+                let count = addresses.len();
+                match save_peers(&peers_file, addresses) {
+                    Ok(()) => debug!("Saved {} peer addresses to {}", count, peers_file),
+                    Err(e) => warn!("Failed to save peer addresses to {}: {}", peers_file, e),
+                }
+                Ok(())
+            }))
+    }));
+
+    Ok(())
+}
+
+fn parse_max_peers(value: &str) -> Result<usize, Error> {
+    let max_peers = usize::from_str(value)?;
+    if max_peers == 0 {
+        return Err(err_msg("The maximum number of peers must be at least 1"));
+    }
+    Ok(max_peers)
+}
+
+/// Reads the password from the first line of `path`, such that it doesn't show up in the process
+/// list.
+fn read_password<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    let contents = fs::read_to_string(path)?;
+    Ok(contents.lines().next().unwrap_or_default().to_string())
+}
+
+/// Reads the peer addresses saved by `save_peers`. A missing or corrupted file, e.g. if we crashed
+/// while writing it, is treated as empty.
+fn load_peers<P: AsRef<Path>>(path: P) -> Result<Vec<PeerAddress>, io::Error> {
+    let path = path.as_ref();
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    match DeserializeWithLength::deserialize::<u32, _>(&mut &bytes[..]) {
+        Ok(addresses) => Ok(addresses),
+        Err(e) => {
+            warn!("Ignoring corrupted peers file {}: {}", path.display(), e);
+            Ok(Vec::new())
+        },
+    }
+}
+
+/// Writes the peer addresses to a temporary file first and moves it over `path`, such that `path`
+/// is never left partially written.
+fn save_peers<P: AsRef<Path>>(path: P, addresses: Vec<PeerAddress>) -> Result<(), io::Error> {
+    let path = path.as_ref();
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(&SerializeWithLength::serialize_to_vec::<u32>(&addresses))?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+fn main() {
+    simple_logger::init_with_level(log::Level::Info).expect("Failed to initialize logging");
+
+    if let Err(e) = run_app() {
+        eprintln!("Error: {}", e);
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nimiq_keys::KeyPair;
+    use tempdir::TempDir;
+
+    use network_primitives::address::{NetAddress, PeerAddressType, PeerId};
+
+    use super::*;
+
+    fn peer_address(host: &str) -> PeerAddress {
+        let key_pair = KeyPair::generate();
+        let mut peer_address = PeerAddress {
+            ty: PeerAddressType::Wss(host.to_string(), 8443),
+            services: ServiceFlags::FULL,
+            timestamp: 1,
+            net_address: NetAddress::Unspecified,
+            public_key: key_pair.public.clone(),
+            distance: 0,
+            signature: None,
+            peer_id: PeerId::from(&key_pair.public),
+        };
+        peer_address.signature = Some(key_pair.sign(&peer_address.get_signature_data()));
+        peer_address
+    }
+
+    #[test]
+    fn it_restores_saved_peers() {
+        let dir = TempDir::new("seed").unwrap();
+        let path = dir.path().join("peers.dat");
+        let addresses = vec![peer_address("a.example.com"), peer_address("b.example.com")];
+
+        save_peers(&path, addresses.clone()).unwrap();
+        assert_eq!(load_peers(&path).unwrap(), addresses);
+        // Nothing is left behind but the peers file.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn it_starts_without_peers_file() {
+        let dir = TempDir::new("seed").unwrap();
+        assert!(load_peers(dir.path().join("peers.dat")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_ignores_truncated_peers_file() {
+        let dir = TempDir::new("seed").unwrap();
+        let path = dir.path().join("peers.dat");
+        save_peers(&path, vec![peer_address("a.example.com")]).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(load_peers(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_reads_the_password_from_the_first_line() {
+        let dir = TempDir::new("seed").unwrap();
+        let path = dir.path().join("password");
+        fs::write(&path, "secret\n").unwrap();
+        assert_eq!(read_password(&path).unwrap(), "secret");
+    }
+
+    #[test]
+    fn it_rejects_zero_max_peers() {
+        assert_eq!(parse_max_peers("20000").unwrap(), 20000);
+        assert!(parse_max_peers("0").is_err());
+    }
+}
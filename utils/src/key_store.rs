@@ -1,6 +1,7 @@
 use std::fs;
-use std::io::Error as IoError;
+use std::io::{Error as IoError, ErrorKind};
 
+use clear_on_drop::clear::Clear;
 use failure::Fail;
 
 use beserial::{Deserialize, Serialize};
@@ -18,15 +19,29 @@ impl KeyStore {
 
     pub fn load_key<T: Serialize + Deserialize>(&self) -> Result<T, Error> {
         match fs::read(&self.path) {
-            Ok(data) => {
-                Deserialize::deserialize_from_vec(&data).map_err(|_| Error::InvalidKey)
+            Ok(mut data) => {
+                let key = Deserialize::deserialize_from_vec(&data).map_err(|_| Error::InvalidKey);
+                // Don't leave a copy of the private key in memory.
+                data.as_mut_slice().clear();
+                key
             },
             Err(e) => Err(Error::IoError(e)),
         }
     }
 
     pub fn save_key<T: Serialize + Deserialize>(&self, key_pair: &T) -> Result<(), Error> {
-        Ok(fs::write(&self.path, key_pair.serialize_to_vec())?)
+        let mut data = key_pair.serialize_to_vec();
+        let result = fs::write(&self.path, &data);
+        data.as_mut_slice().clear();
+        Ok(result?)
+    }
+
+    /// Removes the key file. It's not an error if it doesn't exist.
+    pub fn remove_key(&self) -> Result<(), Error> {
+        match fs::remove_file(&self.path) {
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            result => Ok(result?),
+        }
     }
 }
 
@@ -5,6 +5,10 @@
 #[macro_use]
 extern crate log;
 
+#[cfg(feature = "lazy_static")]
+#[macro_use]
+extern crate lazy_static;
+
 #[cfg(feature = "beserial_derive")]
 #[macro_use]
 extern crate beserial_derive;
@@ -41,3 +45,7 @@ pub mod unique_id;
 pub mod otp;
 #[cfg(feature = "log2")]
 pub mod log2;
+#[cfg(feature = "memory")]
+pub mod memory;
+#[cfg(feature = "tls")]
+pub mod tls;
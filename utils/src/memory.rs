@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Components whose memory usage is accounted for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MemoryCategory {
+    /// Transactions in the mempool.
+    Mempool,
+    /// Messages queued for sending to peers.
+    PeerQueues,
+    /// Caches, e.g. of accounts tree chunks.
+    Caches,
+}
+
+impl MemoryCategory {
+    pub const ALL: [MemoryCategory; 3] = [
+        MemoryCategory::Mempool,
+        MemoryCategory::PeerQueues,
+        MemoryCategory::Caches,
+    ];
+
+    fn index(self) -> usize {
+        match self {
+            MemoryCategory::Mempool => 0,
+            MemoryCategory::PeerQueues => 1,
+            MemoryCategory::Caches => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemoryPressure {
+    /// Usage is below the high watermark.
+    Normal,
+    /// Usage is above the high watermark, load should be shed.
+    High,
+    /// Usage is close to the budget, everything that isn't needed should be dropped.
+    Critical,
+}
+
+/// Tracks the memory used by the node's components against a budget.
+///
+/// The accountant only keeps the numbers. Components report their usage, and the owner of the
+/// accountant decides how to shed load when the `pressure` rises.
+#[derive(Debug)]
+pub struct MemoryAccountant {
+    budget: Option<usize>,
+    usage: [AtomicUsize; 3],
+}
+
+impl MemoryAccountant {
+    /// Fraction of the budget above which the pressure is `High`.
+    pub const HIGH_WATERMARK: f64 = 0.8;
+    /// Fraction of the budget above which the pressure is `Critical`.
+    pub const CRITICAL_WATERMARK: f64 = 0.95;
+
+    /// Creates an accountant. Without a budget, the pressure is always `Normal`.
+    pub fn new(budget: Option<usize>) -> Self {
+        MemoryAccountant {
+            budget,
+            usage: [AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0)],
+        }
+    }
+
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    /// Sets the usage of a category, for components that measure their total size.
+    pub fn set(&self, category: MemoryCategory, bytes: usize) {
+        self.usage[category.index()].store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add(&self, category: MemoryCategory, bytes: usize) {
+        self.usage[category.index()].fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn sub(&self, category: MemoryCategory, bytes: usize) {
+        self.usage[category.index()].fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    pub fn usage(&self, category: MemoryCategory) -> usize {
+        self.usage[category.index()].load(Ordering::Relaxed)
+    }
+
+    /// Total usage of all categories.
+    pub fn total(&self) -> usize {
+        MemoryCategory::ALL.iter().map(|&category| self.usage(category)).sum()
+    }
+
+    pub fn pressure(&self) -> MemoryPressure {
+        let budget = match self.budget {
+            Some(budget) => budget as f64,
+            None => return MemoryPressure::Normal,
+        };
+        let total = self.total() as f64;
+        if total >= budget * Self::CRITICAL_WATERMARK {
+            MemoryPressure::Critical
+        } else if total >= budget * Self::HIGH_WATERMARK {
+            MemoryPressure::High
+        } else {
+            MemoryPressure::Normal
+        }
+    }
+
+    /// Number of bytes that need to be freed to get below the high watermark again.
+    pub fn excess(&self) -> usize {
+        match self.budget {
+            Some(budget) => self.total().saturating_sub((budget as f64 * Self::HIGH_WATERMARK) as usize),
+            None => 0,
+        }
+    }
+}
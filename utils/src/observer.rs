@@ -1,4 +1,8 @@
-use std::sync::{Weak, Arc};
+use std::marker::PhantomData;
+use std::sync::{Weak, Arc, Mutex};
+
+use futures::{Poll, Stream};
+use futures::sync::mpsc;
 
 pub trait Listener<E>: Send + Sync {
     fn on_event(&self, event: &E);
@@ -13,12 +17,66 @@ impl<E, F: Fn(&E)> Listener<E> for F
 
 pub type ListenerHandle = usize;
 
-#[derive(Default)]
+/// Keeps a listener registered with a notifier for events of type `E`. The subscription owns the
+/// listener, so the listener is dropped and deregistered together with the subscription.
+///
+/// Dropping a subscription doesn't lock the notifier, the notifier only keeps a weak reference to
+/// the listener and skips it from then on. So it's safe to drop a subscription while the
+/// notifier is locked, e.g. if its owner is dropped by one of its own listeners.
+#[must_use = "The listener is deregistered when the subscription is dropped"]
+pub struct Subscription<'l, E> {
+    _listener: Arc<dyn Send + Sync + 'l>,
+    _event: PhantomData<fn(E)>,
+}
+
+impl<'l, E> Subscription<'l, E> {
+    fn new<T: Send + Sync + 'l>(listener: T) -> (Self, Arc<T>) {
+        let listener = Arc::new(listener);
+        (Subscription { _listener: Arc::clone(&listener) as Arc<dyn Send + Sync + 'l>, _event: PhantomData }, listener)
+    }
+}
+
+enum Registered<L: ?Sized> {
+    /// Listeners that stay registered until they are deregistered.
+    Owned(Box<L>),
+    /// Listeners that are owned by a subscription.
+    Subscribed(Weak<L>),
+}
+
+impl<L: ?Sized> Registered<L> {
+    fn is_alive(&self) -> bool {
+        match self {
+            Registered::Owned(_) => true,
+            Registered::Subscribed(listener) => listener.upgrade().is_some(),
+        }
+    }
+
+    fn with<F: FnOnce(&L)>(&self, f: F) {
+        match self {
+            Registered::Owned(listener) => f(listener),
+            Registered::Subscribed(listener) => if let Some(listener) = listener.upgrade() {
+                f(&listener)
+            },
+        }
+    }
+}
+
+struct Registration<'l, E> {
+    handle: ListenerHandle,
+    listener: Registered<dyn Listener<E> + 'l>,
+}
+
 pub struct Notifier<'l, E> {
-    listeners: Vec<(ListenerHandle, Box<dyn Listener<E> + 'l>)>,
+    listeners: Vec<Registration<'l, E>>,
     next_handle: ListenerHandle
 }
 
+impl<'l, E> Default for Notifier<'l, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'l, E> Notifier<'l, E> {
     pub fn new() -> Self {
         Self {
@@ -28,25 +86,78 @@ impl<'l, E> Notifier<'l, E> {
     }
 
     pub fn register<T: Listener<E> + 'l>(&mut self, listener: T) -> ListenerHandle {
+        self.add(Registered::Owned(Box::new(listener)))
+    }
+
+    /// Registers `listener` as long as the returned subscription is kept.
+    pub fn subscribe<T: Listener<E> + 'l>(&mut self, listener: T) -> Subscription<'l, E> {
+        let (subscription, listener) = Subscription::new(listener);
+        let listener: Weak<dyn Listener<E> + 'l> = Arc::downgrade(&listener);
+        self.add(Registered::Subscribed(listener));
+        subscription
+    }
+
+    pub fn deregister(&mut self, handle: ListenerHandle) {
+        self.listeners.retain(|registration| registration.handle != handle && registration.listener.is_alive());
+    }
+
+    /// Number of registered listeners, without those whose subscription was dropped.
+    pub fn len(&self) -> usize {
+        self.listeners.iter().filter(|registration| registration.listener.is_alive()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn notify(&self, event: E) {
+        for registration in &self.listeners {
+            registration.listener.with(|listener| listener.on_event(&event));
+        }
+    }
+
+    fn add(&mut self, listener: Registered<dyn Listener<E> + 'l>) -> ListenerHandle {
+        self.listeners.retain(|registration| registration.listener.is_alive());
         let handle = self.next_handle;
-        self.listeners.push((handle, Box::new(listener)));
+        self.listeners.push(Registration {
+            handle,
+            listener,
+        });
         self.next_handle += 1;
         handle
     }
+}
 
-    pub fn deregister(&mut self, handle: ListenerHandle) {
-        for (i, (stored_handle, _)) in self.listeners.iter().enumerate() {
-            if handle == *stored_handle {
-                self.listeners.remove(i);
-                return;
-            }
+impl<'l, E: Clone + Send + 'l> Notifier<'l, E> {
+    /// Subscribes a stream of the events, so that they can be handled asynchronously instead of
+    /// on the thread that notifies. The stream buffers at most `capacity` events; while it is
+    /// full, further events are dropped. Dropping the stream deregisters it.
+    pub fn subscribe_stream(&mut self, capacity: usize) -> EventStream<'l, E> {
+        let (sender, receiver) = mpsc::channel(capacity);
+        let sender = Mutex::new(sender);
+        let subscription = self.subscribe(move |event: &E| {
+            // The stream is either full or gone.
+            sender.lock().unwrap().try_send(event.clone()).ok();
+        });
+        EventStream {
+            receiver,
+            _subscription: subscription,
         }
     }
+}
 
-    pub fn notify(&self, event: E) {
-        for (_, listener) in &self.listeners {
-            listener.on_event(&event);
-        }
+/// A stream of events of a notifier, see `Notifier::subscribe_stream`.
+pub struct EventStream<'l, E> {
+    receiver: mpsc::Receiver<E>,
+    _subscription: Subscription<'l, E>,
+}
+
+impl<E> Stream for EventStream<'_, E> {
+    type Item = E;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<E>, ()> {
+        self.receiver.poll()
     }
 }
 
@@ -81,7 +192,7 @@ impl<E, F: Fn(E)> PassThroughListener<E> for F
 }
 
 pub struct PassThroughNotifier<'l, E> {
-    listener: Option<Box<dyn PassThroughListener<E> + 'l>>,
+    listener: Option<Registered<dyn PassThroughListener<E> + 'l>>,
 }
 
 impl<E> Default for PassThroughNotifier<'_, E> {
@@ -98,7 +209,16 @@ impl<'l, E> PassThroughNotifier<'l, E> {
     }
 
     pub fn register<T: PassThroughListener<E> + 'l>(&mut self, listener: T) {
-        self.listener = Some(Box::new(listener));
+        self.listener = Some(Registered::Owned(Box::new(listener)));
+    }
+
+    /// Registers `listener` as long as the returned subscription is kept. Like `register`, this
+    /// replaces the current listener.
+    pub fn subscribe<T: PassThroughListener<E> + 'l>(&mut self, listener: T) -> Subscription<'l, E> {
+        let (subscription, listener) = Subscription::new(listener);
+        let listener: Weak<dyn PassThroughListener<E> + 'l> = Arc::downgrade(&listener);
+        self.listener = Some(Registered::Subscribed(listener));
+        subscription
     }
 
     pub fn deregister(&mut self) {
@@ -107,7 +227,7 @@ impl<'l, E> PassThroughNotifier<'l, E> {
 
     pub fn notify(&self, event: E) {
         if let Some(ref listener) = self.listener {
-            listener.on_event(event);
+            listener.with(|listener| listener.on_event(event));
         }
     }
 }
@@ -1,6 +1,12 @@
+use std::cmp;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use std::time::Instant;
 
+use parking_lot::{Mutex, RwLock};
+
 /// A `RateLimit` can be used to limit the number of occurrences
 /// of certain actions within a time period.
 pub struct RateLimit {
@@ -64,3 +70,154 @@ impl RateLimit {
         self.allowed_occurrences.saturating_sub(self.counter)
     }
 }
+
+/// Counts how many actions the rate limiters of a name allowed and how many they denied. All
+/// limiters with the same name share their counters, e.g. the per-peer limiters of all peers.
+#[derive(Debug, Default)]
+pub struct RateLimitCounters {
+    allowed: AtomicUsize,
+    limited: AtomicUsize,
+}
+
+impl RateLimitCounters {
+    pub fn allowed(&self) -> usize {
+        self.allowed.load(Ordering::Acquire)
+    }
+
+    pub fn limited(&self) -> usize {
+        self.limited.load(Ordering::Acquire)
+    }
+}
+
+lazy_static! {
+    static ref RATE_LIMIT_COUNTERS: RwLock<BTreeMap<&'static str, Arc<RateLimitCounters>>> = RwLock::new(BTreeMap::new());
+}
+
+/// The counters of all rate limiters that were created so far, by their names.
+pub fn rate_limit_counters() -> Vec<(&'static str, Arc<RateLimitCounters>)> {
+    RATE_LIMIT_COUNTERS.read().iter()
+        .map(|(name, counters)| (*name, Arc::clone(counters)))
+        .collect()
+}
+
+/// A token bucket that holds at most `capacity` tokens and is refilled at a rate of `capacity`
+/// tokens per `refill_period`.
+struct TokenBucket {
+    capacity: usize,
+    refill_period: Duration,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant) {
+        let periods = now.duration_since(self.last_refill).div_duration_f64(self.refill_period);
+        self.tokens = (self.tokens + periods * self.capacity as f64).min(self.capacity as f64);
+        self.last_refill = now;
+    }
+}
+
+/// A rate limit that is shared between threads and can be nested, e.g. a limit for all requests
+/// of a peer with a limit per message type below it. An action is only allowed if it's within the
+/// limits of the limiter and all of its ancestors, and then counts towards all of them.
+///
+/// Don't share a parent between peers or clients: Then a few of them can use up the limit of all
+/// the others.
+///
+/// Unlike `RateLimit`, the limit is a token bucket: Actions may use up all of `capacity` at once,
+/// after which they are allowed again at the rate the bucket refills.
+pub struct RateLimiter {
+    name: &'static str,
+    parent: Option<Arc<RateLimiter>>,
+    bucket: Mutex<TokenBucket>,
+    counters: Arc<RateLimitCounters>,
+}
+
+impl RateLimiter {
+    /// Creates a top-level `RateLimiter`.
+    ///
+    /// * `name` - The name its actions are counted under, see `rate_limit_counters`.
+    /// * `capacity` - The number of actions allowed within `refill_period`.
+    /// * `refill_period` - The time it takes to refill the limit completely.
+    pub fn new(name: &'static str, capacity: usize, refill_period: Duration) -> Arc<Self> {
+        Arc::new(Self::with_optional_parent(None, name, capacity, refill_period))
+    }
+
+    /// Creates a `RateLimiter` with a `refill_period` of one minute.
+    pub fn new_per_minute(name: &'static str, capacity: usize) -> Arc<Self> {
+        Self::new(name, capacity, RateLimit::ONE_MINUTE)
+    }
+
+    /// Creates a `RateLimiter` whose actions must also be within the limit of `parent`.
+    pub fn with_parent(parent: &Arc<RateLimiter>, name: &'static str, capacity: usize, refill_period: Duration) -> Arc<Self> {
+        Arc::new(Self::with_optional_parent(Some(Arc::clone(parent)), name, capacity, refill_period))
+    }
+
+    fn with_optional_parent(parent: Option<Arc<RateLimiter>>, name: &'static str, capacity: usize, refill_period: Duration) -> Self {
+        let counters = Arc::clone(RATE_LIMIT_COUNTERS.write()
+            .entry(name)
+            .or_insert_with(|| Arc::new(RateLimitCounters::default())));
+        RateLimiter {
+            name,
+            parent,
+            bucket: Mutex::new(TokenBucket {
+                capacity,
+                refill_period,
+                tokens: capacity as f64,
+                last_refill: Instant::now(),
+            }),
+            counters,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Determine whether a single action is still within the rate limits.
+    #[inline]
+    pub fn note_single(&self) -> bool {
+        self.note(1)
+    }
+
+    /// Determine whether `number` actions are still within the rate limits. If so, they count
+    /// towards the limits of this limiter and its ancestors, otherwise towards none of them.
+    pub fn note(&self, number: usize) -> bool {
+        // Limiters are always locked from the bottom up, so this can't deadlock.
+        let mut buckets = Vec::new();
+        let mut limiter = Some(self);
+        while let Some(current) = limiter {
+            buckets.push((current, current.bucket.lock()));
+            limiter = current.parent.as_deref();
+        }
+
+        let now = Instant::now();
+        for (limiter, bucket) in buckets.iter_mut() {
+            bucket.refill(now);
+            if bucket.tokens < number as f64 {
+                limiter.counters.limited.fetch_add(number, Ordering::AcqRel);
+                return false;
+            }
+        }
+
+        for (limiter, bucket) in buckets.iter_mut() {
+            bucket.tokens -= number as f64;
+            limiter.counters.allowed.fetch_add(number, Ordering::AcqRel);
+        }
+        true
+    }
+
+    /// Determine how many actions are still within the rate limits.
+    pub fn num_allowed(&self) -> usize {
+        let now = Instant::now();
+        let mut bucket = self.bucket.lock();
+        bucket.refill(now);
+        let num_allowed = bucket.tokens as usize;
+        drop(bucket);
+
+        match self.parent {
+            Some(ref parent) => cmp::min(num_allowed, parent.num_allowed()),
+            None => num_allowed,
+        }
+    }
+}
@@ -2,18 +2,57 @@ use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 
 use futures::prelude::*;
+use futures::stream;
 use futures::sync::oneshot;
 use parking_lot::{Mutex, MutexGuard};
 use tokio::timer::{Delay, Interval};
 
+/// When a timer runs its closure, see `Timers::schedules`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// Runs once at `deadline`.
+    Delay { deadline: Instant },
+    /// Runs every `period` after `start`. Ticks that were missed because the executor was busy
+    /// are caught up on at once.
+    Interval { start: Instant, period: Duration },
+    /// Runs at `start + n * period`. Ticks that were missed because the executor was busy are
+    /// skipped, such that the timer neither drifts nor bursts.
+    FixedRate { start: Instant, period: Duration },
+}
+
+impl Schedule {
+    /// The next time the timer runs after `now`.
+    pub fn next_deadline(&self, now: Instant) -> Instant {
+        match *self {
+            Schedule::Delay { deadline } => deadline,
+            Schedule::Interval { start, period } | Schedule::FixedRate { start, period } => Self::next_tick(start, period, now),
+        }
+    }
+
+    /// The first of the ticks `start + n * period` (with `n >= 1`) that lies after `now`.
+    fn next_tick(start: Instant, period: Duration, now: Instant) -> Instant {
+        let elapsed = if now > start { now - start } else { Duration::from_secs(0) };
+        let ticks = elapsed.as_nanos() / period.as_nanos() + 1;
+        start + Duration::from_nanos((period.as_nanos() * ticks) as u64)
+    }
+}
+
+struct Timer {
+    abort: oneshot::Sender<()>,
+    schedule: Schedule,
+    /// The closure of intervals, such that they can be restarted.
+    func: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
 #[derive(Default)]
 pub struct Timers<K: Eq + Hash + Debug> {
-    delays: Mutex<HashMap<K, oneshot::Sender<()>>>,
-    intervals: Mutex<HashMap<K, oneshot::Sender<()>>>,
+    delays: Mutex<HashMap<K, Timer>>,
+    intervals: Mutex<HashMap<K, Timer>>,
 }
 
 impl<K: Eq + Hash + Debug> Timers<K> {
@@ -30,12 +69,12 @@ impl<K: Eq + Hash + Debug> Timers<K> {
         let mut delays = self.delays.lock();
         let mut intervals = self.intervals.lock();
 
-        for (_, sender) in delays.drain() {
-            sender.send(()).unwrap_or(());
+        for (_, timer) in delays.drain() {
+            timer.abort.send(()).unwrap_or(());
         }
 
-        for (_, sender) in intervals.drain() {
-            sender.send(()).unwrap_or(());
+        for (_, timer) in intervals.drain() {
+            timer.abort.send(()).unwrap_or(());
         }
     }
 
@@ -77,7 +116,18 @@ impl<K: Eq + Hash + Debug> Timers<K> {
     pub fn set_interval<F: Send + Sync + 'static>(&self, key: K, func: F, duration: Duration)
         where F: Fn() {
         let mut intervals = self.intervals.lock();
-        self.set_interval_guarded(key, func, duration, &mut intervals);
+        let schedule = Schedule::Interval { start: Instant::now(), period: duration };
+        self.set_interval_guarded(key, Arc::new(func), schedule, &mut intervals);
+    }
+
+    /// Like `set_interval`, but the closure is executed at `start + n * period`. If the executor
+    /// falls behind, missed executions are skipped instead of being caught up on. Fixed-rate
+    /// intervals share their keys with the other intervals.
+    pub fn set_fixed_rate_interval<F: Send + Sync + 'static>(&self, key: K, func: F, start: Instant, period: Duration)
+        where F: Fn() {
+        let mut intervals = self.intervals.lock();
+        let schedule = Schedule::FixedRate { start, period };
+        self.set_interval_guarded(key, Arc::new(func), schedule, &mut intervals);
     }
 
     /// Aborts the interval and prevents any new execution. Also cleans up the internal handle.
@@ -91,7 +141,29 @@ impl<K: Eq + Hash + Debug> Timers<K> {
         where F: Fn() {
         let mut intervals = self.intervals.lock();
         self.clear_timer_guarded(&key, &mut intervals);
-        self.set_interval_guarded(key, func, duration, &mut intervals);
+        let schedule = Schedule::Interval { start: Instant::now(), period: duration };
+        self.set_interval_guarded(key, Arc::new(func), schedule, &mut intervals);
+    }
+
+    /// Restarts the interval with the same closure, such that it's next executed `period` from
+    /// now and every `period` after that. Fixed-rate intervals stay fixed-rate. Returns `false`
+    /// if there is no interval under this key.
+    pub fn restart_interval(&self, key: K, period: Duration) -> bool {
+        let mut intervals = self.intervals.lock();
+        let timer = match intervals.remove(&key) {
+            Some(timer) => timer,
+            None => return false,
+        };
+        timer.abort.send(()).unwrap_or(());
+
+        let start = Instant::now();
+        let schedule = match timer.schedule {
+            Schedule::FixedRate { .. } => Schedule::FixedRate { start, period },
+            _ => Schedule::Interval { start, period },
+        };
+        let func = timer.func.expect("Intervals keep their closure");
+        self.set_interval_guarded(key, func, schedule, &mut intervals);
+        true
     }
 
     /// Checks whether a recurring closure exists under this key.
@@ -100,14 +172,15 @@ impl<K: Eq + Hash + Debug> Timers<K> {
     }
 
     // Internal functions
-    fn set_delay_guarded<F: Send + 'static>(&self, key: K, func: F, delay: Duration, delays: &mut MutexGuard<HashMap<K, oneshot::Sender<()>>>)
+    fn set_delay_guarded<F: Send + 'static>(&self, key: K, func: F, delay: Duration, delays: &mut MutexGuard<HashMap<K, Timer>>)
         where F: FnOnce() {
         if delays.contains_key(&key) {
             error!("Duplicate delay for key {:?}", &key);
             return;
         }
 
-        let task = Delay::new(Instant::now() + delay)
+        let deadline = Instant::now() + delay;
+        let task = Delay::new(deadline)
             .and_then(move |_| {
                 func();
                 Ok(())
@@ -116,38 +189,65 @@ impl<K: Eq + Hash + Debug> Timers<K> {
         let task = task.select(rx.map_err(|_| ()))
             .map(|_| ()).map_err(|_| ());
 
-        delays.insert(key, tx);
+        delays.insert(key, Timer { abort: tx, schedule: Schedule::Delay { deadline }, func: None });
         tokio::spawn(task);
     }
 
-    pub fn set_interval_guarded<F: Send + Sync + 'static>(&self, key: K, func: F, duration: Duration, intervals: &mut MutexGuard<HashMap<K, oneshot::Sender<()>>>)
-        where F: Fn() {
+    fn set_interval_guarded(&self, key: K, func: Arc<dyn Fn() + Send + Sync>, schedule: Schedule, intervals: &mut MutexGuard<HashMap<K, Timer>>) {
         if intervals.contains_key(&key) {
-            error!("Duplicate delay for key {:?}", &key);
+            error!("Duplicate interval for key {:?}", &key);
             return;
         }
 
-        let task = Interval::new_interval(duration)
+        let ticks: Box<dyn Stream<Item=(), Error=()> + Send> = match schedule {
+            Schedule::Interval { start, period } => Box::new(Interval::new(start + period, period)
+                .map(|_| ())
+                .map_err(|_| ())),
+            Schedule::FixedRate { start, period } => {
+                assert!(period > Duration::from_secs(0), "`period` must be non-zero.");
+                Box::new(stream::unfold((), move |_| {
+                    let deadline = Schedule::next_tick(start, period, Instant::now());
+                    Some(Delay::new(deadline).map(|_| ((), ())))
+                }).map_err(|_| ()))
+            },
+            _ => unreachable!("Not an interval schedule"),
+        };
+
+        let tick_func = Arc::clone(&func);
+        let task = ticks
             .for_each(move |_| {
-                func();
+                tick_func();
                 Ok(())
-            }).map_err(|_| ());
+            });
         let (tx, rx) = oneshot::channel();
         let task = task.select(rx.map_err(|_| ()))
             .map(|_| ()).map_err(|_| ());
 
-        intervals.insert(key, tx);
+        intervals.insert(key, Timer { abort: tx, schedule, func: Some(func) });
         tokio::spawn(task);
     }
 
-    fn clear_timer_guarded(&self, key: &K, guard: &mut MutexGuard<HashMap<K, oneshot::Sender<()>>>) {
-        let handle = guard.remove(key);
-        if let Some(handle) = handle {
-            handle.send(()).unwrap_or(());
+    fn clear_timer_guarded(&self, key: &K, guard: &mut MutexGuard<HashMap<K, Timer>>) {
+        let timer = guard.remove(key);
+        if let Some(timer) = timer {
+            timer.abort.send(()).unwrap_or(());
         }
     }
 }
 
+impl<K: Eq + Hash + Debug + Clone> Timers<K> {
+    /// The schedules of all timers by their keys, e.g. to report them for debugging. Delays are
+    /// listed until they are cleared, even if they were already executed.
+    pub fn schedules(&self) -> Vec<(K, Schedule)> {
+        let mut schedules: Vec<(K, Schedule)> = Vec::new();
+        schedules.extend(self.delays.lock().iter()
+            .map(|(key, timer)| (key.clone(), timer.schedule.clone())));
+        schedules.extend(self.intervals.lock().iter()
+            .map(|(key, timer)| (key.clone(), timer.schedule.clone())));
+        schedules
+    }
+}
+
 impl<K: Eq + Hash + Debug> Drop for Timers<K> {
     fn drop(&mut self) {
         self.clear_all()
@@ -0,0 +1,62 @@
+use std::fmt::Debug;
+use std::io;
+use std::time::Duration;
+
+use futures::Future;
+use openssl::error::ErrorStack;
+use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode};
+use openssl::x509::X509Name;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::timer::Timeout;
+use tokio_openssl::{SslAcceptorExt, SslStream};
+
+/// Time a client has to complete the TLS handshake, such that clients that never finish it
+/// don't hold their connections open.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// PEM files for terminating TLS in a server.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub certificate_file: String,
+    pub private_key_file: String,
+    /// If set, clients must present a certificate issued by one of the CAs in this file.
+    pub client_ca_file: Option<String>,
+}
+
+/// Sets up the acceptor that terminates TLS for the clients of a server. If a client CA is
+/// configured, clients must present a certificate issued by it.
+///
+/// This uses OpenSSL directly instead of native-tls like the network crate, since native-tls
+/// can't verify client certificates.
+pub fn tls_acceptor(config: &TlsConfig) -> Result<SslAcceptor, ErrorStack> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+    builder.set_private_key_file(&config.private_key_file, SslFiletype::PEM)?;
+    builder.set_certificate_chain_file(&config.certificate_file)?;
+    builder.check_private_key()?;
+
+    if let Some(ref client_ca_file) = config.client_ca_file {
+        builder.set_ca_file(client_ca_file)?;
+        // Tell clients which certificates are accepted.
+        builder.set_client_ca_list(X509Name::load_client_ca_file(client_ca_file)?);
+        builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    }
+
+    Ok(builder.build())
+}
+
+/// Performs the server side of the TLS handshake on `stream`. Fails with `ErrorKind::TimedOut` if
+/// the client doesn't complete it within `timeout`.
+pub fn accept<S>(acceptor: &SslAcceptor, stream: S, timeout: Duration) -> impl Future<Item=SslStream<S>, Error=io::Error>
+    where S: AsyncRead + AsyncWrite + Debug
+{
+    Timeout::new(acceptor.accept_async(stream), timeout)
+        .map_err(|e| {
+            if e.is_elapsed() {
+                io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out")
+            } else if let Some(e) = e.into_inner() {
+                io::Error::new(io::ErrorKind::Other, e.to_string())
+            } else {
+                io::Error::new(io::ErrorKind::Other, "Timer failed")
+            }
+        })
+}
@@ -0,0 +1,39 @@
+use nimiq_utils::memory::*;
+
+#[test]
+fn it_accounts_usage_per_category() {
+    let accountant = MemoryAccountant::new(Some(1000));
+
+    accountant.set(MemoryCategory::Mempool, 300);
+    accountant.add(MemoryCategory::PeerQueues, 200);
+    accountant.add(MemoryCategory::PeerQueues, 100);
+    accountant.sub(MemoryCategory::PeerQueues, 50);
+
+    assert_eq!(accountant.usage(MemoryCategory::Mempool), 300);
+    assert_eq!(accountant.usage(MemoryCategory::PeerQueues), 250);
+    assert_eq!(accountant.usage(MemoryCategory::Caches), 0);
+    assert_eq!(accountant.total(), 550);
+}
+
+#[test]
+fn it_reports_pressure() {
+    let accountant = MemoryAccountant::new(Some(1000));
+    assert_eq!(accountant.pressure(), MemoryPressure::Normal);
+    assert_eq!(accountant.excess(), 0);
+
+    accountant.set(MemoryCategory::Mempool, 850);
+    assert_eq!(accountant.pressure(), MemoryPressure::High);
+    assert_eq!(accountant.excess(), 50);
+
+    accountant.set(MemoryCategory::Caches, 100);
+    assert_eq!(accountant.pressure(), MemoryPressure::Critical);
+    assert_eq!(accountant.excess(), 150);
+}
+
+#[test]
+fn it_has_no_pressure_without_budget() {
+    let accountant = MemoryAccountant::new(None);
+    accountant.set(MemoryCategory::Mempool, usize::max_value() / 2);
+    assert_eq!(accountant.pressure(), MemoryPressure::Normal);
+    assert_eq!(accountant.excess(), 0);
+}
@@ -17,4 +17,10 @@ pub mod rate_limit;
 #[cfg(feature = "unique-id")]
 pub mod unique_id;
 #[cfg(feature = "otp")]
-pub mod otp;
\ No newline at end of file
+pub mod otp;
+#[cfg(feature = "memory")]
+pub mod memory;
+#[cfg(feature = "timers")]
+pub mod timers;
+#[cfg(feature = "tls")]
+pub mod tls;
\ No newline at end of file
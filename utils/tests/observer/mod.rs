@@ -109,3 +109,69 @@ fn it_can_pass_through_notifications() {
     assert_eq!(*event1_rc1.read().unwrap(), 0);
     assert_eq!(*event2_rc1.read().unwrap(), 42);
 }
+
+#[test]
+fn it_deregisters_dropped_subscriptions() {
+    let mut notifier: Notifier<u32> = Notifier::new();
+
+    let event_rc1 = Arc::new(RwLock::new(0));
+    let event_rc2 = event_rc1.clone();
+    let subscription = notifier.subscribe(move |e: &u32| *event_rc2.write().unwrap() = *e);
+    let _handle = notifier.register(|_: &u32| {});
+    assert_eq!(notifier.len(), 2);
+
+    notifier.notify(42);
+    assert_eq!(*event_rc1.read().unwrap(), 42);
+
+    drop(subscription);
+    assert_eq!(notifier.len(), 1);
+
+    notifier.notify(4711);
+    assert_eq!(*event_rc1.read().unwrap(), 42);
+}
+
+#[test]
+fn it_drops_listeners_with_their_subscription() {
+    let mut notifier: Notifier<u32> = Notifier::new();
+
+    let captured = Arc::new(());
+    let captured_clone = captured.clone();
+    let subscription = notifier.subscribe(move |_: &u32| { let _ = &captured_clone; });
+    assert_eq!(Arc::strong_count(&captured), 2);
+
+    // The listener is dropped right away, not only with the next registration.
+    drop(subscription);
+    assert_eq!(Arc::strong_count(&captured), 1);
+}
+
+#[test]
+fn it_can_subscribe_streams() {
+    use futures::Stream;
+
+    let mut notifier: Notifier<u32> = Notifier::new();
+    let stream = notifier.subscribe_stream(1);
+
+    // The stream buffers `capacity` events plus one for the notifier, the rest is dropped.
+    for event in 1..=5 {
+        notifier.notify(event);
+    }
+    let events: Vec<u32> = stream.take(2).wait().map(Result::unwrap).collect();
+    assert_eq!(events, vec![1, 2]);
+    assert!(notifier.is_empty());
+}
+
+#[test]
+fn it_can_subscribe_to_pass_through_notifications() {
+    let mut notifier: PassThroughNotifier<u32> = PassThroughNotifier::new();
+
+    let event_rc1 = Arc::new(RwLock::new(0));
+    let event_rc2 = event_rc1.clone();
+    let subscription = notifier.subscribe(move |e: u32| *event_rc2.write().unwrap() = e);
+
+    notifier.notify(42);
+    assert_eq!(*event_rc1.read().unwrap(), 42);
+
+    drop(subscription);
+    notifier.notify(4711);
+    assert_eq!(*event_rc1.read().unwrap(), 42);
+}
@@ -31,3 +31,41 @@ fn it_frees_limit_after_time() {
     assert_eq!(limit.num_allowed(), 1);
     assert!(limit.note(1));
 }
+
+#[test]
+fn it_limits_nested_limiters() {
+    let global = RateLimiter::new_per_minute("test_global", 3);
+    let peer1 = RateLimiter::with_parent(&global, "test_peer", 2, Duration::from_secs(60));
+    let peer2 = RateLimiter::with_parent(&global, "test_peer", 2, Duration::from_secs(60));
+
+    assert!(peer1.note(2));
+    assert!(!peer1.note_single());
+    assert_eq!(global.num_allowed(), 1);
+
+    // The global limit applies to both peers.
+    assert_eq!(peer2.num_allowed(), 1);
+    assert!(!peer2.note(2));
+    assert!(peer2.note_single());
+    assert!(!peer2.note_single());
+    assert_eq!(global.num_allowed(), 0);
+
+    let counters: Vec<(&str, usize, usize)> = rate_limit_counters().into_iter()
+        .filter(|(name, _)| name.starts_with("test_"))
+        .map(|(name, counters)| (name, counters.allowed(), counters.limited()))
+        .collect();
+    assert_eq!(counters, vec![("test_global", 3, 3), ("test_peer", 3, 1)]);
+}
+
+#[test]
+fn it_refills_limiters() {
+    let time_period = Duration::from_millis(100);
+    let limiter = RateLimiter::new("refill", 2, time_period);
+
+    assert!(limiter.note(2));
+    assert!(!limiter.note_single());
+
+    sleep(time_period);
+
+    assert_eq!(limiter.num_allowed(), 2);
+    assert!(limiter.note_single());
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use futures::future;
+use tokio::runtime::Runtime;
+
+use nimiq_utils::timers::*;
+
+#[test]
+fn it_computes_drift_free_deadlines() {
+    let start = Instant::now();
+    let period = Duration::from_secs(10);
+    let schedule = Schedule::FixedRate { start, period };
+
+    assert_eq!(schedule.next_deadline(start), start + period);
+    assert_eq!(schedule.next_deadline(start + Duration::from_secs(3)), start + period);
+    assert_eq!(schedule.next_deadline(start + period), start + 2 * period);
+    // Missed ticks are skipped.
+    assert_eq!(schedule.next_deadline(start + Duration::from_secs(42)), start + 5 * period);
+}
+
+#[test]
+fn it_restarts_intervals_with_their_closure() {
+    let runtime = Runtime::new().unwrap();
+    let timers = Arc::new(Timers::new());
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let timers1 = Arc::clone(&timers);
+    let count1 = Arc::clone(&count);
+    runtime.executor().spawn(future::lazy(move || {
+        timers1.set_fixed_rate_interval("tick", move || { count1.fetch_add(1, Ordering::SeqCst); }, Instant::now(), Duration::from_millis(10));
+        Ok(())
+    }));
+    sleep(Duration::from_millis(100));
+    assert!(count.load(Ordering::SeqCst) > 0);
+
+    // Restarting keeps the closure, but no tick is due within the new period.
+    let timers2 = Arc::clone(&timers);
+    let (tx, rx) = std::sync::mpsc::channel();
+    runtime.executor().spawn(future::lazy(move || {
+        tx.send(timers2.restart_interval("tick", Duration::from_secs(3600))).unwrap();
+        Ok(())
+    }));
+    assert!(rx.recv().unwrap());
+    // A tick might have been running while restarting.
+    sleep(Duration::from_millis(20));
+    let ticks = count.load(Ordering::SeqCst);
+    sleep(Duration::from_millis(50));
+    assert_eq!(count.load(Ordering::SeqCst), ticks);
+
+    let schedules = timers.schedules();
+    assert_eq!(schedules.len(), 1);
+    match schedules[0] {
+        ("tick", Schedule::FixedRate { period, .. }) => assert_eq!(period, Duration::from_secs(3600)),
+        ref schedule => panic!("Unexpected schedule: {:?}", schedule),
+    }
+    assert!(!timers.restart_interval("missing", Duration::from_secs(1)));
+
+    timers.clear_all();
+}
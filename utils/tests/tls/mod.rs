@@ -0,0 +1,167 @@
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use futures::{Future, Stream};
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::ssl::{SslConnector, SslMethod};
+use openssl::x509::{X509, X509Builder, X509NameBuilder};
+use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+use tempdir::TempDir;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+use tokio::timer::Delay;
+use tokio_openssl::{SslConnectorExt, SslStream};
+
+use nimiq_utils::tls::{accept, tls_acceptor, TlsConfig};
+
+struct Identity {
+    certificate: X509,
+    key: PKey<Private>,
+}
+
+impl Identity {
+    fn new(name: &str, serial: u32, issuer: Option<&Identity>) -> Identity {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+        let mut subject = X509NameBuilder::new().unwrap();
+        subject.append_entry_by_text("CN", name).unwrap();
+        let subject = subject.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap()).unwrap();
+        builder.set_subject_name(&subject).unwrap();
+        match issuer {
+            Some(issuer) => builder.set_issuer_name(issuer.certificate.subject_name()).unwrap(),
+            None => builder.set_issuer_name(&subject).unwrap(),
+        }
+        builder.set_pubkey(&key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        match issuer {
+            Some(issuer) => {
+                let alt_name = SubjectAlternativeName::new().dns("localhost")
+                    .build(&builder.x509v3_context(Some(&*issuer.certificate), None)).unwrap();
+                builder.append_extension(alt_name).unwrap();
+                builder.sign(&issuer.key, MessageDigest::sha256()).unwrap();
+            },
+            None => {
+                builder.append_extension(BasicConstraints::new().critical().ca().build().unwrap()).unwrap();
+                builder.sign(&key, MessageDigest::sha256()).unwrap();
+            },
+        }
+
+        Identity { certificate: builder.build(), key }
+    }
+}
+
+/// A CA that issued the certificates of the server and of a client.
+struct Pki {
+    dir: TempDir,
+    ca: Identity,
+    client: Identity,
+}
+
+impl Pki {
+    fn new() -> Pki {
+        let dir = TempDir::new("tls").unwrap();
+        let ca = Identity::new("ca", 1, None);
+        let server = Identity::new("localhost", 2, Some(&ca));
+        let client = Identity::new("client", 3, Some(&ca));
+
+        fs::write(dir.path().join("ca.pem"), ca.certificate.to_pem().unwrap()).unwrap();
+        fs::write(dir.path().join("server.pem"), server.certificate.to_pem().unwrap()).unwrap();
+        fs::write(dir.path().join("server.key"), server.key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+
+        Pki { dir, ca, client }
+    }
+
+    fn file(&self, name: &str) -> String {
+        self.dir.path().join(name).to_str().unwrap().to_string()
+    }
+
+    fn config(&self, verify_clients: bool) -> TlsConfig {
+        TlsConfig {
+            certificate_file: self.file("server.pem"),
+            private_key_file: self.file("server.key"),
+            client_ca_file: if verify_clients { Some(self.file("ca.pem")) } else { None },
+        }
+    }
+
+    fn connector(&self, with_certificate: bool) -> SslConnector {
+        let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+        builder.cert_store_mut().add_cert(self.ca.certificate.clone()).unwrap();
+        if with_certificate {
+            builder.set_certificate(&self.client.certificate).unwrap();
+            builder.set_private_key(&self.client.key).unwrap();
+        }
+        builder.build()
+    }
+}
+
+/// Accepts a single connection with `config` and returns the result of the server side of the
+/// handshake. The client connects with `connector`, or doesn't start the handshake at all.
+fn handshake(config: &TlsConfig, connector: Option<SslConnector>, timeout: Duration) -> io::Result<()> {
+    let acceptor = tls_acceptor(config).unwrap();
+    let listener = TcpListener::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = listener.incoming()
+        .into_future()
+        .map_err(|(e, _)| e)
+        .and_then(move |(socket, _)| accept(&acceptor, socket.unwrap(), timeout))
+        .map(|_| ());
+
+    // The client result doesn't matter, the server decides whether the handshake succeeded. The
+    // client's stream is kept open until the server is done.
+    let client = TcpStream::connect(&addr)
+        .and_then(move |socket| -> Box<dyn Future<Item=Option<SslStream<TcpStream>>, Error=io::Error> + Send> {
+            match connector {
+                Some(connector) => Box::new(connector.connect_async("localhost", socket)
+                    .map(Some)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))),
+                // Keep the connection open without sending anything.
+                None => Box::new(Delay::new(Instant::now() + timeout * 2)
+                    .map(move |_| {
+                        drop(socket);
+                        None
+                    })
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))),
+            }
+        })
+        .then(|result| Ok::<_, io::Error>(result.ok()));
+
+    let mut runtime = Runtime::new().unwrap();
+    runtime.block_on(server.join(client).map(|_| ()))
+}
+
+#[test]
+fn it_completes_a_handshake() {
+    let pki = Pki::new();
+    assert!(handshake(&pki.config(false), Some(pki.connector(false)), Duration::from_secs(10)).is_ok());
+}
+
+#[test]
+fn it_accepts_clients_with_a_certificate_of_the_client_ca() {
+    let pki = Pki::new();
+    assert!(handshake(&pki.config(true), Some(pki.connector(true)), Duration::from_secs(10)).is_ok());
+}
+
+#[test]
+fn it_rejects_clients_without_a_certificate() {
+    let pki = Pki::new();
+    assert!(handshake(&pki.config(true), Some(pki.connector(false)), Duration::from_secs(10)).is_err());
+}
+
+#[test]
+fn it_times_out_handshakes() {
+    let pki = Pki::new();
+    let result = handshake(&pki.config(false), None, Duration::from_millis(100));
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+}
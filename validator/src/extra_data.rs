@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use block_albatross::MicroExtrinsics;
+
+/// Provides the extra data of the micro blocks a validator produces, e.g. so that pools can tag
+/// their blocks.
+#[derive(Clone)]
+pub enum ExtraDataProvider {
+    /// The same extra data for every block
+    Static(Vec<u8>),
+    /// Called with the block number and view number of each block
+    Callback(Arc<dyn Fn(u32, u32) -> Vec<u8> + Send + Sync>),
+}
+
+impl ExtraDataProvider {
+    /// Returns the extra data for a block. Extra data that exceeds
+    /// `MicroExtrinsics::MAX_EXTRA_DATA_SIZE` is truncated.
+    pub fn extra_data(&self, block_number: u32, view_number: u32) -> Vec<u8> {
+        let mut extra_data = match self {
+            ExtraDataProvider::Static(extra_data) => extra_data.clone(),
+            ExtraDataProvider::Callback(callback) => callback(block_number, view_number),
+        };
+        if extra_data.len() > MicroExtrinsics::MAX_EXTRA_DATA_SIZE {
+            warn!("Extra data for block #{}.{} is too long ({} bytes), truncating it", block_number, view_number, extra_data.len());
+            extra_data.truncate(MicroExtrinsics::MAX_EXTRA_DATA_SIZE);
+        }
+        extra_data
+    }
+}
+
+impl Default for ExtraDataProvider {
+    fn default() -> Self {
+        ExtraDataProvider::Static(Vec::new())
+    }
+}
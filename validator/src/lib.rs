@@ -27,7 +27,9 @@ pub mod validator;
 pub mod validator_network;
 pub mod validator_agent;
 pub mod error;
+pub mod extra_data;
 pub mod slash;
+pub mod partition;
 pub mod signature_aggregation;
 pub mod pool;
 
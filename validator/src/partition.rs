@@ -0,0 +1,28 @@
+use primitives::policy::{SLOTS, TWO_THIRD_SLOTS};
+
+/// How many slots of the current epoch this validator can reach. If less than two thirds of the
+/// slots are reachable, neither view changes nor macro blocks can complete.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PartitionStatus {
+    /// Slots of validators for which we have a validator info, including our own.
+    pub known_slots: u16,
+    /// Slots of validators we're connected to, including our own.
+    pub reachable_slots: u16,
+    /// Slots that signed the most recent view change or pBFT commit of this epoch.
+    pub contributing_slots: Option<u16>,
+}
+
+impl PartitionStatus {
+    pub fn reachable_fraction(&self) -> f64 {
+        f64::from(self.reachable_slots) / f64::from(SLOTS)
+    }
+
+    pub fn contributing_fraction(&self) -> Option<f64> {
+        self.contributing_slots.map(|slots| f64::from(slots) / f64::from(SLOTS))
+    }
+
+    pub fn is_partitioned(&self) -> bool {
+        self.reachable_slots < TWO_THIRD_SLOTS
+            || self.contributing_slots.map_or(false, |slots| slots < TWO_THIRD_SLOTS)
+    }
+}
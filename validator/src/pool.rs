@@ -118,6 +118,18 @@ impl ValidatorPool {
         true
     }
 
+    /// Forgets the validator infos that are valid from before `block_number`, unless their
+    /// validator is active or connected.
+    pub fn prune_infos(&mut self, block_number: u32) {
+        let validator_id_by_pubkey = &self.validator_id_by_pubkey;
+        let potential_validators = &self.potential_validators;
+        self.infos.retain(|pubkey, info| {
+            info.message.valid_from >= block_number
+                || validator_id_by_pubkey.contains_key(pubkey)
+                || potential_validators.contains_key(pubkey)
+        });
+    }
+
     /// Called when a connected validator peer disconnects
     pub fn on_validator_left(&mut self, agent: Arc<ValidatorAgent>) {
         let agent_state = agent.state.read();
@@ -160,4 +172,29 @@ impl ValidatorPool {
     pub fn active_validator_count(&self) -> usize {
         self.active_validators.num_groups()
     }
+
+    /// Returns the number of slots of the given active validators.
+    pub fn slots_of<I: IntoIterator<Item=usize>>(&self, validator_ids: I) -> u16 {
+        validator_ids.into_iter()
+            .filter_map(|validator_id| self.active_validators.get(validator_id))
+            .map(|group| group.0)
+            .sum()
+    }
+
+    /// Returns the number of slots of active validators for which we have a validator info.
+    /// Blacklisted validators (i.e. ourself) are not counted.
+    pub fn known_slots(&self) -> u16 {
+        self.active_validators.iter_groups()
+            .filter(|group| {
+                let pubkey = group.1.compressed();
+                !self.blacklist.contains(pubkey) && self.infos.contains_key(pubkey)
+            })
+            .map(|group| group.0)
+            .sum()
+    }
+
+    /// Returns the number of slots of active validators we're connected to.
+    pub fn connected_slots(&self) -> u16 {
+        self.slots_of(self.active_validator_agents.keys().cloned())
+    }
 }
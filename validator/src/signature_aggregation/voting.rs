@@ -261,6 +261,16 @@ impl<T: Tag> VoteAggregation<T> {
         self.inner.push_update(level_update.update);
     }
 
+    /// Verified individual and multi-signatures collected so far
+    pub fn verified_signatures(&self) -> Vec<Signature> {
+        self.inner.verified_signatures()
+    }
+
+    /// Adds signatures, that were verified by a previous aggregation for the same tag
+    pub fn push_verified_signatures(&self, signatures: Vec<Signature>) {
+        self.inner.push_verified_signatures(signatures);
+    }
+
     pub fn votes(&self) -> usize {
         self.inner.protocol.votes()
     }
@@ -41,6 +41,11 @@ impl ForkProofPool {
         }
     }
 
+    /// Removes fork proofs of blocks before `block_number`, which can't be slashed anymore.
+    pub fn prune(&mut self, block_number: u32) {
+        self.fork_proofs.retain(|fork_proof| fork_proof.header1.block_number >= block_number);
+    }
+
     /// Returns a list of current fork proofs.
     pub fn get_fork_proofs_for_block(&self, max_size: usize) -> Vec<ForkProof> {
         let mut proofs = Vec::new();
@@ -54,3 +59,48 @@ impl ForkProofPool {
         proofs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use block_albatross::MicroHeader;
+    use bls::bls12_381::KeyPair;
+    use hash::Blake2bHash;
+
+    use super::*;
+
+    fn fork_proof(key_pair: &KeyPair, block_number: u32) -> ForkProof {
+        let header1 = MicroHeader {
+            version: 1,
+            block_number,
+            view_number: 0,
+            parent_hash: Blake2bHash::default(),
+            extrinsics_root: Blake2bHash::default(),
+            state_root: Blake2bHash::default(),
+            seed: key_pair.sign(&Blake2bHash::default()).compress(),
+            timestamp: 0,
+        };
+        let mut header2 = header1.clone();
+        header2.timestamp = 1;
+        ForkProof {
+            justification1: key_pair.sign(&header1).compress(),
+            justification2: key_pair.sign(&header2).compress(),
+            header1,
+            header2,
+        }
+    }
+
+    #[test]
+    fn it_prunes_fork_proofs_of_old_blocks() {
+        let key_pair = KeyPair::generate(&mut rand::thread_rng());
+        let old = fork_proof(&key_pair, 9);
+        let recent = fork_proof(&key_pair, 10);
+
+        let mut pool = ForkProofPool::new();
+        assert!(pool.insert(old.clone()));
+        assert!(pool.insert(recent.clone()));
+
+        pool.prune(10);
+        assert!(!pool.contains(&old));
+        assert!(pool.contains(&recent));
+    }
+}
@@ -1,7 +1,9 @@
 use std::sync::{Arc, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::collections::HashMap;
+use std::iter;
 
+use futures_cpupool::{Builder, CpuPool};
 use parking_lot::RwLock;
 
 use account::Account;
@@ -26,20 +28,27 @@ use block_albatross::{
     ViewChangeProof,
 };
 use block_production_albatross::BlockProducer;
+use block_production_albatross::blacklist::Blacklist;
+use block_production_albatross::selector::TransactionSelector;
+use block_production_albatross::validator_keys::ValidatorKeys;
 use blockchain_albatross::Blockchain;
 use blockchain_base::BlockchainEvent;
-use bls::bls12_381::KeyPair;
+use bls::bls12_381::{CompressedPublicKey, KeyPair};
 use collections::grouped_list::Group;
 use consensus::{AlbatrossConsensusProtocol, Consensus, ConsensusEvent};
 use hash::{Blake2bHash, Hash};
+use network::network_config::NodeRole;
+use network_primitives::heartbeat::{Heartbeat, SignedHeartbeat, ValidatorLiveness};
 use network_primitives::networks::NetworkInfo;
-use network_primitives::validator_info::{SignedValidatorInfo, ValidatorInfo};
+use primitives::policy;
 use primitives::validators::IndexedSlot;
 use utils::mutable_once::MutableOnce;
 use utils::timers::Timers;
-use utils::observer::ListenerHandle;
+use utils::observer::Subscription;
 
 use crate::error::Error;
+use crate::extra_data::ExtraDataProvider;
+use crate::partition::PartitionStatus;
 use crate::slash::ForkProofPool;
 use crate::validator_network::{ValidatorNetwork, ValidatorNetworkEvent};
 
@@ -58,19 +67,27 @@ pub enum ValidatorStatus {
     Active,
 }
 
+/// Subscriptions of the validator's listeners, which are deregistered when the validator is
+/// dropped.
 struct ValidatorListeners {
-    consensus: ListenerHandle,
-    blockchain: ListenerHandle,
+    _consensus: Subscription<'static, ConsensusEvent>,
+    _blockchain: Subscription<'static, BlockchainEvent<Block>>,
+    _validator_network: Subscription<'static, ValidatorNetworkEvent>,
 }
 
 pub struct Validator {
     blockchain: Arc<Blockchain<'static>>,
-    block_producer: BlockProducer<'static>,
+    /// Signs with the key of the current epoch, see `ValidatorState::signing_key`.
+    block_producer: RwLock<BlockProducer<'static>>,
     consensus: Arc<Consensus<AlbatrossConsensusProtocol>>,
     validator_network: Arc<ValidatorNetwork>,
-    validator_key: KeyPair,
+    validator_keys: Arc<ValidatorKeys>,
+    extra_data: ExtraDataProvider,
+    manual_block_production: bool,
 
     timers: Timers<ValidatorTimer>,
+    /// Handles the blockchain events, see `init_listeners`
+    blockchain_event_pool: CpuPool,
 
     state: RwLock<ValidatorState>,
 
@@ -81,52 +98,87 @@ pub struct Validator {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum ValidatorTimer {
     ViewChange,
+    Heartbeat,
 }
 
 pub struct ValidatorState {
     pk_idx: Option<u16>,
+    /// The key that was elected for the current epoch. During a key rotation, the previous key
+    /// keeps signing until the new key is elected.
+    signing_key: KeyPair,
     slots: Option<u16>,
     status: ValidatorStatus,
     fork_proof_pool: ForkProofPool,
     view_number: u32,
     active_view_change: Option<ViewChange>,
+    /// A view change that additionally skips the views of producers that are known to be offline.
+    /// It's aggregated alongside `active_view_change`.
+    skip_view_change: Option<ViewChange>,
     proposed_extrinsics: HashMap<Blake2bHash, MacroExtrinsics>,
 }
 
 impl Validator {
     const BLOCK_TIMEOUT: Duration = Duration::from_secs(10);
+    /// Block timeout if the next block producer is known to be offline
+    const OFFLINE_PRODUCER_TIMEOUT: Duration = Duration::from_secs(2);
+    const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+    /// Maximum number of views a view change skips, if their producers are offline
+    const MAX_SKIPPED_VIEWS: u32 = 8;
     //const PBFT_TIMEOUT: Duration = Duration::from_secs(60);
 
-    pub fn new(consensus: Arc<Consensus<AlbatrossConsensusProtocol>>, validator_key: KeyPair) -> Result<Arc<Self>, Error> {
-        let compressed_public_key = validator_key.public.compress();
-        let info = ValidatorInfo {
-            public_key: compressed_public_key,
-            peer_address: consensus.network.network_config.peer_address().clone(),
-            udp_address: None,
-            valid_from: consensus.blockchain.block_number(),
-        };
-        let validator_network = ValidatorNetwork::new(consensus.network.clone(), consensus.blockchain.clone(), SignedValidatorInfo::from_message(info, &validator_key.secret, 0));
-        let block_producer = BlockProducer::new(consensus.blockchain.clone(), consensus.mempool.clone(), validator_key.clone());
+    /// If `verify_blocks` is set, produced blocks are checked against the blockchain before they
+    /// are relayed or proposed. The extra data of produced micro blocks is taken from `extra_data`,
+    /// transactions on the `blacklist` are left out of them and `local_transactions_size` bytes are
+    /// reserved for locally submitted transactions. The rest of the block is filled by the
+    /// `transaction_selector`. Signatures are verified by `verification_threads` threads and
+    /// blockchain events are handled by `blockchain_event_threads` threads.
+    ///
+    /// With `manual_block_production`, the validator neither produces blocks in its slots nor
+    /// starts view changes when a block times out. The chain is then driven by the test RPC
+    /// methods, which must not compete with blocks produced by the validator.
+    ///
+    /// During a key rotation, the validator signs with whichever of its `validator_keys` was
+    /// elected for the current epoch.
+    pub fn new(consensus: Arc<Consensus<AlbatrossConsensusProtocol>>, validator_keys: Arc<ValidatorKeys>, liveness: Arc<ValidatorLiveness>, verify_blocks: bool, extra_data: ExtraDataProvider, blacklist: Arc<Blacklist>, local_transactions_size: usize, transaction_selector: Arc<dyn TransactionSelector>, verification_threads: usize, blockchain_event_threads: usize, manual_block_production: bool) -> Result<Arc<Self>, Error> {
+        if !handel::verifier::init_shared_cpu_pool(verification_threads) {
+            warn!("Signature verification pool already exists, can't change its size to {}", verification_threads);
+        }
+
+        let validator_network = ValidatorNetwork::new(consensus.network.clone(), consensus.blockchain.clone(), Arc::clone(&validator_keys), liveness);
+        let signing_key = validator_keys.current();
+        let mut block_producer = BlockProducer::new(consensus.blockchain.clone(), consensus.mempool.clone(), signing_key.clone());
+        block_producer.verify_blocks = verify_blocks;
+        block_producer.blacklist = blacklist;
+        block_producer.local_transactions_size = local_transactions_size;
+        block_producer.transaction_selector = transaction_selector;
         let view_number = consensus.blockchain.next_view_number();
 
         debug!("Initializing validator");
 
         let this = Arc::new(Validator {
             blockchain: consensus.blockchain.clone(),
-            block_producer,
+            block_producer: RwLock::new(block_producer),
             consensus,
             validator_network,
 
-            validator_key,
+            validator_keys,
+            extra_data,
+            manual_block_production,
             timers: Timers::new(),
+            blockchain_event_pool: Builder::new()
+                .pool_size(blockchain_event_threads)
+                .name_prefix("blockchain-events-")
+                .create(),
 
             state: RwLock::new(ValidatorState {
                 pk_idx: None,
+                signing_key,
                 slots: None,
                 status: ValidatorStatus::None,
                 fork_proof_pool: ForkProofPool::new(),
                 view_number,
                 active_view_change: None,
+                skip_view_change: None,
                 proposed_extrinsics: HashMap::new(),
             }),
 
@@ -143,7 +195,7 @@ impl Validator {
 
         // Setup event handlers for blockchain events
         let weak = Arc::downgrade(this);
-        let consensus = this.consensus.notifier.write().register(move |e: &ConsensusEvent| {
+        let consensus = this.consensus.notifier.write().subscribe(move |e: &ConsensusEvent| {
             let this = upgrade_weak!(weak);
             match e {
                 ConsensusEvent::Established => this.on_consensus_established(),
@@ -154,7 +206,7 @@ impl Validator {
 
         // Set up event handlers for blockchain events
         let weak = Arc::downgrade(this);
-        let blockchain = this.blockchain.notifier.write().register(move |e: &BlockchainEvent<Block>| {
+        let blockchain = this.blockchain.notifier.write().subscribe(move |e: &BlockchainEvent<Block>| {
             // We're spawning this handler in a thread, since it does quite a lot of work.
             // Specifically this might lock the validator state, but in this handler the Blockchain
             // also still holds the push_lock. This can cause a dead-lock with another thread that
@@ -165,39 +217,93 @@ impl Validator {
             // But except for rebranching, this is only the type of the event and a hash, so not
             // very expensive to clone anyway.
             let e = e.clone();
-            tokio::spawn(futures::future::lazy(move|| {
-                this.on_blockchain_event(&e);
+            // The handler runs on the validator's own pool, which limits how many events are
+            // handled at the same time, without taking threads from the runtime.
+            let handler = Arc::clone(&this);
+            this.blockchain_event_pool.spawn_fn(move || -> Result<(), ()> {
+                handler.on_blockchain_event(&e);
                 Ok(())
-            }));
+            }).forget();
         });
 
         // Set up event handlers for validator network events
         let weak = Arc::downgrade(this);
-        this.validator_network.notifier.write().register(move |e: ValidatorNetworkEvent| {
+        let validator_network = this.validator_network.notifier.write().subscribe(move |e: ValidatorNetworkEvent| {
             let this = upgrade_weak!(weak);
             this.on_validator_network_event(e);
         });
 
         // Set up the view change timer in case there's a block timeout
         // Note: In start_view_change() we check so that it's only executed if we are an active validator
+        this.set_view_change_interval(Self::BLOCK_TIMEOUT);
+
+        // Let the other validators know that we're online
         let weak = Arc::downgrade(this);
-        this.timers.set_interval(ValidatorTimer::ViewChange, move || {
+        this.timers.set_fixed_rate_interval(ValidatorTimer::Heartbeat, move || {
             let this = upgrade_weak!(weak);
-            this.on_block_timeout();
-        }, Self::BLOCK_TIMEOUT);
+            this.send_heartbeat();
+        }, Instant::now(), Self::HEARTBEAT_INTERVAL);
 
-        // remember listeners for when we drop this validator
+        // keep the listeners registered as long as this validator lives
         let listeners = ValidatorListeners {
-            consensus,
-            blockchain,
+            _consensus: consensus,
+            _blockchain: blockchain,
+            _validator_network: validator_network,
         };
         unsafe { this.listeners.replace(Some(listeners)); }
     }
 
     fn on_block_timeout(&self) {
+        if self.manual_block_production {
+            return;
+        }
+
+        // The timeout might have been shortened because the block producer was offline. Retries
+        // of the view change use the full timeout again.
+        self.reset_view_change_interval(Self::BLOCK_TIMEOUT);
         self.start_view_change();
     }
 
+    fn send_heartbeat(&self) {
+        let state = self.state.read();
+
+        // Heartbeats are only exchanged between active validators
+        if state.status != ValidatorStatus::Active {
+            return;
+        }
+
+        let pk_idx = state.pk_idx.expect("Checked above that we are an active validator");
+        let key_pair = state.signing_key.clone();
+        drop(state);
+
+        let heartbeat = Heartbeat {
+            block_number: self.blockchain.height() + 1,
+            timestamp: self.consensus.network.network_time.now(),
+        };
+        let heartbeat = SignedHeartbeat::from_message(heartbeat, &key_pair.secret, pk_idx);
+        self.validator_network.send_heartbeat(heartbeat);
+    }
+
+    /// Removes the fork proofs of blocks before `epoch`.
+    pub fn prune_fork_proofs(&self, epoch: u32) {
+        self.state.write().fork_proof_pool.prune(policy::first_block_of(epoch));
+    }
+
+    /// Removes the infos of inactive validators that are valid from before `epoch`.
+    pub fn prune_validator_infos(&self, epoch: u32) {
+        self.validator_network.prune_validator_infos(policy::first_block_of(epoch));
+    }
+
+    /// Forgets the proofs of the view changes that happened before `epoch`.
+    pub fn prune_view_changes(&self, epoch: u32) {
+        self.validator_network.prune_view_changes(policy::first_block_of(epoch));
+    }
+
+    /// Returns how many slots of the current epoch this validator can reach.
+    pub fn partition_status(&self) -> PartitionStatus {
+        self.validator_network.partition_status()
+    }
+
     pub fn on_consensus_established(&self) {
         trace!("Consensus established");
         self.init_epoch();
@@ -216,14 +322,23 @@ impl Validator {
         state.status = ValidatorStatus::None;
     }
 
-    fn reset_view_change_interval(&self, timeout: Duration) {
-        let weak = self.self_weak.clone();
-        self.timers.reset_interval(ValidatorTimer::ViewChange, move || {
+    fn set_view_change_interval(&self, timeout: Duration) {
+        let weak = Weak::clone(&self.self_weak);
+        self.timers.set_interval(ValidatorTimer::ViewChange, move || {
             let this = upgrade_weak!(weak);
             this.on_block_timeout();
         }, timeout);
     }
 
+    fn reset_view_change_interval(&self, timeout: Duration) {
+        // The timer is set up with the validator, but if it's gone, the validator would never
+        // change the view again.
+        if !self.timers.restart_interval(ValidatorTimer::ViewChange, timeout) {
+            warn!("View change timer missing, setting it up again");
+            self.set_view_change_interval(timeout);
+        }
+    }
+
     fn on_blockchain_event(&self, event: &BlockchainEvent<Block>) {
         // Handle each block type (which is directly related to each event type).
         match event {
@@ -236,6 +351,8 @@ impl Validator {
             BlockchainEvent::Extended(hash) => {
                 self.on_blockchain_extended(hash);
                 self.validator_network.on_blockchain_changed(hash);
+                // Announces a key that a rotation just added.
+                self.validator_network.update_own_infos();
             },
 
             BlockchainEvent::Rebranched(old_chain, new_chain) => {
@@ -259,7 +376,7 @@ impl Validator {
             // NOTE: This doesn't take the state lock, so we don't need to drop it
             self.reset_view_change_interval(Self::BLOCK_TIMEOUT);
             state.active_view_change = None;
-
+            state.skip_view_change = None;
         }
 
         // If we're an active validator, we need to check if we're the next block producer.
@@ -274,9 +391,21 @@ impl Validator {
         let mut state = self.state.write();
         state.view_number = 0;
 
-        match self.get_pk_idx_and_slots() {
-            Some((pk_idx, slots)) => {
+        // The key a rotation replaced isn't needed anymore, once the new key was elected.
+        if let Some(public_key) = self.validator_keys.complete_rotation(|public_key| self.is_key_in_use(public_key)) {
+            info!("Key rotation completed, dropping validator key {:?}", public_key);
+        }
+        self.validator_network.update_own_infos();
+
+        let elected = self.validator_keys.elected(&self.blockchain.current_validators());
+        match elected {
+            Some((key_pair, pk_idx, slots)) => {
                 debug!("Setting validator to active: pk_idx={}", pk_idx);
+                if key_pair.public.compress() != state.signing_key.public.compress() {
+                    info!("Signing with validator key {:?}", key_pair.public.compress());
+                    self.block_producer.write().validator_key = key_pair.clone();
+                    state.signing_key = key_pair;
+                }
                 state.pk_idx = Some(pk_idx);
                 state.slots = Some(slots);
                 state.status = ValidatorStatus::Active;
@@ -284,6 +413,7 @@ impl Validator {
                 // Notify validator network that we have finality and update epoch-related state
                 // (i.e. set the validator ID)
                 self.validator_network.reset_epoch(Some(pk_idx as usize));
+                self.consensus.network.set_role(NodeRole::ActiveValidator);
             },
             None => {
                 debug!("Setting validator to inactive");
@@ -294,6 +424,7 @@ impl Validator {
                 // Notify validator network that we have finality and update epoch-related state
                 // (i.e. remove the validator ID)
                 self.validator_network.reset_epoch(None);
+                self.consensus.network.set_role(NodeRole::Validator);
             },
         }
     }
@@ -346,6 +477,8 @@ impl Validator {
                 self.on_pbft_commit_complete(hash, proposal, proof)
             },
             ValidatorNetworkEvent::ForkProof(event) => self.on_fork_proof(*event),
+            // The validator network already warns about partitions.
+            ValidatorNetworkEvent::PartitionChanged(_) => {},
         }
     }
 
@@ -362,11 +495,10 @@ impl Validator {
             SlotChange::ViewChange(view_change, view_change_proof) => {
                 let mut state = self.state.write();
 
-                // If we have an active view change with this view number, clear it.
-                if let Some(vc) = &state.active_view_change {
-                    if vc == &view_change {
-                        state.active_view_change = None;
-                    }
+                // If one of our active view changes completed, we don't need the other one anymore.
+                if state.active_view_change.as_ref() == Some(&view_change) || state.skip_view_change.as_ref() == Some(&view_change) {
+                    state.active_view_change = None;
+                    state.skip_view_change = None;
                 }
 
                 // check if this view change is still relevant
@@ -389,10 +521,13 @@ impl Validator {
 
         // Check if we are the next block producer and act accordingly
         let IndexedSlot { slot, .. } = self.blockchain.get_next_block_producer(view_number, None);
-        let public_key = self.validator_key.public.compress();
+        let public_key = self.state.read().signing_key.public.compress();
         trace!("Next block producer: {:?}", slot.public_key.compressed());
 
-        if slot.public_key.compressed() == &public_key {
+        if self.manual_block_production {
+            trace!("Manual block production, not producing the next block");
+        }
+        else if slot.public_key.compressed() == &public_key {
             let weak = self.self_weak.clone();
             trace!("Spawning thread to produce next block");
             tokio::spawn(futures::lazy(move || {
@@ -405,6 +540,11 @@ impl Validator {
                 Ok(())
             }));
         }
+        else if self.get_pk_idx(slot.public_key.compressed()).map_or(false, |pk_idx| self.validator_network.is_offline(pk_idx)) {
+            // Don't wait for the full block timeout, if we know that the producer is offline.
+            debug!("Next block producer is offline, starting view change early");
+            self.reset_view_change_interval(Self::OFFLINE_PRODUCER_TIMEOUT);
+        }
     }
 
     pub fn on_pbft_proposal(&self, hash: Blake2bHash, _proposal: PbftProposal) {
@@ -417,13 +557,14 @@ impl Validator {
 
         // Note: we don't verify this hash as the network validator already did.
         let pk_idx = state.pk_idx.expect("Already checked that we are an active validator before calling this function");
+        let key_pair = state.signing_key.clone();
 
         drop(state);
 
         trace!("Signing prepare: pk_idx={}", pk_idx);
         let prepare_message = SignedPbftPrepareMessage::from_message(
             PbftPrepareMessage { block_hash: hash.clone() },
-            &self.validator_key.secret,
+            &key_pair.secret,
             pk_idx
         );
 
@@ -441,13 +582,14 @@ impl Validator {
 
         // Note: we don't verify this hash as the network validator already did
         let pk_idx = state.pk_idx.expect("Already checked that we are an active validator before calling this function");
+        let key_pair = state.signing_key.clone();
 
         drop(state);
 
         trace!("Signing commit message: pk_idx={}", pk_idx);
         let commit_message = SignedPbftCommitMessage::from_message(
             PbftCommitMessage { block_hash: hash },
-            &self.validator_key.secret,
+            &key_pair.secret,
             pk_idx
         );
 
@@ -485,9 +627,21 @@ impl Validator {
             return;
         }
 
-        // If we already started a view change (i.e. added our contribution), we don't do anything
-        if state.active_view_change.is_some() {
-            debug!("View change already started");
+        let pk_idx = state.pk_idx.expect("Checked above that we are an active validator");
+        let key_pair = state.signing_key.clone();
+
+        // If we already started a view change (i.e. added our contribution), it didn't complete
+        // in time. We restart it and keep the contributions we already have.
+        if let Some(view_change) = state.active_view_change.clone() {
+            debug!("View change {} didn't complete in time, restarting it", view_change);
+            let view_change_messages = iter::once(view_change)
+                .chain(state.skip_view_change.clone())
+                .map(|view_change| SignedViewChange::from_message(view_change, &key_pair.secret, pk_idx))
+                .collect::<Vec<SignedViewChange>>();
+            drop(state);
+            for view_change_message in view_change_messages {
+                self.validator_network.restart_view_change(view_change_message);
+            }
             return;
         }
 
@@ -498,22 +652,49 @@ impl Validator {
 
         info!("Starting view change to {}", message);
 
-        let pk_idx = state.pk_idx.expect("Checked above that we are an active validator");
-        let view_change_message = SignedViewChange::from_message(message.clone(), &self.validator_key.secret, pk_idx);
+        let view_change_message = SignedViewChange::from_message(message.clone(), &key_pair.secret, pk_idx);
         state.active_view_change = Some(message);
 
+        // If the next producers are known to be offline, we also aggregate a view change that
+        // skips their views. Whichever completes first is used.
+        let skip_view_number = self.skip_ahead_view_number(new_view_number);
+        let skip_view_change_message = if skip_view_number > new_view_number {
+            let message = ViewChange { block_number, new_view_number: skip_view_number };
+            info!("Producers of views {}..{} are offline, also starting view change to {}", new_view_number, skip_view_number, message);
+            state.skip_view_change = Some(message.clone());
+            Some(SignedViewChange::from_message(message, &key_pair.secret, pk_idx))
+        }
+        else {
+            None
+        };
+
         drop(state);
 
         // Broadcast our view change number message to the other validators.
         self.validator_network.start_view_change(view_change_message);
-     }
-
-    fn get_pk_idx_and_slots(&self) -> Option<(u16, u16)> {
-        let compressed = self.validator_key.public.compress();
-        let validator_list = self.blockchain.current_validators();
-        let item = validator_list.groups().iter().enumerate()
-            .find(|(_, Group(_, public_key))| public_key.compressed() == &compressed);
-        item.map(|(i, Group(num_slots, _))| (i as u16, *num_slots))
+        if let Some(skip_view_change_message) = skip_view_change_message {
+            self.validator_network.start_view_change(skip_view_change_message);
+        }
+    }
+
+    /// Returns the first view number from `view_number` on, whose block producer isn't known to be
+    /// offline. At most `MAX_SKIPPED_VIEWS` views are skipped.
+    fn skip_ahead_view_number(&self, view_number: u32) -> u32 {
+        let mut new_view_number = view_number;
+        while new_view_number - view_number < Self::MAX_SKIPPED_VIEWS {
+            let IndexedSlot { slot, .. } = self.blockchain.get_next_block_producer(new_view_number, None);
+            match self.get_pk_idx(slot.public_key.compressed()) {
+                Some(pk_idx) if self.validator_network.is_offline(pk_idx) => new_view_number += 1,
+                _ => break,
+            }
+        }
+        new_view_number
+    }
+
+    fn get_pk_idx(&self, public_key: &CompressedPublicKey) -> Option<u16> {
+        self.blockchain.current_validators().groups().iter()
+            .position(|Group(_, validator_key)| validator_key.compressed() == public_key)
+            .map(|i| i as u16)
     }
 
     fn produce_macro_block(&self, view_change: Option<ViewChangeProof>) {
@@ -521,37 +702,67 @@ impl Validator {
 
         // FIXME: Don't use network time
         let timestamp = self.consensus.network.network_time.now();
-        let (pbft_proposal, proposed_extrinsics) = self.block_producer.next_macro_block_proposal(timestamp, state.view_number, view_change);
+        let (pbft_proposal, proposed_extrinsics) = match self.block_producer.read().verified_macro_block_proposal(timestamp, state.view_number, view_change) {
+            Ok(proposal) => proposal,
+            Err(e) => {
+                error!("Produced invalid macro block proposal: {}", e);
+                return;
+            },
+        };
         state.proposed_extrinsics.insert(pbft_proposal.header.hash(), proposed_extrinsics);
         let pk_idx = state.pk_idx.expect("Checked that we are an active validator before entering this function");
+        let key_pair = state.signing_key.clone();
 
         drop(state);
 
-        let signed_proposal = SignedPbftProposal::from_message(pbft_proposal, &self.validator_key.secret, pk_idx);
+        let signed_proposal = SignedPbftProposal::from_message(pbft_proposal, &key_pair.secret, pk_idx);
         self.validator_network.start_pbft(signed_proposal)
             .unwrap_or_else(|e| error!("Failed to start pBFT proposal: {}", e));
 
     }
 
     fn produce_micro_block(&self, view_change_proof: Option<ViewChangeProof>) {
+        let state = self.state.read();
+        let view_number = state.view_number;
+        let extra_data = self.extra_data.extra_data(self.blockchain.block_number() + 1, view_number);
+
         let max_size = MicroBlock::MAX_SIZE
             - MicroHeader::SIZE
-            - MicroExtrinsics::get_metadata_size(0, 0);
-
-        let state = self.state.read();
+            - MicroExtrinsics::get_metadata_size(0, extra_data.len());
         let fork_proofs = state.fork_proof_pool.get_fork_proofs_for_block(max_size);
         let timestamp = self.consensus.network.network_time.now();
-        let view_number = state.view_number;
 
         // Drop lock before push, otherwise two concurrent threads can dead-lock because the
         // validator and blockchain lock are circular dependent.
         drop(state);
 
-        let block = self.block_producer.next_micro_block(fork_proofs, timestamp, view_number, vec![], view_change_proof);
+        // The transactions are selected once for the template. It's only signed afterwards, with
+        // the time at which it's finished.
+        let block_producer = self.block_producer.read();
+        let template = block_producer.new_block_template(fork_proofs, timestamp, view_number, extra_data, view_change_proof);
+        if template.parent_hash() != &self.blockchain.head_hash() {
+            debug!("Head changed while producing block #{}.{}, dropping it", template.block_number(), template.view_number());
+            return;
+        }
+
+        let timestamp = self.consensus.network.network_time.now();
+        let block = match block_producer.verified_block_template(template, timestamp) {
+            Ok(block) => block,
+            Err(e) => {
+                error!("Produced invalid micro block: {}", e);
+                return;
+            },
+        };
+        drop(block_producer);
         info!("Produced block #{}.{}: {}",
               block.header.block_number,
               block.header.view_number,
               block.header.hash::<Blake2bHash>());
+        if let Some(stats) = self.block_producer.read().last_stats() {
+            debug!("Block production stats: {} transactions, {} bytes, {} fees, selection took {:?}, receipts took {:?}, state root took {:?}",
+                   stats.num_transactions, stats.size, stats.fees,
+                   stats.selection_time, stats.receipts_time, stats.state_root_time);
+        }
 
         // Automatically relays block.
         match self.blockchain.push(Block::Micro(block)) {
@@ -561,25 +772,21 @@ impl Validator {
     }
 
     fn is_potential_validator(&self) -> bool {
+        self.validator_keys.public_keys().iter().any(|public_key| self.is_staked(public_key))
+    }
+
+    fn is_staked(&self, public_key: &CompressedPublicKey) -> bool {
         let validator_registry = NetworkInfo::from_network_id(self.blockchain.network_id).validator_registry_address().expect("Albatross consensus always has the address set.");
         let contract = self.blockchain.state().accounts().get(validator_registry, None);
         if let Account::Staking(contract) = contract {
-            let public_key = self.validator_key.public.compress();
-
-            // FIXME: Inefficient linear scan.
-            contract.active_stake_sorted.iter().any(|stake| stake.validator_key() == &public_key)
+            contract.has_validator(public_key)
         } else {
             panic!("Validator registry has a wrong account type.");
         }
     }
-}
 
-impl Drop for Validator {
-    fn drop(&mut self) {
-        if let Some(listeners) = self.listeners.as_ref() {
-            self.consensus.notifier.write().deregister(listeners.consensus);
-            self.blockchain.notifier.write().deregister(listeners.blockchain);
-            self.validator_network.notifier.write().deregister();
-        }
+    /// Whether a key is elected for the current epoch or staked for the next ones.
+    fn is_key_in_use(&self, public_key: &CompressedPublicKey) -> bool {
+        self.get_pk_idx(public_key).is_some() || self.is_staked(public_key)
     }
 }
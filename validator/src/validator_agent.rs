@@ -1,21 +1,25 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::cmp::Ordering;
 use std::fmt;
 use std::time::Duration;
 
+use beserial::Serialize;
+use collections::LimitHashSet;
 use network_primitives::validator_info::{ValidatorInfo, SignedValidatorInfo};
+use network_primitives::heartbeat::SignedHeartbeat;
 use network_primitives::address::PeerId;
 use network::Peer;
 use utils::observer::{PassThroughNotifier, weak_passthru_listener};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use bls::bls12_381::CompressedPublicKey;
 use block_albatross::{SignedPbftProposal, ForkProof, ViewChange, PbftPrepareMessage,
                       PbftCommitMessage};
 use primitives::policy;
 use blockchain_albatross::Blockchain;
-use hash::{Hash, Blake2bHash};
+use hash::{Hash, Blake2bHash, Blake2bHasher, Hasher};
 use handel::update::LevelUpdateMessage;
-use utils::rate_limit::RateLimit;
+use utils::rate_limit::RateLimiter;
 use messages::ViewChangeProofMessage;
 
 
@@ -27,29 +31,119 @@ pub enum ValidatorAgentEvent {
     PbftProposal(Box<SignedPbftProposal>),
     PbftPrepare(Box<LevelUpdateMessage<PbftPrepareMessage>>),
     PbftCommit(Box<LevelUpdateMessage<PbftCommitMessage>>),
+    Heartbeat(Box<SignedHeartbeat>),
 }
 
 pub struct ValidatorAgentState {
     pub(crate) validator_info: Option<SignedValidatorInfo>,
-    pbft_proposal_limit: RateLimit,
+    pbft_proposal_limit: Arc<RateLimiter>,
+    heartbeat_limit: Arc<RateLimiter>,
+}
+
+/// The validator infos, fork proofs and pbft proposals received by any validator agent, so that
+/// replayed messages are dropped before they are verified again, no matter through which peer
+/// they arrive. Shared by all validator agents of the validator network.
+///
+/// Messages are checked against their heights before they are remembered, such that old messages
+/// are dropped even after they were evicted from the caches.
+pub struct KnownMessages {
+    validator_infos: Mutex<LimitHashSet<Blake2bHash>>,
+    fork_proofs: Mutex<LimitHashSet<Blake2bHash>>,
+    pbft_proposals: Mutex<LimitHashSet<Blake2bHash>>,
+    /// The newest `valid_from` of the verified infos of each validator. Infos that are older are
+    /// dropped.
+    validator_info_heights: Mutex<HashMap<CompressedPublicKey, u32>>,
+}
+
+impl KnownMessages {
+    /// Maximum number of messages of each type that are remembered
+    const MAX_MESSAGES: usize = 4096;
+    /// Maximum number of validators whose newest info is remembered. If there are more, the
+    /// heights are forgotten, so older infos are only dropped by their hashes until they are
+    /// relearned.
+    const MAX_VALIDATOR_INFO_HEIGHTS: usize = 4 * policy::SLOTS as usize;
+
+    pub fn new() -> Self {
+        KnownMessages {
+            validator_infos: Mutex::new(LimitHashSet::new(Self::MAX_MESSAGES)),
+            fork_proofs: Mutex::new(LimitHashSet::new(Self::MAX_MESSAGES)),
+            pbft_proposals: Mutex::new(LimitHashSet::new(Self::MAX_MESSAGES)),
+            validator_info_heights: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remembers `message` as received. Returns `false` if it was received before.
+    fn note_message<M: Serialize>(known: &Mutex<LimitHashSet<Blake2bHash>>, message: &M) -> bool {
+        let hash = Blake2bHasher::default().digest(&message.serialize_to_vec());
+        known.lock().insert(hash)
+    }
+
+    /// Returns `false` if the info was received before or is older than a verified info of the
+    /// same validator.
+    pub fn note_validator_info(&self, info: &SignedValidatorInfo) -> bool {
+        let newest = self.validator_info_heights.lock().get(&info.message.public_key).cloned();
+        if newest.map_or(false, |newest| info.message.valid_from < newest) {
+            return false;
+        }
+        Self::note_message(&self.validator_infos, info)
+    }
+
+    /// Records the height of an info whose signature was verified.
+    pub fn verified_validator_info(&self, info: &ValidatorInfo) {
+        let mut heights = self.validator_info_heights.lock();
+        if heights.len() >= Self::MAX_VALIDATOR_INFO_HEIGHTS && !heights.contains_key(&info.public_key) {
+            heights.clear();
+        }
+        let newest = heights.entry(info.public_key.clone()).or_insert(info.valid_from);
+        *newest = u32::max(*newest, info.valid_from);
+    }
+
+    /// Returns `false` if the fork proof was received before. Its height is checked by the agent.
+    pub fn note_fork_proof(&self, fork_proof: &ForkProof) -> bool {
+        Self::note_message(&self.fork_proofs, fork_proof)
+    }
+
+    /// Returns `false` if the proposal was received before. Its height is checked by the agent.
+    pub fn note_pbft_proposal(&self, proposal: &SignedPbftProposal) -> bool {
+        Self::note_message(&self.pbft_proposals, proposal)
+    }
+}
+
+impl Default for KnownMessages {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub struct ValidatorAgent {
     pub(crate) peer: Arc<Peer>,
     pub(crate) blockchain: Arc<Blockchain<'static>>,
     pub(crate) state: RwLock<ValidatorAgentState>,
+    known_messages: Arc<KnownMessages>,
     pub notifier: RwLock<PassThroughNotifier<'static, ValidatorAgentEvent>>,
 }
 
 impl ValidatorAgent {
-    pub fn new(peer: Arc<Peer>, blockchain: Arc<Blockchain<'static>>) -> Arc<Self> {
+    /// Maximum number of pbft proposals received from this peer within `PBFT_PROPOSAL_RATE_PERIOD`
+    const PBFT_PROPOSAL_RATE_LIMIT: usize = 5;
+    const PBFT_PROPOSAL_RATE_PERIOD: Duration = Duration::from_secs(10);
+
+    /// Maximum number of heartbeats received from this peer within `HEARTBEAT_RATE_PERIOD`.
+    /// Validators send a heartbeat every two seconds, so this leaves room for some jitter.
+    const HEARTBEAT_RATE_LIMIT: usize = 10;
+    const HEARTBEAT_RATE_PERIOD: Duration = Duration::from_secs(10);
+
+    /// Replays are checked against `known_messages`, which is shared by all validator agents.
+    pub fn new(peer: Arc<Peer>, blockchain: Arc<Blockchain<'static>>, known_messages: &Arc<KnownMessages>) -> Arc<Self> {
         let agent = Arc::new(Self {
             peer,
             blockchain,
             state: RwLock::new(ValidatorAgentState {
                 validator_info: None,
-                pbft_proposal_limit: RateLimit::new(5, Duration::from_secs(10)),
+                pbft_proposal_limit: RateLimiter::new("validator_peer_pbft_proposals", Self::PBFT_PROPOSAL_RATE_LIMIT, Self::PBFT_PROPOSAL_RATE_PERIOD),
+                heartbeat_limit: RateLimiter::new("validator_peer_heartbeats", Self::HEARTBEAT_RATE_LIMIT, Self::HEARTBEAT_RATE_PERIOD),
             }),
+            known_messages: Arc::clone(known_messages),
             notifier: RwLock::new(PassThroughNotifier::new()),
         });
 
@@ -85,6 +179,10 @@ impl ValidatorAgent {
             .register(weak_passthru_listener( Arc::downgrade(this), |this, view_change_proof| {
                 this.on_view_change_proof(view_change_proof);
             }));
+        this.peer.channel.msg_notifier.heartbeat.write()
+            .register(weak_passthru_listener( Arc::downgrade(this), |this, heartbeat| {
+                this.on_heartbeat(heartbeat);
+            }));
     }
 
     /// When a list of validator infos is received, verify the signatures and notify
@@ -93,12 +191,23 @@ impl ValidatorAgent {
 
         let mut valid_infos = Vec::new();
         for signed_info in signed_infos {
-            // TODO: first check if we already know this validator. If so, we don't need to check
-            // the signature of this info.
+            // The info of the peer itself links it to its validator, so it's processed until we
+            // have it, even if it was received through another peer before.
+            let own_info = signed_info.message.peer_address.peer_id == self.peer_id()
+                && self.state.read().validator_info.is_none();
+            if !self.known_messages.note_validator_info(&signed_info) && !own_info {
+                trace!("[VALIDATOR-INFO] Ignoring known or outdated validator info: {}", signed_info.message.peer_address);
+                continue;
+            }
+
             if let Ok(public_key) = signed_info.message.public_key.uncompress() {
                 let signature_okay = signed_info.verify(&public_key);
                 trace!("[VALIDATOR-INFO] {:#?}, signature_okay={}", signed_info.message, signature_okay);
                 if signature_okay {
+                    self.known_messages.verified_validator_info(&signed_info.message);
+                    if signed_info.message.peer_address.peer_id == self.peer_id() {
+                        self.state.write().validator_info = Some(signed_info.clone());
+                    }
                     valid_infos.push(signed_info);
                 }
             }
@@ -107,7 +216,9 @@ impl ValidatorAgent {
             }
         }
 
-        self.notifier.read().notify(ValidatorAgentEvent::ValidatorInfos(valid_infos));
+        if !valid_infos.is_empty() {
+            self.notifier.read().notify(ValidatorAgentEvent::ValidatorInfos(valid_infos));
+        }
     }
 
     /// When a fork proof message is received
@@ -119,6 +230,11 @@ impl ValidatorAgent {
             return;
         }
 
+        if !self.known_messages.note_fork_proof(&fork_proof) {
+            debug!("[FORK-PROOF] Already known");
+            return;
+        }
+
         debug!("[FORK-PROOF] Header 1: {:?}", fork_proof.header1);
         debug!("[FORK_PROOF] Header 2: {:?}", fork_proof.header2);
         let block_number = fork_proof.block_number();
@@ -170,13 +286,31 @@ impl ValidatorAgent {
         self.notifier.read().notify(ValidatorAgentEvent::ViewChangeProof(Box::new(proof)))
     }
 
-    /// When a pbft block proposal is received
-    fn on_pbft_proposal_message(&self, proposal: SignedPbftProposal) {
-        if !self.state.write().pbft_proposal_limit.note_single() {
-            warn!("Ignoring proposal - rate limit exceeded");
+    /// When a heartbeat is received. The signature is verified by the `ValidatorNetwork`, which
+    /// knows the validator that signed it.
+    fn on_heartbeat(&self, heartbeat: SignedHeartbeat) {
+        trace!("[HEARTBEAT] Received: signer_idx={} heartbeat={:?} peer={}",
+               heartbeat.signer_idx,
+               heartbeat.message,
+               self.peer.peer_address());
+
+        let blockchain_epoch = policy::epoch_at(self.blockchain.block_number() + 1);
+        if policy::epoch_at(heartbeat.message.block_number) != blockchain_epoch {
+            debug!("[HEARTBEAT] Ignoring heartbeat for another epoch: block_number={}", heartbeat.message.block_number);
+            return;
+        }
+
+        // Heartbeats are verified by the validator network, so limit them before that.
+        if !self.state.read().heartbeat_limit.note_single() {
+            warn!("[HEARTBEAT] Ignoring heartbeat - rate limit exceeded: peer={}", self.peer.peer_address());
             return;
         }
 
+        self.notifier.read().notify(ValidatorAgentEvent::Heartbeat(Box::new(heartbeat)))
+    }
+
+    /// When a pbft block proposal is received
+    fn on_pbft_proposal_message(&self, proposal: SignedPbftProposal) {
         trace!("Received macro block proposal: {:?}", &proposal);
 
         let proposal_block = proposal.message.header.block_number;
@@ -195,6 +329,16 @@ impl ValidatorAgent {
             return;
         }
 
+        // Replays don't count towards the rate limit.
+        if !self.known_messages.note_pbft_proposal(&proposal) {
+            trace!("[PBFT-PROPOSAL] Ignoring known proposal: {:?}", proposal.message.header);
+            return;
+        }
+        if !self.state.read().pbft_proposal_limit.note_single() {
+            warn!("Ignoring proposal - rate limit exceeded");
+            return;
+        }
+
         self.notifier.read().notify(ValidatorAgentEvent::PbftProposal(Box::new(proposal)));
     }
 
@@ -238,4 +382,101 @@ impl fmt::Debug for ValidatorAgent {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(f, "ValidatorAgent {{ peer_id: {}, public_key: {:?} }}", self.peer_id(), self.public_key())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use block_albatross::MicroHeader;
+    use bls::bls12_381::KeyPair;
+    use network_primitives::address::NetAddress;
+    use network_primitives::address::peer_address::{PeerAddress, PeerAddressType};
+    use network_primitives::services::ServiceFlags;
+
+    use super::*;
+
+    fn peer_address() -> PeerAddress {
+        let public_key = nimiq_keys::KeyPair::generate().public;
+        PeerAddress {
+            ty: PeerAddressType::Dumb,
+            services: ServiceFlags::VALIDATOR,
+            timestamp: 0,
+            net_address: NetAddress::Unspecified,
+            peer_id: PeerId::from(&public_key),
+            public_key,
+            distance: 0,
+            signature: None,
+        }
+    }
+
+    fn validator_info(key_pair: &KeyPair, peer_address: &PeerAddress, valid_from: u32) -> SignedValidatorInfo {
+        let info = ValidatorInfo {
+            public_key: key_pair.public.compress(),
+            peer_address: peer_address.clone(),
+            udp_address: None,
+            valid_from,
+        };
+        SignedValidatorInfo::from_message(info, &key_pair.secret, 0)
+    }
+
+    #[test]
+    fn it_drops_replayed_and_outdated_validator_infos() {
+        let known_messages = KnownMessages::new();
+        let key_pair = KeyPair::generate(&mut rand::thread_rng());
+        let peer_address = peer_address();
+
+        let info = validator_info(&key_pair, &peer_address, 10);
+        assert!(known_messages.note_validator_info(&info));
+        known_messages.verified_validator_info(&info.message);
+        // Replays are dropped, no matter which agent received them.
+        assert!(!known_messages.note_validator_info(&info));
+
+        // Older infos are dropped by their height, even if they were never received before.
+        assert!(!known_messages.note_validator_info(&validator_info(&key_pair, &peer_address, 9)));
+
+        let newer = validator_info(&key_pair, &peer_address, 11);
+        assert!(known_messages.note_validator_info(&newer));
+        known_messages.verified_validator_info(&newer.message);
+        assert!(!known_messages.note_validator_info(&validator_info(&key_pair, &peer_address, 10)));
+
+        // The heights are per validator.
+        let other_key_pair = KeyPair::generate(&mut rand::thread_rng());
+        assert!(known_messages.note_validator_info(&validator_info(&other_key_pair, &peer_address, 1)));
+    }
+
+    #[test]
+    fn it_only_records_the_heights_of_verified_validator_infos() {
+        let known_messages = KnownMessages::new();
+        let key_pair = KeyPair::generate(&mut rand::thread_rng());
+        let peer_address = peer_address();
+
+        // An info with a forged height doesn't suppress the older, genuine infos before it's verified.
+        assert!(known_messages.note_validator_info(&validator_info(&key_pair, &peer_address, 1000)));
+        assert!(known_messages.note_validator_info(&validator_info(&key_pair, &peer_address, 10)));
+    }
+
+    #[test]
+    fn it_drops_replayed_fork_proofs() {
+        let known_messages = KnownMessages::new();
+        let key_pair = KeyPair::generate(&mut rand::thread_rng());
+        let header1 = MicroHeader {
+            version: 1,
+            block_number: 1,
+            view_number: 0,
+            parent_hash: Blake2bHash::default(),
+            extrinsics_root: Blake2bHash::default(),
+            state_root: Blake2bHash::default(),
+            seed: key_pair.sign(&Blake2bHash::default()).compress(),
+            timestamp: 0,
+        };
+        let mut header2 = header1.clone();
+        header2.timestamp = 1;
+        let fork_proof = ForkProof {
+            justification1: key_pair.sign(&header1).compress(),
+            justification2: key_pair.sign(&header2).compress(),
+            header1,
+            header2,
+        };
+
+        assert!(known_messages.note_fork_proof(&fork_proof));
+        assert!(!known_messages.note_fork_proof(&fork_proof));
+    }
+}
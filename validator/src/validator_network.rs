@@ -3,7 +3,7 @@ use std::sync::{Arc, Weak};
 use std::fmt;
 
 use failure::Fail;
-use parking_lot::{RwLock, RwLockUpgradableReadGuard};
+use parking_lot::{Mutex, RwLock, RwLockUpgradableReadGuard};
 use tokio;
 use futures::future;
 
@@ -15,23 +15,27 @@ use block_albatross::{
     SignedViewChange, ViewChange, ViewChangeProof
 };
 use block_albatross::signed::AggregateProof;
+use block_production_albatross::validator_keys::ValidatorKeys;
 use blockchain_albatross::Blockchain;
 use collections::grouped_list::Group;
 use hash::{Blake2bHash, Hash};
 use messages::{Message, ViewChangeProofMessage};
 use network::{Network, NetworkEvent, Peer};
-use network_primitives::validator_info::{SignedValidatorInfo};
+use network_primitives::address::peer_address::PeerAddress;
+use network_primitives::validator_info::{SignedValidatorInfo, ValidatorInfo};
+use network_primitives::heartbeat::{SignedHeartbeat, ValidatorLiveness};
 use network_primitives::address::PeerId;
 use primitives::policy::{SLOTS, TWO_THIRD_SLOTS, is_macro_block_at};
 use primitives::validators::IndexedSlot;
 use utils::mutable_once::MutableOnce;
-use utils::observer::{PassThroughNotifier, weak_listener, weak_passthru_listener};
+use utils::observer::{PassThroughNotifier, Subscription, weak_listener, weak_passthru_listener};
 use handel::aggregation::AggregationEvent;
 use handel::update::LevelUpdateMessage;
 
-use crate::validator_agent::{ValidatorAgent, ValidatorAgentEvent};
+use crate::validator_agent::{KnownMessages, ValidatorAgent, ValidatorAgentEvent};
 use crate::signature_aggregation::view_change::ViewChangeAggregation;
 use crate::signature_aggregation::pbft::PbftAggregation;
+use crate::partition::PartitionStatus;
 use crate::pool::ValidatorPool;
 
 
@@ -65,6 +69,9 @@ pub enum ValidatorNetworkEvent {
 
     /// When the pBFT proof is complete
     PbftComplete(Box<(Blake2bHash, PbftProposal, PbftProof)>),
+
+    /// When less than two thirds of the slots became reachable, or when reachability recovered.
+    PartitionChanged(Box<PartitionStatus>),
 }
 
 
@@ -79,6 +86,9 @@ struct PbftState {
 
     /// The state of the signature aggregation for pBFT prepare and commit
     aggregation: Arc<RwLock<PbftAggregation>>,
+
+    /// Keeps the handlers of the prepare and commit aggregation registered
+    subscriptions: Vec<Arc<Subscription<'static, AggregationEvent>>>,
 }
 
 impl PbftState {
@@ -88,6 +98,7 @@ impl PbftState {
             proposal,
             block_hash,
             aggregation,
+            subscriptions: Vec::new(),
         }
     }
 
@@ -152,9 +163,12 @@ struct ValidatorNetworkState {
     /// NOTE: This becomes obsolete once we can actively connect to validators
     agents: HashMap<PeerId, Arc<ValidatorAgent>>,
 
+    /// Keeps the handlers of the agents' messages registered while the agents are connected
+    agent_subscriptions: HashMap<PeerId, Subscription<'static, ValidatorAgentEvent>>,
+
     /// Maps (view-change-number, block-number) to the proof that is being aggregated
-    /// and a flag whether it's finalized. clear after macro block
-    view_changes: HashMap<ViewChange, ViewChangeAggregation>,
+    /// (and the subscription to its completion). clear after macro block
+    view_changes: HashMap<ViewChange, (ViewChangeAggregation, Subscription<'static, AggregationEvent>)>,
     complete_view_changes: HashMap<ViewChange, ViewChangeProof>,
 
     /// If we're in pBFT phase, this is the current state of it
@@ -162,6 +176,12 @@ struct ValidatorNetworkState {
 
     /// If we're an active validator, set our validator ID here
     validator_id: Option<usize>,
+
+    /// Slots that signed the most recent view change or pBFT commit of this epoch
+    contributing_slots: Option<u16>,
+
+    /// Whether we detected a network partition at the last check
+    partitioned: bool,
 }
 
 impl ValidatorNetworkState {
@@ -177,8 +197,14 @@ impl ValidatorNetworkState {
 pub struct ValidatorNetwork {
     blockchain: Arc<Blockchain<'static>>,
 
-    /// The signed validator info for this node
-    info: SignedValidatorInfo,
+    /// The keys of this node, see `update_own_infos`
+    keys: Arc<ValidatorKeys>,
+
+    /// The address validators connect to this node with
+    peer_address: PeerAddress,
+
+    /// The signed validator infos for this node, one for each of its validator keys
+    infos: RwLock<Vec<SignedValidatorInfo>>,
 
     /// The validator network state
     state: RwLock<ValidatorNetworkState>,
@@ -186,26 +212,42 @@ pub struct ValidatorNetwork {
     /// Stores validator contact information and holds references to connected validators
     validators: Arc<RwLock<ValidatorPool>>,
 
+    /// Heartbeats of the validators of the current epoch
+    liveness: Arc<ValidatorLiveness>,
+
+    /// The messages received from all validator agents together, to drop replays
+    known_messages: Arc<KnownMessages>,
+
     self_weak: MutableOnce<Weak<ValidatorNetwork>>,
     pub notifier: RwLock<PassThroughNotifier<'static, ValidatorNetworkEvent>>,
+    network_subscription: Mutex<Option<Subscription<'static, NetworkEvent>>>,
 }
 
 impl ValidatorNetwork {
     const MAX_VALIDATOR_INFOS: usize = 64;
 
-    pub fn new(network: Arc<Network<Blockchain<'static>>>, blockchain: Arc<Blockchain<'static>>, info: SignedValidatorInfo) -> Arc<Self> {
+    pub fn new(network: Arc<Network<Blockchain<'static>>>, blockchain: Arc<Blockchain<'static>>, keys: Arc<ValidatorKeys>, liveness: Arc<ValidatorLiveness>) -> Arc<Self> {
         let mut pool = ValidatorPool::new(Arc::clone(&network));
+        let peer_address = network.network_config.peer_address();
+        let infos = Self::sign_infos(&keys, &peer_address, blockchain.block_number());
 
         // blacklist ourself
-        pool.blacklist(info.message.public_key.clone());
+        for info in infos.iter() {
+            pool.blacklist(info.message.public_key.clone());
+        }
 
         let this = Arc::new(ValidatorNetwork {
             blockchain,
-            info,
+            keys,
+            peer_address,
+            infos: RwLock::new(infos),
             state: RwLock::new(ValidatorNetworkState::default()),
             validators: Arc::new(RwLock::new(pool)),
+            liveness,
+            known_messages: Arc::new(KnownMessages::new()),
             self_weak: MutableOnce::new(Weak::new()),
             notifier: RwLock::new(PassThroughNotifier::new()),
+            network_subscription: Mutex::new(None),
         });
 
         Self::init_listeners(&this, network);
@@ -217,24 +259,25 @@ impl ValidatorNetwork {
         unsafe { this.self_weak.replace(Arc::downgrade(this)) };
 
         // Register for peers joining and leaving
-        network.notifier.write().register(weak_listener(Arc::downgrade(this), |this, event| {
+        let subscription = network.notifier.write().subscribe(weak_listener(Arc::downgrade(this), |this, event| {
             match event {
                 NetworkEvent::PeerJoined(peer) => this.on_peer_joined(&peer),
                 NetworkEvent::PeerLeft(peer) => this.on_peer_left(&peer),
                 _ => {}
             }
         }));
+        *this.network_subscription.lock() = Some(subscription);
     }
 
     fn on_peer_joined(&self, peer: &Arc<Peer>) {
         if peer.peer_address().services.is_validator() {
-            let agent = ValidatorAgent::new(Arc::clone(peer), Arc::clone(&self.blockchain));
+            let agent = ValidatorAgent::new(Arc::clone(peer), Arc::clone(&self.blockchain), &self.known_messages);
 
             // Insert into set of all agents that have the validator service flag
             self.state.write().agents.insert(agent.peer_id(), Arc::clone(&agent));
 
             // Register for messages received by agent
-            agent.notifier.write().register(weak_passthru_listener(Weak::clone(&self.self_weak), |this, event| {
+            let subscription = agent.notifier.write().subscribe(weak_passthru_listener(Weak::clone(&self.self_weak), |this, event| {
                 match event {
                     ValidatorAgentEvent::ValidatorInfos(infos) => {
                         this.on_validator_infos(infos);
@@ -262,8 +305,12 @@ impl ValidatorNetwork {
                     ValidatorAgentEvent::PbftCommit(level_update) => {
                         this.on_pbft_commit_level_update(*level_update);
                     },
+                    ValidatorAgentEvent::Heartbeat(heartbeat) => {
+                        this.on_heartbeat(*heartbeat);
+                    },
                 }
             }));
+            self.state.write().agent_subscriptions.insert(agent.peer_id(), subscription);
 
             // Send known validator infos to peer
             let mut infos = self.state.read().agents.iter()
@@ -272,14 +319,48 @@ impl ValidatorNetwork {
                 })
                 .take(Self::MAX_VALIDATOR_INFOS) // limit the number of validator infos
                 .collect::<Vec<SignedValidatorInfo>>();
-            infos.push(self.info.clone()); // add our infos
+            infos.extend(self.infos.read().iter().cloned()); // add our infos
             peer.channel.send_or_close(Message::ValidatorInfo(infos));
         }
     }
 
+    fn sign_infos(keys: &ValidatorKeys, peer_address: &PeerAddress, valid_from: u32) -> Vec<SignedValidatorInfo> {
+        keys.key_pairs().into_iter()
+            .map(|key_pair| {
+                let info = ValidatorInfo {
+                    public_key: key_pair.public.compress(),
+                    peer_address: peer_address.clone(),
+                    udp_address: None,
+                    valid_from,
+                };
+                SignedValidatorInfo::from_message(info, &key_pair.secret, 0)
+            })
+            .collect()
+    }
+
+    /// Signs and announces our validator infos again, if our keys changed because of a key
+    /// rotation. The other validators need the info of a new key before it is elected.
+    pub fn update_own_infos(&self) {
+        let public_keys = self.keys.public_keys();
+        if self.infos.read().iter().map(|info| &info.message.public_key).eq(public_keys.iter()) {
+            return;
+        }
+
+        let infos = Self::sign_infos(&self.keys, &self.peer_address, self.blockchain.block_number());
+        {
+            let mut validators = self.validators.write();
+            for info in infos.iter() {
+                validators.blacklist(info.message.public_key.clone());
+            }
+        }
+        *self.infos.write() = infos.clone();
+        self.broadcast_potential(Message::ValidatorInfo(infos));
+    }
+
     fn on_peer_left(&self, peer: &Arc<Peer>) {
         let mut state = self.state.write();
 
+        state.agent_subscriptions.remove(&peer.peer_address().peer_id);
         if let Some(agent) = state.agents.remove(&peer.peer_address().peer_id) {
             info!("Validator left: {}", agent.peer_id());
             self.validators.write().on_validator_left(agent);
@@ -305,11 +386,32 @@ impl ValidatorNetwork {
         }
     }
 
+    /// Verifies a heartbeat against the public key of its signer and records it
+    fn on_heartbeat(&self, heartbeat: SignedHeartbeat) {
+        if self.liveness.verify_heartbeat(&heartbeat) {
+            trace!("Heartbeat from validator {}", heartbeat.signer_idx);
+        }
+        else {
+            debug!("Ignoring invalid or replayed heartbeat from validator {}", heartbeat.signer_idx);
+        }
+    }
+
     fn on_fork_proof(&self, fork_proof: ForkProof) {
         self.notifier.read().notify(ValidatorNetworkEvent::ForkProof(Box::new(fork_proof.clone())));
         self.broadcast_fork_proof(fork_proof);
     }
 
+    /// Forgets the infos of inactive validators that are valid from before `block_number`.
+    pub fn prune_validator_infos(&self, block_number: u32) {
+        self.validators.write().prune_infos(block_number);
+    }
+
+    /// Forgets the proofs of the view changes that happened before `block_number`.
+    pub fn prune_view_changes(&self, block_number: u32) {
+        self.state.write().complete_view_changes
+            .retain(|view_change, _| view_change.block_number >= block_number);
+    }
+
     /// Called when we reach finality - i.e. when a macro block was produced. This must be called be the
     /// validator.
     ///
@@ -327,13 +429,85 @@ impl ValidatorNetwork {
         // Set validator ID
         state.validator_id = validator_id;
 
+        // Aggregations of the last epoch were signed by other validators
+        state.contributing_slots = None;
+
         // Create mapping from validator ID to agent/peer
         // reset validator pool for new epoch
         self.validators.write().reset_epoch(&self.blockchain.current_validators());
+
+        // Start tracking the heartbeats of the new validators
+        let validators = self.blockchain.current_validators();
+        self.liveness.reset(validators.groups().iter()
+            .map(|Group(_, public_key)| public_key.compressed().clone())
+            .collect());
+    }
+
+    /// Sends our heartbeat to the other active validators
+    pub fn send_heartbeat(&self, heartbeat: SignedHeartbeat) {
+        // We don't receive our own heartbeats
+        self.liveness.on_heartbeat(heartbeat.signer_idx, &heartbeat.message);
+        self.broadcast_active(Message::Heartbeat(Box::new(heartbeat)));
+    }
+
+    /// Whether the validator with `pk_idx` is known to be offline
+    pub fn is_offline(&self, pk_idx: u16) -> bool {
+        self.liveness.is_offline(pk_idx)
+    }
+
+    /// Returns how many slots of the current epoch are reachable.
+    pub fn partition_status(&self) -> PartitionStatus {
+        let state = self.state.read();
+        let validators = self.validators.read();
+
+        let own_slots = state.validator_id
+            .map(|validator_id| validators.slots_of(Some(validator_id)))
+            .unwrap_or(0);
+
+        PartitionStatus {
+            known_slots: validators.known_slots() + own_slots,
+            reachable_slots: validators.connected_slots() + own_slots,
+            contributing_slots: state.contributing_slots,
+        }
+    }
+
+    /// Checks whether we can reach enough slots and notifies if this changed since the last check.
+    /// Only active validators are checked.
+    fn check_partition(&self) {
+        if self.state.read().validator_id.is_none() {
+            return;
+        }
+
+        let status = self.partition_status();
+        let partitioned = status.is_partitioned();
+        {
+            let mut state = self.state.write();
+            if state.partitioned == partitioned {
+                return;
+            }
+            state.partitioned = partitioned;
+        }
+
+        if partitioned {
+            warn!("Possible network partition: {} / {} slots reachable, {} / {} slots known, {:?} slots contributing",
+                  status.reachable_slots, SLOTS, status.known_slots, SLOTS, status.contributing_slots);
+        }
+        else {
+            info!("Network partition resolved: {} / {} slots reachable", status.reachable_slots, SLOTS);
+        }
+        self.notifier.read().notify(ValidatorNetworkEvent::PartitionChanged(Box::new(status)));
+    }
+
+    /// Records the slots that signed a completed aggregation.
+    fn on_aggregation_signers<I: IntoIterator<Item=usize>>(&self, signers: I) {
+        let slots = self.validators.read().slots_of(signers);
+        self.state.write().contributing_slots = Some(slots);
     }
 
     /// Called when a new block is added
     pub fn on_blockchain_changed(&self, _hash: &Blake2bHash) {
+        self.check_partition();
+
         let mut state = self.state.write();
         let new_height = self.blockchain.block_number();
 
@@ -399,7 +573,7 @@ impl ValidatorNetwork {
             return;
         }
 
-        if let Some(aggregation) = state.view_changes.get(&update_message.tag) {
+        if let Some((aggregation, _)) = state.view_changes.get(&update_message.tag) {
             aggregation.push_update(update_message);
             debug!("View change: {}", fmt_vote_progress(aggregation.votes()));
         }
@@ -407,13 +581,13 @@ impl ValidatorNetwork {
             let view_change = update_message.tag.clone();
 
             // create view change
-            let aggregation = self.new_view_change(view_change.clone(), node_id);
+            let (aggregation, subscription) = self.new_view_change(view_change.clone(), node_id);
 
             // add update
             aggregation.push_update(update_message);
 
             let mut state = RwLockUpgradableReadGuard::upgrade(state);
-            state.view_changes.insert(view_change, aggregation);
+            state.view_changes.insert(view_change, (aggregation, subscription));
         }
     }
 
@@ -452,6 +626,8 @@ impl ValidatorNetwork {
 
         drop(state);
 
+        self.on_aggregation_signers(proof.signers.iter());
+
         // notify validator
         self.notifier.read()
             .notify(ValidatorNetworkEvent::ViewChangeComplete(Box::new((view_change.clone(), proof.clone()))));
@@ -484,7 +660,7 @@ impl ValidatorNetwork {
 
         debug!("pBFT proposal by validator {}: {}", validator_id, block_hash);
 
-        let pbft = PbftState::new(
+        let mut pbft = PbftState::new(
             block_hash.clone(),
             signed_proposal.clone(),
             validator_id,
@@ -517,8 +693,8 @@ impl ValidatorNetwork {
 
         // The prepare handler. This will store the finished prepare proof in the pBFT state
         let key = block_hash.clone();
-        pbft.aggregation.read().prepare_aggregation.notifier.write()
-            .register(weak_passthru_listener(Weak::clone(&self.self_weak), move |this, event| {
+        let subscription = pbft.aggregation.read().prepare_aggregation.notifier.write()
+            .subscribe(weak_passthru_listener(Weak::clone(&self.self_weak), move |this, event| {
                 match event {
                     AggregationEvent::Complete { best } => {
                         let event = if let Some(pbft) = this.state.write().get_pbft_state_mut(&key) {
@@ -537,12 +713,13 @@ impl ValidatorNetwork {
                     }
                 }
             }));
+        pbft.subscriptions.push(Arc::new(subscription));
 
         // The commit handler. This will store the finished commit proof and construct the
         // pBFT proof.
         let key = block_hash.clone();
-        pbft.aggregation.read().commit_aggregation.notifier.write()
-            .register(weak_passthru_listener(Weak::clone(&self.self_weak), move |this, event| {
+        let subscription = pbft.aggregation.read().commit_aggregation.notifier.write()
+            .subscribe(weak_passthru_listener(Weak::clone(&self.self_weak), move |this, event| {
                 match event {
                     AggregationEvent::Complete { best } => {
                         let event = if let Some(pbft) = this.state.write().get_pbft_state_mut(&key) {
@@ -566,11 +743,15 @@ impl ValidatorNetwork {
                         };
                         // If we generated a prepare complete event, notify the validator
                         if let Some(event) = event {
+                            if let ValidatorNetworkEvent::PbftComplete(ref pbft) = event {
+                                this.on_aggregation_signers(pbft.2.commit.signers.iter());
+                            }
                             this.notifier.read().notify(event)
                         }
                     }
                 }
             }));
+        pbft.subscriptions.push(Arc::new(subscription));
 
         if !buffered {
             // Replace pBFT state
@@ -632,20 +813,46 @@ impl ValidatorNetwork {
         let view_change = signed_view_change.message.clone();
         let mut state = self.state.write();
 
-        if let Some(aggregation) = state.view_changes.get(&view_change) {
+        if let Some((aggregation, _)) = state.view_changes.get(&view_change) {
             aggregation.push_contribution(signed_view_change);
         }
         else {
             let node_id = state.validator_id.expect("Validator ID not set");
             assert_eq!(signed_view_change.signer_idx as usize, node_id);
 
-            let aggregation = self.new_view_change(view_change.clone(), node_id);
+            let (aggregation, subscription) = self.new_view_change(view_change.clone(), node_id);
             aggregation.push_contribution(signed_view_change);
-            state.view_changes.insert(view_change, aggregation);
+            state.view_changes.insert(view_change, (aggregation, subscription));
+        }
+    }
+
+    /// Restarts a view-change that didn't complete in time. Signatures are only valid for the
+    /// same view-change, so we carry over the verified signatures of the previous aggregation,
+    /// including the multi-signatures it aggregated, instead of starting from zero.
+    pub fn restart_view_change(&self, signed_view_change: SignedViewChange) {
+        let view_change = signed_view_change.message.clone();
+        let mut state = self.state.write();
+
+        if state.complete_view_changes.contains_key(&view_change) {
+            debug!("View change already complete: {}", view_change);
+            return;
         }
+
+        let node_id = state.validator_id.expect("Validator ID not set");
+        assert_eq!(signed_view_change.signer_idx as usize, node_id);
+
+        let signatures = state.view_changes.remove(&view_change)
+            .map(|(aggregation, _)| aggregation.verified_signatures())
+            .unwrap_or_default();
+        debug!("Restarting view change {} with {} verified signatures", view_change, signatures.len());
+
+        let (aggregation, subscription) = self.new_view_change(view_change.clone(), node_id);
+        aggregation.push_contribution(signed_view_change);
+        aggregation.push_verified_signatures(signatures);
+        state.view_changes.insert(view_change, (aggregation, subscription));
     }
 
-    fn new_view_change(&self, view_change: ViewChange, node_id: usize) -> ViewChangeAggregation {
+    fn new_view_change(&self, view_change: ViewChange, node_id: usize) -> (ViewChangeAggregation, Subscription<'static, AggregationEvent>) {
         // Create view change aggregation
         let aggregation = ViewChangeAggregation::new(
             view_change.clone(),
@@ -656,7 +863,7 @@ impl ValidatorNetwork {
         debug!("New view change for: {}, node_id={}", view_change, node_id);
 
         // Register handler for when done and start (or use Future)
-        aggregation.inner.notifier.write().register(weak_passthru_listener(Weak::clone(&self.self_weak), move |this, event| {
+        let subscription = aggregation.inner.notifier.write().subscribe(weak_passthru_listener(Weak::clone(&self.self_weak), move |this, event| {
             match event {
                 AggregationEvent::Complete { best } => {
                     let view_change = view_change.clone();
@@ -669,7 +876,7 @@ impl ValidatorNetwork {
             }
         }));
 
-        aggregation
+        (aggregation, subscription)
     }
 
     /// Start pBFT phase with our proposal
@@ -37,15 +37,10 @@ impl<'env> WalletStore<'env> {
             }
         };
 
-        let mut wallets= Vec::new();
         let mut cursor = txn.cursor(&self.wallet_db);
-        let mut wallet: Option<(Address, Locked<WalletAccount>)> = cursor.first();
-
-        while let Some((address, _)) = wallet {
-            wallets.push(address);
-            wallet = cursor.next();
-        }
-
+        let wallets = cursor.iter::<Address, Locked<WalletAccount>>()
+            .map(|(address, _)| address)
+            .collect();
         wallets
     }
 